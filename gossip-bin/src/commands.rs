@@ -1,4 +1,7 @@
-use gossip_lib::{Error, ErrorKind, PersonList, PersonListMetadata, PersonTable, Table, GLOBALS};
+use gossip_lib::{
+    CustomFeed1, DmChannel, Error, ErrorKind, PersonList, PersonListMetadata, PersonTable,
+    ReplaceableConflict, Table, GLOBALS,
+};
 use nostr_types::{
     EncryptedPrivateKey, Event, EventKind, Filter, Id, NAddr, NostrBech32, NostrUrl, ParsedTag,
     PreEvent, PrivateKey, PublicKey, RelayUrl, Tag, UncheckedUrl, Unixtime,
@@ -25,7 +28,7 @@ impl Command {
     }
 }
 
-const COMMANDS: [Command; 52] = [
+const COMMANDS: [Command; 63] = [
     Command {
         cmd: "oneshot",
         usage_params: "{depends}",
@@ -36,6 +39,16 @@ const COMMANDS: [Command; 52] = [
         usage_params: "<listname>",
         desc: "add a new person list with the given name",
     },
+    Command {
+        cmd: "add_custom_feed",
+        usage_params: "<name> <pubkey> [<pubkey> ...]",
+        desc: "add (or replace) a custom feed of the given people's events. It is subscribed the next time gossip starts.",
+    },
+    Command {
+        cmd: "audit_events",
+        usage_params: "",
+        desc: "re-verify the signatures of all stored events, moving any that fail into quarantine",
+    },
     Command {
         cmd: "backdate_eose",
         usage_params: "",
@@ -56,6 +69,16 @@ const COMMANDS: [Command; 52] = [
         usage_params: "",
         desc: "clear relay avoidance timeouts.",
     },
+    Command {
+        cmd: "copy_person_relays",
+        usage_params: "<from_pubkey> <to_pubkey>",
+        desc: "copy the relays of one person to another who has not published a relay list.",
+    },
+    Command {
+        cmd: "custom_feeds",
+        usage_params: "",
+        desc: "list the custom feeds.",
+    },
     Command {
         cmd: "decrypt",
         usage_params: "<pubkey> <ciphertext>",
@@ -91,6 +114,11 @@ const COMMANDS: [Command; 52] = [
         usage_params: "<relayurl>",
         desc: "Set a relay rank to 0 so it will never connect, and also hide form thie list. This is better than delete because deleted relays quickly come back with default settings."
     },
+    Command {
+        cmd: "dm_conversation",
+        usage_params: "<pubkey> [<pubkey> ...]",
+        desc: "print the DM conversation with these people, NIP-04 and NIP-17 messages together, oldest first",
+    },
     Command {
         cmd: "dump_handlers",
         usage_params: "",
@@ -111,6 +139,11 @@ const COMMANDS: [Command; 52] = [
         usage_params: "<pubkey> <kind>",
         desc: "print IDs of all events from <pubkeyhex> of kind=<kind>",
     },
+    Command {
+        cmd: "export_config",
+        usage_params: "<file>",
+        desc: "export relays and settings (but not keys) to a portable config file",
+    },
     Command {
         cmd: "export_encrypted_key",
         usage_params: "",
@@ -131,6 +164,11 @@ const COMMANDS: [Command; 52] = [
         usage_params: "<command>",
         desc: "show documentation of <command> if <command is specified, otherwise documentation of all commands",
     },
+    Command {
+        cmd: "import_config",
+        usage_params: "<file> [replace]",
+        desc: "import relays and settings from a config file, merging unless 'replace' is given",
+    },
     Command {
         cmd: "import_encrypted_private_key",
         usage_params: "<ncryptsec>",
@@ -156,6 +194,11 @@ const COMMANDS: [Command; 52] = [
         usage_params: "",
         desc: "login on the command line before starting the gossip GUI",
     },
+    Command {
+        cmd: "lookup_nip05",
+        usage_params: "<user@domain>",
+        desc: "resolve a NIP-05 identifier to a public key and relays, without following",
+    },
     Command {
         cmd: "offline",
         usage_params: "",
@@ -191,6 +234,11 @@ const COMMANDS: [Command; 52] = [
         usage_params: "<pubkey>",
         desc: "print all the person-relay records for the given person",
     },
+    Command {
+        cmd: "print_quarantined_events",
+        usage_params: "",
+        desc: "print the events that were quarantined by audit_events",
+    },
     Command {
         cmd: "print_relay",
         usage_params: "<url>",
@@ -221,6 +269,11 @@ const COMMANDS: [Command; 52] = [
         usage_params: "",
         desc: "prune unused people",
     },
+    Command {
+        cmd: "purge_quarantined_events",
+        usage_params: "",
+        desc: "permanently remove all quarantined events",
+    },
     Command {
         cmd: "reaction_stats",
         usage_params: "",
@@ -236,11 +289,21 @@ const COMMANDS: [Command; 52] = [
         usage_params: "",
         desc: "Rebuild all event-related indices",
     },
+    Command {
+        cmd: "recover_storage",
+        usage_params: "",
+        desc: "Repair a corrupted local database, keeping a copy of the corrupted one. This runs before the database is opened.",
+    },
     Command {
         cmd: "rename_person_list",
         usage_params: "<number> <newname>",
         desc: "Rename a person list",
     },
+    Command {
+        cmd: "replaceable_conflicts",
+        usage_params: "<pubkey> <kind>",
+        desc: "Show relays that disagree about the latest version of a person's replaceable events of a kind",
+    },
     Command {
         cmd: "reprocess_recent",
         usage_params: "",
@@ -306,10 +369,14 @@ pub fn handle_command(mut args: env::Args) -> Result<bool, Error> {
     match command.cmd {
         "oneshot" => oneshot(command, args)?,
         "add_person_list" => add_person_list(command, args)?,
+        "add_custom_feed" => add_custom_feed(command, args)?,
+        "audit_events" => audit_events()?,
         "backdate_eose" => backdate_eose()?,
         "bech32_decode" => bech32_decode(command, args)?,
         "bech32_encode_naddr" => bech32_encode_naddr(command, args)?,
         "clear_timeouts" => clear_timeouts()?,
+        "copy_person_relays" => copy_person_relays(command, args)?,
+        "custom_feeds" => custom_feeds()?,
         "decrypt" => decrypt(command, args)?,
         "delete_by_kind" => delete_by_kind(command, args)?,
         "delete_by_id" => delete_by_id(command, args)?,
@@ -317,15 +384,18 @@ pub fn handle_command(mut args: env::Args) -> Result<bool, Error> {
         "delete_relay" => delete_relay(command, args)?,
         "dpi" => override_dpi(command, args)?,
         "disable_relay" => disable_relay(command, args)?,
+        "dm_conversation" => dm_conversation(command, args)?,
         "dump_handlers" => dump_handlers()?,
         "events_of_kind" => events_of_kind(command, args)?,
         "events_of_pubkey" => events_of_pubkey(command, args)?,
         "events_of_pubkey_and_kind" => events_of_pubkey_and_kind(command, args)?,
+        "export_config" => export_config(command, args)?,
         "export_encrypted_key" => export_encrypted_key()?,
         "force_migration_level" => force_migration_level(command, args)?,
         "giftwraps" => giftwraps(command)?,
         "help" => help(command, args)?,
         "import_encrypted_private_key" => import_encrypted_private_key(command, args)?,
+        "import_config" => import_config(command, args)?,
         "import_event" => import_event(command, args)?,
         "keys" => keys()?,
         "login" => {
@@ -333,6 +403,7 @@ pub fn handle_command(mut args: env::Args) -> Result<bool, Error> {
             return Ok(false);
         }
         "import_lmdb_events" => import_lmdb_events(command, args)?,
+        "lookup_nip05" => lookup_nip05(command, args)?,
         "offline" => {
             offline()?;
             return Ok(false);
@@ -343,16 +414,19 @@ pub fn handle_command(mut args: env::Args) -> Result<bool, Error> {
         "print_person_lists" => print_person_lists(command)?,
         "print_person" => print_person(command, args)?,
         "print_person_relays" => print_person_relays(command, args)?,
+        "print_quarantined_events" => print_quarantined_events()?,
         "print_relay" => print_relay(command, args)?,
         "print_relays" => print_relays(command)?,
         "print_seen_on" => print_seen_on(command, args)?,
         "prune_cache" => prune_cache()?,
         "prune_old_events" => prune_old_events()?,
         "prune_unused_people" => prune_unused_people()?,
+        "purge_quarantined_events" => purge_quarantined_events()?,
         "reaction_stats" => reaction_stats(command, args)?,
         "rebuild_fof" => rebuild_fof()?,
         "rebuild_indices" => rebuild_indices()?,
         "rename_person_list" => rename_person_list(command, args)?,
+        "replaceable_conflicts" => replaceable_conflicts(command, args)?,
         "reprocess_recent" => reprocess_recent(command)?,
         "reprocess_relay_lists" => reprocess_relay_lists()?,
         "reset_relay_auth" => reset_relay_auth()?,
@@ -420,6 +494,54 @@ pub fn add_person_list(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn add_custom_feed(cmd: Command, mut args: env::Args) -> Result<(), Error> {
+    let name = match args.next() {
+        Some(s) => s,
+        None => return cmd.usage("Missing name parameter".to_string()),
+    };
+
+    let mut pubkeys: Vec<PublicKey> = Vec::new();
+    for s in args {
+        let pubkey = match PublicKey::try_from_hex_string(&s, true) {
+            Ok(pk) => pk,
+            Err(_) => PublicKey::try_from_bech32_string(&s, true)?,
+        };
+        pubkeys.push(pubkey);
+    }
+    if pubkeys.is_empty() {
+        return cmd.usage("Missing pubkey parameter".to_string());
+    }
+
+    let custom_feed = CustomFeed1 {
+        name,
+        pubkeys,
+        kinds: vec![],
+    };
+    GLOBALS.db().write_custom_feed(&custom_feed, None)?;
+    Ok(())
+}
+
+pub fn audit_events() -> Result<(), Error> {
+    let total = GLOBALS.db().get_event_len()?;
+    let mut checked: usize = 0;
+    let mut quarantined: usize = 0;
+    let mut after: Option<Id> = None;
+
+    loop {
+        let (c, q, next) = GLOBALS.db().audit_events(after, 1000)?;
+        checked += c;
+        quarantined += q;
+        println!("Audited {checked} of {total} events, {quarantined} quarantined");
+        match next {
+            Some(id) => after = Some(id),
+            None => break,
+        }
+    }
+
+    println!("Audit complete. {quarantined} events quarantined.");
+    Ok(())
+}
+
 pub fn backdate_eose() -> Result<(), Error> {
     let now = Unixtime::now();
     let ago = (now.0 - 60 * 60 * 24) as u64;
@@ -564,6 +686,41 @@ pub fn clear_timeouts() -> Result<(), Error> {
         .modify_all_relays(|r| r.avoid_until = None, None)
 }
 
+pub fn copy_person_relays(cmd: Command, mut args: env::Args) -> Result<(), Error> {
+    let from = match args.next() {
+        Some(s) => match PublicKey::try_from_hex_string(&s, true) {
+            Ok(pk) => pk,
+            Err(_) => PublicKey::try_from_bech32_string(&s, true)?,
+        },
+        None => return cmd.usage("Missing from_pubkey parameter".to_string()),
+    };
+
+    let to = match args.next() {
+        Some(s) => match PublicKey::try_from_hex_string(&s, true) {
+            Ok(pk) => pk,
+            Err(_) => PublicKey::try_from_bech32_string(&s, true)?,
+        },
+        None => return cmd.usage("Missing to_pubkey parameter".to_string()),
+    };
+
+    let count = GLOBALS.db().copy_person_relays(from, to, None)?;
+    println!("Copied {} relays", count);
+    Ok(())
+}
+
+pub fn custom_feeds() -> Result<(), Error> {
+    for custom_feed in GLOBALS.db().get_custom_feeds()? {
+        println!("{}:", custom_feed.name);
+        for pubkey in &custom_feed.pubkeys {
+            println!("  {}", pubkey.as_bech32_string());
+        }
+        if !custom_feed.kinds.is_empty() {
+            println!("  kinds: {:?}", custom_feed.kinds);
+        }
+    }
+    Ok(())
+}
+
 pub fn decrypt(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     let pubkey = match args.next() {
         Some(s) => match PublicKey::try_from_hex_string(&s, true) {
@@ -622,7 +779,7 @@ pub fn delete_by_id(cmd: Command, mut args: env::Args) -> Result<(), Error> {
 
     let id = match Id::try_from_hex_string(&idstr) {
         Ok(id) => id,
-        Err(_) => Id::try_from_bech32_string(&idstr)?
+        Err(_) => Id::try_from_bech32_string(&idstr)?,
     };
 
     GLOBALS.db().delete_event(id, None)?;
@@ -784,7 +941,7 @@ pub fn delete_spam_by_content(cmd: Command, mut args: env::Args) -> Result<(), E
 
 pub fn delete_relay(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     let rurl = match args.next() {
-        Some(urlstr) => RelayUrl::try_from_str(&urlstr)?,
+        Some(urlstr) => gossip_lib::relay::parse_relay_url(&urlstr)?,
         None => return cmd.usage("Missing relay url parameter".to_string()),
     };
 
@@ -808,7 +965,7 @@ pub fn override_dpi(cmd: Command, mut args: env::Args) -> Result<(), Error> {
 
 pub fn disable_relay(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     let rurl = match args.next() {
-        Some(urlstr) => RelayUrl::try_from_str(&urlstr)?,
+        Some(urlstr) => gossip_lib::relay::parse_relay_url(&urlstr)?,
         None => return cmd.usage("Missing relay url parameter".to_string()),
     };
 
@@ -824,6 +981,69 @@ pub fn disable_relay(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn dm_conversation(cmd: Command, args: env::Args) -> Result<(), Error> {
+    let mut pubkeys: Vec<PublicKey> = Vec::new();
+    for s in args {
+        let pubkey = match PublicKey::try_from_hex_string(&s, true) {
+            Ok(pk) => pk,
+            Err(_) => PublicKey::try_from_bech32_string(&s, true)?,
+        };
+        pubkeys.push(pubkey);
+    }
+    if pubkeys.is_empty() {
+        return cmd.usage("Missing pubkey parameter".to_string());
+    }
+
+    login()?;
+
+    let my_pubkey = GLOBALS.identity.public_key();
+    pubkeys.retain(|pk| Some(*pk) != my_pubkey);
+    let channel = DmChannel::new(&pubkeys);
+
+    // dm_events() gives newest first
+    let ids = GLOBALS.db().dm_events(&channel)?;
+    println!("{} messages with {}", ids.len(), channel.name());
+
+    for id in ids.iter().rev() {
+        let event = match GLOBALS.db().read_event(*id)? {
+            Some(event) => event,
+            None => continue,
+        };
+        let (scheme, message) = if event.kind == EventKind::GiftWrap {
+            match GLOBALS.dm_cache.unwrap_giftwrap(&event) {
+                Ok(rumor) => ("nip17", Ok(rumor.into_event_with_bad_signature())),
+                Err(e) => ("nip17", Err(e)),
+            }
+        } else {
+            let mut event = event;
+            match GLOBALS.dm_cache.decrypt_event_contents(&event) {
+                Ok(plaintext) => {
+                    event.content = plaintext;
+                    ("nip04", Ok(event))
+                }
+                Err(e) => ("nip04", Err(e)),
+            }
+        };
+        match message {
+            Ok(message) => println!(
+                "{} [{}] {}: {}",
+                message.created_at.0,
+                scheme,
+                gossip_lib::names::best_name_from_pubkey_lookup(&message.pubkey),
+                message.content
+            ),
+            Err(e) => println!(
+                "[{}] {}: unable to decrypt: {}",
+                scheme,
+                id.as_hex_string(),
+                e
+            ),
+        }
+    }
+
+    Ok(())
+}
+
 pub fn dump_handlers() -> Result<(), Error> {
     use gossip_lib::HandlersTable;
 
@@ -888,6 +1108,29 @@ pub fn import_encrypted_private_key(cmd: Command, mut args: env::Args) -> Result
     Ok(())
 }
 
+pub fn import_config(cmd: Command, mut args: env::Args) -> Result<(), Error> {
+    let path = match args.next() {
+        Some(path) => path,
+        None => return cmd.usage("Missing file parameter".to_string()),
+    };
+    let replace = match args.next() {
+        None => false,
+        Some(s) if s == "replace" => true,
+        Some(s) => return cmd.usage(format!("Unknown option: {s}")),
+    };
+
+    let file = std::fs::File::open(path)?;
+    let missing = GLOBALS
+        .db()
+        .import_config(std::io::BufReader::new(file), replace)?;
+    for id in missing {
+        println!("List event {} is not stored locally", id.as_hex_string());
+    }
+
+    println!("Ok.");
+    Ok(())
+}
+
 pub fn import_event(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     let event = match args.next() {
         Some(json) => {
@@ -925,6 +1168,24 @@ pub fn keys() -> Result<(), Error> {
     Ok(())
 }
 
+pub fn lookup_nip05(cmd: Command, mut args: env::Args) -> Result<(), Error> {
+    let identifier = match args.next() {
+        Some(s) => s,
+        None => return cmd.usage("Missing user@domain parameter".to_string()),
+    };
+
+    let (pubkey, relays) = GLOBALS
+        .runtime
+        .block_on(gossip_lib::Overlord::lookup_nip05(&identifier))?;
+
+    println!("public key: {}", pubkey.as_bech32_string());
+    for relay in relays {
+        println!("relay: {}", relay);
+    }
+
+    Ok(())
+}
+
 pub fn import_lmdb_events(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     use speedy::Readable;
     use std::io::Write;
@@ -970,9 +1231,19 @@ pub fn print_event(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn print_quarantined_events() -> Result<(), Error> {
+    for (id, maybe_event) in GLOBALS.db().read_quarantined_events()? {
+        match maybe_event {
+            Some(event) => println!("{}", serde_json::to_string(&event)?),
+            None => println!("{} (unparsable)", id.as_hex_string()),
+        }
+    }
+    Ok(())
+}
+
 pub fn print_relay(cmd: Command, mut args: env::Args) -> Result<(), Error> {
     if let Some(url) = args.next() {
-        let rurl = RelayUrl::try_from_str(&url)?;
+        let rurl = gossip_lib::relay::parse_relay_url(&url)?;
         if let Some(relay) = GLOBALS.db().read_relay(&rurl)? {
             println!("{}", serde_json::to_string_pretty(&relay)?);
         } else {
@@ -1199,6 +1470,19 @@ pub fn events_of_pubkey_and_kind(cmd: Command, mut args: env::Args) -> Result<()
     Ok(())
 }
 
+pub fn export_config(cmd: Command, mut args: env::Args) -> Result<(), Error> {
+    let path = match args.next() {
+        Some(path) => path,
+        None => return cmd.usage("Missing file parameter".to_string()),
+    };
+
+    let file = std::fs::File::create(path)?;
+    GLOBALS.db().export_config(std::io::BufWriter::new(file))?;
+
+    println!("Ok.");
+    Ok(())
+}
+
 pub fn export_encrypted_key() -> Result<(), Error> {
     let epk = match GLOBALS.db().read_encrypted_private_key()? {
         Some(epk) => epk,
@@ -1271,6 +1555,12 @@ pub fn giftwraps(_cmd: Command) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn purge_quarantined_events() -> Result<(), Error> {
+    let count = GLOBALS.db().purge_quarantined_events(None)?;
+    println!("{count} quarantined events purged.");
+    Ok(())
+}
+
 pub fn reaction_stats(_cmd: Command, mut _args: env::Args) -> Result<(), Error> {
     use std::collections::HashMap;
     let mut reactions: HashMap<String, usize> = HashMap::new();
@@ -1297,6 +1587,66 @@ pub fn rebuild_fof() -> Result<(), Error> {
     Ok(())
 }
 
+pub fn replaceable_conflicts(cmd: Command, mut args: env::Args) -> Result<(), Error> {
+    let pubkey = match args.next() {
+        Some(s) => match PublicKey::try_from_hex_string(&s, true) {
+            Ok(pk) => pk,
+            Err(_) => PublicKey::try_from_bech32_string(&s, true)?,
+        },
+        None => return cmd.usage("Missing pubkey parameter".to_string()),
+    };
+
+    let kind: EventKind = match args.next() {
+        Some(integer) => integer.parse::<u32>()?.into(),
+        None => return cmd.usage("Missing kind parameter".to_string()),
+    };
+
+    let conflicts = GLOBALS.db().detect_replaceable_conflicts(pubkey, kind)?;
+    if conflicts.is_empty() {
+        println!("No conflicts seen");
+    }
+    for conflict in conflicts {
+        match conflict {
+            ReplaceableConflict::Equivocation {
+                parameter,
+                created_at,
+                versions,
+            } => {
+                println!(
+                    "d={:?}: {} different versions at {}",
+                    parameter,
+                    versions.len(),
+                    created_at
+                );
+                for (id, relays) in versions {
+                    let relays: Vec<&str> = relays.iter().map(|r| r.as_str()).collect();
+                    println!("  {} from {}", id.as_hex_string(), relays.join(", "));
+                }
+            }
+            ReplaceableConflict::Stale {
+                parameter,
+                relay,
+                served,
+                served_created_at,
+                latest,
+                latest_created_at,
+            } => {
+                println!(
+                    "d={:?}: {} served {} from {}, but {} from {} is newer",
+                    parameter,
+                    relay,
+                    served.as_hex_string(),
+                    served_created_at,
+                    latest.as_hex_string(),
+                    latest_created_at
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub fn reprocess_recent(_cmd: Command) -> Result<(), Error> {
     login()?;
 
@@ -11,6 +11,7 @@ mod date_ago;
 mod ui;
 mod unsaved_settings;
 
+use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{Error, RunState, GLOBALS};
 use std::sync::atomic::Ordering;
 use std::{env, thread};
@@ -61,14 +62,42 @@ fn main() -> Result<(), Error> {
         let _ = args.next(); // rapid param
     }
 
+    // A NIP-21 nostr: URI (e.g. handed to us by the OS) is opened, not run as a command
+    let nostr_uri: Option<String> = env::args()
+        .nth(if rapid { 2 } else { 1 })
+        .filter(|arg| arg.starts_with("nostr:"));
+    let command_mode = args.len() > 0 && nostr_uri.is_none();
+
+    // Recovering the database has to happen before it is opened
+    if env::args().nth(if rapid { 2 } else { 1 }).as_deref() == Some("recover_storage") {
+        match gossip_lib::Storage::recover(rapid) {
+            Ok(recovery) => println!("{}", recovery),
+            Err(e) => println!("{}", e),
+        }
+        return Ok(());
+    }
+
     // Initialize the lib
-    gossip_lib::init(rapid, args.len() > 0)?;
+    gossip_lib::init(rapid, command_mode)?;
+
+    // Quiet hours are in local time
+    GLOBALS.local_utc_offset_secs.store(
+        chrono::Local::now().offset().local_minus_utc(),
+        Ordering::Relaxed,
+    );
 
     // Setup async, and allow non-async code the context to spawn tasks
     let _main_rt = GLOBALS.runtime.enter(); // <-- this allows it.
 
+    // If we were handed a nostr: URI, have the overlord open it once it is running
+    if let Some(uri) = nostr_uri {
+        let _ = GLOBALS
+            .to_overlord
+            .send(ToOverlordMessage::HandleNostrUri(uri));
+    }
+
     // If we were handed a command, execute the command and return
-    if args.len() > 0 {
+    if command_mode {
         match commands::handle_command(args) {
             Err(e) => {
                 println!("{}", e);
@@ -221,6 +221,16 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
         }
         FeedKind::Thread { id, .. } => {
             if let Some(parent) = GLOBALS.feed.get_thread_parent() {
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    add_left_space(ui);
+                    match GLOBALS.feed.get_thread_title() {
+                        Some(title) => ui.heading(title),
+                        None => ui.heading(RichText::new("Thread").weak()),
+                    };
+                });
+                ui.add_space(6.0);
+
                 if app.notecache.try_update_and_get(&id).is_none() {
                     ui.add_space(4.0);
                     ui.label("LOADING...");
@@ -278,6 +288,72 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
 
             render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
         }
+        FeedKind::Hashtag(hashtag) => {
+            ui.add_space(10.0);
+            ui.allocate_ui_with_layout(
+                Vec2::new(ui.available_width(), ui.spacing().interact_size.y),
+                egui::Layout::left_to_right(egui::Align::Center),
+                |ui| {
+                    add_left_space(ui);
+                    ui.heading(format!("#{}", &hashtag));
+                    recompute_btn(app, ui);
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add_space(8.0);
+
+                        let followed = GLOBALS.db().is_hashtag_followed(&hashtag).unwrap_or(false);
+                        let text = if followed { "Unfollow" } else { "Follow" };
+                        if widgets::Button::bordered(&app.theme, text)
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let result = if followed {
+                                GLOBALS.db().unfollow_hashtag(&hashtag, None)
+                            } else {
+                                GLOBALS.db().follow_hashtag(&hashtag, None)
+                            };
+                            if let Err(e) = result {
+                                GLOBALS.status_queue.write().write(format!("{}", e));
+                            }
+                        }
+                    });
+                },
+            );
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
+        FeedKind::Custom(name) => {
+            ui.add_space(10.0);
+            ui.allocate_ui_with_layout(
+                Vec2::new(ui.available_width(), ui.spacing().interact_size.y),
+                egui::Layout::left_to_right(egui::Align::Center),
+                |ui| {
+                    add_left_space(ui);
+                    ui.heading(&name);
+                    recompute_btn(app, ui);
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add_space(8.0);
+
+                        if widgets::Button::bordered(&app.theme, "Remove")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS
+                                .to_overlord
+                                .send(ToOverlordMessage::UnsubscribeCustomFeed(name.clone()));
+                            app.set_page(ctx, Page::Feed(FeedKind::Global));
+                        }
+                    });
+                },
+            );
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
         FeedKind::DmChat(channel) => {
             if !GLOBALS.identity.is_unlocked() {
                 ui.add_space(10.0);
@@ -510,6 +586,8 @@ fn render_load_more(app: &mut GossipUi, ui: &mut Ui) {
             let relays_still_loading = GLOBALS.loading_more.load(Ordering::SeqCst);
             let msg = if relays_still_loading > 0 {
                 format!("Load More (loading from {})", relays_still_loading)
+            } else if GLOBALS.feed.older_exhausted() {
+                "Load More (relays have nothing older)".to_owned()
             } else {
                 "Load More".to_owned()
             };
@@ -129,7 +129,8 @@ pub(super) fn render_content(
                             render_profile_link(app, ui, pubkey);
                         }
                         NostrBech32::Relay(url) => {
-                            if let Ok(relay_url) = RelayUrl::try_from_unchecked_url(url) {
+                            if let Ok(relay_url) = gossip_lib::relay::relay_url_from_unchecked(url)
+                            {
                                 render_relay_link(app, ui, relay_url);
                             } else {
                                 ui.label(RichText::new(&url.0).underline());
@@ -253,7 +254,7 @@ pub(super) fn render_hyperlink(
         }
     }
 
-    if let Ok(relay_url) = RelayUrl::try_from_str(link) {
+    if let Ok(relay_url) = gossip_lib::relay::parse_relay_url(link) {
         render_relay_link(app, ui, relay_url);
         return;
     }
@@ -409,11 +410,9 @@ pub(super) fn render_parameterized_event_link(
 pub(super) fn render_hashtag(app: &mut GossipUi, ui: &mut Ui, s: &String) {
     let hashtag = format!("#{}", s);
     if ui.link(&hashtag).clicked() {
-        app.search = s.to_ascii_lowercase();
-        app.set_page(ui.ctx(), Page::SearchLocal);
-        let _ = GLOBALS
-            .to_overlord
-            .send(ToOverlordMessage::SearchLocally(app.search.clone()));
+        if let Some(hashtag) = gossip_lib::normalize_hashtag(s) {
+            app.set_page(ui.ctx(), Page::Feed(FeedKind::Hashtag(hashtag)));
+        }
     }
 }
 
@@ -19,9 +19,12 @@ use egui::{
     UiBuilder,
 };
 use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::git;
+use gossip_lib::polls::{PollResults, PollType};
 use gossip_lib::{relay, DmChannel, FeedKind, Person, PersonTable, Table, ZapState, GLOBALS};
 use nostr_types::{
-    Event, EventDelegation, EventKind, EventReference, IdHex, NAddr, NEvent, NostrUrl, UncheckedUrl,
+    Event, EventDelegation, EventKind, EventReference, Id, IdHex, NAddr, NEvent, NostrUrl,
+    UncheckedUrl,
 };
 use serde::Serialize;
 
@@ -457,6 +460,9 @@ pub fn render_note_inside_framing(
                                             }),
                                         );
                                     };
+                                    if !matches!(GLOBALS.db().has_event(irt), Ok(true)) {
+                                        render_load_parent_link(ui, note.event.id);
+                                    }
                                 }
                                 ui.reset_style();
                             }
@@ -501,6 +507,11 @@ pub fn render_note_inside_framing(
                                         };
                                     }
                                     ui.reset_style();
+                                } else {
+                                    ui.add_space(8.0);
+                                    ui.style_mut().override_text_style = Some(TextStyle::Small);
+                                    render_load_parent_link(ui, note.event.id);
+                                    ui.reset_style();
                                 }
                             }
                             None => (),
@@ -717,6 +728,45 @@ pub fn render_note_inside_framing(
                         });
                 }
 
+                // reported?
+                for (report_type, count) in &note.reports {
+                    Frame::none()
+                        .inner_margin(Margin {
+                            left: footer_margin_left,
+                            bottom: 0.0,
+                            right: 0.0,
+                            top: 8.0,
+                        })
+                        .show(ui, |ui| {
+                            ui.label(
+                                RichText::new(format!(
+                                    "Reported as {} by {} {} you follow",
+                                    report_type,
+                                    count,
+                                    if *count == 1 { "person" } else { "people" }
+                                ))
+                                .italics(),
+                            );
+                        });
+                }
+
+                // labeled?
+                let labels = note.labels();
+                if !labels.is_empty() {
+                    Frame::none()
+                        .inner_margin(Margin {
+                            left: footer_margin_left,
+                            bottom: 0.0,
+                            right: 0.0,
+                            top: 8.0,
+                        })
+                        .show(ui, |ui| {
+                            ui.label(
+                                RichText::new(format!("Labels: {}", labels.join(", "))).italics(),
+                            );
+                        });
+                }
+
                 // proxied?
                 if let Some((proxy, id)) = note.event.proxy() {
                     Frame::none()
@@ -836,12 +886,7 @@ pub fn render_note_inside_framing(
                                     .on_hover_text("Quote")
                                     .clicked()
                                     {
-                                        let relays: Vec<UncheckedUrl> = note
-                                            .seen_on
-                                            .iter()
-                                            .map(|(url, _)| url.to_unchecked_url())
-                                            .take(3)
-                                            .collect();
+                                        let relays = share_relay_hints(&note.event);
 
                                         if !app.draft_data.draft.ends_with(' ')
                                             && !app.draft_data.draft.is_empty()
@@ -1031,14 +1076,19 @@ pub fn render_note_inside_framing(
                                         .filter_map(|(c, s)| if *c == '+' { None } else { Some(s) })
                                         .sum();
 
+                                    // Relays that count for us (NIP-45) may know of more
+                                    let label = match note.relay_reaction_count {
+                                        Some(total) if total > like_count + reaction_count => {
+                                            format!(
+                                                "{}+{} of {}",
+                                                like_count, reaction_count, total
+                                            )
+                                        }
+                                        _ => format!("{}+{}", like_count, reaction_count),
+                                    };
+
                                     if ui
-                                        .add(
-                                            Label::new(format!(
-                                                "{}+{}",
-                                                like_count, reaction_count
-                                            ))
-                                            .sense(Sense::hover()),
-                                        )
+                                        .add(Label::new(label).sense(Sense::hover()))
                                         .on_hover_ui(hover_ui)
                                         .on_disabled_hover_ui(hover_ui)
                                         .clicked()
@@ -1229,6 +1279,22 @@ fn thin_separator(ui: &mut Ui, stroke: Stroke) {
     ui.reset_style();
 }
 
+// Offers to fetch the event a reply replies to, when we don't have it
+fn render_load_parent_link(ui: &mut Ui, id: Id) {
+    ui.add_space(4.0);
+    if ui
+        .link("load parent")
+        .on_hover_text(
+            "Look for the note this replies to on the relays it names and where its author writes",
+        )
+        .clicked()
+    {
+        let _ = GLOBALS
+            .to_overlord
+            .send(ToOverlordMessage::FetchParentOf(id));
+    }
+}
+
 fn render_subject(ui: &mut Ui, event: &Event) {
     let subject = if let Some(subject) = event.subject() {
         subject
@@ -1244,6 +1310,125 @@ fn render_subject(ui: &mut Ui, event: &Event) {
     ui.reset_style();
 }
 
+fn render_git_header(ui: &mut Ui, event: &Event) {
+    let what = if git::is_patch(event.kind) {
+        match git::patch_subject(event) {
+            Some(subject) => format!("Patch: {}", subject),
+            None => "Patch".to_owned(),
+        }
+    } else {
+        "Issue".to_owned()
+    };
+    let text = match git::repo_of(event) {
+        Some(addr) => {
+            let name = match GLOBALS.db().get_repo(&addr) {
+                Ok(Some(repo)) => repo.name,
+                _ => addr.d,
+            };
+            format!("{} (repository {})", what, name)
+        }
+        None => what,
+    };
+    ui.label(RichText::new(text).weak());
+    ui.add_space(4.0);
+}
+
+fn render_community_header(app: &mut GossipUi, ui: &mut Ui, note: &NoteData) {
+    ui.horizontal_wrapped(|ui| {
+        let text = match &note.community_approval {
+            Some(community) => format!("✓ Approved in community {}", community.name),
+            None => {
+                let names: Vec<&str> = note
+                    .communities
+                    .iter()
+                    .map(|(_, name)| name.as_str())
+                    .collect();
+                format!("Posted to community {}", names.join(", "))
+            }
+        };
+        ui.label(RichText::new(text).weak());
+
+        if GLOBALS.identity.is_unlocked() {
+            for (addr, name) in &note.communities {
+                if ui
+                    .link(RichText::new(format!("Post to {}", name)).small())
+                    .clicked()
+                {
+                    app.draft_data.community = Some(addr.clone());
+                    app.draft_data.replying_to = None;
+                    app.draft_data.repost = None;
+                    app.show_post_area = true;
+                    app.draft_needs_focus = true;
+                }
+            }
+        }
+    });
+    ui.add_space(4.0);
+}
+
+fn render_poll(ui: &mut Ui, results: &PollResults) {
+    let poll = &results.poll;
+    let can_vote = GLOBALS.identity.is_unlocked() && !poll.has_ended();
+    let ours = results.our_choices.clone().unwrap_or_default();
+
+    ui.vertical(|ui| {
+        ui.add_space(4.0);
+        for (option, count) in &results.tallies {
+            ui.horizontal(|ui| {
+                let chosen = ours.contains(&option.id);
+                let label = if option.label.is_empty() {
+                    option.id.as_str()
+                } else {
+                    option.label.as_str()
+                };
+                if can_vote {
+                    if ui.selectable_label(chosen, label).clicked() {
+                        // Our latest response replaces the earlier ones
+                        let choices = match poll.poll_type {
+                            PollType::SingleChoice => vec![option.id.clone()],
+                            PollType::MultipleChoice => {
+                                let mut choices = ours.clone();
+                                if chosen {
+                                    choices.retain(|c| c != &option.id);
+                                } else {
+                                    choices.push(option.id.clone());
+                                }
+                                choices
+                            }
+                        };
+                        if !choices.is_empty() {
+                            let _ = GLOBALS
+                                .to_overlord
+                                .send(ToOverlordMessage::VoteInPoll(poll.id, choices));
+                        }
+                    }
+                } else if chosen {
+                    ui.label(RichText::new(label).strong());
+                } else {
+                    ui.label(label);
+                }
+                ui.label(RichText::new(format!("{}", count)).weak());
+            });
+        }
+
+        let mut summary = format!("{} voted", results.voters);
+        if poll.poll_type == PollType::MultipleChoice {
+            summary.push_str(", multiple choice");
+        }
+        if poll.has_ended() {
+            summary.push_str(", ended");
+        } else if let Some(ends_at) = poll.ends_at {
+            if let Ok(stamp) = time::OffsetDateTime::from_unix_timestamp(ends_at.0) {
+                if let Ok(formatted) = stamp.format(&time::format_description::well_known::Rfc2822)
+                {
+                    summary.push_str(&format!(", ends {}", formatted));
+                }
+            }
+        }
+        ui.label(RichText::new(summary).weak().text_style(TextStyle::Small));
+    });
+}
+
 fn render_note_between_header_and_footer(
     app: &mut GossipUi,
     ui: &mut Ui,
@@ -1270,11 +1455,11 @@ fn render_note_between_header_and_footer(
                                 .color(color)
                                 .text_style(TextStyle::Small),
                         );
-                    } else if event.content_warning().is_some()
+                    } else if note.content_warning().is_some()
                         && !app.approved.contains(&event.id)
                         && read_setting!(approve_content_warning)
                     {
-                        let text = match event.content_warning().unwrap() {
+                        let text = match note.content_warning().unwrap() {
                             Some(cw) => format!("Content-Warning: {}", cw),
                             None => "Content-Warning".to_string(),
                         };
@@ -1355,6 +1540,14 @@ fn render_note_between_header_and_footer(
                         // Possible subject line
                         render_subject(ui, event);
 
+                        if git::is_patch(event.kind) || git::is_issue(event.kind) {
+                            render_git_header(ui, event);
+                        }
+
+                        if !note.communities.is_empty() {
+                            render_community_header(app, ui, &note);
+                        }
+
                         content::render_content(
                             app,
                             ui,
@@ -1363,6 +1556,10 @@ fn render_note_between_header_and_footer(
                             content_inner_margin,
                             bottom_of_avatar,
                         );
+
+                        if let Some(results) = &note.poll_results {
+                            render_poll(ui, results);
+                        }
                     }
                 });
             });
@@ -1459,18 +1656,24 @@ fn render_repost(
     }
 }
 
+// Relay hints for links to this note that are shared with others
+fn share_relay_hints(event: &Event) -> Vec<UncheckedUrl> {
+    GLOBALS
+        .db()
+        .get_relay_hints_for_event(event.id, relay::MAX_RELAY_HINTS)
+        .unwrap_or_default()
+        .iter()
+        .map(|url| url.to_unchecked_url())
+        .collect()
+}
+
 fn note_actions(
     app: &mut GossipUi,
     ui: &mut Ui,
     note: &std::cell::Ref<NoteData>,
     _render_data: &NoteRenderData,
 ) {
-    let relays: Vec<UncheckedUrl> = note
-        .seen_on
-        .iter()
-        .map(|(url, _)| url.to_unchecked_url())
-        .take(3)
-        .collect();
+    let relays = share_relay_hints(&note.event);
 
     let text = egui::RichText::new("=").size(13.0);
     let response = widgets::Button::primary(&app.theme, text)
@@ -1579,6 +1782,24 @@ fn note_actions(
                         )));
                     }
                 }
+
+                // Chance to post it again with more work, for relays that want more
+                let declared_work: u8 = note
+                    .event
+                    .tags
+                    .iter()
+                    .find(|t| t.tagname() == "nonce")
+                    .and_then(|t| t.get_index(2).parse().ok())
+                    .unwrap_or(0);
+                let target_bits = (declared_work.max(read_setting!(pow)) + 4).min(40);
+                my_items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                    format!("Post Again with {} Bits of Work", target_bits),
+                    Box::new(move |_, _| {
+                        let _ = GLOBALS
+                            .to_overlord
+                            .send(ToOverlordMessage::RemineEvent(note.event.id, target_bits));
+                    }),
+                )));
             }
 
             items.push(MoreMenuItem::SubMenu(MoreMenuSubMenu::new(
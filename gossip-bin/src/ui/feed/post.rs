@@ -1,6 +1,8 @@
 use super::FeedNoteParams;
 use crate::ui::widgets::{InformationPopup, MoreMenuButton, MoreMenuItem};
-use crate::ui::{widgets, you, FeedKind, GossipUi, HighlightType, Label, Page, Sense, Theme};
+use crate::ui::{
+    widgets, you, DraftData, FeedKind, GossipUi, HighlightType, Label, Page, Sense, Theme,
+};
 use eframe::egui;
 use eframe::epaint::text::LayoutJob;
 use egui::containers::CollapsingHeader;
@@ -9,10 +11,16 @@ use egui_winit::egui::text::{CCursor, CCursorRange};
 use egui_winit::egui::text_edit::TextEditOutput;
 use egui_winit::egui::{vec2, AboveOrBelow, Id};
 use gossip_lib::comms::ToOverlordMessage;
-use gossip_lib::{DmChannel, PersonTable, Relay, Table, GLOBALS};
+use gossip_lib::{DmChannel, Draft, PersonTable, Relay, Table, GLOBALS};
 use memoize::memoize;
-use nostr_types::{ContentSegment, NostrBech32, NostrUrl, ParsedTag, ShatteredContent, Tag};
+use nostr_types::{
+    ContentSegment, NostrBech32, NostrUrl, ParsedTag, RelayUrl, ShatteredContent, Tag,
+};
 use std::collections::HashMap;
+use std::time::Duration;
+
+// How often, at most, a changing draft is saved to the database
+const AUTOSAVE_SECONDS: i64 = 3;
 
 #[memoize]
 pub fn textarea_highlighter(theme: Theme, text: String, interests: Vec<String>) -> LayoutJob {
@@ -188,7 +196,7 @@ fn dm_posting_area(
         }
     } else {
         let text = "BASIC ENCRYPTION";
-        let tt_text = "WARNING: Using older less-secure DM technology (NIP-04; recipient(s) have not signalled the ability to accept the newer method)";
+        let tt_text = "WARNING: Using older less-secure DM technology (NIP-04; we could not find relays for you or the recipient(s) to receive the newer method on)";
 
         // if app.theme.dark_mode {
         (app.theme.amber_400(), app.theme.neutral_50(), text, tt_text)
@@ -197,6 +205,8 @@ fn dm_posting_area(
         //}
     };
 
+    offer_saved_drafts(ui, &mut app.dm_draft_data, Some(dm_channel));
+
     // Text area
     let theme = app.theme;
     let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
@@ -412,9 +422,13 @@ fn dm_posting_area(
             in_reply_to: None,
             annotation: app.dm_draft_data.is_annotate,
             dm_channel: Some(dm_channel.to_owned()),
+            relay_urls: None,
+            pow_job: app.dm_draft_data.draft_id,
         });
 
         app.reset_draft();
+    } else {
+        autosave_draft(ctx, &mut app.dm_draft_data, Some(dm_channel));
     }
 
     // List tags that will be applied
@@ -442,6 +456,56 @@ fn dm_posting_area(
     }
 }
 
+// If the user wants to post to chosen relays but has not chosen any yet
+fn no_relays_chosen(app: &GossipUi) -> bool {
+    matches!(app.draft_data.relay_urls, Some(ref urls) if urls.is_empty())
+}
+
+// The relays to post to, when the user is choosing them
+fn chosen_relays(app: &mut GossipUi, ui: &mut Ui) {
+    let mut remove: Option<RelayUrl> = None;
+    let mut add: Option<RelayUrl> = None;
+    let chosen = app.draft_data.relay_urls.clone().unwrap_or_default();
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Post only to: ");
+        for url in chosen.iter() {
+            ui.label(url.as_str());
+            if ui.small_button("✕").on_hover_text("Remove").clicked() {
+                remove = Some(url.clone());
+            }
+        }
+
+        egui::ComboBox::from_id_salt(Id::new("post_relay_chooser"))
+            .selected_text("Add relay")
+            .show_ui(ui, |ui| {
+                let relays = GLOBALS
+                    .db()
+                    .filter_relays(|r| r.has_any_usage_bit() && !chosen.contains(&r.url))
+                    .unwrap_or_default();
+                for relay in relays.iter() {
+                    let text = if relay.has_usage_bits(Relay::WRITE) {
+                        RichText::new(relay.url.as_str())
+                    } else {
+                        RichText::new(format!("{} (not a WRITE relay)", relay.url.as_str())).weak()
+                    };
+                    if ui.selectable_label(false, text).clicked() {
+                        add = Some(relay.url.clone());
+                    }
+                }
+            });
+    });
+
+    if let Some(urls) = app.draft_data.relay_urls.as_mut() {
+        if let Some(url) = remove {
+            urls.retain(|u| *u != url);
+        }
+        if let Some(url) = add {
+            urls.push(url);
+        }
+    }
+}
+
 fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
     // Maybe render post we are replying to or reposting
 
@@ -478,6 +542,8 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
             }
 
             if app.draft_data.repost.is_none() {
+                offer_saved_drafts(ui, &mut app.draft_data, None);
+
                 // Text area
                 let theme = app.theme;
                 let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
@@ -518,6 +584,25 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     ui.add_space(10.0);
                 }
 
+                if app.draft_data.relay_urls.is_some() {
+                    chosen_relays(app, ui);
+                    ui.add_space(10.0);
+                }
+
+                if let Some(community) = &app.draft_data.community {
+                    let mut post_normally = false;
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Posting to community: {}", community.d));
+                        if ui.small_button("✕").on_hover_text("Post normally").clicked() {
+                            post_normally = true;
+                        }
+                    });
+                    if post_normally {
+                        app.draft_data.community = None;
+                    }
+                    ui.add_space(10.0);
+                }
+
                 // if we are tagging, we will consume arrow presses and enter key
                 let enter_key;
                 (app.draft_data.tagging_search_selected, enter_key) =
@@ -672,6 +757,22 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     )));
                 }
 
+                if app.draft_data.relay_urls.is_some() {
+                    items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                        "Post to All Relays",
+                        Box::new(|_, app| {
+                            app.draft_data.relay_urls = None;
+                        }),
+                    )));
+                } else {
+                    items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                        "Post to Chosen Relays",
+                        Box::new(|_, app| {
+                            app.draft_data.relay_urls = Some(Vec::new());
+                        }),
+                    )));
+                }
+
                 items.push(MoreMenuItem::Button(
                     MoreMenuButton::new(
                         "Show raw preview",
@@ -727,6 +828,7 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                             .show(ui)
                             .clicked()
                             && (!app.draft_data.draft.is_empty() || app.draft_data.repost.is_some())
+                            && !no_relays_chosen(app)
                         {
                             send_now = true;
                         }
@@ -761,6 +863,7 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     .show(ui)
                     .clicked()
                     && (!app.draft_data.draft.is_empty() || app.draft_data.repost.is_some())
+                    && !no_relays_chosen(app)
                 {
                     send_now = true;
                 }
@@ -791,6 +894,8 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     in_reply_to: Some(replying_to_id),
                     annotation: app.draft_data.is_annotate,
                     dm_channel: None,
+                    relay_urls: app.draft_data.relay_urls.clone(),
+                    pow_job: app.draft_data.draft_id,
                 });
             }
             None => {
@@ -798,6 +903,13 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     let _ = GLOBALS
                         .to_overlord
                         .send(ToOverlordMessage::Repost(event_id));
+                } else if let Some(community) = app.draft_data.community.clone() {
+                    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::PostToCommunity(
+                        community,
+                        replaced,
+                        tags,
+                        app.draft_data.draft_id,
+                    ));
                 } else {
                     let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
                         content: replaced,
@@ -805,6 +917,8 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                         in_reply_to: None,
                         annotation: app.draft_data.is_annotate,
                         dm_channel: None,
+                        relay_urls: app.draft_data.relay_urls.clone(),
+                        pow_job: app.draft_data.draft_id,
                     });
                 }
             }
@@ -820,6 +934,8 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                 app.set_page(ctx, Page::Feed(FeedKind::Person(pubkey)));
             }
         }
+    } else {
+        autosave_draft(ctx, &mut app.draft_data, None);
     }
 
     // List tags that will be applied
@@ -1042,7 +1158,104 @@ fn show_tag_hovers(ui: &mut Ui, app: &mut GossipUi, output: &mut TextEditOutput)
     }
 }
 
-fn do_replacements(draft: &str, replacements: &HashMap<String, ContentSegment>) -> String {
+// Save the draft while it changes (every few seconds at most), so it survives a
+// crash or navigating away
+fn autosave_draft(ctx: &Context, draft_data: &mut DraftData, dm_channel: Option<&DmChannel>) {
+    let draft = draft_data.to_saved_draft(dm_channel);
+    match draft_data.autosaved {
+        Some(ref saved) => {
+            if saved.same_as(&draft) {
+                return;
+            }
+            let wait = AUTOSAVE_SECONDS - (draft.saved_at.0 - saved.saved_at.0);
+            if wait > 0 {
+                // Make sure we come back to save it even if nothing else happens
+                ctx.request_repaint_after(Duration::from_secs(wait as u64));
+                return;
+            }
+        }
+        None => {
+            if draft.is_empty() {
+                return;
+            }
+        }
+    }
+
+    let result = if draft.is_empty() {
+        GLOBALS.db().delete_draft(draft.id, None)
+    } else {
+        GLOBALS.db().save_draft(&draft, None)
+    };
+    match result {
+        Ok(()) => draft_data.autosaved = Some(draft),
+        Err(e) => tracing::error!("{}", e),
+    }
+}
+
+// While the composer is empty, offer the drafts saved earlier for the same
+// thing (a new post, a reply to the same note, or the same DM channel)
+fn offer_saved_drafts(ui: &mut Ui, draft_data: &mut DraftData, dm_channel: Option<&DmChannel>) {
+    if !draft_data.draft.is_empty() {
+        return;
+    }
+
+    if draft_data.saved_drafts.is_none() {
+        draft_data.saved_drafts = Some(GLOBALS.db().load_drafts().unwrap_or_default());
+    }
+
+    let keys = dm_channel.map(|c| c.keys().to_vec());
+    let drafts: Vec<Draft> = draft_data
+        .saved_drafts
+        .iter()
+        .flatten()
+        .filter(|d| {
+            d.id != draft_data.draft_id
+                && d.dm_channel == keys
+                && d.replying_to == draft_data.replying_to
+        })
+        .take(3)
+        .cloned()
+        .collect();
+
+    let mut restore: Option<Draft> = None;
+    let mut discard: Option<u64> = None;
+    for draft in drafts {
+        ui.horizontal_wrapped(|ui| {
+            let mut snippet: String = draft.content.chars().take(60).collect();
+            if snippet.len() < draft.content.len() {
+                snippet.push('…');
+            }
+            ui.label(
+                RichText::new(format!(
+                    "Unsent draft from {}: {}",
+                    crate::date_ago::date_ago(draft.saved_at),
+                    snippet
+                ))
+                .weak(),
+            );
+            if ui.link("Restore").clicked() {
+                restore = Some(draft.clone());
+            }
+            if ui.link("Discard").clicked() {
+                discard = Some(draft.id);
+            }
+        });
+    }
+    if let Some(id) = discard {
+        let _ = GLOBALS.db().delete_draft(id, None);
+        if let Some(saved_drafts) = &mut draft_data.saved_drafts {
+            saved_drafts.retain(|d| d.id != id);
+        }
+    }
+    if let Some(draft) = restore {
+        draft_data.restore(draft);
+    }
+}
+
+pub(in crate::ui) fn do_replacements(
+    draft: &str,
+    replacements: &HashMap<String, ContentSegment>,
+) -> String {
     let mut output = draft.to_owned();
     for (pat, content) in replacements {
         if let ContentSegment::NostrUrl(nostr_url) = content {
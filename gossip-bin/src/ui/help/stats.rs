@@ -1,6 +1,7 @@
 use super::GossipUi;
 use eframe::egui;
 use egui::{Context, Ui};
+use gossip_lib::relay_metrics;
 use gossip_lib::{FollowingsTable, HandlersTable, PersonTable, Table, GLOBALS};
 use humansize::{format_size, DECIMAL};
 use std::sync::atomic::Ordering;
@@ -23,6 +24,43 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
 
         ui.add_space(6.0);
 
+        let throughput = relay_metrics::throughput();
+        ui.label(format!(
+            "Over the last {} seconds: {:.1} events/s processed, {:.1} events/s and {}/s received",
+            relay_metrics::THROUGHPUT_WINDOW_SECS,
+            throughput.events_processed_per_sec,
+            throughput.events_received_per_sec,
+            format_size(throughput.bytes_per_sec as u64, DECIMAL)
+        ));
+        ui.label(format!(
+            "Event processing: {:.0} µs per event, {:.0}% busy",
+            throughput.micros_per_event,
+            throughput.processing_load * 100.0
+        ))
+        .on_hover_text(
+            "If processing is busy most of the time, gossip is processing-bound. \
+             If it is mostly idle while little arrives, it is network-bound.",
+        );
+        ui.label(format!(
+            "Signatures: {:.1}/s verified, {:.1}/s skipped as already verified",
+            throughput.verifications_per_sec, throughput.verifications_skipped_per_sec
+        ));
+        ui.collapsing("Throughput by relay", |ui| {
+            if throughput.relays.is_empty() {
+                ui.label("Nothing received recently");
+            }
+            for (url, relay) in throughput.relays.iter() {
+                ui.label(format!(
+                    "{}: {:.1} events/s, {}/s",
+                    url,
+                    relay.events_per_sec,
+                    format_size(relay.bytes_per_sec as u64, DECIMAL)
+                ));
+            }
+        });
+
+        ui.add_space(6.0);
+
         let num_stalled = GLOBALS.fetcher.num_requests_stalled();
         let num_in_flight = GLOBALS.fetcher.num_requests_in_flight();
 
@@ -61,6 +99,19 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         ));
         ui.add_space(6.0);
 
+        let (avoided, performed, false_positives) = GLOBALS.db().event_bloom_stats();
+        ui.label(format!(
+            "Duplicate checks: {} answered from memory, {} confirmed in LMDB ({} false positives)",
+            avoided, performed, false_positives
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Event Index (Address): {} bytes",
+            GLOBALS.db().get_event_addr_index_size().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
         ui.label(format!(
             "Event Index (Author + Kind): {} bytes",
             GLOBALS.db().get_event_akci_index_size().unwrap_or(0)
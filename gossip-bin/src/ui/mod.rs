@@ -70,14 +70,15 @@ use egui_winit::egui::Response;
 use egui_winit::egui::ViewportBuilder;
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{
-    DmChannel, DmChannelData, Error, FeedKind, MediaLoadingResult, Person, PersonList, Private,
-    RunState, ZapState, GLOBALS,
+    DmChannel, DmChannelData, Draft, Error, FeedKind, MediaLoadingResult, Navigation, Person,
+    PersonList, Private, RunState, ZapState, GLOBALS,
 };
 use handler::Handlers;
 use nostr_types::ContentSegment;
 use nostr_types::RelayUrl;
 use nostr_types::{
-    EventKind, FileMetadata, Id, Metadata, MilliSatoshi, Profile, PublicKey, UncheckedUrl, Url,
+    EventKind, FileMetadata, Id, Metadata, MilliSatoshi, NAddr, Profile, PublicKey, UncheckedUrl,
+    Unixtime, Url,
 };
 use widgets::ModalEntry;
 
@@ -372,6 +373,21 @@ pub struct DraftData {
 
     // If this is an annotation
     pub is_annotate: bool,
+
+    // If set, post only to these relays
+    pub relay_urls: Option<Vec<RelayUrl>>,
+
+    // If set, post to this NIP-72 community
+    pub community: Option<NAddr>,
+
+    // Identifies the draft when it is saved to the database
+    pub draft_id: u64,
+
+    // What we last saved to the database, if anything
+    pub autosaved: Option<Draft>,
+
+    // Drafts saved to the database, loaded when the composer opens
+    pub saved_drafts: Option<Vec<Draft>>,
 }
 
 impl Default for DraftData {
@@ -400,6 +416,12 @@ impl Default for DraftData {
             tagging_search_results: Vec::new(),
 
             is_annotate: false,
+            relay_urls: None,
+            community: None,
+
+            draft_id: new_draft_id(),
+            autosaved: None,
+            saved_drafts: None,
         }
     }
 }
@@ -424,9 +446,56 @@ impl DraftData {
         self.tagging_search_searched = None;
         self.tagging_search_results.clear();
         self.is_annotate = false;
+        self.relay_urls = None;
+        self.community = None;
+        self.draft_id = new_draft_id();
+        self.autosaved = None;
+        self.saved_drafts = None;
+    }
+
+    /// What would be saved to the database for this draft
+    pub fn to_saved_draft(&self, dm_channel: Option<&DmChannel>) -> Draft {
+        let non_empty = |include: bool, s: &String| {
+            if include && !s.is_empty() {
+                Some(s.clone())
+            } else {
+                None
+            }
+        };
+        Draft {
+            id: self.draft_id,
+            content: feed::post::do_replacements(&self.draft, &self.replacements),
+            subject: non_empty(self.include_subject, &self.subject),
+            content_warning: non_empty(self.include_content_warning, &self.content_warning),
+            replying_to: self.replying_to,
+            dm_channel: dm_channel.map(|c| c.keys().to_vec()),
+            annotation: self.is_annotate,
+            saved_at: Unixtime::now(),
+        }
+    }
+
+    /// Continue a draft saved to the database
+    pub fn restore(&mut self, draft: Draft) {
+        self.clear();
+        self.draft = draft.content.clone();
+        self.include_subject = draft.subject.is_some();
+        self.subject = draft.subject.clone().unwrap_or_default();
+        self.include_content_warning = draft.content_warning.is_some();
+        self.content_warning = draft.content_warning.clone().unwrap_or_default();
+        self.replying_to = draft.replying_to;
+        self.is_annotate = draft.annotation;
+        self.draft_id = draft.id;
+        self.autosaved = Some(draft);
     }
 }
 
+fn new_draft_id() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
 struct GossipUi {
     #[cfg(feature = "video-ffmpeg")]
     audio_device: Option<AudioDevice>,
@@ -741,6 +810,16 @@ impl GossipUi {
             }
         });
 
+        // Let them know about posts they were composing when gossip last stopped
+        if let Ok(drafts) = GLOBALS.db().load_drafts() {
+            if !drafts.is_empty() {
+                GLOBALS.status_queue.write().write(format!(
+                    "{} unsent draft(s) were saved. Open the composer to restore them.",
+                    drafts.len()
+                ));
+            }
+        }
+
         GossipUi {
             #[cfg(feature = "video-ffmpeg")]
             audio_device,
@@ -927,6 +1006,19 @@ impl GossipUi {
     }
 
     fn set_page_inner(&mut self, ctx: &Context, page: Page) {
+        // Leaving a composer abandons proof of work still being mined for its draft.
+        // Sending a draft gives the composer a new draft id, so what was sent carries on.
+        if self.show_post_area {
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::PowCancel(self.draft_data.draft_id));
+        }
+        if let Page::Feed(FeedKind::DmChat(_)) = &self.page {
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::PowCancel(self.dm_draft_data.draft_id));
+        }
+
         // Setting the page often requires some associated actions:
         match &page {
             Page::Feed(feed_kind) => {
@@ -950,6 +1042,9 @@ impl GossipUi {
                 let _ = GLOBALS
                     .to_overlord
                     .send(ToOverlordMessage::UpdateMetadata(*pubkey));
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::FetchBadges(*pubkey));
             }
             Page::PersonFollows(pubkey) => {
                 self.close_all_menus_except_feeds(ctx);
@@ -1164,6 +1259,24 @@ impl GossipUi {
                     more += 1;
                 }
             }
+            for hashtag in GLOBALS.db().get_followed_hashtags().unwrap_or_default() {
+                let title = format!("#{}", hashtag);
+                self.add_menu_item_page(
+                    ui,
+                    Page::Feed(FeedKind::Hashtag(hashtag)),
+                    Some(&title),
+                    true,
+                );
+            }
+            for custom_feed in GLOBALS.db().get_custom_feeds().unwrap_or_default() {
+                let title = custom_feed.name.clone();
+                self.add_menu_item_page(
+                    ui,
+                    Page::Feed(FeedKind::Custom(custom_feed.name)),
+                    Some(&title),
+                    true,
+                );
+            }
             if more != 0 {
                 self.add_menu_item_page(
                     ui,
@@ -1509,6 +1622,7 @@ impl GossipUi {
 
     fn enable_ui(&self) -> bool {
         !relays::is_entry_dialog_active(self)
+            && !relays::is_credential_dialog_active(self)
             && self.person_qr.is_none()
             && self.render_qr.is_none()
             && self.render_raw.is_none()
@@ -1619,9 +1733,15 @@ impl GossipUi {
                             pubkey: person.pubkey,
                             relays: Vec::new(),
                         };
-                        let relays = GLOBALS.people.get_active_person_write_relays();
+                        let relays = GLOBALS
+                            .db()
+                            .get_relay_hints_for_person(
+                                person.pubkey,
+                                gossip_lib::relay::MAX_RELAY_HINTS,
+                            )
+                            .unwrap_or_default();
                         for relay_url in relays {
-                            profile.relays.push(UncheckedUrl(format!("{}", relay_url)));
+                            profile.relays.push(relay_url.to_unchecked_url());
                         }
                         o.copied_text = format!("https://njump.me/{}", profile.as_bech32_string())
                     });
@@ -2127,10 +2247,15 @@ impl GossipUi {
 
     fn reset_draft(&mut self) {
         if let Page::Feed(FeedKind::DmChat(_)) = &self.page {
+            let _ = GLOBALS.db().delete_draft(self.dm_draft_data.draft_id, None);
             self.dm_draft_data.clear();
             self.dm_draft_data_target = None;
         } else {
+            let _ = GLOBALS.db().delete_draft(self.draft_data.draft_id, None);
             self.previous_draft_data = self.draft_data.clone();
+            // If it comes back (undo send), it needs saving again
+            self.previous_draft_data.autosaved = None;
+            self.previous_draft_data.saved_drafts = None;
             self.draft_data.clear();
             self.show_post_area = false;
             self.draft_needs_focus = false;
@@ -2220,6 +2345,11 @@ impl eframe::App for GossipUi {
 
         self.frame_count += 1;
 
+        // Any input counts as activity for the auto-lock timer
+        if ctx.input(|i| !i.events.is_empty()) {
+            GLOBALS.note_activity();
+        }
+
         // Enforce FPS limiting.
         // No amount of notifies or request_repaint()s can bypass this.
         let presleep_now = Instant::now();
@@ -2339,6 +2469,9 @@ impl eframe::App for GossipUi {
         if relays::is_entry_dialog_active(self) {
             relays::entry_dialog(ctx, self);
         }
+        if relays::is_credential_dialog_active(self) {
+            relays::credential_dialog(ctx, self);
+        }
 
         // If login is forced, it takes over
         if GLOBALS.wait_for_login.load(Ordering::Relaxed) {
@@ -2356,6 +2489,14 @@ impl eframe::App for GossipUi {
             return wait_for_prune(self, ctx, status);
         }
 
+        // Navigate if the overlord asked us to (e.g. a nostr: URI was opened)
+        let navigation = GLOBALS.navigation_request.write().take();
+        match navigation {
+            Some(Navigation::Person(pubkey)) => self.set_page(ctx, Page::Person(pubkey)),
+            Some(Navigation::Feed(feed_kind)) => self.set_page(ctx, Page::Feed(feed_kind)),
+            None => {}
+        }
+
         // Wizard does its own panels
         if let Page::Wizard(wp) = self.page {
             return wizard::update(self, ctx, frame, wp);
@@ -2377,6 +2518,15 @@ impl eframe::App for GossipUi {
         // Side panel
         self.side_panel(ctx);
 
+        // Saved drafts are loaded again the next time a composer opens
+        let dm_composer_open = matches!(self.page, Page::Feed(FeedKind::DmChat(_)));
+        if !dm_composer_open {
+            self.dm_draft_data.saved_drafts = None;
+        }
+        if !self.show_post_area_fn() || dm_composer_open {
+            self.draft_data.saved_drafts = None;
+        }
+
         let (show_top_post_area, show_bottom_post_area) = if self.show_post_area_fn() {
             let posting_area_at_top = if matches!(self.page, Page::Feed(FeedKind::DmChat(_))) {
                 read_setting!(dm_posting_area_at_top)
@@ -2727,7 +2877,9 @@ fn read_feed_include_all(feed_kind: &FeedKind, ctx: &Context) -> bool {
     let mut key = feed_kind.anchor_key();
     key.push_str("_include_all");
     let egui_id = egui::Id::new(key);
-    let include_all: bool = ctx.data_mut(|d| d.get_persisted(egui_id)).unwrap_or(false);
+    let include_all: bool = ctx
+        .data_mut(|d| d.get_persisted(egui_id))
+        .unwrap_or_else(|| GLOBALS.db().read_setting_include_replies());
     include_all
 }
 
@@ -2,7 +2,8 @@ use std::{cell::RefCell, rc::Rc};
 
 use eframe::egui::{self, Align, Color32, Layout, RichText, Ui};
 use egui_extras::{Size, StripBuilder};
-use gossip_lib::{comms::ToOverlordMessage, PendingItem, PersonList, GLOBALS};
+use gossip_lib::{comms::ToOverlordMessage, FeedKind, PendingItem, PersonList, GLOBALS};
+use nostr_types::{Id, RelayUrl, Unixtime};
 
 use crate::ui::{Page, Theme};
 
@@ -75,6 +76,15 @@ impl<'a> Notification<'a> for Pending {
             PendingItem::NeedWriteRelays => self.need_relays(theme, ui, "WRITE"),
             PendingItem::NeedDiscoverRelays => self.need_relays(theme, ui, "DISCOVER"),
             PendingItem::NeedDMRelays => self.need_relays(theme, ui, "DM"),
+            PendingItem::RelayNeedsConfig(ref url) => {
+                let url = url.clone();
+                self.relay_needs_config(theme, ui, url)
+            }
+            PendingItem::SignerLocked => self.signer_locked(theme, ui),
+            PendingItem::PostUndelivered { id, ref relay } => {
+                let relay = relay.clone();
+                self.post_undelivered(theme, ui, id, relay)
+            }
             _ => None,
         }
     }
@@ -310,6 +320,126 @@ impl Pending {
         self.layout(theme, ui, description, action)
     }
 
+    fn relay_needs_config(&mut self, theme: &Theme, ui: &mut Ui, url: RelayUrl) -> Option<Page> {
+        let description = |_theme: &Theme, ui: &mut Ui| -> Option<Page> {
+            ui.label(format!(
+                "You now write to {}, which may not have your profile, relay list and lists",
+                url
+            ));
+            None
+        };
+        let action = |theme: &Theme, ui: &mut Ui| -> Option<Page> {
+            ui.scope(|ui| {
+                super::manage_style(theme, ui.style_mut());
+                if ui.button("Not Now").clicked() {
+                    GLOBALS
+                        .pending
+                        .remove(&PendingItem::RelayNeedsConfig(url.clone()));
+                }
+            });
+            ui.add_space(10.0);
+            ui.scope(|ui| {
+                super::approve_style(theme, ui.style_mut());
+                if ui.button("Copy Now").clicked() {
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::SyncConfigToRelay(url.clone()));
+                }
+            });
+            None
+        };
+        self.layout(theme, ui, description, action)
+    }
+
+    fn post_undelivered(
+        &mut self,
+        theme: &Theme,
+        ui: &mut Ui,
+        id: Id,
+        relay: RelayUrl,
+    ) -> Option<Page> {
+        let description = |_theme: &Theme, ui: &mut Ui| -> Option<Page> {
+            let post = match GLOBALS.undelivered_posts.get(&(id, relay.clone())) {
+                Some(post) => post.clone(),
+                None => return None,
+            };
+            ui.label(format!("{} refused your post: {}", relay, post.message));
+            match post.retry_at {
+                Some(retry_at) => ui.label(format!(
+                    "Sending again in {} seconds",
+                    (retry_at.0 - Unixtime::now().0).max(0)
+                )),
+                None if post.attempts > 0 => {
+                    ui.label(format!("Gave up after {} retries", post.attempts))
+                }
+                None => ui.label("This will not be retried automatically"),
+            };
+            None
+        };
+        let action = |theme: &Theme, ui: &mut Ui| -> Option<Page> {
+            let mut new_page = None;
+            ui.scope(|ui| {
+                super::manage_style(theme, ui.style_mut());
+                if ui.button("View").clicked() {
+                    new_page = Some(Page::Feed(FeedKind::Thread {
+                        id,
+                        referenced_by: id,
+                        author: GLOBALS.identity.public_key(),
+                    }));
+                }
+            });
+            ui.add_space(10.0);
+            ui.scope(|ui| {
+                super::manage_style(theme, ui.style_mut());
+                if ui.button("Cancel").clicked() {
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::CancelDelivery(id, relay.clone()));
+                }
+            });
+            ui.add_space(10.0);
+            ui.scope(|ui| {
+                super::approve_style(theme, ui.style_mut());
+                if ui.button("Retry").clicked() {
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::RetryDelivery(id, relay.clone()));
+                }
+            });
+            new_page
+        };
+        self.layout(theme, ui, description, action)
+    }
+
+    fn signer_locked(&mut self, theme: &Theme, ui: &mut Ui) -> Option<Page> {
+        let description = |_theme: &Theme, ui: &mut Ui| -> Option<Page> {
+            let count = GLOBALS.held_for_signer.read().len();
+            ui.label(format!(
+                "Your key is locked. {} action(s) will finish once you unlock it.",
+                count
+            ));
+            None
+        };
+        let action = |theme: &Theme, ui: &mut Ui| -> Option<Page> {
+            let mut new_page = None;
+            ui.scope(|ui| {
+                super::manage_style(theme, ui.style_mut());
+                if ui.button("Discard").clicked() {
+                    gossip_lib::Overlord::discard_held_for_signer();
+                }
+            });
+            ui.add_space(10.0);
+            ui.scope(|ui| {
+                super::approve_style(theme, ui.style_mut());
+                if ui.button("Unlock").clicked() {
+                    new_page = Some(crate::ui::Page::YourKeys);
+                }
+            });
+            new_page
+        };
+        self.layout(theme, ui, description, action)
+    }
+
     fn need_relays(&mut self, theme: &Theme, ui: &mut Ui, which: &'static str) -> Option<Page> {
         let description = |_theme: &Theme, ui: &mut Ui| -> Option<Page> {
             ui.label(format!("Your have not selected any {} relays", which));
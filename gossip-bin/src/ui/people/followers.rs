@@ -50,7 +50,7 @@ pub(super) fn update(
             return;
         }
 
-        let count = followers.set.len();
+        let count = followers.count();
         ui.heading(format!("{} Followers", count));
 
         let height: f32 = 48.0;
@@ -11,8 +11,8 @@ use egui_winit::egui::text_edit::TextEditOutput;
 use egui_winit::egui::vec2;
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{
-    FeedKind, Freshness, People, Person, PersonList, PersonListMetadata, PersonTable, Private,
-    Table, GLOBALS,
+    FeedKind, Freshness, Overlord, People, Person, PersonList, PersonListDiff, PersonListMetadata,
+    PersonTable, Private, Table, GLOBALS,
 };
 use nostr_types::{Profile, PublicKey, Unixtime};
 
@@ -35,6 +35,13 @@ pub(in crate::ui) struct ListUi {
 
     entering_follow_someone_on_list: bool,
     clear_list_needs_confirm: bool,
+    publish_preview: Option<PersonListDiff>,
+
+    // import follows
+    importing_follows: bool,
+    import_follows_text: String,
+    import_follows_publish: bool,
+    import_follows_report: Option<String>,
 }
 
 impl ListUi {
@@ -58,6 +65,13 @@ impl ListUi {
 
             entering_follow_someone_on_list: false,
             clear_list_needs_confirm: false,
+            publish_preview: None,
+
+            // import follows
+            importing_follows: false,
+            import_follows_text: String::new(),
+            import_follows_publish: false,
+            import_follows_report: None,
         }
     }
 }
@@ -90,8 +104,12 @@ pub(super) fn update(
     let mut enabled = false;
     if app.people_list.clear_list_needs_confirm {
         render_clear_list_confirm_popup(ui, app, list);
+    } else if app.people_list.publish_preview.is_some() {
+        render_publish_preview_popup(ui, app);
     } else if app.people_list.entering_follow_someone_on_list {
         render_add_contact_popup(ui, app, list, &metadata);
+    } else if app.people_list.importing_follows {
+        render_import_follows_popup(ui, app);
     } else if let Some(list) = app.deleting_list {
         super::list::render_delete_list_dialog(ui, app, list);
     } else if app.creating_list {
@@ -183,12 +201,13 @@ pub(super) fn update(
             if GLOBALS.identity.is_unlocked() {
                 if ui
                     .button("↑ Publish ↑")
-                    .on_hover_text("This publishes the list to your relays")
+                    .on_hover_text("This publishes the list to your relays, after showing you what would change")
                     .clicked()
                 {
-                    let _ = GLOBALS
-                        .to_overlord
-                        .send(ToOverlordMessage::PushPersonList(list));
+                    match Overlord::preview_person_list_publish(list) {
+                        Ok(diff) => app.people_list.publish_preview = Some(diff),
+                        Err(e) => GLOBALS.status_queue.write().write(format!("{}", e)),
+                    }
                 }
             } else {
                 ui.horizontal(|ui| {
@@ -836,6 +855,16 @@ pub(super) fn render_more_list_actions(
                 }),
             )));
         }
+        if list == PersonList::Followed {
+            items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                "Import...",
+                Box::new(|_, app| {
+                    app.people_list.importing_follows = true;
+                    app.people_list.import_follows_report = None;
+                }),
+            )));
+        }
+
         items.push(MoreMenuItem::Button(
             MoreMenuButton::new(
                 "Clear All",
@@ -926,6 +955,169 @@ fn render_clear_list_confirm_popup(ui: &mut Ui, app: &mut GossipUi, list: Person
     }
 }
 
+fn render_publish_preview_popup(ui: &mut Ui, app: &mut GossipUi) {
+    let diff = match app.people_list.publish_preview.clone() {
+        Some(diff) => diff,
+        None => return,
+    };
+
+    const DLG_SIZE: Vec2 = vec2(400.0, 300.0);
+    let popup = widgets::modal_popup(ui.ctx(), DLG_SIZE, DLG_SIZE, true, |ui| {
+        ui.vertical(|ui| {
+            ui.heading("Publish list");
+            ui.add_space(8.0);
+            ui.label(format!(
+                "The published list will have {} people.",
+                diff.count
+            ));
+            match diff.published_at {
+                None => {
+                    ui.label("We have not seen this list published before.");
+                }
+                Some(_) if diff.is_unchanged() => {
+                    ui.label("This is the same as the version we last saw published.");
+                }
+                Some(published_at) => {
+                    let ago = crate::date_ago::date_ago(published_at);
+                    ui.label(format!(
+                        "Compared to the version published {} ago: {} added, {} removed.",
+                        ago,
+                        diff.added.len(),
+                        diff.removed.len()
+                    ));
+                }
+            }
+            if diff.removes_most() {
+                ui.add_space(5.0);
+                ui.label(
+                    RichText::new(
+                        "This removes at least half of the people on the published list. \
+                        If you did not mean to do that, cancel and import the published \
+                        list first.",
+                    )
+                    .color(app.theme.warning_marker_text_color()),
+                );
+            }
+
+            ui.add_space(8.0);
+            egui::ScrollArea::vertical()
+                .max_height(140.0)
+                .show(ui, |ui| {
+                    for pubkey in &diff.added {
+                        ui.label(format!(
+                            "+ {}",
+                            gossip_lib::names::best_name_from_pubkey_lookup(pubkey)
+                        ));
+                    }
+                    for pubkey in &diff.removed {
+                        ui.label(
+                            RichText::new(format!(
+                                "- {}",
+                                gossip_lib::names::best_name_from_pubkey_lookup(pubkey)
+                            ))
+                            .color(app.theme.warning_marker_text_color()),
+                        );
+                    }
+                });
+
+            ui.add_space(8.0);
+            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                ui.horizontal(|ui| {
+                    if widgets::Button::secondary(&app.theme, "Cancel")
+                        .show(ui)
+                        .clicked()
+                    {
+                        app.people_list.publish_preview = None;
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::default()), |ui| {
+                        if widgets::Button::primary(&app.theme, "Publish")
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS
+                                .to_overlord
+                                .send(ToOverlordMessage::PushPersonList(diff.list));
+                            app.people_list.publish_preview = None;
+                        }
+                    });
+                });
+            });
+        });
+    });
+
+    if popup.inner.clicked() {
+        app.people_list.publish_preview = None;
+    }
+}
+
+fn render_import_follows_popup(ui: &mut Ui, app: &mut GossipUi) {
+    const DLG_SIZE: Vec2 = vec2(400.0, 260.0);
+    let popup = widgets::modal_popup(ui.ctx(), DLG_SIZE, DLG_SIZE, true, |ui| {
+        ui.vertical(|ui| {
+            ui.heading("Import follows");
+            ui.add_space(8.0);
+            ui.label("Paste a list of npubs, nprofiles or hex keys, such as a CSV export from another app. Relay hints in nprofiles are kept. Lines without a key are skipped.");
+            ui.add_space(8.0);
+
+            egui::ScrollArea::vertical()
+                .max_height(120.0)
+                .show(ui, |ui| {
+                    ui.add(
+                        text_edit_multiline!(app, app.people_list.import_follows_text)
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(6)
+                            .hint_text("npub1..., nprofile1... or hex, one or more per line"),
+                    );
+                });
+
+            if let Some(report) = &app.people_list.import_follows_report {
+                ui.add_space(5.0);
+                ui.label(report);
+            }
+
+            ui.add_space(8.0);
+            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(
+                        &mut app.people_list.import_follows_publish,
+                        "Publish Following List",
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::default()), |ui| {
+                        if widgets::Button::primary(&app.theme, "Import")
+                            .show(ui)
+                            .clicked()
+                        {
+                            let (profiles, invalid) =
+                                People::parse_profile_list(&app.people_list.import_follows_text);
+                            if profiles.is_empty() {
+                                app.people_list.import_follows_report =
+                                    Some(format!("No keys found ({} lines skipped)", invalid));
+                            } else {
+                                GLOBALS.status_queue.write().write(format!(
+                                    "Importing {} keys ({} lines without a key skipped)",
+                                    profiles.len(),
+                                    invalid
+                                ));
+                                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::ImportFollows(
+                                    profiles,
+                                    app.people_list.import_follows_publish,
+                                ));
+                                app.people_list.import_follows_text.clear();
+                                app.people_list.importing_follows = false;
+                                mark_refresh(app);
+                            }
+                        }
+                    });
+                });
+            });
+        });
+    });
+
+    if popup.inner.clicked() {
+        app.people_list.importing_follows = false;
+    }
+}
+
 fn mark_refresh(app: &mut GossipUi) {
     app.people_list.cache_next_refresh = Instant::now();
 }
@@ -12,10 +12,10 @@ use egui_winit::egui::Response;
 use egui_winit::egui::Widget;
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{
-    DmChannel, FeedKind, Freshness, People, Person, PersonList, PersonTable, Private, Table,
-    GLOBALS,
+    Badge, DmChannel, FeedKind, Freshness, MediaLoadingResult, People, Person, PersonList,
+    PersonTable, Private, Table, GLOBALS,
 };
-use nostr_types::PublicKey;
+use nostr_types::{PublicKey, Url};
 use serde_json::Value;
 
 const ITEM_V_SPACE: f32 = 2.0;
@@ -23,6 +23,7 @@ const AVATAR_COL_WIDTH: f32 = AVATAR_SIZE_F32 * 3.0;
 const AVATAR_COL_SPACE: f32 = 20.0;
 const AVATAR_COL_WIDTH_SPACE: f32 = AVATAR_COL_WIDTH + AVATAR_COL_SPACE * 2.0;
 const MIN_ITEM_WIDTH: f32 = 200.0;
+const BADGE_SIZE: f32 = 32.0;
 
 pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
     let (pubkey, person) = match &app.page {
@@ -187,6 +188,21 @@ fn content(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, pubkey: PublicKey, pe
                 }
             }
 
+            let badges = gossip_lib::badges_for(pubkey).unwrap_or_default();
+            if !badges.is_empty() {
+                make_frame().show(ui, |ui| {
+                    ui.vertical(|ui| {
+                        item_label(ui, "Badges");
+                        ui.add_space(ITEM_V_SPACE);
+                        ui.horizontal_wrapped(|ui| {
+                            for badge in &badges {
+                                show_badge(app, ui, badge);
+                            }
+                        });
+                    });
+                });
+            }
+
             ui.add_space(10.0);
             ui.horizontal(|ui| {
                 ui.add_space(10.0);
@@ -368,6 +384,20 @@ fn content(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, pubkey: PublicKey, pe
                                             .to_overlord
                                             .send(ToOverlordMessage::SetActivePerson(pubkey));
                                     }
+                                } else if ui
+                                    .add(egui::Button::new("Refresh relays").small())
+                                    .on_hover_text(
+                                        "Fetch their latest relay list, in case you cannot reach them",
+                                    )
+                                    .clicked()
+                                {
+                                    app.setting_active_person = true;
+                                    let _ = GLOBALS
+                                        .to_overlord
+                                        .send(ToOverlordMessage::RefreshRelayList(person.pubkey));
+                                    let _ = GLOBALS
+                                        .to_overlord
+                                        .send(ToOverlordMessage::SetActivePerson(pubkey));
                                 }
                             });
                             ui.add_space(ITEM_V_SPACE);
@@ -594,7 +624,15 @@ fn content(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, pubkey: PublicKey, pe
 
                             ui.add_space(BTN_SPACING);
 
-                            if widgets::Button::primary(&app.theme, "Their Followers")
+                            let followers = {
+                                let followers = GLOBALS.followers.read();
+                                if followers.who == Some(pubkey) && followers.count() > 0 {
+                                    format!("Their Followers ({})", followers.count())
+                                } else {
+                                    "Their Followers".to_owned()
+                                }
+                            };
+                            if widgets::Button::primary(&app.theme, followers)
                                 .show(ui)
                                 .clicked()
                             {
@@ -711,6 +749,26 @@ fn profile_item_qr(
     }
 }
 
+fn show_badge(app: &mut GossipUi, ui: &mut Ui, badge: &Badge) {
+    let hover = if badge.description.is_empty() {
+        badge.name.clone()
+    } else {
+        format!("{}\n\n{}", badge.name, badge.description)
+    };
+
+    let image = badge
+        .small_image()
+        .and_then(|uu| Url::try_from_unchecked_url(uu).ok())
+        .map(|url| app.try_get_media(ui.ctx(), url, false, None));
+
+    if let Some(MediaLoadingResult::Ready(texture)) = image {
+        ui.add(egui::Image::new(&texture).fit_to_exact_size(Vec2::splat(BADGE_SIZE)))
+            .on_hover_text(hover);
+    } else {
+        ui.label(&badge.name).on_hover_text(hover);
+    }
+}
+
 fn make_frame() -> egui::Frame {
     egui::Frame::none()
         .inner_margin(egui::Margin {
@@ -6,6 +6,7 @@ use crate::ui::Page;
 use eframe::egui;
 use egui::{Context, Ui};
 use egui_winit::egui::Id;
+use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::Relay;
 use gossip_lib::GLOBALS;
 use nostr_types::RelayUrl;
@@ -47,6 +48,14 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
             widgets::TextEdit::search(&app.theme, &app.assets, &mut app.relays.search)
                 .desired_width(super::SEARCH_WIDTH)
                 .show(ui);
+            if widgets::Button::secondary(&app.theme, "Reconnect All")
+                .show(ui)
+                .on_hover_text("Drop and re-establish all relay connections, e.g. after waking from sleep or changing networks")
+                .clicked()
+            {
+                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::ReconnectAll);
+            }
+            btn_h_space!(ui);
             if widgets::Button::primary(&app.theme, "Add Relay")
                 .show(ui)
                 .clicked()
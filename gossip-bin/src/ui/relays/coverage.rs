@@ -56,7 +56,12 @@ impl<'a> CoverageEntry<'a> {
                 .size(list_entry::TITLE_FONT_SIZE)
                 .into(),
             Align::LEFT,
-            Some(app.theme.accent_color()),
+            // People on no relay at all stand out
+            Some(if self.relays.is_empty() {
+                app.theme.warning_marker_text_color()
+            } else {
+                app.theme.accent_color()
+            }),
             None,
         );
 
@@ -121,11 +126,13 @@ fn find_relays_for_pubkey(pk: &PublicKey) -> Vec<RelayUrl> {
 }
 
 pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    let uncovered = GLOBALS.relay_picker.uncovered_followed();
+
     widgets::page_header(
         ui,
         format!(
             "Low Coverage Report (less than {} relays)",
-            read_setting!(num_relays_per_person)
+            read_setting!(feed_relays_per_person)
         ),
         |ui| {
             ui.spacing_mut().button_padding *= 2.0;
@@ -139,6 +146,17 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
                     .send(ToOverlordMessage::RefreshScoresAndPickRelays);
             }
             ui.add_space(10.0);
+            if ui
+                .add_enabled(!uncovered.is_empty(), egui::Button::new("Find Relays"))
+                .on_hover_text("Ask the discover and indexer relays where the people who are not on any relay post")
+                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                .clicked()
+            {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::FindRelaysForUncovered);
+            }
+            ui.add_space(10.0);
             {
                 widgets::set_important_button_visuals(ui, app);
 
@@ -159,19 +177,64 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
             app.set_page(ctx, Page::Settings);
         }
     });
-    if GLOBALS.relay_picker.pubkey_counts_iter().count() > 0 {
+    ui.collapsing("Recent Relay-Picker Decisions", |ui| {
+        let decisions = GLOBALS.relay_picker.decisions();
+        if decisions.is_empty() {
+            ui.label("Nothing decided yet");
+        }
+        egui::ScrollArea::vertical()
+            .id_salt("relay-picker-decisions")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for decision in decisions.iter() {
+                    ui.label(format!(
+                        "{}: {}",
+                        crate::date_ago::date_ago(decision.when),
+                        decision
+                    ))
+                    .on_hover_text(
+                        decision
+                            .pubkeys
+                            .iter()
+                            .map(gossip_lib::names::best_name_from_pubkey_lookup)
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                    );
+                }
+            });
+    });
+    if GLOBALS.relay_picker.pubkey_counts_iter().count() > 0 || !uncovered.is_empty() {
         ui.label(
             format!("The Relay-Picker has tried to connect to at least {} relays \
                 for each person that you follow, however the pubkeys listed below are not fully covered. \
                 You can manually ask the Relay-Picker to pick again, however most of the time it has already \
-                tried its best.", read_setting!(num_relays_per_person)));
+                tried its best.", read_setting!(feed_relays_per_person)));
+        if !uncovered.is_empty() {
+            ui.add_space(5.0);
+            ui.label(
+                RichText::new(format!(
+                    "{} of them are not on any relay, so you won't see their posts. \
+                    Use 'Find Relays' to look harder for where they post.",
+                    uncovered.len()
+                ))
+                .color(app.theme.warning_marker_text_color()),
+            );
+        }
 
         ui.add_space(10.0);
         let id_salt = ui.auto_id_with("relay-coverage-scroll");
         app.vert_scroll_area().id_salt(id_salt).show(ui, |ui| {
+            // The uncovered people first, then the rest that are not fully covered
+            let needed = read_setting!(feed_relays_per_person) as usize;
+            let mut entries: Vec<(PublicKey, usize)> =
+                uncovered.iter().map(|pk| (*pk, needed)).collect();
             for elem in GLOBALS.relay_picker.pubkey_counts_iter() {
-                let pk = elem.key();
-                let count = elem.value();
+                if !uncovered.contains(elem.key()) {
+                    entries.push((*elem.key(), *elem.value()));
+                }
+            }
+
+            for (pk, count) in entries.iter() {
                 let name = gossip_lib::names::best_name_from_pubkey_lookup(pk);
                 let relays = find_relays_for_pubkey(pk);
                 let hover_text = format!("Go to profile of {}", name);
@@ -3,6 +3,7 @@ use crate::ui::{widgets, Page};
 use eframe::egui;
 use egui::{Context, Ui};
 use egui_winit::egui::Id;
+use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::Relay;
 use gossip_lib::GLOBALS;
 
@@ -45,6 +46,19 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
             widgets::TextEdit::search(&app.theme, &app.assets, &mut app.relays.search)
                 .desired_width(super::SEARCH_WIDTH)
                 .show(ui);
+            let health_check_status = GLOBALS.health_check_status.read().clone();
+            if let Some(status) = health_check_status {
+                ui.label(status);
+            } else if widgets::Button::secondary(&app.theme, "Check all")
+                .show(ui)
+                .on_hover_text("Connect to every known relay to see which are healthy")
+                .clicked()
+            {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::HealthCheckAllRelays);
+            }
+            btn_h_space!(ui);
             if widgets::Button::primary(&app.theme, "Add Relay")
                 .show(ui)
                 .clicked()
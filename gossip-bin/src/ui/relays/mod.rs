@@ -7,7 +7,7 @@ use super::{
 use eframe::egui;
 use egui::{Context, Ui};
 use egui_winit::egui::{vec2, Id, RichText};
-use gossip_lib::{comms::ToOverlordMessage, Relay, ScoreFactors, GLOBALS};
+use gossip_lib::{comms::ToOverlordMessage, Relay, RelayCredential, ScoreFactors, GLOBALS};
 use nostr_types::RelayUrl;
 
 mod active;
@@ -41,6 +41,12 @@ pub(super) struct RelayUi {
     /// Add Relay dialog
     add_dialog_step: AddRelayDialogStep,
     new_relay_url: String,
+
+    /// Relay credential dialog
+    credential_url: Option<RelayUrl>,
+    credential_is_header: bool,
+    credential_name: String,
+    credential_value: String,
 }
 
 impl RelayUi {
@@ -57,6 +63,10 @@ impl RelayUi {
             edit_needs_scroll: false,
             add_dialog_step: AddRelayDialogStep::Inactive,
             new_relay_url: RELAY_URL_PREPOPULATE.to_string(),
+            credential_url: None,
+            credential_is_header: true,
+            credential_name: String::new(),
+            credential_value: String::new(),
         }
     }
 
@@ -68,6 +78,7 @@ impl RelayUi {
         self.edit_needs_scroll = edit_relay.is_some();
         self.add_dialog_step = AddRelayDialogStep::Inactive;
         self.new_relay_url = RELAY_URL_PREPOPULATE.to_string();
+        self.credential_url = None;
         self.filter = RelayFilter::All;
     }
 }
@@ -115,6 +126,7 @@ pub(super) enum RelayFilter {
     DM,
     Search,
     Global,
+    Indexer,
     Hidden,
     AlwaysAllowConnect,
     NeverAllowConnect,
@@ -135,6 +147,7 @@ impl RelayFilter {
             RelayFilter::DM => "DM",
             RelayFilter::Search => "Search",
             RelayFilter::Global => "Global Feed",
+            RelayFilter::Indexer => "Indexer",
             RelayFilter::Hidden => "Hidden",
             RelayFilter::AlwaysAllowConnect => "Always allow connect",
             RelayFilter::NeverAllowConnect => "Never allow connect",
@@ -361,7 +374,7 @@ fn entry_dialog_step1(ui: &mut Ui, ctx: &Context, app: &mut GossipUi) {
                     .on_hover_cursor(egui::CursorIcon::PointingHand)
                     .clicked()
                 {
-                    if let Ok(url) = RelayUrl::try_from_str(&app.relays.new_relay_url) {
+                    if let Ok(url) = gossip_lib::relay::parse_relay_url(&app.relays.new_relay_url) {
                         let _ = GLOBALS
                             .to_overlord
                             .send(ToOverlordMessage::AddRelay(url.clone()));
@@ -427,6 +440,108 @@ fn entry_dialog_step2(ui: &mut Ui, app: &mut GossipUi) {
     }
 }
 
+pub(super) fn is_credential_dialog_active(app: &GossipUi) -> bool {
+    app.relays.credential_url.is_some()
+}
+
+pub(in crate::ui) fn start_credential_dialog(app: &mut GossipUi, url: RelayUrl) {
+    // Show the current credential, if we can decrypt it
+    let credential = gossip_lib::relay::get_relay_credential(&url).unwrap_or_default();
+    app.relays.credential_is_header =
+        !matches!(credential, Some(RelayCredential::UrlParameter { .. }));
+    app.relays.credential_name = credential
+        .as_ref()
+        .map(|c| c.name().to_owned())
+        .unwrap_or_else(|| "Authorization".to_owned());
+    app.relays.credential_value = credential
+        .as_ref()
+        .map(|c| c.value().to_owned())
+        .unwrap_or_default();
+    app.relays.credential_url = Some(url);
+}
+
+fn stop_credential_dialog(app: &mut GossipUi) {
+    app.relays.credential_url = None;
+    app.relays.credential_name.clear();
+    app.relays.credential_value.clear();
+}
+
+pub(super) fn credential_dialog(ctx: &Context, app: &mut GossipUi) {
+    let url = match app.relays.credential_url.clone() {
+        Some(url) => url,
+        None => return,
+    };
+
+    let dlg_size = vec2(ctx.screen_rect().width() * 0.5, 220.0);
+
+    let response = widgets::modal_popup(ctx, dlg_size, dlg_size, true, |ui| {
+        ui.add_space(10.0);
+        ui.heading(format!("Credential for {}", url));
+        ui.add_space(5.0);
+        ui.label("Some paid relays give you a token to connect with. It is sent with every connection to this relay, and takes effect the next time we connect.");
+        ui.add_space(10.0);
+
+        if !GLOBALS.identity.is_unlocked() {
+            ui.label("Credentials are stored encrypted with your key. Unlock your key to set one.");
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Send as:");
+            ui.radio_value(&mut app.relays.credential_is_header, true, "HTTP header");
+            ui.radio_value(&mut app.relays.credential_is_header, false, "URL parameter");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.add(
+                text_edit_line!(app, app.relays.credential_name)
+                    .desired_width(200.0)
+                    .hint_text("Authorization"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Value:");
+            ui.add(
+                text_edit_line!(app, app.relays.credential_value)
+                    .password(true)
+                    .desired_width(200.0),
+            );
+        });
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            if ui.button("Remove").clicked() {
+                match gossip_lib::relay::set_relay_credential(&url, None) {
+                    Ok(()) => stop_credential_dialog(app),
+                    Err(e) => GLOBALS.status_queue.write().write(format!("{}", e)),
+                }
+            }
+            let can_save = !app.relays.credential_name.trim().is_empty()
+                && !app.relays.credential_value.is_empty();
+            if ui
+                .add_enabled(can_save, egui::Button::new("Save"))
+                .clicked()
+            {
+                let name = app.relays.credential_name.trim().to_owned();
+                let value = app.relays.credential_value.clone();
+                let credential = if app.relays.credential_is_header {
+                    RelayCredential::Header { name, value }
+                } else {
+                    RelayCredential::UrlParameter { name, value }
+                };
+                match gossip_lib::relay::set_relay_credential(&url, Some(credential)) {
+                    Ok(()) => stop_credential_dialog(app),
+                    Err(e) => GLOBALS.status_queue.write().write(format!("{}", e)),
+                }
+            }
+        });
+    });
+
+    if response.inner.clicked() || response.response.clicked_elsewhere() {
+        stop_credential_dialog(app);
+    }
+}
+
 ///
 /// Draw button with configure popup
 ///
@@ -597,6 +712,11 @@ pub(super) fn relay_filter_combo(app: &mut GossipUi, ui: &mut Ui) {
                 RelayFilter::Global,
                 RelayFilter::Global.get_name(),
             );
+            ui.selectable_value(
+                &mut app.relays.filter,
+                RelayFilter::Indexer,
+                RelayFilter::Indexer.get_name(),
+            );
             ui.selectable_value(
                 &mut app.relays.filter,
                 RelayFilter::Hidden,
@@ -691,6 +811,7 @@ pub(super) fn filter_relay(rui: &RelayUi, ri: &Relay) -> bool {
         RelayFilter::DM => ri.has_usage_bits(Relay::DM),
         RelayFilter::Search => ri.has_usage_bits(Relay::SEARCH),
         RelayFilter::Global => ri.has_usage_bits(Relay::GLOBAL),
+        RelayFilter::Indexer => ri.has_usage_bits(Relay::INDEXER),
         RelayFilter::Hidden => ri.hidden,
         RelayFilter::AlwaysAllowConnect => ri.allow_connect == Some(true),
         RelayFilter::NeverAllowConnect => ri.allow_connect == Some(false),
@@ -2,6 +2,7 @@ use crate::ui::GossipUi;
 use eframe::egui;
 use egui::widgets::Slider;
 use egui::{Context, Ui};
+use gossip_lib::{FeedOrdering, FutureEventAction};
 
 pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
     ui.heading("Content");
@@ -39,6 +40,60 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         reset_button!(app, ui, feed_thread_scroll_to_main_event);
     });
 
+    ui.horizontal(|ui| {
+        ui.label("Order feeds: ")
+            .on_hover_text("Grouping by thread puts replies under the note they reply to. Takes effect when the feed next recomputes.");
+        let current = FeedOrdering::from_setting(&app.unsaved_settings.feed_ordering);
+        egui::ComboBox::from_id_salt("FeedOrdering")
+            .selected_text(current.name())
+            .show_ui(ui, |ui| {
+                for ordering in FeedOrdering::all() {
+                    if ui
+                        .add(egui::widgets::SelectableLabel::new(
+                            *ordering == current,
+                            ordering.name(),
+                        ))
+                        .clicked()
+                    {
+                        app.unsaved_settings.feed_ordering = ordering.as_str().to_owned();
+                    }
+                }
+            });
+        reset_button!(app, ui, feed_ordering);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Allow events from the future by: ").on_hover_text(
+            "Clocks differ, so events a little ahead of our time are treated normally.",
+        );
+        ui.add(
+            Slider::new(&mut app.unsaved_settings.future_allowance_secs, 0..=3600).text("seconds"),
+        );
+        reset_button!(app, ui, future_allowance_secs);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Events further in the future: ")
+            .on_hover_text("Accepted events show up once their time arrives. Sorting by time received shows them right away, as if they were created when they arrived.");
+        let current = FutureEventAction::from_setting(&app.unsaved_settings.future_event_action);
+        egui::ComboBox::from_id_salt("FutureEventAction")
+            .selected_text(current.name())
+            .show_ui(ui, |ui| {
+                for action in FutureEventAction::all() {
+                    if ui
+                        .add(egui::widgets::SelectableLabel::new(
+                            *action == current,
+                            action.name(),
+                        ))
+                        .clicked()
+                    {
+                        app.unsaved_settings.future_event_action = action.as_str().to_owned();
+                    }
+                }
+            });
+        reset_button!(app, ui, future_event_action);
+    });
+
     ui.add_space(10.0);
     ui.heading("Event Selection Settings");
     ui.add_space(10.0);
@@ -98,6 +153,15 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         reset_button!(app, ui, show_long_form);
     });
 
+    ui.horizontal(|ui| {
+        ui.checkbox(
+            &mut app.unsaved_settings.include_replies,
+            "Include replies in feeds",
+        )
+        .on_hover_text("Each feed can still be switched between main posts and replies. Replies always show in threads.");
+        reset_button!(app, ui, include_replies);
+    });
+
     ui.add_space(10.0);
     ui.heading("Spam Settings");
     ui.add_space(10.0);
@@ -120,6 +184,15 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         reset_button!(app, ui, limit_inbox_seeking_to_inbox_relays);
     });
 
+    ui.horizontal(|ui| {
+        ui.checkbox(
+            &mut app.unsaved_settings.fetch_inbox_on_feed_relays,
+            "Look for mentions on the relays of people you follow",
+        )
+            .on_hover_text("People who don't use NIP-65 may send replies and mentions to their own relays rather than to your READ relays. Turning this off only looks on your READ relays, which uses far fewer subscriptions if you follow many people. Takes effect as relays are next assigned.");
+        reset_button!(app, ui, fetch_inbox_on_feed_relays);
+    });
+
     ui.horizontal(|ui| {
         ui.checkbox(
             &mut app.unsaved_settings.apply_spam_filter_on_incoming_events,
@@ -212,5 +285,18 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         reset_button!(app, ui, show_deleted_events);
     });
 
+    ui.horizontal(|ui| {
+        ui.checkbox(
+            &mut app.unsaved_settings.retain_deleted_events,
+            "Retain deleted events in the database",
+        )
+        .on_hover_text(if app.unsaved_settings.retain_deleted_events {
+            "Disable to remove events from the database when their author deletes them"
+        } else {
+            "Enable to keep deleted events (marked as deleted) for moderation or review"
+        });
+        reset_button!(app, ui, retain_deleted_events);
+    });
+
     ui.add_space(20.0);
 }
@@ -1,7 +1,9 @@
 use crate::ui::GossipUi;
 use eframe::egui;
 use egui::widgets::Slider;
-use egui::{Context, Ui};
+use egui::{Context, TextEdit, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{Rebuild, Storage, GLOBALS};
 
 pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
     ui.heading("Storage Settings");
@@ -24,8 +26,182 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         reset_button!(app, ui, cache_prune_period_days);
     });
 
+    ui.horizontal(|ui| {
+        ui.checkbox(
+            &mut app.unsaved_settings.auto_prune,
+            "Prune old events and cached files automatically",
+        )
+        .on_hover_text(
+            "Uses the periods above. Your own events and threads you took part in are kept.",
+        );
+        reset_button!(app, ui, auto_prune);
+    });
+
+    ui.add_enabled_ui(app.unsaved_settings.auto_prune, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Prune automatically every");
+            ui.add(
+                Slider::new(&mut app.unsaved_settings.auto_prune_interval_days, 1..=90)
+                    .text("days"),
+            );
+            reset_button!(app, ui, auto_prune_interval_days);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Unless the database is smaller than")
+                .on_hover_text("Small databases are left alone until the next time pruning is due");
+            ui.add(
+                Slider::new(&mut app.unsaved_settings.auto_prune_min_size_mb, 0..=16384).text("MB"),
+            );
+            reset_button!(app, ui, auto_prune_min_size_mb);
+        });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("How many decrypted DMs to keep in memory")
+            .on_hover_text(
+                "Saves decrypting them again every time they are shown. Zero keeps none.",
+            );
+        ui.add(Slider::new(&mut app.unsaved_settings.dm_cache_size, 0..=4096).text("DMs"));
+        reset_button!(app, ui, dm_cache_size);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Summarize reactions to an event once it has more than")
+            .on_hover_text(
+                "Keeps counts and who reacted instead of a record per reaction. Zero never summarizes.",
+            );
+        ui.add(
+            Slider::new(
+                &mut app.unsaved_settings.reaction_summary_threshold,
+                0..=10000,
+            )
+            .text("reactions"),
+        );
+        reset_button!(app, ui, reaction_summary_threshold);
+    });
+
+    ui.horizontal(|ui| {
+        ui.checkbox(
+            &mut app.unsaved_settings.prune_summarized_reactions,
+            "Delete reaction events once they are summarized",
+        )
+        .on_hover_text("Saves space, but the reactions can no longer be viewed or rebroadcast");
+        reset_button!(app, ui, prune_summarized_reactions);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Only store events of these kinds")
+            .on_hover_text("Kind numbers separated by spaces, e.g. '0 1 3 5 10002'. Leave empty to store every kind. Your own events are always stored. Takes effect on restart. See PRUNING.md for what you give up.");
+        ui.add(
+            TextEdit::singleline(&mut app.unsaved_settings.stored_kinds)
+                .desired_width(200.0),
+        );
+        reset_button!(app, ui, stored_kinds);
+    });
+    ui.label("Events of other kinds are kept in memory only. They are left out of every feed except Global and are not counted on the notes they refer to.");
+
+    ui.add_space(20.0);
+    ui.label("Other than automatic pruning, pruning must be done from the command line when gossip is not running. See https://github.com/mikedilger/gossip/tree/master/docs/PRUNING.md");
+
+    ui.add_space(20.0);
+    ui.heading("Compaction");
+    ui.add_space(10.0);
+
+    ui.label("Pruning frees space inside the database file but does not shrink it. Compacting rewrites the database into a new file without the free space. This happens at startup, so it takes effect the next time gossip starts.");
+    ui.label("WARNING: compacting temporarily needs about as much free disk space again as the database itself uses.");
+    ui.horizontal(|ui| {
+        if Storage::is_compaction_requested() {
+            ui.label("The database will be compacted when gossip next starts.");
+            if ui.button("Cancel").clicked() {
+                if let Err(e) = Storage::cancel_compaction_request() {
+                    GLOBALS.status_queue.write().write(format!("{}", e));
+                }
+            }
+        } else if ui.button("Compact on next restart").clicked() {
+            if let Err(e) = Storage::request_compaction() {
+                GLOBALS.status_queue.write().write(format!("{}", e));
+            }
+        }
+    });
+
     ui.add_space(20.0);
-    ui.label("Pruning must be done from the command line when gossip is not running. See https://github.com/mikedilger/gossip/tree/master/docs/PRUNING.md");
+    ui.heading("Migrations and Rebuilds");
+    ui.add_space(10.0);
+
+    match GLOBALS.db().migration_status() {
+        Ok(migrations) => {
+            let applied = migrations.iter().filter(|m| m.applied).count();
+            let label = ui.label(format!(
+                "Database migrations applied: {} of {}",
+                applied,
+                migrations.len()
+            ));
+            let pending: Vec<String> = migrations
+                .iter()
+                .filter(|m| !m.applied)
+                .map(|m| format!("m{}", m.level))
+                .collect();
+            if !pending.is_empty() {
+                label.on_hover_text(format!("Not yet applied: {}", pending.join(", ")));
+            }
+        }
+        Err(e) => {
+            ui.label(format!("Could not read the migration level: {}", e));
+        }
+    }
+
+    ui.label("If notes, threads or reactions go missing or show up wrong, rebuilding the data gossip derives from stored events may help. Rebuilds happen at startup, so they take effect the next time gossip starts.");
+    for rebuild in Rebuild::ALL {
+        ui.horizontal(|ui| {
+            ui.label(rebuild.name());
+            if GLOBALS.db().is_rebuild_pending(rebuild) {
+                ui.label("will be rebuilt when gossip next starts.");
+                if ui.button("Cancel").clicked() {
+                    if let Err(e) = GLOBALS.db().set_rebuild_pending(rebuild, false, None) {
+                        GLOBALS.status_queue.write().write(format!("{}", e));
+                    }
+                }
+            } else if ui.button("Rebuild at next startup").clicked() {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::ScheduleRebuild(rebuild));
+            }
+        });
+    }
+
+    ui.add_space(20.0);
+    ui.heading("Audit");
+    ui.add_space(10.0);
+
+    let audit_status = GLOBALS.audit_status.read().clone();
+    ui.horizontal(|ui| {
+        if let Some(status) = audit_status {
+            ui.label(status);
+            if ui.button("Cancel").clicked() {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::AuditStoredEventsCancel);
+            }
+        } else if ui
+            .button("Verify all stored events")
+            .on_hover_text("Re-verify the signature of every stored event. Events that fail are moved into quarantine.")
+            .clicked()
+        {
+            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::AuditStoredEvents);
+        }
+    });
+
+    let quarantined = GLOBALS.db().get_quarantine_len().unwrap_or(0);
+    ui.horizontal(|ui| {
+        ui.label(format!("Quarantined events: {}", quarantined));
+        if quarantined > 0 && ui.button("Purge").clicked() {
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::PurgeQuarantinedEvents);
+        }
+    });
+    ui.label("Quarantined events can be reviewed from the command line with: gossip print_quarantined_events");
 
     ui.add_space(20.0);
 }
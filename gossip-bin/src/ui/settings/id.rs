@@ -46,5 +46,15 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         reset_button!(app, ui, login_at_startup);
     });
 
+    // Auto-lock
+    ui.add_space(20.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Lock key after inactivity: ")
+            .on_hover_text("Lock your private key after this many minutes without any input. Actions that need the key wait until you unlock it again. Zero means never.");
+        ui.add(Slider::new(&mut app.unsaved_settings.auto_lock_minutes, 0..=240).text("minutes"));
+        reset_button!(app, ui, auto_lock_minutes);
+    });
+
     ui.add_space(20.0);
 }
@@ -16,6 +16,29 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         reset_button!(app, ui, offline);
     });
 
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut app.unsaved_settings.quiet_hours, "Quiet Hours")
+            .on_hover_text(
+                "If selected, between the hours below (local time) gossip stops following people and fetching reactions, keeping only your inbox and DMs. Takes effect on save.",
+            );
+        reset_button!(app, ui, quiet_hours);
+    });
+
+    if app.unsaved_settings.quiet_hours {
+        ui.horizontal(|ui| {
+            ui.label("Quiet from")
+                .on_hover_text("The hour quiet mode starts");
+            ui.add(Slider::new(&mut app.unsaved_settings.quiet_hours_start, 0..=23).text("h"));
+            reset_button!(app, ui, quiet_hours_start);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Quiet until")
+                .on_hover_text("The hour quiet mode ends");
+            ui.add(Slider::new(&mut app.unsaved_settings.quiet_hours_end, 0..=23).text("h"));
+            reset_button!(app, ui, quiet_hours_end);
+        });
+    }
+
     ui.horizontal(|ui| {
         ui.checkbox(&mut app.unsaved_settings.load_avatars, "Fetch Avatars").on_hover_text("If disabled, avatars will not be fetched, but cached avatars will still display. Takes effect on save.");
         reset_button!(app, ui, load_avatars);
@@ -26,6 +49,16 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         reset_button!(app, ui, load_media);
     });
 
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut app.unsaved_settings.prefetch_profile_media, "Fetch Avatars and Banners Early").on_hover_text("If enabled, avatars and banners are fetched as soon as someone's metadata arrives, so they are ready when displayed. If disabled, they are fetched when first displayed. Takes effect on save.");
+        reset_button!(app, ui, prefetch_profile_media);
+    });
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut app.unsaved_settings.metered_connection, "Metered Connection").on_hover_text("If enabled, avatars and banners are never fetched early, only when they are displayed. Use this when you pay for the data you use. Takes effect on save.");
+        reset_button!(app, ui, metered_connection);
+    });
+
     ui.horizontal(|ui| {
         ui.checkbox(&mut app.unsaved_settings.check_nip05, "Check NIP-05").on_hover_text("If disabled, NIP-05 fetches will not be performed, but existing knowledge will be preserved, and following someone by NIP-05 will override this and do the fetch. Takes effect on save.");
         reset_button!(app, ui, check_nip05);
@@ -65,9 +98,24 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
     });
 
     ui.horizontal(|ui| {
-        ui.label("Number of relays to query per person: ").on_hover_text("We will query N relays per person. Many people share the same relays so those will be queried about multiple people. Takes affect on restart. I recommend 2. Too many and gossip will (currently) keep connecting to new relays trying to find the unfindable, loading many events from each. Takes effect on restart.");
-        ui.add(Slider::new(&mut app.unsaved_settings.num_relays_per_person, 1..=4).text("relays"));
-        reset_button!(app, ui, num_relays_per_person);
+        ui.checkbox(
+            &mut app.unsaved_settings.auto_advertise_on_change,
+            "Automatically advertise relay list when it changes",
+        )
+        .on_hover_text("If enabled, changing the inbox, outbox or DM usage of your relays will republish your relay list shortly after you stop making changes, as long as your key is unlocked.");
+        reset_button!(app, ui, auto_advertise_on_change);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Number of relays to query per person for their feed: ").on_hover_text("We will query N relays per person for their posts. Many people share the same relays so those will be queried about multiple people. I recommend 2. Too many and gossip will (currently) keep connecting to new relays trying to find the unfindable, loading many events from each. Takes effect on restart.");
+        ui.add(Slider::new(&mut app.unsaved_settings.feed_relays_per_person, 1..=4).text("relays"));
+        reset_button!(app, ui, feed_relays_per_person);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Number of relays to query per person for their metadata: ").on_hover_text("We will ask N relays per person for their metadata. Metadata is small, so asking more relays makes it more likely to be found and up to date without loading many events.");
+        ui.add(Slider::new(&mut app.unsaved_settings.metadata_relays_per_person, 1..=6).text("relays"));
+        reset_button!(app, ui, metadata_relays_per_person);
     });
 
     ui.horizontal(|ui| {
@@ -85,6 +133,26 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         reset_button!(app, ui, num_relays_for_counting);
     });
 
+    ui.horizontal(|ui| {
+        ui.label("Number of relays to query for a hashtag feed: ")
+            .on_hover_text("We will pick the N best of your read relays to do this.");
+        ui.add(
+            Slider::new(&mut app.unsaved_settings.num_relays_for_hashtags, 1..=20).text("relays"),
+        );
+        reset_button!(app, ui, num_relays_for_hashtags);
+    });
+
+    ui.horizontal(|ui| {
+        ui.checkbox(
+            &mut app.unsaved_settings.restore_manual_relays,
+            "Reconnect on startup to relays you connected to for the global or a relay feed",
+        )
+        .on_hover_text(
+            "Relays you disconnect from, or have not used for a feed in two weeks, are not reconnected to.",
+        );
+        reset_button!(app, ui, restore_manual_relays);
+    });
+
     ui.add_space(10.0);
     ui.heading("HTTP Fetch Settings");
     ui.add_space(10.0);
@@ -120,6 +188,19 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         );
         reset_button!(app, ui, fetcher_max_requests_per_host);
     });
+    ui.horizontal(|ui| {
+        ui.label("Max simultaneous HTTP requests overall");
+        ui.add(
+            Slider::new(&mut app.unsaved_settings.fetcher_max_requests, 1..=64).text("requests"),
+        );
+        reset_button!(app, ui, fetcher_max_requests);
+    });
+    ui.horizontal(|ui| {
+        ui.label("How many times to retry a request after a timeout or rate limit")
+            .on_hover_text("The host is avoided for a while before each retry");
+        ui.add(Slider::new(&mut app.unsaved_settings.fetcher_max_retries, 0..=10).text("retries"));
+        reset_button!(app, ui, fetcher_max_retries);
+    });
     ui.horizontal(|ui| {
         ui.label("How long to avoid contacting a host after a minor error");
         ui.add(
@@ -275,6 +356,17 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         );
         reset_button!(app, ui, media_becomes_stale_hours);
     });
+    ui.horizontal(|ui| {
+        ui.label("How long before a connected relay's NIP-11 information becomes stale and needs rechecking?");
+        ui.add(
+            Slider::new(
+                &mut app.unsaved_settings.relay_info_becomes_stale_hours,
+                1..=168,
+            )
+            .text("hours"),
+        );
+        reset_button!(app, ui, relay_info_becomes_stale_hours);
+    });
 
     ui.add_space(20.0);
 }
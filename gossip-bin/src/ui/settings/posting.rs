@@ -23,6 +23,24 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         reset_button!(app, ui, pow);
     });
 
+    ui.horizontal(|ui| {
+        ui.label("Maximum people tagged in replies: ")
+            .on_hover_text("Replies copy the people tagged in the post being replied to. Beyond this many, the earliest tagged are dropped. The author of the post and of the thread come first.");
+        ui.add(
+            Slider::new(&mut app.unsaved_settings.max_reply_p_tags, 0..=500).text("people"),
+        );
+        reset_button!(app, ui, max_reply_p_tags);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Retries when a relay refuses a post: ")
+            .on_hover_text("When a relay refuses a post because it is rate-limiting or having trouble, send it again this many times, waiting longer each time. Posts refused for other reasons are not retried automatically. Refused posts are listed under Pending.");
+        ui.add(
+            Slider::new(&mut app.unsaved_settings.post_retry_attempts, 0..=10).text("retries"),
+        );
+        reset_button!(app, ui, post_retry_attempts);
+    });
+
     ui.horizontal(|ui| {
         ui.checkbox(
             &mut app.unsaved_settings.set_client_tag,
@@ -32,6 +50,15 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         reset_button!(app, ui, set_client_tag);
     });
 
+    ui.horizontal(|ui| {
+        ui.checkbox(
+            &mut app.unsaved_settings.strip_url_tracking,
+            "Remove tracking parameters from links in posts",
+        )
+        .on_hover_text("Removes parameters like utm_source and fbclid from links before publishing. Links inside code are left alone.");
+        reset_button!(app, ui, strip_url_tracking);
+    });
+
     ui.horizontal(|ui| {
         ui.checkbox(
             &mut app.unsaved_settings.set_user_agent,
@@ -4,7 +4,9 @@ use nostr_types::{PublicKeyHex, RelayUrl, Unixtime};
 use std::fmt;
 
 use crate::ui::{widgets, GossipUi, Theme};
+use gossip_lib::relay_metrics::{self, IngestCounts};
 use gossip_lib::{comms::ToOverlordMessage, Relay, GLOBALS};
+use humansize::{format_size, DECIMAL};
 
 use super::{
     list_entry::{
@@ -19,13 +21,13 @@ const LIST_VIEW_HEIGHT: f32 = 60.0;
 /// Height of the list view (width always max. available)
 const DETAIL_VIEW_HEIGHT: f32 = 90.0;
 /// Height of the edit view (width always max. available)
-const EDIT_VIEW_HEIGHT: f32 = 300.0;
+const EDIT_VIEW_HEIGHT: f32 = 450.0;
 /// Height required for one auth-permission drop-down
 const EDIT_VIEW_AUTH_PERM_HEIGHT: f32 = 25.0;
 /// Y-offset for first separator
 const HLINE_1_Y_OFFSET: f32 = LIST_VIEW_HEIGHT;
 /// Y-offset for second separator
-const HLINE_2_Y_OFFSET: f32 = 230.0;
+const HLINE_2_Y_OFFSET: f32 = 380.0;
 /// Y top for the detail section
 const DETAIL_SECTION_TOP: f32 = TEXT_TOP + LIST_VIEW_HEIGHT + 20.0;
 /// Space needed for rank adjuster
@@ -52,7 +54,15 @@ const USAGE_LINE_X_END: f32 = -10.0;
 const USAGE_LINE_THICKNESS: f32 = 1.0;
 /// Start of permission section from top
 const PERMISSION_SECTION_TOP: f32 = 230.0;
-const PERMISSION_SECTION_SIZE: Vec2 = Vec2 { x: 223.0, y: 50.0 };
+const PERMISSION_SECTION_SIZE: Vec2 = Vec2 { x: 223.0, y: 200.0 };
+/// Choices offered for the maximum backfill age, in seconds
+const MAX_BACKFILL_AGE_CHOICES: [Option<u64>; 5] = [
+    None,
+    Some(60 * 60 * 6),
+    Some(60 * 60 * 24),
+    Some(60 * 60 * 24 * 7),
+    Some(60 * 60 * 24 * 30),
+];
 /// Spacing between nip11 text rows
 const NIP11_Y_SPACING: f32 = 20.0;
 /// Status symbol for status color indicator
@@ -80,6 +90,7 @@ const SPAMSAFE_HOVER_TEXT: &str = "Relay is trusted to filter spam. If not set,
 const DM_USE_HOVER_TEXT: &str = "Use Relay to receive and send Direct Messages";
 const GLOBAL_FEED_HOVER_TEXT: &str = "Use Relay for Global feed";
 const SEARCH_USE_HOVER_TEXT: &str = "Use Relay in searches";
+const INDEXER_HOVER_TEXT: &str = "Relay has broad coverage of profiles and relay lists. Used to find people we know no relays for.";
 
 #[derive(Clone, PartialEq)]
 pub enum RelayEntryView {
@@ -137,6 +148,7 @@ struct UsageBits {
     dm: bool,
     global_feed: bool,
     search: bool,
+    indexer: bool,
 }
 
 impl UsageBits {
@@ -152,6 +164,7 @@ impl UsageBits {
             dm: usage_bits & Relay::DM == Relay::DM,
             global_feed: usage_bits & Relay::GLOBAL == Relay::GLOBAL,
             search: usage_bits & Relay::SEARCH == Relay::SEARCH,
+            indexer: usage_bits & Relay::INDEXER == Relay::INDEXER,
         }
     }
 
@@ -287,7 +300,7 @@ impl RelayEntry {
 impl RelayEntry {
     fn paint_title(&self, ui: &mut Ui, theme: &Theme, rect: &Rect) {
         let pos = rect.min + vec2(TEXT_LEFT + STATUS_SYMBOL_SPACE, TEXT_TOP);
-        super::relay_url_at(
+        let url_response = super::relay_url_at(
             ui,
             theme,
             pos,
@@ -295,8 +308,43 @@ impl RelayEntry {
             &self.relay.url,
             Some(list_entry::TITLE_FONT_SIZE),
             true,
-        )
-        .on_hover_text(self.relay.url.as_str().to_owned());
+        );
+        let url_rect = url_response.rect;
+        url_response.on_hover_text(self.relay.url.as_str().to_owned());
+
+        // paint the no-post lock
+        let mut right = url_rect.max.x;
+        if self.relay.no_post {
+            let lock = RichText::new("\u{1F512} no post").size(list_entry::TITLE_FONT_SIZE);
+            let pos = pos2(right + 10.0, pos.y);
+            let rect = draw_text_at(
+                ui,
+                pos,
+                lock.into(),
+                Align::LEFT,
+                Some(theme.red_500()),
+                None,
+            );
+            ui.interact(rect, ui.next_auto_id().with("no_post"), Sense::hover())
+                .on_hover_text("Nothing is ever posted to this relay");
+            right = rect.max.x;
+        }
+
+        // paint the content mute
+        if self.relay.mute_content {
+            let mute = RichText::new("\u{1F507} muted").size(list_entry::TITLE_FONT_SIZE);
+            let pos = pos2(right + 10.0, pos.y);
+            let rect = draw_text_at(
+                ui,
+                pos,
+                mute.into(),
+                Align::LEFT,
+                Some(theme.red_500()),
+                None,
+            );
+            ui.interact(rect, ui.next_auto_id().with("mute_content"), Sense::hover())
+                .on_hover_text("Events seen only on this relay are hidden from feeds");
+        }
 
         // paint status indicator
         // green - connected
@@ -450,6 +498,22 @@ impl RelayEntry {
                 .send(ToOverlordMessage::TestRelay(self.relay.url.to_owned()));
         }
 
+        let pos = pos + vec2(80.0, 0.0);
+        let id = self.make_id("credential");
+        let text = if GLOBALS
+            .db()
+            .has_relay_credential(&self.relay.url)
+            .unwrap_or(false)
+        {
+            "Credential \u{2714}"
+        } else {
+            "Credential"
+        };
+        let response_credential = draw_link_at(ui, id, pos, text.into(), Align::Min, true, true);
+        if response_credential.clicked() {
+            crate::ui::relays::start_credential_dialog(app, self.relay.url.clone());
+        }
+
         // pass the response back so the page knows the edit view should close
         response_hide | response_feed
     }
@@ -463,7 +527,7 @@ impl RelayEntry {
                 self.relay.success_rate() * 100.0,
                 self.relay.success_count
             ));
-            draw_text_at(
+            let rect = draw_text_at(
                 ui,
                 pos,
                 text.into(),
@@ -471,29 +535,38 @@ impl RelayEntry {
                 Some(ui.visuals().text_color()),
                 None,
             );
+            ui.interact(rect, ui.next_auto_id().with("ingest"), Sense::hover())
+                .on_hover_text(ingest_summary(&self.relay));
 
             // ---- Following ----
             let pos = pos + vec2(STATS_COL_2_X, 0.0);
             // let mut active = self.enabled;
-            let text = if let Some(count) = self.user_count {
-                RichText::new(format!("Following: {}", count))
+            let mut text = if let Some(count) = self.user_count {
+                format!("Following: {}", count)
             } else {
                 // active = false;
-                RichText::new("Following: ---")
+                "Following: ---".to_string()
+            };
+            // Warn if we are near the relay's subscription limit
+            let color = if relay_metrics::filter_usage(&self.relay).near_limit() {
+                text += " ⚠";
+                ui.visuals().warn_fg_color
+            } else {
+                ui.visuals().text_color()
             };
+            let text = RichText::new(text);
             // let id = self.make_id("following_link");
             // let response = draw_link_at(ui, id, pos, text.into(), Align::Min, active, true);
             // if response.clicked() {
             //     // TODO go to following page for this relay?
             // }
-            draw_text_at(
-                ui,
-                pos,
-                text.into(),
-                Align::LEFT,
-                Some(ui.visuals().text_color()),
-                None,
-            );
+            let rect = draw_text_at(ui, pos, text.into(), Align::LEFT, Some(color), None);
+            ui.interact(
+                rect,
+                ui.next_auto_id().with("subscriptions"),
+                Sense::hover(),
+            )
+            .on_hover_text(subscriptions_summary(&self.relay));
 
             // ---- Last event ----
             let pos = pos + vec2(STATS_COL_3_X, 0.0);
@@ -504,7 +577,7 @@ impl RelayEntry {
                 ago += "?";
             }
             let text = RichText::new(format!("Last event: {}", ago));
-            draw_text_at(
+            let rect = draw_text_at(
                 ui,
                 pos,
                 text.into(),
@@ -512,6 +585,12 @@ impl RelayEntry {
                 Some(ui.visuals().text_color()),
                 None,
             );
+            let skew = match self.relay.clock_skew {
+                Some(skew) => format!("Estimated clock skew: {:+} seconds", skew),
+                None => "Estimated clock skew: unknown".to_string(),
+            };
+            ui.interact(rect, ui.next_auto_id().with("skew"), Sense::hover())
+                .on_hover_text(skew);
 
             // ---- Last connection ----
             let pos = pos + vec2(STATS_COL_4_X, 0.0);
@@ -522,7 +601,7 @@ impl RelayEntry {
                 ago += "?";
             }
             let text = RichText::new(format!("Last connection: {}", ago));
-            draw_text_at(
+            let rect = draw_text_at(
                 ui,
                 pos,
                 text.into(),
@@ -530,6 +609,17 @@ impl RelayEntry {
                 Some(ui.visuals().text_color()),
                 None,
             );
+            let rejection = match &self.relay.last_rejection {
+                Some((rejection, message, at)) => format!(
+                    "Last rejected us ({}) {}: {}",
+                    rejection,
+                    crate::date_ago::date_ago(Unixtime(*at as i64)),
+                    message
+                ),
+                None => "Has not rejected us".to_string(),
+            };
+            ui.interact(rect, ui.next_auto_id().with("rejection"), Sense::hover())
+                .on_hover_text(rejection);
 
             // ---- Rank ----
             let pos = pos + vec2(STATS_COL_5_X, 0.0);
@@ -1191,6 +1281,36 @@ impl RelayEntry {
                 None,
             );
         }
+        {
+            // ---- Indexer use ----
+            let pos = pos + vec2(USAGE_SWITCH_X_SPACING, 0.0);
+            let id = self.make_id("indexer_switch");
+            let sw_rect = Rect::from_min_size(pos - vec2(0.0, USAGE_SWITCH_Y_OFFSET), switch_size);
+            let response = widgets::switch_custom_at(
+                ui,
+                true,
+                &mut self.usage.indexer,
+                sw_rect,
+                id,
+                knob_fill,
+                on_fill,
+                off_fill,
+            );
+            if response.changed() {
+                modify_relay(&self.relay.url, |relay| {
+                    relay.adjust_usage_bit(Relay::INDEXER, self.usage.indexer)
+                });
+            }
+            response.on_hover_text(INDEXER_HOVER_TEXT);
+            draw_text_at(
+                ui,
+                pos + vec2(ui.spacing().item_spacing.x + switch_size.x, 0.0),
+                "Indexer".into(),
+                Align::LEFT,
+                Some(ui.visuals().text_color()),
+                None,
+            );
+        }
     }
 
     pub fn paint_rank_setting(&mut self, ui: &mut Ui, rect: &Rect) {
@@ -1322,6 +1442,78 @@ impl RelayEntry {
                     });
                 }
             }
+
+            ui.add_space(3.0);
+            let mut max_backfill_age = self.relay.max_backfill_age;
+            let response = max_backfill_age_combo(ui, &mut max_backfill_age, "Max backfill:");
+            if response.is_some() && response.unwrap().changed() {
+                modify_relay(&self.relay.url, |relay| {
+                    relay.max_backfill_age = max_backfill_age;
+                });
+            }
+
+            ui.add_space(3.0);
+            skip_events_before_edit(ui, &self.relay);
+
+            ui.add_space(3.0);
+            let mut nostr_subprotocol = self.relay.nostr_subprotocol;
+            let response = ui
+                .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                    let response = ui.checkbox(&mut nostr_subprotocol, "");
+                    ui.add(egui::Label::new("Nostr subprotocol:")).on_hover_text(
+                        "Ask for the 'nostr' websocket subprotocol when connecting. Some relays require it. This is turned on automatically when a relay refuses to connect without it.",
+                    );
+                    response
+                })
+                .inner;
+            if response.changed() {
+                modify_relay(&self.relay.url, |relay| {
+                    relay.nostr_subprotocol = nostr_subprotocol;
+                });
+            }
+
+            ui.add_space(3.0);
+            let mut no_post = self.relay.no_post;
+            let response = ui
+                .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                    let response = ui.checkbox(&mut no_post, "");
+                    ui.add(egui::Label::new("Never post here:")).on_hover_text(
+                        "Read only. Nothing will ever be posted to this relay, even if it is a Write relay or you pick it when posting.",
+                    );
+                    response
+                })
+                .inner;
+            if response.changed() {
+                modify_relay(&self.relay.url, |relay| {
+                    relay.no_post = no_post;
+                });
+            }
+
+            ui.add_space(3.0);
+            let mut trust = self.relay.trust;
+            let response = trust_combo(ui, &mut trust, "Content trust:");
+            if response.is_some() && response.unwrap().changed() {
+                modify_relay(&self.relay.url, |relay| {
+                    relay.trust = trust;
+                });
+            }
+
+            ui.add_space(3.0);
+            let mut mute_content = self.relay.mute_content;
+            let response = ui
+                .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                    let response = ui.checkbox(&mut mute_content, "");
+                    ui.add(egui::Label::new("Mute content:")).on_hover_text(
+                        "Hide events that were seen only on this relay. Events also seen on another relay still show, and the relay is still used.",
+                    );
+                    response
+                })
+                .inner;
+            if response.changed() {
+                modify_relay(&self.relay.url, |relay| {
+                    relay.mute_content = mute_content;
+                });
+            }
         });
     }
 
@@ -1433,6 +1625,53 @@ impl RelayEntry {
     }
 }
 
+fn ingest_summary(relay: &Relay) -> String {
+    fn describe(counts: IngestCounts) -> String {
+        format!(
+            "{} events ({} new, {} duplicate{}), {}",
+            counts.events_received,
+            counts.events_new,
+            counts.events_duplicate(),
+            match counts.unique_rate() {
+                Some(rate) => format!(", {:.0}% unique", rate * 100.0),
+                None => "".to_string(),
+            },
+            format_size(counts.bytes_received, DECIMAL)
+        )
+    }
+
+    format!(
+        "Last {} minutes: {}\nAll time: {}",
+        relay_metrics::INGEST_WINDOW_MINUTES,
+        describe(relay_metrics::recent(&relay.url)),
+        describe(relay_metrics::totals(relay))
+    )
+}
+
+fn subscriptions_summary(relay: &Relay) -> String {
+    let subs = relay_metrics::active_subscriptions(&relay.url);
+    let summary = if subs.is_empty() {
+        "No active subscriptions".to_string()
+    } else {
+        let list: Vec<String> = subs.iter().map(|s| s.to_string()).collect();
+        format!("Subscriptions: {}", list.join(", "))
+    };
+
+    let usage = relay_metrics::filter_usage(relay);
+    if usage.over_limit() {
+        format!(
+            "{}\nUsing {}: the relay is likely dropping some, so feeds may be incomplete",
+            summary, usage
+        )
+    } else if usage.near_limit() {
+        format!("{}\nUsing {}: nearing the relay's limit", summary, usage)
+    } else if usage.limit().is_some() {
+        format!("{}\nUsing {}", summary, usage)
+    } else {
+        summary
+    }
+}
+
 fn modify_relay<M>(relay_url: &RelayUrl, mut modify: M)
 where
     M: FnMut(&mut Relay),
@@ -1480,3 +1719,117 @@ fn permission_combo(
     })
     .inner
 }
+
+fn max_backfill_age_text(age: Option<u64>) -> String {
+    match age {
+        None => "Unlimited".to_owned(),
+        Some(secs) if secs % (60 * 60 * 24) == 0 => format!("{} days", secs / (60 * 60 * 24)),
+        Some(secs) => format!("{} hours", secs / (60 * 60)),
+    }
+}
+
+fn trust_text(trust: u64) -> String {
+    match trust.cmp(&Relay::DEFAULT_TRUST) {
+        std::cmp::Ordering::Less => format!("{} (distrusted)", trust),
+        std::cmp::Ordering::Equal => format!("{} (ordinary)", trust),
+        std::cmp::Ordering::Greater => format!("{} (trusted)", trust),
+    }
+}
+
+fn trust_combo(ui: &mut Ui, trust: &mut u64, title: impl Into<WidgetText>) -> Option<Response> {
+    ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+        let combo = egui::ComboBox::from_id_salt(ui.next_auto_id());
+        let response = combo
+            .width(100.0)
+            .selected_text(trust_text(*trust))
+            .show_ui(ui, |ui| {
+                (0..=9)
+                    .map(|choice| ui.selectable_value(trust, choice, trust_text(choice)))
+                    .reduce(|a, b| a | b)
+            })
+            .inner
+            .flatten();
+
+        ui.add(egui::Label::new(title)).on_hover_text(
+            "How far you trust what this relay serves, separate from its relay-picker rank. Reports seen on a trusted relay count even when you don't follow who made them. Posts from people you don't follow that were seen only on distrusted relays are left out of the global feed and your inbox.",
+        );
+        response
+    })
+    .inner
+}
+
+// A YYYY-MM-DD field for the relay's `skip_events_before`. Empty means unset.
+fn skip_events_before_edit(ui: &mut Ui, relay: &Relay) {
+    let id = egui::Id::new(("skip_events_before", relay.url.as_str()));
+    let current = relay
+        .skip_events_before
+        .and_then(|t| chrono::DateTime::from_timestamp(t.0, 0))
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let mut text: String = ui.data_mut(|d| d.get_temp(id)).unwrap_or(current.clone());
+
+    ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut text)
+                .hint_text("YYYY-MM-DD")
+                .desired_width(80.0),
+        );
+        ui.add(egui::Label::new("Skip events before:")).on_hover_text(
+            "Drop events from this relay that were created before this date, for relays that serve an archive you don't want. Profiles, relay lists and other replaceable events are still kept. Leave empty for no cutoff.",
+        );
+
+        if response.lost_focus() && text != current {
+            let trimmed = text.trim();
+            let cutoff = if trimmed.is_empty() {
+                Some(None)
+            } else {
+                chrono::NaiveDate::parse_from_str(trimmed, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+                    .map(|t| Some(Unixtime(t.and_utc().timestamp())))
+            };
+            match cutoff {
+                Some(cutoff) => {
+                    modify_relay(&relay.url, |relay| {
+                        relay.skip_events_before = cutoff;
+                    });
+                }
+                None => {
+                    GLOBALS
+                        .status_queue
+                        .write()
+                        .write(format!("Not a date: {}. Use YYYY-MM-DD.", trimmed));
+                    text = current.clone();
+                }
+            }
+        }
+    });
+
+    ui.data_mut(|d| d.insert_temp(id, text));
+}
+
+fn max_backfill_age_combo(
+    ui: &mut Ui,
+    age: &mut Option<u64>,
+    title: impl Into<WidgetText>,
+) -> Option<Response> {
+    ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+        let combo = egui::ComboBox::from_id_salt(ui.next_auto_id());
+        let response = combo
+            .width(70.0)
+            .selected_text(max_backfill_age_text(*age))
+            .show_ui(ui, |ui| {
+                MAX_BACKFILL_AGE_CHOICES
+                    .iter()
+                    .map(|choice| ui.selectable_value(age, *choice, max_backfill_age_text(*choice)))
+                    .reduce(|a, b| a | b)
+            })
+            .inner
+            .flatten();
+
+        ui.add(egui::Label::new(title))
+            .on_hover_text("When reconnecting, do not ask this relay for events older than this");
+        response
+    })
+    .inner
+}
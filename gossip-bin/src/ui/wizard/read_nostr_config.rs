@@ -140,7 +140,9 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
             ui.scope(|ui| {
                 app.theme.secondary_button_style(ui.style_mut());
                 if ui.button("Fetch From This Relay").clicked() {
-                    if let Ok(rurl) = RelayUrl::try_from_str(&app.wizard_state.relay_url) {
+                    if let Ok(rurl) =
+                        gossip_lib::relay::parse_relay_url(&app.wizard_state.relay_url)
+                    {
                         let _ = GLOBALS
                             .to_overlord
                             .send(ToOverlordMessage::SubscribeConfig(Some(vec![
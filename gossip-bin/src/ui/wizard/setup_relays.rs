@@ -200,7 +200,7 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
 
         ui.horizontal(|ui| {
             if ui.button("  ^  Add to Outbox").clicked() {
-                if let Ok(rurl) = RelayUrl::try_from_str(&app.wizard_state.relay_url) {
+                if let Ok(rurl) = gossip_lib::relay::parse_relay_url(&app.wizard_state.relay_url) {
                     if !relay_options.contains_key(&rurl) {
                         relay_options.insert(rurl.clone(), read_relay(&rurl));
                     }
@@ -213,7 +213,7 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
             }
 
             if ui.button("  ^  Add to Inbox").clicked() {
-                if let Ok(rurl) = RelayUrl::try_from_str(&app.wizard_state.relay_url) {
+                if let Ok(rurl) = gossip_lib::relay::parse_relay_url(&app.wizard_state.relay_url) {
                     if !relay_options.contains_key(&rurl) {
                         relay_options.insert(rurl.clone(), read_relay(&rurl));
                     }
@@ -226,7 +226,7 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
             }
 
             if ui.button("  ^  Add to Discovery").clicked() {
-                if let Ok(rurl) = RelayUrl::try_from_str(&app.wizard_state.relay_url) {
+                if let Ok(rurl) = gossip_lib::relay::parse_relay_url(&app.wizard_state.relay_url) {
                     if !relay_options.contains_key(&rurl) {
                         relay_options.insert(rurl.clone(), read_relay(&rurl));
                     }
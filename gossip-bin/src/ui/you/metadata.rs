@@ -1,7 +1,8 @@
 use super::{GossipUi, Page};
 use eframe::egui;
-use egui::{Align, Color32, Context, Layout, RichText, TextEdit, Ui};
+use egui::{Align, Color32, Context, DragValue, Layout, RichText, TextEdit, Ui};
 use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::nip24::{self, Birthday};
 use gossip_lib::{PersonTable, Relay, Table, GLOBALS};
 use lazy_static::lazy_static;
 use nostr_types::Metadata;
@@ -70,6 +71,22 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
                 ui.add_space(18.0);
                 edit_line(ui, "NIP-05", &mut app.metadata.nip05, edit_color);
                 ui.add_space(18.0);
+                edit_nip24_line(
+                    ui,
+                    "Display Name",
+                    nip24::DISPLAY_NAME,
+                    &mut app.metadata,
+                    edit_color,
+                );
+                ui.add_space(18.0);
+                edit_nip24_line(ui, "Website", nip24::WEBSITE, &mut app.metadata, edit_color);
+                ui.add_space(18.0);
+                edit_nip24_line(ui, "Banner", nip24::BANNER, &mut app.metadata, edit_color);
+                ui.add_space(18.0);
+                edit_bot(ui, &mut app.metadata);
+                ui.add_space(18.0);
+                edit_birthday(ui, &mut app.metadata);
+                ui.add_space(18.0);
                 edit_lines_other(ui, &mut app.metadata.other, edit_color);
                 ui.add_space(18.0);
             } else {
@@ -81,6 +98,16 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
                 ui.add_space(18.0);
                 view_line(ui, "NIP-05", view_metadata.nip05.as_ref());
                 ui.add_space(18.0);
+                view_nip24_line(ui, "Display Name", nip24::DISPLAY_NAME, view_metadata);
+                ui.add_space(18.0);
+                view_nip24_line(ui, "Website", nip24::WEBSITE, view_metadata);
+                ui.add_space(18.0);
+                view_nip24_line(ui, "Banner", nip24::BANNER, view_metadata);
+                ui.add_space(18.0);
+                view_bot(ui, view_metadata);
+                ui.add_space(18.0);
+                view_birthday(ui, view_metadata);
+                ui.add_space(18.0);
                 view_lines_other(ui, &view_metadata.other);
                 ui.add_space(18.0);
             }
@@ -93,10 +120,17 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
                             text_edit_line!(app, app.new_metadata_fieldname).desired_width(120.0),
                         );
                         if ui.button("ADD").clicked() {
-                            app.metadata.other.insert(
-                                app.new_metadata_fieldname.clone(),
-                                Value::String("".to_owned()),
-                            );
+                            // Don't clobber an existing field, and leave NIP-24 fields
+                            // to their own editors above
+                            let fieldname = app.new_metadata_fieldname.trim().to_owned();
+                            if !fieldname.is_empty()
+                                && !nip24::FIELDS.contains(&fieldname.as_str())
+                                && !app.metadata.other.contains_key(&fieldname)
+                            {
+                                app.metadata
+                                    .other
+                                    .insert(fieldname, Value::String("".to_owned()));
+                            }
                             app.new_metadata_fieldname = "".to_owned();
                         }
                     });
@@ -195,6 +229,9 @@ fn edit_line(ui: &mut Ui, field: &str, data: &mut Option<String>, edit_color: Co
 
 fn view_lines_other(ui: &mut Ui, other: &Map<String, Value>) {
     for (field, jsonvalue) in other.iter() {
+        if nip24::FIELDS.contains(&field.as_str()) {
+            continue;
+        }
         ui.horizontal(|ui| {
             ui.label(format!("{}: ", field));
             if let Value::String(s) = jsonvalue {
@@ -212,20 +249,27 @@ fn view_lines_other(ui: &mut Ui, other: &Map<String, Value>) {
 fn edit_lines_other(ui: &mut Ui, other: &mut Map<String, Value>, edit_color: Color32) {
     let mut to_remove: Vec<String> = Vec::new();
     for (field, jsonvalue) in other.iter_mut() {
+        if nip24::FIELDS.contains(&field.as_str()) {
+            continue;
+        }
         ui.horizontal(|ui| {
             ui.label(format!("{}: ", field));
-            if let Value::String(s) = jsonvalue {
-                ui.with_layout(Layout::right_to_left(Align::TOP), |ui| {
-                    if ui.button("Remove").clicked() {
-                        to_remove.push(field.to_owned());
-                    }
+            ui.with_layout(Layout::right_to_left(Align::TOP), |ui| {
+                if ui.button("Remove").clicked() {
+                    to_remove.push(field.to_owned());
+                }
+                if let Value::String(s) = jsonvalue {
                     ui.add(
                         TextEdit::singleline(s)
                             .text_color(edit_color)
                             .desired_width(f32::INFINITY),
                     );
-                });
-            }
+                } else if let Ok(s) = serde_json::to_string(&jsonvalue) {
+                    // Not editable here, but kept as-is when saved
+                    ui.label(s)
+                        .on_hover_text("This field is not a string. It will be kept as-is.");
+                }
+            });
         });
         ui.add_space(18.0);
     }
@@ -233,3 +277,94 @@ fn edit_lines_other(ui: &mut Ui, other: &mut Map<String, Value>, edit_color: Col
         other.remove(rem);
     }
 }
+
+fn view_nip24_line(ui: &mut Ui, label: &str, field: &str, metadata: &Metadata) {
+    let data = nip24::get_string(metadata, field).map(|s| s.to_owned());
+    view_line(ui, label, data.as_ref());
+}
+
+fn edit_nip24_line(
+    ui: &mut Ui,
+    label: &str,
+    field: &str,
+    metadata: &mut Metadata,
+    edit_color: Color32,
+) {
+    let mut data = nip24::get_string(metadata, field).map(|s| s.to_owned());
+    let before = data.clone();
+    edit_line(ui, label, &mut data, edit_color);
+    if data != before {
+        nip24::set_string(metadata, field, data);
+    }
+}
+
+fn view_bot(ui: &mut Ui, metadata: &Metadata) {
+    ui.horizontal(|ui| {
+        ui.label("Bot: ");
+        ui.label(if nip24::bot(metadata) { "yes" } else { "no" });
+    });
+}
+
+fn edit_bot(ui: &mut Ui, metadata: &mut Metadata) {
+    ui.horizontal(|ui| {
+        ui.label("Bot: ");
+        let mut bot = nip24::bot(metadata);
+        if ui.checkbox(&mut bot, "This account is automated").changed() {
+            nip24::set_bot(metadata, bot);
+        }
+    });
+}
+
+fn format_birthday(birthday: &Birthday) -> String {
+    let part = |n: Option<u64>| n.map(|n| n.to_string()).unwrap_or("?".to_owned());
+    format!(
+        "{}-{}-{}",
+        part(birthday.year.map(|n| n as u64)),
+        part(birthday.month.map(|n| n as u64)),
+        part(birthday.day.map(|n| n as u64))
+    )
+}
+
+fn view_birthday(ui: &mut Ui, metadata: &Metadata) {
+    ui.horizontal(|ui| {
+        ui.label("Birthday: ");
+        match nip24::birthday(metadata) {
+            Some(birthday) => ui.label(format_birthday(&birthday)),
+            None => ui.label(RichText::new("none").italics().weak()),
+        };
+    });
+}
+
+fn edit_birthday(ui: &mut Ui, metadata: &mut Metadata) {
+    ui.horizontal(|ui| {
+        ui.label("Birthday: ");
+        match nip24::birthday(metadata) {
+            Some(mut birthday) => {
+                // Zero means that part is left out
+                let mut year = birthday.year.unwrap_or(0);
+                let mut month = birthday.month.unwrap_or(0);
+                let mut day = birthday.day.unwrap_or(0);
+                let mut changed = false;
+                ui.label("year");
+                changed |= ui.add(DragValue::new(&mut year).range(0..=9999)).changed();
+                ui.label("month");
+                changed |= ui.add(DragValue::new(&mut month).range(0..=12)).changed();
+                ui.label("day");
+                changed |= ui.add(DragValue::new(&mut day).range(0..=31)).changed();
+                if ui.button("Remove").clicked() {
+                    nip24::set_birthday(metadata, None);
+                } else if changed {
+                    birthday.year = Some(year).filter(|n| *n != 0);
+                    birthday.month = Some(month).filter(|n| *n != 0);
+                    birthday.day = Some(day).filter(|n| *n != 0);
+                    nip24::set_birthday(metadata, Some(birthday));
+                }
+            }
+            None => {
+                if ui.button("Add").clicked() {
+                    nip24::set_birthday(metadata, Some(Birthday::default()));
+                }
+            }
+        }
+    });
+}
@@ -23,6 +23,13 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         app.vert_scroll_area().id_salt("your_keys").show(ui, |ui| {
             if GLOBALS.identity.is_unlocked() {
                 ui.heading("Ready to sign events");
+                if ui
+                    .button("Lock Private Key")
+                    .on_hover_text("Forget the decrypted key. You will need your passphrase to post, react, zap or send DMs again.")
+                    .clicked()
+                {
+                    let _ = gossip_lib::Overlord::lock_key();
+                }
 
                 ui.add_space(10.0);
                 ui.separator();
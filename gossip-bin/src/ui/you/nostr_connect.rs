@@ -118,9 +118,9 @@ fn setup_unconnected_service(app: &mut GossipUi, ui: &mut Ui) {
     });
 
     if !app.nostr_connect_name.is_empty() && !app.nostr_connect_relay1.is_empty() {
-        if let Ok(relay1) = RelayUrl::try_from_str(&app.nostr_connect_relay1) {
+        if let Ok(relay1) = gossip_lib::relay::parse_relay_url(&app.nostr_connect_relay1) {
             if !app.nostr_connect_relay2.is_empty() {
-                if let Ok(relay2) = RelayUrl::try_from_str(&app.nostr_connect_relay2) {
+                if let Ok(relay2) = gossip_lib::relay::parse_relay_url(&app.nostr_connect_relay2) {
                     if ui.button("Create Service").clicked() {
                         create_service(app.nostr_connect_name.clone(), vec![relay1, relay2]);
                         app.nostr_connect_name = "".to_string();
@@ -35,20 +35,29 @@ pub struct UnsavedSettings {
     // ID settings
     pub log_n: u8,
     pub login_at_startup: bool,
+    pub auto_lock_minutes: u32,
 
     // Network settings
     pub offline: bool,
+    pub quiet_hours: bool,
+    pub quiet_hours_start: u8,
+    pub quiet_hours_end: u8,
     pub load_avatars: bool,
     pub load_media: bool,
+    pub prefetch_profile_media: bool,
+    pub metered_connection: bool,
     pub check_nip05: bool,
     pub automatically_fetch_metadata: bool,
     pub relay_connection_requires_approval: bool,
     pub relay_auth_requires_approval: bool,
+    pub auto_advertise_on_change: bool,
 
     // Relay settings
-    pub num_relays_per_person: u8,
+    pub feed_relays_per_person: u8,
+    pub metadata_relays_per_person: u8,
     pub max_relays: u8,
     pub num_relays_for_counting: u8,
+    pub num_relays_for_hashtags: u8,
 
     // Feed Settings
     pub load_more_count: u64,
@@ -56,11 +65,13 @@ pub struct UnsavedSettings {
     // Event Selection
     pub reposts: bool,
     pub show_long_form: bool,
+    pub include_replies: bool,
     pub show_mentions: bool,
     pub enable_picture_events: bool,
     pub enable_comments: bool,
     pub direct_messages: bool,
     pub future_allowance_secs: u64,
+    pub future_event_action: String,
 
     // Event Content Settings
     pub hide_mutes_entirely: bool,
@@ -69,8 +80,10 @@ pub struct UnsavedSettings {
     pub show_media: bool,
     pub approve_content_warning: bool,
     pub show_deleted_events: bool,
+    pub retain_deleted_events: bool,
     pub avoid_spam_on_unsafe_relays: bool,
     pub limit_inbox_seeking_to_inbox_relays: bool,
+    pub fetch_inbox_on_feed_relays: bool,
     pub apply_spam_filter_on_incoming_events: bool,
     pub apply_spam_filter_on_threads: bool,
     pub apply_spam_filter_on_inbox: bool,
@@ -78,7 +91,10 @@ pub struct UnsavedSettings {
 
     // Posting Settings
     pub pow: u8,
+    pub max_reply_p_tags: usize,
+    pub post_retry_attempts: u32,
     pub set_client_tag: bool,
+    pub strip_url_tracking: bool,
     pub set_user_agent: bool,
     pub delegatee_tag: String,
 
@@ -87,6 +103,7 @@ pub struct UnsavedSettings {
     pub recompute_feed_periodically: bool,
     pub feed_recompute_interval_ms: u32,
     pub feed_thread_scroll_to_main_event: bool,
+    pub feed_ordering: String,
     pub theme_variant: String,
     pub dark_mode: bool,
     pub follow_os_dark_mode: bool,
@@ -110,6 +127,7 @@ pub struct UnsavedSettings {
     pub nip05_becomes_stale_if_invalid_minutes: u64,
     pub avatar_becomes_stale_hours: u64,
     pub media_becomes_stale_hours: u64,
+    pub relay_info_becomes_stale_hours: u64,
 
     // Websocket settings
     pub max_websocket_message_size_kb: usize,
@@ -117,11 +135,14 @@ pub struct UnsavedSettings {
     pub websocket_accept_unmasked_frames: bool,
     pub websocket_connect_timeout_sec: u64,
     pub websocket_ping_frequency_sec: u64,
+    pub restore_manual_relays: bool,
 
     // HTTP settings
     pub fetcher_connect_timeout_sec: u64,
     pub fetcher_timeout_sec: u64,
     pub fetcher_max_requests_per_host: usize,
+    pub fetcher_max_requests: usize,
+    pub fetcher_max_retries: u64,
     pub fetcher_host_exclusion_on_low_error_secs: u64,
     pub fetcher_host_exclusion_on_med_error_secs: u64,
     pub fetcher_host_exclusion_on_high_error_secs: u64,
@@ -129,6 +150,13 @@ pub struct UnsavedSettings {
     // Database settings
     pub prune_period_days: u64,
     pub cache_prune_period_days: u64,
+    pub auto_prune: bool,
+    pub auto_prune_interval_days: u64,
+    pub auto_prune_min_size_mb: u64,
+    pub dm_cache_size: usize,
+    pub reaction_summary_threshold: u64,
+    pub prune_summarized_reactions: bool,
+    pub stored_kinds: String,
 
     pub blossom_servers: String,
 
@@ -140,36 +168,49 @@ impl Default for UnsavedSettings {
         UnsavedSettings {
             log_n: default_setting!(log_n),
             login_at_startup: default_setting!(login_at_startup),
+            auto_lock_minutes: default_setting!(auto_lock_minutes),
             offline: default_setting!(offline),
+            quiet_hours: default_setting!(quiet_hours),
+            quiet_hours_start: default_setting!(quiet_hours_start),
+            quiet_hours_end: default_setting!(quiet_hours_end),
             load_avatars: default_setting!(load_avatars),
             load_media: default_setting!(load_media),
+            prefetch_profile_media: default_setting!(prefetch_profile_media),
+            metered_connection: default_setting!(metered_connection),
             check_nip05: default_setting!(check_nip05),
             automatically_fetch_metadata: default_setting!(automatically_fetch_metadata),
             relay_connection_requires_approval: default_setting!(
                 relay_connection_requires_approval
             ),
             relay_auth_requires_approval: default_setting!(relay_auth_requires_approval),
-            num_relays_per_person: default_setting!(num_relays_per_person),
+            auto_advertise_on_change: default_setting!(auto_advertise_on_change),
+            feed_relays_per_person: default_setting!(feed_relays_per_person),
+            metadata_relays_per_person: default_setting!(metadata_relays_per_person),
             max_relays: default_setting!(max_relays),
             num_relays_for_counting: default_setting!(num_relays_for_counting),
+            num_relays_for_hashtags: default_setting!(num_relays_for_hashtags),
             load_more_count: default_setting!(load_more_count),
             reposts: default_setting!(reposts),
             show_long_form: default_setting!(show_long_form),
+            include_replies: default_setting!(include_replies),
             show_mentions: default_setting!(show_mentions),
             enable_picture_events: default_setting!(enable_picture_events),
             enable_comments: default_setting!(enable_comments),
             direct_messages: default_setting!(direct_messages),
             future_allowance_secs: default_setting!(future_allowance_secs),
+            future_event_action: default_setting!(future_event_action),
             hide_mutes_entirely: default_setting!(hide_mutes_entirely),
             reactions: default_setting!(reactions),
             enable_zap_receipts: default_setting!(enable_zap_receipts),
             show_media: default_setting!(show_media),
             approve_content_warning: default_setting!(approve_content_warning),
             show_deleted_events: default_setting!(show_deleted_events),
+            retain_deleted_events: default_setting!(retain_deleted_events),
             avoid_spam_on_unsafe_relays: default_setting!(avoid_spam_on_unsafe_relays),
             limit_inbox_seeking_to_inbox_relays: default_setting!(
                 limit_inbox_seeking_to_inbox_relays
             ),
+            fetch_inbox_on_feed_relays: default_setting!(fetch_inbox_on_feed_relays),
             apply_spam_filter_on_incoming_events: default_setting!(
                 apply_spam_filter_on_incoming_events
             ),
@@ -177,13 +218,17 @@ impl Default for UnsavedSettings {
             apply_spam_filter_on_inbox: default_setting!(apply_spam_filter_on_inbox),
             apply_spam_filter_on_global: default_setting!(apply_spam_filter_on_global),
             pow: default_setting!(pow),
+            max_reply_p_tags: default_setting!(max_reply_p_tags),
+            post_retry_attempts: default_setting!(post_retry_attempts),
             set_client_tag: default_setting!(set_client_tag),
+            strip_url_tracking: default_setting!(strip_url_tracking),
             set_user_agent: default_setting!(set_user_agent),
             delegatee_tag: default_setting!(delegatee_tag),
             max_fps: default_setting!(max_fps),
             recompute_feed_periodically: default_setting!(recompute_feed_periodically),
             feed_recompute_interval_ms: default_setting!(feed_recompute_interval_ms),
             feed_thread_scroll_to_main_event: default_setting!(feed_thread_scroll_to_main_event),
+            feed_ordering: default_setting!(feed_ordering),
             theme_variant: default_setting!(theme_variant),
             dark_mode: default_setting!(dark_mode),
             follow_os_dark_mode: default_setting!(follow_os_dark_mode),
@@ -209,14 +254,18 @@ impl Default for UnsavedSettings {
             ),
             avatar_becomes_stale_hours: default_setting!(avatar_becomes_stale_hours),
             media_becomes_stale_hours: default_setting!(media_becomes_stale_hours),
+            relay_info_becomes_stale_hours: default_setting!(relay_info_becomes_stale_hours),
             max_websocket_message_size_kb: default_setting!(max_websocket_message_size_kb),
             max_websocket_frame_size_kb: default_setting!(max_websocket_frame_size_kb),
             websocket_accept_unmasked_frames: default_setting!(websocket_accept_unmasked_frames),
             websocket_connect_timeout_sec: default_setting!(websocket_connect_timeout_sec),
             websocket_ping_frequency_sec: default_setting!(websocket_ping_frequency_sec),
+            restore_manual_relays: default_setting!(restore_manual_relays),
             fetcher_connect_timeout_sec: default_setting!(fetcher_connect_timeout_sec),
             fetcher_timeout_sec: default_setting!(fetcher_timeout_sec),
             fetcher_max_requests_per_host: default_setting!(fetcher_max_requests_per_host),
+            fetcher_max_requests: default_setting!(fetcher_max_requests),
+            fetcher_max_retries: default_setting!(fetcher_max_retries),
             fetcher_host_exclusion_on_low_error_secs: default_setting!(
                 fetcher_host_exclusion_on_low_error_secs
             ),
@@ -228,6 +277,13 @@ impl Default for UnsavedSettings {
             ),
             prune_period_days: default_setting!(prune_period_days),
             cache_prune_period_days: default_setting!(prune_period_days),
+            auto_prune: default_setting!(auto_prune),
+            auto_prune_interval_days: default_setting!(auto_prune_interval_days),
+            auto_prune_min_size_mb: default_setting!(auto_prune_min_size_mb),
+            dm_cache_size: default_setting!(dm_cache_size),
+            reaction_summary_threshold: default_setting!(reaction_summary_threshold),
+            prune_summarized_reactions: default_setting!(prune_summarized_reactions),
+            stored_kinds: default_setting!(stored_kinds),
             blossom_servers: default_setting!(blossom_servers),
             undo_send_seconds: default_setting!(undo_send_seconds),
         }
@@ -239,32 +295,45 @@ impl UnsavedSettings {
         UnsavedSettings {
             log_n: load_setting!(log_n),
             login_at_startup: load_setting!(login_at_startup),
+            auto_lock_minutes: load_setting!(auto_lock_minutes),
             offline: load_setting!(offline),
+            quiet_hours: load_setting!(quiet_hours),
+            quiet_hours_start: load_setting!(quiet_hours_start),
+            quiet_hours_end: load_setting!(quiet_hours_end),
             load_avatars: load_setting!(load_avatars),
             load_media: load_setting!(load_media),
+            prefetch_profile_media: load_setting!(prefetch_profile_media),
+            metered_connection: load_setting!(metered_connection),
             check_nip05: load_setting!(check_nip05),
             automatically_fetch_metadata: load_setting!(automatically_fetch_metadata),
             relay_connection_requires_approval: load_setting!(relay_connection_requires_approval),
             relay_auth_requires_approval: load_setting!(relay_auth_requires_approval),
-            num_relays_per_person: load_setting!(num_relays_per_person),
+            auto_advertise_on_change: load_setting!(auto_advertise_on_change),
+            feed_relays_per_person: load_setting!(feed_relays_per_person),
+            metadata_relays_per_person: load_setting!(metadata_relays_per_person),
             max_relays: load_setting!(max_relays),
             num_relays_for_counting: load_setting!(num_relays_for_counting),
+            num_relays_for_hashtags: load_setting!(num_relays_for_hashtags),
             load_more_count: load_setting!(load_more_count),
             reposts: load_setting!(reposts),
             show_long_form: load_setting!(show_long_form),
+            include_replies: load_setting!(include_replies),
             show_mentions: load_setting!(show_mentions),
             enable_picture_events: load_setting!(enable_picture_events),
             enable_comments: load_setting!(enable_comments),
             direct_messages: load_setting!(direct_messages),
             future_allowance_secs: load_setting!(future_allowance_secs),
+            future_event_action: load_setting!(future_event_action),
             hide_mutes_entirely: load_setting!(hide_mutes_entirely),
             reactions: load_setting!(reactions),
             enable_zap_receipts: load_setting!(enable_zap_receipts),
             show_media: load_setting!(show_media),
             approve_content_warning: load_setting!(approve_content_warning),
             show_deleted_events: load_setting!(show_deleted_events),
+            retain_deleted_events: load_setting!(retain_deleted_events),
             avoid_spam_on_unsafe_relays: load_setting!(avoid_spam_on_unsafe_relays),
             limit_inbox_seeking_to_inbox_relays: load_setting!(limit_inbox_seeking_to_inbox_relays),
+            fetch_inbox_on_feed_relays: load_setting!(fetch_inbox_on_feed_relays),
             apply_spam_filter_on_incoming_events: load_setting!(
                 apply_spam_filter_on_incoming_events
             ),
@@ -272,13 +341,17 @@ impl UnsavedSettings {
             apply_spam_filter_on_inbox: load_setting!(apply_spam_filter_on_inbox),
             apply_spam_filter_on_global: load_setting!(apply_spam_filter_on_global),
             pow: load_setting!(pow),
+            max_reply_p_tags: load_setting!(max_reply_p_tags),
+            post_retry_attempts: load_setting!(post_retry_attempts),
             set_client_tag: load_setting!(set_client_tag),
+            strip_url_tracking: load_setting!(strip_url_tracking),
             set_user_agent: load_setting!(set_user_agent),
             delegatee_tag: load_setting!(delegatee_tag),
             max_fps: load_setting!(max_fps),
             recompute_feed_periodically: load_setting!(recompute_feed_periodically),
             feed_recompute_interval_ms: load_setting!(feed_recompute_interval_ms),
             feed_thread_scroll_to_main_event: load_setting!(feed_thread_scroll_to_main_event),
+            feed_ordering: load_setting!(feed_ordering),
             theme_variant: load_setting!(theme_variant),
             dark_mode: load_setting!(dark_mode),
             follow_os_dark_mode: load_setting!(follow_os_dark_mode),
@@ -302,14 +375,18 @@ impl UnsavedSettings {
             ),
             avatar_becomes_stale_hours: load_setting!(avatar_becomes_stale_hours),
             media_becomes_stale_hours: load_setting!(media_becomes_stale_hours),
+            relay_info_becomes_stale_hours: load_setting!(relay_info_becomes_stale_hours),
             max_websocket_message_size_kb: load_setting!(max_websocket_message_size_kb),
             max_websocket_frame_size_kb: load_setting!(max_websocket_frame_size_kb),
             websocket_accept_unmasked_frames: load_setting!(websocket_accept_unmasked_frames),
             websocket_connect_timeout_sec: load_setting!(websocket_connect_timeout_sec),
             websocket_ping_frequency_sec: load_setting!(websocket_ping_frequency_sec),
+            restore_manual_relays: load_setting!(restore_manual_relays),
             fetcher_connect_timeout_sec: load_setting!(fetcher_connect_timeout_sec),
             fetcher_timeout_sec: load_setting!(fetcher_timeout_sec),
             fetcher_max_requests_per_host: load_setting!(fetcher_max_requests_per_host),
+            fetcher_max_requests: load_setting!(fetcher_max_requests),
+            fetcher_max_retries: load_setting!(fetcher_max_retries),
             fetcher_host_exclusion_on_low_error_secs: load_setting!(
                 fetcher_host_exclusion_on_low_error_secs
             ),
@@ -321,6 +398,13 @@ impl UnsavedSettings {
             ),
             prune_period_days: load_setting!(prune_period_days),
             cache_prune_period_days: load_setting!(cache_prune_period_days),
+            auto_prune: load_setting!(auto_prune),
+            auto_prune_interval_days: load_setting!(auto_prune_interval_days),
+            auto_prune_min_size_mb: load_setting!(auto_prune_min_size_mb),
+            dm_cache_size: load_setting!(dm_cache_size),
+            reaction_summary_threshold: load_setting!(reaction_summary_threshold),
+            prune_summarized_reactions: load_setting!(prune_summarized_reactions),
+            stored_kinds: load_setting!(stored_kinds),
             blossom_servers: load_setting!(blossom_servers),
             undo_send_seconds: load_setting!(undo_send_seconds),
         }
@@ -330,44 +414,61 @@ impl UnsavedSettings {
         let mut txn = GLOBALS.db().get_write_txn()?;
         save_setting!(log_n, self, txn);
         save_setting!(login_at_startup, self, txn);
+        save_setting!(auto_lock_minutes, self, txn);
         save_setting!(offline, self, txn);
+        save_setting!(quiet_hours, self, txn);
+        save_setting!(quiet_hours_start, self, txn);
+        save_setting!(quiet_hours_end, self, txn);
         save_setting!(load_avatars, self, txn);
         save_setting!(load_media, self, txn);
+        save_setting!(prefetch_profile_media, self, txn);
+        save_setting!(metered_connection, self, txn);
         save_setting!(check_nip05, self, txn);
         save_setting!(automatically_fetch_metadata, self, txn);
         save_setting!(relay_connection_requires_approval, self, txn);
         save_setting!(relay_auth_requires_approval, self, txn);
-        save_setting!(num_relays_per_person, self, txn);
+        save_setting!(auto_advertise_on_change, self, txn);
+        save_setting!(feed_relays_per_person, self, txn);
+        save_setting!(metadata_relays_per_person, self, txn);
         save_setting!(max_relays, self, txn);
         save_setting!(num_relays_for_counting, self, txn);
+        save_setting!(num_relays_for_hashtags, self, txn);
         save_setting!(load_more_count, self, txn);
         save_setting!(reposts, self, txn);
         save_setting!(show_long_form, self, txn);
+        save_setting!(include_replies, self, txn);
         save_setting!(show_mentions, self, txn);
         save_setting!(enable_picture_events, self, txn);
         save_setting!(enable_comments, self, txn);
         save_setting!(direct_messages, self, txn);
         save_setting!(future_allowance_secs, self, txn);
+        save_setting!(future_event_action, self, txn);
         save_setting!(hide_mutes_entirely, self, txn);
         save_setting!(reactions, self, txn);
         save_setting!(enable_zap_receipts, self, txn);
         save_setting!(show_media, self, txn);
         save_setting!(approve_content_warning, self, txn);
         save_setting!(show_deleted_events, self, txn);
+        save_setting!(retain_deleted_events, self, txn);
         save_setting!(avoid_spam_on_unsafe_relays, self, txn);
         save_setting!(limit_inbox_seeking_to_inbox_relays, self, txn);
+        save_setting!(fetch_inbox_on_feed_relays, self, txn);
         save_setting!(apply_spam_filter_on_incoming_events, self, txn);
         save_setting!(apply_spam_filter_on_threads, self, txn);
         save_setting!(apply_spam_filter_on_inbox, self, txn);
         save_setting!(apply_spam_filter_on_global, self, txn);
         save_setting!(pow, self, txn);
+        save_setting!(max_reply_p_tags, self, txn);
+        save_setting!(post_retry_attempts, self, txn);
         save_setting!(set_client_tag, self, txn);
+        save_setting!(strip_url_tracking, self, txn);
         save_setting!(set_user_agent, self, txn);
         save_setting!(delegatee_tag, self, txn);
         save_setting!(max_fps, self, txn);
         save_setting!(recompute_feed_periodically, self, txn);
         save_setting!(feed_recompute_interval_ms, self, txn);
         save_setting!(feed_thread_scroll_to_main_event, self, txn);
+        save_setting!(feed_ordering, self, txn);
         save_setting!(theme_variant, self, txn);
         save_setting!(dark_mode, self, txn);
         save_setting!(follow_os_dark_mode, self, txn);
@@ -389,19 +490,30 @@ impl UnsavedSettings {
         save_setting!(nip05_becomes_stale_if_invalid_minutes, self, txn);
         save_setting!(avatar_becomes_stale_hours, self, txn);
         save_setting!(media_becomes_stale_hours, self, txn);
+        save_setting!(relay_info_becomes_stale_hours, self, txn);
         save_setting!(max_websocket_message_size_kb, self, txn);
         save_setting!(max_websocket_frame_size_kb, self, txn);
         save_setting!(websocket_accept_unmasked_frames, self, txn);
         save_setting!(websocket_connect_timeout_sec, self, txn);
         save_setting!(websocket_ping_frequency_sec, self, txn);
+        save_setting!(restore_manual_relays, self, txn);
         save_setting!(fetcher_connect_timeout_sec, self, txn);
         save_setting!(fetcher_timeout_sec, self, txn);
         save_setting!(fetcher_max_requests_per_host, self, txn);
+        save_setting!(fetcher_max_requests, self, txn);
+        save_setting!(fetcher_max_retries, self, txn);
         save_setting!(fetcher_host_exclusion_on_low_error_secs, self, txn);
         save_setting!(fetcher_host_exclusion_on_med_error_secs, self, txn);
         save_setting!(fetcher_host_exclusion_on_high_error_secs, self, txn);
         save_setting!(prune_period_days, self, txn);
         save_setting!(cache_prune_period_days, self, txn);
+        save_setting!(auto_prune, self, txn);
+        save_setting!(auto_prune_interval_days, self, txn);
+        save_setting!(auto_prune_min_size_mb, self, txn);
+        save_setting!(dm_cache_size, self, txn);
+        save_setting!(reaction_summary_threshold, self, txn);
+        save_setting!(prune_summarized_reactions, self, txn);
+        save_setting!(stored_kinds, self, txn);
         save_setting!(blossom_servers, self, txn);
         save_setting!(undo_send_seconds, self, txn);
         txn.commit()?;
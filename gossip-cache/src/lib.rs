@@ -1,7 +1,9 @@
+use gossip_lib::community::{self, Community};
+use gossip_lib::polls::{self, PollResults};
 use gossip_lib::{GLOBALS, Person, PersonList, PersonTable, Private, Table};
 use nostr_types::{
     ContentSegment, Event, EventDelegation, EventKind, EventReference, Id, MilliSatoshi, NAddr,
-    NostrBech32, ParsedTag, PublicKey, RelayUrl, ShatteredContent, Unixtime,
+    NostrBech32, ParsedTag, PublicKey, RelayUrl, ShatteredContent, Tag, Unixtime,
 };
 
 use std::cell::RefCell;
@@ -121,6 +123,9 @@ pub struct NoteData {
     /// Deletion reasons if any
     pub deletions: Vec<String>,
 
+    /// How many people we follow reported this note, by type of report
+    pub reports: Vec<(String, usize)>,
+
     /// Annotations by the author
     pub annotations: Vec<(Unixtime, String)>,
 
@@ -139,6 +144,18 @@ pub struct NoteData {
     /// Has the current user reacted to this post?
     pub our_reaction: Option<char>,
 
+    /// How many reactions relays counted for us (NIP-45), if any did
+    pub relay_reaction_count: Option<usize>,
+
+    /// The current tallies, if this is a poll
+    pub poll_results: Option<PollResults>,
+
+    /// The communities this note was posted to, and their names
+    pub communities: Vec<(NAddr, String)>,
+
+    /// The community whose moderators approved this note, if any did
+    pub community_approval: Option<Community>,
+
     /// The total amount of MilliSatoshi zapped to this note
     pub zaptotal: MilliSatoshi,
 
@@ -165,6 +182,9 @@ pub struct NoteData {
 
     /// i-tag
     pub itag: Option<String>,
+
+    /// Tags that event hooks gave this note, shown in place of its own
+    pub hook_tags: Option<Vec<Tag>>,
 }
 
 impl NoteData {
@@ -179,7 +199,7 @@ impl NoteData {
             encryption = EncryptionType::Giftwrap;
             // Use the rumor for subsequent processing, but swap for the Giftwrap's id
             // since that is the effective event (database-accessible, deletable, etc)
-            if let Ok(rumor) = GLOBALS.identity.unwrap_giftwrap(&event) {
+            if let Ok(rumor) = GLOBALS.dm_cache.unwrap_giftwrap(&event) {
                 let id = event.id;
                 event = rumor.into_event_with_bad_signature();
                 event.id = id; // lie, keep the giftwrap id
@@ -194,6 +214,11 @@ impl NoteData {
         // This function checks that the deletion author is allowed
         let deletions = GLOBALS.db().get_deletions(&event).unwrap_or_default();
 
+        // This function only counts reports by people we follow
+        let reports = gossip_lib::reports::count_reports(
+            &GLOBALS.db().get_reports_for(event.id).unwrap_or_default(),
+        );
+
         // This function checks the authors match
         let annotations = GLOBALS.db().get_annotations(&event).unwrap_or_default();
 
@@ -202,6 +227,13 @@ impl NoteData {
             .get_reactions(event.id)
             .unwrap_or((vec![], None));
 
+        let relay_reaction_count = GLOBALS.reaction_counts.get(&event.id).map(|c| *c);
+
+        let poll_results = poll_results(&event);
+
+        let communities = communities(&event);
+        let community_approval = community_approval(&event, &communities);
+
         let zaptotal = GLOBALS
             .db()
             .get_zap_total(event.id)
@@ -259,7 +291,7 @@ impl NoteData {
             EventKind::Repost => ("".to_owned(), embedded_event_error),
             EventKind::GenericRepost => ("".to_owned(), None),
             EventKind::EncryptedDirectMessage => {
-                match GLOBALS.identity.decrypt_event_contents(&event) {
+                match GLOBALS.dm_cache.decrypt_event_contents(&event) {
                     Ok(m) => (m, None),
                     Err(_) => ("".to_owned(), Some("DECRYPTION FAILED".to_owned())),
                 }
@@ -382,6 +414,8 @@ impl NoteData {
 
         let volatile = GLOBALS.db().event_is_volatile(event.id);
 
+        let hook_tags = GLOBALS.db().read_hook_tags(event.id).unwrap_or_default();
+
         let mut itag = None;
         for tag in &event.tags {
             if tag.tagname() == "i" {
@@ -396,12 +430,17 @@ impl NoteData {
             author,
             lists,
             deletions,
+            reports,
             annotations,
             repost,
             embedded_event,
             mentions,
             reactions,
             our_reaction,
+            relay_reaction_count,
+            poll_results,
+            communities,
+            community_approval,
             zaptotal,
             seen_on,
             shattered_content,
@@ -411,6 +450,7 @@ impl NoteData {
             bookmarked,
             volatile,
             itag,
+            hook_tags,
         }
     }
 
@@ -423,6 +463,14 @@ impl NoteData {
         self.reactions.clear();
         self.reactions.append(&mut reactions);
         self.our_reaction = our_reaction;
+        self.relay_reaction_count = GLOBALS.reaction_counts.get(&self.event.id).map(|c| *c);
+
+        // Update poll tallies
+        self.poll_results = poll_results(&self.event);
+
+        // Update communities, whose definitions and approvals may come later
+        self.communities = communities(&self.event);
+        self.community_approval = community_approval(&self.event, &self.communities);
 
         // Update seen_on
         let mut seen_on = GLOBALS
@@ -455,6 +503,31 @@ impl NoteData {
         self.lists.contains_key(&PersonList::Muted)
     }
 
+    /// The tags to show, which are those from event hooks if any gave it tags
+    pub fn tags(&self) -> &[Tag] {
+        match &self.hook_tags {
+            Some(tags) => tags,
+            None => &self.event.tags,
+        }
+    }
+
+    /// The content warning, if the note has one, and its reason if it gives one
+    pub fn content_warning(&self) -> Option<Option<String>> {
+        self.tags().iter().find_map(|tag| match tag.parse() {
+            Ok(ParsedTag::ContentWarning(reason)) => Some(reason.filter(|r| !r.is_empty())),
+            _ => None,
+        })
+    }
+
+    /// NIP-32 labels on the note
+    pub fn labels(&self) -> Vec<String> {
+        self.tags()
+            .iter()
+            .filter(|tag| tag.tagname() == "l")
+            .map(|tag| tag.value().to_owned())
+            .collect()
+    }
+
     pub fn event_reference(&self) -> EventReference {
         if self.event.kind.is_replaceable() {
             EventReference::Addr(NAddr {
@@ -473,3 +546,30 @@ impl NoteData {
         }
     }
 }
+
+fn poll_results(event: &Event) -> Option<PollResults> {
+    if !polls::is_poll(event.kind) {
+        return None;
+    }
+    GLOBALS.db().get_poll_results(event.id).ok().flatten()
+}
+
+fn communities(event: &Event) -> Vec<(NAddr, String)> {
+    community::communities_of(event)
+        .into_iter()
+        .map(|addr| {
+            let name = match GLOBALS.db().get_community(&addr) {
+                Ok(Some(community)) => community.name,
+                _ => addr.d.clone(),
+            };
+            (addr, name)
+        })
+        .collect()
+}
+
+fn community_approval(event: &Event, communities: &[(NAddr, String)]) -> Option<Community> {
+    if communities.is_empty() {
+        return None;
+    }
+    GLOBALS.db().get_community_approval(event).ok().flatten()
+}
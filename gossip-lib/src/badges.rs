@@ -0,0 +1,138 @@
+//! NIP-58 badges
+//!
+//! A badge is defined by its issuer in a badge definition (kind 30009), given to
+//! people in a badge award (kind 8), and a person picks which of their awards to
+//! show in their profile badges (kind 30008).
+
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use nostr_types::{Event, EventKind, Id, NAddr, ParsedTag, PublicKey, UncheckedUrl};
+
+/// The `d` tag of a profile badges event
+pub const PROFILE_BADGES_D: &str = "profile_badges";
+
+/// A badge that was awarded to someone and that they chose to show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Badge {
+    /// The address of the badge definition
+    pub definition: NAddr,
+
+    /// The award event
+    pub award: Id,
+
+    /// Who defined and awarded the badge
+    pub issuer: PublicKey,
+
+    pub name: String,
+    pub description: String,
+    pub image: Option<UncheckedUrl>,
+    pub thumb: Option<UncheckedUrl>,
+}
+
+impl Badge {
+    /// The image to show at a small size
+    pub fn small_image(&self) -> Option<&UncheckedUrl> {
+        self.thumb.as_ref().or(self.image.as_ref())
+    }
+
+    pub(crate) fn from_definition(definition: &Event, naddr: NAddr, award: Id) -> Option<Badge> {
+        if definition.kind != EventKind::BadgeDefinition
+            || definition.pubkey != naddr.author
+            || definition.parameter().as_deref() != Some(naddr.d.as_str())
+        {
+            return None;
+        }
+
+        let mut badge = Badge {
+            issuer: naddr.author,
+            definition: naddr,
+            award,
+            name: String::new(),
+            description: String::new(),
+            image: None,
+            thumb: None,
+        };
+        for tag in &definition.tags {
+            match tag.tagname() {
+                "name" => badge.name = tag.value().to_owned(),
+                "description" => badge.description = tag.value().to_owned(),
+                "image" => badge.image = Some(UncheckedUrl::from_str(tag.value())),
+                // The first thumb is the preferred one
+                "thumb" if badge.thumb.is_none() => {
+                    badge.thumb = Some(UncheckedUrl::from_str(tag.value()))
+                }
+                _ => {}
+            }
+        }
+        if badge.name.is_empty() {
+            badge.name = badge.definition.d.clone();
+        }
+
+        Some(badge)
+    }
+}
+
+/// The badges `pubkey` chose to show, as from
+/// [get_badges_for](crate::Storage::get_badges_for). These are cached, and the cache
+/// is refreshed as badge events are processed, so this is cheap to call every frame.
+pub fn badges_for(pubkey: PublicKey) -> Result<Vec<Badge>, Error> {
+    if let Some(badges) = GLOBALS.badges.get(&pubkey) {
+        return Ok(badges.clone());
+    }
+    let badges = GLOBALS.db().get_badges_for(pubkey)?;
+    GLOBALS.badges.insert(pubkey, badges.clone());
+    Ok(badges)
+}
+
+/// The (badge definition, badge award) pairs listed in a profile badges event, in
+/// the order they should be shown
+pub(crate) fn profile_badge_pairs(event: &Event) -> Vec<(NAddr, Id)> {
+    let mut pairs: Vec<(NAddr, Id)> = Vec::new();
+    let mut definition: Option<NAddr> = None;
+    for tag in &event.tags {
+        match tag.parse() {
+            Ok(ParsedTag::Address { address, .. }) => definition = Some(address),
+            Ok(ParsedTag::Event { id, .. }) => {
+                // Each award follows the definition it is for
+                if let Some(naddr) = definition.take() {
+                    if naddr.kind == EventKind::BadgeDefinition {
+                        pairs.push((naddr, id));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    pairs
+}
+
+/// Whether `award` is a valid award of the badge defined at `definition` to
+/// `recipient`. Only the author of the definition can award it.
+pub(crate) fn is_valid_award(award: &Event, definition: &NAddr, recipient: PublicKey) -> bool {
+    if award.kind != EventKind::BadgeAward || award.pubkey != definition.author {
+        return false;
+    }
+
+    let mut for_definition = false;
+    let mut for_recipient = false;
+    for tag in &award.tags {
+        match tag.parse() {
+            Ok(ParsedTag::Address { address, .. }) => {
+                if address.kind == definition.kind
+                    && address.author == definition.author
+                    && address.d == definition.d
+                {
+                    for_definition = true;
+                }
+            }
+            Ok(ParsedTag::Pubkey { pubkey, .. }) => {
+                if pubkey == recipient {
+                    for_recipient = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for_definition && for_recipient
+}
@@ -1,8 +1,8 @@
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
 use nostr_types::{
-    ContentEncryptionAlgorithm, Event, EventKind, EventReference, Id, ParsedTag, PreEvent,
-    RelayUrl, Tag, Unixtime,
+    ContentEncryptionAlgorithm, Event, EventKind, EventReference, Id, ParsedTag, PreEvent, Tag,
+    Unixtime,
 };
 use std::collections::BTreeMap;
 
@@ -27,7 +27,7 @@ impl BookmarkList {
                     author_pubkey,
                 } => {
                     let relays = match recommended_relay_url {
-                        Some(url) => match RelayUrl::try_from_unchecked_url(&url) {
+                        Some(url) => match crate::relay::relay_url_from_unchecked(&url) {
                             Ok(rurl) => vec![rurl],
                             Err(_) => vec![],
                         },
@@ -56,6 +56,14 @@ impl ClientIdentity {
         Ok(())
     }
 
+    /// Lock the private key, forgetting the decrypted key until it is unlocked again
+    pub(crate) fn lock(&self) -> Result<(), Error> {
+        if self.is_unlocked() {
+            self.load()?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn clear_public_key(&self) -> Result<(), Error> {
         *self.inner.write_arc() = Identity::None;
         self.on_keychange()?;
@@ -4,13 +4,15 @@ use crate::misc::Private;
 use crate::nostr_connect_server::{Approval, ParsedCommand};
 use crate::people::PersonList;
 use crate::relay::Relay;
+use crate::storage::Rebuild;
 use nostr_types::{
-    Event, EventKind, EventReference, Id, Metadata, MilliSatoshi, NAddr, Profile, PublicKey,
-    RelayUrl, Tag, UncheckedUrl, Unixtime,
+    Event, EventKind, EventReference, Filter, Id, Metadata, MilliSatoshi, NAddr, Profile,
+    PublicKey, RelayUrl, Tag, UncheckedUrl, Unixtime,
 };
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// This is a message sent to the Overlord. Tasks which take any amount of time,
 /// especially involving relays, are handled by the Overlord in this way. There is
@@ -28,6 +30,12 @@ pub enum ToOverlordMessage {
     /// Calls [advertise_relay_list_one](crate::Overlord::advertise_relay_list)
     AdvertiseRelayListOne(RelayUrl, Box<Event>, Box<Event>),
 
+    /// Calls [audit_stored_events](crate::Overlord::audit_stored_events)
+    AuditStoredEvents,
+
+    /// Calls [audit_stored_events_cancel](crate::Overlord::audit_stored_events_cancel)
+    AuditStoredEventsCancel,
+
     /// Calls [auth_approved](crate::Overlord::auth_approved)
     /// pass 'true' as the second parameter for a permanent approval
     AuthApproved(RelayUrl, bool),
@@ -48,6 +56,12 @@ pub enum ToOverlordMessage {
     /// Removes a bookmark, and publishes new bookmarks list
     BookmarkRm(EventReference),
 
+    /// Calls [boost_relay](crate::Overlord::boost_relay)
+    BoostRelay(RelayUrl, Duration),
+
+    /// Calls [cancel_delivery](crate::Overlord::cancel_delivery)
+    CancelDelivery(Id, RelayUrl),
+
     /// Calls [change_passphrase](crate::Overlord::change_passphrase)
     ChangePassphrase { old: String, new: String },
 
@@ -62,6 +76,15 @@ pub enum ToOverlordMessage {
     /// pass 'true' as the second parameter for a permanent approval
     ConnectDeclined(RelayUrl, bool),
 
+    /// Calls [connect_normally](crate::Overlord::connect_normally)
+    ConnectNormally,
+
+    /// Calls [connect_only](crate::Overlord::connect_only)
+    ConnectOnly(Vec<RelayUrl>),
+
+    /// Calls [copy_person_relays](crate::Overlord::copy_person_relays)
+    CopyPersonRelays(PublicKey, PublicKey),
+
     /// Calls [delegation_reset](crate::Overlord::delegation_reset)
     DelegationReset,
 
@@ -80,12 +103,21 @@ pub enum ToOverlordMessage {
     /// Calls [drop_relay](crate::Overlord::drop_relay)
     DropRelay(RelayUrl),
 
+    /// Calls [fetch_badges](crate::Overlord::fetch_badges)
+    FetchBadges(PublicKey),
+
     /// Calls [fetch_event](crate::Overlord::fetch_event)
     FetchEvent(Id, Vec<RelayUrl>),
 
     /// Calls [fetch_naddr](crate::Overlord::fetch_naddr)
     FetchNAddr(NAddr),
 
+    /// Calls [fetch_parent_of](crate::Overlord::fetch_parent_of)
+    FetchParentOf(Id),
+
+    /// Calls [find_relays_for_uncovered](crate::Overlord::find_relays_for_uncovered)
+    FindRelaysForUncovered,
+
     /// Calls [follow_pubkey](crate::Overlord::follow_pubkey)
     FollowPubkey(PublicKey, PersonList, Private),
 
@@ -98,9 +130,18 @@ pub enum ToOverlordMessage {
     /// Calls [generate_private_key](crate::Overlord::generate_private_key)
     GeneratePrivateKey(String),
 
+    /// Calls [handle_nostr_uri](crate::Overlord::handle_nostr_uri)
+    HandleNostrUri(String),
+
+    /// Calls [health_check_all_relays](crate::Overlord::health_check_all_relays)
+    HealthCheckAllRelays,
+
     /// Calls [hide_or_show_relay](crate::Overlord::hide_or_show_relay)
     HideOrShowRelay(RelayUrl, bool),
 
+    /// Calls [import_follows](crate::Overlord::import_follows)
+    ImportFollows(Vec<Profile>, bool),
+
     /// Calls [import_priv](crate::Overlord::import_priv)
     ImportPriv {
         // nsec, hex, or ncryptsec
@@ -114,6 +155,9 @@ pub enum ToOverlordMessage {
     /// Calls [load_more_current_feed](crate::Overlord::load_more_current_feed)
     LoadMoreCurrentFeed,
 
+    /// Calls [load_older](crate::Overlord::load_older)
+    LoadOlder(Unixtime),
+
     /// internal (minions use this channel too)
     MinionJobComplete(RelayUrl, u64),
 
@@ -130,6 +174,8 @@ pub enum ToOverlordMessage {
         in_reply_to: Option<Id>,
         annotation: bool,
         dm_channel: Option<DmChannel>,
+        relay_urls: Option<Vec<RelayUrl>>,
+        pow_job: u64,
     },
 
     /// Calls [post_again](crate::Overlord::post_again)
@@ -141,6 +187,15 @@ pub enum ToOverlordMessage {
     /// Calls [post_nip46_event](crate::Overlord::post_nip46_event)
     PostNip46Event(Event, Vec<RelayUrl>),
 
+    /// Calls [post_to_community](crate::Overlord::post_to_community)
+    PostToCommunity(NAddr, String, Vec<Tag>, u64),
+
+    /// Calls [pow_cancel](crate::Overlord::pow_cancel)
+    PowCancel(u64),
+
+    /// Calls [purge_quarantined_events](crate::Overlord::purge_quarantined_events)
+    PurgeQuarantinedEvents,
+
     /// Calls [push_blossom_servers](crate::Overlord::push_blossom_servers)
     PushBlossomServers,
 
@@ -156,18 +211,33 @@ pub enum ToOverlordMessage {
     /// Calls [react](crate::Overlord::react)
     React(Id, PublicKey, char),
 
+    /// Calls [reconnect_all](crate::Overlord::reconnect_all)
+    ReconnectAll,
+
     /// internal (the overlord sends messages to itself sometimes!)
     ReengageMinion(RelayUrl, Vec<RelayJob>),
 
+    /// Calls [refresh_relay_list](crate::Overlord::refresh_relay_list)
+    RefreshRelayList(PublicKey),
+
     /// Calls [refresh_scores_and_pick_relays](crate::Overlord::refresh_scores_and_pick_relays)
     RefreshScoresAndPickRelays,
 
     /// Calls [reresh_subscribed_metadata](crate::Overlord::refresh_subscribed_metadata)
     RefreshSubscribedMetadata,
 
+    /// Calls [remine_event](crate::Overlord::remine_event)
+    RemineEvent(Id, u8),
+
     /// Calls [repost](crate::Overlord::repost)
     Repost(Id),
 
+    /// Calls [retry_delivery](crate::Overlord::retry_delivery)
+    RetryDelivery(Id, RelayUrl),
+
+    /// Calls [schedule_rebuild](crate::Overlord::schedule_rebuild)
+    ScheduleRebuild(Rebuild),
+
     /// Calls [search](crate::Overlord::search_locally)
     SearchLocally(String),
 
@@ -186,9 +256,15 @@ pub enum ToOverlordMessage {
     /// internal
     SetPersonFeed(PublicKey, Unixtime),
 
+    /// Calls [set_quiet_mode](crate::Overlord::set_quiet_mode)
+    SetQuietMode(bool),
+
     /// internal
     SetRelayFeed(RelayUrl, Unixtime),
 
+    /// Calls [set_relay_usage_bits](crate::Overlord::set_relay_usage_bits)
+    SetRelayUsageBits(RelayUrl, u64),
+
     /// internal
     SetThreadFeed {
         id: Id,
@@ -205,15 +281,24 @@ pub enum ToOverlordMessage {
     /// Calls [subscribe_config](crate::Overlord::subscribe_config)
     SubscribeConfig(Option<Vec<RelayUrl>>),
 
+    /// Calls [subscribe_custom_feed](crate::Overlord::subscribe_custom_feed)
+    SubscribeCustomFeed(String, Vec<PublicKey>, Vec<EventKind>),
+
     /// Calls [subscribe_discover](crate::Overlord::subscribe_discover)
     SubscribeDiscover(Vec<PublicKey>, Option<Vec<RelayUrl>>),
 
+    /// Calls [subscribe_hashtag_feed](crate::Overlord::subscribe_hashtag_feed)
+    SubscribeHashtagFeed(String),
+
     /// Calls [subscribe_inbox](crate::Overlord::subscribe_inbox)
     SubscribeInbox(Option<Vec<RelayUrl>>),
 
     /// Calls [subscribe_nip46](crate::Overlord::subscribe_nip46)
     SubscribeNip46(Vec<RelayUrl>),
 
+    /// Calls [sync_config_to_relay](crate::Overlord::sync_config_to_relay)
+    SyncConfigToRelay(RelayUrl),
+
     /// Calls [test_relay](crate::Overlord::test_relay)
     TestRelay(RelayUrl),
 
@@ -226,6 +311,9 @@ pub enum ToOverlordMessage {
     /// Calls [unlock_key](crate::Overlord::unlock_key)
     UnlockKey(String),
 
+    /// Calls [unsubscribe_custom_feed](crate::Overlord::unsubscribe_custom_feed)
+    UnsubscribeCustomFeed(String),
+
     /// Calls [update_metadata](crate::Overlord::update_metadata)
     UpdateMetadata(PublicKey),
 
@@ -244,6 +332,9 @@ pub enum ToOverlordMessage {
     /// Calls [visible_notes_changed](crate::Overlord::visible_notes_changed)
     VisibleNotesChanged(Vec<Id>),
 
+    /// Calls [vote_in_poll](crate::Overlord::vote_in_poll)
+    VoteInPoll(Id, Vec<String>),
+
     /// Calls [zap_start](crate::Overlord::zap_start)
     ZapStart(Id, PublicKey, UncheckedUrl),
 
@@ -251,6 +342,31 @@ pub enum ToOverlordMessage {
     Zap(Id, PublicKey, MilliSatoshi, String),
 }
 
+impl ToOverlordMessage {
+    /// Whether handling this message signs an event with our private key. These
+    /// are held back while the key is locked, rather than failing part way.
+    pub fn needs_signer(&self) -> bool {
+        matches!(
+            self,
+            ToOverlordMessage::AdvertiseRelayList
+                | ToOverlordMessage::BookmarkAdd(..)
+                | ToOverlordMessage::BookmarkRm(..)
+                | ToOverlordMessage::DeletePost(..)
+                | ToOverlordMessage::Post { .. }
+                | ToOverlordMessage::PostToCommunity(..)
+                | ToOverlordMessage::PushBlossomServers
+                | ToOverlordMessage::PushMetadata(..)
+                | ToOverlordMessage::PushPersonList(..)
+                | ToOverlordMessage::React(..)
+                | ToOverlordMessage::RemineEvent(..)
+                | ToOverlordMessage::Repost(..)
+                | ToOverlordMessage::ShareHandlerRecommendations(..)
+                | ToOverlordMessage::VoteInPoll(..)
+                | ToOverlordMessage::Zap(..)
+        )
+    }
+}
+
 /// Internal to gossip-lib.
 /// This is a message sent to the minions
 #[derive(Debug, Clone)]
@@ -282,10 +398,13 @@ pub(crate) enum ToMinionPayloadDetail {
     AdvertiseRelayList(Box<Event>, Box<Event>),
     AuthApproved,
     AuthDeclined,
+    CountSubscribe(Filter),
     FetchEvent(Id),
     FetchNAddr(NAddr),
     PostEvents(Vec<Event>),
+    Reconnect,
     Shutdown,
+    SkipEventsBefore(Option<Unixtime>),
     Subscribe(FilterSet),
     Unsubscribe(FilterSet),
     UnsubscribeReplies,
@@ -297,6 +416,7 @@ pub enum RelayConnectionReason {
     Config,
     Counting,
     Discovery,
+    Engagement,
     FetchAugments,
     FetchDirectMessages,
     FetchContacts,
@@ -317,6 +437,9 @@ pub enum RelayConnectionReason {
     Search,
     SubscribePerson,
     SubscribeGlobal,
+    SubscribeHashtag,
+    SubscribeCustomFeed,
+    SyncConfig,
 }
 
 impl fmt::Display for RelayConnectionReason {
@@ -333,6 +456,7 @@ impl RelayConnectionReason {
             Discovery => "Searching for other people's Relay Lists",
             Config => "Reading our client configuration",
             Counting => "Counting",
+            Engagement => "Watching for reactions and zaps to our notes",
             FetchInbox => "Searching for inbox of us",
             FetchAugments => "Fetching events that augment other events (likes, zaps, deletions)",
             FetchDirectMessages => "Fetching direct messages",
@@ -354,6 +478,9 @@ impl RelayConnectionReason {
             Search => "Search",
             SubscribePerson => "Subscribe to the events of a person",
             SubscribeGlobal => "Subscribe to the global feed on a relay",
+            SubscribeHashtag => "Subscribe to a hashtag feed",
+            SubscribeCustomFeed => "Subscribe to a custom feed",
+            SyncConfig => "Copying our profile, relay list and lists to a relay",
         }
     }
 
@@ -363,6 +490,7 @@ impl RelayConnectionReason {
             Discovery => false,
             Config => false,
             Counting => false,
+            Engagement => true,
             FetchInbox => true,
             FetchAugments => false,
             FetchDirectMessages => true,
@@ -384,6 +512,9 @@ impl RelayConnectionReason {
             Search => false,
             SubscribePerson => false,
             SubscribeGlobal => false,
+            SubscribeHashtag => false,
+            SubscribeCustomFeed => true,
+            SyncConfig => false,
         }
     }
 }
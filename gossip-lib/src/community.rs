@@ -0,0 +1,130 @@
+//! NIP-72 moderated communities
+//!
+//! A community is defined by an addressable event (kind 34550) that names its
+//! moderators and the relays it uses. Posts to a community carry an `a` tag
+//! pointing at that definition, and moderators approve them with kind 4550
+//! events that tag both the community and the post.
+
+use nostr_types::{Event, EventKind, Id, NAddr, ParsedTag, PublicKey, RelayUrl, Tag};
+
+/// The kind number of a community definition
+pub const COMMUNITY_DEFINITION_KIND: u32 = 34550;
+
+/// The kind number of a community post approval
+pub const APPROVAL_KIND: u32 = 4550;
+
+/// Whether events of this kind are community definitions
+pub fn is_community_definition(kind: EventKind) -> bool {
+    u32::from(kind) == COMMUNITY_DEFINITION_KIND
+}
+
+/// Whether events of this kind are community post approvals
+pub fn is_approval(kind: EventKind) -> bool {
+    u32::from(kind) == APPROVAL_KIND
+}
+
+/// A community, as parsed from its definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Community {
+    pub addr: NAddr,
+    pub name: String,
+    pub description: String,
+    pub moderators: Vec<PublicKey>,
+
+    /// The relays the community uses. Posts go to these.
+    pub relays: Vec<RelayUrl>,
+}
+
+impl Community {
+    pub fn from_event(event: &Event) -> Option<Community> {
+        if !is_community_definition(event.kind) {
+            return None;
+        }
+
+        let mut d: Option<String> = None;
+        let mut name = String::new();
+        let mut description = String::new();
+        let mut moderators: Vec<PublicKey> = Vec::new();
+        let mut relays: Vec<RelayUrl> = Vec::new();
+
+        for tag in &event.tags {
+            match tag.tagname() {
+                "d" => d = Some(tag.value().to_owned()),
+                "name" => name = tag.value().to_owned(),
+                "description" => description = tag.value().to_owned(),
+                "relay" => {
+                    if let Ok(url) = crate::relay::parse_relay_url(tag.value()) {
+                        if !relays.contains(&url) {
+                            relays.push(url);
+                        }
+                    }
+                }
+                "p" if tag.get_index(3) == "moderator" => {
+                    if let Ok(pubkey) = PublicKey::try_from_hex_string(tag.value(), true) {
+                        moderators.push(pubkey);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let d = d?;
+        if name.is_empty() {
+            name = d.clone();
+        }
+
+        Some(Community {
+            addr: NAddr {
+                d,
+                relays: relays.clone(),
+                kind: event.kind,
+                author: event.pubkey,
+            },
+            name,
+            description,
+            moderators,
+            relays,
+        })
+    }
+
+    /// Whether this person may approve posts. The community's author always may.
+    pub fn is_moderator(&self, pubkey: PublicKey) -> bool {
+        pubkey == self.addr.author || self.moderators.contains(&pubkey)
+    }
+
+    /// The tag that puts a post in this community
+    pub fn tag(&self) -> Tag {
+        let mut address = self.addr.clone();
+        address.relays.truncate(1);
+        ParsedTag::Address {
+            address,
+            marker: None,
+        }
+        .into_tag()
+    }
+}
+
+/// The communities an event was posted to
+pub fn communities_of(event: &Event) -> Vec<NAddr> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.parse() {
+            Ok(ParsedTag::Address { address, .. }) if is_community_definition(address.kind) => {
+                Some(address)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The post an approval approves
+pub fn approved_id(event: &Event) -> Option<Id> {
+    if !is_approval(event.kind) {
+        return None;
+    }
+    event.tags.iter().find_map(|tag| match tag.parse() {
+        Ok(ParsedTag::Event { id, .. }) => Some(id),
+        _ => None,
+    })
+}
@@ -0,0 +1,190 @@
+use crate::comms::{
+    RelayConnectionReason, RelayJob, ToMinionPayload, ToMinionPayloadDetail, ToOverlordMessage,
+};
+use crate::globals::GLOBALS;
+use crate::manager;
+use crate::pending::PendingItem;
+use crate::relay::RelayRejection;
+use nostr_types::{Id, RelayUrl, Unixtime};
+
+// Seconds to wait before the first automatic retry. Each later retry waits
+// twice as long as the one before.
+const FIRST_RETRY_DELAY: i64 = 30;
+
+/// A post that a relay refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndeliveredPost {
+    pub id: Id,
+    pub relay: RelayUrl,
+
+    /// What the relay said when it last refused the post
+    pub message: String,
+
+    /// How many times we have sent it again
+    pub attempts: u32,
+
+    /// When we will next send it again, if we will
+    pub retry_at: Option<Unixtime>,
+}
+
+/// Whether a relay refusing a post with this message may accept it later.
+///
+/// Being rate-limited or hitting an error on the relay are worth retrying.
+/// Anything else (auth-required, blocked, restricted, invalid, pow, ...) will
+/// fail the same way again.
+pub fn is_transient_rejection(message: &str) -> bool {
+    let prefix = message.split(':').next().unwrap_or("").trim();
+    prefix == "error" || RelayRejection::from_message(message) == Some(RelayRejection::RateLimited)
+}
+
+/// Handle a relay refusing one of our posts
+pub(crate) fn post_rejected(relay: &RelayUrl, id: Id, message: &str) {
+    let key = (id, relay.clone());
+    let max_attempts = GLOBALS.db().read_setting_post_retry_attempts();
+
+    let mut post = GLOBALS
+        .undelivered_posts
+        .get(&key)
+        .map(|p| p.clone())
+        .unwrap_or(UndeliveredPost {
+            id,
+            relay: relay.clone(),
+            message: String::new(),
+            attempts: 0,
+            retry_at: None,
+        });
+    post.message = message.to_owned();
+    post.retry_at = if is_transient_rejection(message) && post.attempts < max_attempts {
+        let delay = FIRST_RETRY_DELAY << post.attempts.min(10);
+        Some(Unixtime(Unixtime::now().0 + delay))
+    } else {
+        None
+    };
+
+    // Once some relay has the post it got out, and relays that refused it are
+    // retried quietly
+    let delivered = match GLOBALS.db().get_event_seen_on_relay(id) {
+        Ok(seen_on) => !seen_on.is_empty(),
+        Err(_) => false,
+    };
+
+    if post.retry_at.is_none() && !delivered {
+        GLOBALS
+            .status_queue
+            .write()
+            .write(format!("{} did not accept your post: {}", relay, message));
+    }
+
+    GLOBALS.undelivered_posts.insert(key, post);
+    if !delivered {
+        GLOBALS.pending.insert(PendingItem::PostUndelivered {
+            id,
+            relay: relay.clone(),
+        });
+    }
+}
+
+/// Handle a relay accepting one of our posts
+pub(crate) fn post_accepted(relay: &RelayUrl, id: Id) {
+    // A note that was mined again replaces the old one once a relay has it
+    if let Some((_, old_id)) = GLOBALS.remined_posts.remove(&id) {
+        let _ = GLOBALS
+            .to_overlord
+            .send(ToOverlordMessage::DeletePost(old_id));
+    }
+
+    GLOBALS.undelivered_posts.remove(&(id, relay.clone()));
+    GLOBALS.pending.remove(&PendingItem::PostUndelivered {
+        id,
+        relay: relay.clone(),
+    });
+
+    // The post got out, so it is no longer pending for the relays that refused it
+    // either. Those that may accept it later are still retried.
+    for post in GLOBALS.undelivered_posts.iter().filter(|p| p.id == id) {
+        GLOBALS.pending.remove(&PendingItem::PostUndelivered {
+            id,
+            relay: post.relay.clone(),
+        });
+    }
+}
+
+/// Send again the undelivered posts whose retry time has come
+pub(crate) fn retry_due() {
+    let now = Unixtime::now();
+    let due: Vec<(Id, RelayUrl)> = GLOBALS
+        .undelivered_posts
+        .iter()
+        .filter(|p| p.retry_at.is_some_and(|t| t <= now))
+        .map(|p| p.key().clone())
+        .collect();
+
+    for (id, relay) in due {
+        retry(id, relay);
+    }
+}
+
+/// Send an undelivered post to its relay again now
+pub(crate) fn retry(id: Id, relay: RelayUrl) {
+    let event = match GLOBALS.db().read_event(id) {
+        Ok(Some(event)) => event,
+        Ok(None) => {
+            // We no longer have it, so there is nothing to send
+            cancel(id, relay);
+            return;
+        }
+        Err(e) => {
+            tracing::error!("{}", e);
+            return;
+        }
+    };
+
+    if let Some(mut post) = GLOBALS.undelivered_posts.get_mut(&(id, relay.clone())) {
+        post.attempts += 1;
+        post.retry_at = None;
+    }
+
+    tracing::debug!("Sending post {} to {} again", id.as_hex_string(), relay);
+    manager::run_jobs_on_all_relays(
+        vec![relay],
+        vec![RelayJob {
+            reason: RelayConnectionReason::PostEvent,
+            payload: ToMinionPayload {
+                job_id: rand::random::<u64>(),
+                detail: ToMinionPayloadDetail::PostEvents(vec![event]),
+            },
+        }],
+    );
+}
+
+/// Give up on delivering a post to a relay
+pub(crate) fn cancel(id: Id, relay: RelayUrl) {
+    GLOBALS.undelivered_posts.remove(&(id, relay.clone()));
+    GLOBALS
+        .pending
+        .remove(&PendingItem::PostUndelivered { id, relay });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_transient_rejection() {
+        assert!(is_transient_rejection("rate-limited: slow down"));
+        assert!(is_transient_rejection(
+            "error: could not connect to the database"
+        ));
+        assert!(!is_transient_rejection(
+            "auth-required: we only accept events from registered users"
+        ));
+        assert!(!is_transient_rejection("blocked: you are banned"));
+        assert!(!is_transient_rejection(
+            "pow: difficulty 26 is less than 30"
+        ));
+        assert!(!is_transient_rejection(
+            "invalid: event creation date is too far off"
+        ));
+        assert!(!is_transient_rejection(""));
+    }
+}
@@ -389,12 +389,29 @@ impl Connection {
         Ok(())
     }
 
+    // Refuse to post to relays the user marked no-post
+    fn check_may_post(&self) -> Result<(), Error> {
+        if let Ok(url) = crate::relay::parse_relay_url(&self.relay_url) {
+            if let Some(relay) = GLOBALS.db().read_relay(&url)? {
+                if relay.no_post {
+                    return Err(ErrorKind::General(format!(
+                        "{} is marked no-post, not posting.",
+                        self.relay_url
+                    ))
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Post an event to the relay
     pub async fn post_event(
         &mut self,
         event: Event,
         timeout: Duration,
     ) -> Result<(bool, String), Error> {
+        self.check_may_post()?;
         let event_id = event.id;
         let message = ClientMessage::Event(Box::new(event));
         self.send_message(message).await?;
@@ -419,6 +436,7 @@ impl Connection {
         json: String,
         timeout: Duration,
     ) -> Result<(bool, String), Error> {
+        self.check_may_post()?;
         let wire = format!("[\"EVENT\",{}]", json);
         let msg = Message::Text(wire);
         self.inner_send_message(msg).await?;
@@ -0,0 +1,135 @@
+//! An in-memory LRU of decrypted DM content
+//!
+//! Decrypting DMs (especially unwrapping NIP-17 giftwraps) on every render is
+//! expensive, so the plaintext is kept here keyed by the event id. Plaintext is
+//! zeroized when it is evicted or the cache is cleared, and the cache is cleared
+//! whenever the identity changes or is deleted.
+
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use nostr_types::{Event, Id, Rumor};
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use zeroize::Zeroizing;
+
+#[derive(Default)]
+struct DmCacheInner {
+    entries: HashMap<Id, Zeroizing<String>>,
+
+    // Least recently used first
+    order: VecDeque<Id>,
+}
+
+impl DmCacheInner {
+    fn get(&mut self, id: Id) -> Option<Zeroizing<String>> {
+        let plaintext = self.entries.get(&id)?.clone();
+        self.touch(id);
+        Some(plaintext)
+    }
+
+    fn insert(&mut self, id: Id, plaintext: Zeroizing<String>, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        if self.entries.insert(id, plaintext).is_some() {
+            self.touch(id);
+        } else {
+            self.order.push_back(id);
+        }
+        while self.order.len() > capacity {
+            if let Some(old) = self.order.pop_front() {
+                // Dropping the entry zeroizes it
+                self.entries.remove(&old);
+            }
+        }
+    }
+
+    fn touch(&mut self, id: Id) {
+        if let Some(pos) = self.order.iter().position(|i| *i == id) {
+            self.order.remove(pos);
+        }
+        self.order.push_back(id);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// Decrypted DM content, keyed by event id
+#[derive(Default)]
+pub struct DmCache {
+    inner: Mutex<DmCacheInner>,
+}
+
+impl DmCache {
+    pub(crate) fn new() -> DmCache {
+        DmCache::default()
+    }
+
+    /// Decrypt the contents of a NIP-04 DM, using the cached plaintext if we have it
+    pub fn decrypt_event_contents(&self, event: &Event) -> Result<String, Error> {
+        if let Some(plaintext) = self.inner.lock().get(event.id) {
+            return Ok((*plaintext).clone());
+        }
+        let plaintext = GLOBALS.identity.decrypt_event_contents(event)?;
+        self.insert(event.id, Zeroizing::new(plaintext.clone()));
+        Ok(plaintext)
+    }
+
+    /// Unwrap a giftwrap, using the cached rumor if we have it
+    pub fn unwrap_giftwrap(&self, event: &Event) -> Result<Rumor, Error> {
+        if let Some(json) = self.inner.lock().get(event.id) {
+            if let Ok(rumor) = serde_json::from_str::<Rumor>(&json) {
+                return Ok(rumor);
+            }
+        }
+        let rumor = GLOBALS.identity.unwrap_giftwrap(event)?;
+        if let Ok(json) = serde_json::to_string(&rumor) {
+            self.insert(event.id, Zeroizing::new(json));
+        }
+        Ok(rumor)
+    }
+
+    /// Forget all decrypted content, zeroizing it
+    pub fn clear(&self) {
+        self.inner.lock().clear();
+    }
+
+    fn insert(&self, id: Id, plaintext: Zeroizing<String>) {
+        let capacity = GLOBALS.db().read_setting_dm_cache_size();
+        self.inner.lock().insert(id, plaintext, capacity);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn id(n: u8) -> Id {
+        Id([n; 32])
+    }
+
+    #[test]
+    fn test_dm_cache_eviction() {
+        let mut cache = DmCacheInner::default();
+        cache.insert(id(1), Zeroizing::new("one".to_owned()), 2);
+        cache.insert(id(2), Zeroizing::new("two".to_owned()), 2);
+
+        // Using 1 makes 2 the least recently used
+        assert_eq!(cache.get(id(1)).as_deref().map(|s| s.as_str()), Some("one"));
+        cache.insert(id(3), Zeroizing::new("three".to_owned()), 2);
+        assert!(cache.get(id(2)).is_none());
+        assert!(cache.get(id(1)).is_some());
+        assert!(cache.get(id(3)).is_some());
+
+        // A zero capacity caches nothing
+        cache.insert(id(4), Zeroizing::new("four".to_owned()), 0);
+        assert!(cache.get(id(4)).is_none());
+
+        cache.clear();
+        assert!(cache.get(id(1)).is_none());
+        assert!(cache.order.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::globals::GLOBALS;
+use crate::relay::{self, Relay};
 use nostr_types::{Event, EventKind, PublicKey, Unixtime};
 use sha2::Digest;
 
@@ -10,34 +11,55 @@ use sha2::Digest;
 // The pubkey of the gossip user is not included. If they send themselves
 // a note, that channel has an empty vec.
 //
-// The second field indicates whether or not we can use NIP-17
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+// The second field indicates whether or not we can use NIP-17. It is not part of
+// the channel's identity: NIP-04 and NIP-17 messages between the same people are
+// one conversation, even if someone's DM relays turn up while we are reading it.
+#[derive(Debug, Clone)]
 pub struct DmChannel(Vec<PublicKey>, bool);
 
+impl PartialEq for DmChannel {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for DmChannel {}
+
+impl PartialOrd for DmChannel {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DmChannel {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl std::hash::Hash for DmChannel {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
 impl DmChannel {
     pub fn new(public_keys: &[PublicKey]) -> DmChannel {
         let mut vec = public_keys.to_owned();
         vec.sort();
         vec.dedup();
 
-        let can_use_nip17 = {
-            if let Some(pk) = GLOBALS.db().read_setting_public_key() {
-                if !matches!(GLOBALS.db().has_dm_relays(pk), Ok(true)) {
-                    false
-                } else {
-                    let mut others_can_use_nip17 = true;
-                    for pk in &vec {
-                        if !matches!(GLOBALS.db().has_dm_relays(*pk), Ok(true)) {
-                            others_can_use_nip17 = false;
-                            break;
-                        }
-                    }
-                    others_can_use_nip17
-                }
-            } else {
-                false
-            }
-        };
+        // Giftwraps go to a person's DM relays, or to their INBOX relays if they
+        // have not listed any (see relay::get_dm_inboxes()), so we can use NIP-17
+        // as long as everybody has one or the other. Our own copy goes to the
+        // relays we have marked DM, or INBOX if none are.
+        let can_use_nip17 = GLOBALS.db().read_setting_public_key().is_some()
+            && [Relay::DM, Relay::INBOX].iter().any(|usage| {
+                matches!(Relay::choose_relay_urls(*usage, |_| true), Ok(urls) if !urls.is_empty())
+            })
+            && vec.iter().all(|pk| {
+                matches!(relay::get_dm_inboxes(*pk), Ok(urls) if !urls.is_empty())
+            });
 
         DmChannel(vec, can_use_nip17)
     }
@@ -97,7 +119,7 @@ impl DmChannel {
                 Some(Self::new(&people))
             }
         } else if event.kind == EventKind::GiftWrap {
-            if let Ok(rumor) = GLOBALS.identity.unwrap_giftwrap(event) {
+            if let Ok(rumor) = GLOBALS.dm_cache.unwrap_giftwrap(event) {
                 let rumor_event = rumor.into_event_with_bad_signature();
                 let mut people: Vec<PublicKey> =
                     rumor_event.people().iter().map(|(pk, _, _)| *pk).collect();
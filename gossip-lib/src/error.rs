@@ -1,5 +1,6 @@
 use crate::comms::{ToMinionMessage, ToOverlordMessage};
 use crate::people::PersonList;
+use crate::relay::RelayRejection;
 use nostr_types::RelayUrl;
 
 /// Error kinds that can occur in gossip-lib
@@ -16,6 +17,7 @@ pub enum ErrorKind {
     EmptyJob,
     EngageDisallowed,
     EngagePending,
+    EngageWaitingForKey,
     EventNotFound,
     FromUtf8(std::string::FromUtf8Error),
     General(String),
@@ -27,6 +29,8 @@ pub enum ErrorKind {
     Lmdb(heed::Error),
     MaxRelaysReached,
     MpscSend(tokio::sync::mpsc::error::SendError<ToOverlordMessage>),
+    Nip05DnsFailed(String),
+    Nip05HttpFailed(String),
     Nip05KeyNotFound,
     Nip46CommandMissingId,
     Nip46CommandNotJsonObject,
@@ -64,14 +68,16 @@ pub enum ErrorKind {
     Offline,
     ParseInt(std::num::ParseIntError),
     ParseBool(std::str::ParseBoolError),
+    PowCancelled,
     RecordIsNotNewable,
     Regex(regex::Error),
-    RelayRejectedUs,
+    RelayRejectedUs(RelayRejection, String),
     ReqwestHttpError(reqwest::Error),
     SerdeJson(serde_json::Error),
     ShuttingDown,
     SliceError(std::array::TryFromSliceError),
     Speedy(speedy::Error),
+    StorageCorrupted,
     Svg(usvg::Error),
     TagNotIndexed(String),
     Timeout(tokio::time::error::Elapsed),
@@ -120,6 +126,7 @@ impl std::fmt::Display for Error {
             EmptyJob => write!(f, "relay job is empty"),
             EngageDisallowed => write!(f, "relay is disallowed"),
             EngagePending => write!(f, "relay approval is pending"),
+            EngageWaitingForKey => write!(f, "relay credential is waiting for the key to be unlocked"),
             EventNotFound => write!(f, "Event not found"),
             FromUtf8(e) => write!(f, "UTF-8 error: {e}"),
             GroupDmsNotSupported => write!(f, "Group DMs are not supported under NIP-04"),
@@ -128,12 +135,18 @@ impl std::fmt::Display for Error {
             JoinError(e) => write!(f, "Task join error: {e}"),
             KeySizeWrong => write!(f, "Key size is wrong"),
             KeyInvalid => write!(f, "Key is invalid"),
+            Lmdb(heed::Error::Mdb(heed::MdbError::MapFull)) => write!(
+                f,
+                "LMDB: the database map is full and could not be enlarged. Restart gossip and it will be."
+            ),
             Lmdb(e) => write!(f, "LMDB: {e}"),
             MaxRelaysReached => write!(
                 f,
                 "Maximum relay connections reached, will not connect to another"
             ),
             MpscSend(e) => write!(f, "Error sending mpsc: {e}"),
+            Nip05DnsFailed(s) => write!(f, "NIP-05 domain could not be resolved: {s}"),
+            Nip05HttpFailed(s) => write!(f, "NIP-05 fetch failed: {s}"),
             Nip05KeyNotFound => write!(f, "NIP-05 public key not found"),
             Nip46CommandMissingId => write!(f, "NIP-46 command missing ID"),
             Nip46CommandNotJsonObject => write!(f, "NIP-46 command not a json object"),
@@ -173,14 +186,19 @@ impl std::fmt::Display for Error {
             Offline => write!(f, "Offline"),
             ParseInt(e) => write!(f, "Bad integer: {e}"),
             ParseBool(e) => write!(f, "Bad bool: {e}"),
+            PowCancelled => write!(f, "Proof of work cancelled"),
             RecordIsNotNewable => write!(f, "Record is not newable"),
             Regex(e) => write!(f, "Regex: {e}"),
-            RelayRejectedUs => write!(f, "Relay rejected us."),
+            RelayRejectedUs(r, msg) => write!(f, "Relay rejected us ({r}): {msg}"),
             ReqwestHttpError(e) => write!(f, "HTTP (reqwest) error: {e}"),
             SerdeJson(e) => write!(f, "SerdeJson Error: {e}"),
             ShuttingDown => write!(f, "Shutting down"),
             SliceError(e) => write!(f, "Slice: {e}"),
             Speedy(e) => write!(f, "Speedy: {e}"),
+            StorageCorrupted => write!(
+                f,
+                "The local database is corrupted. Run `gossip recover_storage` to repair it (a copy of the corrupted one is kept)"
+            ),
             Svg(e) => write!(f, "SVG: {e}"),
             TagNotIndexed(s) => write!(f, "Tag not indexed: {s}"),
             Timeout(e) => write!(f, "Timeout: {e}"),
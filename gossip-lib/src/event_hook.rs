@@ -0,0 +1,122 @@
+//! Hooks for integrators to react to or filter incoming events
+//!
+//! Hooks are registered with [register_event_hook](crate::Globals::register_event_hook)
+//! and every new event that would be stored is passed through them, in the order they
+//! were registered, just before it is saved. Duplicates and ephemeral events are not
+//! passed through hooks.
+//!
+//! The first hook to return [HookAction::Drop] stops the event; later hooks do not see
+//! it. A hook that returns [HookAction::ModifyTags] changes the tags that later hooks
+//! see. The final tags are stored apart from the event, which is stored unchanged
+//! so that it still matches its signature, and can be read back with
+//! [read_hook_tags](crate::Storage::read_hook_tags). When the note is shown, its
+//! content warning and labels come from these tags. A hook that panics is logged and
+//! treated as [HookAction::Keep], it does not stop processing.
+//!
+//! Hooks run while event processing holds a lock on the hook list, so a hook must not
+//! register another hook.
+
+use nostr_types::{Event, PublicKey, Tag};
+use std::borrow::Cow;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// What an event hook wants done with an event
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookAction {
+    /// Continue processing the event as is
+    Keep,
+
+    /// Do not store or process the event any further
+    Drop,
+
+    /// Replace the event's tags, e.g. to add a label.
+    ///
+    /// The signed event itself is not changed. The new tags are stored next to it.
+    ModifyTags(Vec<Tag>),
+}
+
+/// An event hook
+pub type EventHook = Box<dyn Fn(&Event) -> HookAction + Send + Sync>;
+
+/// A hook that drops every event authored by `pubkey`
+pub fn drop_author_hook(pubkey: PublicKey) -> EventHook {
+    Box::new(move |event: &Event| {
+        if event.pubkey == pubkey {
+            HookAction::Drop
+        } else {
+            HookAction::Keep
+        }
+    })
+}
+
+/// Run the hooks in order. Returns None if the event was dropped, otherwise
+/// the event, which is owned only if a hook modified it.
+pub(crate) fn run_event_hooks<'a>(hooks: &[EventHook], event: &'a Event) -> Option<Cow<'a, Event>> {
+    let mut event = Cow::Borrowed(event);
+    for (n, hook) in hooks.iter().enumerate() {
+        match catch_unwind(AssertUnwindSafe(|| hook(&event))) {
+            Ok(HookAction::Keep) => {}
+            Ok(HookAction::Drop) => return None,
+            Ok(HookAction::ModifyTags(tags)) => event.to_mut().tags = tags,
+            Err(_) => {
+                tracing::error!(
+                    "Event hook {} panicked on event {}",
+                    n,
+                    event.id.as_hex_string()
+                );
+            }
+        }
+    }
+    Some(event)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::signed_event;
+    use nostr_types::{EventKind, KeySigner, Signer};
+
+    fn text_note(signer: &KeySigner) -> Event {
+        signed_event(signer, 1700000000, EventKind::TextNote, vec![], "hello")
+    }
+
+    #[test]
+    fn test_drop_author_hook() {
+        let spammer = KeySigner::generate("", 1).unwrap();
+        let friend = KeySigner::generate("", 1).unwrap();
+        let hooks = vec![drop_author_hook(spammer.public_key())];
+
+        assert!(run_event_hooks(&hooks, &text_note(&spammer)).is_none());
+        assert!(matches!(
+            run_event_hooks(&hooks, &text_note(&friend)),
+            Some(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_event_hook_order_and_isolation() {
+        let author = KeySigner::generate("", 1).unwrap();
+        let event = text_note(&author);
+        let label = Tag::new(&["l", "checked"]);
+
+        let hooks: Vec<EventHook> = vec![
+            Box::new(|_: &Event| panic!("broken hook")),
+            {
+                let label = label.clone();
+                Box::new(move |_: &Event| HookAction::ModifyTags(vec![label.clone()]))
+            },
+            // Sees the tags set by the previous hook
+            Box::new(|e: &Event| {
+                if e.tags.is_empty() {
+                    HookAction::Drop
+                } else {
+                    HookAction::Keep
+                }
+            }),
+        ];
+
+        let output = run_event_hooks(&hooks, &event).unwrap();
+        assert_eq!(output.tags, vec![label]);
+        assert_eq!(output.id, event.id);
+    }
+}
@@ -20,6 +20,8 @@ pub enum FeedKind {
     DmChat(DmChannel),
     Global,
     Relay(RelayUrl),
+    Hashtag(String), // normalized, see normalize_hashtag()
+    Custom(String),  // name of a stored CustomFeed
 }
 
 impl std::fmt::Display for FeedKind {
@@ -40,6 +42,8 @@ impl std::fmt::Display for FeedKind {
             FeedKind::DmChat(channel) => write!(f, "{}", channel.name()),
             FeedKind::Global => write!(f, "Global"),
             FeedKind::Relay(relayurl) => write!(f, "{}", relayurl),
+            FeedKind::Hashtag(hashtag) => write!(f, "#{}", hashtag),
+            FeedKind::Custom(name) => write!(f, "{}", name),
         }
     }
 }
@@ -56,6 +60,8 @@ impl FeedKind {
             Self::DmChat(_) => "dmchat".to_owned(),
             Self::Global => "global".to_owned(),
             Self::Relay(relayurl) => format!("relay {}", relayurl),
+            Self::Hashtag(hashtag) => format!("hashtag {}", hashtag),
+            Self::Custom(name) => format!("custom {}", name),
         }
     }
 
@@ -69,6 +75,8 @@ impl FeedKind {
             Self::DmChat(_) => false, // always full
             Self::Global => true,
             Self::Relay(_) => true,
+            Self::Hashtag(_) => true,
+            Self::Custom(_) => true,
         }
     }
 
@@ -80,3 +88,14 @@ impl FeedKind {
         }
     }
 }
+
+/// Normalize a hashtag as typed or as found in content: strip leading '#'s and
+/// lowercase it. Returns None if nothing usable remains.
+pub fn normalize_hashtag(hashtag: &str) -> Option<String> {
+    let hashtag = hashtag.trim().trim_start_matches('#');
+    if hashtag.is_empty() || hashtag.contains(char::is_whitespace) {
+        None
+    } else {
+        Some(hashtag.to_lowercase())
+    }
+}
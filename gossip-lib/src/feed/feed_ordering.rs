@@ -0,0 +1,160 @@
+use nostr_types::{Event, EventReference, Id, Unixtime};
+use std::collections::HashMap;
+
+/// How the events of a feed are ordered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FeedOrdering {
+    /// Newest first
+    #[default]
+    ReverseChronological,
+
+    /// Replies are clustered under their root, oldest reply first. Threads are
+    /// ordered by the time of their root, newest first.
+    ThreadGrouped,
+}
+
+impl FeedOrdering {
+    /// All orderings, for choosing from
+    pub fn all() -> &'static [FeedOrdering] {
+        &[
+            FeedOrdering::ReverseChronological,
+            FeedOrdering::ThreadGrouped,
+        ]
+    }
+
+    /// A name to show the user
+    pub fn name(&self) -> &'static str {
+        match *self {
+            FeedOrdering::ReverseChronological => "Newest first",
+            FeedOrdering::ThreadGrouped => "Grouped by thread",
+        }
+    }
+
+    /// The value stored in the `feed_ordering` setting
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            FeedOrdering::ReverseChronological => "reverse_chronological",
+            FeedOrdering::ThreadGrouped => "thread_grouped",
+        }
+    }
+
+    /// Parse the `feed_ordering` setting, defaulting if it is not recognized
+    pub fn from_setting(s: &str) -> FeedOrdering {
+        FeedOrdering::all()
+            .iter()
+            .find(|o| o.as_str() == s)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Order the events. `root_time` looks up when a thread root that is not
+    /// among the events was created, if we have it.
+    pub(crate) fn order<F>(&self, mut events: Vec<Event>, root_time: F) -> Vec<Id>
+    where
+        F: Fn(Id) -> Option<Unixtime>,
+    {
+        // Stable, so events with the same time keep the order storage gave them
+        events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        if *self == FeedOrdering::ReverseChronological {
+            return events.iter().map(|e| e.id).collect();
+        }
+
+        // Group by root, in order of each thread's newest event
+        let mut groups: Vec<(Id, Vec<&Event>)> = Vec::new();
+        let mut index: HashMap<Id, usize> = HashMap::new();
+        for event in events.iter() {
+            let root = match event.replies_to_root() {
+                Some(EventReference::Id { id, .. }) => id,
+                _ => event.id,
+            };
+            match index.get(&root) {
+                Some(&i) => groups[i].1.push(event),
+                None => {
+                    index.insert(root, groups.len());
+                    groups.push((root, vec![event]));
+                }
+            }
+        }
+
+        // Order threads by the time of their root. If we don't have the root,
+        // use the earliest event we have in the thread.
+        let mut groups: Vec<(Unixtime, Id, Vec<&Event>)> = groups
+            .drain(..)
+            .map(|(root, members)| {
+                let time = match members.iter().find(|e| e.id == root) {
+                    Some(e) => e.created_at,
+                    None => root_time(root).unwrap_or(members[members.len() - 1].created_at),
+                };
+                (time, root, members)
+            })
+            .collect();
+        groups.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let mut output: Vec<Id> = Vec::with_capacity(events.len());
+        for (_, root, mut members) in groups.drain(..) {
+            // Root first, then replies oldest first
+            members.reverse();
+            if let Some(pos) = members.iter().position(|e| e.id == root) {
+                let r = members.remove(pos);
+                members.insert(0, r);
+            }
+            output.extend(members.iter().map(|e| e.id));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::signed_event;
+    use nostr_types::{EventKind, KeySigner, ParsedTag};
+
+    #[test]
+    fn test_feed_ordering() {
+        let signer = KeySigner::generate("", 1).unwrap();
+        let note = |created_at: i64, root: Option<Id>| {
+            let tags = match root {
+                Some(id) => vec![ParsedTag::Event {
+                    id,
+                    recommended_relay_url: None,
+                    marker: Some("root".to_owned()),
+                    author_pubkey: None,
+                }
+                .into_tag()],
+                None => vec![],
+            };
+            signed_event(&signer, created_at, EventKind::TextNote, tags, "")
+        };
+
+        let root1 = note(100, None);
+        let root2 = note(200, None);
+        let events = vec![
+            root1.clone(),
+            root2.clone(),
+            note(300, Some(root1.id)),
+            note(400, Some(root2.id)),
+            note(500, Some(root1.id)),
+            note(600, Some(Id([9; 32]))), // root not in the feed
+        ];
+        let ids: Vec<Id> = events.iter().map(|e| e.id).collect();
+        let id = |n: usize| ids[n - 1];
+
+        assert_eq!(
+            FeedOrdering::ReverseChronological.order(events.clone(), |_| None),
+            vec![id(6), id(5), id(4), id(3), id(2), id(1)]
+        );
+
+        assert_eq!(
+            FeedOrdering::ThreadGrouped.order(events.clone(), |_| None),
+            vec![id(6), id(2), id(4), id(1), id(3), id(5)]
+        );
+
+        // A known root time places its thread
+        assert_eq!(
+            FeedOrdering::ThreadGrouped.order(events, |_| Some(Unixtime(50))),
+            vec![id(2), id(4), id(1), id(3), id(5), id(6)]
+        );
+    }
+}
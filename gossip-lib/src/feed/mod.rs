@@ -1,13 +1,18 @@
 mod feed_kind;
-pub use feed_kind::FeedKind;
+pub use feed_kind::{normalize_hashtag, FeedKind};
+
+mod feed_ordering;
+pub use feed_ordering::FeedOrdering;
 
 use crate::comms::{ToMinionMessage, ToMinionPayload, ToMinionPayloadDetail, ToOverlordMessage};
 use crate::error::{Error, ErrorKind};
 use crate::filter_set::FilterSet;
 use crate::globals::GLOBALS;
 use crate::people::PersonList;
-use dashmap::DashMap;
-use nostr_types::{Event, EventKind, EventReference, Filter, Id, NAddr, PublicKey, Unixtime};
+use dashmap::{DashMap, DashSet};
+use nostr_types::{
+    Event, EventKind, EventReference, Filter, Id, NAddr, PublicKey, RelayUrl, Unixtime,
+};
 use parking_lot::RwLock;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -33,12 +38,20 @@ pub struct Feed {
     inbox_is_indirect: AtomicBool,
     feed_anchors: DashMap<String, Unixtime>,
 
+    // The oldest `until` we asked relays to load older events before, per feed,
+    // and the feeds where relays had nothing older than that
+    older_requested: DashMap<String, Unixtime>,
+    older_exhausted: DashSet<String>,
+
     // We only recompute the feed at specified intervals (or when they switch)
     interval_ms: Arc<RwLock<u32>>,
     last_computed: Arc<RwLock<Option<Instant>>>,
 
     thread_parent: Arc<RwLock<Option<Id>>>,
 
+    // The display title of the thread parent, once we have it
+    thread_title: Arc<RwLock<Option<String>>>,
+
     last_volatile_feed: Arc<RwLock<Option<FeedKind>>>,
 }
 
@@ -58,18 +71,20 @@ impl Feed {
             current_inbox_events: Arc::new(RwLock::new(Vec::new())),
             inbox_is_indirect: AtomicBool::new(false),
             feed_anchors: DashMap::new(),
+            older_requested: DashMap::new(),
+            older_exhausted: DashSet::new(),
             interval_ms: Arc::new(RwLock::new(10000)), // Every 10 seconds, until we load from settings
             last_computed: Arc::new(RwLock::new(None)),
             thread_parent: Arc::new(RwLock::new(None)),
+            thread_title: Arc::new(RwLock::new(None)),
             last_volatile_feed: Arc::new(RwLock::new(None)),
         }
     }
 
     /// This changes the window where the feed pulls its events from by backing up the
     /// anchor time to the time of the earliest event currently in the feed.
-    //
-    /// This doesn't deal with minion subscriptions.
-    pub(crate) fn load_more(&self) -> Result<Unixtime, Error> {
+    /// See [load_older](Feed::load_older).
+    pub(crate) fn load_more(&self) -> Result<Option<Unixtime>, Error> {
         let anchor_key = self.current_feed_kind.read_arc().anchor_key();
 
         let current_events = if matches!(self.get_feed_kind(), FeedKind::Inbox(_)) {
@@ -79,16 +94,28 @@ impl Feed {
         };
 
         // Load the timestamp of the earliest event in the feed so far
-        if let Some(earliest_id) = current_events.read_arc().iter().next_back() {
-            let earliest_event = GLOBALS.db().read_event(*earliest_id)?;
+        let earliest_id = if FeedOrdering::from_setting(&GLOBALS.db().read_setting_feed_ordering())
+            == FeedOrdering::ReverseChronological
+            || matches!(self.get_feed_kind(), FeedKind::Inbox(_))
+        {
+            current_events.read_arc().iter().next_back().copied()
+        } else {
+            // Grouped feeds are not in time order, look for it
+            let mut earliest: Option<(Unixtime, Id)> = None;
+            for id in current_events.read_arc().iter() {
+                if let Some(event) = GLOBALS.db().read_event(*id)? {
+                    if earliest.map(|(t, _)| event.created_at < t).unwrap_or(true) {
+                        earliest = Some((event.created_at, *id));
+                    }
+                }
+            }
+            earliest.map(|(_, id)| id)
+        };
+        if let Some(earliest_id) = earliest_id {
+            let earliest_event = GLOBALS.db().read_event(earliest_id)?;
             if let Some(event) = earliest_event {
                 // Move the anchor back to the earliest event we have so far
-                self.feed_anchors.insert(anchor_key, event.created_at);
-
-                // Recompute now to get the storage data
-                self.sync_recompute();
-
-                Ok(event.created_at)
+                self.load_older(event.created_at)
             } else {
                 Err(ErrorKind::LoadMoreFailed.into())
             }
@@ -97,6 +124,54 @@ impl Feed {
         }
     }
 
+    /// Page the current feed backwards by moving its window back to `before`, which
+    /// pulls more events from local storage.
+    ///
+    /// Returns the time before which relays should be asked for older events, or None
+    /// if they should not be asked: because that window was already requested, or
+    /// because relays returned nothing older last time.
+    ///
+    /// This doesn't deal with minion subscriptions, see
+    /// [Overlord::load_older](crate::Overlord::load_older).
+    pub fn load_older(&self, before: Unixtime) -> Result<Option<Unixtime>, Error> {
+        let anchor_key = self.current_feed_kind.read_arc().anchor_key();
+
+        // Only ever move the anchor back
+        let anchor = match self.feed_anchors.get(&anchor_key) {
+            Some(current) if *current < before => *current,
+            _ => before,
+        };
+        self.feed_anchors.insert(anchor_key.clone(), anchor);
+
+        // Recompute now to get the storage data
+        self.sync_recompute();
+
+        if self.older_exhausted.contains(&anchor_key) {
+            return Ok(None);
+        }
+
+        if let Some(requested) = self.older_requested.get(&anchor_key).map(|r| *r) {
+            if anchor >= requested {
+                // The feed did not get any older since we last asked. If the relays
+                // are done answering, they have nothing older.
+                if GLOBALS.loading_more.load(Ordering::SeqCst) == 0 {
+                    self.older_exhausted.insert(anchor_key);
+                }
+                return Ok(None);
+            }
+        }
+
+        self.older_requested.insert(anchor_key, anchor);
+        Ok(Some(anchor))
+    }
+
+    /// Whether relays had no older events for the current feed the last time we
+    /// asked them
+    pub fn older_exhausted(&self) -> bool {
+        let anchor_key = self.current_feed_kind.read_arc().anchor_key();
+        self.older_exhausted.contains(&anchor_key)
+    }
+
     pub(crate) fn current_anchor(&self) -> Unixtime {
         let anchor_key = self.current_feed_kind.read_arc().anchor_key();
         match self.feed_anchors.get(&anchor_key) {
@@ -162,6 +237,19 @@ impl Feed {
                 },
             });
         }
+
+        // Stop listening to Hashtag events. Hashtag feeds all share one subscription
+        // handle, so this is done even when switching from one hashtag to another.
+        let _ = GLOBALS.to_minions.send(ToMinionMessage {
+            target: "all".to_string(),
+            payload: ToMinionPayload {
+                job_id: 0,
+                detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::HashtagFeedFuture {
+                    hashtag: "".to_owned(),  // does not matter
+                    anchor: Unixtime::now(), // does not matter
+                }),
+            },
+        });
     }
 
     // NOTE: This is called by synchronous UI code, so it doesn't need to be re-entrant.
@@ -198,6 +286,7 @@ impl Feed {
         } else {
             None
         };
+        self.update_thread_title();
 
         // Clear the volatile storage if the feed is volatile
         // and different from the last time
@@ -210,6 +299,10 @@ impl Feed {
             *self.last_volatile_feed.write() = Some(feed_kind.clone());
         }
 
+        // Give relays another chance to have older events for this feed
+        self.older_requested.remove(&feed_kind.anchor_key());
+        self.older_exhausted.remove(&feed_kind.anchor_key());
+
         // Set the feed kind
         *self.current_feed_kind.write_arc() = feed_kind;
 
@@ -257,6 +350,11 @@ impl Feed {
                     .to_overlord
                     .send(ToOverlordMessage::SetRelayFeed(relay_url.clone(), anchor));
             }
+            FeedKind::Hashtag(hashtag) => {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::SubscribeHashtagFeed(hashtag.clone()));
+            }
             _ => (),
         }
     }
@@ -328,6 +426,26 @@ impl Feed {
     /// parent to the highest locally available one (or the event if it is not local)
     pub(crate) fn set_thread_parent(&self, id: Id) {
         *self.thread_parent.write_arc() = Some(id);
+        self.update_thread_title();
+    }
+
+    /// Get the display title of the current thread feed, taken from the `subject`
+    /// or `title` tag of its parent, or else the first line of its content.
+    /// This is None until the parent event has been loaded.
+    pub fn get_thread_title(&self) -> Option<String> {
+        self.thread_title.read_arc().clone()
+    }
+
+    fn update_thread_title(&self) {
+        let maybe_tp = *self.thread_parent.read_arc();
+        let title = match maybe_tp {
+            Some(tp) => match GLOBALS.db().read_event(tp) {
+                Ok(Some(event)) => thread_display_title(&event),
+                _ => None,
+            },
+            None => None,
+        };
+        *self.thread_title.write_arc() = title;
     }
 
     /// Are we switching feeds?
@@ -398,6 +516,7 @@ impl Feed {
         };
 
         let anchor: Unixtime = self.current_anchor();
+        let ordering = FeedOrdering::from_setting(&GLOBALS.db().read_setting_feed_ordering());
 
         match current_feed_kind {
             FeedKind::List(list, with_replies) => {
@@ -416,7 +535,7 @@ impl Feed {
                 let events = if filter.authors.is_empty() {
                     Default::default()
                 } else {
-                    Self::load_event_range(anchor, filter, with_replies, |_| true).await?
+                    Self::load_event_range(anchor, filter, with_replies, ordering, |_| true).await?
                 };
 
                 *self.current_feed_events.write_arc() = events;
@@ -438,6 +557,9 @@ impl Feed {
                         }
                     }
                 }
+
+                // The parent may have arrived, or changed
+                self.update_thread_title();
             }
             FeedKind::Person(person_pubkey) => {
                 let filter = {
@@ -447,7 +569,24 @@ impl Feed {
                     filter
                 };
 
-                let events = Self::load_event_range(anchor, filter, true, |_| true).await?;
+                let events =
+                    Self::load_event_range(anchor, filter, true, ordering, |_| true).await?;
+
+                *self.current_feed_events.write_arc() = events;
+            }
+            FeedKind::Custom(name) => {
+                let events = match GLOBALS.db().read_custom_feed(&name)? {
+                    Some(custom_feed) if !custom_feed.pubkeys.is_empty() => {
+                        let filter = {
+                            let mut filter = Filter::new();
+                            filter.authors = custom_feed.pubkeys.clone();
+                            filter.kinds = custom_feed.event_kinds();
+                            filter
+                        };
+                        Self::load_event_range(anchor, filter, true, ordering, |_| true).await?
+                    }
+                    _ => Default::default(),
+                };
 
                 *self.current_feed_events.write_arc() = events;
             }
@@ -455,9 +594,43 @@ impl Feed {
                 let ids = GLOBALS.db().dm_events(&channel)?;
                 *self.current_feed_events.write_arc() = ids;
             }
+            FeedKind::Hashtag(hashtag) => {
+                // 't' tags are not in the tag index, but we keep a hashtag index
+                let dismissed = GLOBALS.dismissed.read().await.clone();
+                let muted = content_muted_relays();
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let kinds = feed_displayable_event_kinds(false);
+
+                let mut events: Vec<Event> = Vec::new();
+                for id in GLOBALS.db().get_event_ids_with_hashtag(&hashtag)? {
+                    if let Some(event) = GLOBALS.db().read_event(id)? {
+                        if kinds.contains(&event.kind)
+                            && basic_screen(&event, true, &dismissed, &muted)
+                        {
+                            events.push(event);
+                        }
+                    }
+                }
+                let mut events: Vec<Event> = events.into_iter().map(by_sort_time).collect();
+                events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+                // Everything after the anchor, plus a chunk before it
+                let after = events.iter().take_while(|e| e.created_at >= anchor).count();
+                events.truncate(after + limit);
+
+                *self.current_feed_events.write_arc() = events.iter().map(|e| e.id).collect();
+            }
             FeedKind::Global | FeedKind::Relay(_) => {
                 let dismissed = GLOBALS.dismissed.read().await.clone();
 
+                // Looking at a relay's own feed shows its content even if it is muted
+                // or distrusted
+                let (muted, distrusted) = if matches!(current_feed_kind, FeedKind::Global) {
+                    (content_muted_relays(), distrusted_relays())
+                } else {
+                    (vec![], vec![])
+                };
+
                 let screen_spam = {
                     if GLOBALS.db().read_setting_apply_spam_filter_on_global() {
                         |event: &Event| {
@@ -472,7 +645,11 @@ impl Feed {
                     }
                 };
 
-                let screen = |e: &Event| basic_screen(e, true, &dismissed) && screen_spam(e);
+                let screen = |e: &Event| {
+                    basic_screen(e, true, &dismissed, &muted)
+                        && screen_spam(e)
+                        && !from_stranger_on_distrusted_relays(e, &distrusted)
+                };
 
                 let events = GLOBALS.db().load_volatile_events(screen);
                 *self.current_feed_events.write_arc() = events.iter().map(|e| e.id).collect();
@@ -514,9 +691,12 @@ impl Feed {
                 }
             };
 
+            let distrusted = distrusted_relays();
+
             let screen = |e: &Event| {
                 screen_spam(e)
                     && e.pubkey != my_pubkey
+                    && !from_stranger_on_distrusted_relays(e, &distrusted)
                     && (indirect // don't screen further, keep all the 'p' tags
                         || (
                             // Either it is a direct reply
@@ -533,7 +713,14 @@ impl Feed {
                         ))
             };
 
-            let events = Self::load_event_range(anchor, filter, true, screen).await?;
+            let events = Self::load_event_range(
+                anchor,
+                filter,
+                true,
+                FeedOrdering::ReverseChronological,
+                screen,
+            )
+            .await?;
             *self.current_inbox_events.write_arc() = events;
         }
 
@@ -551,16 +738,18 @@ impl Feed {
         since: Unixtime,
         filter: Filter,
         include_replies: bool,
+        ordering: FeedOrdering,
         screen: F,
     ) -> Result<Vec<Id>, Error>
     where
         F: Fn(&Event) -> bool,
     {
-        let now = Unixtime::now();
         let limit = GLOBALS.db().read_setting_load_more_count() as usize;
         let dismissed = GLOBALS.dismissed.read().await.clone();
+        let muted = content_muted_relays();
 
-        let outer_screen = |e: &Event| basic_screen(e, include_replies, &dismissed) && screen(e);
+        let outer_screen =
+            |e: &Event| basic_screen(e, include_replies, &dismissed, &muted) && screen(e);
 
         let mut before_filter = filter;
         let mut after_filter = before_filter.clone();
@@ -568,8 +757,10 @@ impl Feed {
         before_filter.until = Some(since - Duration::from_secs(1));
         before_filter.limit = Some(limit);
 
+        // No `until`, events from the future may have been clamped to an earlier time.
+        // basic_screen() leaves out the ones that are still in the future.
         after_filter.since = Some(since);
-        after_filter.until = Some(now);
+        after_filter.until = None;
 
         let events = GLOBALS
             .db()
@@ -579,25 +770,105 @@ impl Feed {
             .db()
             .find_events_by_filter(&before_filter, outer_screen)?;
 
-        Ok(events
-            .iter()
-            .map(|e| e.id)
-            .chain(events2.iter().map(|e| e.id))
-            .collect())
+        let events: Vec<Event> = events
+            .into_iter()
+            .chain(events2)
+            .map(by_sort_time)
+            .collect();
+        Ok(ordering.order(events, |id| {
+            GLOBALS
+                .db()
+                .read_event(id)
+                .ok()
+                .flatten()
+                .and_then(|e| GLOBALS.db().event_sort_time(&e).ok())
+        }))
     }
 }
 
 #[inline]
-fn basic_screen(e: &Event, include_replies: bool, dismissed: &[Id]) -> bool {
+fn basic_screen(e: &Event, include_replies: bool, dismissed: &[Id], muted: &[RelayUrl]) -> bool {
     let now = Unixtime::now();
 
-    e.created_at <= now
-        && (include_replies || e.replies_to().is_none())
+    GLOBALS.db().event_sort_time(e).is_ok_and(|t| t <= now)
+        && (include_replies || !replies_to_others(e))
         && e.kind != EventKind::EncryptedDirectMessage
         && e.kind != EventKind::DmChat
         && e.kind != EventKind::GiftWrap
         && !dismissed.contains(&e.id)
         && !e.is_annotation()
+        && !seen_only_on(e.id, muted)
+}
+
+// Whether an event is a reply to somebody else. Replies that only continue the
+// author's own thread are kept in feeds that leave replies out.
+fn replies_to_others(e: &Event) -> bool {
+    let by_author = |reference: EventReference| match reference {
+        EventReference::Id { id, .. } => {
+            matches!(GLOBALS.db().read_event(id), Ok(Some(parent)) if parent.pubkey == e.pubkey)
+        }
+        EventReference::Addr(NAddr { author, .. }) => author == e.pubkey,
+    };
+
+    match e.replies_to() {
+        None => false,
+        Some(parent) => !by_author(parent) || !e.replies_to_root().map(by_author).unwrap_or(true),
+    }
+}
+
+// Relays whose content the user has muted
+fn content_muted_relays() -> Vec<RelayUrl> {
+    match GLOBALS.db().filter_relays(|r| r.mute_content) {
+        Ok(relays) => relays.into_iter().map(|r| r.url).collect(),
+        Err(e) => {
+            tracing::error!("{}", e);
+            vec![]
+        }
+    }
+}
+
+// Relays whose content the user trusts less than an ordinary relay's
+fn distrusted_relays() -> Vec<RelayUrl> {
+    match GLOBALS.db().filter_relays(|r| r.is_distrusted()) {
+        Ok(relays) => relays.into_iter().map(|r| r.url).collect(),
+        Err(e) => {
+            tracing::error!("{}", e);
+            vec![]
+        }
+    }
+}
+
+// Whether every relay an event was seen on is one of `relays`. Events we have
+// no provenance for (e.g. ones we created) are not hidden.
+fn seen_only_on(id: Id, relays: &[RelayUrl]) -> bool {
+    if relays.is_empty() {
+        return false;
+    }
+    match GLOBALS.db().get_event_seen_on_relay(id) {
+        Ok(seen_on) => !seen_on.is_empty() && seen_on.iter().all(|(url, _)| relays.contains(url)),
+        Err(_) => false,
+    }
+}
+
+// Whether an event is by somebody we don't follow and was seen only on relays
+// whose content we distrust
+fn from_stranger_on_distrusted_relays(e: &Event, distrusted: &[RelayUrl]) -> bool {
+    seen_only_on(e.id, distrusted)
+        && !matches!(
+            GLOBALS
+                .db()
+                .is_person_in_list(&e.pubkey, PersonList::Followed),
+            Ok(true)
+        )
+}
+
+// An event with its created_at replaced by the time it should be ordered by. This
+// is only for ordering, the event no longer verifies.
+fn by_sort_time(mut e: Event) -> Event {
+    if let Ok(time) = GLOBALS.db().event_sort_time(&e) {
+        e.created_at = time;
+    }
+    e
 }
 
 pub fn enabled_event_kinds() -> Vec<EventKind> {
@@ -739,7 +1010,7 @@ pub fn feed_related_event_kinds(mut dms: bool) -> Vec<EventKind> {
         dms = false;
     }
 
-    enabled_event_kinds()
+    let mut kinds: Vec<EventKind> = enabled_event_kinds()
         .drain(..)
         .filter(|k| {
             k.is_feed_related()
@@ -748,7 +1019,16 @@ pub fn feed_related_event_kinds(mut dms: bool) -> Vec<EventKind> {
                         && *k != EventKind::DmChat
                         && *k != EventKind::GiftWrap))
         })
-        .collect()
+        .collect();
+
+    // NIP-88 polls show in feeds like notes do
+    kinds.push(crate::polls::POLL_KIND.into());
+
+    // NIP-34 patches and issues show in feeds like notes do
+    kinds.push(crate::git::PATCH_KIND.into());
+    kinds.push(crate::git::ISSUE_KIND.into());
+
+    kinds
 }
 
 pub fn feed_displayable_event_kinds(mut dms: bool) -> Vec<EventKind> {
@@ -756,7 +1036,7 @@ pub fn feed_displayable_event_kinds(mut dms: bool) -> Vec<EventKind> {
     if !GLOBALS.identity.is_unlocked() {
         dms = false;
     }
-    enabled_event_kinds()
+    let mut kinds: Vec<EventKind> = enabled_event_kinds()
         .drain(..)
         .filter(|k| {
             k.is_feed_displayable()
@@ -765,7 +1045,16 @@ pub fn feed_displayable_event_kinds(mut dms: bool) -> Vec<EventKind> {
                         && *k != EventKind::DmChat
                         && *k != EventKind::GiftWrap))
         })
-        .collect()
+        .collect();
+
+    // NIP-88 polls show in feeds like notes do
+    kinds.push(crate::polls::POLL_KIND.into());
+
+    // NIP-34 patches and issues show in feeds like notes do
+    kinds.push(crate::git::PATCH_KIND.into());
+    kinds.push(crate::git::ISSUE_KIND.into());
+
+    kinds
 }
 
 pub fn feed_augment_event_kinds() -> Vec<EventKind> {
@@ -775,6 +1064,31 @@ pub fn feed_augment_event_kinds() -> Vec<EventKind> {
         .collect()
 }
 
+/// The title to show for a thread starting at `event`: its `subject` or `title`
+/// tag, or else the first line of its content (shortened if long)
+pub fn thread_display_title(event: &Event) -> Option<String> {
+    const MAX_CHARS: usize = 80;
+
+    if let Some(subject) = event.subject() {
+        return Some(subject.to_owned());
+    }
+    if let Some(title) = event.title() {
+        return Some(title.to_owned());
+    }
+
+    let line = event
+        .content
+        .lines()
+        .map(|l| l.trim())
+        .find(|l| !l.is_empty())?;
+    if line.chars().count() > MAX_CHARS {
+        let short: String = line.chars().take(MAX_CHARS - 1).collect();
+        Some(format!("{}…", short.trim_end()))
+    } else {
+        Some(line.to_owned())
+    }
+}
+
 fn xor_ids(id1: Id, id2: Id) -> Id {
     Id(id1
         .0
@@ -385,8 +385,11 @@ pub struct Fetcher {
     /// over the network, but the data still needs to be loaded into memory
     cache_dir: RwLock<PathBuf>,
 
-    /// The load currently applied to a host
-    host_load: DashMap<String, Arc<Semaphore>>,
+    /// The load currently applied overall, and how many requests it allows
+    overall_load: RwLock<Option<(usize, Arc<Semaphore>)>>,
+
+    /// The load currently applied to a host, and how many requests it allows
+    host_load: DashMap<String, (usize, Arc<Semaphore>)>,
 
     /// Penalized hosts; upon certain errors we time out the host and try again later
     penalty_box: DashMap<String, Unixtime>,
@@ -527,12 +530,16 @@ impl Fetcher {
             .db()
             .read_setting_fetcher_host_exclusion_on_high_error_secs();
 
+        let mut retries: u64 = 0;
+
         loop {
             // Moved to Queued
             self.set_state(&url, FetchState::Queued);
 
-            let semaphore = self.acquire_host(host.as_str()).await;
-            let _permit = semaphore.acquire().await.unwrap();
+            let host_semaphore = self.acquire_host(host.as_str()).await;
+            let _host_permit = host_semaphore.acquire().await.unwrap();
+            let overall_semaphore = self.acquire_overall();
+            let _overall_permit = overall_semaphore.acquire().await.unwrap();
 
             // Move to Fetching
             self.set_state(&url, FetchState::Fetching);
@@ -542,7 +549,10 @@ impl Fetcher {
             let client = self.client.read().unwrap().clone().unwrap();
 
             // Build the request
-            let mut req = client.get(url.as_str());
+            // (the timeout is set per request so that changing the setting takes effect)
+            let mut req = client.get(url.as_str()).timeout(Duration::from_secs(
+                GLOBALS.db().read_setting_fetcher_timeout_sec(),
+            ));
             if let Some(ref etag) = etag {
                 req = req.header("if-none-match", etag.to_owned());
             }
@@ -579,6 +589,9 @@ impl Fetcher {
                     } else if e.is_timeout() {
                         // Sinbin and try again later
                         self.sinbin(&url, Duration::from_secs(med_exclusion));
+                        if !self.may_retry(&url, &mut retries, format!("Timeout: {e}")) {
+                            return;
+                        }
                         continue;
                     } else if e.is_request() {
                         self.failed(&url, format!("Request error: {e}"));
@@ -604,6 +617,9 @@ impl Fetcher {
             if status.is_informational() {
                 // Sinbin and try again later
                 self.sinbin(&url, Duration::from_secs(med_exclusion));
+                if !self.may_retry(&url, &mut retries, format!("{}", status)) {
+                    return;
+                }
                 continue;
             } else if status.is_redirection() {
                 if status == StatusCode::NOT_MODIFIED {
@@ -642,9 +658,15 @@ impl Fetcher {
                 return;
             } else if status == StatusCode::REQUEST_TIMEOUT {
                 self.sinbin(&url, Duration::from_secs(low_exclusion));
+                if !self.may_retry(&url, &mut retries, format!("{}", status)) {
+                    return;
+                }
                 continue;
             } else if status == StatusCode::TOO_MANY_REQUESTS {
                 self.sinbin(&url, Duration::from_secs(med_exclusion));
+                if !self.may_retry(&url, &mut retries, format!("{}", status)) {
+                    return;
+                }
                 continue;
             } else if !status.is_success() {
                 self.failed(&url, format!("{}", status));
@@ -690,6 +712,16 @@ impl Fetcher {
         }
     }
 
+    // Count a retry, or fail the fetch if it has used them all up
+    fn may_retry(&self, url: &Url, retries: &mut u64, error: String) -> bool {
+        if *retries >= GLOBALS.db().read_setting_fetcher_max_retries() {
+            self.failed(url, format!("{error} (gave up after {} retries)", *retries));
+            return false;
+        }
+        *retries += 1;
+        true
+    }
+
     fn set_state(&self, url: &Url, state: FetchState) {
         if let Some(mut refmut) = self.url_data.get_mut(url) {
             refmut.value_mut().state = state;
@@ -750,21 +782,34 @@ impl Fetcher {
             }
         }
 
-        // Make sure we have an entry for the host in the host_load
-        // NOTE: if the setting changes, we don't update existing hosts because
-        //       replacing the semaphore is too complex.
-        {
-            if !self.host_load.contains_key(host) {
-                let num_permits = GLOBALS.db().read_setting_fetcher_max_requests_per_host();
-                let _ = self
-                    .host_load
-                    .entry(host.to_string())
-                    .or_insert(Arc::new(Semaphore::new(num_permits)));
-            }
+        // Make sure we have an entry for the host in the host_load, replacing it if
+        // the setting changed. Requests holding permits from the old semaphore finish
+        // normally, so the new limit is fully in force once they are done.
+        let num_permits = GLOBALS.db().read_setting_fetcher_max_requests_per_host();
+        let mut entry = self
+            .host_load
+            .entry(host.to_string())
+            .or_insert((num_permits, Arc::new(Semaphore::new(num_permits))));
+        if entry.value().0 != num_permits {
+            *entry.value_mut() = (num_permits, Arc::new(Semaphore::new(num_permits)));
         }
 
         // get the semaphore
-        self.host_load.get(host).unwrap().value().clone()
+        entry.value().1.clone()
+    }
+
+    // The semaphore limiting how many requests run at once across all hosts
+    fn acquire_overall(&self) -> Arc<Semaphore> {
+        let num_permits = GLOBALS.db().read_setting_fetcher_max_requests();
+        let mut overall_load = self.overall_load.write().unwrap();
+        match &*overall_load {
+            Some((n, semaphore)) if *n == num_permits => semaphore.clone(),
+            _ => {
+                let semaphore = Arc::new(Semaphore::new(num_permits));
+                *overall_load = Some((num_permits, semaphore.clone()));
+                semaphore
+            }
+        }
     }
 
     fn sinbin(&self, url: &Url, duration: Duration) {
@@ -2,6 +2,7 @@
 
 use crate::dm_channel::DmChannel;
 use crate::globals::GLOBALS;
+use crate::storage::{PersonTable, Table};
 use nostr_types::{EventKind, Filter, Id, NAddr, ParsedTag, PublicKey, Unixtime};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -24,10 +25,30 @@ impl FeedRange {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FilterSet {
-    Augments(Vec<Id>),
+    Augments {
+        ids: Vec<Id>,
+        // Relays that count reactions for us (NIP-45) need not send them
+        reactions: bool,
+    },
     Config,
+    CustomFeedFuture {
+        name: String,
+        pubkeys: Vec<PublicKey>,
+        kinds: Vec<EventKind>,
+        anchor: Unixtime,
+    },
+    CustomFeedChunk {
+        name: String,
+        pubkeys: Vec<PublicKey>,
+        kinds: Vec<EventKind>,
+        anchor: Unixtime,
+    },
     Discover(Vec<PublicKey>),
     DmChannel(DmChannel),
+    Engagement {
+        ids: Vec<Id>,
+        anchor: Unixtime,
+    },
     FollowersOf(PublicKey),
     GeneralFeedFuture {
         pubkeys: Vec<PublicKey>,
@@ -40,9 +61,18 @@ pub enum FilterSet {
     Giftwraps(FeedRange),
     GlobalFeedFuture(Unixtime),
     GlobalFeedChunk(Unixtime),
+    HashtagFeedFuture {
+        hashtag: String,
+        anchor: Unixtime,
+    },
+    HashtagFeedChunk {
+        hashtag: String,
+        anchor: Unixtime,
+    },
     InboxFeedFuture(Unixtime),
     InboxFeedChunk(Unixtime),
     Metadata(Vec<PublicKey>),
+    MetadataExtras(Vec<PublicKey>),
     Nip46,
     PersonFeedFuture {
         pubkey: PublicKey,
@@ -60,19 +90,25 @@ pub enum FilterSet {
 impl FilterSet {
     pub fn temporary(&self) -> bool {
         match self {
-            FilterSet::Augments(_) => true,
+            FilterSet::Augments { .. } => true,
             FilterSet::Config => false,
+            FilterSet::CustomFeedFuture { .. } => false,
+            FilterSet::CustomFeedChunk { .. } => true,
             FilterSet::Discover(_) => true,
             FilterSet::DmChannel(_) => false,
+            FilterSet::Engagement { .. } => false,
             FilterSet::FollowersOf(_) => true,
             FilterSet::GeneralFeedFuture { .. } => false,
             FilterSet::GeneralFeedChunk { .. } => true,
             FilterSet::Giftwraps(_) => false,
             FilterSet::GlobalFeedFuture(_) => false,
             FilterSet::GlobalFeedChunk(_) => true,
+            FilterSet::HashtagFeedFuture { .. } => false,
+            FilterSet::HashtagFeedChunk { .. } => true,
             FilterSet::InboxFeedFuture(_) => false,
             FilterSet::InboxFeedChunk(_) => true,
             FilterSet::Metadata(_) => true,
+            FilterSet::MetadataExtras(_) => true,
             FilterSet::Nip46 => false,
             FilterSet::PersonFeedFuture { .. } => false,
             FilterSet::PersonFeedChunk { .. } => true,
@@ -84,9 +120,12 @@ impl FilterSet {
 
     pub fn can_have_duplicates(&self) -> bool {
         match self {
+            FilterSet::CustomFeedChunk { .. } => true,
             FilterSet::GeneralFeedChunk { .. } => true,
             FilterSet::GlobalFeedChunk(_) => true,
+            FilterSet::HashtagFeedChunk { .. } => true,
             FilterSet::InboxFeedChunk(_) => true,
+            FilterSet::MetadataExtras(_) => true,
             FilterSet::PersonFeedChunk { .. } => true,
             _ => false,
         }
@@ -94,8 +133,10 @@ impl FilterSet {
 
     pub fn is_loading_more(&self) -> bool {
         match self {
+            FilterSet::CustomFeedChunk { .. } => true,
             FilterSet::GeneralFeedChunk { .. } => true,
             FilterSet::GlobalFeedChunk(_) => true,
+            FilterSet::HashtagFeedChunk { .. } => true,
             FilterSet::InboxFeedChunk(_) => true,
             FilterSet::PersonFeedChunk { .. } => true,
             _ => false,
@@ -104,19 +145,25 @@ impl FilterSet {
 
     pub fn inner_handle(&self) -> &'static str {
         match self {
-            FilterSet::Augments(_) => "augments",
+            FilterSet::Augments { .. } => "augments",
             FilterSet::Config => "config_feed",
+            FilterSet::CustomFeedFuture { .. } => "custom_feed",
+            FilterSet::CustomFeedChunk { .. } => "custom_feed_chunk",
             FilterSet::Discover(_) => "discover_feed",
             FilterSet::DmChannel(_) => "dm_channel",
+            FilterSet::Engagement { .. } => "engagement",
             FilterSet::FollowersOf(_) => "followers_of",
             FilterSet::GeneralFeedFuture { .. } => "general_feed",
             FilterSet::GeneralFeedChunk { .. } => "general_feed_chunk",
             FilterSet::Giftwraps(_) => "giftwraps",
             FilterSet::GlobalFeedFuture(_) => "global_feed",
             FilterSet::GlobalFeedChunk(_) => "global_feed_chunk",
+            FilterSet::HashtagFeedFuture { .. } => "hashtag_feed",
+            FilterSet::HashtagFeedChunk { .. } => "hashtag_feed_chunk",
             FilterSet::InboxFeedFuture(_) => "inbox_feed",
             FilterSet::InboxFeedChunk(_) => "inbox_feed_chunk",
             FilterSet::Metadata(_) => "subscribe_metadata",
+            FilterSet::MetadataExtras(_) => "subscribe_metadata_extras",
             FilterSet::Nip46 => "nip46",
             FilterSet::PersonFeedFuture { .. } => "person_feed",
             FilterSet::PersonFeedChunk { .. } => "person_feed_chunk",
@@ -127,6 +174,11 @@ impl FilterSet {
     }
 
     pub fn handle(&self, job_id: u64) -> String {
+        // Several custom feeds can be subscribed at once
+        if let FilterSet::CustomFeedFuture { name, .. } = self {
+            return format!("{}:{}", self.inner_handle(), name);
+        }
+
         let mut handle = self.inner_handle().to_owned();
         if self.temporary() {
             handle = format!("temp_{}", handle);
@@ -137,10 +189,34 @@ impl FilterSet {
         handle
     }
 
+    /// The filter sets that fetch metadata for these people: the metadata itself,
+    /// and the extra kinds when the metadata is limited to what is newer than we
+    /// have.
+    pub fn metadata(pubkeys: Vec<PublicKey>) -> Vec<FilterSet> {
+        if metadata_since(&pubkeys).is_some() {
+            vec![
+                FilterSet::MetadataExtras(pubkeys.clone()),
+                FilterSet::Metadata(pubkeys),
+            ]
+        } else {
+            vec![FilterSet::Metadata(pubkeys)]
+        }
+    }
+
     pub fn filter(&self, spamsafe: bool) -> Option<Filter> {
         match self {
-            FilterSet::Augments(ids) => {
-                let event_kinds = crate::feed::feed_augment_event_kinds();
+            FilterSet::Augments { ids, reactions } => {
+                let mut event_kinds = crate::feed::feed_augment_event_kinds();
+                if !reactions {
+                    event_kinds.retain(|k| *k != EventKind::Reaction);
+                }
+
+                // Responses to polls
+                event_kinds.push(crate::polls::POLL_RESPONSE_KIND.into());
+
+                // Reports and labels. Only those by people we follow are counted.
+                event_kinds.push(EventKind::Reporting);
+                event_kinds.push(EventKind::Label);
 
                 let mut filter = Filter {
                     kinds: event_kinds,
@@ -165,7 +241,53 @@ impl FilterSet {
                         EventKind::BookmarkList,
                         EventKind::UserServerList,
                     ],
-                    // these are all replaceable, no since required
+                    // the minion sets since, per relay
+                    ..Default::default()
+                })
+            }
+            FilterSet::CustomFeedFuture {
+                pubkeys,
+                kinds,
+                anchor,
+                ..
+            } => {
+                if pubkeys.is_empty() {
+                    return None;
+                }
+
+                let range = FeedRange::After { since: *anchor };
+                let (since, until, limit) = range.since_until_limit();
+                Some(Filter {
+                    authors: pubkeys.to_vec(),
+                    kinds: kinds.to_vec(),
+                    since,
+                    until,
+                    limit,
+                    ..Default::default()
+                })
+            }
+            FilterSet::CustomFeedChunk {
+                pubkeys,
+                kinds,
+                anchor,
+                ..
+            } => {
+                if pubkeys.is_empty() {
+                    return None;
+                }
+
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let range = FeedRange::ChunkBefore {
+                    until: *anchor,
+                    limit,
+                };
+                let (since, until, limit) = range.since_until_limit();
+                Some(Filter {
+                    authors: pubkeys.to_vec(),
+                    kinds: kinds.to_vec(),
+                    since,
+                    until,
+                    limit,
                     ..Default::default()
                 })
             }
@@ -195,6 +317,27 @@ impl FilterSet {
                 filter.set_tag_values('p', authors.iter().map(|x| x.as_hex_string()).collect());
                 Some(filter)
             }
+            FilterSet::Engagement { ids, anchor } => {
+                if ids.is_empty() {
+                    return None;
+                }
+
+                let pubkey = GLOBALS.identity.public_key()?;
+
+                // Reactions, zaps and deletions of our notes
+                let mut filter = Filter {
+                    kinds: vec![
+                        EventKind::Reaction,
+                        EventKind::Zap,
+                        EventKind::EventDeletion,
+                    ],
+                    since: Some(*anchor),
+                    ..Default::default()
+                };
+                filter.set_tag_values('e', ids.iter().map(|id| id.as_hex_string()).collect());
+                filter.set_tag_values('p', vec![pubkey.as_hex_string()]);
+                Some(filter)
+            }
             FilterSet::FollowersOf(pubkey) => {
                 let mut filter = Filter {
                     kinds: vec![EventKind::ContactList],
@@ -303,6 +446,42 @@ impl FilterSet {
                     ..Default::default()
                 })
             }
+            FilterSet::HashtagFeedFuture { hashtag, anchor } => {
+                // Do not load feed related event kinds, or the limit will be wrong
+                let event_kinds = crate::feed::feed_displayable_event_kinds(false);
+
+                let range = FeedRange::After { since: *anchor };
+                let (since, until, limit) = range.since_until_limit();
+                let mut filter = Filter {
+                    kinds: event_kinds,
+                    since,
+                    until,
+                    limit,
+                    ..Default::default()
+                };
+                filter.set_tag_values('t', vec![hashtag.to_owned()]);
+                Some(filter)
+            }
+            FilterSet::HashtagFeedChunk { hashtag, anchor } => {
+                // Do not load feed related event kinds, or the limit will be wrong
+                let event_kinds = crate::feed::feed_displayable_event_kinds(false);
+
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let range = FeedRange::ChunkBefore {
+                    until: *anchor,
+                    limit,
+                };
+                let (since, until, limit) = range.since_until_limit();
+                let mut filter = Filter {
+                    kinds: event_kinds,
+                    since,
+                    until,
+                    limit,
+                    ..Default::default()
+                };
+                filter.set_tag_values('t', vec![hashtag.to_owned()]);
+                Some(filter)
+            }
             FilterSet::InboxFeedFuture(anchor) => {
                 let pubkey = GLOBALS.identity.public_key()?;
 
@@ -332,21 +511,30 @@ impl FilterSet {
                 Some(filter)
             }
             FilterSet::Metadata(pubkeys) => {
+                // Relays only need to send what is newer than what we have. The
+                // extra kinds can't be held to that, so when it applies they are
+                // left to a separate FilterSet::MetadataExtras.
+                let since = metadata_since(pubkeys);
+                let mut kinds = vec![
+                    EventKind::ContactList,
+                    EventKind::Metadata,
+                    EventKind::RelayList,
+                ];
+                if since.is_none() {
+                    kinds.extend(METADATA_EXTRA_KINDS);
+                }
                 Some(Filter {
                     authors: pubkeys.to_vec(),
-                    kinds: vec![
-                        EventKind::ContactList,
-                        EventKind::Metadata,
-                        EventKind::RelayList,
-                        EventKind::DmRelayList,
-                        EventKind::HandlerRecommendation,
-                        EventKind::RequestToVanish,
-                    ],
-                    // FIXME: we could probably get a since-last-fetched-their-metadata here.
-                    //        but relays should just return the latest of these.
+                    kinds,
+                    since,
                     ..Default::default()
                 })
             }
+            FilterSet::MetadataExtras(pubkeys) => Some(Filter {
+                authors: pubkeys.to_vec(),
+                kinds: METADATA_EXTRA_KINDS.to_vec(),
+                ..Default::default()
+            }),
             FilterSet::Nip46 => {
                 let pubkey = GLOBALS.identity.public_key()?;
 
@@ -480,3 +668,35 @@ impl FilterSet {
         filter
     }
 }
+
+// Metadata kinds that most people never publish (DM relay lists, handler
+// recommendations, requests to vanish), so that having none of them says nothing
+// about how much we are missing.
+const METADATA_EXTRA_KINDS: [EventKind; 3] = [
+    EventKind::DmRelayList,
+    EventKind::HandlerRecommendation,
+    EventKind::RequestToVanish,
+];
+
+/// The `since` for a metadata subscription: the oldest of the latest metadata,
+/// relay list and contact list that we have from any of these people. It is
+/// inclusive, so a relay holding a different version from the same second still
+/// sends it. None if we are missing any of these from anybody.
+///
+/// The extra kinds don't hold this back; they are fetched in full separately.
+fn metadata_since(pubkeys: &[PublicKey]) -> Option<Unixtime> {
+    let mut since: Option<i64> = None;
+    for pubkey in pubkeys {
+        let person = PersonTable::read_record(*pubkey, None).ok()??;
+        let contact_list = GLOBALS
+            .db()
+            .get_replaceable_event(EventKind::ContactList, *pubkey, "")
+            .ok()??;
+        let latest = person
+            .metadata_created_at?
+            .min(person.relay_list_created_at?)
+            .min(contact_list.created_at.0);
+        since = Some(since.map_or(latest, |s| s.min(latest)));
+    }
+    since.map(Unixtime)
+}
@@ -0,0 +1,144 @@
+//! NIP-34 git stuff
+//!
+//! A repository is announced with an addressable event (kind 30617). Patches
+//! (kind 1617) and issues (kind 1621) name the repository they are for in an
+//! `a` tag pointing at that announcement.
+
+use nostr_types::{Event, EventKind, NAddr, ParsedTag, RelayUrl};
+
+/// The kind number of a repository announcement
+pub const REPO_ANNOUNCEMENT_KIND: u32 = 30617;
+
+/// The kind number of a patch
+pub const PATCH_KIND: u32 = 1617;
+
+/// The kind number of an issue
+pub const ISSUE_KIND: u32 = 1621;
+
+/// Whether events of this kind are repository announcements
+pub fn is_repo_announcement(kind: EventKind) -> bool {
+    u32::from(kind) == REPO_ANNOUNCEMENT_KIND
+}
+
+/// Whether events of this kind are patches
+pub fn is_patch(kind: EventKind) -> bool {
+    u32::from(kind) == PATCH_KIND
+}
+
+/// Whether events of this kind are issues
+pub fn is_issue(kind: EventKind) -> bool {
+    u32::from(kind) == ISSUE_KIND
+}
+
+/// The value of an `a` tag that refers to the repository at `addr`
+pub(crate) fn repo_tag_value(addr: &NAddr) -> String {
+    format!(
+        "{}:{}:{}",
+        REPO_ANNOUNCEMENT_KIND,
+        addr.author.as_hex_string(),
+        addr.d
+    )
+}
+
+/// A repository, as parsed from its announcement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repo {
+    pub addr: NAddr,
+    pub name: String,
+    pub description: String,
+    pub web: Vec<String>,
+    pub clone: Vec<String>,
+}
+
+impl Repo {
+    pub fn from_event(event: &Event) -> Option<Repo> {
+        if !is_repo_announcement(event.kind) {
+            return None;
+        }
+
+        let mut d: Option<String> = None;
+        let mut name = String::new();
+        let mut description = String::new();
+        let mut web: Vec<String> = Vec::new();
+        let mut clone: Vec<String> = Vec::new();
+        let mut relays: Vec<RelayUrl> = Vec::new();
+
+        for tag in &event.tags {
+            // These tags may carry several values
+            let values = || {
+                (1..)
+                    .map(|i| tag.get_index(i))
+                    .take_while(|v| !v.is_empty())
+                    .map(|v| v.to_owned())
+            };
+            match tag.tagname() {
+                "d" => d = Some(tag.value().to_owned()),
+                "name" => name = tag.value().to_owned(),
+                "description" => description = tag.value().to_owned(),
+                "web" => web.extend(values()),
+                "clone" => clone.extend(values()),
+                "relays" => {
+                    relays.extend(values().filter_map(|r| crate::relay::parse_relay_url(&r).ok()))
+                }
+                _ => {}
+            }
+        }
+
+        let d = d?;
+        if name.is_empty() {
+            name = d.clone();
+        }
+
+        Some(Repo {
+            addr: NAddr {
+                d,
+                relays,
+                kind: event.kind,
+                author: event.pubkey,
+            },
+            name,
+            description,
+            web,
+            clone,
+        })
+    }
+}
+
+/// The repository a patch or issue is for, if it names one
+pub fn repo_of(event: &Event) -> Option<NAddr> {
+    if !is_patch(event.kind) && !is_issue(event.kind) {
+        return None;
+    }
+    event.tags.iter().find_map(|tag| match tag.parse() {
+        Ok(ParsedTag::Address { address, .. }) if is_repo_announcement(address.kind) => {
+            Some(address)
+        }
+        _ => None,
+    })
+}
+
+/// A one line summary of a patch, taken from the `Subject:` header of its
+/// `git format-patch` content
+pub fn patch_subject(event: &Event) -> Option<String> {
+    if !is_patch(event.kind) {
+        return None;
+    }
+    let line = event
+        .content
+        .lines()
+        .find_map(|line| line.strip_prefix("Subject:"))?
+        .trim();
+    // git puts "[PATCH n/m]" in front of the subject
+    let line = match line.strip_prefix('[') {
+        Some(rest) if rest.starts_with("PATCH") => match rest.find(']') {
+            Some(end) => rest[end + 1..].trim(),
+            None => line,
+        },
+        _ => line,
+    };
+    if line.is_empty() {
+        None
+    } else {
+        Some(line.to_owned())
+    }
+}
@@ -1,17 +1,22 @@
+use crate::badges::Badge;
 use crate::blossom::{BlobDescriptor, Blossom};
 use crate::bookmarks::BookmarkList;
 use crate::client_identity::ClientIdentity;
 use crate::comms::{RelayJob, ToMinionMessage, ToOverlordMessage};
 use crate::delegation::Delegation;
+use crate::delivery::UndeliveredPost;
+use crate::dm_cache::DmCache;
 use crate::error::Error;
+use crate::event_hook::EventHook;
 use crate::feed::Feed;
 use crate::fetcher::Fetcher;
 use crate::media::Media;
 use crate::minion::MinionExitReason;
-use crate::misc::ZapState;
+use crate::misc::{Navigation, ZapState};
 use crate::pending::Pending;
 use crate::people::{FollowList, People, Person};
-use crate::relay::Relay;
+use crate::relay::{Nip11Change, Relay};
+use crate::relay_metrics::{ActiveSubscription, RelayIngest, Throughput};
 use crate::relay_picker::RelayPicker;
 use crate::relay_test_results::RelayTestResults;
 use crate::seeker::Seeker;
@@ -20,13 +25,17 @@ use crate::storage::{HandlersTable, Storage, Table};
 use crate::user_identity::UserIdentity;
 use crate::RunState;
 use dashmap::{DashMap, DashSet};
-use nostr_types::{Event, EventKind, Id, Profile, PublicKey, RelayUrl, UncheckedUrl};
+use nostr_types::{
+    Event, EventKind, Id, NAddr, Profile, PublicKey, RelayUrl, UncheckedUrl, Unixtime,
+};
 use parking_lot::RwLock as PRwLock;
 use regex::Regex;
 use rhai::{Engine, AST};
 use std::collections::HashSet;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::atomic::{
+    AtomicBool, AtomicI32, AtomicI64, AtomicU32, AtomicU64, AtomicUsize, Ordering,
+};
 use std::sync::{Arc, OnceLock};
 use tokio::runtime::Runtime;
 use tokio::sync::{broadcast, mpsc, Mutex, Notify, RwLock};
@@ -46,6 +55,15 @@ pub struct Globals {
     /// To create a sender, just clone() it.
     pub to_overlord: mpsc::UnboundedSender<ToOverlordMessage>,
 
+    /// This is a broadcast channel of ephemeral events (kinds 20000-29999), which are
+    /// never stored. To receive them (e.g. typing indicators, presence), run
+    /// .subscribe() on it.
+    pub ephemeral_tx: broadcast::Sender<Event>,
+
+    /// This is a broadcast channel of changes in what relays say they support in their
+    /// NIP-11 documents. To be told about them, run .subscribe() on it.
+    pub nip11_changes: broadcast::Sender<Nip11Change>,
+
     /// Current minion tasks
     pub minions: Arc<PRwLock<tokio::task::JoinSet<Result<MinionExitReason, Error>>>>,
 
@@ -77,6 +95,25 @@ pub struct Globals {
     /// jobs.
     pub connected_relays: DashMap<RelayUrl, Vec<RelayJob>>,
 
+    /// Live event ingestion metrics per relay
+    pub relay_ingest: DashMap<RelayUrl, RelayIngest>,
+
+    /// Live event and download throughput
+    pub throughput: PRwLock<Throughput>,
+
+    /// Subscriptions currently open on each connected relay, as reported by the minions
+    pub relay_subscriptions: DashMap<RelayUrl, Vec<ActiveSubscription>>,
+
+    /// Relays we warned are near (false) or over (true) their subscription limit
+    pub relays_near_filter_limit: DashMap<RelayUrl, bool>,
+
+    /// How many times in a row each relay sent a message over our size limit
+    pub(crate) relays_oversized_messages: DashMap<RelayUrl, u32>,
+
+    /// AUTH decisions the user made without asking us to remember them. These last
+    /// for this run so that reconnecting to the relay does not ask again.
+    pub relay_auth_decisions: DashMap<RelayUrl, bool>,
+
     /// The relay picker, used to pick the next relay
     pub relay_picker: RelayPicker,
 
@@ -86,6 +123,9 @@ pub struct Globals {
     /// Client identity wrapping a Signer
     pub client_identity: ClientIdentity,
 
+    /// Decrypted DM content
+    pub dm_cache: DmCache,
+
     /// Dismissed Events
     pub dismissed: RwLock<Vec<Id>>,
 
@@ -126,6 +166,13 @@ pub struct Globals {
     /// Media loading
     pub media: Media,
 
+    /// If we are in quiet mode, see [set_quiet_mode](crate::Overlord::set_quiet_mode)
+    pub quiet_mode: AtomicBool,
+
+    /// If set, these are the only relays we connect to, see
+    /// [connect_only](crate::Overlord::connect_only)
+    pub connect_only: PRwLock<Option<Vec<RelayUrl>>>,
+
     /// Search results
     pub searching: AtomicBool,
     pub search_job: AtomicU64,
@@ -164,10 +211,20 @@ pub struct Globals {
     pub(crate) spam_filter_engine: Engine,
     pub(crate) spam_filter: Option<AST>,
 
+    /// The local time zone's offset from UTC in seconds, which quiet hours are in.
+    /// gossip-lib has no time zone support, so the front end sets this.
+    pub local_utc_offset_secs: AtomicI32,
+
     // Wait for login
     pub wait_for_login: AtomicBool,
     pub wait_for_login_notify: Notify,
 
+    /// When the user last did something (unixtime), for locking the key after inactivity
+    pub last_activity: AtomicI64,
+
+    /// Actions that need the private key, held until it is unlocked
+    pub held_for_signer: PRwLock<Vec<ToOverlordMessage>>,
+
     // Wait for data migration
     pub wait_for_data_migration: AtomicBool,
 
@@ -177,6 +234,9 @@ pub struct Globals {
     /// Pending actions
     pub pending: Pending,
 
+    /// Posts that relays refused, by id and relay
+    pub undelivered_posts: DashMap<(Id, RelayUrl), UndeliveredPost>,
+
     /// Loading more - how many relays are still loading a chunk of events.
     pub loading_more: AtomicUsize,
 
@@ -190,6 +250,19 @@ pub struct Globals {
     /// If we are doing a long database prune, this will indicate the status
     pub prune_status: PRwLock<Option<String>>,
 
+    /// If we are auditing stored events, this will indicate the status
+    pub audit_status: PRwLock<Option<String>>,
+
+    /// Set this to stop a running audit of stored events
+    pub audit_cancel: AtomicBool,
+
+    /// Proof of work being mined right now, by job token, each with a flag that
+    /// stops it
+    pub pow_jobs: DashMap<u64, Arc<AtomicBool>>,
+
+    /// Progress of a health check of all relays, while one is running
+    pub health_check_status: PRwLock<Option<String>>,
+
     /// Relay tests
     pub relay_tests: DashMap<RelayUrl, Option<RelayTestResults>>,
 
@@ -208,11 +281,43 @@ pub struct Globals {
     /// Follows (we keep it in memory only, for just one person)
     pub follows: PRwLock<FollowList>,
 
+    /// NIP-45 COUNT results by job id, the largest count any relay gave
+    pub relay_counts: DashMap<u64, usize>,
+
+    /// NIP-45 COUNT results for reactions, by the event reacted to
+    pub reaction_counts: DashMap<Id, usize>,
+
+    /// Which event each pending reaction COUNT job is counting for
+    pub(crate) reaction_count_jobs: DashMap<u64, Id>,
+
     /// Delayed posts
     pub delayed_posts: DashSet<Id>,
 
+    /// Notes mined again, keyed by the new note's id, with the id of the note each
+    /// replaces. The old note is deleted once a relay accepts the new one.
+    pub(crate) remined_posts: DashMap<Id, Id>,
+
+    /// People whose badges were asked for before we had their profile badges
+    pub(crate) badges_wanted: DashSet<PublicKey>,
+
+    /// The badges people chose to show, until their badge events change
+    pub(crate) badges: DashMap<PublicKey, Vec<Badge>>,
+
+    /// Bumped on every relay change that should be auto-advertised, so that only
+    /// the last one of a burst of changes results in an advertisement
+    pub auto_advertise_generation: AtomicU64,
+
+    /// Navigation requested from outside of the UI, for the UI to pick up
+    pub navigation_request: PRwLock<Option<Navigation>>,
+
+    /// An addressable event to navigate to once it arrives
+    pub awaited_navigation: PRwLock<Option<NAddr>>,
+
     /// Notify the UI to redraw.
     pub notify_ui_redraw: Notify,
+
+    /// Hooks that new events are passed through before being stored
+    pub(crate) event_hooks: PRwLock<Vec<EventHook>>,
 }
 
 lazy_static! {
@@ -226,6 +331,12 @@ lazy_static! {
         // Setup a communications channel from the Minions to the Overlord.
         let (to_overlord, tmp_overlord_receiver) = mpsc::unbounded_channel();
 
+        // Setup a broadcast channel for ephemeral events
+        let (ephemeral_tx, _) = broadcast::channel(256);
+
+        // Setup a broadcast channel for NIP-11 changes
+        let (nip11_changes, _) = broadcast::channel(64);
+
         // Setup a watch channel for going offline state change
         // We start in the Offline state
         let (write_runstate, read_runstate) = watcher::channel(RunState::Initializing);
@@ -237,6 +348,8 @@ lazy_static! {
             runtime: Arc::new(runtime),
             to_minions,
             to_overlord,
+            ephemeral_tx,
+            nip11_changes,
             minions: Arc::new(PRwLock::new(tokio::task::JoinSet::new())),
             minions_task_url: DashMap::new(),
             write_runstate,
@@ -244,9 +357,16 @@ lazy_static! {
             tmp_overlord_receiver: Mutex::new(Some(tmp_overlord_receiver)),
             people: People::new(),
             connected_relays: DashMap::new(),
+            relay_ingest: DashMap::new(),
+            throughput: PRwLock::new(Throughput::default()),
+            relay_subscriptions: DashMap::new(),
+            relays_near_filter_limit: DashMap::new(),
+            relays_oversized_messages: DashMap::new(),
+            relay_auth_decisions: DashMap::new(),
             relay_picker: Default::default(),
             identity: UserIdentity::default(),
             client_identity: ClientIdentity::default(),
+            dm_cache: DmCache::new(),
             dismissed: RwLock::new(Vec::new()),
             feed: Feed::new(),
             fetcher: Fetcher::new(),
@@ -262,6 +382,8 @@ lazy_static! {
             unread_inbox: AtomicUsize::new(0),
             delegation: Delegation::default(),
             media: Media::new(),
+            quiet_mode: AtomicBool::new(false),
+            connect_only: PRwLock::new(None),
             searching: AtomicBool::new(false),
             search_job: AtomicU64::new(0),
             events_being_searched_for: PRwLock::new(Vec::new()),
@@ -278,24 +400,42 @@ lazy_static! {
             events_processed: AtomicU32::new(0),
             spam_filter_engine,
             spam_filter,
+            local_utc_offset_secs: AtomicI32::new(0),
             wait_for_login: AtomicBool::new(false),
             wait_for_login_notify: Notify::new(),
+            last_activity: AtomicI64::new(Unixtime::now().0),
+            held_for_signer: PRwLock::new(Vec::new()),
             wait_for_data_migration: AtomicBool::new(false),
             advertise_jobs_remaining: AtomicUsize::new(0),
             pending: Pending::new(),
+            undelivered_posts: DashMap::new(),
             loading_more: AtomicUsize::new(0),
             bookmarks: Arc::new(PRwLock::new(BookmarkList::empty())),
             current_bookmarks: PRwLock::new(Vec::new()),
             recompute_current_bookmarks: Arc::new(Notify::new()),
             prune_status: PRwLock::new(None),
+            audit_status: PRwLock::new(None),
+            audit_cancel: AtomicBool::new(false),
+            pow_jobs: DashMap::new(),
+            health_check_status: PRwLock::new(None),
             relay_tests: DashMap::new(),
             handlers: DashMap::new(),
             blossom: OnceLock::new(),
             blossom_uploads: DashMap::new(),
             followers: PRwLock::new(FollowList::default()),
             follows: PRwLock::new(FollowList::default()),
+            relay_counts: DashMap::new(),
+            reaction_counts: DashMap::new(),
+            reaction_count_jobs: DashMap::new(),
             delayed_posts: DashSet::new(),
+            remined_posts: DashMap::new(),
+            badges_wanted: DashSet::new(),
+            badges: DashMap::new(),
+            auto_advertise_generation: AtomicU64::new(0),
+            navigation_request: PRwLock::new(None),
+            awaited_navigation: PRwLock::new(None),
             notify_ui_redraw: Notify::new(),
+            event_hooks: PRwLock::new(Vec::new()),
         }
     };
 }
@@ -308,6 +448,12 @@ impl Globals {
         }
     }
 
+    /// Register a hook that every new event is passed through before it is stored.
+    /// Hooks run in the order they were registered. See [HookAction](crate::HookAction).
+    pub fn register_event_hook(&self, hook: EventHook) {
+        self.event_hooks.write().push(hook);
+    }
+
     pub fn ui_invalidate_note(&self, id: Id) {
         self.ui_notes_to_invalidate.write().push(id);
         self.notify_ui_redraw.notify_waiters();
@@ -328,6 +474,12 @@ impl Globals {
         self.notify_ui_redraw.notify_waiters();
     }
 
+    /// Note that the user did something, which restarts the auto-lock timer
+    pub fn note_activity(&self) {
+        self.last_activity
+            .store(Unixtime::now().0, Ordering::Relaxed);
+    }
+
     pub fn get_your_nprofile() -> Option<Profile> {
         let public_key = GLOBALS.identity.public_key()?;
 
@@ -344,8 +496,9 @@ impl Globals {
                 tracing::error!("{}", e);
                 return None;
             }
-            Ok(relays) => {
-                for relay in relays {
+            Ok(mut relays) => {
+                relays.sort_by(|a, b| b.score().partial_cmp(&a.score()).unwrap());
+                for relay in relays.iter().take(crate::relay::MAX_RELAY_HINTS) {
                     profile.relays.push(relay.url.to_unchecked_url());
                 }
             }
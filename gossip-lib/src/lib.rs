@@ -64,6 +64,9 @@
 //! with the storage engine. In some cases, the `Overlord` has more complex code for doing this,
 //! but in many cases, you can interact with `GLOBALS.db()` directly.
 
+mod badges;
+pub use badges::{badges_for, Badge};
+
 pub mod blossom;
 pub use blossom::Blossom;
 
@@ -76,9 +79,19 @@ pub use client_identity::ClientIdentity;
 /// Defines messages sent to the overlord
 pub mod comms;
 
+/// NIP-72 moderated communities
+pub mod community;
+pub use community::Community;
+
 mod delegation;
 pub use delegation::Delegation;
 
+mod delivery;
+pub use delivery::UndeliveredPost;
+
+mod dm_cache;
+pub use dm_cache::DmCache;
+
 mod dm_channel;
 pub use dm_channel::{DmChannel, DmChannelData};
 
@@ -88,10 +101,14 @@ pub mod direct;
 mod error;
 pub use error::{Error, ErrorKind};
 
+mod event_hook;
+pub use event_hook::{drop_author_hook, EventHook, HookAction};
+
 mod feed;
 pub use feed::{
     enabled_event_kinds, feed_augment_event_kinds, feed_displayable_event_kinds,
-    feed_related_event_kinds, Feed, FeedKind,
+    feed_related_event_kinds, normalize_hashtag, thread_display_title, Feed, FeedKind,
+    FeedOrdering,
 };
 
 mod fetcher;
@@ -99,6 +116,10 @@ pub use fetcher::{FetchResult, Fetcher};
 
 mod filter_set;
 
+/// NIP-34 git repositories, patches and issues
+pub mod git;
+pub use git::Repo;
+
 mod globals;
 pub use globals::{Globals, GLOBALS};
 
@@ -110,7 +131,9 @@ pub use media::{media_url_mimetype, Media, MediaLoadingResult};
 mod minion;
 
 mod misc;
-pub use misc::{Freshness, Private, ZapState};
+pub use misc::{
+    normalize_key_payload, Freshness, FutureEventAction, Navigation, Private, ZapState,
+};
 
 /// Rendering various names of users
 pub mod names;
@@ -118,10 +141,16 @@ pub mod names;
 /// nip05 handling
 pub mod nip05;
 
+/// NIP-24 extra metadata fields
+pub mod nip24;
+
 #[allow(dead_code)]
 pub mod nostr_connect_server;
 pub use nostr_connect_server::{Nip46Server, Nip46UnconnectedServer};
 
+mod notification;
+pub use notification::{Notification, NotificationKind};
+
 mod overlord;
 pub use overlord::Overlord;
 
@@ -131,12 +160,17 @@ pub use pending::PendingItem;
 
 mod people;
 pub use people::{
-    hash_person_list_event, FollowList, People, Person, PersonList, PersonListMetadata,
+    hash_person_list_event, FollowList, People, Person, PersonList, PersonListDiff,
+    PersonListMetadata,
 };
 
 mod person_relay;
 pub use person_relay::PersonRelay;
 
+/// NIP-88 polls
+pub mod polls;
+pub use polls::{Poll, PollResults};
+
 mod post;
 
 /// Processing incoming events
@@ -146,9 +180,16 @@ mod profile;
 pub use profile::Profile;
 
 mod relationship;
+pub use relationship::{ReactionSummary, Relationships};
+
+/// NIP-56 reports and NIP-32 labels
+pub mod reports;
+pub use reports::{Label, Report};
 
 pub mod relay;
-pub use relay::{Relay, ScoreFactors};
+pub use relay::{Nip11Change, Relay, RelayCredential, ScoreFactors};
+
+pub mod relay_metrics;
 
 pub mod relay_picker;
 pub use relay_picker::RelayPicker;
@@ -166,10 +207,20 @@ pub use status::StatusQueue;
 
 mod storage;
 pub use storage::types::*;
-pub use storage::{FollowingsTable, HandlersTable, PersonTable, Storage, Table};
+pub use storage::{
+    Draft, FollowingsTable, HandlersTable, MigrationInfo, PersonTable, ReadTxn, Rebuild,
+    ReplaceableConflict, Storage, StorageRecovery, Table, WriteTxn,
+};
 
 mod tasks;
 
+#[cfg(test)]
+mod test_util;
+
+/// A minimal relay on loopback for testing relay protocol handling
+#[cfg(any(test, feature = "test-relay"))]
+pub mod test_relay;
+
 mod user_identity;
 pub use user_identity::UserIdentity;
 
@@ -221,12 +272,13 @@ impl std::convert::TryFrom<u8> for RunState {
 pub fn init(rapid: bool, command_mode: bool) -> Result<(), Error> {
     use std::sync::atomic::Ordering;
 
-    // Initialize storage
+    // Initialize storage, unless it is corrupted. Recovery is left to the user.
+    let dir = Profile::lmdb_dir()?;
+    Storage::check(&dir, rapid)?;
     if !command_mode {
         // Ignore compaction errors
         let _ = Storage::compact();
     }
-    let dir = Profile::lmdb_dir()?;
     let storage = Storage::new(dir, rapid)?;
     GLOBALS
         .storage
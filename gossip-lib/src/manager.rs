@@ -1,8 +1,9 @@
-use crate::comms::{RelayJob, ToMinionMessage};
+use crate::comms::{RelayJob, ToMinionMessage, ToMinionPayloadDetail, ToOverlordMessage};
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
 use crate::minion::Minion;
 use crate::pending::PendingItem;
+use crate::relay::Relay;
 use dashmap::mapref::entry::Entry;
 use nostr_types::RelayUrl;
 
@@ -60,6 +61,14 @@ pub(crate) fn engage_minion(url: RelayUrl, jobs: Vec<RelayJob>) {
 async fn engage_minion_inner(url: RelayUrl, mut jobs: Vec<RelayJob>) -> Result<(), Error> {
     let relay = GLOBALS.db().read_or_create_relay(&url, None)?;
 
+    // Never post to a no-post relay, no matter who asked
+    if remove_posting_jobs(&relay, &mut jobs) {
+        tracing::warn!("Refusing to post to {}, it is marked no-post", url);
+        if jobs.is_empty() {
+            return Err(ErrorKind::EngageDisallowed.into());
+        }
+    }
+
     if GLOBALS
         .db()
         .read_setting_relay_connection_requires_approval()
@@ -83,6 +92,13 @@ async fn engage_minion_inner(url: RelayUrl, mut jobs: Vec<RelayJob>) -> Result<(
         return Err(ErrorKind::Offline.into());
     }
 
+    // Do not connect to relays left out of a connect_only() allowlist
+    if let Some(allowed) = &*GLOBALS.connect_only.read() {
+        if !allowed.contains(&url) {
+            return Err(ErrorKind::EngageDisallowed.into());
+        }
+    }
+
     if jobs.is_empty() {
         return Err(ErrorKind::EmptyJob.into());
     }
@@ -92,6 +108,18 @@ async fn engage_minion_inner(url: RelayUrl, mut jobs: Vec<RelayJob>) -> Result<(
         return Err(ErrorKind::EngageDisallowed.into());
     }
 
+    // The relay's credential can't be decrypted while the key is locked, and the
+    // relay would refuse us without it. Quietly hold the jobs until the key is
+    // unlocked.
+    if !GLOBALS.connected_relays.contains_key(&url)
+        && !GLOBALS.identity.is_unlocked()
+        && GLOBALS.db().has_relay_credential(&url)?
+    {
+        tracing::debug!("{}: Waiting for the key to be unlocked to connect", &url);
+        hold_until_unlocked(url, jobs);
+        return Err(ErrorKind::EngageWaitingForKey.into());
+    }
+
     let entry = GLOBALS.connected_relays.entry(url.clone());
 
     if let Entry::Occupied(mut oe) = entry {
@@ -111,7 +139,7 @@ async fn engage_minion_inner(url: RelayUrl, mut jobs: Vec<RelayJob>) -> Result<(
             if !job.reason.persistent() {
                 if let Some(pos) = vec.iter().position(|e| e.reason == job.reason) {
                     vec[pos] = job;
-                    return Ok(());
+                    continue;
                 }
             }
             vec.push(job);
@@ -132,3 +160,90 @@ async fn engage_minion_inner(url: RelayUrl, mut jobs: Vec<RelayJob>) -> Result<(
 
     Ok(())
 }
+
+/// Whether we may connect to this relay right now without asking the user. This
+/// makes the same checks as engaging a minion does, except that a relay still
+/// awaiting approval is refused rather than put to the user.
+pub(crate) fn may_connect_unasked(relay: &Relay) -> bool {
+    if GLOBALS
+        .db()
+        .read_setting_relay_connection_requires_approval()
+        && relay.allow_connect != Some(true)
+    {
+        return false;
+    }
+
+    if relay.allow_connect == Some(false) || GLOBALS.db().read_setting_offline() {
+        return false;
+    }
+
+    if let Some(allowed) = &*GLOBALS.connect_only.read() {
+        if !allowed.contains(&relay.url) {
+            return false;
+        }
+    }
+
+    !relay.should_avoid()
+}
+
+// Hold jobs for a relay until the key is unlocked, merging them with any already
+// held for it
+fn hold_until_unlocked(url: RelayUrl, mut jobs: Vec<RelayJob>) {
+    let mut held = GLOBALS.held_for_signer.write();
+    for message in held.iter_mut() {
+        if let ToOverlordMessage::ReengageMinion(held_url, held_jobs) = message {
+            if *held_url == url {
+                held_jobs.append(&mut jobs);
+                return;
+            }
+        }
+    }
+    held.push(ToOverlordMessage::ReengageMinion(url, jobs));
+}
+
+// Remove jobs that post events if the relay is marked no-post. Returns true if
+// any were removed.
+fn remove_posting_jobs(relay: &Relay, jobs: &mut Vec<RelayJob>) -> bool {
+    if !relay.no_post {
+        return false;
+    }
+
+    let count = jobs.len();
+    jobs.retain(|job| !matches!(job.payload.detail, ToMinionPayloadDetail::PostEvents(_)));
+    jobs.len() < count
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comms::{RelayConnectionReason, ToMinionPayload};
+    use crate::filter_set::FilterSet;
+
+    #[test]
+    fn test_remove_posting_jobs() {
+        let job = |detail| RelayJob {
+            reason: RelayConnectionReason::PostEvent,
+            payload: ToMinionPayload { job_id: 1, detail },
+        };
+        let mut jobs = vec![
+            job(ToMinionPayloadDetail::PostEvents(vec![])),
+            job(ToMinionPayloadDetail::Subscribe(FilterSet::Metadata(
+                vec![],
+            ))),
+        ];
+
+        let mut relay = Relay::new(RelayUrl::try_from_str("wss://relay.example").unwrap());
+        relay.set_usage_bits(Relay::WRITE);
+        assert!(!remove_posting_jobs(&relay, &mut jobs));
+        assert_eq!(jobs.len(), 2);
+
+        // Posting is skipped even though the relay is a WRITE relay
+        relay.no_post = true;
+        assert!(remove_posting_jobs(&relay, &mut jobs));
+        assert_eq!(jobs.len(), 1);
+        assert!(matches!(
+            jobs[0].payload.detail,
+            ToMinionPayloadDetail::Subscribe(_)
+        ));
+    }
+}
@@ -3,6 +3,7 @@ use crate::comms::ToOverlordMessage;
 use crate::error::Error;
 use crate::globals::GLOBALS;
 use nostr_types::{RelayMessage, Unixtime};
+use std::sync::atomic::Ordering;
 
 impl Minion {
     pub(super) async fn handle_nostr_message(&mut self, ws_message: String) -> Result<(), Error> {
@@ -24,6 +25,11 @@ impl Minion {
 
         match relay_message {
             RelayMessage::Event(subid, event) => {
+                // Ignore events on subscriptions we don't have open
+                if self.subscription_map.get_mut_by_id(&subid.0).is_none() {
+                    return self.ignore_stray_event(subid).await;
+                }
+
                 let handle = self
                     .subscription_map
                     .get_handle_by_id(&subid.0)
@@ -61,6 +67,7 @@ impl Minion {
                                 Some(old) => old.max(event.created_at.0 as u64),
                                 None => event.created_at.0 as u64,
                             });
+
                         GLOBALS.db().modify_relay(
                             &self.dbrelay.url,
                             |relay| {
@@ -72,13 +79,38 @@ impl Minion {
                 }
 
                 // Remove from sought set
-                if let Some(ess) = self.sought_events.remove(&event.id) {
-                    // and notify the overlord of the completed job
-                    for job_id in ess.job_ids.iter() {
-                        self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
-                            self.url.clone(),
-                            *job_id,
-                        ))?;
+                let sought = match self.sought_events.remove(&event.id) {
+                    Some(ess) => {
+                        // and notify the overlord of the completed job
+                        for job_id in ess.job_ids.iter() {
+                            self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+                                self.url.clone(),
+                                *job_id,
+                            ))?;
+                        }
+                        true
+                    }
+                    None => false,
+                };
+
+                // Drop archive content from relays the user set a cutoff for.
+                // Replaceable events are current state however old they are, and
+                // events we sought or authored are always wanted, so those are
+                // never cut off.
+                if let Some(cutoff) = self.dbrelay.skip_events_before {
+                    if event.created_at < cutoff
+                        && !sought
+                        && !event.kind.is_replaceable()
+                        && !event.kind.is_parameterized_replaceable()
+                        && GLOBALS.identity.public_key() != Some(event.pubkey)
+                    {
+                        tracing::trace!(
+                            "{}: Skipped Event before cutoff: {:?} @{}",
+                            &self.url,
+                            event.kind,
+                            event.created_at
+                        );
+                        return Ok(());
                     }
                 }
 
@@ -93,6 +125,7 @@ impl Minion {
             }
             RelayMessage::Notice(msg) => {
                 tracing::warn!("{}: NOTICE: {}", &self.url, msg);
+                self.check_rejection(&msg)?;
             }
             RelayMessage::Notify(msg) => {
                 // We currently don't support this as a user-facing UI element, but we
@@ -133,6 +166,18 @@ impl Minion {
                                 None,
                             )?;
                         }
+                        if handle == "config_feed" {
+                            // Reconnecting only needs config events newer than this
+                            let now = Unixtime::now().0 as u64;
+                            self.dbrelay.last_config_eose_at = Some(now);
+                            GLOBALS.db().modify_relay(
+                                &self.dbrelay.url,
+                                |relay| {
+                                    relay.last_config_eose_at = Some(now);
+                                },
+                                None,
+                            )?;
+                        }
                     }
                     None => {
                         tracing::debug!(
@@ -168,6 +213,16 @@ impl Minion {
                         } else {
                             tracing::info!("Authenticated to {}", &self.url);
                             self.auth_state = AuthState::Authenticated;
+
+                            // Remember that this relay wants AUTH, so we can be ready
+                            // for it next time
+                            let now = Unixtime::now().0 as u64;
+                            self.dbrelay.last_auth_at = Some(now);
+                            GLOBALS.db().modify_relay(
+                                &self.url,
+                                |r| r.last_auth_at = Some(now),
+                                None,
+                            )?;
                             self.try_subscribe_waiting().await?;
                         }
                         return Ok(());
@@ -190,6 +245,15 @@ impl Minion {
                     }
                 }
 
+                // Track delivery of our posts
+                if self.posting_ids.contains_key(&id) {
+                    if ok {
+                        crate::delivery::post_accepted(&self.url, id);
+                    } else {
+                        crate::delivery::post_rejected(&self.url, id, &ok_message);
+                    }
+                }
+
                 if let Some(job_id) = self.posting_ids.get(&id).copied() {
                     if ok {
                         // Save seen_on data
@@ -228,6 +292,10 @@ impl Minion {
                     // Take it out of the posting_ids whether or not job is done
                     self.posting_ids.remove(&id);
                 }
+
+                if !ok {
+                    self.check_rejection(&ok_message)?;
+                }
             }
             RelayMessage::Auth(challenge) => {
                 if self.auth_state.is_authenticated() || self.auth_state.failed() {
@@ -239,6 +307,15 @@ impl Minion {
                 self.maybe_authenticate().await?;
             }
             RelayMessage::Closed(subid, message) => {
+                if let Some(job_id) = self.count_jobs.remove(&subid.0) {
+                    tracing::info!("{}: Would not count: {}", &self.url, message);
+                    self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+                        self.url.clone(),
+                        job_id,
+                    ))?;
+                    return Ok(());
+                }
+
                 let handle = self
                     .subscription_map
                     .get_handle_by_id(&subid.0)
@@ -248,6 +325,8 @@ impl Minion {
                     tracing::info!("{}: Closed: {}: {}", &self.url, handle, message);
                 }
 
+                self.check_rejection(&message)?;
+
                 // Check the machine-readable prefix
                 if let Some(prefix) = message.split(':').next() {
                     match prefix {
@@ -290,7 +369,7 @@ impl Minion {
                             self.failed_subs.insert(handle.clone());
                         }
                         "auth-required" => {
-                            if self.dbrelay.allow_auth == Some(false) {
+                            if self.allow_auth() == Some(false) {
                                 // we don't allow auth to this relay.
                                 // fail this subscription handle
                                 self.failed_subs.insert(handle.clone());
@@ -343,16 +422,46 @@ impl Minion {
                     }
                 }
 
-                // Remove the subscription
-                tracing::debug!("{}: removed subscription {}", &self.url, handle);
-                let _ = self.subscription_map.remove(&handle);
-            }
-            RelayMessage::Count(_subid, _count_result) => {
-                tracing::info!(
-                    "{} Relay returned count results that we do not yet support",
-                    &self.url
-                );
+                // Remove the subscription, and tell the overlord the job is over so it
+                // doesn't keep believing we are subscribed
+                if let Some(sub) = self.subscription_map.get(&handle) {
+                    if handle.contains("_feed_chunk") {
+                        self.loading_more -= 1;
+                        let _ = GLOBALS.loading_more.fetch_sub(1, Ordering::SeqCst);
+                    }
+                    tracing::debug!("{}: removed subscription {}", &self.url, handle);
+                    let _ = self.subscription_map.remove(&handle);
+                    self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+                        self.url.clone(),
+                        sub.get_job_id(),
+                    ))?;
+                }
             }
+            RelayMessage::Count(subid, count_result) => match self.count_jobs.remove(&subid.0) {
+                Some(job_id) => {
+                    if let Some(id) = GLOBALS.reaction_count_jobs.get(&job_id).map(|id| *id) {
+                        GLOBALS
+                            .reaction_counts
+                            .entry(id)
+                            .and_modify(|c| *c = (*c).max(count_result.count))
+                            .or_insert(count_result.count);
+                        GLOBALS.ui_invalidate_note(id);
+                    } else {
+                        GLOBALS
+                            .relay_counts
+                            .entry(job_id)
+                            .and_modify(|c| *c = (*c).max(count_result.count))
+                            .or_insert(count_result.count);
+                    }
+                    self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+                        self.url.clone(),
+                        job_id,
+                    ))?;
+                }
+                None => {
+                    tracing::debug!("{}: Unexpected count for {}", &self.url, subid.0);
+                }
+            },
         }
 
         Ok(())
@@ -6,7 +6,8 @@ use crate::comms::{ToMinionMessage, ToMinionPayload, ToMinionPayloadDetail, ToOv
 use crate::error::{Error, ErrorKind};
 use crate::filter_set::FilterSet;
 use crate::globals::GLOBALS;
-use crate::relay::Relay;
+use crate::relay::{Relay, RelayCredential, RelayRejection};
+use crate::relay_metrics;
 use crate::{RunState, USER_AGENT};
 use base64::Engine;
 use encoding_rs::{Encoding, UTF_8};
@@ -17,7 +18,7 @@ use http::Uri;
 use mime::Mime;
 use nostr_types::{
     ClientMessage, EventKind, Filter, Id, KeySigner, NAddr, PreEvent, PublicKey,
-    RelayInformationDocument, RelayUrl, Signer, Tag, Unixtime,
+    RelayInformationDocument, RelayUrl, Signer, SubscriptionId, Tag, Unixtime,
 };
 use reqwest::Response;
 use std::borrow::Cow;
@@ -32,6 +33,16 @@ use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use tungstenite::protocol::{Message as WsMessage, WebSocketConfig};
 use watcher::Receiver as WatchReceiver;
 
+// How long we wait for a relay to answer our ping before we consider the connection dead
+const PONG_TIMEOUT_SECS: u64 = 20;
+
+// How long we hold back subscriptions waiting for the AUTH challenge of a relay that
+// required AUTH before
+const AUTH_CHALLENGE_WAIT_SECS: i64 = 5;
+
+// How many relay-wide rejections we take on one connection before giving up on it
+const MAX_RELAY_WIDE_REJECTIONS: usize = 3;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AuthState {
     None,
@@ -63,9 +74,12 @@ pub struct EventSeekState {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MinionExitReason {
     GotDisconnected,
+    GotReconnectMessage,
     GotShutdownMessage,
     GotWSClose,
     LostOverlord,
+    MessageTooLarge,
+    PongTimeout,
     SubscriptionsCompletedSuccessfully,
     SubscriptionsCompletedWithFailures,
     Unknown,
@@ -92,9 +106,11 @@ pub struct Minion {
     next_events_subscription_id: u32,
     posting_jobs: HashMap<u64, Vec<Id>>,
     posting_ids: HashMap<Id, u64>,
+    count_jobs: HashMap<String, u64>,
     sought_events: HashMap<Id, EventSeekState>,
     sought_naddrs: HashMap<NAddr, EventSeekState>,
     last_message_sent: String,
+    ping_sent_at: Option<Unixtime>,
     auth_challenge: String,
     subscriptions_waiting_for_auth: HashMap<String, Unixtime>,
     subscriptions_waiting_for_metadata: Vec<(u64, Vec<PublicKey>)>,
@@ -102,11 +118,14 @@ pub struct Minion {
     read_runstate: WatchReceiver<RunState>,
     exiting: Option<MinionExitReason>,
     auth_state: AuthState,
+    anticipate_auth_until: Option<Unixtime>,
     failed_subs: HashSet<String>,
+    stray_events: HashMap<String, usize>,
     initial_handling: bool,
     loading_more: usize,
     subscriptions_empty_asof: Option<Unixtime>,
     fake_auth_signer: KeySigner,
+    relay_wide_rejections: usize,
 }
 
 impl Drop for Minion {
@@ -128,6 +147,8 @@ impl Minion {
             return Err(ErrorKind::Offline.into());
         }
 
+        let subscription_map = SubscriptionMap::new(url.clone());
+
         Ok(Minion {
             url,
             to_overlord,
@@ -135,13 +156,15 @@ impl Minion {
             dbrelay,
             nip11: None,
             stream: None,
-            subscription_map: SubscriptionMap::new(),
+            subscription_map,
             next_events_subscription_id: 0,
             posting_jobs: HashMap::new(),
             posting_ids: HashMap::new(),
+            count_jobs: HashMap::new(),
             sought_events: HashMap::new(),
             sought_naddrs: HashMap::new(),
             last_message_sent: String::new(),
+            ping_sent_at: None,
             auth_challenge: "".to_string(),
             subscriptions_waiting_for_auth: HashMap::new(),
             subscriptions_waiting_for_metadata: Vec::new(),
@@ -149,15 +172,129 @@ impl Minion {
             read_runstate,
             exiting: None,
             auth_state: AuthState::None,
+            anticipate_auth_until: None,
             failed_subs: HashSet::new(),
+            stray_events: HashMap::new(),
             initial_handling: true,
             loading_more: 0,
             subscriptions_empty_asof: None,
             fake_auth_signer: KeySigner::generate("", 1)?,
+            relay_wide_rejections: 0,
         })
     }
 }
 
+// Build the websocket upgrade request for a relay
+fn websocket_request(
+    url: &RelayUrl,
+    nostr_subprotocol: bool,
+    user_agent: bool,
+    credential: Option<&RelayCredential>,
+) -> Result<http::Request<()>, Error> {
+    let key: [u8; 16] = rand::random();
+
+    let req = http::request::Request::builder().method("GET");
+
+    let req = if user_agent {
+        req.header("User-Agent", USER_AGENT)
+    } else {
+        req
+    };
+
+    // Some relays want an Origin header to filter requests. Of course we
+    // don't have an Origin, but whatever, for these specific relays we will
+    // give them something.
+    let req = if url.as_str() == "wss://relay.snort.social"
+        || url.as_str() == "wss://relay-pub.deschooling.us"
+    {
+        // Like Damus, we will set it to the URL of the relay itself
+        req.header("Origin", url.as_str())
+    } else {
+        req
+    };
+
+    // A few relays refuse the upgrade unless we ask for the nostr subprotocol
+    let req = if nostr_subprotocol {
+        req.header("Sec-WebSocket-Protocol", "nostr")
+    } else {
+        req
+    };
+
+    // Apply the relay's out of band credential, if it has one
+    let (req, uri) = match credential {
+        Some(RelayCredential::Header { name, value }) => (
+            req.header(name.as_str(), value.as_str()),
+            url.as_str().parse::<Uri>()?,
+        ),
+        Some(RelayCredential::UrlParameter { name, value }) => {
+            let mut with_param = url::Url::parse(url.as_str())?;
+            with_param.query_pairs_mut().append_pair(name, value);
+            (req, with_param.as_str().parse::<Uri>()?)
+        }
+        None => (req, url.as_str().parse::<Uri>()?),
+    };
+
+    // Include the port if there is one (loopback test relays always have one)
+    let host = match uri.authority() {
+        Some(authority) => authority.as_str().to_owned(),
+        None => return Err(ErrorKind::UrlHasNoHostname.into()),
+    };
+    Ok(req
+        .header("Host", host)
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header(
+            "Sec-WebSocket-Key",
+            base64::engine::general_purpose::STANDARD.encode(key),
+        )
+        .uri(uri)
+        .body(())?)
+}
+
+// Whether a refused handshake could be because we did not ask for the nostr
+// subprotocol. Relays that insist on it answer the upgrade with a client error.
+fn handshake_may_want_subprotocol(status: http::StatusCode) -> bool {
+    status == http::StatusCode::BAD_REQUEST || status == http::StatusCode::UPGRADE_REQUIRED
+}
+
+// Parse an HTTP Date header (e.g. "Sun, 06 Nov 1994 08:49:37 GMT") into unix seconds
+fn parse_http_date(date: &str) -> Option<i64> {
+    let mut parts = date.split_whitespace().skip(1); // day of the week
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':').map(|n| n.parse::<i64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT" || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
+}
+
 impl Minion {
     pub(crate) async fn handle(
         &mut self,
@@ -200,48 +337,15 @@ impl Minion {
                 if let Err(e) = self.fetch_nip11(fetcher_timeout).await {
                     if matches!(e.kind, ErrorKind::ShuttingDown) {
                         return Ok(MinionExitReason::GotShutdownMessage);
+                    } else if crate::relay::is_loopback(&self.url) {
+                        // Local (e.g. mock) relays often don't serve NIP-11
+                        tracing::debug!("{}: No NIP-11 from loopback relay: {}", &self.url, e);
                     } else {
                         return Err(e);
                     }
                 }
             }
 
-            let key: [u8; 16] = rand::random();
-
-            let req = http::request::Request::builder().method("GET");
-
-            let req = if GLOBALS.db().read_setting_set_user_agent() {
-                req.header("User-Agent", USER_AGENT)
-            } else {
-                req
-            };
-
-            // Some relays want an Origin header to filter requests. Of course we
-            // don't have an Origin, but whatever, for these specific relays we will
-            // give them something.
-            let req = if self.url.as_str() == "wss://relay.snort.social"
-                || self.url.as_str() == "wss://relay-pub.deschooling.us"
-            {
-                // Like Damus, we will set it to the URL of the relay itself
-                req.header("Origin", self.url.as_str())
-            } else {
-                req
-            };
-
-            let uri: http::Uri = self.url.as_str().parse::<Uri>()?;
-            let host = uri.host().unwrap(); // fixme
-            let req = req
-                .header("Host", host)
-                .header("Connection", "Upgrade")
-                .header("Upgrade", "websocket")
-                .header("Sec-WebSocket-Version", "13")
-                .header(
-                    "Sec-WebSocket-Key",
-                    base64::engine::general_purpose::STANDARD.encode(key),
-                )
-                .uri(uri)
-                .body(())?;
-
             let config: WebSocketConfig = WebSocketConfig {
                 // Tungstenite default is 64 MiB.
                 // Cameri nostream relay limits to 0.5 a megabyte
@@ -265,25 +369,93 @@ impl Minion {
                 GLOBALS.db().read_setting_websocket_connect_timeout_sec()
             };
 
-            let connect_future = tokio::time::timeout(
-                std::time::Duration::new(connect_timeout_secs, 0),
-                tokio_tungstenite::connect_async_with_config(req, Some(config), false),
-            );
+            // The relay's out of band credential is stored encrypted, so we can
+            // only use it once the identity is unlocked
+            let credential = match crate::relay::get_relay_credential(&self.url) {
+                Ok(credential) => credential,
+                Err(e) => {
+                    tracing::warn!(
+                        "{}: Connecting without the relay's credential: {}",
+                        &self.url,
+                        e
+                    );
+                    None
+                }
+            };
+
+            // Some relays refuse the upgrade unless we ask for the nostr subprotocol.
+            // If we did not ask and the handshake is refused, try once more asking.
+            let mut nostr_subprotocol = self.dbrelay.nostr_subprotocol;
+            let (websocket_stream, response) = loop {
+                let req = websocket_request(
+                    &self.url,
+                    nostr_subprotocol,
+                    GLOBALS.db().read_setting_set_user_agent(),
+                    credential.as_ref(),
+                )?;
+
+                let connect_future = tokio::time::timeout(
+                    std::time::Duration::new(connect_timeout_secs, 0),
+                    tokio_tungstenite::connect_async_with_config(req, Some(config), false),
+                );
+
+                tokio::select! {
+                    _ = self.read_runstate.wait_for(|runstate| !runstate.going_online()) => {
+                        return Ok(MinionExitReason::GotShutdownMessage);
+                    },
+                    connect_result = connect_future => {
+                        match connect_result? {
+                            Ok(pair) => break pair,
+                            Err(tungstenite::Error::Http(ref response))
+                                if !nostr_subprotocol
+                                    && handshake_may_want_subprotocol(response.status()) =>
+                            {
+                                tracing::debug!(
+                                    "{}: Handshake refused ({}), retrying with nostr subprotocol",
+                                    &self.url,
+                                    response.status()
+                                );
+                                nostr_subprotocol = true;
+                            }
+                            Err(e) => return Err(e.into()),
+                        }
+                    },
+                }
+            };
 
-            let websocket_stream;
-            let response;
-            tokio::select! {
-                _ = self.read_runstate.wait_for(|runstate| !runstate.going_online()) => {
-                    return Ok(MinionExitReason::GotShutdownMessage);
-                },
-                connect_result = connect_future => {
-                    (websocket_stream, response) = connect_result??;
-                },
+            // Remember that this relay needs the subprotocol
+            if nostr_subprotocol && !self.dbrelay.nostr_subprotocol {
+                tracing::info!("{}: Relay requires the nostr subprotocol", &self.url);
+                self.dbrelay.nostr_subprotocol = true;
+                GLOBALS.db().modify_relay(
+                    &self.url,
+                    |relay| relay.nostr_subprotocol = true,
+                    None,
+                )?;
             }
 
             // Check the status code of the response
             if response.status().as_u16() == 4000 {
-                return Err(ErrorKind::RelayRejectedUs.into());
+                let rejection = RelayRejection::Blocked;
+                let message = "HTTP status 4000".to_owned();
+                self.record_rejection(rejection, &message)?;
+                return Err(ErrorKind::RelayRejectedUs(rejection, message).into());
+            }
+
+            // The Date header of the handshake is the relay's own clock
+            if let Some(date) = response
+                .headers()
+                .get(http::header::DATE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_http_date)
+            {
+                self.dbrelay.update_clock_skew(date - Unixtime::now().0);
+                let clock_skew = self.dbrelay.clock_skew;
+                GLOBALS.db().modify_relay(
+                    &self.url,
+                    |relay| relay.clock_skew = clock_skew,
+                    None,
+                )?;
             }
 
             tracing::debug!("{}: Connected", &self.url);
@@ -296,6 +468,14 @@ impl Minion {
         // Bump the success count for the relay
         self.bump_success_count(true).await;
 
+        // If this relay required AUTH last time and we would authenticate without
+        // asking, hold our initial subscriptions back briefly for the challenge so
+        // they are not refused first.
+        if self.dbrelay.last_auth_at.is_some() && self.auth_preapproved() {
+            self.anticipate_auth_until =
+                Some(Unixtime(Unixtime::now().0 + AUTH_CHALLENGE_WAIT_SECS));
+        }
+
         // Handle initial messages
         for message in messages.drain(..) {
             self.handle_overlord_message(message).await?;
@@ -327,6 +507,8 @@ impl Minion {
                     #[allow(clippy::if_same_then_else)]
                     if let ErrorKind::Websocket(_) = e.kind {
                         return Err(e);
+                    } else if let ErrorKind::RelayRejectedUs(..) = e.kind {
+                        return Err(e);
                     } else if matches!(e.kind, ErrorKind::Nostr(nostr_types::Error::NoPrivateKey)) {
                         // don't log
                     } else if matches!(e.kind, ErrorKind::NoPrivateKey) {
@@ -408,6 +590,11 @@ impl Minion {
                     match serde_json::from_str::<RelayInformationDocument>(&text) {
                         Ok(nip11) => {
                             tracing::debug!("{}: {}", &self.url, nip11);
+                            crate::relay::note_nip11_change(
+                                &self.url,
+                                self.dbrelay.nip11.as_ref(),
+                                &nip11,
+                            )?;
                             self.nip11 = Some(nip11);
                             self.dbrelay.nip11 = self.nip11.clone();
                         }
@@ -445,8 +632,21 @@ impl Minion {
             },
             _ = ping_timer.tick() => {
                 ws_stream.send(WsMessage::Ping(vec![0x1])).await?;
+                if self.ping_sent_at.is_none() {
+                    self.ping_sent_at = Some(Unixtime::now());
+                }
             },
             _ = task_timer.tick()  => { // 2.5 seconds
+                // If a ping went unanswered, the connection is probably dead (e.g. after the
+                // machine slept or the network changed). Close it so the overlord reconnects.
+                if let Some(sent_at) = self.ping_sent_at {
+                    if Unixtime::now() - sent_at > Duration::from_secs(PONG_TIMEOUT_SECS) {
+                        tracing::info!("{}: no pong within {} seconds", &self.url, PONG_TIMEOUT_SECS);
+                        self.exiting = Some(MinionExitReason::PongTimeout);
+                        return Ok(());
+                    }
+                }
+
                 // Update subscription for sought events
                 self.get_events().await?;
                 self.get_naddrs().await?;
@@ -469,7 +669,25 @@ impl Minion {
             },
             ws_message = ws_stream.next() => {
                 let ws_message = match ws_message {
-                    Some(m) => m,
+                    Some(Ok(m)) => m,
+                    Some(Err(e)) => {
+                        // The stream can't be read past a message that was too large, but
+                        // the relay itself is fine, so just reconnect
+                        if let ErrorKind::Websocket(tungstenite::error::Error::Capacity(
+                            tungstenite::error::CapacityError::MessageTooLong { size, max_size },
+                        )) = &e.kind
+                        {
+                            tracing::warn!(
+                                "{}: Sent a message of {} bytes, over our limit of {} bytes",
+                                &self.url,
+                                size,
+                                max_size
+                            );
+                            self.exiting = Some(MinionExitReason::MessageTooLarge);
+                            return Ok(());
+                        }
+                        return Err(e);
+                    }
                     None => {
                         if ws_stream.is_terminated() {
                             // possibly connection reset
@@ -478,9 +696,13 @@ impl Minion {
                         }
                         return Ok(());
                     }
-                }?;
+                };
 
                 GLOBALS.bytes_read.fetch_add(ws_message.len(), Ordering::Relaxed);
+                relay_metrics::record_bytes(&self.url, ws_message.len());
+
+                // Any traffic from the relay shows the connection is alive
+                self.ping_sent_at = None;
 
                 tracing::trace!("{}: Handling message", &self.url);
                 match ws_message {
@@ -577,6 +799,17 @@ impl Minion {
                     );
                 }
             }
+            ToMinionPayloadDetail::CountSubscribe(filter) => {
+                // COUNT requests are answered once and need no CLOSE
+                let subid = format!("count{}", message.job_id);
+                self.count_jobs.insert(subid.clone(), message.job_id);
+                let msg = ClientMessage::Count(SubscriptionId(subid), filter);
+                let wire = serde_json::to_string(&msg)?;
+                let ws_stream = self.stream.as_mut().unwrap();
+                tracing::trace!("{}: Sending {}", &self.url, &wire);
+                self.last_message_sent = wire.clone();
+                ws_stream.send(WsMessage::Text(wire)).await?;
+            }
             ToMinionPayloadDetail::FetchEvent(id) => {
                 // We don't ask the relay immediately. See task_timer.
                 self.sought_events
@@ -615,10 +848,17 @@ impl Minion {
                     tracing::info!("Posted event kind={} to {}", kind, &self.url);
                 }
             }
+            ToMinionPayloadDetail::Reconnect => {
+                tracing::debug!("{}: Websocket listener reconnecting", &self.url);
+                self.exiting = Some(MinionExitReason::GotReconnectMessage);
+            }
             ToMinionPayloadDetail::Shutdown => {
                 tracing::debug!("{}: Websocket listener shutting down", &self.url);
                 self.exiting = Some(MinionExitReason::GotShutdownMessage);
             }
+            ToMinionPayloadDetail::SkipEventsBefore(cutoff) => {
+                self.dbrelay.skip_events_before = cutoff; // save in our memory copy of the relay
+            }
             ToMinionPayloadDetail::Subscribe(filter_set) => {
                 let handle = filter_set.handle(message.job_id);
 
@@ -631,26 +871,79 @@ impl Minion {
                 // If we aren't running it already, OR if it can have duplicates
                 if !self.subscription_map.has(&handle) || filter_set.can_have_duplicates() {
                     let spamsafe = self.dbrelay.has_usage_bits(Relay::SPAMSAFE);
-                    if let Some(filter) = filter_set.filter(spamsafe) {
+                    if let Some(mut filter) = filter_set.filter(spamsafe) {
+                        // Only ask for config events newer than this relay gave us before
+                        if matches!(filter_set, FilterSet::Config) {
+                            filter.since = self.config_since()?;
+                        }
+
+                        // Give relays with bad clocks more overlap
+                        let overlap = self.dbrelay.since_overlap();
+                        if let Some(since) = filter.since {
+                            filter.since = Some(Unixtime(since.0 - overlap));
+                        }
+
+                        // Don't reach further back than the user allows for this relay.
+                        // Loading more is explicit, and giftwraps are deliberately backdated,
+                        // so those are left alone.
+                        if let Some(max_age) = self.dbrelay.max_backfill_age {
+                            if filter.until.is_none()
+                                && !filter_set.is_loading_more()
+                                && !matches!(filter_set, FilterSet::Giftwraps(_))
+                            {
+                                let floor = Unixtime(Unixtime::now().0 - max_age as i64);
+                                if let Some(since) = filter.since {
+                                    if since < floor {
+                                        filter.since = Some(floor);
+                                    }
+                                }
+                            }
+                        }
                         self.subscribe(filter, &handle, message.job_id).await?;
                     }
                 } else {
                     // It does not allow duplicates and we are already running it,
                     // but maybe we can save it for later...
 
-                    if let FilterSet::Metadata(pubkeys) = filter_set {
-                        // Save for later
-                        self.subscriptions_waiting_for_metadata
-                            .push((message.job_id, pubkeys));
+                    match filter_set {
+                        FilterSet::Metadata(pubkeys) => {
+                            // Save for later
+                            self.subscriptions_waiting_for_metadata
+                                .push((message.job_id, pubkeys));
+                        }
+                        FilterSet::Engagement { .. } => {
+                            // Our recent notes changed, replace the filter
+                            let spamsafe = self.dbrelay.has_usage_bits(Relay::SPAMSAFE);
+                            if let Some(mut filter) = filter_set.filter(spamsafe) {
+                                if let Some(since) = filter.since {
+                                    let overlap = self.dbrelay.since_overlap();
+                                    filter.since = Some(Unixtime(since.0 - overlap));
+                                }
+                                self.subscribe(filter, &handle, message.job_id).await?;
+                            }
+                        }
+                        FilterSet::CustomFeedFuture { .. } => {
+                            // The custom feed was redefined, replace the filter
+                            let spamsafe = self.dbrelay.has_usage_bits(Relay::SPAMSAFE);
+                            if let Some(filter) = filter_set.filter(spamsafe) {
+                                self.subscribe(filter, &handle, message.job_id).await?;
+                            }
+                        }
+                        _ => {}
                     }
                 }
             }
             ToMinionPayloadDetail::Unsubscribe(filter_set) => {
-                let handles = self
-                    .subscription_map
-                    .get_all_handles_matching(filter_set.inner_handle());
-                for handle in handles {
-                    self.unsubscribe(&handle).await?;
+                if matches!(filter_set, FilterSet::CustomFeedFuture { .. }) {
+                    // Only this custom feed, not all of them
+                    self.unsubscribe(&filter_set.handle(message.job_id)).await?;
+                } else {
+                    let handles = self
+                        .subscription_map
+                        .get_all_handles_matching(filter_set.inner_handle());
+                    for handle in handles {
+                        self.unsubscribe(&handle).await?;
+                    }
                 }
             }
             ToMinionPayloadDetail::UnsubscribeReplies => {
@@ -732,6 +1025,33 @@ impl Minion {
         Ok(())
     }
 
+    // Where to pick our config subscription up from on this relay. We start over
+    // if we don't have our config events anymore.
+    fn config_since(&self) -> Result<Option<Unixtime>, Error> {
+        let pubkey = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => return Ok(None),
+        };
+        let have_config = GLOBALS
+            .db()
+            .get_replaceable_event(EventKind::ContactList, pubkey, "")?
+            .is_some()
+            || GLOBALS
+                .db()
+                .get_replaceable_event(EventKind::RelayList, pubkey, "")?
+                .is_some();
+        if !have_config {
+            return Ok(None);
+        }
+
+        // Read it fresh, it is cleared when the identity changes
+        Ok(GLOBALS
+            .db()
+            .read_relay(&self.url)?
+            .and_then(|relay| relay.last_config_eose_at)
+            .map(|at| Unixtime(at as i64)))
+    }
+
     // This is run every tick
     async fn try_subscribe_waiting(&mut self) -> Result<(), Error> {
         // Subscribe to metadata that is waiting (unless we already have a
@@ -784,6 +1104,21 @@ impl Minion {
             }
         }
 
+        // If the AUTH challenge we anticipated never came (or AUTH failed), stop
+        // holding subscriptions back for it
+        if let Some(until) = self.anticipate_auth_until {
+            if matches!(self.auth_state, AuthState::None | AuthState::Failed)
+                && Unixtime::now() >= until
+            {
+                self.anticipate_auth_until = None;
+                let mut handles = std::mem::take(&mut self.subscriptions_waiting_for_auth);
+                for (handle, _) in handles.drain() {
+                    tracing::info!("Sending held subscription {} to {}", handle, &self.url);
+                    self.send_subscription(&handle).await?;
+                }
+            }
+        }
+
         // Retry rate-limited subscriptions
         if !self.subscriptions_rate_limited.is_empty() {
             let mut handles = std::mem::take(&mut self.subscriptions_rate_limited);
@@ -829,6 +1164,7 @@ impl Minion {
             sub.set_filter(filter);
             let old_job_id = sub.change_job_id(job_id);
             let id = sub.get_id();
+            self.subscription_map.publish();
             tracing::debug!(
                 "UPDATED SUBSCRIPTION on {} handle={}, id={}",
                 &self.url,
@@ -850,7 +1186,7 @@ impl Minion {
             );
         }
 
-        if self.auth_state.is_waiting() {
+        if self.auth_state.is_waiting() || self.anticipating_auth() {
             // Save this, subscribe after AUTH completes
             self.subscriptions_waiting_for_auth
                 .insert(handle.to_owned(), Unixtime::now());
@@ -911,9 +1247,82 @@ impl Minion {
         Ok(())
     }
 
+    // An event came in under a subscription we don't have open. A few may still be
+    // in flight when we close a subscription, but a relay that keeps sending after
+    // CLOSE, or uses ids we never sent, is asked (once) to close it.
+    async fn ignore_stray_event(&mut self, subid: SubscriptionId) -> Result<(), Error> {
+        // How many events after a CLOSE we put down to them being in flight
+        const IN_FLIGHT_ALLOWANCE: usize = 10;
+
+        let closed = self.subscription_map.was_closed(&subid.0);
+        let count = {
+            let count = self.stray_events.entry(subid.0.clone()).or_insert(0);
+            *count += 1;
+            *count
+        };
+
+        let misbehaving = if closed {
+            count == IN_FLIGHT_ALLOWANCE + 1
+        } else {
+            count == 1
+        };
+        if !misbehaving {
+            tracing::trace!(
+                "{}: ignoring event on closed subscription {}",
+                &self.url,
+                subid.0
+            );
+            return Ok(());
+        }
+
+        if closed {
+            tracing::warn!(
+                "{}: still sending events on subscription {} after we closed it",
+                &self.url,
+                subid.0
+            );
+        } else {
+            tracing::warn!(
+                "{}: sent an event on subscription {} which we never opened",
+                &self.url,
+                subid.0
+            );
+        }
+
+        let wire = serde_json::to_string(&ClientMessage::Close(subid))?;
+        let websocket_stream = self.stream.as_mut().unwrap();
+        tracing::trace!("{}: Sending {}", &self.url, &wire);
+        self.last_message_sent = wire.clone();
+        websocket_stream.send(WsMessage::Text(wire)).await?;
+        Ok(())
+    }
+
+    // The user's AUTH decision for this relay, whether remembered or for this run only
+    fn allow_auth(&self) -> Option<bool> {
+        self.dbrelay
+            .allow_auth
+            .or_else(|| GLOBALS.relay_auth_decisions.get(&self.url).map(|d| *d))
+    }
+
+    // If we would AUTH to this relay without asking the user
+    fn auth_preapproved(&self) -> bool {
+        if !GLOBALS.identity.is_unlocked() {
+            return false;
+        }
+        !GLOBALS.db().read_setting_relay_auth_requires_approval() || self.allow_auth() == Some(true)
+    }
+
+    // If we are holding subscriptions back for an AUTH challenge we expect
+    fn anticipating_auth(&self) -> bool {
+        match self.anticipate_auth_until {
+            Some(until) => self.auth_state == AuthState::None && Unixtime::now() < until,
+            None => false,
+        }
+    }
+
     async fn maybe_authenticate(&mut self) -> Result<(), Error> {
         if GLOBALS.db().read_setting_relay_auth_requires_approval() {
-            match self.dbrelay.allow_auth {
+            match self.allow_auth() {
                 Some(true) => self.real_authenticate().await?,
                 Some(false) => self.fake_authenticate().await?,
                 None => {
@@ -1039,8 +1448,12 @@ impl Minion {
         // Update in self
         self.dbrelay.failure_count += 1;
 
-        // Save to storage
-        if let Err(e) = GLOBALS.db().write_relay(&self.dbrelay, None) {
+        // Save to storage (modifying the stored record, which may have newer
+        // ingestion metrics than our copy)
+        if let Err(e) = GLOBALS
+            .db()
+            .modify_relay(&self.url, |r| r.failure_count += 1, None)
+        {
             tracing::error!("{}: ERROR bumping relay failure count: {}", &self.url, e);
         }
     }
@@ -1054,9 +1467,104 @@ impl Minion {
             self.dbrelay.last_connected_at = Some(now);
         }
 
-        // Save to storage
-        if let Err(e) = GLOBALS.db().write_relay(&self.dbrelay, None) {
+        // Save to storage (modifying the stored record, which may have newer
+        // ingestion metrics than our copy)
+        if let Err(e) = GLOBALS.db().modify_relay(
+            &self.url,
+            |r| {
+                r.success_count += 1;
+                if also_bump_last_connected {
+                    r.last_connected_at = Some(now);
+                }
+            },
+            None,
+        ) {
             tracing::error!("{}: ERROR bumping relay success count: {}", &self.url, e);
         }
     }
+
+    // Remember why the relay rejected us, for display
+    fn record_rejection(&mut self, rejection: RelayRejection, message: &str) -> Result<(), Error> {
+        let last_rejection = Some((rejection, message.to_owned(), Unixtime::now().0 as u64));
+        self.dbrelay.last_rejection = last_rejection.clone();
+        GLOBALS.db().modify_relay(
+            &self.url,
+            |r| r.last_rejection = last_rejection.clone(),
+            None,
+        )?;
+        Ok(())
+    }
+
+    // If the relay message is a rejection, record it. If the relay keeps rejecting us
+    // for a relay-wide reason, return an error so the minion exits and the overlord
+    // excludes the relay. Other rejections may only apply to one event or filter.
+    fn check_rejection(&mut self, message: &str) -> Result<(), Error> {
+        if let Some(rejection) = RelayRejection::from_message(message) {
+            self.record_rejection(rejection, message)?;
+            if rejection.is_relay_wide() {
+                self.relay_wide_rejections += 1;
+                if self.relay_wide_rejections >= MAX_RELAY_WIDE_REJECTIONS {
+                    return Err(ErrorKind::RelayRejectedUs(rejection, message.to_owned()).into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784111777)
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(1709164800)
+        );
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 PST"), None);
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_websocket_request_subprotocol() {
+        let url = RelayUrl::try_from_str("wss://relay.example.com").unwrap();
+
+        let req = websocket_request(&url, false, false, None).unwrap();
+        assert!(req.headers().get("Sec-WebSocket-Protocol").is_none());
+        assert_eq!(req.headers().get("Host").unwrap(), "relay.example.com");
+
+        let req = websocket_request(&url, true, false, None).unwrap();
+        assert_eq!(
+            req.headers().get("Sec-WebSocket-Protocol").unwrap(),
+            "nostr"
+        );
+        assert_eq!(req.headers().get("Upgrade").unwrap(), "websocket");
+    }
+
+    #[test]
+    fn test_websocket_request_credential() {
+        let url = RelayUrl::try_from_str("wss://relay.example.com").unwrap();
+
+        let credential = RelayCredential::Header {
+            name: "Authorization".to_owned(),
+            value: "Bearer abc".to_owned(),
+        };
+        let req = websocket_request(&url, false, false, Some(&credential)).unwrap();
+        assert_eq!(req.headers().get("Authorization").unwrap(), "Bearer abc");
+        assert_eq!(req.uri().query(), None);
+
+        let credential = RelayCredential::UrlParameter {
+            name: "token".to_owned(),
+            value: "a b".to_owned(),
+        };
+        let req = websocket_request(&url, false, false, Some(&credential)).unwrap();
+        assert_eq!(req.uri().query(), Some("token=a+b"));
+        assert_eq!(req.headers().get("Host").unwrap(), "relay.example.com");
+        assert!(!format!("{:?}", credential).contains("a b"));
+    }
 }
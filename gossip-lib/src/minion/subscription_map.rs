@@ -1,20 +1,26 @@
 use super::subscription::Subscription;
-use nostr_types::Filter;
-use std::collections::HashMap;
+use crate::globals::GLOBALS;
+use crate::relay_metrics::ActiveSubscription;
+use nostr_types::{Filter, RelayUrl};
+use std::collections::{HashMap, HashSet};
 
 // handle is a coder-friendly string like "general_feed"
 // id is a short numeric string like "0", counting up from 0.
 pub struct SubscriptionMap {
+    url: RelayUrl,
     handle_to_id: HashMap<String, String>,
     by_id: HashMap<String, Subscription>,
+    closed: HashSet<String>,
     count: usize,
 }
 
 impl SubscriptionMap {
-    pub fn new() -> SubscriptionMap {
+    pub fn new(url: RelayUrl) -> SubscriptionMap {
         SubscriptionMap {
+            url,
             handle_to_id: HashMap::new(),
             by_id: HashMap::new(),
+            closed: HashSet::new(),
             count: 0,
         }
     }
@@ -25,6 +31,7 @@ impl SubscriptionMap {
         self.count += 1;
         self.handle_to_id.insert(handle.to_owned(), id.clone());
         self.by_id.insert(id.clone(), sub);
+        self.publish();
         id
     }
 
@@ -83,16 +90,43 @@ impl SubscriptionMap {
             let id = id.to_owned();
             self.by_id.remove(&id);
             self.handle_to_id.remove(handle);
+            self.closed.insert(id.clone());
+            self.publish();
             Some(id)
         } else {
             None
         }
     }
 
+    /// Whether we had a subscription with this id and closed it. Ids are never reused.
+    pub fn was_closed(&self, id: &str) -> bool {
+        self.closed.contains(id)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.by_id.is_empty()
     }
 
+    /// Report our subscriptions in GLOBALS.relay_subscriptions. This is done on
+    /// add and remove; call it after changing a subscription's filter.
+    pub fn publish(&self) {
+        let mut subs: Vec<ActiveSubscription> = self
+            .handle_to_id
+            .iter()
+            .filter_map(|(handle, id)| {
+                self.by_id.get(id).map(|sub| ActiveSubscription {
+                    handle: handle.to_owned(),
+                    id: id.to_owned(),
+                    job_id: sub.get_job_id(),
+                    authors: sub.get_filter().authors.len(),
+                })
+            })
+            .collect();
+        subs.sort_by(|a, b| a.handle.cmp(&b.handle));
+        GLOBALS.relay_subscriptions.insert(self.url.clone(), subs);
+        crate::relay_metrics::check_filter_usage(&self.url);
+    }
+
     /*
         pub fn remove_by_id(&mut self, id: &str) {
             self.by_id.remove(id);
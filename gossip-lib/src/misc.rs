@@ -1,4 +1,5 @@
 use crate::error::Error;
+use crate::feed::FeedKind;
 use crate::globals::GLOBALS;
 use nostr_types::{Event, EventReference, Id, PayRequestData, PublicKey, UncheckedUrl};
 use std::ops::Deref;
@@ -13,6 +14,93 @@ pub enum ZapState {
     ReadyToPay(Id, String), // String is the Zap Invoice as a string, to be shown as a QR code
 }
 
+/// Somewhere the UI should navigate to, as requested from outside of the UI
+/// (e.g. by a `nostr:` URI handed to us by the OS)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Navigation {
+    Person(PublicKey),
+    Feed(FeedKind),
+}
+
+/// Ask the UI to navigate to an addressable event that we were waiting for,
+/// if this is it
+pub(crate) fn navigate_if_awaited(event: &Event) {
+    if !event.kind.is_parameterized_replaceable() {
+        return;
+    }
+
+    let mut awaited = GLOBALS.awaited_navigation.write();
+    let is_awaited = awaited.as_ref().is_some_and(|ea| {
+        ea.kind == event.kind
+            && ea.author == event.pubkey
+            && event.parameter().as_deref() == Some(ea.d.as_str())
+    });
+    if !is_awaited {
+        return;
+    }
+    *awaited = None;
+
+    *GLOBALS.navigation_request.write() = Some(Navigation::Feed(FeedKind::Thread {
+        id: event.id,
+        referenced_by: event.id,
+        author: Some(event.pubkey),
+    }));
+    GLOBALS.notify_ui_redraw.notify_waiters();
+}
+
+/// What to do with events whose created_at is further in the future than the
+/// `future_allowance_secs` setting allows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FutureEventAction {
+    /// Keep them, and show them by their own time once it arrives
+    Accept,
+
+    /// Keep them, but sort them by the time we first received them
+    Clamp,
+
+    /// Don't keep them
+    #[default]
+    Drop,
+}
+
+impl FutureEventAction {
+    /// All actions, for choosing from
+    pub fn all() -> &'static [FutureEventAction] {
+        &[
+            FutureEventAction::Accept,
+            FutureEventAction::Clamp,
+            FutureEventAction::Drop,
+        ]
+    }
+
+    /// A name to show the user
+    pub fn name(&self) -> &'static str {
+        match *self {
+            FutureEventAction::Accept => "Accept",
+            FutureEventAction::Clamp => "Sort by time received",
+            FutureEventAction::Drop => "Drop",
+        }
+    }
+
+    /// The value stored in the `future_event_action` setting
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            FutureEventAction::Accept => "accept",
+            FutureEventAction::Clamp => "clamp",
+            FutureEventAction::Drop => "drop",
+        }
+    }
+
+    /// Parse the `future_event_action` setting, defaulting if it is not recognized
+    pub fn from_setting(s: &str) -> FutureEventAction {
+        FutureEventAction::all()
+            .iter()
+            .find(|a| a.as_str() == s)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Freshness {
     NeverSought,
@@ -106,3 +194,50 @@ pub fn exponential_decay(base: f32, halflife_seconds: u64, elapsed_seconds: u64)
     let decay_constant = 2.0_f32.ln() / halflife_seconds as f32;
     base * E.powf(-decay_constant * elapsed_seconds as f32)
 }
+
+/// Clean up a private key as it comes from a QR code or OCR: drop any whitespace
+/// (QR text often wraps), drop a `nostr:` prefix, and lowercase it if it is all
+/// uppercase (QR alphanumeric mode only has uppercase). Mixed case bech32 is left
+/// alone so that it fails to parse, as bech32 requires.
+pub fn normalize_key_payload(payload: &str) -> String {
+    let mut key: String = payload.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if key.len() >= 6 && key[..6].eq_ignore_ascii_case("nostr:") {
+        key.drain(..6);
+    }
+
+    let is_hex = key.chars().all(|c| c.is_ascii_hexdigit());
+    if is_hex || !key.chars().any(|c| c.is_ascii_lowercase()) {
+        key.make_ascii_lowercase();
+    }
+
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NSEC: &str = "nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5";
+
+    #[test]
+    fn test_normalize_key_payload() {
+        assert_eq!(normalize_key_payload(NSEC), NSEC);
+        assert_eq!(normalize_key_payload(&format!("nostr:{NSEC}")), NSEC);
+        assert_eq!(normalize_key_payload(&format!("NOSTR:{NSEC}")), NSEC);
+        assert_eq!(
+            normalize_key_payload(&format!(" {}\n{} \r\n", &NSEC[..30], &NSEC[30..])),
+            NSEC
+        );
+        assert_eq!(normalize_key_payload(&NSEC.to_ascii_uppercase()), NSEC);
+
+        // Mixed case bech32 is not valid and is not fixed up
+        let mixed = format!("NSEC{}", &NSEC[4..]);
+        assert_eq!(normalize_key_payload(&mixed), mixed);
+        assert!(nostr_types::PrivateKey::try_from_bech32_string(&mixed).is_err());
+
+        // Hex is not case sensitive
+        let hex = "67DEA2ED018072D675F5415ECFAED7D2597555E202D85B3D65EA4E58D2D92FFA";
+        assert_eq!(normalize_key_payload(hex), hex.to_ascii_lowercase());
+    }
+}
@@ -2,7 +2,8 @@ use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
 use crate::misc::Private;
 use crate::people::{Person, PersonList};
-use nostr_types::{Metadata, Nip05, PublicKey, RelayUrl, Unixtime};
+use nostr_types::{Metadata, Nip05, PublicKey, PublicKeyHex, RelayUrl, Unixtime};
+use std::net::SocketAddr;
 use std::sync::atomic::Ordering;
 
 // This updates the people map and the database with the result
@@ -51,7 +52,7 @@ pub async fn validate_nip05(person: Person) -> Result<(), Error> {
 
     // Check if the response matches their public key
     let mut valid = false;
-    match nip05file.names.get(&user) {
+    match find_name(&nip05file, &user) {
         Some(pk) => {
             if let Ok(pubkey) = PublicKey::try_from_hex_string(pk, true) {
                 if pubkey == person.pubkey {
@@ -106,7 +107,7 @@ pub async fn get_and_follow_nip05(
     let nip05file = fetch_nip05(&user, &domain).await?;
 
     // Get their pubkey
-    let pubkey = match nip05file.names.get(&user) {
+    let pubkey = match find_name(&nip05file, &user) {
         Some(pk) => PublicKey::try_from_hex_string(pk, true)?,
         None => return Err((ErrorKind::Nip05KeyNotFound, file!(), line!()).into()),
     };
@@ -129,6 +130,32 @@ pub async fn get_and_follow_nip05(
     Ok(())
 }
 
+/// Resolve a NIP-05 identifier to a public key and the relays it lists for them,
+/// without following or saving anything
+pub async fn lookup_nip05(nip05: &str) -> Result<(PublicKey, Vec<RelayUrl>), Error> {
+    // Split their DNS ID
+    let (user, domain) = parse_nip05(nip05)?;
+
+    // Fetch NIP-05
+    let nip05file = fetch_nip05(&user, &domain).await?;
+
+    // Get their pubkey
+    let pubkey = match find_name(&nip05file, &user) {
+        Some(pk) => PublicKey::try_from_hex_string(pk, true)?,
+        None => return Err((ErrorKind::Nip05KeyNotFound, file!(), line!()).into()),
+    };
+
+    let relays: Vec<RelayUrl> = match nip05file.relays.get(&pubkey.into()) {
+        Some(relays) => relays
+            .iter()
+            .filter_map(|r| crate::relay::relay_url_from_unchecked(r).ok())
+            .collect(),
+        None => vec![],
+    };
+
+    Ok((pubkey, relays))
+}
+
 fn update_relays(nip05: &str, nip05file: Nip05, pubkey: &PublicKey) -> Result<(), Error> {
     // Set their relays
     let relays = match nip05file.relays.get(&(*pubkey).into()) {
@@ -137,7 +164,7 @@ fn update_relays(nip05: &str, nip05file: Nip05, pubkey: &PublicKey) -> Result<()
     };
     for relay in relays.iter() {
         // Save relay
-        if let Ok(relay_url) = RelayUrl::try_from_unchecked_url(relay) {
+        if let Ok(relay_url) = crate::relay::relay_url_from_unchecked(relay) {
             GLOBALS.db().write_relay_if_missing(&relay_url, None)?;
 
             // Update person_relay
@@ -158,8 +185,20 @@ fn update_relays(nip05: &str, nip05file: Nip05, pubkey: &PublicKey) -> Result<()
     Ok(())
 }
 
-// returns user and domain
+// Names are case-insensitive, but not every server lowercases them
+fn find_name<'a>(nip05file: &'a Nip05, user: &str) -> Option<&'a PublicKeyHex> {
+    nip05file.names.get(user).or_else(|| {
+        nip05file
+            .names
+            .iter()
+            .find(|(name, _)| name.to_lowercase() == user)
+            .map(|(_, pk)| pk)
+    })
+}
+
+// returns user and domain, lowercased as NIP-05 is case-insensitive
 pub fn parse_nip05(nip05: &str) -> Result<(String, String), Error> {
+    let nip05 = nip05.trim().to_lowercase();
     let mut parts: Vec<&str> = nip05.split('@').collect();
 
     // Add the underscore as a username if they just specified a domain name.
@@ -184,20 +223,47 @@ pub fn parse_nip05(nip05: &str) -> Result<(String, String), Error> {
 async fn fetch_nip05(user: &str, domain: &str) -> Result<Nip05, Error> {
     // FIXME add user-agent if configured
 
-    let nip05_future = reqwest::Client::builder()
+    let mut builder = reqwest::Client::builder()
         .timeout(std::time::Duration::new(60, 0))
         .redirect(reqwest::redirect::Policy::none()) // see NIP-05
         .gzip(true)
         .brotli(true)
-        .deflate(true)
+        .deflate(true);
+
+    // Resolve the domain first, so that we can tell DNS failures apart, and then
+    // connect to what we resolved rather than resolving it again. Through a proxy
+    // the proxy does the resolving.
+    if !https_proxy_configured() {
+        let host = domain.split(':').next().unwrap_or(domain);
+        let addrs: Vec<SocketAddr> = match tokio::net::lookup_host((host, 443)).await {
+            Ok(addrs) => addrs.collect(),
+            Err(e) => return Err(ErrorKind::Nip05DnsFailed(format!("{domain}: {e}")).into()),
+        };
+        builder = builder.resolve_to_addrs(host, &addrs);
+    }
+
+    let nip05_future = builder
         .build()?
         .get(format!(
             "https://{}/.well-known/nostr.json?name={}",
             domain, user
         ))
         .send();
-    let response = nip05_future.await?;
+    let response = nip05_future
+        .await
+        .map_err(|e| ErrorKind::Nip05HttpFailed(format!("{domain}: {e}")))?;
+    if !response.status().is_success() {
+        return Err(ErrorKind::Nip05HttpFailed(format!("{domain}: {}", response.status())).into());
+    }
     let bytes = response.bytes().await?;
     GLOBALS.bytes_read.fetch_add(bytes.len(), Ordering::Relaxed);
     Ok(serde_json::from_slice(&bytes)?)
 }
+
+// Whether reqwest will send https requests through a proxy, which it takes from
+// the environment
+fn https_proxy_configured() -> bool {
+    ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .any(|var| std::env::var(var).is_ok_and(|v| !v.is_empty()))
+}
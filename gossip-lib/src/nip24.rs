@@ -0,0 +1,113 @@
+//! NIP-24 extra metadata fields
+//!
+//! `Metadata` only models the original NIP-01 fields. Everything else lands in
+//! `Metadata::other` and is written back out as-is, so fields we do not understand
+//! survive a round trip. These helpers read and write the common NIP-24 fields in
+//! `other` without disturbing anything else there.
+
+use nostr_types::Metadata;
+use serde_json::map::Map;
+use serde_json::value::Value;
+
+pub const DISPLAY_NAME: &str = "display_name";
+pub const WEBSITE: &str = "website";
+pub const BANNER: &str = "banner";
+pub const BOT: &str = "bot";
+pub const BIRTHDAY: &str = "birthday";
+
+/// All of the NIP-24 fields handled here
+pub const FIELDS: &[&str] = &[DISPLAY_NAME, WEBSITE, BANNER, BOT, BIRTHDAY];
+
+/// A NIP-24 birthday. Any part may be omitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Birthday {
+    pub year: Option<u16>,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+}
+
+/// Get a string field
+pub fn get_string<'a>(metadata: &'a Metadata, field: &str) -> Option<&'a str> {
+    match metadata.other.get(field) {
+        Some(Value::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+/// Set a string field, or remove it if `value` is None
+pub fn set_string(metadata: &mut Metadata, field: &str, value: Option<String>) {
+    match value {
+        Some(s) => {
+            metadata.other.insert(field.to_owned(), Value::String(s));
+        }
+        None => {
+            metadata.other.remove(field);
+        }
+    }
+}
+
+/// If the profile is marked as a bot
+pub fn bot(metadata: &Metadata) -> bool {
+    match metadata.other.get(BOT) {
+        Some(Value::Bool(b)) => *b,
+        // Some clients wrote it as a string
+        Some(Value::String(s)) => s == "true",
+        _ => false,
+    }
+}
+
+/// Mark or unmark the profile as a bot. Unmarking removes the field.
+pub fn set_bot(metadata: &mut Metadata, bot: bool) {
+    if bot {
+        metadata.other.insert(BOT.to_owned(), Value::Bool(true));
+    } else {
+        metadata.other.remove(BOT);
+    }
+}
+
+/// The profile's birthday, if it has one
+pub fn birthday(metadata: &Metadata) -> Option<Birthday> {
+    let map = match metadata.other.get(BIRTHDAY) {
+        Some(Value::Object(map)) => map,
+        _ => return None,
+    };
+    let part = |key: &str| map.get(key).and_then(|v| v.as_u64());
+    Some(Birthday {
+        year: part("year").and_then(|n| u16::try_from(n).ok()),
+        month: part("month").and_then(|n| u8::try_from(n).ok()),
+        day: part("day").and_then(|n| u8::try_from(n).ok()),
+    })
+}
+
+/// Set the profile's birthday, or remove it if `birthday` is None.
+///
+/// Keys inside an existing birthday object other than year, month and day are
+/// left alone.
+pub fn set_birthday(metadata: &mut Metadata, birthday: Option<Birthday>) {
+    let birthday = match birthday {
+        Some(b) => b,
+        None => {
+            metadata.other.remove(BIRTHDAY);
+            return;
+        }
+    };
+
+    let mut map = match metadata.other.remove(BIRTHDAY) {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    let mut set = |key: &str, value: Option<u64>| match value {
+        Some(n) => {
+            map.insert(key.to_owned(), Value::from(n));
+        }
+        None => {
+            map.remove(key);
+        }
+    };
+    set("year", birthday.year.map(|n| n as u64));
+    set("month", birthday.month.map(|n| n as u64));
+    set("day", birthday.day.map(|n| n as u64));
+    metadata
+        .other
+        .insert(BIRTHDAY.to_owned(), Value::Object(map));
+}
@@ -0,0 +1,79 @@
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use nostr_types::{Event, EventKind, EventReference};
+
+/// Notification type, aliased to the latest version
+pub type Notification = crate::storage::types::Notification1;
+
+/// Notification kind type, aliased to the latest version
+pub type NotificationKind = crate::storage::types::NotificationKind1;
+
+/// Record a notification if this event is something another person did that
+/// targets the user. Returns true if a new notification was stored.
+pub(crate) fn process_notifications(event: &Event) -> Result<bool, Error> {
+    let my_pubkey = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => return Ok(false),
+    };
+
+    // Our own events never notify us
+    if event.pubkey == my_pubkey {
+        return Ok(false);
+    }
+
+    let mut notification = Notification {
+        id: event.id,
+        kind: NotificationKind::Mention,
+        from: event.pubkey,
+        target: None,
+        created_at: event.created_at,
+        read: false,
+    };
+
+    if let Some((EventReference::Id { id, .. }, reaction)) = event.reacts_to() {
+        if !GLOBALS.db().is_my_event(id)? {
+            return Ok(false);
+        }
+        notification.kind = NotificationKind::Reaction(reaction);
+        notification.target = Some(id);
+    } else if event.kind == EventKind::Zap {
+        let zapdata = match event.zaps() {
+            Ok(Some(zapdata)) => zapdata,
+            _ => return Ok(false),
+        };
+        if !event.is_tagged(&my_pubkey) {
+            return Ok(false);
+        }
+        notification.kind = NotificationKind::Zap(zapdata.amount);
+        notification.from = zapdata.payer;
+        if let EventReference::Id { id, .. } = zapdata.zapped_event {
+            notification.target = Some(id);
+        }
+    } else if event.kind == EventKind::ContactList {
+        if !event.is_tagged(&my_pubkey) {
+            return Ok(false);
+        }
+        notification.kind = NotificationKind::NewFollower;
+    } else if event.kind.is_feed_displayable() {
+        match event.replies_to() {
+            Some(EventReference::Id { id, .. }) if GLOBALS.db().is_my_event(id)? => {
+                notification.kind = NotificationKind::Reply;
+                notification.target = Some(id);
+            }
+            Some(EventReference::Addr(ea)) if ea.author == my_pubkey => {
+                notification.kind = NotificationKind::Reply;
+            }
+            _ => {
+                if !event.is_tagged(&my_pubkey)
+                    && !event.people_referenced_in_content().contains(&my_pubkey)
+                {
+                    return Ok(false);
+                }
+            }
+        }
+    } else {
+        return Ok(false);
+    }
+
+    GLOBALS.db().write_notification(&notification, None)
+}
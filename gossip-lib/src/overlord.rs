@@ -10,28 +10,29 @@ use crate::filter_set::{FeedRange, FilterSet};
 use crate::globals::GLOBALS;
 use crate::manager;
 use crate::minion::MinionExitReason;
-use crate::misc::{Private, ZapState};
+use crate::misc::{Navigation, Private, ZapState};
 use crate::nostr_connect_server::{Approval, ParsedCommand};
 use crate::pending::PendingItem;
-use crate::people::{Person, PersonList};
+use crate::people::{Person, PersonList, PersonListDiff};
+use crate::person_relay::PersonRelay;
 use crate::relay;
-use crate::relay::Relay;
+use crate::relay::{Relay, RelayRejection};
+use crate::relay_metrics;
 use crate::relay_picker::RelayAssignment;
 use crate::relay_test_results::{RelayTestResult, RelayTestResults};
-use crate::storage::types::{HandlerKey, ScoreFactors};
-use crate::storage::{PersonTable, Table};
+use crate::storage::types::{CustomFeed1, HandlerKey, ScoreFactors};
+use crate::storage::{PersonTable, Rebuild, Storage, Table};
 use crate::RunState;
 use heed::RwTxn;
 use http::StatusCode;
 use nostr_types::{
     EncryptedPrivateKey, Event, EventKind, EventReference, Filter, Id, Metadata, MilliSatoshi,
-    NAddr, NostrBech32, ParsedTag, PayRequestData, PreEvent, PrivateKey, Profile, PublicKey,
-    RelayUrl, Tag, UncheckedUrl, Unixtime,
+    NAddr, NostrBech32, NostrUrl, ParsedTag, PayRequestData, PreEvent, PrivateKey, Profile,
+    PublicKey, RelayInformationDocument, RelayUrl, Tag, UncheckedUrl, Unixtime,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
-use std::sync::mpsc;
 use std::time::Duration;
 #[cfg(unix)]
 use tokio::signal::unix::{signal, SignalKind};
@@ -45,6 +46,25 @@ use zeroize::Zeroize;
 
 type MinionResult = Result<MinionExitReason, Error>;
 
+// How long relay changes must settle before the relay list is auto-advertised
+const AUTO_ADVERTISE_DEBOUNCE_SECS: u64 = 30;
+
+// Relays the user connected to for a feed are reconnected to on startup for this long
+const MANUAL_RELAY_MAX_AGE_DAYS: i64 = 14;
+
+// How many times in a row a relay may send a message over our size limit before we
+// give up on the jobs that keep fetching it
+const MAX_OVERSIZED_MESSAGES: u32 = 4;
+
+// The outcome of probing a relay in a health check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RelayHealth {
+    Healthy,
+    Unresponsive,
+    Unreachable,
+    TimedOut,
+}
+
 /// The overlord handles any operation that involves talking to relays, and a few more.
 ///
 /// There are two ways to engage the Overlord to do something:
@@ -57,6 +77,9 @@ pub struct Overlord {
     to_minions: Sender<ToMinionMessage>,
     inbox: UnboundedReceiver<ToOverlordMessage>,
     read_runstate: WatchReceiver<RunState>,
+
+    // The task gathering relays and ancestors for the current thread feed
+    thread_gathering: Option<task::JoinHandle<()>>,
 }
 
 impl Overlord {
@@ -103,6 +126,7 @@ impl Overlord {
             to_minions,
             inbox,
             read_runstate: GLOBALS.read_runstate.clone(),
+            thread_gathering: None,
         }
     }
 
@@ -113,6 +137,10 @@ impl Overlord {
             tracing::error!("{}", e);
         }
 
+        if let Err(e) = relay_metrics::save() {
+            tracing::error!("{}", e);
+        }
+
         if let Err(e) = GLOBALS.db().sync() {
             tracing::error!("{}", e);
         } else {
@@ -294,6 +322,16 @@ impl Overlord {
     }
 
     async fn pick_relays(&mut self) {
+        // In quiet mode we do not follow anybody
+        if GLOBALS.quiet_mode.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // While connecting only to chosen relays, we don't pick any
+        if GLOBALS.connect_only.read().is_some() {
+            return;
+        }
+
         // Garbage collect
         match GLOBALS.relay_picker.garbage_collect().await {
             Ok(mut idle) => {
@@ -367,9 +405,9 @@ impl Overlord {
         ];
 
         // Until NIP-65 is in widespread use, we should listen to inbox
-        // of us on all these relays too
-        // Only do this if we aren't already doing it.
-        let mut fetch_inbox = true;
+        // of us on all these relays too (unless the user relies on their READ
+        // relays alone). Only do this if we aren't already doing it.
+        let mut fetch_inbox = GLOBALS.db().read_setting_fetch_inbox_on_feed_relays();
         if let Some(jobs) = GLOBALS.connected_relays.get(&assignment.relay_url) {
             for job in &*jobs {
                 if job.reason == RelayConnectionReason::FetchInbox {
@@ -417,6 +455,10 @@ impl Overlord {
         // Remove from our hashmap
         GLOBALS.minions_task_url.remove(&id);
 
+        // Its subscriptions are gone with it
+        GLOBALS.relay_subscriptions.remove(&url);
+        GLOBALS.relays_near_filter_limit.remove(&url);
+
         // Set to not connected, and take any unfinished jobs
         let mut relayjobs = GLOBALS
             .connected_relays
@@ -441,11 +483,33 @@ impl Overlord {
                     } else {
                         tracing::info!("Minion {} completed: {:?}", &url, exitreason);
                     }
+                    if exitreason != MinionExitReason::MessageTooLarge {
+                        GLOBALS.relays_oversized_messages.remove(&url);
+                    }
                     exclusion = match exitreason {
                         MinionExitReason::GotDisconnected => 60 * 2,
+                        MinionExitReason::GotReconnectMessage => 1, // restart persistent jobs promptly
                         MinionExitReason::GotShutdownMessage => 0,
                         MinionExitReason::GotWSClose => 60 * 2,
                         MinionExitReason::LostOverlord => 0,
+                        MinionExitReason::MessageTooLarge => {
+                            // Resubscribing will likely fetch the same message again, so
+                            // back off more each time, and eventually drop the jobs
+                            let mut count = GLOBALS
+                                .relays_oversized_messages
+                                .entry(url.clone())
+                                .or_insert(0);
+                            *count += 1;
+                            if *count >= MAX_OVERSIZED_MESSAGES {
+                                relayjobs = vec![];
+                                GLOBALS.status_queue.write().write(format!(
+                                    "{} keeps sending messages that are too large, giving up on it for now.",
+                                    &url
+                                ));
+                            }
+                            15 * 4_u64.pow((*count).min(MAX_OVERSIZED_MESSAGES) - 1)
+                        }
+                        MinionExitReason::PongTimeout => 15, // likely slept or network changed
                         MinionExitReason::SubscriptionsCompletedSuccessfully => {
                             // The jobs completed but we didn't get messages for them before the
                             // minion exited. Clear those jobs.
@@ -460,8 +524,19 @@ impl Overlord {
                     Self::bump_failure_count(&url);
                     tracing::warn!("Minion {} completed with error: {}", &url, e);
                     exclusion = 60 * 2;
-                    if let ErrorKind::RelayRejectedUs = e.kind {
-                        exclusion = 60 * 10;
+                    if let ErrorKind::RelayRejectedUs(rejection, _) = e.kind {
+                        exclusion = match rejection {
+                            RelayRejection::RateLimited => 60,
+                            RelayRejection::AuthRequired => {
+                                GLOBALS.status_queue.write().write(format!(
+                                    "{} requires authentication. Allow AUTH to use it.",
+                                    &url
+                                ));
+                                60 * 2
+                            }
+                            // The handshake was refused outright
+                            _ => 60 * 60 * 6,
+                        };
                     } else if let ErrorKind::ReqwestHttpError(_) = e.kind {
                         exclusion = 60 * 10;
                     } else if let ErrorKind::Timeout(_) = e.kind {
@@ -586,7 +661,32 @@ impl Overlord {
         }
     }
 
+    // Report the failure of work that was spawned to run on its own, which has no
+    // caller to hand the error back to
+    fn report_spawned_error(e: Error) {
+        tracing::error!("{}", e);
+
+        // Cancelled proof of work was already reported when it stopped
+        if !matches!(e.kind, ErrorKind::PowCancelled) {
+            GLOBALS.status_queue.write().write(format!("{}", e));
+        }
+    }
+
     async fn handle_message(&mut self, message: ToOverlordMessage) -> Result<(), Error> {
+        // Don't start what we can't finish if the key is locked
+        if message.needs_signer()
+            && GLOBALS.identity.has_private_key()
+            && !GLOBALS.identity.is_unlocked()
+        {
+            Self::hold_for_signer(message);
+            return Ok(());
+        }
+
+        // Signing something counts as activity for the auto-lock timer
+        if message.needs_signer() {
+            GLOBALS.note_activity();
+        }
+
         match message {
             ToOverlordMessage::AddRelay(relay_url) => {
                 self.add_relay(relay_url).await?;
@@ -597,6 +697,12 @@ impl Overlord {
             ToOverlordMessage::AdvertiseRelayListOne(relay_url, event, dmevent) => {
                 self.advertise_relay_list_one(relay_url, event, dmevent)?;
             }
+            ToOverlordMessage::AuditStoredEvents => {
+                Self::audit_stored_events();
+            }
+            ToOverlordMessage::AuditStoredEventsCancel => {
+                Self::audit_stored_events_cancel();
+            }
             ToOverlordMessage::AuthApproved(relay_url, permanent) => {
                 self.auth_approved(relay_url, permanent)?;
             }
@@ -612,6 +718,12 @@ impl Overlord {
             ToOverlordMessage::BookmarkRm(er) => {
                 self.bookmark_rm(er)?;
             }
+            ToOverlordMessage::BoostRelay(relay_url, duration) => {
+                self.boost_relay(relay_url, duration).await;
+            }
+            ToOverlordMessage::CancelDelivery(id, relay_url) => {
+                Self::cancel_delivery(id, relay_url);
+            }
             ToOverlordMessage::ChangePassphrase { old, new } => {
                 Self::change_passphrase(old, new).await?;
             }
@@ -624,6 +736,15 @@ impl Overlord {
             ToOverlordMessage::ConnectDeclined(relay_url, permanent) => {
                 self.connect_declined(relay_url, permanent)?;
             }
+            ToOverlordMessage::ConnectNormally => {
+                self.connect_normally().await?;
+            }
+            ToOverlordMessage::ConnectOnly(relay_urls) => {
+                self.connect_only(relay_urls)?;
+            }
+            ToOverlordMessage::CopyPersonRelays(from, to) => {
+                self.copy_person_relays(from, to).await?;
+            }
             ToOverlordMessage::DelegationReset => {
                 Self::delegation_reset().await?;
             }
@@ -642,12 +763,21 @@ impl Overlord {
             ToOverlordMessage::DropRelay(relay_url) => {
                 self.drop_relay(relay_url)?;
             }
+            ToOverlordMessage::FetchBadges(pubkey) => {
+                self.fetch_badges(pubkey)?;
+            }
             ToOverlordMessage::FetchEvent(id, relay_urls) => {
                 self.fetch_event(id, relay_urls)?;
             }
             ToOverlordMessage::FetchNAddr(ea) => {
                 self.fetch_naddr(ea)?;
             }
+            ToOverlordMessage::FetchParentOf(id) => {
+                self.fetch_parent_of(id)?;
+            }
+            ToOverlordMessage::FindRelaysForUncovered => {
+                self.find_relays_for_uncovered()?;
+            }
             ToOverlordMessage::FollowPubkey(pubkey, list, private) => {
                 self.follow_pubkey(pubkey, list, private)?;
             }
@@ -669,9 +799,18 @@ impl Overlord {
                     GLOBALS.status_queue.write().write(format!("{}", e));
                 }
             }
+            ToOverlordMessage::HandleNostrUri(uri) => {
+                self.handle_nostr_uri(uri)?;
+            }
+            ToOverlordMessage::HealthCheckAllRelays => {
+                Self::health_check_all_relays();
+            }
             ToOverlordMessage::HideOrShowRelay(relay_url, hidden) => {
                 Self::hide_or_show_relay(relay_url, hidden)?;
             }
+            ToOverlordMessage::ImportFollows(list, publish) => {
+                self.import_follows(list, publish).await?;
+            }
             ToOverlordMessage::ImportPriv { privkey, password } => {
                 Self::import_priv(privkey, password)?;
             }
@@ -681,6 +820,9 @@ impl Overlord {
             ToOverlordMessage::LoadMoreCurrentFeed => {
                 self.load_more()?;
             }
+            ToOverlordMessage::LoadOlder(before) => {
+                self.load_older(before)?;
+            }
             ToOverlordMessage::MinionJobComplete(url, job_id) => {
                 self.finish_job(url, Some(job_id), None)?;
             }
@@ -704,6 +846,9 @@ impl Overlord {
             ToOverlordMessage::Nip46ServerOpApprovalResponse(pubkey, parsed_command, approval) => {
                 self.nip46_server_op_approval_response(pubkey, parsed_command, approval)?;
             }
+            ToOverlordMessage::RefreshRelayList(pubkey) => {
+                self.refresh_relay_list(pubkey)?;
+            }
             ToOverlordMessage::RefreshScoresAndPickRelays => {
                 self.refresh_scores_and_pick_relays().await?;
             }
@@ -713,9 +858,25 @@ impl Overlord {
                 in_reply_to,
                 annotation,
                 dm_channel,
+                relay_urls,
+                pow_job,
             } => {
-                self.post(content, tags, in_reply_to, annotation, dm_channel)
-                    .await?;
+                // Mining proof of work can take a long time, so don't wait here
+                std::mem::drop(tokio::task::spawn(async move {
+                    if let Err(e) = Self::post(
+                        content,
+                        tags,
+                        in_reply_to,
+                        annotation,
+                        dm_channel,
+                        relay_urls,
+                        pow_job,
+                    )
+                    .await
+                    {
+                        Self::report_spawned_error(e);
+                    }
+                }));
             }
             ToOverlordMessage::PostAgain(event) => {
                 self.post_again(event)?;
@@ -726,6 +887,21 @@ impl Overlord {
             ToOverlordMessage::PostNip46Event(event, relays) => {
                 self.post_nip46_event(event, relays)?;
             }
+            ToOverlordMessage::PostToCommunity(community, content, tags, pow_job) => {
+                // Mining proof of work can take a long time, so don't wait here
+                std::mem::drop(tokio::task::spawn(async move {
+                    if let Err(e) = Self::post_to_community(community, content, tags, pow_job).await
+                    {
+                        Self::report_spawned_error(e);
+                    }
+                }));
+            }
+            ToOverlordMessage::PowCancel(pow_job) => {
+                Self::pow_cancel(pow_job);
+            }
+            ToOverlordMessage::PurgeQuarantinedEvents => {
+                Self::purge_quarantined_events()?;
+            }
             ToOverlordMessage::PushBlossomServers => {
                 self.push_blossom_servers().await?;
             }
@@ -739,7 +915,14 @@ impl Overlord {
                 Self::rank_relay(relay_url, rank)?;
             }
             ToOverlordMessage::React(id, pubkey, emoji) => {
-                self.react(id, pubkey, emoji)?;
+                std::mem::drop(tokio::task::spawn(async move {
+                    if let Err(e) = Self::react(id, pubkey, emoji).await {
+                        Self::report_spawned_error(e);
+                    }
+                }));
+            }
+            ToOverlordMessage::ReconnectAll => {
+                self.reconnect_all()?;
             }
             ToOverlordMessage::ReengageMinion(url, jobs) => {
                 manager::engage_minion(url, jobs);
@@ -747,8 +930,25 @@ impl Overlord {
             ToOverlordMessage::RefreshSubscribedMetadata => {
                 self.refresh_subscribed_metadata()?;
             }
+            ToOverlordMessage::RemineEvent(id, target_bits) => {
+                std::mem::drop(tokio::task::spawn(async move {
+                    if let Err(e) = Self::remine_event(id, target_bits).await {
+                        Self::report_spawned_error(e);
+                    }
+                }));
+            }
             ToOverlordMessage::Repost(id) => {
-                self.repost(id)?;
+                std::mem::drop(tokio::task::spawn(async move {
+                    if let Err(e) = Self::repost(id).await {
+                        Self::report_spawned_error(e);
+                    }
+                }));
+            }
+            ToOverlordMessage::RetryDelivery(id, relay_url) => {
+                Self::retry_delivery(id, relay_url);
+            }
+            ToOverlordMessage::ScheduleRebuild(rebuild) => {
+                Self::schedule_rebuild(rebuild)?;
             }
             ToOverlordMessage::SearchLocally(text) => {
                 Self::search_locally(text)?;
@@ -768,9 +968,15 @@ impl Overlord {
             ToOverlordMessage::SetPersonFeed(pubkey, anchor) => {
                 self.set_person_feed(pubkey, anchor)?;
             }
+            ToOverlordMessage::SetQuietMode(quiet) => {
+                self.set_quiet_mode(quiet).await?;
+            }
             ToOverlordMessage::SetRelayFeed(relay_url, anchor) => {
                 self.set_relay_feed(relay_url, anchor)?;
             }
+            ToOverlordMessage::SetRelayUsageBits(relay_url, bits) => {
+                self.set_relay_usage_bits(relay_url, bits)?;
+            }
             ToOverlordMessage::SetThreadFeed {
                 id,
                 referenced_by,
@@ -787,15 +993,24 @@ impl Overlord {
             ToOverlordMessage::SubscribeConfig(opt_relays) => {
                 self.subscribe_config(opt_relays)?;
             }
+            ToOverlordMessage::SubscribeCustomFeed(name, pubkeys, kinds) => {
+                self.subscribe_custom_feed(name, pubkeys, kinds)?;
+            }
             ToOverlordMessage::SubscribeDiscover(pubkeys, opt_relays) => {
                 self.subscribe_discover(pubkeys, opt_relays)?;
             }
+            ToOverlordMessage::SubscribeHashtagFeed(hashtag) => {
+                self.subscribe_hashtag_feed(hashtag)?;
+            }
             ToOverlordMessage::SubscribeInbox(opt_relays) => {
                 self.subscribe_inbox(opt_relays)?;
             }
             ToOverlordMessage::SubscribeNip46(relays) => {
                 self.subscribe_nip46(relays)?;
             }
+            ToOverlordMessage::SyncConfigToRelay(relay_url) => {
+                Self::sync_config_to_relay(relay_url)?;
+            }
             ToOverlordMessage::TestRelay(relay_url) => {
                 Self::test_relay(relay_url);
             }
@@ -808,6 +1023,9 @@ impl Overlord {
             ToOverlordMessage::UnlockKey(password) => {
                 Self::unlock_key(password)?;
             }
+            ToOverlordMessage::UnsubscribeCustomFeed(name) => {
+                self.unsubscribe_custom_feed(name)?;
+            }
             ToOverlordMessage::UpdateMetadata(pubkey) => {
                 self.update_metadata(pubkey)?;
             }
@@ -823,6 +1041,13 @@ impl Overlord {
             ToOverlordMessage::VisibleNotesChanged(visible) => {
                 self.visible_notes_changed(visible)?;
             }
+            ToOverlordMessage::VoteInPoll(id, choices) => {
+                std::mem::drop(tokio::task::spawn(async move {
+                    if let Err(e) = Self::vote_in_poll(id, choices).await {
+                        Self::report_spawned_error(e);
+                    }
+                }));
+            }
             ToOverlordMessage::ZapStart(id, pubkey, lnurl) => {
                 self.zap_start(id, pubkey, lnurl).await?;
             }
@@ -966,8 +1191,76 @@ impl Overlord {
         Ok(())
     }
 
+    /// Re-verify the signatures of all stored events in the background, moving any
+    /// that fail into quarantine. Progress is available in `GLOBALS.audit_status`
+    /// while it runs.
+    pub fn audit_stored_events() {
+        if GLOBALS.audit_status.read().is_some() {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("An audit of stored events is already running.".to_owned());
+            return;
+        }
+
+        GLOBALS.audit_cancel.store(false, Ordering::Relaxed);
+        *GLOBALS.audit_status.write() = Some("Starting audit...".to_owned());
+
+        std::mem::drop(task::spawn_blocking(|| {
+            let result = Self::audit_stored_events_inner();
+            *GLOBALS.audit_status.write() = None;
+            match result {
+                Ok((checked, quarantined)) => {
+                    GLOBALS.status_queue.write().write(format!(
+                        "Audited {} events, {} quarantined.",
+                        checked, quarantined
+                    ));
+                }
+                Err(e) => tracing::error!("Audit of stored events failed: {}", e),
+            }
+        }));
+    }
+
+    fn audit_stored_events_inner() -> Result<(usize, usize), Error> {
+        const BATCH: usize = 1000;
+
+        let total = GLOBALS.db().get_event_len()?;
+        let mut checked: usize = 0;
+        let mut quarantined: usize = 0;
+        let mut after: Option<Id> = None;
+
+        loop {
+            if GLOBALS.audit_cancel.load(Ordering::Relaxed) {
+                tracing::info!("Audit of stored events cancelled");
+                break;
+            }
+
+            let (c, q, next) = GLOBALS.db().audit_events(after, BATCH)?;
+            checked += c;
+            quarantined += q;
+
+            *GLOBALS.audit_status.write() = Some(format!(
+                "Audited {} of about {} events, {} quarantined",
+                checked, total, quarantined
+            ));
+
+            match next {
+                Some(id) => after = Some(id),
+                None => break,
+            }
+        }
+
+        Ok((checked, quarantined))
+    }
+
+    /// Cancel a running audit of stored events. Events already quarantined
+    /// remain quarantined.
+    pub fn audit_stored_events_cancel() {
+        GLOBALS.audit_cancel.store(true, Ordering::Relaxed);
+    }
+
     /// User has approved authentication on this relay. Save this result for later
-    /// and inform the minion.
+    /// (for this run only, unless `permanent`) and inform the minion.
     pub fn auth_approved(&mut self, relay_url: RelayUrl, permanent: bool) -> Result<(), Error> {
         if permanent {
             // Save the answer in the relay record
@@ -978,6 +1271,9 @@ impl Overlord {
                 },
                 None,
             )?;
+            GLOBALS.relay_auth_decisions.remove(&relay_url);
+        } else {
+            GLOBALS.relay_auth_decisions.insert(relay_url.clone(), true);
         }
 
         if GLOBALS.connected_relays.contains_key(&relay_url) {
@@ -1002,7 +1298,7 @@ impl Overlord {
     }
 
     /// User has declined authentication on this relay. Save this result for later
-    /// and inform the minion.
+    /// (for this run only, unless `permanent`) and inform the minion.
     pub fn auth_declined(&mut self, relay_url: RelayUrl, permanent: bool) -> Result<(), Error> {
         if permanent {
             // Save the answer in the relay record
@@ -1013,6 +1309,11 @@ impl Overlord {
                 },
                 None,
             )?;
+            GLOBALS.relay_auth_decisions.remove(&relay_url);
+        } else {
+            GLOBALS
+                .relay_auth_decisions
+                .insert(relay_url.clone(), false);
         }
 
         if GLOBALS.connected_relays.contains_key(&relay_url) {
@@ -1229,6 +1530,131 @@ impl Overlord {
         Ok(())
     }
 
+    /// Prefer `relay_url` when picking relays, and keep it connected, for `duration`.
+    /// This is for while the user is browsing something that comes from it.
+    pub async fn boost_relay(&mut self, relay_url: RelayUrl, duration: Duration) {
+        GLOBALS.relay_picker.boost(&relay_url, duration);
+        self.pick_relays().await;
+    }
+
+    /// Go back to connecting to relays normally after [connect_only](Self::connect_only).
+    /// Relays are picked again.
+    pub async fn connect_normally(&mut self) -> Result<(), Error> {
+        let allowed = match GLOBALS.connect_only.write().take() {
+            Some(allowed) => allowed,
+            None => return Ok(()),
+        };
+
+        tracing::info!("Connecting to relays normally again");
+
+        // Stop following everybody on the relays we were limited to. The relay
+        // picker will give them their share again.
+        for url in allowed {
+            GLOBALS.relay_picker.relay_disconnected(&url, 0);
+            self.finish_job(url.clone(), None, Some(RelayConnectionReason::Follow))?;
+            let _ = self.to_minions.send(ToMinionMessage {
+                target: url.as_str().to_owned(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::GeneralFeedFuture {
+                        pubkeys: vec![],
+                        anchor: Unixtime::now(), // does not matter
+                    }),
+                },
+            });
+        }
+
+        if !GLOBALS.db().read_setting_offline() {
+            GLOBALS.relay_picker.refresh_person_relay_scores().await?;
+            self.pick_relays().await;
+        }
+
+        self.subscribe_config(None)?;
+        self.subscribe_inbox(None)?;
+        self.subscribe_giftwraps()?;
+
+        Ok(())
+    }
+
+    /// Disconnect from every relay except `urls`, and follow everybody, along with
+    /// our config, inbox and DMs, on those.
+    ///
+    /// No other relay is connected to, for any reason, until
+    /// [connect_normally](Self::connect_normally) is called or gossip restarts. Nothing
+    /// about the relays is changed in the database.
+    pub fn connect_only(&mut self, urls: Vec<RelayUrl>) -> Result<(), Error> {
+        tracing::info!("Connecting only to {} relays", urls.len());
+
+        *GLOBALS.connect_only.write() = Some(urls.clone());
+
+        // The relay picker no longer has the others
+        let picked: Vec<RelayUrl> = GLOBALS
+            .relay_picker
+            .relay_assignments_iter()
+            .map(|refmulti| refmulti.key().to_owned())
+            .collect();
+        for url in picked {
+            if !urls.contains(&url) {
+                GLOBALS.relay_picker.relay_disconnected(&url, 0);
+            }
+        }
+
+        // Disconnect from the others
+        let connected: Vec<RelayUrl> = GLOBALS
+            .connected_relays
+            .iter()
+            .map(|refmulti| refmulti.key().to_owned())
+            .collect();
+        for url in connected {
+            if !urls.contains(&url) {
+                let _ = self.to_minions.send(ToMinionMessage {
+                    target: url.as_str().to_owned(),
+                    payload: ToMinionPayload {
+                        job_id: 0,
+                        detail: ToMinionPayloadDetail::Shutdown,
+                    },
+                });
+            }
+        }
+
+        // Follow everybody on the ones we have
+        let pubkeys = GLOBALS.people.get_subscribed_pubkeys();
+        let anchor = GLOBALS.feed.current_anchor();
+        manager::run_jobs_on_all_relays(
+            urls.clone(),
+            vec![
+                RelayJob {
+                    reason: RelayConnectionReason::Follow,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::GeneralFeedFuture {
+                            pubkeys: pubkeys.clone(),
+                            anchor,
+                        }),
+                    },
+                },
+                RelayJob {
+                    reason: RelayConnectionReason::Follow,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::GeneralFeedChunk {
+                            pubkeys,
+                            anchor,
+                        }),
+                    },
+                },
+            ],
+        );
+
+        self.subscribe_config(Some(urls.clone()))?;
+        self.subscribe_inbox(Some(urls))?;
+
+        // Only on the DM and INBOX relays among them
+        self.subscribe_giftwraps()?;
+
+        Ok(())
+    }
+
     /// Remove any key delegation setup
     pub async fn delegation_reset() -> Result<(), Error> {
         if GLOBALS.delegation.reset() {
@@ -1489,6 +1915,9 @@ impl Overlord {
     /// Disconnect from the specified relay. This may not happen immediately if the minion
     /// handling that relay is stuck waiting for a timeout.
     pub fn drop_relay(&mut self, relay_url: RelayUrl) -> Result<(), Error> {
+        // Don't reconnect to it next time
+        Self::forget_manual_relay(&relay_url)?;
+
         let _ = self.to_minions.send(ToMinionMessage {
             target: relay_url.as_str().to_owned(),
             payload: ToMinionPayload {
@@ -1500,6 +1929,59 @@ impl Overlord {
         Ok(())
     }
 
+    /// Fetch the NIP-58 badges a person shows on their profile, along with the
+    /// badge definitions and awards needed to display them
+    pub fn fetch_badges(&mut self, pubkey: PublicKey) -> Result<(), Error> {
+        let relays = relay::get_some_pubkey_outboxes(pubkey)?;
+
+        let profile_badges = GLOBALS.db().get_replaceable_event(
+            EventKind::ProfileBadges,
+            pubkey,
+            crate::badges::PROFILE_BADGES_D,
+        )?;
+
+        // Check for a newer one. If we don't have one yet, fetch the rest once it
+        // arrives.
+        if profile_badges.is_none() {
+            GLOBALS.badges_wanted.insert(pubkey);
+        }
+        self.fetch_naddr(NAddr {
+            d: crate::badges::PROFILE_BADGES_D.to_owned(),
+            relays: relays.iter().map(|r| r.to_unchecked_url()).collect(),
+            kind: EventKind::ProfileBadges,
+            author: pubkey,
+        })?;
+
+        if let Some(event) = profile_badges {
+            for (mut naddr, award) in crate::badges::profile_badge_pairs(&event) {
+                // Awards are usually on the issuer's relays and the recipient's
+                let mut award_relays = relays.clone();
+                for url in relay::get_some_pubkey_outboxes(naddr.author)? {
+                    if !award_relays.contains(&url) {
+                        award_relays.push(url);
+                    }
+                }
+                self.fetch_event(award, award_relays)?;
+
+                if GLOBALS
+                    .db()
+                    .get_replaceable_event(EventKind::BadgeDefinition, naddr.author, &naddr.d)?
+                    .is_none()
+                {
+                    if naddr.relays.is_empty() {
+                        naddr.relays = relay::get_some_pubkey_outboxes(naddr.author)?
+                            .iter()
+                            .map(|r| r.to_unchecked_url())
+                            .collect();
+                    }
+                    self.fetch_naddr(naddr)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Fetch an event from specific relays by event `Id`
     pub fn fetch_event(&mut self, id: Id, mut relay_urls: Vec<RelayUrl>) -> Result<(), Error> {
         // Use READ relays if relays are unknown
@@ -1534,7 +2016,7 @@ impl Overlord {
         let relays: Vec<RelayUrl> = ea
             .relays
             .iter()
-            .filter_map(|uu| RelayUrl::try_from_unchecked_url(uu).ok())
+            .filter_map(|uu| crate::relay::relay_url_from_unchecked(uu).ok())
             .collect();
 
         manager::run_jobs_on_all_relays(
@@ -1551,6 +2033,103 @@ impl Overlord {
         Ok(())
     }
 
+    /// Fetch the event that the event `id` replies to, and the root of its thread,
+    /// if we don't have them. Unlike loading the whole thread, this only looks for
+    /// those events: on the relays hinted in the reply's tags, the relays the reply
+    /// was seen on, and the outboxes of the parent's author.
+    pub fn fetch_parent_of(&mut self, id: Id) -> Result<(), Error> {
+        let event = match GLOBALS.db().read_event(id)? {
+            Some(event) => event,
+            None => return Ok(()),
+        };
+
+        let seen_on: Vec<RelayUrl> = GLOBALS
+            .db()
+            .get_event_seen_on_relay(id)?
+            .drain(..)
+            .map(|(url, _time)| url)
+            .collect();
+
+        for eref in [event.replies_to(), event.replies_to_root()]
+            .into_iter()
+            .flatten()
+        {
+            match eref {
+                EventReference::Id {
+                    id: parent,
+                    author,
+                    mut relays,
+                    ..
+                } => {
+                    if GLOBALS.db().has_event(parent)? {
+                        continue;
+                    }
+                    relays.extend(seen_on.iter().cloned());
+                    relays.sort();
+                    relays.dedup();
+                    match author {
+                        Some(author) => GLOBALS
+                            .seeker
+                            .seek_id_and_author(parent, author, relays, false)?,
+                        None => {
+                            // Replies are usually also published to where the replier writes
+                            relays.extend(relay::get_some_pubkey_outboxes(event.pubkey)?);
+                            GLOBALS.seeker.seek_id(parent, relays, false)?;
+                        }
+                    }
+                }
+                EventReference::Addr(mut ea) => {
+                    if GLOBALS
+                        .db()
+                        .get_replaceable_event(ea.kind, ea.author, &ea.d)?
+                        .is_some()
+                    {
+                        continue;
+                    }
+                    ea.relays
+                        .extend(seen_on.iter().map(|r| r.to_unchecked_url()));
+                    ea.relays.extend(
+                        relay::get_some_pubkey_outboxes(ea.author)?
+                            .iter()
+                            .map(|r| r.to_unchecked_url()),
+                    );
+                    ea.relays.sort();
+                    ea.relays.dedup();
+                    self.fetch_naddr(ea)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Look harder for the relays of followed people who are not assigned to any
+    /// relay, asking both our discover relays and the indexer relays for their
+    /// relay lists. Once those come in, picking relays again will cover them.
+    pub fn find_relays_for_uncovered(&mut self) -> Result<(), Error> {
+        let pubkeys = GLOBALS.relay_picker.uncovered_followed();
+        if pubkeys.is_empty() {
+            return Ok(());
+        }
+
+        let mut relays: Vec<RelayUrl> = Relay::choose_relay_urls(Relay::DISCOVER, |_| true)?;
+        for url in relay::first_contact_relays()? {
+            if !relays.contains(&url) {
+                relays.push(url);
+            }
+        }
+
+        GLOBALS.status_queue.write().write(format!(
+            "Looking for the relays of {} people on {} relays",
+            pubkeys.len(),
+            relays.len()
+        ));
+
+        self.subscribe_discover(pubkeys, Some(relays))?;
+
+        Ok(())
+    }
+
     /// Follow a person by `PublicKey`
     pub fn follow_pubkey(
         &mut self,
@@ -1582,7 +2161,7 @@ impl Overlord {
     ) -> Result<(), Error> {
         // Set their relays
         for relay in nprofile.relays.iter() {
-            if let Ok(relay_url) = RelayUrl::try_from_unchecked_url(relay) {
+            if let Ok(relay_url) = crate::relay::relay_url_from_unchecked(relay) {
                 // Create relay if missing
                 GLOBALS.db().write_relay_if_missing(&relay_url, None)?;
 
@@ -1619,26 +2198,237 @@ impl Overlord {
         Ok(())
     }
 
-    /// Hide or Show a relay. This adjusts the `hidden` a flag on the `Relay` record
-    /// (You could easily do this yourself by talking to GLOBALS.db() directly too)
-    pub fn hide_or_show_relay(relay_url: RelayUrl, hidden: bool) -> Result<(), Error> {
-        if let Some(mut relay) = GLOBALS.db().read_relay(&relay_url)? {
-            relay.hidden = hidden;
-            GLOBALS.db().write_relay(&relay, None)?;
-        }
-
-        Ok(())
-    }
+    /// Handle a NIP-21 `nostr:` URI, such as one handed to us by the OS. This fetches
+    /// whatever it refers to (if we don't already have it) and asks the UI to navigate
+    /// there via `GLOBALS.navigation_request`.
+    pub fn handle_nostr_uri(&mut self, uri: String) -> Result<(), Error> {
+        let uri = uri.trim();
+        let nb32 = match NostrUrl::try_from_string(uri) {
+            Some(nurl) => Some(nurl.0),
+            None => NostrBech32::try_from_string(uri),
+        };
+        let nb32 = match nb32 {
+            Some(nb32) => nb32,
+            None => {
+                GLOBALS
+                    .status_queue
+                    .write()
+                    .write(format!("Could not understand nostr URI: {}", uri));
+                return Ok(());
+            }
+        };
 
-    /// Import a private key
-    pub fn import_priv(mut privkey: String, mut password: String) -> Result<(), Error> {
-        if privkey.starts_with("ncryptsec") {
-            let epk = EncryptedPrivateKey(privkey);
-            match GLOBALS.identity.set_encrypted_private_key(epk, &password) {
-                Ok(_) => {
-                    GLOBALS.identity.unlock(&password)?;
-                    GLOBALS.client_identity.generate_private_key(&password)?;
-                    password.zeroize();
+        let navigation = match nb32 {
+            NostrBech32::CryptSec(_) => {
+                GLOBALS.status_queue.write().write(
+                    "That nostr URI is an encrypted private key. Not opening it.".to_owned(),
+                );
+                return Ok(());
+            }
+            NostrBech32::Id(id) => {
+                self.fetch_event(id, vec![])?;
+                Navigation::Feed(FeedKind::Thread {
+                    id,
+                    referenced_by: id,
+                    author: None,
+                })
+            }
+            NostrBech32::NAddr(ea) => {
+                match GLOBALS
+                    .db()
+                    .get_replaceable_event(ea.kind, ea.author, &ea.d)?
+                {
+                    Some(event) => Navigation::Feed(FeedKind::Thread {
+                        id: event.id,
+                        referenced_by: event.id,
+                        author: Some(event.pubkey),
+                    }),
+                    None => {
+                        // Navigate once it arrives
+                        *GLOBALS.awaited_navigation.write() = Some(ea.clone());
+                        self.fetch_naddr(ea)?;
+                        GLOBALS
+                            .status_queue
+                            .write()
+                            .write("Fetching the linked event...".to_owned());
+                        return Ok(());
+                    }
+                }
+            }
+            NostrBech32::NEvent(ne) => {
+                let relays: Vec<RelayUrl> = ne
+                    .relays
+                    .iter()
+                    .filter_map(|r| crate::relay::relay_url_from_unchecked(r).ok())
+                    .collect();
+                self.fetch_event(ne.id, relays)?;
+                Navigation::Feed(FeedKind::Thread {
+                    id: ne.id,
+                    referenced_by: ne.id,
+                    author: ne.author,
+                })
+            }
+            NostrBech32::Profile(prof) => {
+                PersonTable::create_record_if_missing(prof.pubkey, None)?;
+                for relay in prof.relays.iter() {
+                    if let Ok(relay_url) = crate::relay::relay_url_from_unchecked(relay) {
+                        GLOBALS.db().write_relay_if_missing(&relay_url, None)?;
+                        GLOBALS.db().modify_person_relay(
+                            prof.pubkey,
+                            &relay_url,
+                            |pr| {
+                                pr.last_suggested = Some(Unixtime::now().0 as u64);
+                            },
+                            None,
+                        )?;
+                    }
+                }
+                GLOBALS.people.person_of_interest(prof.pubkey);
+                Navigation::Person(prof.pubkey)
+            }
+            NostrBech32::Pubkey(pk) => {
+                PersonTable::create_record_if_missing(pk, None)?;
+                GLOBALS.people.person_of_interest(pk);
+                Navigation::Person(pk)
+            }
+            NostrBech32::Relay(url) => match crate::relay::relay_url_from_unchecked(&url) {
+                Ok(relay_url) => {
+                    GLOBALS.db().write_relay_if_missing(&relay_url, None)?;
+                    Navigation::Feed(FeedKind::Relay(relay_url))
+                }
+                Err(_) => {
+                    GLOBALS
+                        .status_queue
+                        .write()
+                        .write(format!("Invalid relay URL in nostr URI: {}", uri));
+                    return Ok(());
+                }
+            },
+        };
+
+        *GLOBALS.navigation_request.write() = Some(navigation);
+        GLOBALS.notify_ui_redraw.notify_waiters();
+
+        Ok(())
+    }
+
+    /// Hide or Show a relay. This adjusts the `hidden` a flag on the `Relay` record
+    /// (You could easily do this yourself by talking to GLOBALS.db() directly too)
+    pub fn hide_or_show_relay(relay_url: RelayUrl, hidden: bool) -> Result<(), Error> {
+        if let Some(mut relay) = GLOBALS.db().read_relay(&relay_url)? {
+            relay.hidden = hidden;
+            GLOBALS.db().write_relay(&relay, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Follow many people at once (e.g. from a pasted or exported list), optionally
+    /// publishing the updated contact list. People already followed are skipped.
+    /// Relay hints (from nprofiles) are saved for everybody in the list.
+    /// Relay lists and metadata for the newly followed are fetched in bulk.
+    pub async fn import_follows(
+        &mut self,
+        mut list: Vec<Profile>,
+        publish: bool,
+    ) -> Result<(), Error> {
+        // Person relay records are written in transactions of this many
+        const PERSON_RELAYS_PER_TXN: usize = 500;
+
+        let total = list.len();
+
+        // Skip duplicates and people already followed, but keep everybody's relay hints
+        let now = Unixtime::now().0 as u64;
+        let mut seen: HashSet<PublicKey> = HashSet::new();
+        let mut added: Vec<PublicKey> = Vec::new();
+        let mut relay_urls: HashSet<RelayUrl> = HashSet::new();
+        let mut person_relays: Vec<PersonRelay> = Vec::new();
+        for profile in list.drain(..) {
+            for relay in profile.relays.iter() {
+                if let Ok(relay_url) = crate::relay::relay_url_from_unchecked(relay) {
+                    let mut pr = GLOBALS
+                        .db()
+                        .read_person_relay(profile.pubkey, &relay_url)?
+                        .unwrap_or_else(|| PersonRelay::new(profile.pubkey, relay_url.clone()));
+                    pr.last_suggested = Some(now);
+                    person_relays.push(pr);
+                    relay_urls.insert(relay_url);
+                }
+            }
+
+            if seen.insert(profile.pubkey)
+                && !GLOBALS
+                    .db()
+                    .is_person_in_list(&profile.pubkey, PersonList::Followed)?
+            {
+                added.push(profile.pubkey);
+            }
+        }
+
+        let mut txn = GLOBALS.db().get_write_txn()?;
+        for relay_url in relay_urls.iter() {
+            GLOBALS
+                .db()
+                .write_relay_if_missing(relay_url, Some(&mut txn))?;
+        }
+        for pubkey in added.iter() {
+            GLOBALS.db().add_person_to_list(
+                pubkey,
+                PersonList::Followed,
+                Private(false),
+                Some(&mut txn),
+            )?;
+        }
+        txn.commit()?;
+
+        for chunk in person_relays.chunks(PERSON_RELAYS_PER_TXN) {
+            let mut txn = GLOBALS.db().get_write_txn()?;
+            GLOBALS.db().write_person_relays(chunk, Some(&mut txn))?;
+            txn.commit()?;
+        }
+
+        for pubkey in added.iter() {
+            GLOBALS.relay_picker.add_someone(*pubkey)?;
+        }
+
+        GLOBALS.status_queue.write().write(format!(
+            "Imported {} follows ({} already followed or duplicated)",
+            added.len(),
+            total - added.len()
+        ));
+
+        if added.is_empty() {
+            return Ok(());
+        }
+
+        // Find their relays and profiles
+        self.subscribe_discover(added.clone(), None)?;
+        self.update_metadata_in_bulk(added)?;
+
+        if publish {
+            self.push_person_list(PersonList::Followed).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Import a private key
+    pub fn import_priv(privkey: String, mut password: String) -> Result<(), Error> {
+        // It may have come from a QR code
+        let mut privkey = {
+            let mut payload = privkey;
+            let normalized = crate::misc::normalize_key_payload(&payload);
+            payload.zeroize();
+            normalized
+        };
+
+        if privkey.starts_with("ncryptsec") {
+            let epk = EncryptedPrivateKey(privkey);
+            match GLOBALS.identity.set_encrypted_private_key(epk, &password) {
+                Ok(_) => {
+                    GLOBALS.identity.unlock(&password)?;
+                    GLOBALS.client_identity.generate_private_key(&password)?;
+                    password.zeroize();
                 }
                 Err(err) => {
                     password.zeroize();
@@ -1688,8 +2478,24 @@ impl Overlord {
 
     pub fn load_more(&mut self) -> Result<(), Error> {
         // Change the feed range:
-        let anchor = GLOBALS.feed.load_more()?;
+        if let Some(anchor) = GLOBALS.feed.load_more()? {
+            self.load_older_from_relays(anchor)?;
+        }
+
+        Ok(())
+    }
+
+    /// Page the current feed back to `before`, loading what we have locally and
+    /// asking relays for events older than that
+    pub fn load_older(&mut self, before: Unixtime) -> Result<(), Error> {
+        if let Some(anchor) = GLOBALS.feed.load_older(before)? {
+            self.load_older_from_relays(anchor)?;
+        }
 
+        Ok(())
+    }
+
+    fn load_older_from_relays(&mut self, anchor: Unixtime) -> Result<(), Error> {
         // Fetch more based on that feed range
         match GLOBALS.feed.get_feed_kind() {
             FeedKind::List(_, _) => {
@@ -1727,7 +2533,7 @@ impl Overlord {
             FeedKind::Person(pubkey) => {
                 // Get write relays for the person
                 let relays: Vec<RelayUrl> = relay::get_all_pubkey_outboxes(pubkey)?;
-                let num = GLOBALS.db().read_setting_num_relays_per_person() as usize;
+                let num = GLOBALS.db().read_setting_feed_relays_per_person() as usize;
                 manager::run_jobs_on_some_relays(
                     relays,
                     num,
@@ -1758,12 +2564,58 @@ impl Overlord {
                     }],
                 );
             }
+            FeedKind::Hashtag(hashtag) => {
+                manager::run_jobs_on_all_relays(
+                    Self::hashtag_feed_relays()?,
+                    vec![RelayJob {
+                        reason: RelayConnectionReason::SubscribeHashtag,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(FilterSet::HashtagFeedChunk {
+                                hashtag,
+                                anchor,
+                            }),
+                        },
+                    }],
+                );
+            }
+            FeedKind::Custom(name) => {
+                if let Some(custom_feed) = GLOBALS.db().read_custom_feed(&name)? {
+                    let kinds = custom_feed.event_kinds();
+                    for (relay_url, pubkeys) in Self::custom_feed_relays(&custom_feed)? {
+                        manager::run_jobs_on_all_relays(
+                            vec![relay_url],
+                            vec![RelayJob {
+                                reason: RelayConnectionReason::SubscribeCustomFeed,
+                                payload: ToMinionPayload {
+                                    job_id: rand::random::<u64>(),
+                                    detail: ToMinionPayloadDetail::Subscribe(
+                                        FilterSet::CustomFeedChunk {
+                                            name: name.clone(),
+                                            pubkeys,
+                                            kinds: kinds.clone(),
+                                            anchor,
+                                        },
+                                    ),
+                                },
+                            }],
+                        );
+                    }
+                }
+            }
             _ => (), // other feeds can't load more
         }
 
         Ok(())
     }
 
+    /// Resolve a NIP-05 identifier (`user@domain`, `_@domain` or just `domain`) to a
+    /// public key and the relays it lists, without following them or saving anything.
+    /// DNS and HTTP failures come back as distinct errors.
+    pub async fn lookup_nip05(identifier: &str) -> Result<(PublicKey, Vec<RelayUrl>), Error> {
+        crate::nip05::lookup_nip05(identifier).await
+    }
+
     /// Process approved nip46 server operation
     pub fn nip46_server_op_approval_response(
         &mut self,
@@ -1797,6 +2649,70 @@ impl Overlord {
         Ok(())
     }
 
+    /// Drop and re-establish every relay connection, resuming their persistent jobs.
+    /// Useful after the machine wakes from sleep or the network changes, when existing
+    /// websockets are likely dead but minions may not have noticed yet.
+    pub fn reconnect_all(&mut self) -> Result<(), Error> {
+        let _ = self.to_minions.send(ToMinionMessage {
+            target: "all".to_string(),
+            payload: ToMinionPayload {
+                job_id: 0,
+                detail: ToMinionPayloadDetail::Reconnect,
+            },
+        });
+
+        GLOBALS
+            .status_queue
+            .write()
+            .write("Reconnecting to relays.".to_string());
+
+        Ok(())
+    }
+
+    /// Fetch the latest relay list of one person, from the discover relays as well as
+    /// the relays we already associate with them. When it arrives, their person_relay
+    /// entries are updated and relays are re-picked as usual.
+    pub fn refresh_relay_list(&mut self, pubkey: PublicKey) -> Result<(), Error> {
+        let mut relay_urls: Vec<RelayUrl> = Relay::choose_relay_urls(Relay::DISCOVER, |_| true)?;
+        relay_urls.extend(relay::get_all_pubkey_outboxes(pubkey)?);
+        relay_urls.extend(relay::get_all_pubkey_inboxes(pubkey)?);
+        relay_urls.sort();
+        relay_urls.dedup();
+
+        if relay_urls.is_empty() {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("No relays to fetch their relay list from.".to_string());
+            return Ok(());
+        }
+
+        self.subscribe_discover(vec![pubkey], Some(relay_urls))
+    }
+
+    /// Copy the relays of `from` over to `to`, for when `to` has not published a
+    /// relay list but is known to use the same relays. Relays that `to` declared
+    /// themselves are not overwritten. Then pick relays again.
+    pub async fn copy_person_relays(
+        &mut self,
+        from: PublicKey,
+        to: PublicKey,
+    ) -> Result<(), Error> {
+        if from == to {
+            return Ok(());
+        }
+
+        let count = GLOBALS.db().copy_person_relays(from, to, None)?;
+        GLOBALS.status_queue.write().write(format!(
+            "Copied {} relays from {} to {}",
+            count,
+            crate::names::best_name_from_pubkey_lookup(&from),
+            crate::names::best_name_from_pubkey_lookup(&to)
+        ));
+
+        self.refresh_scores_and_pick_relays().await
+    }
+
     /// Trigger the relay picker to find relays for people not fully covered
     pub async fn refresh_scores_and_pick_relays(&mut self) -> Result<(), Error> {
         // When manually doing this, we refresh person_relay scores first which
@@ -1844,7 +2760,7 @@ impl Overlord {
 
     /// React to a post. The backend doesn't read the event, so you have to supply the
     /// pubkey author too.
-    pub fn react(&mut self, id: Id, pubkey: PublicKey, reaction: char) -> Result<(), Error> {
+    pub async fn react(id: Id, pubkey: PublicKey, reaction: char) -> Result<(), Error> {
         let event = {
             let public_key = match GLOBALS.identity.public_key() {
                 Some(pk) => pk,
@@ -1883,18 +2799,7 @@ impl Overlord {
                 content: reaction.to_string(),
             };
 
-            let powint = GLOBALS.db().read_setting_pow();
-            if powint > 0 {
-                let (work_sender, work_receiver) = mpsc::channel();
-                std::thread::spawn(move || {
-                    work_logger(work_receiver, powint);
-                });
-                GLOBALS
-                    .identity
-                    .sign_event_with_pow(pre_event, powint, Some(work_sender))?
-            } else {
-                GLOBALS.identity.sign_event(pre_event)?
-            }
+            crate::post::sign_event(pre_event).await?
         };
 
         let relay_urls: Vec<RelayUrl> = relay::relays_to_post_to(&event)?;
@@ -1920,13 +2825,19 @@ impl Overlord {
     }
 
     /// Post a TextNote (kind 1) event
+    ///
+    /// If `relay_urls` is given, the post goes only to those relays instead of our
+    /// WRITE relays and the relays of people tagged.
+    ///
+    /// `pow_job` is the token to [pow_cancel](Self::pow_cancel) proof of work for it.
     pub async fn post(
-        &mut self,
         content: String,
         tags: Vec<Tag>,
         in_reply_to: Option<Id>,
         annotation: bool,
         dm_channel: Option<DmChannel>,
+        relay_urls: Option<Vec<RelayUrl>>,
+        pow_job: u64,
     ) -> Result<(), Error> {
         let author = match GLOBALS.identity.public_key() {
             Some(pk) => pk,
@@ -1936,6 +2847,35 @@ impl Overlord {
             }
         };
 
+        // Check the relays the user chose, if they chose any
+        if let Some(ref urls) = relay_urls {
+            if dm_channel.is_some() {
+                return Err("Direct messages cannot be posted to chosen relays.".into());
+            }
+            if urls.is_empty() {
+                return Err("No relays were chosen to post to.".into());
+            }
+            let mut any_write = false;
+            for url in urls {
+                match GLOBALS.db().read_relay(url)? {
+                    Some(relay) if relay.no_post => {
+                        return Err(format!("{} is marked no-post, not posting.", url).into())
+                    }
+                    Some(relay) => any_write |= relay.has_usage_bits(Relay::WRITE),
+                    None => return Err(format!("Unknown relay {}, not posting.", url).into()),
+                }
+            }
+            if !any_write {
+                // They asked for these relays explicitly, so post anyway
+                GLOBALS
+                    .status_queue
+                    .write()
+                    .write("None of the chosen relays are WRITE relays.".to_owned());
+            }
+        }
+
+        let is_dm = dm_channel.is_some();
+
         // Prepare events for posting
         let mut prepared_events = match dm_channel {
             Some(channel) => {
@@ -1960,19 +2900,32 @@ impl Overlord {
                             tags,
                             Some(parent),
                             annotation,
+                            pow_job,
                         )
                         .await?
                     } else {
-                        crate::post::prepare_post_comment(author, content, tags, parent, annotation)
-                            .await?
+                        crate::post::prepare_post_comment(
+                            author, content, tags, parent, annotation, pow_job,
+                        )
+                        .await?
                     }
                 } else {
-                    crate::post::prepare_post_normal(author, content, tags, None, annotation)
-                        .await?
+                    crate::post::prepare_post_normal(
+                        author, content, tags, None, annotation, pow_job,
+                    )
+                    .await?
                 }
             }
         };
 
+        // Post only to the chosen relays, instead of our WRITE relays and the
+        // relays of tagged people
+        if let Some(urls) = relay_urls {
+            for (_, relays) in prepared_events.iter_mut() {
+                *relays = urls.clone();
+            }
+        }
+
         for (event, _) in &prepared_events {
             // Process the event locally (ignore any errors)
             let _ = crate::process::process_new_event(event, None, None, false, false);
@@ -1983,6 +2936,11 @@ impl Overlord {
             GLOBALS.delayed_posts.insert(event.id);
         }
 
+        // Watch for engagement with what we just posted
+        if !is_dm {
+            Self::subscribe_engagement()?;
+        }
+
         // Get my latest relay list event (pr #1801)
         let opt_relay_list_event = {
             let mut filter = Filter::new();
@@ -2032,6 +2990,39 @@ impl Overlord {
         Ok(())
     }
 
+    /// Post a TextNote (kind 1) event to a NIP-72 community
+    ///
+    /// The post is tagged with the community (on top of `tags`) and goes to the
+    /// community's relays (or our WRITE relays if it names none). We must already
+    /// have the community definition.
+    pub async fn post_to_community(
+        community: NAddr,
+        content: String,
+        mut tags: Vec<Tag>,
+        pow_job: u64,
+    ) -> Result<(), Error> {
+        let community = match GLOBALS.db().get_community(&community)? {
+            Some(community) => community,
+            None => return Err("Cannot find the community definition.".into()),
+        };
+
+        let relay_urls = if community.relays.is_empty() {
+            None
+        } else {
+            // Post refuses relays it does not know about
+            let mut urls: Vec<RelayUrl> = Vec::new();
+            for url in &community.relays {
+                GLOBALS.db().write_relay_if_missing(url, None)?;
+                urls.push(relay::canonical_url(url));
+            }
+            Some(urls)
+        };
+
+        tags.push(community.tag());
+
+        Self::post(content, tags, None, false, None, relay_urls, pow_job).await
+    }
+
     pub fn post_again(&mut self, event: Event) -> Result<(), Error> {
         let relay_urls = relay::relays_to_post_to(&event)?;
 
@@ -2063,6 +3054,14 @@ impl Overlord {
         GLOBALS.feed.sync_recompute();
     }
 
+    /// Stop mining proof of work for the job with the given token, if it is still
+    /// running. Its events are not posted.
+    pub fn pow_cancel(pow_job: u64) {
+        if let Some(cancel) = GLOBALS.pow_jobs.get(&pow_job) {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
     pub fn post_nip46_event(&mut self, event: Event, relays: Vec<RelayUrl>) -> Result<(), Error> {
         for url in &relays {
             tracing::debug!("Asking {} to post nostrconnect", url);
@@ -2082,6 +3081,16 @@ impl Overlord {
         Ok(())
     }
 
+    /// Permanently remove all quarantined events
+    pub fn purge_quarantined_events() -> Result<(), Error> {
+        let count = GLOBALS.db().purge_quarantined_events(None)?;
+        GLOBALS
+            .status_queue
+            .write()
+            .write(format!("Purged {} quarantined events.", count));
+        Ok(())
+    }
+
     pub async fn push_blossom_servers(&mut self) -> Result<(), Error> {
         let public_key = match GLOBALS.identity.public_key() {
             Some(pk) => pk,
@@ -2120,6 +3129,30 @@ impl Overlord {
         Ok(())
     }
 
+    /// Compare the list as it would be published against the latest version of it
+    /// that we have seen published, without publishing anything
+    pub fn preview_person_list_publish(list: PersonList) -> Result<PersonListDiff, Error> {
+        let local: BTreeSet<PublicKey> = GLOBALS
+            .db()
+            .get_people_in_list(list)?
+            .into_iter()
+            .map(|(pubkey, _)| pubkey)
+            .collect();
+
+        let (published_at, published) = match crate::people::published_person_list_members(list)? {
+            Some((created_at, members)) => (Some(created_at), members),
+            None => (None, BTreeSet::new()),
+        };
+
+        Ok(PersonListDiff {
+            list,
+            count: local.len(),
+            published_at,
+            added: local.difference(&published).copied().collect(),
+            removed: published.difference(&local).copied().collect(),
+        })
+    }
+
     /// Publish the user's specified PersonList
     pub async fn push_person_list(&mut self, list: PersonList) -> Result<(), Error> {
         let metadata = match GLOBALS.db().get_person_list_metadata(list)? {
@@ -2189,6 +3222,66 @@ impl Overlord {
         Ok(())
     }
 
+    /// Publish our latest metadata, contact list, relay list and mute list to a
+    /// relay that may not have them yet (e.g. one we just started writing to), so
+    /// that people who find us through that relay get all of it.
+    ///
+    /// These are the events we already have, so nothing is signed again.
+    pub fn sync_config_to_relay(relay_url: RelayUrl) -> Result<(), Error> {
+        GLOBALS
+            .pending
+            .remove(&PendingItem::RelayNeedsConfig(relay_url.clone()));
+
+        let pubkey = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => return Err((ErrorKind::NoPublicKey, file!(), line!()).into()),
+        };
+
+        let mut events: Vec<Event> = Vec::new();
+        for kind in [
+            EventKind::Metadata,
+            EventKind::ContactList,
+            EventKind::RelayList,
+            EventKind::MuteList,
+        ] {
+            if let Some(event) = GLOBALS.db().get_replaceable_event(kind, pubkey, "")? {
+                events.push(event);
+            }
+        }
+
+        if events.is_empty() {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("We have nothing of ours to copy to that relay.".to_string());
+            return Ok(());
+        }
+
+        tracing::debug!("Copying {} config events to {}", events.len(), relay_url);
+        manager::run_jobs_on_all_relays(
+            vec![relay_url],
+            vec![RelayJob {
+                reason: RelayConnectionReason::SyncConfig,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::PostEvents(events),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Give up on delivering a post that a relay refused
+    pub fn cancel_delivery(id: Id, relay_url: RelayUrl) {
+        crate::delivery::cancel(id, relay_url);
+    }
+
+    /// Send a post that a relay refused to that relay again now
+    pub fn retry_delivery(id: Id, relay_url: RelayUrl) {
+        crate::delivery::retry(id, relay_url);
+    }
+
     /// Rank a relay from 0 to 9.  The default rank is 3.  A rank of 0 means the relay will not be used.
     /// This represent a user's judgement, and is factored into how suitable a relay is for various
     /// purposes.
@@ -2214,7 +3307,7 @@ impl Overlord {
 
         // Sort the people into the relays we will find their metadata at
         for pubkey in &pubkeys {
-            for relay in relay::get_some_pubkey_outboxes(*pubkey)?.drain(..) {
+            for relay in relay::get_metadata_pubkey_outboxes(*pubkey)?.drain(..) {
                 map.entry(relay)
                     .and_modify(|e| e.push(*pubkey))
                     .or_insert_with(|| vec![*pubkey]);
@@ -2224,33 +3317,87 @@ impl Overlord {
         for (url, pubkeys) in map.drain() {
             manager::engage_minion(
                 url.clone(),
-                vec![RelayJob {
-                    reason: RelayConnectionReason::FetchMetadata,
-                    payload: ToMinionPayload {
-                        job_id: rand::random::<u64>(),
-                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::Metadata(pubkeys)),
-                    },
-                }],
+                FilterSet::metadata(pubkeys)
+                    .into_iter()
+                    .map(|filter_set| RelayJob {
+                        reason: RelayConnectionReason::FetchMetadata,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(filter_set),
+                        },
+                    })
+                    .collect(),
             );
         }
 
         Ok(())
     }
 
-    /// Repost a post by `Id`
-    pub fn repost(&mut self, id: Id) -> Result<(), Error> {
-        let reposted_event = match GLOBALS.db().read_event(id)? {
+    /// Post one of our notes again with a fresh proof of work of `target_bits`, for
+    /// relays that rejected it for too little work. The new note has a new id and
+    /// time. Once a relay accepts it, the old note is deleted with a NIP-09 deletion.
+    pub async fn remine_event(id: Id, target_bits: u8) -> Result<(), Error> {
+        let old = match GLOBALS.db().read_event(id)? {
             Some(event) => event,
-            None => {
-                GLOBALS
-                    .status_queue
-                    .write()
-                    .write("Cannot repost - cannot find event.".to_owned());
-                return Ok(());
-            }
+            None => return Err(ErrorKind::EventNotFound.into()),
         };
 
-        let mut protected: bool = false;
+        if Some(old.pubkey) != GLOBALS.identity.public_key() {
+            return Err("Only our own notes can be mined again.".into());
+        }
+        if old.kind == EventKind::GiftWrap {
+            return Err("Gift wrapped messages cannot be mined again.".into());
+        }
+
+        let pre_event = PreEvent {
+            pubkey: old.pubkey,
+            created_at: Unixtime::now(),
+            kind: old.kind,
+            tags: old.tags.clone(),
+            content: old.content.clone(),
+        };
+        let event = crate::post::mine_pow(pre_event, target_bits, rand::random::<u64>()).await?;
+
+        // The old note is kept until a relay accepts the new one, and is then
+        // deleted with a NIP-09 deletion (see delivery::post_accepted())
+        GLOBALS.remined_posts.insert(event.id, old.id);
+
+        // Process the message for ourself
+        crate::process::process_new_event(&event, None, None, false, false)?;
+
+        let relay_urls: Vec<RelayUrl> = relay::relays_to_post_to(&event)?;
+        for url in &relay_urls {
+            tracing::debug!("Asking {} to post", url);
+        }
+
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::PostEvent,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Repost a post by `Id`
+    pub async fn repost(id: Id) -> Result<(), Error> {
+        let reposted_event = match GLOBALS.db().read_event(id)? {
+            Some(event) => event,
+            None => {
+                GLOBALS
+                    .status_queue
+                    .write()
+                    .write("Cannot repost - cannot find event.".to_owned());
+                return Ok(());
+            }
+        };
+
+        let mut protected: bool = false;
         for tag in &reposted_event.tags {
             if tag.tagname() == "-" {
                 protected = true;
@@ -2267,51 +3414,7 @@ impl Overlord {
             }
         };
 
-        let kind: EventKind;
-        let mut tags: Vec<Tag> = vec![
-            ParsedTag::Pubkey {
-                pubkey: reposted_event.pubkey,
-                recommended_relay_url: None,
-                petname: None,
-            }
-            .into_tag(),
-            ParsedTag::Event {
-                id,
-                recommended_relay_url: relay_url.clone(),
-                marker: None,
-                author_pubkey: Some(reposted_event.pubkey),
-            }
-            .into_tag(),
-        ];
-
-        if reposted_event.kind != EventKind::TextNote {
-            kind = EventKind::GenericRepost;
-
-            // Add 'k' tag
-            tags.push(ParsedTag::Kind(reposted_event.kind).into_tag());
-
-            if reposted_event.kind.is_replaceable() {
-                let ea = NAddr {
-                    d: reposted_event.parameter().unwrap_or("".to_string()),
-                    relays: match relay_url {
-                        Some(url) => vec![url.clone()],
-                        None => vec![],
-                    },
-                    kind: reposted_event.kind,
-                    author: reposted_event.pubkey,
-                };
-                // Add 'a' tag
-                tags.push(
-                    ParsedTag::Address {
-                        address: ea,
-                        marker: None,
-                    }
-                    .into_tag(),
-                );
-            }
-        } else {
-            kind = EventKind::Repost;
-        }
+        let (kind, mut tags) = crate::post::repost_tags(&reposted_event, relay_url);
 
         let event = {
             let public_key = match GLOBALS.identity.public_key() {
@@ -2340,18 +3443,7 @@ impl Overlord {
                 content,
             };
 
-            let powint = GLOBALS.db().read_setting_pow();
-            if powint > 0 {
-                let (work_sender, work_receiver) = mpsc::channel();
-                std::thread::spawn(move || {
-                    work_logger(work_receiver, powint);
-                });
-                GLOBALS
-                    .identity
-                    .sign_event_with_pow(pre_event, powint, Some(work_sender))?
-            } else {
-                GLOBALS.identity.sign_event(pre_event)?
-            }
+            crate::post::sign_event(pre_event).await?
         };
 
         // Process this event locally
@@ -2385,6 +3477,49 @@ impl Overlord {
         Ok(())
     }
 
+    /// If enabled, advertise the relay list once relay changes have settled down.
+    /// Each call restarts the wait, so a bulk edit results in a single advertisement.
+    fn schedule_auto_advertise() {
+        if !GLOBALS.db().read_setting_auto_advertise_on_change() {
+            return;
+        }
+
+        let generation = GLOBALS
+            .auto_advertise_generation
+            .fetch_add(1, Ordering::SeqCst)
+            + 1;
+
+        std::mem::drop(tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(AUTO_ADVERTISE_DEBOUNCE_SECS)).await;
+
+            // A later change restarted the wait
+            if GLOBALS.auto_advertise_generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+
+            if !GLOBALS.identity.is_unlocked() {
+                tracing::info!("Relay list changed, but not auto-advertising: key is locked");
+                return;
+            }
+
+            tracing::info!("Relay list changed, auto-advertising it");
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::AdvertiseRelayList);
+        }));
+    }
+
+    /// Schedule a rebuild of derived data (indexes, relationships, etc.) for
+    /// the next time gossip starts, e.g. to recover from a damaged index
+    pub fn schedule_rebuild(rebuild: Rebuild) -> Result<(), Error> {
+        GLOBALS.db().set_rebuild_pending(rebuild, true, None)?;
+        GLOBALS.status_queue.write().write(format!(
+            "{} will be rebuilt next time gossip starts",
+            rebuild.name()
+        ));
+        Ok(())
+    }
+
     /// Search people and notes in the local database.
     /// Search results eventually arrive in `GLOBALS.people_search_results` and `GLOBALS.note_search_results`
     pub fn search_locally(mut text: String) -> Result<(), Error> {
@@ -2417,25 +3552,11 @@ impl Overlord {
                     // do nothing
                 }
                 NostrBech32::NAddr(ea) => {
-                    let mut filter = Filter::new();
-                    filter.add_event_kind(ea.kind);
-                    filter.add_author(ea.author);
-
                     if let Some(event) = GLOBALS
                         .db()
-                        .find_events_by_filter(&filter, |event| {
-                            event.tags.iter().any(|tag| {
-                                if let Ok(ParsedTag::Identifier(d)) = tag.parse() {
-                                    if d == ea.d {
-                                        return true;
-                                    }
-                                }
-                                false
-                            })
-                        })?
-                        .first()
+                        .get_addressable_event(ea.kind, ea.author, &ea.d)?
                     {
-                        note_search_results.push(event.clone());
+                        note_search_results.push(event);
                     } else {
                         let _ = GLOBALS
                             .to_overlord
@@ -2454,7 +3575,7 @@ impl Overlord {
                         let relays: Vec<RelayUrl> = ne
                             .relays
                             .iter()
-                            .filter_map(|r| RelayUrl::try_from_unchecked_url(r).ok())
+                            .filter_map(|r| crate::relay::relay_url_from_unchecked(r).ok())
                             .collect();
 
                         let _ = GLOBALS
@@ -2482,7 +3603,7 @@ impl Overlord {
                         let relays: Vec<RelayUrl> = prof
                             .relays
                             .iter()
-                            .filter_map(|uu| RelayUrl::try_from_unchecked_url(uu).ok())
+                            .filter_map(|uu| crate::relay::relay_url_from_unchecked(uu).ok())
                             .collect();
 
                         for relay in &relays {
@@ -2610,11 +3731,18 @@ impl Overlord {
             }],
         );
 
+        // Giftwraps can't be subscribed by channel. They arrive on our DM (kind 10050)
+        // relays, so make sure we are listening on all of them.
+        if dmchannel.can_use_nip17() {
+            self.subscribe_giftwraps()?;
+        }
+
         Ok(())
     }
 
     fn set_global_feed(&mut self, anchor: Unixtime) -> Result<(), Error> {
         let relay_urls = Relay::choose_relay_urls(Relay::GLOBAL, |_| true)?;
+        Self::remember_manual_relays(&relay_urls)?;
         manager::run_jobs_on_all_relays(
             relay_urls,
             vec![
@@ -2644,6 +3772,14 @@ impl Overlord {
 
     fn set_person_feed(&mut self, pubkey: PublicKey, anchor: Unixtime) -> Result<(), Error> {
         let relays: Vec<RelayUrl> = relay::get_some_pubkey_outboxes(pubkey)?;
+
+        // Keep their relays around while the user browses them
+        for relay_url in &relays {
+            GLOBALS
+                .relay_picker
+                .boost(relay_url, Duration::from_secs(60 * 10));
+        }
+
         manager::run_jobs_on_all_relays(
             relays,
             vec![
@@ -2674,6 +3810,7 @@ impl Overlord {
     }
 
     fn set_relay_feed(&mut self, relay_url: RelayUrl, anchor: Unixtime) -> Result<(), Error> {
+        Self::remember_manual_relays(std::slice::from_ref(&relay_url))?;
         manager::run_jobs_on_all_relays(
             vec![relay_url],
             vec![
@@ -2701,10 +3838,91 @@ impl Overlord {
         Ok(())
     }
 
+    /// Enter or leave quiet mode.
+    ///
+    /// In quiet mode we stop following people on the relays the relay picker chose,
+    /// and stop fetching augments, but keep our inbox, DM and config subscriptions
+    /// on our own relays. On leaving quiet mode relays are picked again and the general
+    /// feed resumes from where each relay left off, so no events are missed.
+    pub async fn set_quiet_mode(&mut self, quiet: bool) -> Result<(), Error> {
+        if GLOBALS.quiet_mode.swap(quiet, Ordering::Relaxed) == quiet {
+            return Ok(());
+        }
+
+        if quiet {
+            tracing::info!("Entering quiet mode");
+
+            let read_relays = Relay::choose_relay_urls(Relay::READ, |_| true)?;
+            let picked: Vec<RelayUrl> = GLOBALS
+                .relay_picker
+                .relay_assignments_iter()
+                .map(|refmulti| refmulti.key().to_owned())
+                .collect();
+            for url in picked {
+                GLOBALS.relay_picker.relay_disconnected(&url, 0);
+
+                let mut filter_sets = vec![FilterSet::GeneralFeedFuture {
+                    pubkeys: vec![],
+                    anchor: Unixtime::now(), // does not matter
+                }];
+                self.finish_job(url.clone(), None, Some(RelayConnectionReason::Follow))?;
+
+                // Our inbox is also followed on every picked relay; keep it only on
+                // our own READ relays
+                if !read_relays.contains(&url) {
+                    filter_sets.push(FilterSet::InboxFeedFuture(Unixtime::now()));
+                    self.finish_job(url.clone(), None, Some(RelayConnectionReason::FetchInbox))?;
+                }
+
+                for filter_set in filter_sets {
+                    let _ = self.to_minions.send(ToMinionMessage {
+                        target: url.as_str().to_owned(),
+                        payload: ToMinionPayload {
+                            job_id: 0,
+                            detail: ToMinionPayloadDetail::Unsubscribe(filter_set),
+                        },
+                    });
+                }
+            }
+
+            // Stop fetching augments everywhere
+            let _ = self.to_minions.send(ToMinionMessage {
+                target: "all".to_string(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::Augments {
+                        ids: vec![],
+                        reactions: true,
+                    }),
+                },
+            });
+        } else {
+            tracing::info!("Leaving quiet mode");
+
+            if !GLOBALS.db().read_setting_offline() {
+                GLOBALS.relay_picker.refresh_person_relay_scores().await?;
+                self.pick_relays().await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace the usage bits of a relay, performing whatever subscription changes
+    /// follow from that (see [update_relay](Self::update_relay)).
+    pub fn set_relay_usage_bits(&mut self, relay_url: RelayUrl, bits: u64) -> Result<(), Error> {
+        let old = GLOBALS.db().read_or_create_relay(&relay_url, None)?;
+        let mut new = old.clone();
+        new.clear_usage_bits(u64::MAX);
+        new.set_usage_bits(bits);
+        self.update_relay(old, new)
+    }
+
     /// This function:
-    ///   1. Sets GLOBALS.feed thread_parent to the highest locally connected event
-    ///   2. Engages the Seeker to climb ancestors from that event
-    ///   3. Subscribes to replies
+    ///   1. Sets GLOBALS.feed thread_parent to the event itself
+    ///   2. Cancels the subscriptions to replies of the previous thread
+    ///   3. Spawns a task to gather the rest (see `gather_thread`), cancelling the
+    ///      task of the previous thread if it is still going
     ///
     /// Note that seprately the UI constructs the thread view from local data including
     /// relationships that are built by process.rs as events flow in.
@@ -2713,6 +3931,48 @@ impl Overlord {
         id: Id,
         referenced_by: Id,
         author: Option<PublicKey>,
+    ) -> Result<(), Error> {
+        // Stop gathering for the thread we were on before
+        if let Some(handle) = self.thread_gathering.take() {
+            handle.abort();
+        }
+
+        // Start with the event itself as the thread parent. Gathering raises it
+        // once the ancestors are known.
+        GLOBALS.feed.set_thread_parent(id);
+
+        // Cancel current subscriptions to replies and root_replies
+        let _ = self.to_minions.send(ToMinionMessage {
+            target: "all".to_string(),
+            payload: ToMinionPayload {
+                job_id: 0,
+                detail: ToMinionPayloadDetail::UnsubscribeReplies,
+            },
+        });
+
+        // On a big thread gathering takes many storage reads, so it runs in a task
+        // instead of holding up the overlord
+        self.thread_gathering = Some(task::spawn(async move {
+            if let Err(e) = Self::gather_thread(id, referenced_by, author).await {
+                tracing::error!("{}", e);
+            }
+        }));
+
+        Ok(())
+    }
+
+    /// This function:
+    ///   1. Sets GLOBALS.feed thread_parent to the highest locally connected event
+    ///   2. Subscribes to replies to the root
+    ///   3. Engages the Seeker to climb ancestors from that event
+    ///   4. Subscribes to replies
+    ///
+    /// It yields between steps, so that aborting it (when the user switches to
+    /// another thread) stops it before it subscribes for the old thread.
+    async fn gather_thread(
+        id: Id,
+        referenced_by: Id,
+        author: Option<PublicKey>,
     ) -> Result<(), Error> {
         let mut eref = EventReference::Id {
             id,
@@ -2730,21 +3990,44 @@ impl Overlord {
 
         let ancestors = crate::misc::get_event_ancestors(eref)?;
 
-        // Set thread parent
+        // Raise the thread parent to the highest local event
         if let Some(ref event) = ancestors.highest_connected_local {
-            // ... to the highest local event
             GLOBALS.feed.set_thread_parent(event.id);
-        } else {
-            // ... else to the event itself (note that it might not be local)
-            GLOBALS.feed.set_thread_parent(id);
         }
 
-        let num_relays_per_person = GLOBALS.db().read_setting_num_relays_per_person();
+        task::yield_now().await;
+
+        // Subscribe to replies to root
+        let mut root_eref_relays: Vec<RelayUrl> = Vec::new();
+        if let Some(ref root_eref) = ancestors.root {
+            let filter_set = match root_eref {
+                EventReference::Id { id, .. } => FilterSet::RepliesToId(*id),
+                EventReference::Addr(naddr) => FilterSet::RepliesToAddr(naddr.clone()),
+            };
+            root_eref_relays = root_eref.copy_relays();
+
+            for url in root_eref_relays.iter() {
+                // Subscribe root replies
+                let jobs: Vec<RelayJob> = vec![RelayJob {
+                    reason: RelayConnectionReason::ReadThread,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(filter_set.clone()),
+                    },
+                }];
+
+                manager::engage_minion(url.to_owned(), jobs);
+            }
+        }
+
+        task::yield_now().await;
+
+        let feed_relays_per_person = GLOBALS.db().read_setting_feed_relays_per_person();
 
         // If we don't have it all, seek the next higher ancestor
         if ancestors.highest_connected_remote.is_some() {
-            // (it won't go higher right now, but if the user clicks they can climb the thread)
-            // FIXME: keep climbing somehow once this comes in.
+            // As each missing ancestor comes in, the Seeker seeks the next one up
+            // (see Seeker::climb), so gaps in the thread fill in progressively.
 
             // Let's first get additional relays the event might be on
             let mut bonus_relays: Vec<RelayUrl> = Vec::new();
@@ -2756,7 +4039,7 @@ impl Overlord {
                         .db()
                         .get_event_seen_on_relay(id)?
                         .drain(..)
-                        .take(num_relays_per_person as usize + 1)
+                        .take(feed_relays_per_person as usize + 1)
                         .map(|(url, _time)| url),
                 );
 
@@ -2792,7 +4075,7 @@ impl Overlord {
                         .db()
                         .get_event_seen_on_relay(referenced_by)?
                         .drain(..)
-                        .take(num_relays_per_person as usize + 1)
+                        .take(feed_relays_per_person as usize + 1)
                         .map(|(url, _time)| url),
                 );
 
@@ -2815,7 +4098,9 @@ impl Overlord {
                         .extend(bonus_relays.iter().map(|r| r.to_unchecked_url()));
                     eaddr.relays.sort();
                     eaddr.relays.dedup();
-                    self.fetch_naddr(eaddr)?;
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::FetchNAddr(eaddr));
                 }
                 Some(EventReference::Id {
                     id,
@@ -2840,37 +4125,7 @@ impl Overlord {
             }
         }
 
-        // Cancel current subscriptions to replies and root_replies
-        let _ = self.to_minions.send(ToMinionMessage {
-            target: "all".to_string(),
-            payload: ToMinionPayload {
-                job_id: 0,
-                detail: ToMinionPayloadDetail::UnsubscribeReplies,
-            },
-        });
-
-        // Subscribe to replies to root
-        let mut root_eref_relays: Vec<RelayUrl> = Vec::new();
-        if let Some(ref root_eref) = ancestors.root {
-            let filter_set = match root_eref {
-                EventReference::Id { id, .. } => FilterSet::RepliesToId(*id),
-                EventReference::Addr(naddr) => FilterSet::RepliesToAddr(naddr.clone()),
-            };
-            root_eref_relays = root_eref.copy_relays();
-
-            for url in root_eref_relays.iter() {
-                // Subscribe root replies
-                let jobs: Vec<RelayJob> = vec![RelayJob {
-                    reason: RelayConnectionReason::ReadThread,
-                    payload: ToMinionPayload {
-                        job_id: rand::random::<u64>(),
-                        detail: ToMinionPayloadDetail::Subscribe(filter_set.clone()),
-                    },
-                }];
-
-                manager::engage_minion(url.to_owned(), jobs);
-            }
-        }
+        task::yield_now().await;
 
         // Search for replies
         {
@@ -2888,7 +4143,7 @@ impl Overlord {
                         .db()
                         .get_event_seen_on_relay(referenced_by)?
                         .drain(..)
-                        .take(num_relays_per_person as usize + 1)
+                        .take(feed_relays_per_person as usize + 1)
                         .map(|(url, _time)| url),
                 );
 
@@ -2904,6 +4159,13 @@ impl Overlord {
             bonus_relays.sort();
             bonus_relays.dedup();
 
+            if bonus_relays.is_empty() && root_eref_relays.is_empty() {
+                GLOBALS
+                    .status_queue
+                    .write()
+                    .write("Could not find any relays to look for replies on.".to_string());
+            }
+
             for url in bonus_relays.iter() {
                 // Subscribe replies
                 let jobs: Vec<RelayJob> = vec![RelayJob {
@@ -3040,6 +4302,9 @@ impl Overlord {
         // Separately subscribe to our giftwraps on our DM and INBOX relays
         self.subscribe_giftwraps()?;
 
+        // Separately subscribe to engagement with our recent notes
+        Self::subscribe_engagement()?;
+
         // Separately subscribe to RelayList discovery for everyone we follow
         // who needs to seek a relay list again.
         let followed = GLOBALS.people.get_subscribed_pubkeys_needing_relay_lists();
@@ -3059,6 +4324,77 @@ impl Overlord {
         relays.dedup();
         self.subscribe_nip46(relays)?;
 
+        // Separately subscribe to the user's custom feeds
+        for custom_feed in GLOBALS.db().get_custom_feeds()? {
+            Self::engage_custom_feed(&custom_feed)?;
+        }
+
+        // Reconnect to the relays the user connected to last time
+        if GLOBALS.db().read_setting_restore_manual_relays() && !GLOBALS.db().read_setting_offline()
+        {
+            self.restore_manual_relays()?;
+        }
+
+        Ok(())
+    }
+
+    // Remember relays the user connected to for a feed, to reconnect to them on startup
+    fn remember_manual_relays(relays: &[RelayUrl]) -> Result<(), Error> {
+        let now = Unixtime::now();
+        let mut sessions = GLOBALS.db().read_manual_relay_sessions()?;
+        for relay in relays {
+            match sessions.iter_mut().find(|(r, _)| r == relay) {
+                Some(session) => session.1 = now,
+                None => sessions.push((relay.to_owned(), now)),
+            }
+        }
+        GLOBALS.db().write_manual_relay_sessions(&sessions, None)?;
+        Ok(())
+    }
+
+    // Forget a relay the user disconnected from
+    fn forget_manual_relay(relay_url: &RelayUrl) -> Result<(), Error> {
+        let mut sessions = GLOBALS.db().read_manual_relay_sessions()?;
+        let before = sessions.len();
+        sessions.retain(|(r, _)| r != relay_url);
+        if sessions.len() != before {
+            GLOBALS.db().write_manual_relay_sessions(&sessions, None)?;
+        }
+        Ok(())
+    }
+
+    /// Reconnect to the relays the user connected to for the global or a relay feed in
+    /// an earlier session, and did not disconnect from, listening for new events.
+    /// Relays not used for a feed in `MANUAL_RELAY_MAX_AGE_DAYS` are forgotten.
+    pub fn restore_manual_relays(&mut self) -> Result<(), Error> {
+        let cutoff = Unixtime::now().0 - MANUAL_RELAY_MAX_AGE_DAYS * 24 * 60 * 60;
+        let mut sessions = GLOBALS.db().read_manual_relay_sessions()?;
+        let before = sessions.len();
+        sessions.retain(|(_, at)| at.0 >= cutoff);
+        if sessions.len() != before {
+            GLOBALS.db().write_manual_relay_sessions(&sessions, None)?;
+        }
+
+        let relays: Vec<RelayUrl> = sessions.into_iter().map(|(r, _)| r).collect();
+        if relays.is_empty() {
+            return Ok(());
+        }
+
+        tracing::info!("Reconnecting to {} relays from last time", relays.len());
+
+        manager::run_jobs_on_all_relays(
+            relays,
+            vec![RelayJob {
+                reason: RelayConnectionReason::SubscribeGlobal,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::GlobalFeedFuture(
+                        Unixtime::now(),
+                    )),
+                },
+            }],
+        );
+
         Ok(())
     }
 
@@ -3153,35 +4489,266 @@ impl Overlord {
             ],
         );
 
-        Ok(())
+        Ok(())
+    }
+
+    /// Subscribe to the user's giftwrap events on their DM and INBOX relays
+    pub fn subscribe_giftwraps(&mut self) -> Result<(), Error> {
+        let mut relays: Vec<Relay> = GLOBALS
+            .db()
+            .filter_relays(|r| r.has_usage_bits(Relay::DM) || r.has_usage_bits(Relay::INBOX))?;
+        let relay_urls: Vec<RelayUrl> = relays.drain(..).map(|r| r.url).collect();
+
+        // 30 days worth (FIXME make this a setting?)
+        let after = Unixtime::now() - Duration::new(3600 * 24 * 30, 0);
+
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::Giftwraps,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::Giftwraps(
+                        FeedRange::After { since: after },
+                    )),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Subscribe to reactions, zaps and deletions of the user's recent notes on their
+    /// READ and WRITE relays, whatever is on screen. These drive notifications.
+    ///
+    /// If already subscribed, the subscription is updated to cover the current set of
+    /// recent notes.
+    pub fn subscribe_engagement() -> Result<(), Error> {
+        // How many of our most recent notes we watch
+        const ENGAGEMENT_NOTE_COUNT: usize = 100;
+
+        let pubkey = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => return Ok(()),
+        };
+
+        let notes: Vec<Event> = {
+            let mut kinds = crate::feed::feed_displayable_event_kinds(false);
+            kinds.retain(|k| {
+                !matches!(
+                    k,
+                    EventKind::EncryptedDirectMessage | EventKind::GiftWrap | EventKind::DmChat
+                )
+            });
+            let filter = Filter {
+                authors: vec![pubkey],
+                kinds,
+                limit: Some(ENGAGEMENT_NOTE_COUNT),
+                ..Default::default()
+            };
+            GLOBALS.db().find_events_by_filter(&filter, |_| true)?
+        };
+
+        // Nothing can react to a note before it exists, and we already have whatever
+        // came in before the newest engagement we hold. Notes come out newest first.
+        let mut anchor = match notes.last() {
+            Some(oldest) => oldest.created_at,
+            None => return Ok(()),
+        };
+        let newest_engagement = {
+            let mut filter = Filter {
+                kinds: vec![
+                    EventKind::Reaction,
+                    EventKind::Zap,
+                    EventKind::EventDeletion,
+                ],
+                limit: Some(1),
+                ..Default::default()
+            };
+            filter.set_tag_values('p', vec![pubkey.as_hex_string()]);
+            GLOBALS.db().find_events_by_filter(&filter, |_| true)?
+        };
+        if let Some(event) = newest_engagement.first() {
+            anchor = anchor.max(event.created_at);
+        }
+
+        let ids: Vec<Id> = notes.iter().map(|e| e.id).collect();
+
+        let relay_urls: Vec<RelayUrl> = GLOBALS
+            .db()
+            .filter_relays(|r| r.has_usage_bits(Relay::READ) || r.has_usage_bits(Relay::WRITE))?
+            .drain(..)
+            .map(|r| r.url)
+            .collect();
+
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::Engagement,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::Engagement { ids, anchor }),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Subscribe to a hashtag feed. This runs on a limited number of our best read
+    /// relays so that it doesn't fan out everywhere.
+    pub fn subscribe_hashtag_feed(&mut self, hashtag: String) -> Result<(), Error> {
+        let hashtag = match crate::feed::normalize_hashtag(&hashtag) {
+            Some(h) => h,
+            None => return Err(ErrorKind::Empty("hashtag".to_owned()).into()),
+        };
+
+        let anchor = GLOBALS.feed.current_anchor();
+        manager::run_jobs_on_all_relays(
+            Self::hashtag_feed_relays()?,
+            vec![
+                RelayJob {
+                    reason: RelayConnectionReason::SubscribeHashtag,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::HashtagFeedFuture {
+                            hashtag: hashtag.clone(),
+                            anchor,
+                        }),
+                    },
+                },
+                RelayJob {
+                    reason: RelayConnectionReason::SubscribeHashtag,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::HashtagFeedChunk {
+                            hashtag,
+                            anchor,
+                        }),
+                    },
+                },
+            ],
+        );
+
+        Ok(())
+    }
+
+    /// Subscribe to a custom feed of the events of the given people, of the given
+    /// kinds (or the usual feed kinds if none are given). The feed is remembered
+    /// under its name and resubscribed on startup until it is unsubscribed. Show it
+    /// with `FeedKind::Custom(name)`.
+    pub fn subscribe_custom_feed(
+        &mut self,
+        name: String,
+        pubkeys: Vec<PublicKey>,
+        kinds: Vec<EventKind>,
+    ) -> Result<(), Error> {
+        let name = name.trim().to_owned();
+        if name.is_empty() {
+            return Err(ErrorKind::Empty("custom feed name".to_owned()).into());
+        }
+
+        // If redefining, drop the old subscriptions, as some relays may no longer
+        // be needed
+        if GLOBALS.db().read_custom_feed(&name)?.is_some() {
+            self.unlisten_custom_feed(name.clone());
+        }
+
+        let custom_feed = CustomFeed1 {
+            name,
+            pubkeys,
+            kinds,
+        };
+        GLOBALS.db().write_custom_feed(&custom_feed, None)?;
+
+        Self::engage_custom_feed(&custom_feed)
     }
 
-    /// Subscribe to the user's giftwrap events on their DM and INBOX relays
-    pub fn subscribe_giftwraps(&mut self) -> Result<(), Error> {
-        let mut relays: Vec<Relay> = GLOBALS
-            .db()
-            .filter_relays(|r| r.has_usage_bits(Relay::DM) || r.has_usage_bits(Relay::INBOX))?;
-        let relay_urls: Vec<RelayUrl> = relays.drain(..).map(|r| r.url).collect();
+    /// Stop a custom feed and forget it
+    pub fn unsubscribe_custom_feed(&mut self, name: String) -> Result<(), Error> {
+        GLOBALS.db().delete_custom_feed(&name, None)?;
+        self.unlisten_custom_feed(name);
+        Ok(())
+    }
 
-        // 30 days worth (FIXME make this a setting?)
-        let after = Unixtime::now() - Duration::new(3600 * 24 * 30, 0);
+    fn unlisten_custom_feed(&mut self, name: String) {
+        let _ = self.to_minions.send(ToMinionMessage {
+            target: "all".to_string(),
+            payload: ToMinionPayload {
+                job_id: 0,
+                detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::CustomFeedFuture {
+                    name,
+                    pubkeys: vec![],         // does not matter
+                    kinds: vec![],           // does not matter
+                    anchor: Unixtime::now(), // does not matter
+                }),
+            },
+        });
+    }
 
-        manager::run_jobs_on_all_relays(
-            relay_urls,
-            vec![RelayJob {
-                reason: RelayConnectionReason::Giftwraps,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::Giftwraps(
-                        FeedRange::After { since: after },
-                    )),
-                },
-            }],
-        );
+    // Subscribe on each person's write relays, asking each relay only for the
+    // people that write there.
+    fn engage_custom_feed(custom_feed: &CustomFeed1) -> Result<(), Error> {
+        let anchor = Unixtime::now();
+        let kinds = custom_feed.event_kinds();
+        for (relay_url, pubkeys) in Self::custom_feed_relays(custom_feed)? {
+            manager::run_jobs_on_all_relays(
+                vec![relay_url],
+                vec![
+                    RelayJob {
+                        reason: RelayConnectionReason::SubscribeCustomFeed,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(FilterSet::CustomFeedFuture {
+                                name: custom_feed.name.clone(),
+                                pubkeys: pubkeys.clone(),
+                                kinds: kinds.clone(),
+                                anchor,
+                            }),
+                        },
+                    },
+                    RelayJob {
+                        reason: RelayConnectionReason::SubscribeCustomFeed,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(FilterSet::CustomFeedChunk {
+                                name: custom_feed.name.clone(),
+                                pubkeys,
+                                kinds: kinds.clone(),
+                                anchor,
+                            }),
+                        },
+                    },
+                ],
+            );
+        }
 
         Ok(())
     }
 
+    fn custom_feed_relays(
+        custom_feed: &CustomFeed1,
+    ) -> Result<HashMap<RelayUrl, Vec<PublicKey>>, Error> {
+        let mut by_relay: HashMap<RelayUrl, Vec<PublicKey>> = HashMap::new();
+        for pubkey in &custom_feed.pubkeys {
+            for relay_url in relay::get_some_pubkey_outboxes(*pubkey)? {
+                by_relay.entry(relay_url).or_default().push(*pubkey);
+            }
+        }
+        Ok(by_relay)
+    }
+
+    fn hashtag_feed_relays() -> Result<Vec<RelayUrl>, Error> {
+        let mut relays = Relay::choose_relays(Relay::READ, |_| true)?;
+        relays.sort_by(|a, b| {
+            b.adjusted_score(ScoreFactors::FULLY_ADJUSTED)
+                .partial_cmp(&a.adjusted_score(ScoreFactors::FULLY_ADJUSTED))
+                .unwrap()
+        });
+        relays.truncate(GLOBALS.db().read_setting_num_relays_for_hashtags() as usize);
+        Ok(relays.drain(..).map(|r| r.url).collect())
+    }
+
     /// Subscribe to nip46 nostr connect relays
     pub fn subscribe_nip46(&mut self, relays: Vec<RelayUrl>) -> Result<(), Error> {
         manager::run_jobs_on_all_relays(
@@ -3218,6 +4785,171 @@ impl Overlord {
         }));
     }
 
+    /// Probe every known relay that we are allowed to connect to without asking:
+    /// connect, fetch its NIP-11 and run a small REQ. Each relay's record is updated,
+    /// and a summary is written to the status queue when done. Progress is available
+    /// in `GLOBALS.health_check_status` while it runs. Nothing is done while offline.
+    pub fn health_check_all_relays() {
+        if GLOBALS.db().read_setting_offline() {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("Relay health check skipped: we are offline.".to_owned());
+            return;
+        }
+        if GLOBALS.health_check_status.read().is_some() {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("A relay health check is already running.".to_owned());
+            return;
+        }
+        *GLOBALS.health_check_status.write() = Some("Starting relay health check...".to_owned());
+
+        std::mem::drop(tokio::task::spawn(async move {
+            let result = Self::health_check_all_relays_inner().await;
+            *GLOBALS.health_check_status.write() = None;
+            match result {
+                Ok((total, healthy, unreachable, timed_out, changed)) => {
+                    GLOBALS.status_queue.write().write(format!(
+                        "Checked {} relays: {} healthy, {} unreachable, {} timed out, {} with changed NIP-11.",
+                        total, healthy, unreachable, timed_out, changed
+                    ));
+                }
+                Err(e) => tracing::error!("Relay health check failed: {}", e),
+            }
+        }));
+    }
+
+    async fn health_check_all_relays_inner() -> Result<(usize, usize, usize, usize, usize), Error> {
+        use futures_util::StreamExt;
+
+        // Don't open hundreds of sockets at once
+        const CONCURRENCY: usize = 10;
+
+        let relays: Vec<Relay> = GLOBALS.db().filter_relays(|r| {
+            r.rank != 0 && !Storage::url_is_banned(&r.url) && manager::may_connect_unasked(r)
+        })?;
+        let total = relays.len();
+
+        let mut checked: usize = 0;
+        let mut healthy: usize = 0;
+        let mut unreachable: usize = 0;
+        let mut timed_out: usize = 0;
+        let mut changed: usize = 0;
+
+        let mut probes = futures_util::stream::iter(relays)
+            .map(|relay| Self::health_check_relay(relay.url))
+            .buffer_unordered(CONCURRENCY);
+        while let Some((health, nip11_changed)) = probes.next().await {
+            checked += 1;
+            match health {
+                RelayHealth::TimedOut => timed_out += 1,
+                RelayHealth::Healthy => healthy += 1,
+                RelayHealth::Unreachable => unreachable += 1,
+                RelayHealth::Unresponsive => {}
+            }
+            if nip11_changed {
+                changed += 1;
+            }
+            *GLOBALS.health_check_status.write() =
+                Some(format!("Checked {} of {} relays", checked, total));
+        }
+
+        Ok((total, healthy, unreachable, timed_out, changed))
+    }
+
+    /// Probe one relay, with a time limit. Returns its health and whether its
+    /// NIP-11 changed. A relay that merely ran out of time is not counted as
+    /// a failure.
+    async fn health_check_relay(url: RelayUrl) -> (RelayHealth, bool) {
+        const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+        let nip11 =
+            match tokio::time::timeout(PROBE_TIMEOUT, crate::direct::fetch_nip11(url.as_str()))
+                .await
+            {
+                Ok(Ok(value)) => serde_json::from_value::<RelayInformationDocument>(value).ok(),
+                _ => None,
+            };
+
+        let health = match tokio::time::timeout(PROBE_TIMEOUT, async {
+            let mut conn = crate::direct::Connection::new(url.as_str().to_owned()).await?;
+            let mut filter = Filter::new();
+            filter.add_event_kind(EventKind::TextNote);
+            filter.limit = Some(1);
+            let result = conn.fetch_events(filter, Duration::from_secs(3)).await?;
+            let _ = conn.disconnect().await;
+            // An EOSE or a CLOSED both mean the relay answered
+            Ok::<bool, Error>(result.post_eose_events.is_some() || result.close_msg.is_some())
+        })
+        .await
+        {
+            Ok(Ok(true)) => RelayHealth::Healthy,
+            Ok(Ok(false)) => RelayHealth::Unresponsive,
+            Ok(Err(_)) => RelayHealth::Unreachable,
+            Err(_) => RelayHealth::TimedOut,
+        };
+
+        let now = Unixtime::now().0 as u64;
+        let mut nip11_changed = false;
+        let old_nip11 = GLOBALS
+            .db()
+            .read_relay(&url)
+            .ok()
+            .flatten()
+            .and_then(|relay| relay.nip11);
+        if let Err(e) = GLOBALS.db().modify_relay(
+            &url,
+            |relay| {
+                match health {
+                    RelayHealth::Unreachable => relay.failure_count += 1,
+                    RelayHealth::TimedOut => (),
+                    _ => {
+                        relay.success_count += 1;
+                        relay.last_connected_at = Some(now);
+                    }
+                }
+                relay.last_attempt_nip11 = Some(now);
+                if let Some(doc) = &nip11 {
+                    if let Some(old) = &relay.nip11 {
+                        nip11_changed =
+                            serde_json::to_value(old).ok() != serde_json::to_value(doc).ok();
+                    }
+                    relay.nip11 = Some(doc.clone());
+                }
+            },
+            None,
+        ) {
+            tracing::error!("{}", e);
+        }
+        if let Some(doc) = &nip11 {
+            if let Err(e) = relay::note_nip11_change(&url, old_nip11.as_ref(), doc) {
+                tracing::error!("{}", e);
+            }
+        }
+
+        (health, nip11_changed)
+    }
+
+    /// Ask relays to count the events matching `filter` (NIP-45), without
+    /// downloading them. The largest count returned is put into
+    /// `GLOBALS.relay_counts` under `job_id`, or into `GLOBALS.reaction_counts` if
+    /// `job_id` is in `GLOBALS.reaction_count_jobs`.
+    pub(crate) fn count_on_relays(job_id: u64, filter: Filter, relays: Vec<Relay>) {
+        let urls: Vec<RelayUrl> = relays.iter().map(|r| r.url.clone()).collect();
+        manager::run_jobs_on_all_relays(
+            urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::Counting,
+                payload: ToMinionPayload {
+                    job_id,
+                    detail: ToMinionPayloadDetail::CountSubscribe(filter),
+                },
+            }],
+        );
+    }
+
     // Start tracking the followers of this pubkey if we are not already
     async fn track_followers(&self, pubkey: PublicKey) -> Result<(), Error> {
         // The UI will handle resetting GLOBALS.followers. Abort if we have a mismatch
@@ -3249,6 +4981,22 @@ impl Overlord {
                 .unwrap()
         });
         relays.truncate(GLOBALS.db().read_setting_num_relays_for_counting() as usize);
+
+        // Relays that support NIP-45 can count them for us. The rest send us the
+        // contact lists and we count them ourselves.
+        let (counting, relays): (Vec<Relay>, Vec<Relay>) =
+            relays.drain(..).partition(|r| r.supports_nip(45));
+        if !counting.is_empty() {
+            let mut filter = Filter {
+                kinds: vec![EventKind::ContactList],
+                ..Default::default()
+            };
+            filter.set_tag_values('p', vec![pubkey.as_hex_string()]);
+            let job_id = rand::random::<u64>();
+            GLOBALS.followers.write().count_job = Some(job_id);
+            Self::count_on_relays(job_id, filter, counting);
+        }
+
         let relays: Vec<RelayUrl> = relays.iter().map(|r| r.url.clone()).collect();
         manager::run_jobs_on_all_relays(
             relays,
@@ -3445,6 +5193,42 @@ impl Overlord {
         })
     }
 
+    /// Hold an action that needs the private key until it is unlocked, and ask the
+    /// user to unlock it
+    fn hold_for_signer(message: ToOverlordMessage) {
+        tracing::info!("Key is locked, holding an action until it is unlocked");
+        GLOBALS.held_for_signer.write().push(message);
+        GLOBALS.pending.insert(PendingItem::SignerLocked);
+        GLOBALS
+            .status_queue
+            .write()
+            .write("Your key is locked. Unlock it to finish what you started.".to_owned());
+    }
+
+    /// Resume the actions that were held because the private key was locked.
+    /// This is called when the key is unlocked.
+    pub fn release_held_for_signer() {
+        GLOBALS.pending.remove(&PendingItem::SignerLocked);
+        let held: Vec<ToOverlordMessage> = GLOBALS.held_for_signer.write().drain(..).collect();
+        for message in held {
+            let _ = GLOBALS.to_overlord.send(message);
+        }
+    }
+
+    /// Drop the actions that were held because the private key was locked
+    pub fn discard_held_for_signer() {
+        GLOBALS.pending.remove(&PendingItem::SignerLocked);
+        GLOBALS.held_for_signer.write().clear();
+    }
+
+    /// Lock the private keys (ours and the client's). Reading still works, but
+    /// anything that needs to sign waits until they are unlocked again.
+    pub fn lock_key() -> Result<(), Error> {
+        GLOBALS.identity.lock()?;
+        GLOBALS.client_identity.lock()?;
+        Ok(())
+    }
+
     /// Unlock the private key with the given passphrase so that gossip can use it.
     /// This is akin to logging in.
     pub fn unlock_key(mut password: String) -> Result<(), Error> {
@@ -3473,20 +5257,25 @@ impl Overlord {
         // for it's retry logic
         GLOBALS.people.metadata_fetch_initiated(&[pubkey]);
 
-        let best_relays = relay::get_some_pubkey_outboxes(pubkey)?;
+        let mut best_relays = relay::get_metadata_pubkey_outboxes(pubkey)?;
+
+        // If we know no relays for them, make first contact through our indexer relays
+        if best_relays.is_empty() {
+            best_relays = relay::first_contact_relays()?;
+        }
 
-        // we do 1 more than num_relays_per_person, which is really for main posts,
-        // since metadata is more important and I didn't want to bother with
-        // another setting.
         manager::run_jobs_on_all_relays(
             best_relays,
-            vec![RelayJob {
-                reason: RelayConnectionReason::FetchMetadata,
-                payload: ToMinionPayload {
-                    job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::Metadata(vec![pubkey])),
-                },
-            }],
+            FilterSet::metadata(vec![pubkey])
+                .into_iter()
+                .map(|filter_set| RelayJob {
+                    reason: RelayConnectionReason::FetchMetadata,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(filter_set),
+                    },
+                })
+                .collect(),
         );
 
         // Mark in globals that we want to recheck their nip-05 when that metadata
@@ -3503,8 +5292,18 @@ impl Overlord {
         GLOBALS.people.metadata_fetch_initiated(&pubkeys);
 
         let mut map: HashMap<RelayUrl, Vec<PublicKey>> = HashMap::new();
+        let mut first_contact_relays: Option<Vec<RelayUrl>> = None;
         for pubkey in pubkeys.drain(..) {
-            let best_relays = relay::get_some_pubkey_outboxes(pubkey)?;
+            let mut best_relays = relay::get_metadata_pubkey_outboxes(pubkey)?;
+
+            // If we know no relays for them, make first contact through our indexer relays
+            if best_relays.is_empty() {
+                if first_contact_relays.is_none() {
+                    first_contact_relays = Some(relay::first_contact_relays()?);
+                }
+                best_relays = first_contact_relays.clone().unwrap_or_default();
+            }
+
             for relay_url in best_relays.iter() {
                 map.entry(relay_url.to_owned())
                     .and_modify(|entry| entry.push(pubkey))
@@ -3514,13 +5313,16 @@ impl Overlord {
         for (relay_url, pubkeys) in map.drain() {
             manager::engage_minion(
                 relay_url.clone(),
-                vec![RelayJob {
-                    reason: RelayConnectionReason::FetchMetadata,
-                    payload: ToMinionPayload {
-                        job_id: rand::random::<u64>(),
-                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::Metadata(pubkeys)),
-                    },
-                }],
+                FilterSet::metadata(pubkeys)
+                    .into_iter()
+                    .map(|filter_set| RelayJob {
+                        reason: RelayConnectionReason::FetchMetadata,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(filter_set),
+                        },
+                    })
+                    .collect(),
             );
         }
 
@@ -3670,7 +5472,7 @@ impl Overlord {
         // If there is a URL
         if let Some(url) = recommended_relay_url
             .as_ref()
-            .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok())
+            .and_then(|rru| crate::relay::relay_url_from_unchecked(rru).ok())
         {
             // Save relay if missing
             GLOBALS.db().write_relay_if_missing(&url, Some(txn))?;
@@ -3724,6 +5526,25 @@ impl Overlord {
             return Ok(());
         }
 
+        // If what we advertise changed, maybe re-advertise
+        let advertised = Relay::INBOX | Relay::OUTBOX | Relay::DM;
+        if (old.get_usage_bits() ^ new.get_usage_bits()) & advertised != 0 {
+            Self::schedule_auto_advertise();
+        }
+
+        // If the cutoff changed, tell the minion which checks it
+        if old.skip_events_before != new.skip_events_before
+            && GLOBALS.connected_relays.contains_key(&new.url)
+        {
+            let _ = self.to_minions.send(ToMinionMessage {
+                target: new.url.as_str().to_owned(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::SkipEventsBefore(new.skip_events_before),
+                },
+            });
+        }
+
         // If rank went to zero
         if old.rank != 0 && new.rank == 0 {
             // Close minion for this relay
@@ -3738,15 +5559,21 @@ impl Overlord {
 
         // if usage bits changed
         if old.get_usage_bits() != new.get_usage_bits() {
-            if old.has_usage_bits(Relay::READ) && !new.has_usage_bits(Relay::READ) {
+            // READ and INBOX relays both receive things addressed to us
+            let old_inbox = old.get_usage_bits() & (Relay::READ | Relay::INBOX) != 0;
+            let new_inbox = new.get_usage_bits() & (Relay::READ | Relay::INBOX) != 0;
+            if old_inbox && !new_inbox {
                 inbox = -1;
-            } else if !old.has_usage_bits(Relay::READ) && new.has_usage_bits(Relay::READ) {
+            } else if !old_inbox && new_inbox {
                 inbox = 1;
             }
 
-            if old.has_usage_bits(Relay::WRITE) && !new.has_usage_bits(Relay::WRITE) {
+            // WRITE and OUTBOX relays both hold our own config events
+            let old_config = old.get_usage_bits() & (Relay::WRITE | Relay::OUTBOX) != 0;
+            let new_config = new.get_usage_bits() & (Relay::WRITE | Relay::OUTBOX) != 0;
+            if old_config && !new_config {
                 config = -1;
-            } else if !old.has_usage_bits(Relay::WRITE) && new.has_usage_bits(Relay::WRITE) {
+            } else if !old_config && new_config {
                 config = 1;
             }
 
@@ -3760,10 +5587,10 @@ impl Overlord {
         // If rank came from zero, start subs on this relay
         if old.rank == 0 && new.rank != 0 {
             // Start minion for this relay
-            if new.has_usage_bits(Relay::READ) {
+            if new.has_usage_bits(Relay::READ) || new.has_usage_bits(Relay::INBOX) {
                 inbox = 1;
             }
-            if new.has_usage_bits(Relay::WRITE) {
+            if new.has_usage_bits(Relay::WRITE) || new.has_usage_bits(Relay::OUTBOX) {
                 config = 1;
             }
             if new.has_usage_bits(Relay::DISCOVER) {
@@ -3804,6 +5631,11 @@ impl Overlord {
 
                     // Subscribe to config on this outbox relay
                     self.subscribe_config(Some(vec![new.url.clone()]))?;
+
+                    // Offer to copy our config to it
+                    GLOBALS
+                        .pending
+                        .insert(PendingItem::RelayNeedsConfig(new.url.clone()));
                 }
             }
             _ => (),
@@ -3818,6 +5650,13 @@ impl Overlord {
             _ => (),
         }
 
+        // Our own relays changed, so relay scores and picks may need to change too
+        if inbox != 0 || config != 0 {
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::RefreshScoresAndPickRelays);
+        }
+
         Ok(())
     }
 
@@ -3827,6 +5666,11 @@ impl Overlord {
     ///
     /// WARNING: DO NOT CALL TOO OFTEN or relays will hate you.
     pub fn visible_notes_changed(&mut self, mut visible: Vec<Id>) -> Result<(), Error> {
+        // In quiet mode we do not fetch augments
+        if GLOBALS.quiet_mode.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
         // Work out which relays to use to find augments for which ids
         let mut augment_subs: HashMap<RelayUrl, Vec<Id>> = HashMap::new();
         for id in visible.drain(..) {
@@ -3845,20 +5689,128 @@ impl Overlord {
             }
         }
 
+        // Relays that support NIP-45 count reactions for us instead of sending them
+        let mut count_subs: HashMap<Id, Vec<Relay>> = HashMap::new();
+
         // Create jobs for minions
         for (relay_url, ids) in augment_subs.drain() {
+            let reactions = match GLOBALS.db().read_relay(&relay_url)? {
+                Some(relay) if relay.supports_nip(45) => {
+                    for id in ids.iter() {
+                        count_subs.entry(*id).or_default().push(relay.clone());
+                    }
+                    false
+                }
+                _ => true,
+            };
             manager::engage_minion(
                 relay_url,
                 vec![RelayJob {
                     reason: RelayConnectionReason::FetchAugments,
                     payload: ToMinionPayload {
                         job_id: rand::random::<u64>(),
-                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::Augments(ids)),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::Augments {
+                            ids,
+                            reactions,
+                        }),
                     },
                 }],
             );
         }
 
+        // Counts for notes no longer visible are of no interest
+        GLOBALS.reaction_count_jobs.clear();
+        for (id, relays) in count_subs.drain() {
+            let mut filter = Filter {
+                kinds: vec![EventKind::Reaction],
+                ..Default::default()
+            };
+            filter.set_tag_values('e', vec![id.as_hex_string()]);
+            let job_id = rand::random::<u64>();
+            GLOBALS.reaction_count_jobs.insert(job_id, id);
+            Self::count_on_relays(job_id, filter, relays);
+        }
+
+        Ok(())
+    }
+
+    /// Vote in the NIP-88 poll `id` by publishing a response that picks `choices`.
+    ///
+    /// The response goes to the relays the poll names, or to our WRITE relays if it
+    /// names none. Votes after the poll has ended, or with more than one choice in
+    /// a single choice poll, are refused.
+    pub async fn vote_in_poll(id: Id, choices: Vec<String>) -> Result<(), Error> {
+        let public_key = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => {
+                tracing::warn!("No public key! Not posting");
+                return Ok(());
+            }
+        };
+
+        let poll = match GLOBALS.db().read_event(id)? {
+            Some(event) => match crate::polls::Poll::from_event(&event) {
+                Some(poll) => poll,
+                None => {
+                    return Err(ErrorKind::General("That event is not a poll.".to_owned()).into())
+                }
+            },
+            None => return Err(ErrorKind::EventNotFound.into()),
+        };
+        poll.check_vote(&choices)?;
+
+        let event = {
+            let mut tags: Vec<Tag> = vec![ParsedTag::Event {
+                id,
+                recommended_relay_url: relay::recommended_relay_hint(id)?
+                    .map(|rr| rr.to_unchecked_url()),
+                marker: None,
+                author_pubkey: Some(poll.author),
+            }
+            .into_tag()];
+            for choice in &choices {
+                tags.push(Tag::new(&["response", choice]));
+            }
+
+            if GLOBALS.db().read_setting_set_client_tag() {
+                tags.push(Tag::new(&["client", "gossip"]));
+            }
+
+            let pre_event = PreEvent {
+                pubkey: public_key,
+                created_at: Unixtime::now(),
+                kind: crate::polls::POLL_RESPONSE_KIND.into(),
+                tags,
+                content: "".to_owned(),
+            };
+
+            crate::post::sign_event(pre_event).await?
+        };
+
+        let mut relay_urls: Vec<RelayUrl> = poll.relays.clone();
+        relay_urls
+            .retain(|r| !matches!(GLOBALS.db().read_relay(r), Ok(Some(relay)) if relay.no_post));
+        if relay_urls.is_empty() {
+            relay_urls = relay::relays_to_post_to(&event)?;
+        }
+        for url in &relay_urls {
+            tracing::debug!("Asking {} to post", url);
+        }
+
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::PostEvent,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
+                },
+            }],
+        );
+
+        // Process the message for ourself
+        crate::process::process_new_event(&event, None, None, false, false)?;
+
         Ok(())
     }
 
@@ -4109,21 +6061,3 @@ impl Overlord {
         Ok(())
     }
 }
-
-fn work_logger(work_receiver: mpsc::Receiver<u8>, powint: u8) {
-    while let Ok(work) = work_receiver.recv() {
-        if work >= powint {
-            // Even if work > powint, it doesn't count since we declared our target.
-            GLOBALS
-                .status_queue
-                .write()
-                .write(format!("Message sent with {powint} bits of work computed."));
-            break;
-        } else {
-            GLOBALS
-                .status_queue
-                .write()
-                .write(format!("PoW: {work}/{powint}"));
-        }
-    }
-}
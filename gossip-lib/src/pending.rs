@@ -5,7 +5,7 @@ use crate::nostr_connect_server::ParsedCommand;
 use crate::people::PersonList;
 use crate::relay::Relay;
 use crate::storage::Storage;
-use nostr_types::{EventKind, Filter, PublicKey, RelayList, RelayUrl, Unixtime};
+use nostr_types::{EventKind, Filter, Id, PublicKey, RelayList, RelayUrl, Unixtime};
 use parking_lot::RwLock as PRwLock;
 use parking_lot::RwLockReadGuard as PRwLockReadGuard;
 use std::collections::hash_map::DefaultHasher;
@@ -43,14 +43,25 @@ pub enum PendingItem {
     PersonListNeverPublished(PersonList),
     PersonListOutOfSync(PersonList),
     PersonListNotPublishedRecently(PersonList),
-    // A posted event didn't make it to all the relays it should go to.
-    // PROBLEM: Often there is a dead relay on somebody's list and so these events pile
-    //          up far too much.
-    // RetryPost(Id),
+    /// A relay refused one of our posts, and no relay has accepted it. It may be
+    /// retried automatically; see [UndeliveredPost](crate::UndeliveredPost). This
+    /// clears itself once any relay accepts the post.
+    PostUndelivered {
+        id: Id,
+        relay: RelayUrl,
+    },
+
     NeedReadRelays,
     NeedWriteRelays,
     NeedDiscoverRelays,
     NeedDMRelays,
+
+    /// We started writing to this relay, which may not have our metadata, contact
+    /// list, relay list or mute list yet
+    RelayNeedsConfig(RelayUrl),
+
+    /// Actions are being held until the private key is unlocked
+    SignerLocked,
 }
 
 pub struct Pending {
@@ -252,7 +263,7 @@ impl Pending {
                 if !dm_relay_lists.is_empty() {
                     for tag in dm_relay_lists[0].tags.iter() {
                         if tag.tagname() == "relay" {
-                            if let Ok(relay_url) = RelayUrl::try_from_str(tag.value()) {
+                            if let Ok(relay_url) = crate::relay::parse_relay_url(tag.value()) {
                                 // Don't use banned relay URLs
                                 if !Storage::url_is_banned(&relay_url) {
                                     relays.push(relay_url);
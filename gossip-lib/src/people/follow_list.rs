@@ -1,3 +1,4 @@
+use crate::globals::GLOBALS;
 use nostr_types::PublicKey;
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
@@ -36,12 +37,28 @@ impl From<SortablePubkey> for PublicKey {
 pub struct FollowList {
     pub who: Option<PublicKey>,
     pub set: BTreeSet<SortablePubkey>,
+
+    /// The job asking NIP-45 relays to count these, if any
+    pub count_job: Option<u64>,
 }
 
 impl FollowList {
     pub fn reset(&mut self, pubkey: PublicKey) {
         self.who = Some(pubkey);
         self.set = BTreeSet::new();
+        if let Some(job_id) = self.count_job.take() {
+            let _ = GLOBALS.relay_counts.remove(&job_id);
+        }
+    }
+
+    /// How many there are. This is the larger of what relays counted for us
+    /// (NIP-45) and what we have collected.
+    pub fn count(&self) -> usize {
+        let relay_count = self
+            .count_job
+            .and_then(|job_id| GLOBALS.relay_counts.get(&job_id).map(|c| *c))
+            .unwrap_or(0);
+        self.set.len().max(relay_count)
     }
 
     pub fn add(&mut self, follower: PublicKey) {
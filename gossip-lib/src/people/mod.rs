@@ -11,11 +11,11 @@ use crate::storage::{PersonTable, Table};
 use dashmap::{DashMap, DashSet};
 use image::RgbaImage;
 use nostr_types::{
-    ContentEncryptionAlgorithm, Event, EventKind, Metadata, ParsedTag, PreEvent, PublicKey,
-    RelayUrl, Tag, UncheckedUrl, Unixtime, Url,
+    ContentEncryptionAlgorithm, Event, EventKind, Metadata, ParsedTag, PreEvent, Profile,
+    PublicKey, RelayUrl, Tag, UncheckedUrl, Unixtime, Url,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::hash::{Hash, Hasher};
 use std::sync::atomic::Ordering;
 use std::time::Duration;
@@ -147,6 +147,57 @@ impl People {
         }
     }
 
+    /// Pull public keys (npub or hex) out of pasted text such as a CSV export,
+    /// one or more per line. Returns the keys found (possibly with duplicates) and
+    /// the number of non-empty lines that had no key in them.
+    pub fn parse_pubkey_list(text: &str) -> (Vec<PublicKey>, usize) {
+        let (profiles, invalid_lines) = Self::parse_profile_list(text);
+        (
+            profiles.into_iter().map(|p| p.pubkey).collect(),
+            invalid_lines,
+        )
+    }
+
+    /// Like [parse_pubkey_list](Self::parse_pubkey_list), but also accepts nprofiles
+    /// and keeps their relay hints. Plain keys come back with no relays.
+    pub fn parse_profile_list(text: &str) -> (Vec<Profile>, usize) {
+        let mut profiles: Vec<Profile> = Vec::new();
+        let mut invalid_lines: usize = 0;
+        for line in text.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut found = false;
+            for field in line.split(|c: char| c == ',' || c == ';' || c.is_whitespace()) {
+                let field = field.trim_matches(|c: char| c == '"' || c == '\'');
+                let field = field.strip_prefix("nostr:").unwrap_or(field);
+                if field.is_empty() {
+                    continue;
+                }
+                if let Ok(profile) = Profile::try_from_bech32_string(field, true) {
+                    profiles.push(profile);
+                    found = true;
+                } else if let Ok(pubkey) = PublicKey::try_from_bech32_string(field, true) {
+                    profiles.push(Profile {
+                        pubkey,
+                        relays: vec![],
+                    });
+                    found = true;
+                } else if let Ok(pubkey) = PublicKey::try_from_hex_string(field, true) {
+                    profiles.push(Profile {
+                        pubkey,
+                        relays: vec![],
+                    });
+                    found = true;
+                }
+            }
+            if !found {
+                invalid_lines += 1;
+            }
+        }
+        (profiles, invalid_lines)
+    }
+
     /// Create person record for this pubkey, if missing
     pub fn create_if_missing(&self, pubkey: PublicKey) {
         if let Err(e) = self.create_all_if_missing(&[pubkey]) {
@@ -314,6 +365,20 @@ impl People {
         // Remove from failed avatars list so the UI will try to fetch the avatar again if missing
         GLOBALS.failed_avatars.write().remove(pubkey);
 
+        if fresh {
+            // Newer metadata came in, so give its avatar another chance. A failed
+            // fetch would otherwise stick, and the UI would never ask again. Stale
+            // copies of the metadata don't retry it, or every relay that sends
+            // one would have us fetch a broken avatar again.
+            if let Some(url) = person.picture() {
+                if let Ok(url) = Url::try_from_unchecked_url(&UncheckedUrl(url.to_owned())) {
+                    GLOBALS.fetcher.clear_for_retry(url);
+                }
+            }
+
+            prefetch_profile_media(&person);
+        }
+
         // Only if they have a nip05 dns id set
         if matches!(person.metadata(), Some(Metadata { nip05: Some(_), .. })) {
             // Recheck nip05 every day if invalid, and every two weeks if valid
@@ -1049,3 +1114,116 @@ pub fn hash_person_list_event(list: PersonList) -> Result<u64, Error> {
         Ok(0)
     }
 }
+
+// Fetch a person's avatar and banner into the cache now, if the settings want that,
+// rather than waiting for them to be displayed. The UI then loads them from the cache.
+fn prefetch_profile_media(person: &Person) {
+    if !GLOBALS.db().read_setting_prefetch_profile_media()
+        || GLOBALS.db().read_setting_metered_connection()
+    {
+        return;
+    }
+
+    let mut urls: Vec<&str> = Vec::new();
+    if GLOBALS.db().read_setting_load_avatars() {
+        urls.extend(person.picture());
+    }
+    if GLOBALS.db().read_setting_load_media() {
+        if let Some(md) = person.metadata() {
+            urls.extend(crate::nip24::get_string(md, crate::nip24::BANNER));
+        }
+    }
+
+    for url in urls {
+        let url = match Url::try_from_unchecked_url(&UncheckedUrl(url.to_owned())) {
+            Ok(url) => url,
+            Err(_) => continue,
+        };
+        task::spawn(async move {
+            if let Ok(FetchResult::Failed(s)) = GLOBALS.fetcher.get(url, true).await {
+                tracing::debug!("Prefetch failed: {s}");
+            }
+        });
+    }
+}
+
+// The members of the latest published event of the list, and when it was published.
+// Private members are only included if the identity is unlocked.
+pub(crate) fn published_person_list_members(
+    list: PersonList,
+) -> Result<Option<(Unixtime, BTreeSet<PublicKey>)>, Error> {
+    let my_pubkey = match GLOBALS.db().read_setting_public_key() {
+        Some(pk) => pk,
+        None => return Err(ErrorKind::NoPublicKey.into()),
+    };
+
+    let metadata = match GLOBALS.db().get_person_list_metadata(list)? {
+        Some(m) => m,
+        None => return Err(ErrorKind::ListNotFound.into()),
+    };
+
+    let event =
+        match GLOBALS
+            .db()
+            .get_replaceable_event(list.event_kind(), my_pubkey, &metadata.dtag)?
+        {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+
+    let mut members: BTreeSet<PublicKey> = BTreeSet::new();
+    for tag in &event.tags {
+        if let Ok(ParsedTag::Pubkey { pubkey, .. }) = tag.parse() {
+            members.insert(pubkey);
+        }
+    }
+
+    if event.kind != EventKind::ContactList
+        && !event.content.is_empty()
+        && GLOBALS.identity.is_unlocked()
+    {
+        let decrypted_content = GLOBALS.identity.decrypt(&my_pubkey, &event.content)?;
+        let tags: Vec<Tag> = serde_json::from_str(&decrypted_content)?;
+        for tag in &tags {
+            if let Ok(ParsedTag::Pubkey { pubkey, .. }) = tag.parse() {
+                members.insert(pubkey);
+            }
+        }
+    }
+
+    Ok(Some((event.created_at, members)))
+}
+
+/// What publishing a person list would change, compared to the latest version of
+/// it that we have seen published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersonListDiff {
+    pub list: PersonList,
+
+    /// How many people the list would have once published
+    pub count: usize,
+
+    /// When the version we compared against was published, or None if we have
+    /// never seen it published
+    pub published_at: Option<Unixtime>,
+
+    /// People on the local list that the published version doesn't have
+    pub added: Vec<PublicKey>,
+
+    /// People on the published version that the local list doesn't have
+    pub removed: Vec<PublicKey>,
+}
+
+impl PersonListDiff {
+    /// Whether publishing would change nothing
+    pub fn is_unchanged(&self) -> bool {
+        self.published_at.is_some() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Whether publishing would drop at least half of the published list. This is
+    /// what a truncated local list looks like.
+    pub fn removes_most(&self) -> bool {
+        let published = self.count + self.removed.len() - self.added.len();
+        !self.removed.is_empty() && self.removed.len() * 2 >= published
+    }
+}
@@ -0,0 +1,350 @@
+//! NIP-88 polls
+//!
+//! A poll (kind 1068) lists its options in `option` tags, the relays its responses
+//! should go to in `relay` tags, and when voting ends in an `endsAt` tag. People
+//! answer it with a poll response (kind 1018) that names the poll in an `e` tag
+//! and the options they picked in `response` tags.
+
+use crate::error::{Error, ErrorKind};
+use nostr_types::{Event, EventKind, Id, ParsedTag, PublicKey, RelayUrl, Unixtime};
+use std::collections::HashMap;
+
+/// The kind number of a poll
+pub const POLL_KIND: u32 = 1068;
+
+/// The kind number of a response to a poll
+pub const POLL_RESPONSE_KIND: u32 = 1018;
+
+/// Whether events of this kind are polls
+pub fn is_poll(kind: EventKind) -> bool {
+    u32::from(kind) == POLL_KIND
+}
+
+/// Whether events of this kind are responses to polls
+pub fn is_poll_response(kind: EventKind) -> bool {
+    u32::from(kind) == POLL_RESPONSE_KIND
+}
+
+/// How many options each voter may pick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PollType {
+    #[default]
+    SingleChoice,
+    MultipleChoice,
+}
+
+/// One of the answers a poll offers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollOption {
+    pub id: String,
+    pub label: String,
+}
+
+/// A poll, as parsed from its event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poll {
+    pub id: Id,
+    pub author: PublicKey,
+    pub question: String,
+    pub options: Vec<PollOption>,
+    pub poll_type: PollType,
+
+    /// The relays responses should be published to, and read from
+    pub relays: Vec<RelayUrl>,
+
+    /// Responses after this time are not counted
+    pub ends_at: Option<Unixtime>,
+}
+
+impl Poll {
+    pub fn from_event(event: &Event) -> Option<Poll> {
+        if !is_poll(event.kind) {
+            return None;
+        }
+
+        let mut poll = Poll {
+            id: event.id,
+            author: event.pubkey,
+            question: event.content.clone(),
+            options: Vec::new(),
+            poll_type: PollType::SingleChoice,
+            relays: Vec::new(),
+            ends_at: None,
+        };
+        for tag in &event.tags {
+            match tag.tagname() {
+                "option" => {
+                    let id = tag.value();
+                    if !id.is_empty() && !poll.options.iter().any(|o| o.id == id) {
+                        poll.options.push(PollOption {
+                            id: id.to_owned(),
+                            label: tag.get_index(2).to_owned(),
+                        });
+                    }
+                }
+                "relay" => {
+                    if let Ok(url) = crate::relay::parse_relay_url(tag.value()) {
+                        if !poll.relays.contains(&url) {
+                            poll.relays.push(url);
+                        }
+                    }
+                }
+                "polltype" if tag.value() == "multiplechoice" => {
+                    poll.poll_type = PollType::MultipleChoice;
+                }
+                "endsAt" => {
+                    if let Ok(secs) = tag.value().parse::<i64>() {
+                        poll.ends_at = Some(Unixtime(secs));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if poll.options.is_empty() {
+            return None;
+        }
+
+        Some(poll)
+    }
+
+    /// Whether voting has ended
+    pub fn has_ended(&self) -> bool {
+        matches!(self.ends_at, Some(ends_at) if Unixtime::now() > ends_at)
+    }
+
+    /// Check that `choices` is a vote that can be cast right now
+    pub(crate) fn check_vote(&self, choices: &[String]) -> Result<(), Error> {
+        if self.has_ended() {
+            return Err(ErrorKind::General("This poll has ended.".to_owned()).into());
+        }
+        if choices.is_empty() {
+            return Err(ErrorKind::General("No option was chosen.".to_owned()).into());
+        }
+        if self.poll_type == PollType::SingleChoice && choices.len() > 1 {
+            return Err(ErrorKind::General("Only one option may be chosen.".to_owned()).into());
+        }
+        for choice in choices {
+            if !self.options.iter().any(|o| &o.id == choice) {
+                return Err(ErrorKind::General(format!("{} is not an option.", choice)).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// The options picked in `response` that count. A single choice poll only
+    /// counts the first one.
+    fn choices_in(&self, response: &Event) -> Vec<String> {
+        let mut choices: Vec<String> = Vec::new();
+        for tag in &response.tags {
+            if tag.tagname() != "response" {
+                continue;
+            }
+            let choice = tag.value();
+            if self.options.iter().any(|o| o.id == choice) && !choices.iter().any(|c| c == choice) {
+                choices.push(choice.to_owned());
+                if self.poll_type == PollType::SingleChoice {
+                    break;
+                }
+            }
+        }
+        choices
+    }
+
+    /// Whether `event` is a response to this poll that was made in time
+    pub(crate) fn counts(&self, event: &Event) -> bool {
+        is_poll_response(event.kind)
+            && event
+                .tags
+                .iter()
+                .any(|t| matches!(t.parse(), Ok(ParsedTag::Event { id, .. }) if id == self.id))
+            && !matches!(self.ends_at, Some(ends_at) if event.created_at > ends_at)
+    }
+}
+
+/// The current tallies of a poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollResults {
+    pub poll: Poll,
+
+    /// Each option with how many people picked it, in the order of the poll
+    pub tallies: Vec<(PollOption, usize)>,
+
+    /// How many people voted
+    pub voters: usize,
+
+    /// The options we picked, if we voted
+    pub our_choices: Option<Vec<String>>,
+}
+
+impl PollResults {
+    /// Tally `responses` to `poll`. Only the latest response of each person counts.
+    pub(crate) fn tally(poll: Poll, responses: &[Event], us: Option<PublicKey>) -> PollResults {
+        let mut latest: HashMap<PublicKey, &Event> = HashMap::new();
+        for response in responses.iter().filter(|r| poll.counts(r)) {
+            match latest.get(&response.pubkey) {
+                Some(existing) if existing.created_at >= response.created_at => {}
+                _ => {
+                    latest.insert(response.pubkey, response);
+                }
+            }
+        }
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut voters: usize = 0;
+        let mut our_choices: Option<Vec<String>> = None;
+        for (pubkey, response) in latest.iter() {
+            let choices = poll.choices_in(response);
+            if choices.is_empty() {
+                continue;
+            }
+            voters += 1;
+            for choice in &choices {
+                *counts.entry(choice.clone()).or_insert(0) += 1;
+            }
+            if Some(*pubkey) == us {
+                our_choices = Some(choices);
+            }
+        }
+
+        let tallies = poll
+            .options
+            .iter()
+            .map(|o| (o.clone(), counts.get(&o.id).copied().unwrap_or(0)))
+            .collect();
+
+        PollResults {
+            poll,
+            tallies,
+            voters,
+            our_choices,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nostr_types::{KeySigner, PreEvent, Signer, Tag};
+
+    fn signed(signer: &KeySigner, kind: u32, created_at: i64, tags: Vec<Tag>) -> Event {
+        let pre_event = PreEvent {
+            pubkey: signer.public_key(),
+            created_at: Unixtime(created_at),
+            kind: kind.into(),
+            tags,
+            content: "Which?".to_owned(),
+        };
+        signer.sign_event(pre_event).unwrap()
+    }
+
+    fn poll_event(signer: &KeySigner, extra: Vec<Tag>) -> Event {
+        let mut tags = vec![
+            Tag::new(&["option", "a", "Apples"]),
+            Tag::new(&["option", "b", "Bananas"]),
+            Tag::new(&["option", "a", "Apples again"]),
+            Tag::new(&["option", "", "Nothing"]),
+        ];
+        tags.extend(extra);
+        signed(signer, POLL_KIND, 1700000000, tags)
+    }
+
+    fn response(signer: &KeySigner, poll: Id, created_at: i64, choices: &[&str]) -> Event {
+        let mut tags = vec![ParsedTag::Event {
+            id: poll,
+            recommended_relay_url: None,
+            marker: None,
+            author_pubkey: None,
+        }
+        .into_tag()];
+        for choice in choices {
+            tags.push(Tag::new(&["response", choice]));
+        }
+        signed(signer, POLL_RESPONSE_KIND, created_at, tags)
+    }
+
+    #[test]
+    fn test_poll_from_event() {
+        let alice = KeySigner::generate("", 1).unwrap();
+
+        let poll = Poll::from_event(&poll_event(&alice, vec![])).unwrap();
+        assert_eq!(poll.question, "Which?");
+        assert_eq!(poll.poll_type, PollType::SingleChoice);
+        assert_eq!(poll.ends_at, None);
+        // Duplicate and empty options are dropped
+        let ids: Vec<&str> = poll.options.iter().map(|o| o.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+
+        let poll = Poll::from_event(&poll_event(
+            &alice,
+            vec![
+                Tag::new(&["polltype", "multiplechoice"]),
+                Tag::new(&["endsAt", "1700001000"]),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(poll.poll_type, PollType::MultipleChoice);
+        assert_eq!(poll.ends_at, Some(Unixtime(1700001000)));
+        assert!(poll.has_ended());
+
+        // A poll needs options
+        let event = signed(&alice, POLL_KIND, 1700000000, vec![]);
+        assert!(Poll::from_event(&event).is_none());
+    }
+
+    #[test]
+    fn test_check_vote() {
+        let alice = KeySigner::generate("", 1).unwrap();
+        let poll = Poll::from_event(&poll_event(&alice, vec![])).unwrap();
+
+        assert!(poll.check_vote(&["a".to_owned()]).is_ok());
+        assert!(poll.check_vote(&[]).is_err());
+        assert!(poll.check_vote(&["c".to_owned()]).is_err());
+        // Only one choice in a single choice poll
+        assert!(poll.check_vote(&["a".to_owned(), "b".to_owned()]).is_err());
+
+        let poll = Poll::from_event(&poll_event(
+            &alice,
+            vec![Tag::new(&["polltype", "multiplechoice"])],
+        ))
+        .unwrap();
+        assert!(poll.check_vote(&["a".to_owned(), "b".to_owned()]).is_ok());
+
+        // No votes after the end
+        let poll = Poll::from_event(&poll_event(&alice, vec![Tag::new(&["endsAt", "1"])])).unwrap();
+        assert!(poll.check_vote(&["a".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn test_tally() {
+        let alice = KeySigner::generate("", 1).unwrap();
+        let bob = KeySigner::generate("", 1).unwrap();
+        let carol = KeySigner::generate("", 1).unwrap();
+        let dave = KeySigner::generate("", 1).unwrap();
+
+        let event = poll_event(&alice, vec![Tag::new(&["endsAt", "1700001000"])]);
+        let poll = Poll::from_event(&event).unwrap();
+
+        let responses = vec![
+            // Bob changes his mind, only the latest counts
+            response(&bob, poll.id, 1700000100, &["a"]),
+            response(&bob, poll.id, 1700000200, &["b"]),
+            // Only the first choice counts in a single choice poll
+            response(&carol, poll.id, 1700000100, &["a", "b"]),
+            // Too late
+            response(&dave, poll.id, 1700002000, &["a"]),
+            // For some other poll
+            response(&dave, Id([7; 32]), 1700000100, &["a"]),
+        ];
+
+        let results = PollResults::tally(poll, &responses, Some(bob.public_key()));
+        assert_eq!(results.voters, 2);
+        let tallies: Vec<(&str, usize)> = results
+            .tallies
+            .iter()
+            .map(|(o, n)| (o.id.as_str(), *n))
+            .collect();
+        assert_eq!(tallies, vec![("a", 1), ("b", 1)]);
+        assert_eq!(results.our_choices, Some(vec!["b".to_owned()]));
+    }
+}
@@ -9,7 +9,8 @@ use nostr_types::{
     NAddr, NostrBech32, ParsedTag, PreEvent, PublicKey, RelayUrl, ShatteredContent, Tag,
     UncheckedUrl, Unixtime, Url,
 };
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{mpsc, Mutex};
 
 pub async fn prepare_post_normal(
     author: PublicKey,
@@ -17,6 +18,7 @@ pub async fn prepare_post_normal(
     mut tags: Vec<Tag>,
     in_reply_to: Option<Event>,
     annotation: bool,
+    pow_job: u64,
 ) -> Result<Vec<(Event, Vec<RelayUrl>)>, Error> {
     add_gossip_tag(&mut tags);
 
@@ -28,6 +30,8 @@ pub async fn prepare_post_normal(
         add_thread_based_tags(author, &mut tags, parent)?;
     }
 
+    let content = strip_url_tracking_if_set(content);
+
     add_tags_mirroring_content(&content, &mut tags, false).await;
 
     let pre_event = PreEvent {
@@ -38,20 +42,7 @@ pub async fn prepare_post_normal(
         content,
     };
 
-    let event = {
-        let powint = GLOBALS.db().read_setting_pow();
-        if powint > 0 {
-            let (work_sender, work_receiver) = mpsc::channel();
-            std::thread::spawn(move || {
-                work_logger(work_receiver, powint);
-            });
-            GLOBALS
-                .identity
-                .sign_event_with_pow(pre_event, powint, Some(work_sender))?
-        } else {
-            GLOBALS.identity.sign_event(pre_event)?
-        }
-    };
+    let event = sign_event_for_job(pre_event, pow_job).await?;
 
     let relays = relay::relays_to_post_to(&event)?;
 
@@ -64,6 +55,7 @@ pub async fn prepare_post_comment(
     mut tags: Vec<Tag>,
     parent: Event,
     annotation: bool,
+    pow_job: u64,
 ) -> Result<Vec<(Event, Vec<RelayUrl>)>, Error> {
     add_gossip_tag(&mut tags);
 
@@ -77,6 +69,8 @@ pub async fn prepare_post_comment(
 
     add_parent_tags(&mut tags, &parent, author);
 
+    let content = strip_url_tracking_if_set(content);
+
     add_tags_mirroring_content(&content, &mut tags, false).await;
 
     let pre_event = PreEvent {
@@ -87,20 +81,7 @@ pub async fn prepare_post_comment(
         content,
     };
 
-    let event = {
-        let powint = GLOBALS.db().read_setting_pow();
-        if powint > 0 {
-            let (work_sender, work_receiver) = mpsc::channel();
-            std::thread::spawn(move || {
-                work_logger(work_receiver, powint);
-            });
-            GLOBALS
-                .identity
-                .sign_event_with_pow(pre_event, powint, Some(work_sender))?
-        } else {
-            GLOBALS.identity.sign_event(pre_event)?
-        }
-    };
+    let event = sign_event_for_job(pre_event, pow_job).await?;
 
     let relays = relay::relays_to_post_to(&event)?;
 
@@ -173,6 +154,8 @@ pub async fn prepare_post_nip17(
 
     add_gossip_tag(&mut tags);
 
+    let content = strip_url_tracking_if_set(content);
+
     add_tags_mirroring_content(&content, &mut tags, true).await;
 
     // All recipients get 'p' tagged on the DM rumor
@@ -199,20 +182,83 @@ pub async fn prepare_post_nip17(
     // To all recipients
     for pk in dm_channel.keys() {
         let event = GLOBALS.identity.giftwrap(pre_event.clone(), *pk)?;
-        let relays = relay::get_dm_relays(*pk)?;
+        let relays = relay::get_dm_inboxes(*pk)?;
         output.push((event, relays));
     }
 
     // And a copy to us
     {
         let event = GLOBALS.identity.giftwrap(pre_event.clone(), our_pk)?;
-        let relays = Relay::choose_relay_urls(Relay::DM, |_| true)?;
+        let mut relays = Relay::choose_relay_urls(Relay::DM, |_| true)?;
+        if relays.is_empty() {
+            relays = Relay::choose_relay_urls(Relay::INBOX, |_| true)?;
+        }
         output.push((event, relays));
     }
 
     Ok(output)
 }
 
+/// The address of a replaceable or addressable event (`kind:pubkey:d`), with the
+/// relay hint if there is one. None if the event is not replaceable.
+pub(crate) fn event_address(event: &Event, relay_hint: Option<UncheckedUrl>) -> Option<NAddr> {
+    if !event.kind.is_replaceable() {
+        return None;
+    }
+    Some(NAddr {
+        d: event.parameter().unwrap_or_default(),
+        relays: relay_hint.into_iter().collect(),
+        kind: event.kind,
+        author: event.pubkey,
+    })
+}
+
+/// The kind and tags of a repost of `reposted`.
+///
+/// Text notes get a kind-6 repost, anything else a kind-16 generic repost with a 'k'
+/// tag. Replaceable and addressable events are also referenced by an 'a' tag, since
+/// the 'e' tag only points at one version of them.
+pub(crate) fn repost_tags(
+    reposted: &Event,
+    relay_hint: Option<UncheckedUrl>,
+) -> (EventKind, Vec<Tag>) {
+    let mut tags: Vec<Tag> = vec![
+        ParsedTag::Pubkey {
+            pubkey: reposted.pubkey,
+            recommended_relay_url: None,
+            petname: None,
+        }
+        .into_tag(),
+        ParsedTag::Event {
+            id: reposted.id,
+            recommended_relay_url: relay_hint.clone(),
+            marker: None,
+            author_pubkey: Some(reposted.pubkey),
+        }
+        .into_tag(),
+    ];
+
+    if reposted.kind == EventKind::TextNote {
+        return (EventKind::Repost, tags);
+    }
+
+    // Add 'k' tag
+    tags.push(ParsedTag::Kind(reposted.kind).into_tag());
+
+    // Add 'a' tag
+    if let Some(address) = event_address(reposted, relay_hint) {
+        tags.push(
+            ParsedTag::Address {
+                address,
+                marker: None,
+            }
+            .into_tag(),
+        );
+    }
+
+    (EventKind::GenericRepost, tags)
+}
+
 fn add_gossip_tag(tags: &mut Vec<Tag>) {
     if GLOBALS.db().read_setting_set_client_tag() {
         tags.push(Tag::new(&["client", "gossip"]));
@@ -229,23 +275,30 @@ async fn add_tags_mirroring_content(content: &str, tags: &mut Vec<Tag>, direct_m
                         // add nothing
                     }
                     NostrBech32::NAddr(ea) => {
-                        // https://github.com/nostr-protocol/nips/pull/1560 may allow us to use 'q'
-                        // in the future
-                        nostr_types::add_addr_to_tags(tags, ea, Some("mention".to_string()));
+                        let address = format!(
+                            "{}:{}:{}",
+                            u32::from(ea.kind),
+                            ea.author.as_hex_string(),
+                            ea.d
+                        );
+                        add_quote_to_tags(tags, &address, ea.relays.first().cloned(), None);
                     }
                     NostrBech32::NEvent(ne) => {
-                        // NIP-10: "Those marked with "mention" denote a quoted or reposted event id."
-                        add_event_to_tags(
+                        let author = ne.author.or_else(|| author_of(ne.id));
+                        add_quote_to_tags(
                             tags,
-                            ne.id,
-                            ne.relays.first().cloned(),
-                            ne.author,
-                            "mention", // this will use 'q', see the function
+                            &ne.id.as_hex_string(),
+                            ne.relays.first().cloned().or_else(|| relay_hint_for(ne.id)),
+                            author,
                         );
                     }
                     NostrBech32::Id(id) => {
-                        // NIP-10: "Those marked with "mention" denote a quoted or reposted event id."
-                        add_event_to_tags(tags, *id, None, None, "mention"); // this will use 'q', see the function
+                        add_quote_to_tags(
+                            tags,
+                            &id.as_hex_string(),
+                            relay_hint_for(*id),
+                            author_of(*id),
+                        );
                     }
                     NostrBech32::Profile(prof) => {
                         if !direct_message {
@@ -287,6 +340,106 @@ async fn add_tags_mirroring_content(content: &str, tags: &mut Vec<Tag>, direct_m
     // content = NostrUrl::urlize(&content);
 }
 
+/// Query parameters that only serve to track where a link was shared. This is
+/// kept short on purpose: a parameter a site actually needs must never be removed.
+const TRACKING_PARAMS: [&str; 15] = [
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_enc_id",
+];
+
+fn is_tracking_param(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name)
+}
+
+fn strip_url_tracking_if_set(content: String) -> String {
+    if GLOBALS.db().read_setting_strip_url_tracking() {
+        strip_url_tracking(&content)
+    } else {
+        content
+    }
+}
+
+/// Remove tracking parameters from the http(s) URLs in `content`, leaving
+/// anything inside `inline code` or ```code blocks``` alone
+fn strip_url_tracking(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+    while !rest.is_empty() {
+        // Copy code verbatim
+        if rest.starts_with('`') {
+            let fence = if rest.starts_with("```") { "```" } else { "`" };
+            if let Some(end) = rest[fence.len()..].find(fence) {
+                let end = fence.len() + end + fence.len();
+                output.push_str(&rest[..end]);
+                rest = &rest[end..];
+                continue;
+            }
+        }
+
+        // Strip URLs up to the next whitespace
+        if rest.starts_with("https://") || rest.starts_with("http://") {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '`')
+                .unwrap_or(rest.len());
+            output.push_str(&strip_tracking_params(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+
+        let mut chars = rest.chars();
+        if let Some(c) = chars.next() {
+            output.push(c);
+        }
+        rest = chars.as_str();
+    }
+    output
+}
+
+/// Remove tracking parameters from the query of a single URL. Nothing else
+/// about the URL is changed, including any punctuation right after it.
+fn strip_tracking_params(url: &str) -> String {
+    let url_end = url
+        .trim_end_matches(|c: char| ".,;:!?)]}'\"".contains(c))
+        .len();
+    let (url, trailing) = url.split_at(url_end);
+
+    let (url, fragment) = match url.find('#') {
+        Some(i) => url.split_at(i),
+        None => (url, ""),
+    };
+    let (base, query) = match url.find('?') {
+        Some(i) => (&url[..i], &url[i + 1..]),
+        None => return format!("{url}{fragment}{trailing}"),
+    };
+
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or("");
+            !is_tracking_param(name)
+        })
+        .collect();
+
+    if kept.is_empty() {
+        format!("{base}{fragment}{trailing}")
+    } else {
+        format!("{base}?{}{fragment}{trailing}", kept.join("&"))
+    }
+}
+
 async fn add_imeta_tag(urlstr: &str, mimetype: &str, tags: &mut Vec<Tag>) {
     //turn into a nostr_types::Url
     let url = match Url::try_from_str(urlstr) {
@@ -352,20 +505,10 @@ fn add_thread_based_tags(
     tags: &mut Vec<Tag>,
     parent: &Event,
 ) -> Result<(), Error> {
-    // Add a 'p' tag for the author we are replying to (except if it is our own key)
-    if parent.pubkey != author {
-        nostr_types::add_pubkey_to_tags(tags, parent.pubkey, None);
-    }
-
-    // Add all the 'p' tags from the note we are replying to (except our own)
+    // Add 'p' tags for the author we are replying to and the people tagged in the
+    // note we are replying to (except our own key)
     // FIXME: Should we avoid taging people who are muted?
-    for tag in &parent.tags {
-        if let Ok(ParsedTag::Pubkey { pubkey, .. }) = tag.parse() {
-            if pubkey != author {
-                nostr_types::add_pubkey_to_tags(tags, pubkey, None);
-            }
-        }
-    }
+    copy_parent_pubkeys(tags, parent, author);
 
     let parent_relay: Option<UncheckedUrl> = GLOBALS
         .db()
@@ -421,23 +564,13 @@ fn add_thread_based_tags(
     add_event_to_tags(
         tags,
         parent.id,
-        parent_relay,
+        parent_relay.clone(),
         Some(parent.pubkey),
         reply_marker,
     );
-    if parent.kind.is_replaceable() {
-        // Add an 'a' tag for the note we are replying to
-        let d = parent.parameter().unwrap_or("".to_owned());
-        nostr_types::add_addr_to_tags(
-            tags,
-            &NAddr {
-                d,
-                relays: vec![],
-                kind: parent.kind,
-                author: parent.pubkey,
-            },
-            Some(reply_marker.to_string()),
-        );
+    // Add an 'a' tag for the note we are replying to
+    if let Some(address) = event_address(parent, parent_relay) {
+        nostr_types::add_addr_to_tags(tags, &address, Some(reply_marker.to_string()));
     }
 
     // Possibly propagate a subject tag
@@ -457,6 +590,45 @@ fn add_thread_based_tags(
     Ok(())
 }
 
+// Quotes of events and addresses in the content are 'q' tagged (NIP-18), not 'e'
+// tagged as mentions (NIP-10):
+//
+//   ["q", <event id or kind:pubkey:d>, <relay hint>, <pubkey, for event ids>]
+//
+// Quoting the event being replied to adds a 'q' tag next to its 'e' reply tag.
+fn add_quote_to_tags(
+    tags: &mut Vec<Tag>,
+    quoted: &str,
+    relay_url: Option<UncheckedUrl>,
+    opt_pubkey: Option<PublicKey>,
+) {
+    if tags
+        .iter()
+        .any(|t| t.tagname() == "q" && t.value() == quoted)
+    {
+        return;
+    }
+
+    let relay_url = relay_url.map(|u| u.as_str().to_owned()).unwrap_or_default();
+    let pubkey = opt_pubkey.map(|pk| pk.as_hex_string());
+    let mut fields: Vec<&str> = vec!["q", quoted, &relay_url];
+    if let Some(pubkey) = &pubkey {
+        fields.push(pubkey);
+    }
+    tags.push(Tag::new(&fields));
+}
+
+fn relay_hint_for(id: Id) -> Option<UncheckedUrl> {
+    relay::recommended_relay_hint(id)
+        .ok()
+        .flatten()
+        .map(|rr| rr.to_unchecked_url())
+}
+
+fn author_of(id: Id) -> Option<PublicKey> {
+    GLOBALS.db().read_event(id).ok().flatten().map(|e| e.pubkey)
+}
+
 fn add_event_to_tags(
     existing_tags: &mut Vec<Tag>,
     added: Id,
@@ -476,6 +648,131 @@ fn add_event_to_tags(
     nostr_types::add_event_to_tags(existing_tags, added, relay_url, marker, opt_pubkey, true)
 }
 
+/// Sign an event we created, first mining proof of work for it if the pow setting
+/// asks for that.
+pub(crate) async fn sign_event(pre_event: PreEvent) -> Result<Event, Error> {
+    sign_event_for_job(pre_event, rand::random::<u64>()).await
+}
+
+/// Like [sign_event], with `pow_job` as the token that cancels mining for it.
+pub(crate) async fn sign_event_for_job(pre_event: PreEvent, pow_job: u64) -> Result<Event, Error> {
+    mine_pow(pre_event, GLOBALS.db().read_setting_pow(), pow_job).await
+}
+
+/// Sign an event after mining `powint` bits of proof of work for it, replacing
+/// any nonce it already has. Mining runs on blocking threads so it doesn't hold up
+/// the async runtime, reports progress through the status queue, and fails with
+/// `ErrorKind::PowCancelled` if `pow_job` is cancelled with
+/// [pow_cancel](crate::Overlord::pow_cancel).
+pub(crate) async fn mine_pow(
+    mut pre_event: PreEvent,
+    powint: u8,
+    pow_job: u64,
+) -> Result<Event, Error> {
+    pre_event.tags.retain(|t| t.tagname() != "nonce");
+
+    if powint == 0 {
+        return GLOBALS.identity.sign_event(pre_event);
+    }
+
+    let cancel = GLOBALS.pow_jobs.entry(pow_job).or_default().clone();
+    let result = tokio::task::spawn_blocking(move || mine(pre_event, powint, &cancel)).await;
+    GLOBALS.pow_jobs.remove(&pow_job);
+
+    let pre_event = match result?? {
+        Some(pre_event) => pre_event,
+        None => {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("Proof of work cancelled, not posting.".to_owned());
+            return Err(ErrorKind::PowCancelled.into());
+        }
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}
+
+// Add a nonce tag (NIP-13) to `pre_event` so that its id has at least `powint`
+// leading zero bits, using all cores. Returns None if `cancel` gets set.
+fn mine(pre_event: PreEvent, powint: u8, cancel: &AtomicBool) -> Result<Option<PreEvent>, Error> {
+    // How many attempts between checks for cancellation
+    const CHECK_EVERY: u64 = 8192;
+
+    let (work_sender, work_receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        work_logger(work_receiver, powint);
+    });
+
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get() as u64)
+        .unwrap_or(1);
+    let done = AtomicBool::new(false);
+    let best = AtomicU8::new(0);
+    let found: Mutex<Option<PreEvent>> = Mutex::new(None);
+    let failed: Mutex<Option<nostr_types::Error>> = Mutex::new(None);
+    let target = powint.to_string();
+
+    std::thread::scope(|scope| {
+        for first_nonce in 0..threads {
+            let work_sender = work_sender.clone();
+            let (done, best, found, failed, target) = (&done, &best, &found, &failed, &target);
+            let mut attempt = pre_event.clone();
+            scope.spawn(move || {
+                attempt.tags.push(Tag::new(&["nonce", "0", target]));
+                let last = attempt.tags.len() - 1;
+                let mut nonce = first_nonce;
+                let mut count: u64 = 0;
+                while !done.load(Ordering::Relaxed) {
+                    attempt.tags[last] = Tag::new(&["nonce", &nonce.to_string(), target]);
+                    let id = match attempt.hash() {
+                        Ok(id) => id,
+                        Err(e) => {
+                            done.store(true, Ordering::Relaxed);
+                            *failed.lock().unwrap() = Some(e);
+                            return;
+                        }
+                    };
+
+                    let work = leading_zero_bits(&id.0);
+                    if work > best.fetch_max(work, Ordering::Relaxed) {
+                        let _ = work_sender.send(work);
+                    }
+                    if work >= powint {
+                        done.store(true, Ordering::Relaxed);
+                        *found.lock().unwrap() = Some(attempt);
+                        return;
+                    }
+
+                    nonce += threads;
+                    count += 1;
+                    if count % CHECK_EVERY == 0 && cancel.load(Ordering::Relaxed) {
+                        done.store(true, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(e) = failed.into_inner().unwrap() {
+        return Err(e.into());
+    }
+    Ok(found.into_inner().unwrap())
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u8 {
+    let mut bits: u8 = 0;
+    for byte in bytes {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros() as u8;
+            break;
+        }
+    }
+    bits
+}
+
 fn work_logger(work_receiver: mpsc::Receiver<u8>, powint: u8) {
     while let Ok(work) = work_receiver.recv() {
         if work >= powint {
@@ -483,7 +780,7 @@ fn work_logger(work_receiver: mpsc::Receiver<u8>, powint: u8) {
             GLOBALS
                 .status_queue
                 .write()
-                .write(format!("Message sent with {powint} bits of work computed."));
+                .write(format!("{powint} bits of work computed."));
             break;
         } else {
             GLOBALS
@@ -523,18 +820,10 @@ fn add_parent_tags(tags: &mut Vec<Tag>, parent: &Event, author: PublicKey) {
     tags.push(ParsedTag::Kind(parent.kind).into_tag());
 
     // a
-    if parent.kind.is_replaceable() {
+    if let Some(address) = event_address(parent, relay_hint.clone()) {
         tags.push(
             ParsedTag::Address {
-                address: NAddr {
-                    d: parent.parameter().unwrap_or_default(),
-                    relays: match relay_hint {
-                        Some(ref h) => vec![h.clone()],
-                        None => vec![],
-                    },
-                    kind: parent.kind,
-                    author: parent.pubkey,
-                },
+                address,
                 marker: None,
             }
             .into_tag(),
@@ -562,15 +851,9 @@ fn add_parent_tags(tags: &mut Vec<Tag>, parent: &Event, author: PublicKey) {
         .into_tag(),
     );
 
-    // Copy all 'p' tags from parent except our own pubkey
+    // Copy the 'p' tags from parent except our own pubkey
     // FIXME: Should we avoid taging people who are muted?
-    for tag in &parent.tags {
-        if let Ok(ParsedTag::Pubkey { pubkey, .. }) = tag.parse() {
-            if pubkey != author {
-                nostr_types::add_pubkey_to_tags(tags, pubkey, None);
-            }
-        }
-    }
+    copy_parent_pubkeys(tags, parent, author);
 
     // Possibly propagate a subject tag
     for tag in &parent.tags {
@@ -585,6 +868,72 @@ fn add_parent_tags(tags: &mut Vec<Tag>, parent: &Event, author: PublicKey) {
     }
 }
 
+// Tag the author of the parent and copy the 'p' tags of the parent (except
+// `author`) into a reply, so that it has at most `max_reply_p_tags` 'p' tags.
+fn copy_parent_pubkeys(tags: &mut Vec<Tag>, parent: &Event, author: PublicKey) {
+    let max = GLOBALS.db().read_setting_max_reply_p_tags();
+    copy_parent_pubkeys_up_to(tags, parent, author, max);
+}
+
+// Every 'p' tag counts toward `max`, including those already in `tags`. The authors
+// of the parent and of the root come first. Past the limit, the people tagged first
+// are dropped, as most clients tag participants in the order they joined the thread.
+fn copy_parent_pubkeys_up_to(tags: &mut Vec<Tag>, parent: &Event, author: PublicKey, max: usize) {
+    let pubkeys_in = |tags: &[Tag]| -> Vec<PublicKey> {
+        let mut pubkeys: Vec<PublicKey> = Vec::new();
+        for tag in tags {
+            if let Ok(ParsedTag::Pubkey { pubkey, .. }) = tag.parse() {
+                if pubkey != author && !pubkeys.contains(&pubkey) {
+                    pubkeys.push(pubkey);
+                }
+            }
+        }
+        pubkeys
+    };
+
+    let present = pubkeys_in(tags);
+    let mut room = max.saturating_sub(present.len());
+
+    let mut first: Vec<PublicKey> = vec![parent.pubkey];
+    if let Some(root_author) = root_author(parent) {
+        first.push(root_author);
+    }
+    let tagged = pubkeys_in(&parent.tags);
+
+    let mut chosen: Vec<PublicKey> = Vec::new();
+    for pubkey in first.iter().chain(tagged.iter().rev()) {
+        if room == 0 {
+            break;
+        }
+        if *pubkey != author && !present.contains(pubkey) && !chosen.contains(pubkey) {
+            chosen.push(*pubkey);
+            room -= 1;
+        }
+    }
+
+    for pubkey in first.iter().chain(tagged.iter()) {
+        if chosen.contains(pubkey) {
+            nostr_types::add_pubkey_to_tags(tags, *pubkey, None);
+        }
+    }
+}
+
+// The author of the root of the thread `event` is in
+fn root_author(event: &Event) -> Option<PublicKey> {
+    for tag in &event.tags {
+        if let Ok(ParsedTag::RootPubkey { pubkey, .. }) = tag.parse() {
+            return Some(pubkey);
+        }
+    }
+
+    match event.replies_to_root() {
+        Some(EventReference::Id { author, .. }) => author,
+        Some(EventReference::Addr(ea)) => Some(ea.author),
+        None if event.replies_to().is_none() => Some(event.pubkey),
+        None => None,
+    }
+}
+
 fn set_parent_as_root_tags(tags: &mut Vec<Tag>, parent: &Event) {
     let relay_hint = relay::recommended_relay_hint(parent.id)
         .ok()
@@ -595,18 +944,10 @@ fn set_parent_as_root_tags(tags: &mut Vec<Tag>, parent: &Event) {
     tags.push(ParsedTag::RootKind(parent.kind).into_tag());
 
     // a
-    if parent.kind.is_replaceable() {
+    if let Some(address) = event_address(parent, relay_hint.clone()) {
         tags.push(
             ParsedTag::RootAddress {
-                address: NAddr {
-                    d: parent.parameter().unwrap_or_default(),
-                    relays: match relay_hint {
-                        Some(ref h) => vec![h.clone()],
-                        None => vec![],
-                    },
-                    kind: parent.kind,
-                    author: parent.pubkey,
-                },
+                address,
                 marker: None,
             }
             .into_tag(),
@@ -634,3 +975,192 @@ fn set_parent_as_root_tags(tags: &mut Vec<Tag>, parent: &Event) {
         .into_tag(),
     );
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::signed_event;
+    use nostr_types::{KeySigner, PrivateKey, Signer};
+
+    fn long_form_article(signer: &KeySigner) -> Event {
+        let tags = vec![
+            Tag::new(&["d", "my-article"]),
+            Tag::new(&["title", "My Article"]),
+        ];
+        signed_event(
+            signer,
+            1700000000,
+            EventKind::LongFormContent,
+            tags,
+            "hello",
+        )
+    }
+
+    #[test]
+    fn test_repost_long_form_article() {
+        let signer = KeySigner::generate("", 1).unwrap();
+        let author = signer.public_key();
+        let article = long_form_article(&signer);
+        let hint = UncheckedUrl::from_str("wss://relay.example/");
+
+        let (kind, tags) = repost_tags(&article, Some(hint.clone()));
+        assert_eq!(kind, EventKind::GenericRepost);
+
+        let a_tag = tags.iter().find(|t| t.tagname() == "a").unwrap();
+        assert_eq!(
+            a_tag.value(),
+            format!("30023:{}:my-article", author.as_hex_string())
+        );
+        match a_tag.parse().unwrap() {
+            ParsedTag::Address { address, .. } => {
+                assert_eq!(address.kind, EventKind::LongFormContent);
+                assert_eq!(address.author, author);
+                assert_eq!(address.d, "my-article");
+                assert_eq!(address.relays, vec![hint]);
+            }
+            _ => panic!("'a' tag did not parse as an address"),
+        }
+
+        // A text note is not addressable
+        let mut note = article.clone();
+        note.kind = EventKind::TextNote;
+        assert!(event_address(&note, None).is_none());
+        let (kind, tags) = repost_tags(&note, None);
+        assert_eq!(kind, EventKind::Repost);
+        assert!(tags.iter().all(|t| t.tagname() != "a"));
+    }
+
+    #[test]
+    fn test_mine() {
+        assert_eq!(leading_zero_bits(&[0, 0, 0x1f, 0xff]), 19);
+        assert_eq!(leading_zero_bits(&[0x80]), 0);
+
+        let pre_event = PreEvent {
+            pubkey: PrivateKey::generate().public_key(),
+            created_at: Unixtime(1700000000),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "hello".to_owned(),
+        };
+        let mined = mine(pre_event, 8, &AtomicBool::new(false))
+            .unwrap()
+            .unwrap();
+        assert!(leading_zero_bits(&mined.hash().unwrap().0) >= 8);
+        let nonce = mined.tags.iter().find(|t| t.tagname() == "nonce").unwrap();
+        assert_eq!(nonce.get_index(2), "8");
+    }
+
+    #[test]
+    fn test_quote_tags_alongside_reply_tags() {
+        let author = PrivateKey::generate().public_key();
+        let replied = "77f7653c67147a125cc624f695029d0557e3ab402e714680eb23dd2499f439a0";
+        let quoted = "ee11a5dff40c19a555f41fe42b48f00e618c91225622ae37b6c2bb67b76c4e49";
+        let hint = UncheckedUrl::from_str("wss://relay.example/");
+
+        let reply_tag = Tag::new(&["e", replied, "", "reply"]);
+        let mut tags = vec![reply_tag.clone()];
+
+        add_quote_to_tags(&mut tags, quoted, Some(hint), Some(author));
+        add_quote_to_tags(&mut tags, quoted, None, None); // quoted twice
+        add_quote_to_tags(&mut tags, replied, None, None); // quoting the parent too
+
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags[0], reply_tag);
+        assert_eq!(
+            tags[1],
+            Tag::new(&["q", quoted, "wss://relay.example/", &author.as_hex_string()])
+        );
+        assert_eq!(tags[2], Tag::new(&["q", replied, ""]));
+    }
+
+    #[test]
+    fn test_copy_parent_pubkeys_in_deep_thread() {
+        let me = KeySigner::generate("", 1).unwrap();
+        let root_author = PrivateKey::generate().public_key();
+        let participants: Vec<PublicKey> = (0..10)
+            .map(|_| PrivateKey::generate().public_key())
+            .collect();
+        let parent_signer = KeySigner::generate("", 1).unwrap();
+
+        // The parent and root authors are tagged after everyone else
+        let mut parent_tags: Vec<Tag> = participants
+            .iter()
+            .chain([me.public_key()].iter())
+            .map(|pubkey| Tag::new(&["p", &pubkey.as_hex_string()]))
+            .collect();
+        parent_tags.push(Tag::new(&[
+            "p",
+            &parent_signer.public_key().as_hex_string(),
+        ]));
+        parent_tags.push(
+            ParsedTag::RootPubkey {
+                pubkey: root_author,
+                recommended_relay_url: None,
+                petname: None,
+            }
+            .into_tag(),
+        );
+        parent_tags.push(Tag::new(&["p", &root_author.as_hex_string()]));
+        let parent = signed_event(
+            &parent_signer,
+            1700000000,
+            EventKind::TextNote,
+            parent_tags,
+            "deep in the thread",
+        );
+
+        let p_tags = |tags: &[Tag]| -> Vec<PublicKey> {
+            tags.iter()
+                .filter_map(|tag| match tag.parse() {
+                    Ok(ParsedTag::Pubkey { pubkey, .. }) => Some(pubkey),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let mut tags: Vec<Tag> = Vec::new();
+        copy_parent_pubkeys_up_to(&mut tags, &parent, me.public_key(), 4);
+        assert_eq!(
+            p_tags(&tags),
+            vec![parent.pubkey, root_author, participants[8], participants[9]]
+        );
+
+        // A 'p' tag already there counts too
+        let mut tags = vec![Tag::new(&["p", &participants[0].as_hex_string()])];
+        copy_parent_pubkeys_up_to(&mut tags, &parent, me.public_key(), 4);
+        assert_eq!(
+            p_tags(&tags),
+            vec![participants[0], parent.pubkey, root_author, participants[9]]
+        );
+
+        let mut tags: Vec<Tag> = Vec::new();
+        copy_parent_pubkeys_up_to(&mut tags, &parent, me.public_key(), 1);
+        assert_eq!(p_tags(&tags), vec![parent.pubkey]);
+    }
+
+    #[test]
+    fn test_strip_url_tracking() {
+        assert_eq!(
+            strip_url_tracking(
+                "see https://example.com/a?id=5&utm_source=x&utm_medium=y&page=2#top now"
+            ),
+            "see https://example.com/a?id=5&page=2#top now"
+        );
+        assert_eq!(
+            strip_url_tracking("(https://example.com/?fbclid=abc)."),
+            "(https://example.com/)."
+        );
+        assert_eq!(
+            strip_url_tracking("https://example.com/?si=1&ref=feed"),
+            "https://example.com/?si=1&ref=feed"
+        );
+        assert_eq!(
+            strip_url_tracking("`https://example.com/?gclid=1` https://example.com/?gclid=1"),
+            "`https://example.com/?gclid=1` https://example.com/"
+        );
+        assert_eq!(
+            strip_url_tracking("```\nhttps://example.com/?utm_id=1\n```"),
+            "```\nhttps://example.com/?utm_id=1\n```"
+        );
+    }
+}
@@ -184,14 +184,69 @@ pub fn process_dm_relay_list(event: &Event) -> Result<(), Error> {
     Ok(())
 }
 
+/// The event embedded in the content of a kind 6 repost, if there is a valid one.
+///
+/// The embedded event must be properly signed, must not itself be a repost, and
+/// must be the event the repost's 'e' tags point at (if it has any).
+pub(crate) fn embedded_repost(event: &Event) -> Option<Event> {
+    use nostr_types::{EventKind, EventReference};
+
+    if event.kind != EventKind::Repost || !event.content.trim_start().starts_with('{') {
+        return None;
+    }
+
+    let inner_event = match serde_json::from_str::<Event>(&event.content) {
+        Ok(inner_event) => inner_event,
+        Err(e) => {
+            tracing::debug!(
+                "Repost {} has malformed content: {}",
+                event.id.as_hex_string(),
+                e
+            );
+            return None;
+        }
+    };
+
+    if inner_event.kind == EventKind::Repost {
+        return None;
+    }
+
+    if let Err(e) = inner_event.verify(None) {
+        tracing::debug!(
+            "Repost {} embeds an invalid event: {}",
+            event.id.as_hex_string(),
+            e
+        );
+        return None;
+    }
+
+    let tagged: Vec<_> = event
+        .mentions()
+        .iter()
+        .filter_map(|eref| match eref {
+            EventReference::Id { id, .. } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if !tagged.is_empty() && !tagged.contains(&inner_event.id) {
+        tracing::debug!(
+            "Repost {} embeds an event it does not tag",
+            event.id.as_hex_string()
+        );
+        return None;
+    }
+
+    Some(inner_event)
+}
+
 // EventKind::Repost
-pub fn process_repost(event: &Event, verify: bool) -> Result<(), Error> {
+pub fn process_repost(event: &Event) -> Result<(), Error> {
     use crate::misc::Freshness;
     use crate::people::People;
     use nostr_types::EventReference;
 
-    // If it has a json encoded inner event
-    if let Ok(inner_event) = serde_json::from_str::<Event>(&event.content) {
+    // If it has a valid json encoded inner event
+    if let Some(inner_event) = embedded_repost(event) {
         // Maybe seek the relay list of the event author
         match People::person_needs_relay_list(inner_event.pubkey) {
             Freshness::NeverSought | Freshness::Stale => {
@@ -205,8 +260,9 @@ pub fn process_repost(event: &Event, verify: bool) -> Result<(), Error> {
             _ => {}
         }
 
-        // process the inner event
-        crate::process::process_new_event(&inner_event, None, None, verify, false)?;
+        // process the inner event (already verified). Duplicates, including our
+        // own events reposted back to us, are recognized and skipped there.
+        crate::process::process_new_event(&inner_event, None, None, false, false)?;
 
         // Seek additional info for this event by id and author
         GLOBALS
@@ -298,7 +354,7 @@ pub fn process_somebody_elses_contact_list(event: &Event, force: bool) -> Result
 
         let mut relay_list: RelayList = Default::default();
         for (url, simple_relay_usage) in srl.0.iter() {
-            if let Ok(relay_url) = RelayUrl::try_from_unchecked_url(url) {
+            if let Ok(relay_url) = crate::relay::relay_url_from_unchecked(url) {
                 if simple_relay_usage.read && simple_relay_usage.write {
                     relay_list.0.insert(relay_url, RelayListUsage::Both);
                 } else if simple_relay_usage.read {
@@ -384,6 +440,29 @@ fn update_or_allocate_person_list_from_event(
     Ok((list, metadata))
 }
 
+// EventKind::ProfileBadges
+pub fn process_profile_badges(event: &Event) -> Result<(), Error> {
+    // Their choice of badges may have changed
+    GLOBALS.badges.remove(&event.pubkey);
+
+    // If someone's badges were asked for before we had this, fetch the rest now
+    if event.parameter().as_deref() == Some(crate::badges::PROFILE_BADGES_D)
+        && GLOBALS.badges_wanted.remove(&event.pubkey).is_some()
+    {
+        let _ = GLOBALS
+            .to_overlord
+            .send(ToOverlordMessage::FetchBadges(event.pubkey));
+    }
+    Ok(())
+}
+
+// EventKind::BadgeDefinition, EventKind::BadgeAward
+pub fn process_badge(_event: &Event) -> Result<(), Error> {
+    // We don't track who shows which badge, and these are rare, so start over
+    GLOBALS.badges.clear();
+    Ok(())
+}
+
 // EventKind::RequestToVanish
 pub fn process_request_to_vanish(event: &Event) -> Result<(), Error> {
     // Only honor if a 'relay' tag is set to 'ALL_RELAYS'
@@ -435,3 +514,54 @@ pub fn process_request_to_vanish(event: &Event) -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::signed_event;
+    use nostr_types::{EventKind, Id, KeySigner};
+
+    fn repost_of(signer: &KeySigner, tagged: Id, content: String) -> Event {
+        let tag = ParsedTag::Event {
+            id: tagged,
+            recommended_relay_url: None,
+            marker: None,
+            author_pubkey: None,
+        }
+        .into_tag();
+        signed_event(signer, 1700000000, EventKind::Repost, vec![tag], &content)
+    }
+
+    #[test]
+    fn test_embedded_repost() {
+        let alice = KeySigner::generate("", 1).unwrap();
+        let bob = KeySigner::generate("", 1).unwrap();
+
+        let note = signed_event(&alice, 1700000000, EventKind::TextNote, vec![], "hello");
+        let json = serde_json::to_string(&note).unwrap();
+
+        // A properly signed note that the repost tags
+        let repost = repost_of(&bob, note.id, json.clone());
+        assert_eq!(embedded_repost(&repost), Some(note.clone()));
+
+        // Malformed content
+        let repost = repost_of(&bob, note.id, "{\"id\":".to_owned());
+        assert_eq!(embedded_repost(&repost), None);
+
+        // Content that does not match its signature
+        let mut forged = note.clone();
+        forged.content = "goodbye".to_owned();
+        let repost = repost_of(&bob, note.id, serde_json::to_string(&forged).unwrap());
+        assert_eq!(embedded_repost(&repost), None);
+
+        // A note other than the one the repost tags
+        let other = signed_event(&alice, 1700000000, EventKind::TextNote, vec![], "other");
+        let repost = repost_of(&bob, other.id, json);
+        assert_eq!(embedded_repost(&repost), None);
+
+        // A repost of a repost
+        let inner = repost_of(&alice, note.id, serde_json::to_string(&note).unwrap());
+        let repost = repost_of(&bob, inner.id, serde_json::to_string(&inner).unwrap());
+        assert_eq!(embedded_repost(&repost), None);
+    }
+}
@@ -2,18 +2,95 @@ use crate::bookmarks::BookmarkList;
 use crate::comms::ToOverlordMessage;
 use crate::error::Error;
 use crate::globals::GLOBALS;
-use crate::misc::Private;
+use crate::misc::{FutureEventAction, Private};
 use crate::relationship::{RelationshipByAddr, RelationshipById};
+use crate::relay_metrics;
 use crate::storage::{PersonTable, Table};
 use crate::Relay;
+use dashmap::DashMap;
 use heed::RwTxn;
 use nostr_types::{
-    Event, EventKind, EventReference, Filter, Id, NAddr, NostrBech32, ParsedTag, RelayUrl, Unixtime,
+    Event, EventKind, EventReference, Filter, Id, NostrBech32, ParsedTag, PreEvent, RelayUrl,
+    Signature, Tag, Unixtime,
 };
+use std::borrow::Cow;
 use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::time::Instant;
 
 mod by_kind;
 
+// How many recently verified events are remembered
+const RECENTLY_VERIFIED_SIZE: usize = 512;
+
+// Signatures of events verified recently, by event id. The same event often arrives
+// from several relays at nearly the same time, before the first copy is stored, and
+// those copies need not be verified again. When it fills up it is emptied, which
+// only costs some repeated verifications.
+static RECENTLY_VERIFIED: OnceLock<DashMap<Id, Signature>> = OnceLock::new();
+
+// Only a copy with the same signature whose id still matches its contents counts, so
+// a forged copy with the same id is verified anew. Hashing is much cheaper than
+// checking the signature.
+fn recently_verified(event: &Event) -> bool {
+    let cache = RECENTLY_VERIFIED.get_or_init(DashMap::new);
+    match cache.get(&event.id) {
+        Some(sig) if *sig == event.sig => {}
+        _ => return false,
+    }
+
+    let pre_event = PreEvent {
+        pubkey: event.pubkey,
+        created_at: event.created_at,
+        kind: event.kind,
+        tags: event.tags.clone(),
+        content: event.content.clone(),
+    };
+    matches!(pre_event.hash(), Ok(id) if id == event.id)
+}
+
+fn remember_verified(event: &Event) {
+    let cache = RECENTLY_VERIFIED.get_or_init(DashMap::new);
+    if cache.len() >= RECENTLY_VERIFIED_SIZE {
+        cache.clear();
+    }
+    cache.insert(event.id, event.sig);
+}
+
+/// Whether events of this kind are ephemeral (NIP-01 kinds 20000-29999), meaning
+/// relays are not expected to store them and neither do we.
+pub fn is_ephemeral(kind: EventKind) -> bool {
+    (20000..30000).contains(&u32::from(kind))
+}
+
+// The kinds listed in the `stored_kinds` setting, or None if every kind is stored.
+// The setting is read once, so changes to it take effect on restart.
+static STORED_KINDS: OnceLock<Option<Vec<u32>>> = OnceLock::new();
+
+/// Whether events of this kind are stored, according to the `stored_kinds` setting.
+///
+/// The setting lists kind numbers separated by spaces or commas. If it is empty,
+/// every kind is stored.
+pub fn is_stored_kind(kind: EventKind) -> bool {
+    let kinds = STORED_KINDS.get_or_init(|| {
+        let kinds: Vec<u32> = GLOBALS
+            .db()
+            .read_setting_stored_kinds()
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter_map(|k| k.parse::<u32>().ok())
+            .collect();
+        if kinds.is_empty() {
+            None
+        } else {
+            Some(kinds)
+        }
+    });
+    match kinds {
+        Some(kinds) => kinds.contains(&u32::from(kind)),
+        None => true,
+    }
+}
+
 /// This is mainly used internally to gossip-lib, but you can use it to stuff events
 /// into gossip from other sources. This processes a new event, saving the results into
 /// the database and also populating the GLOBALS maps.
@@ -23,6 +100,36 @@ pub fn process_new_event(
     subscription: Option<String>,
     verify: bool,
     process_even_if_duplicate: bool,
+) -> Result<(), Error> {
+    let start = Instant::now();
+    let result = process_new_event_inner(
+        event,
+        seen_on,
+        subscription,
+        verify,
+        process_even_if_duplicate,
+    );
+    relay_metrics::record_processing(start.elapsed());
+    result
+}
+
+// Whether a delete we already have means an arriving event is not stored. Deletes
+// older than the event, such as of an earlier version at the same address, don't
+// apply. Deleted events are kept (and shown as deleted) if retain_deleted_events is
+// on, like those deleted after we stored them.
+fn is_dropped_as_deleted(event: &Event) -> Result<bool, Error> {
+    if GLOBALS.db().get_deletions(event)?.is_empty() {
+        return Ok(false);
+    }
+    Ok(!GLOBALS.db().read_setting_retain_deleted_events() || !event.kind.is_feed_displayable())
+}
+
+fn process_new_event_inner(
+    event: &Event,
+    seen_on: Option<RelayUrl>,
+    subscription: Option<String>,
+    verify: bool,
+    process_even_if_duplicate: bool,
 ) -> Result<(), Error> {
     // Now
     let now = Unixtime::now();
@@ -33,12 +140,17 @@ pub fn process_new_event(
         _ => false,
     };
 
+    // Events of kinds the user doesn't store are only kept in memory, like global feed
+    // events, so the UI can still show them. The user's own events are always stored.
+    let stored = is_stored_kind(event.kind) || GLOBALS.identity.public_key() == Some(event.pubkey);
+    let volatile = global_feed || !stored;
+
     // Bump count of events processed
     GLOBALS.events_processed.fetch_add(1, Ordering::SeqCst);
 
     // Detect if duplicate.
     // We still need to process some things even if a duplicate
-    let duplicate = if global_feed {
+    let duplicate = if volatile {
         GLOBALS.db().has_volatile_event(event.id)
     } else {
         GLOBALS.db().has_event(event.id)?
@@ -49,24 +161,57 @@ pub fn process_new_event(
     //    NOTE: relays could send forged events with valid IDs of other events, but if
     //          they do that in an event that is a duplicate of one we already have, this
     //          duplicate will only affect seen-on information, it will not be saved.
-    if !duplicate && verify {
-        let mut maxtime = now;
-        maxtime.0 += GLOBALS.db().read_setting_future_allowance_secs() as i64;
-        if let Err(e) = event.verify(Some(maxtime)) {
-            // Don't print these, they clutter the console
-            tracing::debug!("{}: VERIFY ERROR: {}", e, serde_json::to_string(&event)?);
-            return Ok(());
+    let mut maxtime = now;
+    maxtime.0 += GLOBALS.db().read_setting_future_allowance_secs() as i64;
+    let future_event_action =
+        FutureEventAction::from_setting(&GLOBALS.db().read_setting_future_event_action());
+    if verify {
+        let skip = duplicate || recently_verified(event);
+        relay_metrics::record_verification(skip);
+        if !skip {
+            // Events from too far in the future are only rejected here if we drop them
+            let maxtime = match future_event_action {
+                FutureEventAction::Drop => Some(maxtime),
+                _ => None,
+            };
+            if let Err(e) = event.verify(maxtime) {
+                // Don't print these, they clutter the console
+                tracing::debug!("{}: VERIFY ERROR: {}", e, serde_json::to_string(&event)?);
+                return Ok(());
+            }
+            remember_verified(event);
         }
     }
 
+    // Ephemeral events are handed to live consumers and are never stored or indexed
+    if is_ephemeral(event.kind) {
+        tracing::trace!(
+            "{}: Ephemeral Event: {} {:?} @{}",
+            seen_on.as_ref().map(|r| r.as_str()).unwrap_or("_"),
+            subscription.as_ref().unwrap_or(&"_".to_string()),
+            event.kind,
+            event.created_at
+        );
+        // Some ephemeral kinds are requests that we handle ourselves
+        if event.kind == EventKind::NostrConnect {
+            by_kind::process_nostr_connect(event, seen_on.clone())?;
+        }
+        // An error only means nobody is listening
+        let _ = GLOBALS.ephemeral_tx.send(event.to_owned());
+        return Ok(());
+    }
+
     // Create the person if missing in the database
     PersonTable::create_record_if_missing(event.pubkey, None)?;
 
     // Update seen_on relay related information
     let mut spamsafe = false;
     if let Some(url) = &seen_on {
+        // Count it in the relay's ingestion metrics
+        relay_metrics::record_event(url, !duplicate);
+
         // Save seen-on-relay information
-        if global_feed {
+        if volatile {
             GLOBALS
                 .db()
                 .add_event_seen_on_relay_volatile(event.id, url.to_owned(), now);
@@ -74,6 +219,9 @@ pub fn process_new_event(
             GLOBALS
                 .db()
                 .add_event_seen_on_relay(event.id, url, now, None)?;
+
+            // Watch for relays disagreeing about replaceable events
+            GLOBALS.db().add_replaceable_sighting(event, url, None)?;
         }
 
         // Update person-relay information (seen them on this relay)
@@ -123,12 +271,25 @@ pub fn process_new_event(
         return Ok(()); // No more processing needed for existing event.
     }
 
-    // Bail out if the event was deleted (by id)
-    for (_id, relbyid) in GLOBALS.db().find_relationships_by_id(event.id)? {
-        if let RelationshipById::Deletes { by, reason: _ } = relbyid {
-            if event.delete_author_allowed(by) {
+    // Bail out if the event was already deleted
+    if is_dropped_as_deleted(event)? {
+        tracing::trace!(
+            "{}: Deleted Event: {} {:?} @{}",
+            seen_on.as_ref().map(|r| r.as_str()).unwrap_or("_"),
+            subscription.as_ref().unwrap_or(&"_".to_string()),
+            event.kind,
+            event.created_at
+        );
+        return Ok(());
+    }
+
+    // Run any registered event hooks, which may drop the event or give it tags
+    let hook_tags: Option<Vec<Tag>> = {
+        let hooks = GLOBALS.event_hooks.read();
+        match crate::event_hook::run_event_hooks(&hooks, event) {
+            None => {
                 tracing::trace!(
-                    "{}: Deleted Event: {} {:?} @{}",
+                    "{}: Hook Dropped Event: {} {:?} @{}",
                     seen_on.as_ref().map(|r| r.as_str()).unwrap_or("_"),
                     subscription.as_ref().unwrap_or(&"_".to_string()),
                     event.kind,
@@ -136,35 +297,13 @@ pub fn process_new_event(
                 );
                 return Ok(());
             }
+            Some(Cow::Borrowed(_)) => None,
+            Some(Cow::Owned(modified)) => Some(modified.tags),
         }
-    }
-
-    // Bail out if the event was deleted (by address)
-    if let Some(parameter) = event.parameter() {
-        let ea = NAddr {
-            d: parameter.to_owned(),
-            relays: vec![],
-            kind: event.kind,
-            author: event.pubkey,
-        };
-        for (_id, relbyaddr) in GLOBALS.db().find_relationships_by_addr(&ea)? {
-            if let RelationshipByAddr::Deletes { by, reason: _ } = relbyaddr {
-                if by == event.pubkey {
-                    tracing::trace!(
-                        "{}: Deleted Event: {} {:?} @{}",
-                        seen_on.as_ref().map(|r| r.as_str()).unwrap_or("_"),
-                        subscription.as_ref().unwrap_or(&"_".to_string()),
-                        event.kind,
-                        event.created_at
-                    );
-                    return Ok(());
-                }
-            }
-        }
-    }
+    };
 
     // Save event
-    if global_feed {
+    if volatile {
         GLOBALS.db().write_event_volatile(event.to_owned());
     } else if event.kind.is_replaceable() {
         // Bail if the event is an already-replaced replaceable event
@@ -183,6 +322,18 @@ pub fn process_new_event(
         GLOBALS.db().write_event(event, None)?;
     }
 
+    // Remember when an event from the future arrived, so feeds can order it by that
+    if !volatile && future_event_action == FutureEventAction::Clamp && event.created_at > maxtime {
+        GLOBALS.db().write_clamped_time(event.id, now, None)?;
+    }
+
+    // Tags from hooks are kept apart from the event, which must still match its signature
+    if let Some(tags) = &hook_tags {
+        if !volatile {
+            GLOBALS.db().write_hook_tags(event.id, tags, None)?;
+        }
+    }
+
     // Log
     tracing::debug!(
         "{}: New Event: {} {:?} @{}",
@@ -205,6 +356,9 @@ pub fn process_new_event(
     }
     // FIXME do same for event addr
 
+    // If a nostr: URI is waiting for this event, open it
+    crate::misc::navigate_if_awaited(event);
+
     // If it is a GiftWrap, from here on out operate on the Rumor with the giftwrap's id
     let mut event: &Event = event; // take ownership of this reference
     let mut rumor_event: Event;
@@ -227,7 +381,7 @@ pub fn process_new_event(
                     recommended_relay_url: Some(rurl),
                     ..
                 }) => {
-                    if let Ok(url) = RelayUrl::try_from_unchecked_url(&rurl) {
+                    if let Ok(url) = crate::relay::relay_url_from_unchecked(&rurl) {
                         GLOBALS.db().write_relay_if_missing(&url, None)?;
                     }
                 }
@@ -238,7 +392,7 @@ pub fn process_new_event(
                 }) => {
                     PersonTable::create_record_if_missing(pubkey, None)?;
                     if let Some(uncheckedurl) = maybeurl {
-                        if let Ok(url) = RelayUrl::try_from_unchecked_url(&uncheckedurl) {
+                        if let Ok(url) = crate::relay::relay_url_from_unchecked(&uncheckedurl) {
                             GLOBALS.db().write_relay_if_missing(&url, None)?;
 
                             // upsert person_relay.last_suggested
@@ -259,11 +413,19 @@ pub fn process_new_event(
     // Let seeker know about this event id (in case it was sought)
     GLOBALS.seeker.found(event)?;
 
-    // Save event relationships (whether from a relay or not)
-    // and invalidate UI events that need to be redrawn because those relationships
-    // affect their rendering.
-    let invalid_ids = process_relationships_of_event(event, None)?;
-    GLOBALS.ui_invalidate_notes(&invalid_ids);
+    // Events we don't store must not be referenced from what we do store, so they
+    // get no relationships (nothing is summarized from them either) and no
+    // notifications.
+    if stored {
+        // Save event relationships (whether from a relay or not)
+        // and invalidate UI events that need to be redrawn because those relationships
+        // affect their rendering.
+        let invalid_ids = process_relationships_of_event(event, None)?;
+        GLOBALS.ui_invalidate_notes(&invalid_ids);
+
+        // Record a notification if this targets the user
+        crate::notification::process_notifications(event)?;
+    }
 
     if event.kind.is_feed_displayable() {
         process_feed_displayable_content(event, seen_on.as_ref(), now)?;
@@ -285,10 +447,11 @@ pub fn process_new_event(
         EventKind::FollowSets => by_kind::process_follow_sets(event, ours)?,
         EventKind::RelayList => by_kind::process_relay_list(event)?,
         EventKind::DmRelayList => by_kind::process_dm_relay_list(event)?,
-        EventKind::Repost => by_kind::process_repost(event, verify)?,
-        EventKind::NostrConnect => by_kind::process_nostr_connect(event, seen_on.clone())?,
+        EventKind::Repost => by_kind::process_repost(event)?,
         EventKind::UserServerList => by_kind::process_user_server_list(event, ours)?,
         EventKind::RequestToVanish => by_kind::process_request_to_vanish(event)?,
+        EventKind::ProfileBadges => by_kind::process_profile_badges(event)?,
+        EventKind::BadgeDefinition | EventKind::BadgeAward => by_kind::process_badge(event)?,
         _ => {}
     }
 
@@ -321,7 +484,9 @@ fn process_feed_displayable_content(
                     let relay_urls: Vec<RelayUrl> = ne
                         .relays
                         .iter()
-                        .filter_map(|unchecked| RelayUrl::try_from_unchecked_url(unchecked).ok())
+                        .filter_map(|unchecked| {
+                            crate::relay::relay_url_from_unchecked(unchecked).ok()
+                        })
                         .collect();
                     let _ = GLOBALS
                         .to_overlord
@@ -350,7 +515,7 @@ fn process_feed_displayable_content(
 
                 // Make sure we have their relays
                 for relay in prof.relays {
-                    if let Ok(rurl) = RelayUrl::try_from_unchecked_url(&relay) {
+                    if let Ok(rurl) = crate::relay::relay_url_from_unchecked(&relay) {
                         GLOBALS.db().modify_person_relay(
                             prof.pubkey,
                             &rurl,
@@ -374,7 +539,7 @@ fn process_feed_displayable_content(
                 GLOBALS.people.create_if_missing(pubkey);
             }
             NostrBech32::Relay(relay) => {
-                if let Ok(rurl) = RelayUrl::try_from_unchecked_url(&relay) {
+                if let Ok(rurl) = crate::relay::relay_url_from_unchecked(&relay) {
                     // make sure we have the relay
                     GLOBALS.db().write_relay_if_missing(&rurl, None)?;
                 }
@@ -393,16 +558,17 @@ pub(crate) fn process_relationships_of_event(
     event: &Event,
     rw_txn: Option<&mut RwTxn<'_>>,
 ) -> Result<Vec<Id>, Error> {
-    let mut invalidate: Vec<Id> = Vec::new();
+    match rw_txn {
+        Some(txn) => write_relationships_of_event(event, txn),
+        // With its own transaction, the writes can be retried if the map was full
+        None => GLOBALS
+            .db()
+            .write_with_map_growth(|txn| write_relationships_of_event(event, txn)),
+    }
+}
 
-    let mut local_txn = None;
-    let txn = match rw_txn {
-        Some(x) => x,
-        None => {
-            local_txn = Some(GLOBALS.db().get_write_txn()?);
-            local_txn.as_mut().unwrap()
-        }
-    };
+fn write_relationships_of_event(event: &Event, txn: &mut RwTxn<'_>) -> Result<Vec<Id>, Error> {
+    let mut invalidate: Vec<Id> = Vec::new();
 
     // timestamps
     if event.kind == EventKind::Timestamp {
@@ -419,70 +585,8 @@ pub(crate) fn process_relationships_of_event(
     }
 
     // deletes
-    if let Some((vec, reason)) = event.deletes() {
-        for er in vec.iter() {
-            match er {
-                EventReference::Id { id, .. } => {
-                    // If we have the event,
-                    // Actually delete at this point in some cases
-                    if let Some(deleted_event) = GLOBALS.db().read_event(*id)? {
-                        if !deleted_event.delete_author_allowed(event.pubkey) {
-                            // No further processing if not a valid delete
-                            continue;
-                        }
-                        invalidate.push(deleted_event.id);
-                        if !deleted_event.kind.is_feed_displayable() {
-                            // Otherwise actually delete (PITA to do otherwise)
-                            GLOBALS.db().delete_event(deleted_event.id, Some(txn))?;
-                        }
-                    }
-
-                    // Store the delete (we either don't have the target to verify,
-                    // or we just verified above. In the former case, it is okay because
-                    // we verify on usage)
-                    GLOBALS.db().write_relationship_by_id(
-                        *id,
-                        event.id,
-                        RelationshipById::Deletes {
-                            by: event.pubkey,
-                            reason: reason.clone(),
-                        },
-                        Some(txn),
-                    )?;
-                }
-                EventReference::Addr(ea) => {
-                    // If we have the event,
-                    // Actually delete at this point in some cases
-                    if let Some(deleted_event) = GLOBALS
-                        .db()
-                        .get_replaceable_event(ea.kind, ea.author, &ea.d)?
-                    {
-                        if !deleted_event.delete_author_allowed(event.pubkey) {
-                            // No further processing if not a valid delete
-                            continue;
-                        }
-                        invalidate.push(deleted_event.id);
-                        if !deleted_event.kind.is_feed_displayable() {
-                            // Otherwise actually delete (PITA to do otherwise)
-                            GLOBALS.db().delete_event(deleted_event.id, Some(txn))?;
-                        }
-                    }
-
-                    // Store the delete (we either don't have the target to verify,
-                    // or we just verified above. In the former case, it is okay because
-                    // we verify on usage)
-                    GLOBALS.db().write_relationship_by_addr(
-                        ea.clone(),
-                        event.id,
-                        RelationshipByAddr::Deletes {
-                            by: event.pubkey,
-                            reason: reason.clone(),
-                        },
-                        Some(txn),
-                    )?;
-                }
-            }
-        }
+    if event.kind == EventKind::EventDeletion {
+        invalidate.extend(process_deletion_event(event, txn)?);
     }
 
     // reacts to
@@ -495,13 +599,11 @@ pub(crate) fn process_relationships_of_event(
     )) = event.reacts_to()
     {
         // NOTE: reactions may precede the event they react to. So we cannot validate here.
-        GLOBALS.db().write_relationship_by_id(
+        GLOBALS.db().write_reaction(
             reacted_to_id, // event reacted to
             event.id,      // the reaction event id
-            RelationshipById::ReactsTo {
-                by: event.pubkey,
-                reaction,
-            },
+            event.pubkey,
+            reaction,
             Some(txn),
         )?;
         invalidate.push(reacted_to_id);
@@ -639,13 +741,9 @@ pub(crate) fn process_relationships_of_event(
 
     if event.kind == EventKind::Reporting {
         for tag in &event.tags {
-            if let Ok(ParsedTag::Event {
-                id,
-                recommended_relay_url: Some(rurl),
-                ..
-            }) = tag.parse()
-            {
-                let report = &rurl.0;
+            // The report type is where a relay hint would be
+            if let Ok(ParsedTag::Event { id, .. }) = tag.parse() {
+                let report = tag.get_index(2);
                 GLOBALS.db().write_relationship_by_id(
                     id,
                     event.id,
@@ -699,9 +797,44 @@ pub(crate) fn process_relationships_of_event(
         }
     }
 
+    // Poll responses
+    if crate::polls::is_poll_response(event.kind) {
+        for tag in &event.tags {
+            if let Ok(ParsedTag::Event { id, .. }) = tag.parse() {
+                GLOBALS.db().write_relationship_by_id(
+                    id,
+                    event.id,
+                    RelationshipById::RespondsToPoll,
+                    Some(txn),
+                )?;
+                invalidate.push(id);
+            }
+        }
+    }
+
+    // Community post approvals. Whether the approver moderates the community is
+    // checked when they are read, as the community definition may come later.
+    if crate::community::is_approval(event.kind) {
+        if let Some(id) = crate::community::approved_id(event) {
+            for addr in crate::community::communities_of(event) {
+                GLOBALS.db().write_relationship_by_id(
+                    id,
+                    event.id,
+                    RelationshipById::Approves {
+                        by: event.pubkey,
+                        community_author: addr.author,
+                        community_d: addr.d,
+                    },
+                    Some(txn),
+                )?;
+                invalidate.push(id);
+            }
+        }
+    }
+
     // Reposts
     if event.kind == EventKind::Repost {
-        if let Ok(inner_event) = serde_json::from_str::<Event>(&event.content) {
+        if let Some(inner_event) = by_kind::embedded_repost(event) {
             GLOBALS.db().write_relationship_by_id(
                 inner_event.id,
                 event.id,
@@ -774,8 +907,129 @@ pub(crate) fn process_relationships_of_event(
         None => (),
     }
 
-    if let Some(txn) = local_txn {
-        txn.commit()?;
+    Ok(invalidate)
+}
+
+/// Process a deletion event (kind 5).
+///
+/// Deletions are only honored if the deleting pubkey is allowed to delete the target
+/// (normally meaning it is the author). Deletions targeting events by other authors are
+/// ignored. If we don't yet have the target event, the deletion is recorded anyway so
+/// that the target is dropped when it arrives (see `process_new_event`).
+///
+/// Deleted feed-displayable events are retained (and rendered as deleted, or hidden)
+/// unless the `retain_deleted_events` setting is off, in which case they are removed
+/// from storage.
+///
+/// This returns IDs that should be UI invalidated (must be redrawn)
+pub(crate) fn process_deletion_event(event: &Event, txn: &mut RwTxn<'_>) -> Result<Vec<Id>, Error> {
+    let mut invalidate: Vec<Id> = Vec::new();
+
+    let (vec, reason) = match event.deletes() {
+        Some(x) => x,
+        None => return Ok(invalidate),
+    };
+
+    let retain = GLOBALS.db().read_setting_retain_deleted_events();
+
+    for er in vec.iter() {
+        match er {
+            EventReference::Id { id, .. } => {
+                // If we have the event,
+                // Actually delete at this point in some cases
+                if let Some(deleted_event) = GLOBALS.db().read_event(*id)? {
+                    if !deleted_event.delete_author_allowed(event.pubkey) {
+                        // No further processing if not a valid delete
+                        continue;
+                    }
+                    if deleted_event.created_at > event.created_at {
+                        // Deletes must come after the event they delete
+                        continue;
+                    }
+                    invalidate.push(deleted_event.id);
+
+                    // Uncount a summarized reaction
+                    if let Some((EventReference::Id { id: target, .. }, _)) =
+                        deleted_event.reacts_to()
+                    {
+                        if let Some(changed) = GLOBALS.db().delete_summarized_reaction(
+                            deleted_event.id,
+                            event.pubkey,
+                            Some(target),
+                            Some(txn),
+                        )? {
+                            invalidate.push(changed);
+                        }
+                    }
+
+                    if !retain || !deleted_event.kind.is_feed_displayable() {
+                        // Otherwise actually delete (PITA to do otherwise)
+                        GLOBALS.db().delete_event(deleted_event.id, Some(txn))?;
+                    }
+                } else if let Some(changed) =
+                    GLOBALS
+                        .db()
+                        .delete_summarized_reaction(*id, event.pubkey, None, Some(txn))?
+                {
+                    // A summarized reaction that was pruned
+                    invalidate.push(changed);
+                }
+
+                // Store the delete (we either don't have the target to verify,
+                // or we just verified above. In the former case, it is okay because
+                // we verify on usage)
+                GLOBALS.db().write_relationship_by_id(
+                    *id,
+                    event.id,
+                    RelationshipById::Deletes {
+                        by: event.pubkey,
+                        reason: reason.clone(),
+                    },
+                    Some(txn),
+                )?;
+            }
+            EventReference::Addr(ea) => {
+                // Addresses carry their author, so we can reject deletes of
+                // other people's addressable events even without the target
+                if ea.author != event.pubkey {
+                    continue;
+                }
+
+                // If we have the event,
+                // Actually delete at this point in some cases
+                if let Some(deleted_event) = GLOBALS
+                    .db()
+                    .get_replaceable_event(ea.kind, ea.author, &ea.d)?
+                {
+                    if !deleted_event.delete_author_allowed(event.pubkey) {
+                        // No further processing if not a valid delete
+                        continue;
+                    }
+                    // A newer version than the delete is not affected, but we still
+                    // record the delete for older versions that may arrive later
+                    if deleted_event.created_at <= event.created_at {
+                        invalidate.push(deleted_event.id);
+                        if !retain || !deleted_event.kind.is_feed_displayable() {
+                            // Otherwise actually delete (PITA to do otherwise)
+                            GLOBALS.db().delete_event(deleted_event.id, Some(txn))?;
+                        }
+                    }
+                }
+
+                // Store the delete (we either don't have the target to verify,
+                // or we just verified above. In the former case, it is okay because
+                // we verify on usage)
+                GLOBALS.db().write_relationship_by_addr(
+                    ea.clone(),
+                    event.id,
+                    RelationshipByAddr::Deletes {
+                        by: event.pubkey,
+                        reason: reason.clone(),
+                    },
+                    Some(txn),
+                )?;
+            }
+        }
     }
 
     Ok(invalidate)
@@ -832,3 +1086,130 @@ pub fn update_global_followers(event: &Event) {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{init_globals_storage, signed_event};
+    use nostr_types::{KeySigner, NAddr, Signer};
+
+    fn delete(signer: &KeySigner, created_at: i64, tag: Tag) -> Event {
+        signed_event(signer, created_at, EventKind::EventDeletion, vec![tag], "")
+    }
+
+    fn e_tag(id: Id) -> Tag {
+        ParsedTag::Event {
+            id,
+            recommended_relay_url: None,
+            marker: None,
+            author_pubkey: None,
+        }
+        .into_tag()
+    }
+
+    fn article(signer: &KeySigner, created_at: i64) -> Event {
+        let d_tag = Tag::new(&["d", "article"]);
+        signed_event(
+            signer,
+            created_at,
+            EventKind::LongFormContent,
+            vec![d_tag],
+            "",
+        )
+    }
+
+    // Store and process an event the way process_new_event does, without the
+    // parts that need a running overlord
+    fn store(event: &Event) {
+        if event.kind.is_replaceable() {
+            GLOBALS.db().replace_event(event, None).unwrap();
+        } else {
+            GLOBALS.db().write_event(event, None).unwrap();
+        }
+        process_relationships_of_event(event, None).unwrap();
+    }
+
+    #[test]
+    fn test_deletions() {
+        init_globals_storage();
+        let alice = KeySigner::generate("", 1).unwrap();
+        let bob = KeySigner::generate("", 1).unwrap();
+        GLOBALS
+            .db()
+            .write_setting_retain_deleted_events(&false, None)
+            .unwrap();
+
+        // Deletes by other authors are ignored
+        let note = signed_event(&alice, 1000, EventKind::TextNote, vec![], "hello");
+        store(&note);
+        store(&delete(&bob, 1100, e_tag(note.id)));
+        assert!(GLOBALS.db().read_event(note.id).unwrap().is_some());
+        assert!(GLOBALS.db().get_deletions(&note).unwrap().is_empty());
+        assert!(!is_dropped_as_deleted(&note).unwrap());
+
+        // A delete that arrives before its target drops the target when it comes
+        let note = signed_event(&alice, 2000, EventKind::TextNote, vec![], "oops");
+        store(&delete(&alice, 2100, e_tag(note.id)));
+        assert!(is_dropped_as_deleted(&note).unwrap());
+
+        // unless deleted events are retained, in which case it is shown as deleted
+        GLOBALS
+            .db()
+            .write_setting_retain_deleted_events(&true, None)
+            .unwrap();
+        assert!(!is_dropped_as_deleted(&note).unwrap());
+        assert!(!GLOBALS.db().get_deletions(&note).unwrap().is_empty());
+        GLOBALS
+            .db()
+            .write_setting_retain_deleted_events(&false, None)
+            .unwrap();
+
+        // A delete dated before its target does not apply to it
+        let late = signed_event(&alice, 3000, EventKind::TextNote, vec![], "later");
+        store(&delete(&alice, 2900, e_tag(late.id)));
+        assert!(!is_dropped_as_deleted(&late).unwrap());
+
+        // An addressable event can be published again after it was deleted
+        let first = article(&alice, 4000);
+        store(&first);
+        let addr_tag = ParsedTag::Address {
+            address: NAddr {
+                d: "article".to_owned(),
+                relays: vec![],
+                kind: EventKind::LongFormContent,
+                author: alice.public_key(),
+            },
+            marker: None,
+        }
+        .into_tag();
+        store(&delete(&alice, 4100, addr_tag));
+        assert!(GLOBALS.db().read_event(first.id).unwrap().is_none());
+        assert!(is_dropped_as_deleted(&article(&alice, 4050)).unwrap());
+        let again = article(&alice, 4200);
+        assert!(!is_dropped_as_deleted(&again).unwrap());
+        store(&again);
+        assert!(GLOBALS.db().read_event(again.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_recently_verified() {
+        let signer = KeySigner::generate("", 1).unwrap();
+        let event = signed_event(&signer, 1700000000, EventKind::TextNote, vec![], "hello");
+        assert!(!recently_verified(&event));
+        remember_verified(&event);
+        assert!(recently_verified(&event));
+
+        // A forged copy with the same id and signature but other content
+        let mut forged = event.clone();
+        forged.content = "goodbye".to_owned();
+        assert!(!recently_verified(&forged));
+        assert!(forged.verify(None).is_err());
+
+        // A forged copy with the same id signed by someone else
+        let other = KeySigner::generate("", 1).unwrap();
+        let mut forged = event.clone();
+        forged.sig = signed_event(&other, 1700000000, EventKind::TextNote, vec![], "hello").sig;
+        assert!(!recently_verified(&forged));
+        assert!(forged.verify(None).is_err());
+    }
+}
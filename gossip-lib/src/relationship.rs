@@ -3,3 +3,36 @@ pub type RelationshipById = crate::storage::types::RelationshipById2;
 
 /// Relationship type by NAddr, aliased to the latest version
 pub type RelationshipByAddr = crate::storage::types::RelationshipByAddr3;
+
+/// Summary of reactions to a popular event, aliased to the latest version
+pub type ReactionSummary = crate::storage::types::ReactionSummary1;
+
+use nostr_types::{Id, MilliSatoshi, PublicKey};
+
+/// The events that relate to an event, as used to show its engagement
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Relationships {
+    /// Replies and comments, including replies to its annotations
+    pub replies: Vec<Id>,
+
+    /// Reactions, including those already folded into a summary
+    pub reactions: ReactionSummary,
+
+    /// Zap receipts, with who zapped and how much
+    pub zaps: Vec<(Id, PublicKey, MilliSatoshi)>,
+
+    /// Reposts
+    pub reposts: Vec<Id>,
+
+    /// Deletions by someone allowed to delete the event, with their reasons
+    pub deletions: Vec<(Id, String)>,
+}
+
+impl Relationships {
+    /// The total of all zaps
+    pub fn zap_total(&self) -> MilliSatoshi {
+        self.zaps
+            .iter()
+            .fold(MilliSatoshi(0), |total, (_, _, amount)| total + *amount)
+    }
+}
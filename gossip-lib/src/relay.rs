@@ -4,9 +4,11 @@
 // Relay::choose_relay_urls(Relay::WRITE, |_| true)?; // all ours
 // Relay::choose_relay_urls(Relay::DM, |_| true)?;    // all ours
 // relay::get_some_pubkey_outboxes(pubkey)?  // for subscribing to theirs
+// relay::get_metadata_pubkey_outboxes(pubkey)?  // for fetching their metadata
 // relay::get_all_pubkey_outboxes(pubkey)?   // informational
 // relay::get_all_pubkey_inboxes(pubkey)?    // for replying to them
 // relay::get_dm_relays(pubkey)?             // for DMs to them
+// relay::get_dm_inboxes(pubkey)?            // for giftwrapped DMs to them
 // relay::get_best_relays_with_score(pubkey, usage, score_factors) // for relay picker, and internal
 // relay::recommended_relay_hint(reply_to_id)?    // for a hint
 // relay::relays_for_seeking_replies(&event)?     // to find replies
@@ -14,17 +16,40 @@
 // future: get_all_pubkey_outboxes_for_batch_search(pubkey)?     // for seeker exhaustive search
 
 /// Relay type, aliased to the latest version
-pub type Relay = crate::storage::types::Relay3;
-pub use crate::storage::types::ScoreFactors;
+pub type Relay = crate::storage::types::Relay4;
+
+/// A change in a relay's NIP-11 document, aliased to the latest version
+pub type Nip11Change = crate::storage::types::Nip11Change1;
+
+/// A relay's out of band auth credential, aliased to the latest version
+pub type RelayCredential = crate::storage::types::RelayCredential1;
+
+/// The most relay hints to put in a shareable `nevent`, `naddr` or `nprofile`.
+/// More make links long without making them much easier to resolve.
+pub const MAX_RELAY_HINTS: usize = 3;
+pub use crate::storage::types::{RelayRejection, ScoreFactors};
 
 use crate::error::{Error, ErrorKind};
 use crate::person_relay::PersonRelay;
 use crate::GLOBALS;
-use nostr_types::{Event, EventKind, Id, PublicKey, RelayUrl, RelayUsage, Unixtime};
+use nostr_types::{
+    ContentEncryptionAlgorithm, Event, EventKind, Id, PublicKey, RelayInformationDocument,
+    RelayUrl, RelayUsage, Unixtime,
+};
 
-// Get `num_relays_per_prson` outboxes to subscribe to their events
+// Get `feed_relays_per_person` outboxes to subscribe to their events
 pub fn get_some_pubkey_outboxes(pubkey: PublicKey) -> Result<Vec<RelayUrl>, Error> {
-    let num = GLOBALS.db().read_setting_num_relays_per_person() as usize;
+    let num = GLOBALS.db().read_setting_feed_relays_per_person() as usize;
+    get_best_pubkey_outboxes(pubkey, num)
+}
+
+// Get `metadata_relays_per_person` outboxes to fetch their metadata from
+pub fn get_metadata_pubkey_outboxes(pubkey: PublicKey) -> Result<Vec<RelayUrl>, Error> {
+    let num = GLOBALS.db().read_setting_metadata_relays_per_person() as usize;
+    get_best_pubkey_outboxes(pubkey, num)
+}
+
+fn get_best_pubkey_outboxes(pubkey: PublicKey, num: usize) -> Result<Vec<RelayUrl>, Error> {
     let relays =
         get_best_relays_with_score(pubkey, RelayUsage::Outbox, ScoreFactors::FULLY_ADJUSTED)?
             .iter()
@@ -34,6 +59,12 @@ pub fn get_some_pubkey_outboxes(pubkey: PublicKey) -> Result<Vec<RelayUrl>, Erro
     Ok(relays)
 }
 
+/// Relays to ask about a person we know no relays for, so their metadata and relay
+/// list can be found. These are the relays marked INDEXER.
+pub fn first_contact_relays() -> Result<Vec<RelayUrl>, Error> {
+    Relay::choose_relay_urls(Relay::INDEXER, |_| true)
+}
+
 // Get all person outboxes
 pub fn get_all_pubkey_outboxes(pubkey: PublicKey) -> Result<Vec<RelayUrl>, Error> {
     // Why 0.125?
@@ -71,7 +102,7 @@ pub fn get_all_pubkey_inboxes(pubkey: PublicKey) -> Result<Vec<RelayUrl>, Error>
 /// This gets NIP-17 DM relays only.
 ///
 /// At the time of writing, not many people have these specified, in which case
-/// the caller should fallback to write relays and NIP-04.
+/// the caller should fallback to their INBOX relays (see get_dm_inboxes()).
 pub fn get_dm_relays(pubkey: PublicKey) -> Result<Vec<RelayUrl>, Error> {
     let mut output: Vec<RelayUrl> = Vec::new();
     for pr in GLOBALS.db().get_person_relays(pubkey)?.drain(..) {
@@ -88,6 +119,17 @@ pub fn get_dm_relays(pubkey: PublicKey) -> Result<Vec<RelayUrl>, Error> {
     Ok(output)
 }
 
+/// Where to send a giftwrapped (NIP-17) DM to someone: their kind 10050 DM relays,
+/// or their INBOX relays if they haven't listed any.
+pub fn get_dm_inboxes(pubkey: PublicKey) -> Result<Vec<RelayUrl>, Error> {
+    let dm_relays = get_dm_relays(pubkey)?;
+    if dm_relays.is_empty() {
+        get_all_pubkey_inboxes(pubkey)
+    } else {
+        Ok(dm_relays)
+    }
+}
+
 // The functions below are all about choosing relays for some task,
 // each returning `Result<Vec<RelayUrl>, Error>` (or similar)
 
@@ -124,6 +166,11 @@ pub fn recommended_relay_hint(reply_to: Id) -> Result<Option<RelayUrl>, Error> {
 pub fn relays_for_seeking_replies(event: &Event) -> Result<Vec<RelayUrl>, Error> {
     let mut relays: Vec<RelayUrl> = Vec::new();
 
+    // Relays a poll asks its responses to go to
+    if let Some(poll) = crate::polls::Poll::from_event(event) {
+        relays.extend(poll.relays);
+    }
+
     // Inboxes of the author
     relays.extend(get_all_pubkey_inboxes(event.pubkey)?);
 
@@ -204,6 +251,9 @@ pub fn relays_to_post_to(event: &Event) -> Result<Vec<RelayUrl>, Error> {
         .collect();
     relays.retain(|r| !seen_on.contains(r));
 
+    // Never to relays marked no-post
+    relays.retain(|r| !matches!(GLOBALS.db().read_relay(r), Ok(Some(relay)) if relay.no_post));
+
     relays.sort();
     relays.dedup();
 
@@ -354,3 +404,129 @@ pub fn sort_relays(
     Ok(vec.iter().map(|(url, _)| url.to_owned()).collect())
 }
 */
+
+/// The canonical form of a relay URL.
+///
+/// Default ports are dropped and a trailing slash after a path is removed, so that
+/// `wss://relay.example:443/nostr/` and `wss://relay.example/nostr` are the same relay.
+/// A bare host keeps its single trailing slash, as `RelayUrl` always gives it one.
+pub fn canonical_url(url: &RelayUrl) -> RelayUrl {
+    let mut parsed = match url::Url::parse(url.as_str()) {
+        Ok(parsed) => parsed,
+        Err(_) => return url.to_owned(),
+    };
+    let path = parsed.path().to_owned();
+    if path.len() > 1 && path.ends_with('/') {
+        parsed.set_path(path.trim_end_matches('/'));
+    }
+    if parsed.as_str() == url.as_str() {
+        return url.to_owned();
+    }
+    RelayUrl::try_from_str(parsed.as_str()).unwrap_or_else(|_| url.to_owned())
+}
+
+/// Parse a relay URL that came from outside (an event, a hint, the user) into its
+/// canonical form
+pub fn parse_relay_url(s: &str) -> Result<RelayUrl, nostr_types::Error> {
+    RelayUrl::try_from_str(s).map(|url| canonical_url(&url))
+}
+
+/// Like [parse_relay_url], from an `UncheckedUrl`
+pub fn relay_url_from_unchecked(
+    url: &nostr_types::UncheckedUrl,
+) -> Result<RelayUrl, nostr_types::Error> {
+    RelayUrl::try_from_unchecked_url(url).map(|url| canonical_url(&url))
+}
+
+/// Whether the relay URL's host is `localhost` or a loopback IP address (`127.0.0.0/8`
+/// or `::1`). The scheme and port are not considered, and no DNS lookup is done, so a
+/// hostname that merely resolves to a loopback address is not counted.
+pub fn is_loopback(url: &RelayUrl) -> bool {
+    let uri = match url.as_str().parse::<http::Uri>() {
+        Ok(uri) => uri,
+        Err(_) => return false,
+    };
+    let host = match uri.host() {
+        Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => return false,
+    };
+    if host.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    match host.parse::<std::net::IpAddr>() {
+        Ok(ip) => ip.is_loopback(),
+        Err(_) => false,
+    }
+}
+
+/// If what a relay says it supports changed between its `old` and `new` NIP-11
+/// documents, record the change and announce it on `GLOBALS.nip11_changes`.
+pub(crate) fn note_nip11_change(
+    url: &RelayUrl,
+    old: Option<&RelayInformationDocument>,
+    new: &RelayInformationDocument,
+) -> Result<(), Error> {
+    // The first document we get is not a change
+    let Some(old) = old else {
+        return Ok(());
+    };
+    if let Some(change) = Nip11Change::between(url.clone(), Unixtime::now().0 as u64, old, new) {
+        tracing::info!(
+            "{}: NIP-11 changed (added NIPs {:?}, removed NIPs {:?})",
+            url,
+            change.added_nips,
+            change.removed_nips
+        );
+        GLOBALS.db().write_nip11_change(&change, None)?;
+        let _ = GLOBALS.nip11_changes.send(change);
+    }
+    Ok(())
+}
+
+/// Store the auth credential for a relay, or remove it if `credential` is None.
+/// It is stored encrypted to ourselves, so the identity must be unlocked.
+pub fn set_relay_credential(
+    url: &RelayUrl,
+    credential: Option<RelayCredential>,
+) -> Result<(), Error> {
+    let url = canonical_url(url);
+    let credential = match credential {
+        Some(c) => c,
+        None => return GLOBALS.db().delete_relay_credential(&url, None),
+    };
+
+    if !GLOBALS.identity.is_unlocked() {
+        return Err((ErrorKind::NoPrivateKey, file!(), line!()).into());
+    }
+    let my_pubkey = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => return Err(ErrorKind::NoPublicKey.into()),
+    };
+
+    let plaintext = serde_json::to_string(&credential)?;
+    let ciphertext =
+        GLOBALS
+            .identity
+            .encrypt(&my_pubkey, &plaintext, ContentEncryptionAlgorithm::Nip44v2)?;
+    GLOBALS.db().write_relay_credential(&url, &ciphertext, None)
+}
+
+/// The auth credential for a relay, if it has one. It is stored encrypted, so the
+/// identity must be unlocked to get it.
+pub fn get_relay_credential(url: &RelayUrl) -> Result<Option<RelayCredential>, Error> {
+    let ciphertext = match GLOBALS.db().read_relay_credential(&canonical_url(url))? {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+
+    if !GLOBALS.identity.is_unlocked() {
+        return Err((ErrorKind::NoPrivateKey, file!(), line!()).into());
+    }
+    let my_pubkey = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => return Err(ErrorKind::NoPublicKey.into()),
+    };
+
+    let plaintext = GLOBALS.identity.decrypt(&my_pubkey, &ciphertext)?;
+    Ok(Some(serde_json::from_str(&plaintext)?))
+}
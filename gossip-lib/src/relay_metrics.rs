@@ -0,0 +1,453 @@
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::relay::Relay;
+use nostr_types::{RelayUrl, Unixtime};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// How many minutes of per-relay ingestion history are kept in memory for the live view
+pub const INGEST_WINDOW_MINUTES: i64 = 10;
+
+/// How many seconds of throughput history are kept for the stats panel
+pub const THROUGHPUT_WINDOW_SECS: i64 = 60;
+
+/// Event ingestion counts for a relay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestCounts {
+    /// Events the relay sent us
+    pub events_received: u64,
+
+    /// Events the relay sent us that we did not already have
+    pub events_new: u64,
+
+    /// Bytes of websocket messages the relay sent us
+    pub bytes_received: u64,
+}
+
+impl IngestCounts {
+    /// Events the relay sent us that we already had
+    pub fn events_duplicate(&self) -> u64 {
+        self.events_received.saturating_sub(self.events_new)
+    }
+
+    /// Fraction of received events that were new to us, if any were received
+    pub fn unique_rate(&self) -> Option<f32> {
+        if self.events_received == 0 {
+            None
+        } else {
+            Some(self.events_new as f32 / self.events_received as f32)
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == IngestCounts::default()
+    }
+
+    fn add(&mut self, other: &IngestCounts) {
+        self.events_received += other.events_received;
+        self.events_new += other.events_new;
+        self.bytes_received += other.bytes_received;
+    }
+}
+
+/// Live event ingestion metrics for a relay
+#[derive(Debug, Clone, Default)]
+pub struct RelayIngest {
+    // Per-minute buckets, oldest first
+    window: VecDeque<(i64, IngestCounts)>,
+
+    // Counts not yet added to the relay record
+    unsaved: IngestCounts,
+}
+
+impl RelayIngest {
+    fn current_bucket(&mut self) -> &mut IngestCounts {
+        let minute = Unixtime::now().0 / 60;
+        while let Some((m, _)) = self.window.front() {
+            if *m <= minute - INGEST_WINDOW_MINUTES {
+                self.window.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.window.back().map(|(m, _)| *m) != Some(minute) {
+            self.window.push_back((minute, IngestCounts::default()));
+        }
+        &mut self.window.back_mut().unwrap().1
+    }
+
+    fn recent(&self) -> IngestCounts {
+        let minute = Unixtime::now().0 / 60;
+        let mut counts = IngestCounts::default();
+        for (m, c) in self.window.iter() {
+            if *m > minute - INGEST_WINDOW_MINUTES {
+                counts.add(c);
+            }
+        }
+        counts
+    }
+}
+
+/// Record that an event arrived from a relay, and whether it was new to us
+pub(crate) fn record_event(url: &RelayUrl, new: bool) {
+    let mut ingest = GLOBALS.relay_ingest.entry(url.to_owned()).or_default();
+    let new = if new { 1 } else { 0 };
+    ingest.current_bucket().events_received += 1;
+    ingest.current_bucket().events_new += new;
+    ingest.unsaved.events_received += 1;
+    ingest.unsaved.events_new += new;
+
+    GLOBALS
+        .throughput
+        .write()
+        .current_bucket()
+        .relay(url)
+        .events += 1;
+}
+
+/// Record that bytes arrived from a relay
+pub(crate) fn record_bytes(url: &RelayUrl, bytes: usize) {
+    let mut ingest = GLOBALS.relay_ingest.entry(url.to_owned()).or_default();
+    ingest.current_bucket().bytes_received += bytes as u64;
+    ingest.unsaved.bytes_received += bytes as u64;
+
+    GLOBALS.throughput.write().current_bucket().relay(url).bytes += bytes as u64;
+}
+
+/// Record that an event was processed, from any source, and how long it took
+pub(crate) fn record_processing(elapsed: Duration) {
+    let mut throughput = GLOBALS.throughput.write();
+    let bucket = throughput.current_bucket();
+    bucket.events_processed += 1;
+    bucket.processing_micros += elapsed.as_micros() as u64;
+}
+
+/// Record whether an event's signature was verified, or verifying it was skipped
+/// because we already had it or had verified it moments ago
+pub(crate) fn record_verification(skipped: bool) {
+    let mut throughput = GLOBALS.throughput.write();
+    let bucket = throughput.current_bucket();
+    if skipped {
+        bucket.verifications_skipped += 1;
+    } else {
+        bucket.verifications += 1;
+    }
+}
+
+/// Ingestion counts for a relay over the last `INGEST_WINDOW_MINUTES` minutes
+pub fn recent(url: &RelayUrl) -> IngestCounts {
+    match GLOBALS.relay_ingest.get(url) {
+        Some(ingest) => ingest.recent(),
+        None => IngestCounts::default(),
+    }
+}
+
+/// Ingestion counts for a relay over all time, including counts not yet saved
+pub fn totals(relay: &Relay) -> IngestCounts {
+    let mut counts = IngestCounts {
+        events_received: relay.events_received,
+        events_new: relay.events_new,
+        bytes_received: relay.bytes_received,
+    };
+    if let Some(ingest) = GLOBALS.relay_ingest.get(&relay.url) {
+        counts.add(&ingest.unsaved);
+    }
+    counts
+}
+
+/// Add the counts gathered since the last save to the relay records
+pub(crate) fn save() -> Result<(), Error> {
+    let mut pending: Vec<(RelayUrl, IngestCounts)> = Vec::new();
+    for mut entry in GLOBALS.relay_ingest.iter_mut() {
+        if !entry.unsaved.is_empty() {
+            pending.push((entry.key().clone(), std::mem::take(&mut entry.unsaved)));
+        }
+    }
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let mut txn = GLOBALS.db().get_write_txn()?;
+    for (url, counts) in pending.iter() {
+        GLOBALS.db().modify_relay(
+            url,
+            |relay| {
+                relay.events_received += counts.events_received;
+                relay.events_new += counts.events_new;
+                relay.bytes_received += counts.bytes_received;
+            },
+            Some(&mut txn),
+        )?;
+    }
+    txn.commit()?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RelayCounts {
+    events: u64,
+    bytes: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ThroughputBucket {
+    events_processed: u64,
+    processing_micros: u64,
+    verifications: u64,
+    verifications_skipped: u64,
+    relays: HashMap<RelayUrl, RelayCounts>,
+}
+
+impl ThroughputBucket {
+    fn relay(&mut self, url: &RelayUrl) -> &mut RelayCounts {
+        self.relays.entry(url.to_owned()).or_default()
+    }
+}
+
+/// Live event and download throughput, across all relays
+#[derive(Debug, Default)]
+pub struct Throughput {
+    // Per-second buckets, oldest first
+    window: VecDeque<(i64, ThroughputBucket)>,
+}
+
+impl Throughput {
+    fn current_bucket(&mut self) -> &mut ThroughputBucket {
+        let second = Unixtime::now().0;
+        while let Some((s, _)) = self.window.front() {
+            if *s <= second - THROUGHPUT_WINDOW_SECS {
+                self.window.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.window.back().map(|(s, _)| *s) != Some(second) {
+            self.window.push_back((second, ThroughputBucket::default()));
+        }
+        &mut self.window.back_mut().unwrap().1
+    }
+}
+
+/// Throughput of a single relay
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RelayThroughput {
+    /// Events the relay sent us per second
+    pub events_per_sec: f32,
+
+    /// Bytes the relay sent us per second
+    pub bytes_per_sec: f32,
+}
+
+/// Throughput over the last `THROUGHPUT_WINDOW_SECS` seconds
+///
+/// If events arrive faster than they are processed, or processing load is high,
+/// gossip is processing-bound. If load is low while little arrives, it is
+/// network-bound.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThroughputReport {
+    /// Events processed per second, from relays or elsewhere
+    pub events_processed_per_sec: f32,
+
+    /// Events received from relays per second
+    pub events_received_per_sec: f32,
+
+    /// Bytes received from relays per second
+    pub bytes_per_sec: f32,
+
+    /// Average time spent processing an event, in microseconds
+    pub micros_per_event: f32,
+
+    /// Fraction of the time spent processing events. This can exceed 1.0 when
+    /// events are processed on several threads at once.
+    pub processing_load: f32,
+
+    /// Event signatures verified per second
+    pub verifications_per_sec: f32,
+
+    /// Event signatures not verified per second, because we already had the event
+    /// or had just verified it
+    pub verifications_skipped_per_sec: f32,
+
+    /// Per relay, most bytes first
+    pub relays: Vec<(RelayUrl, RelayThroughput)>,
+}
+
+/// Throughput over the last `THROUGHPUT_WINDOW_SECS` seconds
+pub fn throughput() -> ThroughputReport {
+    let second = Unixtime::now().0;
+    let mut events_processed: u64 = 0;
+    let mut processing_micros: u64 = 0;
+    let mut verifications: u64 = 0;
+    let mut verifications_skipped: u64 = 0;
+    let mut relays: HashMap<RelayUrl, RelayCounts> = HashMap::new();
+    for (s, bucket) in GLOBALS.throughput.read().window.iter() {
+        if *s <= second - THROUGHPUT_WINDOW_SECS {
+            continue;
+        }
+        events_processed += bucket.events_processed;
+        processing_micros += bucket.processing_micros;
+        verifications += bucket.verifications;
+        verifications_skipped += bucket.verifications_skipped;
+        for (url, counts) in bucket.relays.iter() {
+            let total = relays.entry(url.to_owned()).or_default();
+            total.events += counts.events;
+            total.bytes += counts.bytes;
+        }
+    }
+
+    let secs = THROUGHPUT_WINDOW_SECS as f32;
+    let mut report = ThroughputReport {
+        events_processed_per_sec: events_processed as f32 / secs,
+        micros_per_event: if events_processed == 0 {
+            0.0
+        } else {
+            processing_micros as f32 / events_processed as f32
+        },
+        processing_load: processing_micros as f32 / (secs * 1_000_000.0),
+        verifications_per_sec: verifications as f32 / secs,
+        verifications_skipped_per_sec: verifications_skipped as f32 / secs,
+        ..Default::default()
+    };
+    for (url, counts) in relays.drain() {
+        report.events_received_per_sec += counts.events as f32 / secs;
+        report.bytes_per_sec += counts.bytes as f32 / secs;
+        report.relays.push((
+            url,
+            RelayThroughput {
+                events_per_sec: counts.events as f32 / secs,
+                bytes_per_sec: counts.bytes as f32 / secs,
+            },
+        ));
+    }
+    report
+        .relays
+        .sort_by(|a, b| b.1.bytes_per_sec.total_cmp(&a.1.bytes_per_sec));
+    report
+}
+
+/// A subscription that a minion currently has open on its relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveSubscription {
+    /// What the subscription is for, e.g. "general_feed"
+    pub handle: String,
+
+    /// The subscription id sent to the relay
+    pub id: String,
+
+    /// The overlord job the subscription belongs to
+    pub job_id: u64,
+
+    /// How many authors the filter lists
+    pub authors: usize,
+}
+
+impl std::fmt::Display for ActiveSubscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.authors > 0 {
+            write!(f, "{} ({} authors)", self.handle, self.authors)
+        } else {
+            write!(f, "{}", self.handle)
+        }
+    }
+}
+
+/// The subscriptions currently open on a relay, sorted by handle
+pub fn active_subscriptions(url: &RelayUrl) -> Vec<ActiveSubscription> {
+    match GLOBALS.relay_subscriptions.get(url) {
+        Some(subs) => subs.clone(),
+        None => vec![],
+    }
+}
+
+/// How many filters we have open on a relay, against what its NIP-11 document allows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterUsage {
+    /// Filters we have open. Each of our subscriptions carries one filter.
+    pub active: usize,
+
+    /// The relay's `max_subscriptions` limitation
+    pub max_subscriptions: Option<usize>,
+}
+
+impl FilterUsage {
+    /// The relay's limit on open subscriptions. Its `max_filters` limitation counts
+    /// the filters in each subscription, and ours only ever have one, so that one
+    /// doesn't limit us.
+    pub fn limit(&self) -> Option<usize> {
+        self.max_subscriptions
+    }
+
+    /// If we are using most (80%) of what the relay allows
+    pub fn near_limit(&self) -> bool {
+        self.limit()
+            .is_some_and(|limit| self.active * 5 >= limit * 4)
+    }
+
+    /// If we have more open than the relay allows, so some are likely being dropped
+    pub fn over_limit(&self) -> bool {
+        self.limit().is_some_and(|limit| self.active > limit)
+    }
+}
+
+impl std::fmt::Display for FilterUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.limit() {
+            Some(limit) => write!(f, "{} of {} subscriptions", self.active, limit),
+            None => write!(f, "{} subscriptions", self.active),
+        }
+    }
+}
+
+/// How many filters we have open on a relay, against what it allows
+pub fn filter_usage(relay: &Relay) -> FilterUsage {
+    let max_subscriptions = relay
+        .nip11
+        .as_ref()
+        .and_then(|nip11| nip11.limitation.as_ref())
+        .and_then(|limitation| limitation.max_subscriptions)
+        .filter(|n| *n > 0);
+    FilterUsage {
+        active: GLOBALS
+            .relay_subscriptions
+            .get(&relay.url)
+            .map(|subs| subs.len())
+            .unwrap_or(0),
+        max_subscriptions,
+    }
+}
+
+/// Called by minions when their subscriptions change. Warns once when a relay's
+/// subscription limit is approached or exceeded, and again if it is crossed anew.
+pub(crate) fn check_filter_usage(url: &RelayUrl) {
+    let relay = match GLOBALS.db().read_relay(url) {
+        Ok(Some(relay)) => relay,
+        _ => return,
+    };
+    let usage = filter_usage(&relay);
+
+    if !usage.near_limit() {
+        GLOBALS.relays_near_filter_limit.remove(url);
+        return;
+    }
+
+    let over = usage.over_limit();
+    let warned_over = GLOBALS.relays_near_filter_limit.insert(url.clone(), over);
+    if warned_over == Some(over) || warned_over == Some(true) {
+        return;
+    }
+
+    let message = if over {
+        format!(
+            "{} allows {} subscriptions but we have {} open, so some of your feeds may be incomplete",
+            url,
+            usage.limit().unwrap_or(0),
+            usage.active
+        )
+    } else {
+        format!("{} is nearing its subscription limit ({})", url, usage)
+    };
+    tracing::warn!("{}", message);
+    GLOBALS.status_queue.write().write(message);
+}
@@ -4,6 +4,80 @@ use crate::relay;
 use crate::storage::types::ScoreFactors;
 use dashmap::DashMap;
 pub use nostr_types::{PublicKey, RelayUrl, RelayUsage, Unixtime};
+use parking_lot::Mutex;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::time::Duration;
+
+// How much more a boosted relay's score counts when picking
+const BOOST_FACTOR: f32 = 2.0;
+
+// How many decisions the picker remembers
+const DECISION_LOG_SIZE: usize = 200;
+
+/// What the RelayPicker decided about a relay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelayDecisionKind {
+    /// The relay won a pick with this score and was assigned the pubkeys
+    Picked { score: f32 },
+
+    /// The relay disconnected and its pubkeys went back to be picked again
+    Disconnected { penalty_seconds: i64 },
+
+    /// Garbage collection removed the pubkeys (no longer followed) from the relay,
+    /// and maybe left it with nothing to do
+    Collected { idle: bool },
+}
+
+/// A record of one RelayPicker decision, for working out why gossip connected
+/// to or dropped a relay
+#[derive(Debug, Clone)]
+pub struct RelayDecision {
+    pub when: Unixtime,
+    pub relay_url: RelayUrl,
+    pub kind: RelayDecisionKind,
+
+    /// The pubkeys the decision was about
+    pub pubkeys: Vec<PublicKey>,
+}
+
+impl fmt::Display for RelayDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            RelayDecisionKind::Picked { score } => write!(
+                f,
+                "picked {} for {} people (score {:.2})",
+                self.relay_url,
+                self.pubkeys.len(),
+                score
+            ),
+            RelayDecisionKind::Disconnected { penalty_seconds } => {
+                write!(
+                    f,
+                    "{} disconnected, {} people to pick again",
+                    self.relay_url,
+                    self.pubkeys.len()
+                )?;
+                if penalty_seconds > 0 {
+                    write!(f, ", excluded for {}s", penalty_seconds)?;
+                }
+                Ok(())
+            }
+            RelayDecisionKind::Collected { idle } => {
+                write!(
+                    f,
+                    "dropped {} unfollowed people from {}",
+                    self.pubkeys.len(),
+                    self.relay_url
+                )?;
+                if idle {
+                    write!(f, ", leaving it idle")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
 
 /// A RelayAssignment is a record of a relay which is serving (or will serve) the general
 /// feed for a set of public keys.
@@ -47,9 +121,16 @@ pub struct RelayPicker {
     excluded_relays: DashMap<RelayUrl, i64>,
 
     /// For each followed pubkey that still needs assignments, the number of relay
-    /// assignments it is seeking.  These start out at get_num_relays_per_person()
+    /// assignments it is seeking.  These start out at feed_relays_per_person
     /// (if the person doesn't have that many relays, it will do the best it can)
     pubkey_counts: DashMap<PublicKey, usize>,
+
+    /// Relays the user is actively browsing through. They are preferred when picking
+    /// and are not garbage collected until the time in the value.
+    boosted_relays: DashMap<RelayUrl, i64>,
+
+    /// The most recent decisions, oldest first
+    decisions: Mutex<VecDeque<RelayDecision>>,
 }
 
 impl RelayPicker {
@@ -71,6 +152,7 @@ impl RelayPicker {
         self.excluded_relays.clear();
         self.pubkey_counts.clear();
         self.person_relay_scores.clear();
+        self.boosted_relays.clear();
 
         self.refresh_person_relay_scores_inner(true).await?;
 
@@ -94,7 +176,7 @@ impl RelayPicker {
         // Add that the need relays
         self.pubkey_counts.insert(
             pubkey,
-            GLOBALS.db().read_setting_num_relays_per_person() as usize,
+            GLOBALS.db().read_setting_feed_relays_per_person() as usize,
         );
 
         Ok(())
@@ -131,21 +213,31 @@ impl RelayPicker {
             let assignment = elem.value_mut();
 
             // Remove all pubkeys we no longer follow
+            let mut removed: Vec<PublicKey> = Vec::new();
             let mut index = 0;
             while let Some(key) = assignment.pubkeys.get(index) {
                 if followed.binary_search(key).is_err() {
                     // that key is not followed.
-                    assignment.pubkeys.swap_remove(index);
+                    removed.push(assignment.pubkeys.swap_remove(index));
                     // don't bump index, it is now the next one slid back.
                 } else {
                     index += 1;
                 }
             }
 
-            // If assignment is now empty, save as an idle relay
-            if assignment.pubkeys.is_empty() {
+            // If assignment is now empty, save as an idle relay (unless boosted)
+            let is_idle = assignment.pubkeys.is_empty() && !self.is_boosted(&assignment.relay_url);
+            if is_idle {
                 idle.push(assignment.relay_url.clone());
             }
+
+            if !removed.is_empty() {
+                self.record(
+                    &assignment.relay_url,
+                    RelayDecisionKind::Collected { idle: is_idle },
+                    removed,
+                );
+            }
         }
 
         Ok(idle)
@@ -183,7 +275,7 @@ impl RelayPicker {
             if initialize_counts {
                 self.pubkey_counts.insert(
                     *pubkey,
-                    GLOBALS.db().read_setting_num_relays_per_person() as usize,
+                    GLOBALS.db().read_setting_feed_relays_per_person() as usize,
                 );
             }
         }
@@ -215,9 +307,30 @@ impl RelayPicker {
                     .and_modify(|e| *e += 1)
                     .or_insert(1);
             }
+            self.record(
+                url,
+                RelayDecisionKind::Disconnected { penalty_seconds },
+                assignment.pubkeys,
+            );
         }
     }
 
+    /// Prefer a relay when picking and keep it from being garbage collected for
+    /// `duration`. Normal scoring resumes afterwards. A relay in the penalty box
+    /// stays there, it is only preferred once its exclusion expires.
+    pub fn boost(&self, url: &RelayUrl, duration: Duration) {
+        let until = Unixtime::now().0 + duration.as_secs() as i64;
+        self.boosted_relays
+            .entry(url.to_owned())
+            .and_modify(|e| *e = (*e).max(until))
+            .or_insert(until);
+    }
+
+    /// Whether a relay is boosted right now
+    pub fn is_boosted(&self, url: &RelayUrl) -> bool {
+        matches!(self.boosted_relays.get(url), Some(until) if *until > Unixtime::now().0)
+    }
+
     /// Create the next assignment, and return the `RelayUrl` that has it.
     /// You should probably immediately call `get_relay_assignment()` with that `RelayUrl`
     /// to get the newly created assignment. The caller is responsible for making that
@@ -231,6 +344,7 @@ impl RelayPicker {
         // Maybe include excluded relays
         let now = Unixtime::now().0;
         self.excluded_relays.retain(|_, v| *v > now);
+        self.boosted_relays.retain(|_, v| *v > now);
 
         if self.pubkey_counts.is_empty() {
             return Err(ErrorKind::NoPeopleLeft.into());
@@ -285,7 +399,11 @@ impl RelayPicker {
 
                 // Add the score
                 if let Some(mut entry) = scoreboard.get_mut(relay) {
-                    *entry += score;
+                    *entry += if self.boosted_relays.contains_key(relay) {
+                        score * BOOST_FACTOR
+                    } else {
+                        *score
+                    };
                 }
             }
         }
@@ -358,6 +476,14 @@ impl RelayPicker {
         // Only keep pubkey_counts that are still > 0
         self.pubkey_counts.retain(|_, count| *count > 0);
 
+        self.record(
+            &winning_url,
+            RelayDecisionKind::Picked {
+                score: winning_score,
+            },
+            covered_public_keys.clone(),
+        );
+
         let assignment = RelayAssignment {
             relay_url: winning_url.clone(),
             pubkeys: covered_public_keys,
@@ -406,4 +532,62 @@ impl RelayPicker {
     pub fn pubkey_counts_iter(&self) -> dashmap::iter::Iter<'_, PublicKey, usize> {
         self.pubkey_counts.iter()
     }
+
+    /// The followed `PublicKey`s that are not assigned to any relay, so none of
+    /// their events are coming in.
+    pub fn uncovered_followed(&self) -> Vec<PublicKey> {
+        let mut assigned: HashSet<PublicKey> = HashSet::new();
+        for elem in self.relay_assignments.iter() {
+            assigned.extend(elem.value().pubkeys.iter().copied());
+        }
+
+        let mut uncovered: Vec<PublicKey> = GLOBALS
+            .people
+            .get_subscribed_pubkeys()
+            .into_iter()
+            .filter(|pk| !assigned.contains(pk))
+            .collect();
+        uncovered.sort();
+        uncovered
+    }
+
+    /// The most recent decisions, newest first
+    pub fn decisions(&self) -> Vec<RelayDecision> {
+        self.decisions.lock().iter().rev().cloned().collect()
+    }
+
+    fn record(&self, url: &RelayUrl, kind: RelayDecisionKind, pubkeys: Vec<PublicKey>) {
+        let decision = RelayDecision {
+            when: Unixtime::now(),
+            relay_url: url.to_owned(),
+            kind,
+            pubkeys,
+        };
+        tracing::debug!("RelayPicker {}", decision);
+
+        let mut decisions = self.decisions.lock();
+        if decisions.len() >= DECISION_LOG_SIZE {
+            decisions.pop_front();
+        }
+        decisions.push_back(decision);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decision_log_is_bounded() {
+        let picker = RelayPicker::default();
+        let url =
+            |i: usize| RelayUrl::try_from_str(&format!("wss://relay{}.example.com", i)).unwrap();
+        for i in 0..DECISION_LOG_SIZE + 5 {
+            picker.record(&url(i), RelayDecisionKind::Picked { score: 1.0 }, vec![]);
+        }
+        let decisions = picker.decisions();
+        assert_eq!(decisions.len(), DECISION_LOG_SIZE);
+        assert_eq!(decisions[0].relay_url, url(DECISION_LOG_SIZE + 4));
+        assert_eq!(decisions[DECISION_LOG_SIZE - 1].relay_url, url(5));
+    }
 }
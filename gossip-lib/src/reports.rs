@@ -0,0 +1,172 @@
+//! NIP-56 reports and NIP-32 labels
+//!
+//! A report (kind 1984) names a person in a `p` tag, and one of their events in
+//! an `e` tag if it is about an event, with the type of report as the third entry
+//! of the tag. A label (kind 1985) puts its `l` tags on the events, addresses and
+//! people it tags.
+//!
+//! Anybody can publish these, so only the ones by people we follow (and our own)
+//! are counted.
+
+use crate::globals::GLOBALS;
+use crate::people::PersonList;
+use nostr_types::{Event, EventKind, Id, ParsedTag, PublicKey, Unixtime};
+
+/// The types of report NIP-56 defines
+pub const REPORT_TYPES: [&str; 7] = [
+    "nudity",
+    "malware",
+    "profanity",
+    "illegal",
+    "spam",
+    "impersonation",
+    "other",
+];
+
+/// Whether we count the reports and labels of this person
+pub fn is_trusted_labeler(pubkey: PublicKey) -> bool {
+    Some(pubkey) == GLOBALS.identity.public_key()
+        || matches!(
+            GLOBALS
+                .db()
+                .is_person_in_list(&pubkey, PersonList::Followed),
+            Ok(true)
+        )
+}
+
+/// A report of an event or a person
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub by: PublicKey,
+    pub report_type: String,
+
+    /// Anything the reporter said about it
+    pub reason: String,
+
+    pub created_at: Unixtime,
+}
+
+impl Report {
+    /// The report `event` makes about the event `id`, if it is a valid one. The
+    /// report must also name the author of the event, if we know who that is.
+    pub(crate) fn of_event(event: &Event, id: Id, author: Option<PublicKey>) -> Option<Report> {
+        if event.kind != EventKind::Reporting {
+            return None;
+        }
+
+        let tag = event.tags.iter().find(
+            |t| matches!(t.parse(), Ok(ParsedTag::Event { id: tagged, .. }) if tagged == id),
+        )?;
+
+        let names_author = event.tags.iter().any(|t| match t.parse() {
+            Ok(ParsedTag::Pubkey { pubkey, .. }) => author.is_none() || Some(pubkey) == author,
+            _ => false,
+        });
+        if !names_author {
+            return None;
+        }
+
+        Self::new(event, tag.get_index(2))
+    }
+
+    /// The report `event` makes about the person `pubkey` themselves, if it is a
+    /// valid one. Reports of their events are not reports of them.
+    pub(crate) fn of_person(event: &Event, pubkey: PublicKey) -> Option<Report> {
+        if event.kind != EventKind::Reporting {
+            return None;
+        }
+        if event.tags.iter().any(|t| t.tagname() == "e") {
+            return None;
+        }
+
+        let tag = event.tags.iter().find(
+            |t| matches!(t.parse(), Ok(ParsedTag::Pubkey { pubkey: tagged, .. }) if tagged == pubkey),
+        )?;
+
+        Self::new(event, tag.get_index(2))
+    }
+
+    fn new(event: &Event, report_type: &str) -> Option<Report> {
+        if !REPORT_TYPES.contains(&report_type) {
+            return None;
+        }
+        Some(Report {
+            by: event.pubkey,
+            report_type: report_type.to_owned(),
+            reason: event.content.clone(),
+            created_at: event.created_at,
+        })
+    }
+}
+
+/// How many people made each type of report, most reported first
+pub fn count_reports(reports: &[Report]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for report in reports {
+        match counts.iter_mut().find(|(t, _)| *t == report.report_type) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((report.report_type.clone(), 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+}
+
+/// A label put on an event or a person
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub by: PublicKey,
+    pub label: String,
+
+    /// Labels that don't name a namespace are in "ugc" (user generated content)
+    pub namespace: String,
+
+    pub created_at: Unixtime,
+}
+
+impl Label {
+    /// The labels `event` puts on the event `id`
+    pub(crate) fn of_event(event: &Event, id: Id) -> Vec<Label> {
+        let tagged = event
+            .tags
+            .iter()
+            .any(|t| matches!(t.parse(), Ok(ParsedTag::Event { id: tagged, .. }) if tagged == id));
+        if tagged {
+            Self::all_in(event)
+        } else {
+            vec![]
+        }
+    }
+
+    /// The labels `event` puts on the person `pubkey`
+    pub(crate) fn of_person(event: &Event, pubkey: PublicKey) -> Vec<Label> {
+        let tagged = event.tags.iter().any(
+            |t| matches!(t.parse(), Ok(ParsedTag::Pubkey { pubkey: tagged, .. }) if tagged == pubkey),
+        );
+        if tagged {
+            Self::all_in(event)
+        } else {
+            vec![]
+        }
+    }
+
+    fn all_in(event: &Event) -> Vec<Label> {
+        if event.kind != EventKind::Label {
+            return vec![];
+        }
+        event
+            .tags
+            .iter()
+            .filter(|t| t.tagname() == "l" && !t.value().is_empty())
+            .map(|t| Label {
+                by: event.pubkey,
+                label: t.value().to_owned(),
+                namespace: match t.get_index(2) {
+                    "" => "ugc".to_owned(),
+                    namespace => namespace.to_owned(),
+                },
+                created_at: event.created_at,
+            })
+            .collect()
+    }
+}
@@ -7,8 +7,13 @@ use crate::relay;
 use crate::relay::Relay;
 use dashmap::DashMap;
 use nostr_types::{Event, EventReference, Id, PublicKey, RelayUrl, Unixtime};
+use std::collections::HashSet;
 use std::time::Duration;
 
+/// How many missing ancestors we will fetch, one after another, when climbing a
+/// thread before giving up
+const MAX_CLIMB_DEPTH: usize = 32;
+
 #[derive(Debug, Clone)]
 pub enum SeekState {
     WaitingRelayList(PublicKey),
@@ -25,22 +30,27 @@ pub struct SeekData {
 
     /// Once we get the event, should we climb it's parents to the root?
     pub climb: bool,
+
+    /// How many missing ancestors were fetched while climbing to get here
+    pub depth: usize,
 }
 
 impl SeekData {
-    fn new_event(climb: bool) -> SeekData {
+    fn new_event(climb: bool, depth: usize) -> SeekData {
         SeekData {
             start: Unixtime::now(),
             state: SeekState::WaitingEvent,
             climb,
+            depth,
         }
     }
 
-    fn new_relay_list(pubkey: PublicKey, climb: bool) -> SeekData {
+    fn new_relay_list(pubkey: PublicKey, climb: bool, depth: usize) -> SeekData {
         SeekData {
             start: Unixtime::now(),
             state: SeekState::WaitingRelayList(pubkey),
             climb,
+            depth,
         }
     }
 }
@@ -93,7 +103,7 @@ impl Seeker {
         Self::minion_seek_event_at_relays(id, relays);
 
         // Remember when we asked
-        self.events.insert(id, SeekData::new_event(climb));
+        self.events.insert(id, SeekData::new_event(climb, 0));
 
         Ok(())
     }
@@ -128,7 +138,7 @@ impl Seeker {
             Freshness::NeverSought => {
                 Self::minion_seek_relay_list(author);
                 self.events
-                    .insert(id, SeekData::new_relay_list(author, climb));
+                    .insert(id, SeekData::new_relay_list(author, climb, 0));
             }
             Freshness::Stale => {
                 // Seek the relay list because it is stale, but don't let that hold us up
@@ -136,12 +146,12 @@ impl Seeker {
                 Self::minion_seek_relay_list(author);
                 let relays = relay::get_some_pubkey_outboxes(author)?;
                 Self::minion_seek_event_at_relays(id, relays);
-                self.events.insert(id, SeekData::new_event(climb));
+                self.events.insert(id, SeekData::new_event(climb, 0));
             }
             Freshness::Fresh => {
                 let relays = relay::get_some_pubkey_outboxes(author)?;
                 Self::minion_seek_event_at_relays(id, relays);
-                self.events.insert(id, SeekData::new_event(climb));
+                self.events.insert(id, SeekData::new_event(climb, 0));
             }
         }
 
@@ -156,7 +166,7 @@ impl Seeker {
             }
         }
         Self::minion_seek_event_at_relays(id, relays);
-        self.events.insert(id, SeekData::new_event(climb));
+        self.events.insert(id, SeekData::new_event(climb, 0));
     }
 
     /// Inform the seeker that an author's relay list has just arrived
@@ -172,7 +182,7 @@ impl Seeker {
                     let id = *refmutmulti.key();
                     if let Ok(relays) = relay::get_some_pubkey_outboxes(author) {
                         Self::minion_seek_event_at_relays(id, relays);
-                        updates.push((id, SeekData::new_event(data.climb)));
+                        updates.push((id, SeekData::new_event(data.climb, data.depth)));
                     }
                 }
             }
@@ -189,28 +199,88 @@ impl Seeker {
         if let Some((_, data)) = self.events.remove(&event.id) {
             // Possibly seek it's parent
             if data.climb {
-                let mut eref = EventReference::Id {
-                    id: event.id,
-                    author: Some(event.pubkey),
-                    relays: vec![],
-                    marker: None,
-                };
-                while let Some(event) = GLOBALS.db().read_event_reference(&eref)? {
-                    if let Some(parent_eref) = event.replies_to() {
-                        eref = parent_eref;
-                        continue;
-                    } else {
-                        // no missing parent to fetch
+                self.climb(event, data.depth)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Walk up the local ancestors of `event` and seek the first one that is missing
+    fn climb(&self, event: &Event, depth: usize) -> Result<(), Error> {
+        if depth >= MAX_CLIMB_DEPTH {
+            tracing::debug!(
+                "Stopped climbing the thread of id={} after {} missing ancestors",
+                event.id.as_hex_string(),
+                depth
+            );
+            return Ok(());
+        }
+
+        // The event whose parent is missing, and the reference to that parent
+        let mut child = event.clone();
+        let mut visited: HashSet<Id> = HashSet::new();
+        visited.insert(child.id);
+        let parent_eref = loop {
+            let parent_eref = match child.replies_to() {
+                Some(eref) => eref,
+                None => return Ok(()), // reached the root
+            };
+            match GLOBALS.db().read_event_reference(&parent_eref)? {
+                Some(parent) => {
+                    if !visited.insert(parent.id) {
+                        tracing::warn!(
+                            "Reply cycle detected at id={}, not climbing further",
+                            parent.id.as_hex_string()
+                        );
                         return Ok(());
                     }
+                    child = parent;
+                }
+                None => break parent_eref,
+            }
+        };
+
+        // Relays where the child was seen may well have its parent
+        let feed_relays_per_person = GLOBALS.db().read_setting_feed_relays_per_person();
+        let seen_on: Vec<RelayUrl> = GLOBALS
+            .db()
+            .get_event_seen_on_relay(child.id)?
+            .drain(..)
+            .take(feed_relays_per_person as usize + 1)
+            .map(|(url, _time)| url)
+            .collect();
+
+        match parent_eref {
+            EventReference::Id {
+                id,
+                author,
+                mut relays,
+                ..
+            } => {
+                if visited.contains(&id) {
+                    return Ok(());
+                }
+                if !relays.is_empty() {
+                    relays.extend(seen_on);
+                    relays.sort();
+                    relays.dedup();
+                    self.seek_id_and_relays(id, relays, true);
+                } else if let Some(author) = author {
+                    self.seek_id_and_author(id, author, seen_on, true)?;
+                } else {
+                    self.seek_id(id, seen_on, true)?;
                 }
 
-                // FIXME make better use of hints, author hints, etc.
-                // we have lost relay information along the way.
-                if let EventReference::Id { id, .. } = eref {
-                    self.seek_id(id, vec![], true)?;
+                // Carry the depth over so a long chain of missing ancestors ends
+                if let Some(mut data) = self.events.get_mut(&id) {
+                    data.depth = depth + 1;
                 }
             }
+            EventReference::Addr(ea) => {
+                // Addressable parents are fetched but not climbed past
+                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::FetchNAddr(ea));
+            }
         }
 
         Ok(())
@@ -237,7 +307,8 @@ impl Seeker {
                         Freshness::Fresh | Freshness::Stale => {
                             if let Ok(relays) = relay::get_some_pubkey_outboxes(author) {
                                 Self::minion_seek_event_at_relays(id, relays);
-                                updates.push((id, Some(SeekData::new_event(data.climb))));
+                                updates
+                                    .push((id, Some(SeekData::new_event(data.climb, data.depth))));
                                 continue;
                             }
                         }
@@ -247,7 +318,7 @@ impl Seeker {
                     // If it has been 15 seconds, give up the wait and seek from our READ relays
                     if now - data.start > Duration::from_secs(15) {
                         Self::minion_seek_event_at_our_read_relays(id);
-                        updates.push((id, Some(SeekData::new_event(data.climb))));
+                        updates.push((id, Some(SeekData::new_event(data.climb, data.depth))));
                     }
 
                     // Otherwise keep waiting
@@ -0,0 +1,68 @@
+use super::Storage;
+use crate::error::Error;
+use nostr_types::{Event, Id};
+use speedy::Readable;
+use std::ops::Bound;
+
+impl Storage {
+    // Audit -------------------------------------------------------
+
+    /// Re-verify up to `batch` stored events, starting just after `after` (or from
+    /// the beginning if `None`). Events that fail verification, or that cannot even
+    /// be parsed, are moved out of the events table and into the quarantine table.
+    ///
+    /// Returns the number of events checked, the number quarantined, and the id
+    /// to pass as `after` to continue. This is `None` once every event has been
+    /// audited.
+    pub fn audit_events(
+        &self,
+        after: Option<Id>,
+        batch: usize,
+    ) -> Result<(usize, usize, Option<Id>), Error> {
+        let mut checked: usize = 0;
+        let mut last: Option<Id> = None;
+        let mut failures: Vec<(Id, Vec<u8>)> = Vec::new();
+
+        {
+            let txn = self.get_read_txn()?;
+            let range: (Bound<&[u8]>, Bound<&[u8]>) = match after {
+                Some(ref id) => (Bound::Excluded(id.as_slice()), Bound::Unbounded),
+                None => (Bound::Unbounded, Bound::Unbounded),
+            };
+            for result in self.db_events()?.range(&txn, &range)? {
+                let (key, val) = result?;
+                let id = Id(key.try_into()?);
+                let good = match Event::read_from_buffer(val) {
+                    Ok(event) => event.id == id && event.verify(None).is_ok(),
+                    Err(_) => false,
+                };
+                if !good {
+                    failures.push((id, val.to_owned()));
+                }
+                checked += 1;
+                last = Some(id);
+                if checked >= batch {
+                    break;
+                }
+            }
+        }
+
+        // If we ran out of events before filling the batch, we are done
+        if checked < batch {
+            last = None;
+        }
+
+        let quarantined = failures.len();
+        if !failures.is_empty() {
+            let mut txn = self.get_write_txn()?;
+            for (id, bytes) in failures.drain(..) {
+                tracing::warn!("AUDIT: quarantining event {}", id.as_hex_string());
+                self.quarantine_event_bytes(id, &bytes, Some(&mut txn))?;
+                self.delete_event(id, Some(&mut txn))?;
+            }
+            txn.commit()?;
+        }
+
+        Ok((checked, quarantined, last))
+    }
+}
@@ -0,0 +1,86 @@
+use crate::error::Error;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::{Id, Unixtime};
+use std::sync::Mutex;
+
+// Id -> Unixtime
+//   key: id.as_slice()
+//   val: unixtime.0.to_be_bytes()
+
+static CLAMPED_TIMES1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut CLAMPED_TIMES1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_clamped_times1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = CLAMPED_TIMES1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = CLAMPED_TIMES1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = CLAMPED_TIMES1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("clamped_times")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                CLAMPED_TIMES1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_clamped_time1<'a>(
+        &'a self,
+        id: Id,
+        time: Unixtime,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let bytes = time.0.to_be_bytes();
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_clamped_times1()?.put(txn, id.as_slice(), &bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn read_clamped_time1(&self, id: Id) -> Result<Option<Unixtime>, Error> {
+        let txn = self.get_read_txn()?;
+        Ok(self
+            .db_clamped_times1()?
+            .get(&txn, id.as_slice())?
+            .map(|bytes| Unixtime(i64::from_be_bytes(bytes[..8].try_into().unwrap()))))
+    }
+
+    pub(crate) fn delete_clamped_time1<'a>(
+        &'a self,
+        id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_clamped_times1()?.delete(txn, id.as_slice())?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+}
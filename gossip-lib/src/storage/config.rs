@@ -0,0 +1,296 @@
+//! Exporting and importing a portable configuration
+//!
+//! The configuration is a JSON document holding the relays (with their usage bits
+//! and ranks), the settings, and references to the user's follow and mute lists.
+//! It never includes the private key (or the public key), so it is safe to copy
+//! between machines.
+
+use super::Storage;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use heed::RwTxn;
+use nostr_types::{EventKind, Id, RelayUrl};
+use paste::paste;
+use serde::{Deserialize, Serialize};
+use serde_json::map::Map;
+use serde_json::value::Value;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+/// The version of the configuration format written by this version of gossip
+const CONFIG_VERSION: u32 = 1;
+
+// Relays with no usage bits and this rank are not worth exporting
+const DEFAULT_RANK: u64 = 3;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PortableConfig {
+    version: u32,
+    relays: Vec<PortableRelay>,
+    settings: Map<String, Value>,
+    follow_list: Option<Id>,
+    mute_list: Option<Id>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PortableRelay {
+    url: RelayUrl,
+    usage_bits: u64,
+    rank: u64,
+}
+
+// Settings that are exported. Anything identifying the user is left out.
+macro_rules! portable_settings {
+    ($($field:ident),* $(,)?) => {
+        paste! {
+            impl Storage {
+                fn export_settings(&self) -> Result<Map<String, Value>, Error> {
+                    let mut map = Map::new();
+                    $(
+                        map.insert(
+                            stringify!($field).to_owned(),
+                            serde_json::to_value(self.[<read_setting_ $field>]())?,
+                        );
+                    )*
+                    Ok(map)
+                }
+
+                fn import_settings<'a>(
+                    &'a self,
+                    settings: &Map<String, Value>,
+                    replace: bool,
+                    txn: &mut RwTxn<'a>,
+                ) -> Result<(), Error> {
+                    $(
+                        if let Some(value) = settings.get(stringify!($field)) {
+                            // When merging, keep anything already changed from the default
+                            if replace
+                                || self.[<read_setting_ $field>]()
+                                    == Self::[<get_default_setting_ $field>]()
+                            {
+                                match serde_json::from_value(value.clone()) {
+                                    Ok(v) => self.[<write_setting_ $field>](&v, Some(txn))?,
+                                    Err(e) => tracing::warn!(
+                                        "Ignoring setting {}: {}",
+                                        stringify!($field),
+                                        e
+                                    ),
+                                }
+                            }
+                        }
+                    )*
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+portable_settings!(
+    log_n,
+    login_at_startup,
+    auto_lock_minutes,
+    offline,
+    quiet_hours,
+    quiet_hours_start,
+    quiet_hours_end,
+    load_avatars,
+    load_media,
+    prefetch_profile_media,
+    metered_connection,
+    check_nip05,
+    wgpu_renderer,
+    automatically_fetch_metadata,
+    relay_connection_requires_approval,
+    relay_auth_requires_approval,
+    auto_advertise_on_change,
+    feed_relays_per_person,
+    metadata_relays_per_person,
+    max_relays,
+    num_relays_for_counting,
+    num_relays_for_hashtags,
+    load_more_count,
+    reposts,
+    show_long_form,
+    include_replies,
+    show_mentions,
+    enable_picture_events,
+    enable_comments,
+    direct_messages,
+    future_allowance_secs,
+    future_event_action,
+    hide_mutes_entirely,
+    reactions,
+    enable_zap_receipts,
+    show_media,
+    approve_content_warning,
+    show_deleted_events,
+    retain_deleted_events,
+    pow,
+    max_reply_p_tags,
+    post_retry_attempts,
+    set_client_tag,
+    strip_url_tracking,
+    set_user_agent,
+    delegatee_tag,
+    max_fps,
+    recompute_feed_periodically,
+    feed_recompute_interval_ms,
+    feed_ordering,
+    feed_thread_scroll_to_main_event,
+    theme_variant,
+    dark_mode,
+    follow_os_dark_mode,
+    override_dpi,
+    highlight_unread_events,
+    feed_newest_at_bottom,
+    posting_area_at_top,
+    dm_feed_newest_at_bottom,
+    dm_posting_area_at_top,
+    status_bar,
+    image_resize_algorithm,
+    inertial_scrolling,
+    mouse_acceleration,
+    frame_spinner,
+    relay_list_becomes_stale_minutes,
+    metadata_becomes_stale_minutes,
+    nip05_becomes_stale_if_valid_hours,
+    nip05_becomes_stale_if_invalid_minutes,
+    avatar_becomes_stale_hours,
+    media_becomes_stale_hours,
+    relay_info_becomes_stale_hours,
+    max_websocket_message_size_kb,
+    max_websocket_frame_size_kb,
+    websocket_accept_unmasked_frames,
+    websocket_connect_timeout_sec,
+    websocket_ping_frequency_sec,
+    restore_manual_relays,
+    fetcher_connect_timeout_sec,
+    fetcher_timeout_sec,
+    fetcher_max_requests_per_host,
+    fetcher_max_requests,
+    fetcher_max_retries,
+    fetcher_host_exclusion_on_low_error_secs,
+    fetcher_host_exclusion_on_med_error_secs,
+    fetcher_host_exclusion_on_high_error_secs,
+    prune_period_days,
+    cache_prune_period_days,
+    auto_prune,
+    auto_prune_interval_days,
+    auto_prune_min_size_mb,
+    dm_cache_size,
+    reaction_summary_threshold,
+    prune_summarized_reactions,
+    stored_kinds,
+    avoid_spam_on_unsafe_relays,
+    limit_inbox_seeking_to_inbox_relays,
+    fetch_inbox_on_feed_relays,
+    apply_spam_filter_on_incoming_events,
+    apply_spam_filter_on_threads,
+    apply_spam_filter_on_inbox,
+    apply_spam_filter_on_global,
+    blossom_servers,
+    undo_send_seconds,
+);
+
+impl Storage {
+    /// Write the relays, settings and follow/mute list references as a JSON
+    /// configuration document. The private key is never included.
+    pub fn export_config<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut relays: Vec<PortableRelay> = self
+            .filter_relays(|r| r.has_any_usage_bit() || r.rank != DEFAULT_RANK)?
+            .drain(..)
+            .map(|r| PortableRelay {
+                usage_bits: r.get_usage_bits(),
+                rank: r.rank,
+                url: r.url,
+            })
+            .collect();
+        relays.sort_by(|a, b| a.url.as_str().cmp(b.url.as_str()));
+
+        let (follow_list, mute_list) = match GLOBALS.identity.public_key() {
+            Some(pk) => (
+                self.get_replaceable_event(EventKind::ContactList, pk, "")?
+                    .map(|e| e.id),
+                self.get_replaceable_event(EventKind::MuteList, pk, "")?
+                    .map(|e| e.id),
+            ),
+            None => (None, None),
+        };
+
+        let config = PortableConfig {
+            version: CONFIG_VERSION,
+            relays,
+            settings: self.export_settings()?,
+            follow_list,
+            mute_list,
+        };
+
+        serde_json::to_writer_pretty(writer, &config)?;
+        Ok(())
+    }
+
+    /// Read a configuration written by [export_config](Storage::export_config).
+    ///
+    /// By default this merges: relays gain the imported usage bits and keep their
+    /// rank, and only settings still at their default are changed. With `replace`
+    /// the imported relays and settings overwrite what is here, and relays missing
+    /// from the configuration lose their usage bits.
+    ///
+    /// Returns the follow and mute list events referenced by the configuration that
+    /// are not in local storage, so they can be fetched.
+    pub fn import_config<R: Read>(&self, reader: R, replace: bool) -> Result<Vec<Id>, Error> {
+        let config: PortableConfig = serde_json::from_reader(reader)?;
+        if config.version > CONFIG_VERSION {
+            tracing::warn!(
+                "Configuration is version {}, newer than {}. Unknown parts will be ignored.",
+                config.version,
+                CONFIG_VERSION
+            );
+        }
+
+        let mut txn = self.get_write_txn()?;
+
+        let mut imported: HashSet<RelayUrl> = HashSet::new();
+        for pr in config.relays.iter() {
+            if Self::url_is_banned(&pr.url) {
+                continue;
+            }
+            let url = crate::relay::canonical_url(&pr.url);
+            let is_new = self.read_relay(&url)?.is_none();
+            let mut relay = self.read_or_create_relay(&url, Some(&mut txn))?;
+            if replace {
+                relay.clear_usage_bits(u64::MAX);
+            }
+            relay.set_usage_bits(pr.usage_bits);
+            if replace || is_new {
+                relay.rank = pr.rank;
+            }
+            self.write_relay(&relay, Some(&mut txn))?;
+            imported.insert(url);
+        }
+        if replace {
+            self.modify_all_relays(
+                |relay| {
+                    if !imported.contains(&relay.url) {
+                        relay.clear_usage_bits(u64::MAX);
+                    }
+                },
+                Some(&mut txn),
+            )?;
+        }
+
+        self.import_settings(&config.settings, replace, &mut txn)?;
+
+        txn.commit()?;
+
+        let mut missing: Vec<Id> = Vec::new();
+        for id in [config.follow_list, config.mute_list].into_iter().flatten() {
+            if self.read_event(id)?.is_none() {
+                missing.push(id);
+            }
+        }
+        Ok(missing)
+    }
+}
@@ -44,7 +44,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -113,7 +113,7 @@ impl Storage {
     pub fn read_all_configured_handlers(
         &self,
     ) -> Result<Vec<(EventKind, HandlerKey, bool, bool)>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut output: Vec<(EventKind, HandlerKey, bool, bool)> = Vec::new();
         let iter = self.db_configured_handlers()?.iter(&txn)?;
         for result in iter {
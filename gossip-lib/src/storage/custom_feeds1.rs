@@ -0,0 +1,109 @@
+use crate::error::{Error, ErrorKind};
+use crate::storage::types::CustomFeed1;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use speedy::{Readable, Writable};
+use std::sync::Mutex;
+
+// Name -> CustomFeed1
+//   key: key!(name.as_bytes())
+//   val: custom_feed.write_to_vec() | CustomFeed1::read_from_buffer(val)
+
+static CUSTOM_FEEDS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut CUSTOM_FEEDS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_custom_feeds1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = CUSTOM_FEEDS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = CUSTOM_FEEDS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = CUSTOM_FEEDS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("custom_feeds")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                CUSTOM_FEEDS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_custom_feed1<'a>(
+        &'a self,
+        custom_feed: &CustomFeed1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = key!(custom_feed.name.as_bytes());
+        if key.is_empty() {
+            return Err(ErrorKind::Empty("custom feed name".to_owned()).into());
+        }
+        let bytes = custom_feed.write_to_vec()?;
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_custom_feeds1()?.put(txn, key, &bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn read_custom_feed1(&self, name: &str) -> Result<Option<CustomFeed1>, Error> {
+        let key = key!(name.as_bytes());
+        if key.is_empty() {
+            return Ok(None);
+        }
+        let txn = self.get_read_txn()?;
+        match self.db_custom_feeds1()?.get(&txn, key)? {
+            Some(bytes) => Ok(Some(CustomFeed1::read_from_buffer(bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn delete_custom_feed1<'a>(
+        &'a self,
+        name: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = key!(name.as_bytes());
+        if key.is_empty() {
+            return Ok(());
+        }
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let _ = self.db_custom_feeds1()?.delete(txn, key)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn get_custom_feeds1(&self) -> Result<Vec<CustomFeed1>, Error> {
+        let txn = self.get_read_txn()?;
+        let mut output: Vec<CustomFeed1> = Vec::new();
+        for result in self.db_custom_feeds1()?.iter(&txn)? {
+            let (_key, val) = result?;
+            output.push(CustomFeed1::read_from_buffer(val)?);
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,91 @@
+use crate::error::Error;
+use crate::storage::types::Draft1;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use speedy::{Readable, Writable};
+use std::sync::Mutex;
+
+// Draft id -> Draft1
+//   key: draft.id.to_be_bytes()
+//   val: draft.write_to_vec() | Draft1::read_from_buffer(val)
+
+static DRAFTS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut DRAFTS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_drafts1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = DRAFTS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = DRAFTS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = DRAFTS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("drafts")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                DRAFTS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_draft1<'a>(
+        &'a self,
+        draft: &Draft1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = draft.id.to_be_bytes();
+        let bytes = draft.write_to_vec()?;
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_drafts1()?.put(txn, &key, &bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn delete_draft1<'a>(
+        &'a self,
+        id: u64,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = id.to_be_bytes();
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let _ = self.db_drafts1()?.delete(txn, &key)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn get_drafts1(&self) -> Result<Vec<Draft1>, Error> {
+        let txn = self.get_read_txn()?;
+        let mut output: Vec<Draft1> = Vec::new();
+        for result in self.db_drafts1()?.iter(&txn)? {
+            let (_key, val) = result?;
+            output.push(Draft1::read_from_buffer(val)?);
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::{Error, ErrorKind};
+use crate::storage::{RawDatabase, Storage, MAX_LMDB_KEY};
+use heed::types::Bytes;
+use nostr_types::{EventKind, Id, PublicKey, Unixtime};
+use sha2::Digest;
+use std::sync::Mutex;
+
+// Kind:Author:D -> Created:Id
+//
+// Only the latest event of each address is indexed.
+
+static EVENT_ADDR_INDEX_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut EVENT_ADDR_INDEX_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_event_addr_index(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = EVENT_ADDR_INDEX_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = EVENT_ADDR_INDEX_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = EVENT_ADDR_INDEX_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    .name("event_addr_index")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                EVENT_ADDR_INDEX_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+}
+
+pub struct AddrKey(Vec<u8>);
+
+impl AddrKey {
+    // A d tag longer than this is cut short and followed by its hash, to keep the
+    // key within the LMDB limit
+    const MAX_D_LEN: usize = MAX_LMDB_KEY - std::mem::size_of::<u32>() - 32;
+
+    pub fn from_parts(kind: EventKind, author: PublicKey, d: &str) -> Self {
+        let mut key: Vec<u8> = Vec::with_capacity(std::mem::size_of::<u32>() + 32 + d.len());
+        key.extend(u32::from(kind).to_be_bytes());
+        key.extend(author.as_slice());
+        if d.len() > Self::MAX_D_LEN {
+            key.extend(&d.as_bytes()[..Self::MAX_D_LEN - 32]);
+            key.extend(sha2::Sha256::digest(d.as_bytes()));
+        } else {
+            key.extend(d.as_bytes());
+        }
+        AddrKey(key)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+pub struct AddrVal(Vec<u8>);
+
+impl AddrVal {
+    pub fn from_parts(created_at: Unixtime, id: Id) -> Self {
+        let mut val: Vec<u8> = Vec::with_capacity(std::mem::size_of::<i64>() + 32);
+        val.extend(created_at.0.to_be_bytes());
+        val.extend(id.0.as_slice());
+        AddrVal(val)
+    }
+
+    pub fn into_parts(self) -> (Unixtime, Id) {
+        let created_at = Unixtime(i64::from_be_bytes(self.0[..8].try_into().unwrap()));
+        let id = Id(self.0[8..].try_into().unwrap());
+        (created_at, id)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<AddrVal, Error> {
+        if bytes.len() != std::mem::size_of::<i64>() + 32 {
+            return Err(ErrorKind::KeySizeWrong.into());
+        }
+        Ok(AddrVal(bytes.to_owned()))
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nostr_types::PrivateKey;
+
+    #[test]
+    fn test_event_addr_key() {
+        let pubkey = PrivateKey::generate().public_key();
+        let a = AddrKey::from_parts(EventKind::LongFormContent, pubkey, "a");
+        let ab = AddrKey::from_parts(EventKind::LongFormContent, pubkey, "ab");
+        assert_ne!(a.as_slice(), ab.as_slice());
+
+        let created_at = Unixtime::now();
+        let id = Id::try_from_hex_string(
+            "77f7653c67147a125cc624f695029d0557e3ab402e714680eb23dd2499f439a0",
+        )
+        .unwrap();
+        let val = AddrVal::from_bytes(AddrVal::from_parts(created_at, id).as_slice()).unwrap();
+        assert_eq!(val.into_parts(), (created_at, id));
+    }
+
+    #[test]
+    fn test_event_addr_key_long_d() {
+        let pubkey = PrivateKey::generate().public_key();
+        let long_a = format!("{}a", "x".repeat(600));
+        let long_b = format!("{}b", "x".repeat(600));
+        let a = AddrKey::from_parts(EventKind::LongFormContent, pubkey, &long_a);
+        let b = AddrKey::from_parts(EventKind::LongFormContent, pubkey, &long_b);
+        assert_eq!(a.as_slice().len(), MAX_LMDB_KEY);
+        assert_eq!(b.as_slice().len(), MAX_LMDB_KEY);
+        assert_ne!(a.as_slice(), b.as_slice());
+
+        // Short enough d tags are kept whole
+        let d = "x".repeat(AddrKey::MAX_D_LEN);
+        let key = AddrKey::from_parts(EventKind::LongFormContent, pubkey, &d);
+        assert!(key.as_slice().ends_with(d.as_bytes()));
+    }
+}
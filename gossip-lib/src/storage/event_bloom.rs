@@ -0,0 +1,224 @@
+use super::Storage;
+use crate::error::Error;
+use nostr_types::Id;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Never size the filter for fewer events than this, so a fresh database has
+// room to grow before the false-positive rate climbs.
+const MIN_CAPACITY: usize = 1 << 20;
+
+// With about 10 bits per event, 7 probes gives a false-positive rate near 1%
+const BITS_PER_EVENT: u64 = 10;
+const NUM_PROBES: u64 = 7;
+
+/// An in-memory bloom filter of the ids of events in the events table.
+///
+/// `has_event()` consults this before touching LMDB. A miss means the event is
+/// certainly not stored, which is the common case when a relay is streaming new
+/// events at us. A hit is confirmed against LMDB, because the filter can give
+/// false positives and because deleted events are never removed from it.
+///
+/// The filter is sized at startup for twice the number of stored events. If the
+/// database grows well past that, the false-positive rate rises and more hits fall
+/// through to LMDB, but answers remain correct. It is rebuilt on the next start.
+///
+/// The counters record how many LMDB reads were avoided versus performed, and are
+/// shown on the statistics page so the benefit can be observed under real load.
+pub(crate) struct EventBloom {
+    bits: Vec<AtomicU64>,
+    mask: u64,
+    lmdb_reads_avoided: AtomicU64,
+    lmdb_reads_confirmed: AtomicU64,
+    false_positives: AtomicU64,
+}
+
+impl EventBloom {
+    pub(crate) fn new(capacity: usize) -> EventBloom {
+        let num_bits = (capacity.max(MIN_CAPACITY) as u64 * BITS_PER_EVENT).next_power_of_two();
+        let mut bits = Vec::with_capacity((num_bits / 64) as usize);
+        bits.resize_with((num_bits / 64) as usize, || AtomicU64::new(0));
+        EventBloom {
+            bits,
+            mask: num_bits - 1,
+            lmdb_reads_avoided: AtomicU64::new(0),
+            lmdb_reads_confirmed: AtomicU64::new(0),
+            false_positives: AtomicU64::new(0),
+        }
+    }
+
+    // Ids are sha256 hashes, so their bytes are already uniformly distributed and
+    // can be used directly for double hashing.
+    fn probes(&self, id: Id) -> impl Iterator<Item = u64> {
+        let bytes = id.as_slice();
+        let h1 = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) | 1;
+        let mask = self.mask;
+        (0..NUM_PROBES).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) & mask)
+    }
+
+    pub(crate) fn insert(&self, id: Id) {
+        for bit in self.probes(id) {
+            self.bits[(bit / 64) as usize].fetch_or(1 << (bit % 64), Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn might_contain(&self, id: Id) -> bool {
+        self.probes(id).all(|bit| {
+            self.bits[(bit / 64) as usize].load(Ordering::Relaxed) & (1 << (bit % 64)) != 0
+        })
+    }
+}
+
+impl Storage {
+    // Event Bloom -------------------------------------------------
+
+    /// Rebuild the event id bloom filter from the events table
+    pub(crate) fn rebuild_event_bloom(&self) -> Result<(), Error> {
+        let txn = self.get_read_txn()?;
+        let db = self.db_events()?;
+        let bloom = EventBloom::new(db.len(&txn)? as usize * 2);
+        for result in db.iter(&txn)? {
+            let (key, _val) = result?;
+            if let Ok(bytes) = key.try_into() {
+                bloom.insert(Id(bytes));
+            }
+        }
+        *self.event_bloom.write() = Some(bloom);
+        Ok(())
+    }
+
+    pub(crate) fn event_bloom_insert(&self, id: Id) {
+        if let Some(bloom) = &*self.event_bloom.read() {
+            bloom.insert(id);
+        }
+    }
+
+    /// If we have the event, checking the bloom filter before LMDB
+    pub(crate) fn has_event_bloom(&self, id: Id) -> Result<bool, Error> {
+        let guard = self.event_bloom.read();
+        let bloom = match &*guard {
+            Some(bloom) => bloom,
+            None => return self.has_event3(id),
+        };
+
+        if !bloom.might_contain(id) {
+            bloom.lmdb_reads_avoided.fetch_add(1, Ordering::Relaxed);
+            return Ok(false);
+        }
+
+        bloom.lmdb_reads_confirmed.fetch_add(1, Ordering::Relaxed);
+        let has = self.has_event3(id)?;
+        if !has {
+            bloom.false_positives.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(has)
+    }
+
+    /// Event bloom filter statistics since startup: LMDB reads avoided, LMDB reads
+    /// performed, and how many of those performed were false positives.
+    pub fn event_bloom_stats(&self) -> (u64, u64, u64) {
+        match &*self.event_bloom.read() {
+            Some(bloom) => (
+                bloom.lmdb_reads_avoided.load(Ordering::Relaxed),
+                bloom.lmdb_reads_confirmed.load(Ordering::Relaxed),
+                bloom.false_positives.load(Ordering::Relaxed),
+            ),
+            None => (0, 0, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::test::TestStorage;
+
+    #[test]
+    fn test_event_bloom() {
+        let bloom = EventBloom::new(1000);
+        let ids: Vec<Id> = (0..1000)
+            .map(|i: u32| {
+                let mut bytes = [0u8; 32];
+                bytes[0..4].copy_from_slice(&i.to_le_bytes());
+                bytes[8..12].copy_from_slice(&i.wrapping_mul(2654435761).to_le_bytes());
+                Id(bytes)
+            })
+            .collect();
+
+        for id in ids.iter().take(500) {
+            bloom.insert(*id);
+        }
+
+        // No false negatives
+        for id in ids.iter().take(500) {
+            assert!(bloom.might_contain(*id));
+        }
+
+        // Few false positives
+        let false_positives = ids
+            .iter()
+            .skip(500)
+            .filter(|id| bloom.might_contain(**id))
+            .count();
+        assert!(false_positives < 10);
+    }
+
+    // Simulates a relay firehose against a populated events table and reports how
+    // many LMDB reads the filter avoided. See docs/DEVELOPING.md. Run with:
+    //   cargo test --release -p gossip-lib firehose -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn measure_event_bloom_under_firehose() {
+        const STORED: u32 = 1_000_000;
+        const INCOMING: u32 = 100_000;
+        const DUPLICATES: u32 = 20_000;
+
+        let storage = TestStorage::with_map_size("firehose", 1048576 * 1024);
+
+        let id_of = |i: u32| {
+            let mut bytes = [0u8; 32];
+            bytes[0..4].copy_from_slice(&i.to_le_bytes());
+            bytes[8..12].copy_from_slice(&i.wrapping_mul(2654435761).to_le_bytes());
+            bytes[16..20].copy_from_slice(&i.wrapping_mul(40503).to_le_bytes());
+            Id(bytes)
+        };
+
+        // Only presence is checked, so the stored values do not need to be events
+        {
+            let db = storage.db_events().unwrap();
+            let mut txn = storage.env.write_txn().unwrap();
+            for i in 0..STORED {
+                db.put(&mut txn, id_of(i).as_slice(), &[0]).unwrap();
+            }
+            txn.commit().unwrap();
+        }
+        storage.rebuild_event_bloom().unwrap();
+
+        // Mostly new events, with some we already have
+        let incoming: Vec<Id> = (0..DUPLICATES)
+            .map(id_of)
+            .chain((STORED..STORED + INCOMING - DUPLICATES).map(id_of))
+            .collect();
+
+        let start = std::time::Instant::now();
+        for id in incoming.iter() {
+            storage.has_event3(*id).unwrap();
+        }
+        let without = start.elapsed();
+
+        let start = std::time::Instant::now();
+        for id in incoming.iter() {
+            storage.has_event_bloom(*id).unwrap();
+        }
+        let with = start.elapsed();
+
+        let (avoided, performed, false_positives) = storage.event_bloom_stats();
+        println!(
+            "{} stored, {} incoming ({} duplicates): {} LMDB reads avoided, {} performed \
+             ({} false positives); {:?} without the filter, {:?} with it",
+            STORED, INCOMING, DUPLICATES, avoided, performed, false_positives, without, with
+        );
+        assert_eq!(avoided + performed, INCOMING as u64);
+        assert_eq!(performed - false_positives, DUPLICATES as u64);
+    }
+}
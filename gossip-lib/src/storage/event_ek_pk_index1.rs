@@ -27,7 +27,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
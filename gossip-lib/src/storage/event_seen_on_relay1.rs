@@ -28,7 +28,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -44,7 +44,7 @@ impl Storage {
     }
 
     pub(crate) fn get_event_seen_on_relay1_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_event_seen_on_relay1()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -77,7 +77,7 @@ impl Storage {
         id: Id,
     ) -> Result<Vec<(RelayUrl, Unixtime)>, Error> {
         let start_key: Vec<u8> = id.as_slice().to_owned();
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut output: Vec<(RelayUrl, Unixtime)> = Vec::new();
         for result in self
             .db_event_seen_on_relay1()?
@@ -29,7 +29,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -81,6 +81,7 @@ impl Storage {
                 event, // use the outer giftwrap event
                 Some(txn),
             )?;
+            self.write_event_addr_index(event, Some(txn))?;
 
             for hashtag in event.hashtags() {
                 if hashtag.is_empty() {
@@ -96,7 +97,7 @@ impl Storage {
     }
 
     pub(crate) fn read_event3(&self, id: Id) -> Result<Option<EventV3>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         match self.db_events3()?.get(&txn, id.as_slice())? {
             None => Ok(None),
             Some(bytes) => Ok(Some(EventV3::read_from_buffer(bytes)?)),
@@ -104,7 +105,7 @@ impl Storage {
     }
 
     pub(crate) fn has_event3(&self, id: Id) -> Result<bool, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         match self.db_events3()?.get(&txn, id.as_slice())? {
             None => Ok(false),
             Some(_) => Ok(true),
@@ -0,0 +1,103 @@
+use crate::error::{Error, ErrorKind};
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use std::sync::Mutex;
+
+// Hashtag -> ()
+//   key: key!(hashtag.as_bytes())
+//   val: (empty)
+
+static FOLLOWED_HASHTAGS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut FOLLOWED_HASHTAGS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_followed_hashtags1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = FOLLOWED_HASHTAGS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = FOLLOWED_HASHTAGS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = FOLLOWED_HASHTAGS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("followed_hashtags")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                FOLLOWED_HASHTAGS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn follow_hashtag1<'a>(
+        &'a self,
+        hashtag: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = key!(hashtag.as_bytes());
+        if key.is_empty() {
+            return Err(ErrorKind::Empty("hashtag".to_owned()).into());
+        }
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_followed_hashtags1()?.put(txn, key, &[])?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn unfollow_hashtag1<'a>(
+        &'a self,
+        hashtag: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = key!(hashtag.as_bytes());
+        if key.is_empty() {
+            return Err(ErrorKind::Empty("hashtag".to_owned()).into());
+        }
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let _ = self.db_followed_hashtags1()?.delete(txn, key)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn is_hashtag_followed1(&self, hashtag: &str) -> Result<bool, Error> {
+        let key = key!(hashtag.as_bytes());
+        if key.is_empty() {
+            return Ok(false);
+        }
+        let txn = self.get_read_txn()?;
+        Ok(self.db_followed_hashtags1()?.get(&txn, key)?.is_some())
+    }
+
+    pub(crate) fn get_followed_hashtags1(&self) -> Result<Vec<String>, Error> {
+        let txn = self.get_read_txn()?;
+        let mut output: Vec<String> = Vec::new();
+        for result in self.db_followed_hashtags1()?.iter(&txn)? {
+            let (key, _val) = result?;
+            output.push(String::from_utf8_lossy(key).into_owned());
+        }
+        Ok(output)
+    }
+}
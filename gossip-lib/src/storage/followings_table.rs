@@ -33,7 +33,7 @@ impl Table for FollowingsTable {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = GLOBALS.db().env.write_txn()?;
+                let mut txn = GLOBALS.db().get_write_txn()?;
                 let db = GLOBALS
                     .db()
                     .env
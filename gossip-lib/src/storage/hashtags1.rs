@@ -28,7 +28,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -71,7 +71,7 @@ impl Storage {
         if key.is_empty() {
             return Err(ErrorKind::Empty("hashtag".to_owned()).into());
         }
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut output: Vec<Id> = Vec::new();
         let iter = match self.db_hashtags1()?.get_duplicates(&txn, key)? {
             Some(i) => i,
@@ -0,0 +1,86 @@
+use crate::error::Error;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::{Id, Tag};
+use std::sync::Mutex;
+
+// Id -> Vec<Tag>
+//   key: id.as_slice()
+//   val: serde_json::to_vec(&tags)
+
+static HOOK_TAGS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut HOOK_TAGS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_hook_tags1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = HOOK_TAGS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = HOOK_TAGS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = HOOK_TAGS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("hook_tags")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                HOOK_TAGS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_hook_tags1<'a>(
+        &'a self,
+        id: Id,
+        tags: &[Tag],
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let bytes = serde_json::to_vec(tags)?;
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_hook_tags1()?.put(txn, id.as_slice(), &bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn read_hook_tags1(&self, id: Id) -> Result<Option<Vec<Tag>>, Error> {
+        let txn = self.get_read_txn()?;
+        match self.db_hook_tags1()?.get(&txn, id.as_slice())? {
+            None => Ok(None),
+            Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+        }
+    }
+
+    pub(crate) fn delete_hook_tags1<'a>(
+        &'a self,
+        id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_hook_tags1()?.delete(txn, id.as_slice())?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+}
@@ -31,7 +31,7 @@ impl Storage {
         &'a self,
         txn: &mut RwTxn<'a>,
     ) -> Result<(), Error> {
-        let loop_txn = self.env.read_txn()?;
+        let loop_txn = self.get_read_txn()?;
         for result in self.db_person_lists2()?.iter(&loop_txn)? {
             let (key, val) = result?;
             let pubkey = PublicKey::from_bytes(key, true)?;
@@ -26,7 +26,7 @@ impl Storage {
     }
 
     fn m41_migrate_person_records(&self, txn: &mut RwTxn<'_>) -> Result<(), Error> {
-        let loop_txn = self.env.read_txn()?;
+        let loop_txn = self.get_read_txn()?;
 
         let iter = Person3Table::iter(&loop_txn)?;
         for (_pk, p) in iter {
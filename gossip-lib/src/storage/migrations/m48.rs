@@ -0,0 +1,66 @@
+use crate::error::Error;
+use crate::storage::types::Relay4;
+use crate::storage::Storage;
+use heed::RwTxn;
+
+impl Storage {
+    pub(super) fn m48_trigger(&self) -> Result<(), Error> {
+        let _ = self.db_relays3()?;
+        let _ = self.db_relays4()?;
+        Ok(())
+    }
+
+    pub(super) fn m48_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: Migrating relay records...");
+
+        // Migrate
+        self.m48_migrate_relay_records(txn)?;
+
+        Ok(())
+    }
+
+    fn m48_migrate_relay_records<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        let mut old = self.filter_relays3(|_| true)?;
+        for relay3 in old.drain(..) {
+            let relay4 = Relay4 {
+                url: relay3.url,
+                success_count: relay3.success_count,
+                failure_count: relay3.failure_count,
+                last_connected_at: relay3.last_connected_at,
+                last_general_eose_at: relay3.last_general_eose_at,
+                rank: relay3.rank,
+                hidden: relay3.hidden,
+                usage_bits: relay3.usage_bits,
+                nip11: relay3.nip11,
+                last_attempt_nip11: relay3.last_attempt_nip11,
+                allow_connect: relay3.allow_connect,
+                allow_auth: relay3.allow_auth,
+                avoid_until: relay3.avoid_until,
+                clock_skew: None,
+                events_received: 0,
+                events_new: 0,
+                bytes_received: 0,
+                last_rejection: None,
+                max_backfill_age: None,
+                last_auth_at: None,
+                nostr_subprotocol: false,
+                no_post: false,
+                mute_content: false,
+                last_config_eose_at: None,
+                skip_events_before: None,
+                trust: Relay4::DEFAULT_TRUST,
+            };
+            self.write_relay4(&relay4, Some(txn))?;
+        }
+
+        // Clear the old database
+        self.db_relays3()?.clear(txn)?;
+
+        Ok(())
+    }
+}
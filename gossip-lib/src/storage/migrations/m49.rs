@@ -0,0 +1,107 @@
+use crate::error::Error;
+use crate::relay::canonical_url;
+use crate::storage::types::Relay4;
+use crate::storage::Storage;
+use heed::RwTxn;
+use nostr_types::RelayUrl;
+use std::collections::BTreeMap;
+
+impl Storage {
+    pub(super) fn m49_trigger(&self) -> Result<(), Error> {
+        let _ = self.db_relays4()?;
+        Ok(())
+    }
+
+    pub(super) fn m49_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: Merging duplicate relay records...");
+
+        // Migrate
+        self.m49_merge_duplicate_relays(txn)?;
+
+        Ok(())
+    }
+
+    fn m49_merge_duplicate_relays<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        // Group the relays by their canonical URL
+        let mut groups: BTreeMap<RelayUrl, Vec<Relay4>> = BTreeMap::new();
+        for relay in self.filter_relays4(|_| true)?.drain(..) {
+            groups
+                .entry(canonical_url(&relay.url))
+                .or_default()
+                .push(relay);
+        }
+
+        for (url, mut relays) in groups {
+            if relays.len() == 1 && relays[0].url == url {
+                continue;
+            }
+
+            for relay in relays.iter() {
+                self.delete_relay4(&relay.url, Some(txn))?;
+            }
+
+            // Prefer the record already at the canonical URL as the base
+            relays.sort_by_key(|r| r.url != url);
+            let mut merged = relays.remove(0);
+            for relay in relays.drain(..) {
+                m49_merge_into(&mut merged, relay);
+            }
+            merged.url = url;
+            self.write_relay4(&merged, Some(txn))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn m49_merge_into(merged: &mut Relay4, other: Relay4) {
+    merged.success_count += other.success_count;
+    merged.failure_count += other.failure_count;
+    merged.last_connected_at = merged.last_connected_at.max(other.last_connected_at);
+    merged.last_general_eose_at = merged.last_general_eose_at.max(other.last_general_eose_at);
+    merged.rank = merged.rank.max(other.rank);
+    merged.hidden = merged.hidden && other.hidden;
+    merged.usage_bits |= other.usage_bits;
+    if merged.nip11.is_none() {
+        merged.nip11 = other.nip11;
+    }
+    merged.last_attempt_nip11 = merged.last_attempt_nip11.max(other.last_attempt_nip11);
+    // A 'never' wins over an 'always', which wins over 'ask'
+    merged.allow_connect = m49_merge_permission(merged.allow_connect, other.allow_connect);
+    merged.allow_auth = m49_merge_permission(merged.allow_auth, other.allow_auth);
+    merged.avoid_until = merged.avoid_until.max(other.avoid_until);
+    if merged.clock_skew.is_none() {
+        merged.clock_skew = other.clock_skew;
+    }
+    merged.events_received += other.events_received;
+    merged.events_new += other.events_new;
+    merged.bytes_received += other.bytes_received;
+    match (&merged.last_rejection, &other.last_rejection) {
+        (None, Some(_)) => merged.last_rejection = other.last_rejection,
+        (Some(a), Some(b)) if b.2 > a.2 => merged.last_rejection = other.last_rejection,
+        _ => {}
+    }
+    if merged.max_backfill_age.is_none() {
+        merged.max_backfill_age = other.max_backfill_age;
+    }
+    merged.last_auth_at = merged.last_auth_at.max(other.last_auth_at);
+    merged.nostr_subprotocol |= other.nostr_subprotocol;
+    merged.no_post |= other.no_post;
+    merged.mute_content |= other.mute_content;
+    merged.last_config_eose_at = merged.last_config_eose_at.min(other.last_config_eose_at);
+    merged.skip_events_before = merged.skip_events_before.max(other.skip_events_before);
+    merged.trust = merged.trust.min(other.trust);
+}
+
+fn m49_merge_permission(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        _ => None,
+    }
+}
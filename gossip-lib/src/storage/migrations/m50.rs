@@ -0,0 +1,44 @@
+use crate::error::Error;
+use crate::storage::Storage;
+use heed::RwTxn;
+use speedy::Readable;
+
+impl Storage {
+    pub(super) fn m50_trigger(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    pub(super) fn m50_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: splitting relays per person setting...");
+
+        // Migrate
+        self.m50_split_num_relays_per_person(txn)?;
+
+        Ok(())
+    }
+
+    fn m50_split_num_relays_per_person<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        // read the old num_relays_per_person setting, if it was ever set
+        let maybe_num: Option<u8> = match self.db_general()?.get(txn, b"num_relays_per_person") {
+            Err(_) => None,
+            Ok(None) => None,
+            Ok(Some(bytes)) => u8::read_from_buffer(bytes).ok(),
+        };
+
+        // Seed both new settings with it
+        if let Some(num) = maybe_num {
+            self.write_setting_feed_relays_per_person(&num, Some(txn))?;
+            self.write_setting_metadata_relays_per_person(&num, Some(txn))?;
+        }
+
+        // Remove the old setting
+        self.db_general()?.delete(txn, b"num_relays_per_person")?;
+
+        Ok(())
+    }
+}
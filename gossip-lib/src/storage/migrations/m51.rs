@@ -0,0 +1,30 @@
+use crate::error::Error;
+use crate::storage::Storage;
+use heed::RwTxn;
+
+impl Storage {
+    pub(super) fn m51_trigger(&self) -> Result<(), Error> {
+        let _ = self.db_event_addr_index()?;
+        Ok(())
+    }
+
+    pub(super) fn m51_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: flagging need to rebuild indexes for the address index...");
+
+        // Migrate
+        self.m51_rebuild_indexes(txn)?;
+
+        Ok(())
+    }
+
+    fn m51_rebuild_indexes<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        self.set_flag_rebuild_indexes_needed(true, Some(txn))?;
+
+        Ok(())
+    }
+}
@@ -31,14 +31,95 @@ mod m44;
 mod m45;
 mod m46;
 mod m47;
+mod m48;
+mod m49;
+mod m50;
+mod m51;
 
 use super::Storage;
 use crate::error::{Error, ErrorKind};
 use heed::RwTxn;
 
+/// A storage migration, and whether this database has had it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationInfo {
+    pub level: u32,
+    pub applied: bool,
+}
+
+/// Derived data that gossip can rebuild from the stored events. Rebuilds run
+/// at startup, after the migrations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rebuild {
+    Relationships,
+    EventIndexes,
+    TagIndex,
+    RelayLists,
+    FriendsOfFriends,
+}
+
+impl Rebuild {
+    pub const ALL: [Rebuild; 5] = [
+        Rebuild::Relationships,
+        Rebuild::EventIndexes,
+        Rebuild::TagIndex,
+        Rebuild::RelayLists,
+        Rebuild::FriendsOfFriends,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rebuild::Relationships => "Relationships",
+            Rebuild::EventIndexes => "Event indexes",
+            Rebuild::TagIndex => "Tag index",
+            Rebuild::RelayLists => "Relay lists",
+            Rebuild::FriendsOfFriends => "Friends of friends",
+        }
+    }
+}
+
 impl Storage {
     const MIN_MIGRATION_LEVEL: u32 = 23;
-    const MAX_MIGRATION_LEVEL: u32 = 47;
+    const MAX_MIGRATION_LEVEL: u32 = 51;
+
+    /// Every migration this version of gossip knows about, and whether it has
+    /// been applied to this database
+    pub fn migration_status(&self) -> Result<Vec<MigrationInfo>, Error> {
+        let current = self.read_migration_level()?.unwrap_or(0);
+        Ok((Self::MIN_MIGRATION_LEVEL..=Self::MAX_MIGRATION_LEVEL)
+            .map(|level| MigrationInfo {
+                level,
+                applied: level <= current,
+            })
+            .collect())
+    }
+
+    /// Whether a rebuild is waiting for the next startup
+    pub fn is_rebuild_pending(&self, rebuild: Rebuild) -> bool {
+        match rebuild {
+            Rebuild::Relationships => self.get_flag_rebuild_relationships_needed(),
+            Rebuild::EventIndexes => self.get_flag_rebuild_indexes_needed(),
+            Rebuild::TagIndex => self.get_flag_rebuild_tag_index_needed(),
+            Rebuild::RelayLists => self.get_flag_reprocess_relay_lists_needed(),
+            Rebuild::FriendsOfFriends => self.get_flag_rebuild_fof_needed(),
+        }
+    }
+
+    /// Schedule (or unschedule) a rebuild for the next startup
+    pub fn set_rebuild_pending<'a>(
+        &'a self,
+        rebuild: Rebuild,
+        pending: bool,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        match rebuild {
+            Rebuild::Relationships => self.set_flag_rebuild_relationships_needed(pending, rw_txn),
+            Rebuild::EventIndexes => self.set_flag_rebuild_indexes_needed(pending, rw_txn),
+            Rebuild::TagIndex => self.set_flag_rebuild_tag_index_needed(pending, rw_txn),
+            Rebuild::RelayLists => self.set_flag_reprocess_relay_lists_needed(pending, rw_txn),
+            Rebuild::FriendsOfFriends => self.set_flag_rebuild_fof_needed(pending, rw_txn),
+        }
+    }
 
     /// Initialize the database from empty
     pub(super) fn init_from_empty(&self) -> Result<(), Error> {
@@ -55,13 +136,13 @@ impl Storage {
 
         for level in necessary.iter() {
             self.trigger(*level)?;
-            let mut txn = self.env.write_txn()?;
+            let mut txn = self.get_write_txn()?;
             self.migrate_inner(*level, &mut txn)?;
             self.write_migration_level(*level, Some(&mut txn))?;
             txn.commit()?;
         }
 
-        let mut txn = self.env.write_txn()?;
+        let mut txn = self.get_write_txn()?;
         self.write_migration_level(Self::MAX_MIGRATION_LEVEL, Some(&mut txn))?;
         txn.commit()?;
 
@@ -98,7 +179,7 @@ impl Storage {
         while level < Self::MAX_MIGRATION_LEVEL {
             level += 1;
             self.trigger(level)?;
-            let mut txn = self.env.write_txn()?;
+            let mut txn = self.get_write_txn()?;
             self.migrate_inner(level, &mut txn)?;
             self.write_migration_level(level, Some(&mut txn))?;
             txn.commit()?;
@@ -138,6 +219,10 @@ impl Storage {
             45 => self.m45_trigger()?,
             46 => self.m46_trigger()?,
             47 => self.m47_trigger()?,
+            48 => self.m48_trigger()?,
+            49 => self.m49_trigger()?,
+            50 => self.m50_trigger()?,
+            51 => self.m51_trigger()?,
             _ => panic!("Unreachable migration level"),
         }
 
@@ -176,6 +261,10 @@ impl Storage {
             45 => self.m45_migrate(&prefix, txn)?,
             46 => self.m46_migrate(&prefix, txn)?,
             47 => self.m47_migrate(&prefix, txn)?,
+            48 => self.m48_migrate(&prefix, txn)?,
+            49 => self.m49_migrate(&prefix, txn)?,
+            50 => self.m50_migrate(&prefix, txn)?,
+            51 => self.m51_migrate(&prefix, txn)?,
             _ => panic!("Unreachable migration level"),
         };
 
@@ -2,8 +2,14 @@ include!("macros");
 
 const MAX_LMDB_KEY: usize = 511;
 
+mod audit;
+mod config;
+mod event_bloom;
 mod migrations;
+pub use migrations::{MigrationInfo, Rebuild};
 mod prune;
+mod recover;
+pub use recover::StorageRecovery;
 
 // type implementations
 pub mod types;
@@ -18,13 +24,19 @@ pub use person3_table::Person3Table;
 pub mod person4_table;
 pub use person4_table::Person4Table;
 pub type PersonTable = Person4Table;
+pub type Draft = types::Draft1;
 pub mod followings_table;
 pub use followings_table::FollowingsTable;
 pub mod handlers_table;
 pub use handlers_table::HandlersTable;
 
 // database implementations
+mod clamped_times1;
 mod configured_handlers;
+mod custom_feeds1;
+mod drafts1;
+mod event_addr_index;
+use event_addr_index::{AddrKey, AddrVal};
 mod event_akci_index;
 use event_akci_index::AkciKey;
 mod event_kci_index;
@@ -38,10 +50,14 @@ mod event_viewed1;
 mod events2;
 mod events3;
 mod fof;
+mod followed_hashtags1;
 mod general;
 mod hashtags1;
+mod hook_tags1;
+mod nip11_changes1;
 mod nip46servers1;
 mod nip46servers2;
+mod notifications1;
 mod people2;
 mod person_lists2;
 mod person_lists_metadata1;
@@ -49,46 +65,87 @@ mod person_lists_metadata2;
 mod person_lists_metadata3;
 mod person_relays1;
 mod person_relays2;
+mod quarantine1;
+mod reaction_summaries1;
 mod relationships_by_addr1;
 mod relationships_by_addr2;
 mod relationships_by_addr3;
 mod relationships_by_id1;
 mod relationships_by_id2;
+mod relay_credentials1;
 mod relays1;
 mod relays2;
 mod relays3;
+mod relays4;
+mod replaceable_sightings1;
+pub use replaceable_sightings1::ReplaceableConflict;
+mod summarized_reactions1;
+mod txn;
+pub use txn::{ReadTxn, WriteTxn};
 mod unindexed_giftwraps1;
 mod versioned;
 
+use crate::badges::Badge;
+use crate::community::Community;
 use crate::dm_channel::{DmChannel, DmChannelData};
 use crate::error::{Error, ErrorKind};
+use crate::git::Repo;
 use crate::globals::GLOBALS;
 use crate::misc::Private;
 use crate::nostr_connect_server::{Nip46Server, Nip46UnconnectedServer};
+use crate::notification::Notification;
 use crate::people::{PersonList, PersonListMetadata};
 use crate::person_relay::PersonRelay;
+use crate::polls::{Poll, PollResults};
 use crate::profile::Profile;
-use crate::relationship::{RelationshipByAddr, RelationshipById};
-use crate::relay::Relay;
+use crate::relationship::{ReactionSummary, RelationshipByAddr, RelationshipById, Relationships};
+use crate::relay::{Nip11Change, Relay};
+use crate::reports::{Label, Report};
+use crate::storage::types::{CustomFeed1, Draft1};
 use dashmap::DashMap;
+use event_bloom::EventBloom;
 use filetime::FileTime;
 use heed::types::{Bytes, Unit};
 use heed::{Database, Env, EnvFlags, EnvOpenOptions, RoTxn, RwTxn};
 use nostr_types::{
-    EncryptedPrivateKey, Event, EventKind, EventReference, Filter, Id, MilliSatoshi, NAddr,
-    PublicKey, RelayList, RelayListUsage, RelayUrl, Unixtime,
+    ContentEncryptionAlgorithm, EncryptedPrivateKey, Event, EventKind, EventReference, Filter, Id,
+    MilliSatoshi, NAddr, PublicKey, RelayList, RelayListUsage, RelayUrl, Tag, Unixtime,
 };
+use parking_lot::RwLock as PRwLock;
 use paste::paste;
 use speedy::{Readable, Writable};
 use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::ops::Bound;
 use std::path::Path;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use self::event_kci_index::INDEXED_KINDS;
 use self::event_tci_index::INDEXED_TAGS;
 
+/// The smallest LMDB map we open. Note that it is the size of the map in VIRTUAL
+/// address space, and that it doesn't all have to be paged in at the same time.
+const MIN_MAP_SIZE: usize = 1048576 * 1024 * 24; // 24 GB
+
+/// Map sizes are rounded up to this
+const MAP_SIZE_STEP: usize = 1048576 * 1024; // 1 GB
+
+/// How long a full map waits for open transactions to finish before it grows
+const MAP_GROWTH_WAIT: Duration = Duration::from_secs(5);
+
+/// If this file exists in the LMDB directory, compact at the next startup
+const COMPACT_REQUESTED_FILE: &str = "compact_requested";
+
+// Every table is opened by the time migrations are done, including the old
+// versions that migrations move data out of. There are 49 of them; this leaves
+// room for more.
+const MAX_DBS: u32 = 64;
+
+// How many volatile (in-memory only) events are kept. Past this, the oldest are
+// dropped until there are VOLATILE_EVENTS_KEPT.
+const MAX_VOLATILE_EVENTS: usize = 20_000;
+const VOLATILE_EVENTS_KEPT: usize = 18_000;
+
 type RawDatabase = Database<Bytes, Bytes>;
 type EmptyDatabase = Database<Bytes, Unit>;
 
@@ -100,10 +157,18 @@ pub struct Storage {
     env: Env,
     volatile_events: DashMap<Id, Event>,
     volatile_seen_on: DashMap<Id, Vec<(RelayUrl, Unixtime)>>,
+    event_bloom: PRwLock<Option<EventBloom>>,
+
+    // Held for reading by every transaction, and for writing while growing the map
+    txn_gate: PRwLock<()>,
 }
 
 impl Storage {
-    fn new_env<P: AsRef<Path> + std::fmt::Debug>(dir: P, rapid: bool) -> Result<Env, Error> {
+    fn new_env<P: AsRef<Path> + std::fmt::Debug>(
+        dir: P,
+        rapid: bool,
+        map_size: usize,
+    ) -> Result<Env, Error> {
         let mut builder = EnvOpenOptions::new();
 
         let flags = if rapid {
@@ -124,16 +189,14 @@ impl Storage {
         }
 
         // builder.max_readers(126); // this is the default
-        builder.max_dbs(32);
+        builder.max_dbs(MAX_DBS);
 
         // This has to be big enough for all the data.
-        // Note that it is the size of the map in VIRTUAL address space,
-        //   and that it doesn't all have to be paged in at the same time.
         // Some filesystem that doesn't handle sparse files may allocate all
         //   of this, so we don't go too crazy big.
         // NOTE: this cannot be a setting because settings are only available
         //       after the database has been launched.
-        builder.map_size(1048576 * 1024 * 24); // 24 GB
+        builder.map_size(map_size);
 
         let env = unsafe {
             match builder.open(&dir) {
@@ -148,16 +211,178 @@ impl Storage {
         Ok(env)
     }
 
+    /// Pick a map size for the LMDB in `dir` that leaves room for the data to grow
+    fn map_size_for<P: AsRef<Path>>(dir: P, rapid: bool) -> usize {
+        let file_size = fs::metadata(dir.as_ref().join("data.mdb"))
+            .map(|m| m.len() as usize)
+            .unwrap_or(0);
+
+        if rapid {
+            // With WRITE_MAP the file is extended to the whole map, so its size tells
+            // us the previous map size rather than how much data there is
+            file_size.max(MIN_MAP_SIZE)
+        } else {
+            Self::map_size_with_headroom(file_size).max(MIN_MAP_SIZE)
+        }
+    }
+
+    /// Twice the used size, rounded up to a whole step
+    fn map_size_with_headroom(used: usize) -> usize {
+        (used.saturating_mul(2) / MAP_SIZE_STEP + 1) * MAP_SIZE_STEP
+    }
+
     pub fn new<P: AsRef<Path> + std::fmt::Debug>(dir: P, rapid: bool) -> Result<Storage, Error> {
-        let env = Self::new_env(dir, rapid)?;
+        let map_size = Self::map_size_for(&dir, rapid);
+        let env = Self::new_env(dir, rapid, map_size)?;
+        let storage = Self::from_env(env);
+
+        // No transactions are active yet
+        unsafe {
+            storage.grow_map_if_needed()?;
+        }
+
+        Ok(storage)
+    }
 
-        Ok(Storage {
+    fn from_env(env: Env) -> Storage {
+        Storage {
             env,
             volatile_events: DashMap::new(),
             volatile_seen_on: DashMap::new(),
+            event_bloom: PRwLock::new(None),
+            txn_gate: PRwLock::new(()),
+        }
+    }
+
+    /// Grow the LMDB map if less than a quarter of it is free. Returns true if it grew.
+    ///
+    /// Event and relationship writes that fill the map grow it themselves (see
+    /// [grow_full_map](Self::grow_full_map)). Other writes fail with a MapFull error.
+    ///
+    /// # Safety
+    ///
+    /// No transactions may be active in this process.
+    pub(crate) unsafe fn grow_map_if_needed(&self) -> Result<bool, Error> {
+        let map_size = self.env.info().map_size;
+        let used = self.env.non_free_pages_size()? as usize;
+        if used < map_size / 4 * 3 {
+            return Ok(false);
+        }
+
+        self.resize_map(used)?;
+        Ok(true)
+    }
+
+    /// Grow the LMDB map after a write failed because it was full. Returns true if
+    /// it grew.
+    ///
+    /// This waits for open transactions to finish, and gives up if they don't, which
+    /// is what happens if the caller holds one itself.
+    pub(crate) fn grow_full_map(&self) -> Result<bool, Error> {
+        let _gate = match self.txn_gate.try_write_for(MAP_GROWTH_WAIT) {
+            Some(gate) => gate,
+            None => {
+                tracing::warn!("The LMDB map is full, but transactions are open so it can't grow");
+                return Ok(false);
+            }
+        };
+
+        let used = self.env.non_free_pages_size()? as usize;
+
+        // No transactions are active, we hold the gate
+        unsafe {
+            self.resize_map(used)?;
+        }
+        Ok(true)
+    }
+
+    /// Run a write that uses its own transaction. If it fails because the LMDB map is
+    /// full, grow the map and run it again.
+    pub(crate) fn with_map_growth<T, F>(&self, write: F) -> Result<T, Error>
+    where
+        F: Fn() -> Result<T, Error>,
+    {
+        match write() {
+            Err(e)
+                if matches!(
+                    e.kind,
+                    ErrorKind::Lmdb(heed::Error::Mdb(heed::MdbError::MapFull))
+                ) =>
+            {
+                if self.grow_full_map()? {
+                    write()
+                } else {
+                    Err(e)
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Run writes in a transaction of their own, and commit it. If the LMDB map is
+    /// full, grow the map and run them again in a new transaction.
+    ///
+    /// Writes given a caller's transaction can't be retried this way, because the
+    /// map can't grow while that transaction is open. Callers that open the
+    /// transaction use this instead.
+    pub(crate) fn write_with_map_growth<T, F>(&self, write: F) -> Result<T, Error>
+    where
+        F: Fn(&mut RwTxn<'_>) -> Result<T, Error>,
+    {
+        self.with_map_growth(|| {
+            let mut txn = self.get_write_txn()?;
+            let output = write(&mut txn)?;
+            txn.commit()?;
+            Ok(output)
         })
     }
 
+    // Safety: no transactions may be active in this process
+    unsafe fn resize_map(&self, used: usize) -> Result<(), Error> {
+        let map_size = self.env.info().map_size;
+        let new_size = Self::map_size_with_headroom(used).max(map_size + MAP_SIZE_STEP);
+        tracing::info!(
+            "Growing the LMDB map from {} to {} bytes ({} bytes used)",
+            map_size,
+            new_size,
+            used
+        );
+        self.env.resize(new_size)?;
+        Ok(())
+    }
+
+    /// Ask for the database to be compacted the next time gossip starts.
+    ///
+    /// Compaction copies the database, so it temporarily needs about as much free
+    /// disk space again as the database takes.
+    pub fn request_compaction() -> Result<(), Error> {
+        let mut requested = Profile::lmdb_dir()?;
+        requested.push(COMPACT_REQUESTED_FILE);
+        fs::File::create(&requested)?;
+        Ok(())
+    }
+
+    /// Cancel a compaction request
+    pub fn cancel_compaction_request() -> Result<(), Error> {
+        let mut requested = Profile::lmdb_dir()?;
+        requested.push(COMPACT_REQUESTED_FILE);
+        if requested.exists() {
+            fs::remove_file(&requested)?;
+        }
+        Ok(())
+    }
+
+    /// Whether compaction will happen the next time gossip starts
+    pub fn is_compaction_requested() -> bool {
+        match Profile::lmdb_dir() {
+            Ok(mut requested) => {
+                requested.push(COMPACT_REQUESTED_FILE);
+                requested.exists()
+            }
+            Err(_) => false,
+        }
+    }
+
     pub(crate) fn compact() -> Result<(), Error> {
         let lmdb_dir = Profile::lmdb_dir()?;
 
@@ -167,8 +392,18 @@ impl Storage {
             stamp
         };
 
-        // If the stamp exists and is less than 1 week old, do not compact
-        if let Ok(metadata) = fs::metadata(&stamp) {
+        let requested = {
+            let mut requested = lmdb_dir.clone();
+            requested.push(COMPACT_REQUESTED_FILE);
+            requested
+        };
+
+        if requested.exists() {
+            // The user asked for it, compact regardless of the stamp
+            tracing::info!("Compaction was requested");
+            fs::remove_file(&requested)?;
+        } else if let Ok(metadata) = fs::metadata(&stamp) {
+            // If the stamp exists and is less than 1 week old, do not compact
             let last_modified = FileTime::from_last_modification_time(&metadata).seconds();
             let now = FileTime::now().seconds();
             if now - last_modified < 60 * 60 * 24 + 7 {
@@ -206,7 +441,8 @@ impl Storage {
 
         {
             // Open env
-            let env = Self::new_env(lmdb_dir, false)?;
+            let map_size = Self::map_size_for(&lmdb_dir, false);
+            let env = Self::new_env(lmdb_dir, false, map_size)?;
 
             env.force_sync()?;
 
@@ -247,18 +483,31 @@ impl Storage {
         //
         // old-version databases will be handled by their migration code and only
         // triggered into existence if their migration is necessary.
+        let _ = self.db_clamped_times()?;
+        let _ = self.db_custom_feeds()?;
+        let _ = self.db_drafts()?;
+        let _ = self.db_event_addr_index()?;
         let _ = self.db_event_akci_index()?;
         let _ = self.db_event_kci_index()?;
         let _ = self.db_event_tci_index()?;
         let _ = self.db_events()?;
         let _ = self.db_event_seen_on_relay()?;
         let _ = self.db_event_viewed()?;
+        let _ = self.db_followed_hashtags()?;
         let _ = self.db_hashtags()?;
+        let _ = self.db_hook_tags()?;
+        let _ = self.db_nip11_changes()?;
         let _ = self.db_nip46servers()?;
+        let _ = self.db_notifications()?;
         let _ = self.db_person_relays()?;
+        let _ = self.db_quarantine()?;
+        let _ = self.db_reaction_summaries()?;
         let _ = self.db_relationships_by_id()?;
         let _ = self.db_relationships_by_addr()?;
+        let _ = self.db_relay_credentials()?;
         let _ = self.db_relays()?;
+        let _ = self.db_replaceable_sightings()?;
+        let _ = self.db_summarized_reactions()?;
         let _ = self.db_unindexed_giftwraps()?;
         let _ = self.db_person_lists()?;
         let _ = self.db_person_lists_metadata()?;
@@ -274,18 +523,29 @@ impl Storage {
             None => self.init_from_empty()?,
         }
 
+        // Load the ids of stored events into the bloom filter
+        self.rebuild_event_bloom()?;
+
         Ok(())
     }
 
     /// Get a write transaction. With it, you can do multiple writes before you commit it.
     /// Bundling multiple writes together is more efficient.
-    pub fn get_write_txn(&self) -> Result<RwTxn<'_>, Error> {
-        Ok(self.env.write_txn()?)
+    ///
+    /// This returns a [WriteTxn], which derefs to a heed `RwTxn`, rather than an
+    /// `RwTxn` itself. It keeps the LMDB map from being resized while it is open.
+    pub fn get_write_txn(&self) -> Result<WriteTxn<'_>, Error> {
+        let gate = self.txn_gate.read_recursive();
+        Ok(WriteTxn::new(self.env.write_txn()?, gate))
     }
 
     /// Get a read transaction.
-    pub fn get_read_txn(&self) -> Result<RoTxn<'_>, Error> {
-        Ok(self.env.read_txn()?)
+    ///
+    /// This returns a [ReadTxn], which derefs to a heed `RoTxn`, rather than an
+    /// `RoTxn` itself. It keeps the LMDB map from being resized while it is open.
+    pub fn get_read_txn(&self) -> Result<ReadTxn<'_>, Error> {
+        let gate = self.txn_gate.read_recursive();
+        Ok(ReadTxn::new(self.env.read_txn()?, gate))
     }
 
     /// Sync the data to disk. This happens periodically, but sometimes it's useful to force
@@ -302,6 +562,16 @@ impl Storage {
 
     // Database getters ---------------------------------
 
+    #[inline]
+    pub(crate) fn db_custom_feeds(&self) -> Result<RawDatabase, Error> {
+        self.db_custom_feeds1()
+    }
+
+    #[inline]
+    pub(crate) fn db_drafts(&self) -> Result<RawDatabase, Error> {
+        self.db_drafts1()
+    }
+
     #[inline]
     pub(crate) fn db_events(&self) -> Result<RawDatabase, Error> {
         self.db_events3()
@@ -317,6 +587,21 @@ impl Storage {
         self.db_event_viewed1()
     }
 
+    #[inline]
+    pub(crate) fn db_clamped_times(&self) -> Result<RawDatabase, Error> {
+        self.db_clamped_times1()
+    }
+
+    #[inline]
+    pub(crate) fn db_hook_tags(&self) -> Result<RawDatabase, Error> {
+        self.db_hook_tags1()
+    }
+
+    #[inline]
+    pub(crate) fn db_followed_hashtags(&self) -> Result<RawDatabase, Error> {
+        self.db_followed_hashtags1()
+    }
+
     #[inline]
     pub(crate) fn db_hashtags(&self) -> Result<RawDatabase, Error> {
         self.db_hashtags1()
@@ -327,11 +612,36 @@ impl Storage {
         self.db_nip46servers2()
     }
 
+    #[inline]
+    pub(crate) fn db_nip11_changes(&self) -> Result<RawDatabase, Error> {
+        self.db_nip11_changes1()
+    }
+
+    #[inline]
+    pub(crate) fn db_notifications(&self) -> Result<RawDatabase, Error> {
+        self.db_notifications1()
+    }
+
     #[inline]
     pub(crate) fn db_person_relays(&self) -> Result<RawDatabase, Error> {
         self.db_person_relays2()
     }
 
+    #[inline]
+    pub(crate) fn db_quarantine(&self) -> Result<RawDatabase, Error> {
+        self.db_quarantine1()
+    }
+
+    #[inline]
+    pub(crate) fn db_reaction_summaries(&self) -> Result<RawDatabase, Error> {
+        self.db_reaction_summaries1()
+    }
+
+    #[inline]
+    pub(crate) fn db_summarized_reactions(&self) -> Result<RawDatabase, Error> {
+        self.db_summarized_reactions1()
+    }
+
     #[inline]
     pub(crate) fn db_relationships_by_addr(&self) -> Result<RawDatabase, Error> {
         self.db_relationships_by_addr3()
@@ -342,9 +652,19 @@ impl Storage {
         self.db_relationships_by_id2()
     }
 
+    #[inline]
+    pub(crate) fn db_replaceable_sightings(&self) -> Result<RawDatabase, Error> {
+        self.db_replaceable_sightings1()
+    }
+
+    #[inline]
+    pub(crate) fn db_relay_credentials(&self) -> Result<RawDatabase, Error> {
+        self.db_relay_credentials1()
+    }
+
     #[inline]
     pub(crate) fn db_relays(&self) -> Result<RawDatabase, Error> {
-        self.db_relays3()
+        self.db_relays4()
     }
 
     #[inline]
@@ -366,7 +686,7 @@ impl Storage {
 
     /// The number of bytes in the general table
     pub fn get_general_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_general()?.stat(&txn)?;
 
         Ok(stat.page_size as usize
@@ -387,7 +707,7 @@ impl Storage {
 
     /// The number of bytes in the hashtags table
     pub fn get_hashtags_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_hashtags()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -395,7 +715,7 @@ impl Storage {
 
     /// The number of bytes in the nip46servers table
     pub fn get_nip46servers_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_nip46servers()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -404,12 +724,12 @@ impl Storage {
     /// The number of bytes in the relays table
     #[inline]
     pub fn get_relays_size(&self) -> Result<usize, Error> {
-        self.get_relays3_size()
+        self.get_relays4_size()
     }
 
     /// The number of bytes in the event table
     pub fn get_event_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_events()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -417,13 +737,21 @@ impl Storage {
 
     /// The number of events in the event table
     pub fn get_event_len(&self) -> Result<u64, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         Ok(self.db_events()?.len(&txn)?)
     }
 
+    /// The number of bytes in the event_addr_index table
+    pub fn get_event_addr_index_size(&self) -> Result<usize, Error> {
+        let txn = self.get_read_txn()?;
+        let stat = self.db_event_addr_index()?.stat(&txn)?;
+        Ok(stat.page_size as usize
+            * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
+    }
+
     /// The number of bytes in the event_akci_index table
     pub fn get_event_akci_index_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_event_akci_index()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -431,7 +759,7 @@ impl Storage {
 
     /// The number of bytes in the event_kci_index table
     pub fn get_event_kci_index_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_event_kci_index()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -439,7 +767,7 @@ impl Storage {
 
     /// The number of bytes in the event_tci index table
     pub fn get_event_tci_index_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_event_tci_index()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -448,7 +776,7 @@ impl Storage {
     /// The number of bytes in the relationships_by_addr table
     #[inline]
     pub fn get_relationships_by_addr_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_relationships_by_addr()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -457,7 +785,7 @@ impl Storage {
     /// The number of bytes in the relationships_by_id table
     #[inline]
     pub fn get_relationships_by_id_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_relationships_by_id()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -471,7 +799,7 @@ impl Storage {
 
     /// The number of bytes in the person_lists table
     pub fn get_person_lists_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_person_lists()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -479,7 +807,7 @@ impl Storage {
 
     /// The number of bytes in the fof table
     pub fn get_fof_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_fof()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -487,7 +815,7 @@ impl Storage {
 
     /// The number of bytes in the configured handlers table
     pub fn get_configured_handlers_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_configured_handlers()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -517,7 +845,7 @@ impl Storage {
     }
 
     pub(crate) fn read_migration_level(&self) -> Result<Option<u32>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
 
         Ok(self
             .db_general()?
@@ -546,7 +874,7 @@ impl Storage {
 
     /// Read the user's encrypted private key
     pub fn read_encrypted_private_key(&self) -> Result<Option<EncryptedPrivateKey>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
 
         match self.db_general()?.get(&txn, b"encrypted_private_key")? {
             None => Ok(None),
@@ -578,7 +906,7 @@ impl Storage {
 
     /// Read the client's encrypted private key
     pub fn read_client_encrypted_private_key(&self) -> Result<Option<EncryptedPrivateKey>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
 
         match self
             .db_general()?
@@ -615,7 +943,7 @@ impl Storage {
     /// Read NIP-46 unconnected server
     #[allow(dead_code)]
     pub fn read_nip46_unconnected_server(&self) -> Result<Option<Nip46UnconnectedServer>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         match self.db_general()?.get(&txn, b"nip46_unconnected_server")? {
             None => Ok(None),
             Some(bytes) => {
@@ -642,6 +970,80 @@ impl Storage {
         Ok(())
     }
 
+    /// Write when the database was last pruned automatically
+    pub(crate) fn write_last_auto_prune<'a>(
+        &'a self,
+        when: Unixtime,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let bytes = when.0.to_be_bytes();
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_general()?.put(txn, b"last_auto_prune", &bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    /// Read when the database was last pruned automatically
+    pub(crate) fn read_last_auto_prune(&self) -> Result<Option<Unixtime>, Error> {
+        let txn = self.get_read_txn()?;
+
+        Ok(self
+            .db_general()?
+            .get(&txn, b"last_auto_prune")?
+            .map(|bytes| Unixtime(i64::from_be_bytes(bytes[..8].try_into().unwrap()))))
+    }
+
+    /// Write the relays the user connected to themselves (for the global or a relay
+    /// feed), with when they last did, to connect to again next time
+    pub(crate) fn write_manual_relay_sessions<'a>(
+        &'a self,
+        relays: &[(RelayUrl, Unixtime)],
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let sessions: Vec<(String, i64)> = relays
+            .iter()
+            .map(|(r, at)| (r.as_str().to_owned(), at.0))
+            .collect();
+        let bytes = sessions.write_to_vec()?;
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_general()?
+            .put(txn, b"manual_relay_sessions", &bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    /// Read the relays the user connected to themselves, with when they last did
+    pub(crate) fn read_manual_relay_sessions(&self) -> Result<Vec<(RelayUrl, Unixtime)>, Error> {
+        let txn = self.get_read_txn()?;
+        match self.db_general()?.get(&txn, b"manual_relay_sessions")? {
+            None => Ok(vec![]),
+            Some(bytes) => {
+                let sessions = Vec::<(String, i64)>::read_from_buffer(bytes)?;
+                Ok(sessions
+                    .iter()
+                    .filter_map(|(u, at)| {
+                        RelayUrl::try_from_str(u).ok().map(|u| (u, Unixtime(*at)))
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// How many bytes of the database are in use
+    pub fn used_size(&self) -> Result<u64, Error> {
+        Ok(self.env.non_free_pages_size()?)
+    }
+
     // Flags ------------------------------------------------------------
 
     def_flag!(following_only, b"following_only", false);
@@ -673,9 +1075,20 @@ impl Storage {
     );
     def_setting!(log_n, b"log_n", u8, 18);
     def_setting!(login_at_startup, b"login_at_startup", bool, true);
+    def_setting!(auto_lock_minutes, b"auto_lock_minutes", u32, 0);
     def_setting!(offline, b"offline", bool, false);
+    def_setting!(quiet_hours, b"quiet_hours", bool, false);
+    def_setting!(quiet_hours_start, b"quiet_hours_start", u8, 23);
+    def_setting!(quiet_hours_end, b"quiet_hours_end", u8, 7);
     def_setting!(load_avatars, b"load_avatars", bool, true);
     def_setting!(load_media, b"load_media", bool, true);
+    def_setting!(
+        prefetch_profile_media,
+        b"prefetch_profile_media",
+        bool,
+        false
+    );
+    def_setting!(metered_connection, b"metered_connection", bool, false);
     def_setting!(check_nip05, b"check_nip05", bool, true);
     def_setting!(wgpu_renderer, b"wgpu_renderer", bool, false);
     def_setting!(
@@ -696,12 +1109,26 @@ impl Storage {
         bool,
         false
     );
-    def_setting!(num_relays_per_person, b"num_relays_per_person", u8, 2);
+    def_setting!(
+        auto_advertise_on_change,
+        b"auto_advertise_on_change",
+        bool,
+        false
+    );
+    def_setting!(feed_relays_per_person, b"feed_relays_per_person", u8, 2);
+    def_setting!(
+        metadata_relays_per_person,
+        b"metadata_relays_per_person",
+        u8,
+        2
+    );
     def_setting!(max_relays, b"max_relays", u8, 50);
     def_setting!(num_relays_for_counting, b"num_relays_for_counting", u8, 15);
+    def_setting!(num_relays_for_hashtags, b"num_relays_for_hashtags", u8, 5);
     def_setting!(load_more_count, b"load_more_count", u64, 35);
     def_setting!(reposts, b"reposts", bool, true);
     def_setting!(show_long_form, b"show_long_form", bool, false);
+    def_setting!(include_replies, b"include_replies", bool, false);
     def_setting!(show_mentions, b"show_mentions", bool, true);
     def_setting!(enable_picture_events, b"enable_picture_events", bool, true);
     def_setting!(enable_comments, b"enable_comments", bool, false);
@@ -712,6 +1139,12 @@ impl Storage {
         u64,
         60 * 15
     );
+    def_setting!(
+        future_event_action,
+        b"future_event_action",
+        String,
+        "drop".to_owned()
+    );
     def_setting!(hide_mutes_entirely, b"hide_mutes_entirely", bool, false);
     def_setting!(reactions, b"reactions", bool, true);
     def_setting!(enable_zap_receipts, b"enable_zap_receipts", bool, true);
@@ -723,8 +1156,12 @@ impl Storage {
         false
     );
     def_setting!(show_deleted_events, b"show_deleted_events", bool, false);
+    def_setting!(retain_deleted_events, b"retain_deleted_events", bool, true);
     def_setting!(pow, b"pow", u8, 0);
+    def_setting!(max_reply_p_tags, b"max_reply_p_tags", usize, 100);
+    def_setting!(post_retry_attempts, b"post_retry_attempts", u32, 3);
     def_setting!(set_client_tag, b"set_client_tag", bool, false);
+    def_setting!(strip_url_tracking, b"strip_url_tracking", bool, false);
     def_setting!(set_user_agent, b"set_user_agent", bool, false);
     def_setting!(delegatee_tag, b"delegatee_tag", String, String::new());
     def_setting!(max_fps, b"max_fps", u32, 60);
@@ -740,6 +1177,12 @@ impl Storage {
         u32,
         8000
     );
+    def_setting!(
+        feed_ordering,
+        b"feed_ordering",
+        String,
+        "reverse_chronological".to_owned()
+    );
     def_setting!(
         feed_thread_scroll_to_main_event,
         b"feed_thread_scroll_to_main_event",
@@ -823,6 +1266,12 @@ impl Storage {
         u64,
         5
     );
+    def_setting!(
+        relay_info_becomes_stale_hours,
+        b"relay_info_becomes_stale_hours",
+        u64,
+        24
+    );
     def_setting!(
         max_websocket_message_size_kb,
         b"max_websocket_message_size_kb",
@@ -853,6 +1302,7 @@ impl Storage {
         u64,
         55
     );
+    def_setting!(restore_manual_relays, b"restore_manual_relays", bool, false);
     def_setting!(
         fetcher_connect_timeout_sec,
         b"fetcher_connect_timeout_sec",
@@ -866,6 +1316,8 @@ impl Storage {
         usize,
         3
     );
+    def_setting!(fetcher_max_requests, b"fetcher_max_requests", usize, 16);
+    def_setting!(fetcher_max_retries, b"fetcher_max_retries", u64, 3);
     def_setting!(
         fetcher_host_exclusion_on_low_error_secs,
         b"fetcher_host_exclusion_on_low_error_secs",
@@ -886,6 +1338,28 @@ impl Storage {
     );
     def_setting!(prune_period_days, b"prune_period_days", u64, 90);
     def_setting!(cache_prune_period_days, b"cache_prune_period_days", u64, 90);
+    def_setting!(auto_prune, b"auto_prune", bool, false);
+    def_setting!(
+        auto_prune_interval_days,
+        b"auto_prune_interval_days",
+        u64,
+        7
+    );
+    def_setting!(auto_prune_min_size_mb, b"auto_prune_min_size_mb", u64, 1024);
+    def_setting!(dm_cache_size, b"dm_cache_size", usize, 256);
+    def_setting!(
+        reaction_summary_threshold,
+        b"reaction_summary_threshold",
+        u64,
+        500
+    );
+    def_setting!(
+        prune_summarized_reactions,
+        b"prune_summarized_reactions",
+        bool,
+        false
+    );
+    def_setting!(stored_kinds, b"stored_kinds", String, "".to_string());
     def_setting!(
         avoid_spam_on_unsafe_relays,
         b"avoid_spam_on_unsafe_relays",
@@ -898,6 +1372,12 @@ impl Storage {
         bool,
         false
     );
+    def_setting!(
+        fetch_inbox_on_feed_relays,
+        b"fetch_inbox_on_feed_relays",
+        bool,
+        true
+    );
     def_setting!(
         apply_spam_filter_on_incoming_events,
         b"apply_spam_filter_on_incoming_events",
@@ -1029,6 +1509,32 @@ impl Storage {
             .or_insert(vec![(url, when)]);
     }
 
+    /// Remember which version of a replaceable event a relay served us
+    #[inline]
+    pub fn add_replaceable_sighting<'a>(
+        &'a self,
+        event: &Event,
+        url: &RelayUrl,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        if !event.kind.is_replaceable() || Self::url_is_banned(url) {
+            return Ok(());
+        }
+
+        self.add_replaceable_sighting1(event, url, rw_txn)
+    }
+
+    /// Find relays that disagree about the latest version of an author's
+    /// replaceable events of the given kind, as of when we last heard from them
+    #[inline]
+    pub fn detect_replaceable_conflicts(
+        &self,
+        author: PublicKey,
+        kind: EventKind,
+    ) -> Result<Vec<ReplaceableConflict>, Error> {
+        self.detect_replaceable_conflicts1(author, kind)
+    }
+
     /// Get event seen on relay
     #[inline]
     pub fn get_event_seen_on_relay(&self, id: Id) -> Result<Vec<(RelayUrl, Unixtime)>, Error> {
@@ -1039,6 +1545,67 @@ impl Storage {
         }
     }
 
+    /// Whether an event was seen on a relay whose content the user trusts
+    pub fn is_seen_on_trusted_relay(&self, id: Id) -> Result<bool, Error> {
+        for (url, _) in self.get_event_seen_on_relay(id)? {
+            if self.read_relay(&url)?.is_some_and(|r| r.is_trusted()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Relays to put in a shareable `nevent` so others can find the event, at most
+    /// `n` of them.
+    ///
+    /// These are the best relays it was seen on, or if it wasn't seen anywhere, the
+    /// author's best outboxes.
+    pub fn get_relay_hints_for_event(&self, id: Id, n: usize) -> Result<Vec<RelayUrl>, Error> {
+        let seen_on: Vec<RelayUrl> = self
+            .get_event_seen_on_relay(id)?
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect();
+        let mut hints = self.best_relay_hints(seen_on, n)?;
+        if hints.is_empty() {
+            if let Some(event) = self.read_event(id)? {
+                hints = self.get_relay_hints_for_person(event.pubkey, n)?;
+            }
+        }
+        Ok(hints)
+    }
+
+    /// Relays to put in a shareable `nprofile` so others can find the person: their
+    /// best outboxes, at most `n` of them.
+    pub fn get_relay_hints_for_person(
+        &self,
+        pubkey: PublicKey,
+        n: usize,
+    ) -> Result<Vec<RelayUrl>, Error> {
+        let outboxes: Vec<RelayUrl> = self
+            .get_person_relays(pubkey)?
+            .into_iter()
+            .filter(|pr| pr.write)
+            .map(|pr| pr.url)
+            .collect();
+        self.best_relay_hints(outboxes, n)
+    }
+
+    // The `n` best of these relays by score. Loopback relays are left out, nobody else
+    // can reach them.
+    fn best_relay_hints(&self, urls: Vec<RelayUrl>, n: usize) -> Result<Vec<RelayUrl>, Error> {
+        let mut scored: Vec<(RelayUrl, f32)> = Vec::new();
+        for url in urls {
+            if crate::relay::is_loopback(&url) {
+                continue;
+            }
+            let score = self.read_relay(&url)?.map(|r| r.score()).unwrap_or(0.0);
+            scored.push((url, score));
+        }
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(scored.into_iter().take(n).map(|(url, _)| url).collect())
+    }
+
     /// Mark event viewed
     #[inline]
     pub fn mark_event_viewed<'a>(
@@ -1055,59 +1622,252 @@ impl Storage {
         self.is_event_viewed1(id)
     }
 
-    /// Associate a hashtag to an event
+    /// Record the time an event from the future was received at, to be used in
+    /// place of its created_at when ordering feeds
     #[inline]
-    pub fn add_hashtag<'a>(
+    pub fn write_clamped_time<'a>(
         &'a self,
-        hashtag: &String,
         id: Id,
+        time: Unixtime,
         rw_txn: Option<&mut RwTxn<'a>>,
     ) -> Result<(), Error> {
-        self.add_hashtag1(hashtag, id, rw_txn)
+        self.write_clamped_time1(id, time, rw_txn)
     }
 
-    /// Get events with a given hashtag
+    /// The time an event from the future was clamped to, if it was
     #[inline]
-    #[allow(dead_code)]
-    pub fn get_event_ids_with_hashtag(&self, hashtag: &String) -> Result<Vec<Id>, Error> {
-        self.get_event_ids_with_hashtag1(hashtag)
+    pub fn read_clamped_time(&self, id: Id) -> Result<Option<Unixtime>, Error> {
+        self.read_clamped_time1(id)
     }
 
-    /// Write a relay record.
-    ///
-    /// NOTE: this overwrites. You may wish to read first, or you might prefer
-    /// [modify_relay](Storage::modify_relay)
+    /// Record the tags an event hook gave an event. These are kept apart from the
+    /// stored event so that it still matches its signature.
     #[inline]
-    pub(crate) fn write_relay<'a>(
+    pub fn write_hook_tags<'a>(
         &'a self,
-        relay: &Relay,
+        id: Id,
+        tags: &[Tag],
         rw_txn: Option<&mut RwTxn<'a>>,
     ) -> Result<(), Error> {
-        self.write_relay3(relay, rw_txn)
+        self.write_hook_tags1(id, tags, rw_txn)
     }
 
-    /// Delete a relay record
+    /// The tags an event hook gave an event, if any did
     #[inline]
-    #[allow(dead_code)]
-    pub fn delete_relay<'a>(
-        &'a self,
-        url: &RelayUrl,
-        rw_txn: Option<&mut RwTxn<'a>>,
-    ) -> Result<(), Error> {
-        self.delete_relay3(url, rw_txn)
+    pub fn read_hook_tags(&self, id: Id) -> Result<Option<Vec<Tag>>, Error> {
+        self.read_hook_tags1(id)
     }
 
-    /// Write a new relay record only if it is missing
-    pub fn write_relay_if_missing<'a>(
-        &'a self,
-        url: &RelayUrl,
-        rw_txn: Option<&mut RwTxn<'a>>,
-    ) -> Result<(), Error> {
+    /// The time to order an event by in feeds. This is its created_at unless it
+    /// was received from the future and clamped.
+    pub fn event_sort_time(&self, event: &Event) -> Result<Unixtime, Error> {
+        if event.created_at <= Unixtime::now() {
+            return Ok(event.created_at);
+        }
+        Ok(match self.read_clamped_time(event.id)? {
+            Some(clamped) if clamped < event.created_at => clamped,
+            _ => event.created_at,
+        })
+    }
+
+    /// Associate a hashtag to an event
+    #[inline]
+    pub fn add_hashtag<'a>(
+        &'a self,
+        hashtag: &String,
+        id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.add_hashtag1(hashtag, id, rw_txn)
+    }
+
+    /// Get events with a given hashtag
+    #[inline]
+    #[allow(dead_code)]
+    pub fn get_event_ids_with_hashtag(&self, hashtag: &String) -> Result<Vec<Id>, Error> {
+        self.get_event_ids_with_hashtag1(hashtag)
+    }
+
+    /// Follow a (normalized) hashtag
+    #[inline]
+    pub fn follow_hashtag<'a>(
+        &'a self,
+        hashtag: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.follow_hashtag1(hashtag, rw_txn)
+    }
+
+    /// Unfollow a (normalized) hashtag
+    #[inline]
+    pub fn unfollow_hashtag<'a>(
+        &'a self,
+        hashtag: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.unfollow_hashtag1(hashtag, rw_txn)
+    }
+
+    /// Is this (normalized) hashtag followed?
+    #[inline]
+    pub fn is_hashtag_followed(&self, hashtag: &str) -> Result<bool, Error> {
+        self.is_hashtag_followed1(hashtag)
+    }
+
+    /// Get all followed hashtags, sorted
+    #[inline]
+    pub fn get_followed_hashtags(&self) -> Result<Vec<String>, Error> {
+        self.get_followed_hashtags1()
+    }
+
+    /// Write a custom feed definition, replacing any with the same name
+    #[inline]
+    pub fn write_custom_feed<'a>(
+        &'a self,
+        custom_feed: &CustomFeed1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_custom_feed1(custom_feed, rw_txn)
+    }
+
+    /// Read a custom feed definition by name
+    #[inline]
+    pub fn read_custom_feed(&self, name: &str) -> Result<Option<CustomFeed1>, Error> {
+        self.read_custom_feed1(name)
+    }
+
+    /// Delete a custom feed definition
+    #[inline]
+    pub fn delete_custom_feed<'a>(
+        &'a self,
+        name: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_custom_feed1(name, rw_txn)
+    }
+
+    /// Get all custom feed definitions, sorted by name
+    #[inline]
+    pub fn get_custom_feeds(&self) -> Result<Vec<CustomFeed1>, Error> {
+        self.get_custom_feeds1()
+    }
+
+    /// Save a draft post, replacing any earlier save of the same draft.
+    ///
+    /// Direct message drafts are encrypted to ourselves, so they need the key
+    /// to be unlocked.
+    pub fn save_draft<'a>(
+        &'a self,
+        draft: &Draft1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        if draft.dm_channel.is_none() {
+            return self.write_draft1(draft, rw_txn);
+        }
+
+        let public_key = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => return Err(ErrorKind::NoPublicKey.into()),
+        };
+        let encrypt = |s: &str| {
+            GLOBALS
+                .identity
+                .encrypt(&public_key, s, ContentEncryptionAlgorithm::Nip44v2)
+        };
+        let mut draft = draft.clone();
+        draft.content = encrypt(&draft.content)?;
+        if let Some(subject) = &draft.subject {
+            draft.subject = Some(encrypt(subject)?);
+        }
+        if let Some(content_warning) = &draft.content_warning {
+            draft.content_warning = Some(encrypt(content_warning)?);
+        }
+        self.write_draft1(&draft, rw_txn)
+    }
+
+    /// Load all saved draft posts, most recently saved first.
+    ///
+    /// Direct message drafts are left out while the key is locked.
+    pub fn load_drafts(&self) -> Result<Vec<Draft1>, Error> {
+        let public_key = GLOBALS.identity.public_key();
+        let decrypt = |s: &str| match public_key {
+            Some(pk) if GLOBALS.identity.is_unlocked() => GLOBALS.identity.decrypt(&pk, s).ok(),
+            _ => None,
+        };
+        let mut drafts: Vec<Draft1> = self
+            .get_drafts1()?
+            .into_iter()
+            .filter_map(|mut draft| {
+                if draft.dm_channel.is_some() {
+                    draft.content = decrypt(&draft.content)?;
+                    if let Some(subject) = &draft.subject {
+                        draft.subject = Some(decrypt(subject)?);
+                    }
+                    if let Some(content_warning) = &draft.content_warning {
+                        draft.content_warning = Some(decrypt(content_warning)?);
+                    }
+                }
+                Some(draft)
+            })
+            .collect();
+        drafts.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+        Ok(drafts)
+    }
+
+    /// Delete a saved draft post
+    #[inline]
+    pub fn delete_draft<'a>(
+        &'a self,
+        id: u64,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_draft1(id, rw_txn)
+    }
+
+    /// Write a relay record.
+    ///
+    /// NOTE: this overwrites. You may wish to read first, or you might prefer
+    /// [modify_relay](Storage::modify_relay)
+    #[inline]
+    pub(crate) fn write_relay<'a>(
+        &'a self,
+        relay: &Relay,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let url = crate::relay::canonical_url(&relay.url);
+        if url != relay.url {
+            let mut relay = relay.clone();
+            relay.url = url;
+            return self.write_relay4(&relay, rw_txn);
+        }
+        self.write_relay4(relay, rw_txn)
+    }
+
+    /// Delete a relay record
+    #[inline]
+    #[allow(dead_code)]
+    pub fn delete_relay<'a>(
+        &'a self,
+        url: &RelayUrl,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_relay4(&crate::relay::canonical_url(url), rw_txn)
+    }
+
+    /// Write a new relay record only if it is missing
+    pub fn write_relay_if_missing<'a>(
+        &'a self,
+        url: &RelayUrl,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
         // Don't save banned relay URLs
         if Self::url_is_banned(url) {
             return Ok(());
         }
 
+        // Don't save the same relay twice under slightly different URLs
+        let url = &crate::relay::canonical_url(url);
+
         let mut local_txn = None;
         let txn = maybe_local_txn!(self, rw_txn, local_txn);
 
@@ -1132,7 +1892,7 @@ impl Storage {
     where
         M: FnMut(&mut Relay),
     {
-        self.modify_relay3(url, modify, rw_txn)
+        self.modify_relay4(&crate::relay::canonical_url(url), modify, rw_txn)
     }
 
     //// Modify all relay records
@@ -1145,13 +1905,13 @@ impl Storage {
     where
         M: FnMut(&mut Relay),
     {
-        self.modify_all_relays3(modify, rw_txn)
+        self.modify_all_relays4(modify, rw_txn)
     }
 
     /// Read a relay record
     #[inline]
     pub fn read_relay(&self, url: &RelayUrl) -> Result<Option<Relay>, Error> {
-        self.read_relay3(url)
+        self.read_relay4(&crate::relay::canonical_url(url))
     }
 
     /// Read or create relay
@@ -1165,6 +1925,8 @@ impl Storage {
             return Ok(Relay::new(url.to_owned()));
         }
 
+        let url = &crate::relay::canonical_url(url);
+
         let mut local_txn = None;
         let txn = maybe_local_txn!(self, rw_txn, local_txn);
 
@@ -1188,7 +1950,7 @@ impl Storage {
     where
         F: Fn(&Relay) -> bool,
     {
-        self.filter_relays3(f)
+        self.filter_relays4(f)
     }
 
     pub fn load_effective_public_relay_list(&self) -> Result<RelayList, Error> {
@@ -1250,7 +2012,7 @@ impl Storage {
         let mut relays: Vec<RelayUrl> = Vec::new();
         for tag in event.tags.iter() {
             if tag.tagname() == "relay" {
-                if let Ok(relay_url) = RelayUrl::try_from_str(tag.value()) {
+                if let Ok(relay_url) = crate::relay::parse_relay_url(tag.value()) {
                     // Don't use banned relay URLs
                     if !Self::url_is_banned(&relay_url) {
                         relays.push(relay_url);
@@ -1320,7 +2082,13 @@ impl Storage {
             }
         }
 
-        let relay_list = RelayList::from_event(event);
+        let relay_list = RelayList(
+            RelayList::from_event(event)
+                .0
+                .into_iter()
+                .map(|(url, usage)| (crate::relay::canonical_url(&url), usage))
+                .collect(),
+        );
 
         if ours {
             // If INBOX or OUTBOX is set, we also must turn on READ and WRITE
@@ -1400,6 +2168,60 @@ impl Storage {
         Ok(())
     }
 
+    /// Copy the relays that one person reads from and writes to over to another
+    /// person, as relays suggested for them. This bootstraps someone who has not
+    /// published a relay list from an associate who has.
+    ///
+    /// If `to` has relays of their own (from their relay list or NIP-05), those
+    /// are left alone and the copied relays are only marked as suggested.
+    /// Otherwise the read and write flags are copied too, and will be replaced
+    /// when `to` publishes a relay list. Returns how many relays were copied.
+    pub fn copy_person_relays<'a>(
+        &'a self,
+        from: PublicKey,
+        to: PublicKey,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<usize, Error> {
+        let sources: Vec<PersonRelay> = self
+            .get_person_relays(from)?
+            .drain(..)
+            .filter(|pr| pr.read || pr.write)
+            .collect();
+
+        let has_own_relays = match PersonTable::read_record(to, None)? {
+            Some(person) => person.relay_list_created_at.is_some(),
+            None => false,
+        } || self
+            .get_person_relays(to)?
+            .iter()
+            .any(|pr| pr.read || pr.write);
+
+        let now = Unixtime::now().0 as u64;
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        PersonTable::create_record_if_missing(to, Some(txn))?;
+        for source in sources.iter() {
+            self.modify_person_relay(
+                to,
+                &source.url,
+                |pr| {
+                    if !has_own_relays {
+                        pr.read |= source.read;
+                        pr.write |= source.write;
+                    }
+                    pr.last_suggested = Some(now);
+                },
+                Some(txn),
+            )?;
+        }
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(sources.len())
+    }
+
     /// Write an event
     #[inline]
     pub fn write_event<'a>(
@@ -1407,12 +2229,32 @@ impl Storage {
         event: &Event,
         rw_txn: Option<&mut RwTxn<'a>>,
     ) -> Result<(), Error> {
-        self.write_event3(event, rw_txn)
+        match rw_txn {
+            Some(txn) => self.write_event3(event, Some(txn))?,
+            // With its own transaction, the write can be retried if the map was full
+            None => self.with_map_growth(|| self.write_event3(event, None))?,
+        }
+        self.event_bloom_insert(event.id);
+        Ok(())
     }
 
-    #[inline]
+    /// Keep an event in memory only. If too many are kept, the oldest are dropped.
     pub fn write_event_volatile(&self, event: Event) {
         self.volatile_events.insert(event.id, event);
+
+        if self.volatile_events.len() > MAX_VOLATILE_EVENTS {
+            let mut by_age: Vec<(Unixtime, Id)> = self
+                .volatile_events
+                .iter()
+                .map(|e| (e.value().created_at, *e.key()))
+                .collect();
+            by_age.sort();
+            let excess = by_age.len().saturating_sub(VOLATILE_EVENTS_KEPT);
+            for (_, id) in by_age.drain(..excess) {
+                self.volatile_events.remove(&id);
+                self.volatile_seen_on.remove(&id);
+            }
+        }
     }
 
     /// Read an event
@@ -1454,7 +2296,7 @@ impl Storage {
     /// If we have the event
     #[inline]
     pub fn has_event(&self, id: Id) -> Result<bool, Error> {
-        self.has_event3(id)
+        self.has_event_bloom(id)
     }
 
     #[inline]
@@ -1495,6 +2337,12 @@ impl Storage {
         // Delete from event_viewed
         self.db_event_viewed()?.delete(txn, id.as_slice())?;
 
+        // Delete from clamped_times
+        self.delete_clamped_time1(id, Some(txn))?;
+
+        // Delete from hook_tags
+        self.delete_hook_tags1(id, Some(txn))?;
+
         // DO NOT delete from relationships. The related event still applies in case
         // this event comes back, ESPECIALLY deletion relationships!
 
@@ -1508,6 +2356,7 @@ impl Storage {
         //   db_event_hashtags()
         //   db_relationships(), where the ID is the 2nd half of the key
         //   db_reprel()
+        //   db_event_addr_index()
         //   db_event_akci_index()
         //   db_event_kci_index()
 
@@ -1516,6 +2365,136 @@ impl Storage {
         Ok(())
     }
 
+    /// The number of events in the quarantine table
+    #[inline]
+    pub fn get_quarantine_len(&self) -> Result<u64, Error> {
+        self.get_quarantine1_len()
+    }
+
+    /// Put the raw bytes of an event into the quarantine table.
+    ///
+    /// This does not remove the event from the events table; see
+    /// [audit_events](Storage::audit_events)
+    #[inline]
+    pub(crate) fn quarantine_event_bytes<'a>(
+        &'a self,
+        id: Id,
+        bytes: &[u8],
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.quarantine_event_bytes1(id, bytes, rw_txn)
+    }
+
+    /// Read all quarantined events, for review. Events that no longer parse
+    /// are returned as `None` alongside their id.
+    #[inline]
+    pub fn read_quarantined_events(&self) -> Result<Vec<(Id, Option<Event>)>, Error> {
+        self.read_quarantined_events1()
+    }
+
+    /// Permanently remove all quarantined events. Returns how many were removed.
+    #[inline]
+    pub fn purge_quarantined_events<'a>(
+        &'a self,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<usize, Error> {
+        self.purge_quarantine1(rw_txn)
+    }
+
+    /// Write the encrypted credential for a relay
+    #[inline]
+    pub(crate) fn write_relay_credential<'a>(
+        &'a self,
+        url: &RelayUrl,
+        ciphertext: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_relay_credential1(url, ciphertext, rw_txn)
+    }
+
+    /// Read the encrypted credential for a relay
+    #[inline]
+    pub(crate) fn read_relay_credential(&self, url: &RelayUrl) -> Result<Option<String>, Error> {
+        self.read_relay_credential1(url)
+    }
+
+    /// Remove the credential for a relay
+    #[inline]
+    pub(crate) fn delete_relay_credential<'a>(
+        &'a self,
+        url: &RelayUrl,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_relay_credential1(url, rw_txn)
+    }
+
+    /// Whether a relay has a credential
+    pub fn has_relay_credential(&self, url: &RelayUrl) -> Result<bool, Error> {
+        Ok(self
+            .read_relay_credential1(&crate::relay::canonical_url(url))?
+            .is_some())
+    }
+
+    /// Record a change in what a relay says it supports
+    #[inline]
+    pub(crate) fn write_nip11_change<'a>(
+        &'a self,
+        change: &Nip11Change,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_nip11_change1(change, rw_txn)
+    }
+
+    /// The changes seen in what a relay says it supports, oldest first
+    #[inline]
+    pub fn get_nip11_changes(&self, url: &RelayUrl) -> Result<Vec<Nip11Change>, Error> {
+        self.get_nip11_changes1(&crate::relay::canonical_url(url))
+    }
+
+    /// Write a notification. Returns false if an equivalent notification was
+    /// already stored (e.g. the same person reacting to the same note twice).
+    #[inline]
+    pub(crate) fn write_notification<'a>(
+        &'a self,
+        notification: &Notification,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<bool, Error> {
+        self.write_notification1(notification, rw_txn)
+    }
+
+    /// Get notifications matching the filter, newest first
+    #[inline]
+    pub fn get_notifications<F>(&self, f: F) -> Result<Vec<Notification>, Error>
+    where
+        F: Fn(&Notification) -> bool,
+    {
+        self.filter_notifications1(f)
+    }
+
+    /// The number of unread notifications
+    pub fn get_unread_notification_count(&self) -> Result<usize, Error> {
+        Ok(self.filter_notifications1(|n| !n.read)?.len())
+    }
+
+    /// Mark a notification as read
+    pub fn mark_notification_read<'a>(
+        &'a self,
+        notification: &Notification,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let key = notification.key();
+        self.mark_notifications_read1(|n| n.key() == key, rw_txn)
+    }
+
+    /// Mark all notifications as read
+    #[inline]
+    pub fn mark_all_notifications_read<'a>(
+        &'a self,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.mark_notifications_read1(|_| true, rw_txn)
+    }
+
     /// Replace any existing event with the passed in event, if it is of a replaceable kind
     /// and is newer.
     pub fn replace_event<'a>(
@@ -1552,41 +2531,315 @@ impl Storage {
             }
         }
 
-        if found_newer {
-            return Ok(false); // this event is not the latest one.
-        }
-
-        self.write_event(event, rw_txn)?;
-
-        Ok(true)
+        if found_newer {
+            return Ok(false); // this event is not the latest one.
+        }
+
+        self.write_event(event, rw_txn)?;
+
+        Ok(true)
+    }
+
+    /// Get the matching replaceable event (possibly parameterized)
+    /// TBD: optimize this by storing better event indexes
+    pub fn get_replaceable_event(
+        &self,
+        kind: EventKind,
+        pubkey: PublicKey,
+        parameter: &str,
+    ) -> Result<Option<Event>, Error> {
+        if !kind.is_replaceable() {
+            return Err(ErrorKind::General("Event kind is not replaceable".to_owned()).into());
+        }
+
+        if kind.is_parameterized_replaceable() {
+            return self.get_addressable_event(kind, pubkey, parameter);
+        }
+
+        let mut filter = Filter::new();
+        filter.add_event_kind(kind);
+        filter.add_author(pubkey);
+
+        Ok(self
+            .find_events_by_filter(&filter, |e| {
+                if kind.is_parameterized_replaceable() {
+                    e.parameter().as_deref() == Some(parameter)
+                } else {
+                    true
+                }
+            })?
+            .first()
+            .cloned())
+    }
+
+    /// Get the latest event at the address `kind:author:d`, using the address index
+    pub fn get_addressable_event(
+        &self,
+        kind: EventKind,
+        author: PublicKey,
+        d: &str,
+    ) -> Result<Option<Event>, Error> {
+        if !kind.is_parameterized_replaceable() {
+            return Err(ErrorKind::General("Event kind is not addressable".to_owned()).into());
+        }
+
+        let key = AddrKey::from_parts(kind, author, d);
+        let id = {
+            let txn = self.get_read_txn()?;
+            match self.db_event_addr_index()?.get(&txn, key.as_slice())? {
+                Some(bytes) => AddrVal::from_bytes(bytes)?.into_parts().1,
+                None => return Ok(None),
+            }
+        };
+
+        if let Some(event) = self.read_event(id)? {
+            return Ok(Some(event));
+        }
+
+        // The indexed event was deleted, so fall back to a scan for any older one
+        let mut filter = Filter::new();
+        filter.add_event_kind(kind);
+        filter.add_author(author);
+        Ok(self
+            .find_events_by_filter(&filter, |e| e.parameter().as_deref() == Some(d))?
+            .first()
+            .cloned())
+    }
+
+    /// Get the NIP-58 badges that `pubkey` chose to show on their profile.
+    ///
+    /// Badges whose definition or award we don't have, or whose award did not come
+    /// from the author of the definition, are left out.
+    pub fn get_badges_for(&self, pubkey: PublicKey) -> Result<Vec<Badge>, Error> {
+        // Only the person themselves can choose their badges
+        let profile_badges = match self.get_replaceable_event(
+            EventKind::ProfileBadges,
+            pubkey,
+            crate::badges::PROFILE_BADGES_D,
+        )? {
+            Some(event) if event.pubkey == pubkey => event,
+            _ => return Ok(vec![]),
+        };
+
+        let mut badges: Vec<Badge> = Vec::new();
+        for (naddr, award_id) in crate::badges::profile_badge_pairs(&profile_badges) {
+            match self.read_event(award_id)? {
+                Some(award) if crate::badges::is_valid_award(&award, &naddr, pubkey) => {}
+                _ => continue,
+            }
+            let definition = match self.get_replaceable_event(
+                EventKind::BadgeDefinition,
+                naddr.author,
+                &naddr.d,
+            )? {
+                Some(event) => event,
+                None => continue,
+            };
+            if let Some(badge) = Badge::from_definition(&definition, naddr, award_id) {
+                badges.push(badge);
+            }
+        }
+
+        Ok(badges)
+    }
+
+    /// Get the current results of the NIP-88 poll `id`, if we have the poll.
+    ///
+    /// Only the latest response of each person counts, and only if it was made
+    /// before the poll ended.
+    pub fn get_poll_results(&self, id: Id) -> Result<Option<PollResults>, Error> {
+        let poll = match self.read_event(id)? {
+            Some(event) => match Poll::from_event(&event) {
+                Some(poll) => poll,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        let mut responses: Vec<Event> = Vec::new();
+        for (related, rel) in self.find_relationships_by_id(id)? {
+            if rel == RelationshipById::RespondsToPoll {
+                if let Some(event) = self.read_event(related)? {
+                    responses.push(event);
+                }
+            }
+        }
+
+        Ok(Some(PollResults::tally(
+            poll,
+            &responses,
+            GLOBALS.identity.public_key(),
+        )))
+    }
+
+    /// Get the NIP-34 repository announced at `addr`, if we have its announcement
+    pub fn get_repo(&self, addr: &NAddr) -> Result<Option<Repo>, Error> {
+        Ok(self
+            .get_addressable_event(addr.kind, addr.author, &addr.d)?
+            .and_then(|event| Repo::from_event(&event)))
+    }
+
+    /// Get the NIP-34 patches for the repository announced at `addr`, newest first
+    pub fn get_repo_patches(&self, addr: &NAddr) -> Result<Vec<Event>, Error> {
+        self.find_repo_events(addr, crate::git::PATCH_KIND.into())
+    }
+
+    /// Get the NIP-34 issues for the repository announced at `addr`, newest first
+    pub fn get_repo_issues(&self, addr: &NAddr) -> Result<Vec<Event>, Error> {
+        self.find_repo_events(addr, crate::git::ISSUE_KIND.into())
+    }
+
+    // Events of `kind` that name the repository at `addr` in an `a` tag
+    fn find_repo_events(&self, addr: &NAddr, kind: EventKind) -> Result<Vec<Event>, Error> {
+        let mut filter = Filter {
+            kinds: vec![kind],
+            ..Default::default()
+        };
+        filter.set_tag_values('a', vec![crate::git::repo_tag_value(addr)]);
+        self.find_events_by_filter(&filter, |_| true)
+    }
+
+    /// Get the NIP-72 community defined at `addr`, if we have its definition
+    pub fn get_community(&self, addr: &NAddr) -> Result<Option<Community>, Error> {
+        Ok(self
+            .get_addressable_event(addr.kind, addr.author, &addr.d)?
+            .and_then(|event| Community::from_event(&event)))
+    }
+
+    /// Get the community that approved this post, if any of the communities it
+    /// was posted to have approved it. Only approvals by the community's moderators
+    /// count.
+    pub fn get_community_approval(&self, event: &Event) -> Result<Option<Community>, Error> {
+        let communities = crate::community::communities_of(event);
+        for (_, rel) in self.find_relationships_by_id(event.id)? {
+            if let RelationshipById::Approves {
+                by,
+                community_author,
+                community_d,
+            } = rel
+            {
+                let addr = match communities
+                    .iter()
+                    .find(|a| a.author == community_author && a.d == community_d)
+                {
+                    Some(addr) => addr,
+                    None => continue, // not a community it was posted to
+                };
+                if let Some(community) = self.get_community(addr)? {
+                    if community.is_moderator(by) {
+                        return Ok(Some(community));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the reports of an event by people we follow (or ourselves), or seen
+    /// on a relay whose content we trust. Only the latest report of each type
+    /// by each person is kept.
+    pub fn get_reports_for(&self, id: Id) -> Result<Vec<Report>, Error> {
+        let author = self.read_event(id)?.map(|e| e.pubkey);
+
+        let mut reports: Vec<Report> = Vec::new();
+        for (related, rel) in self.find_relationships_by_id(id)? {
+            if let RelationshipById::Reports(_) = rel {
+                if let Some(event) = self.read_event(related)? {
+                    if crate::reports::is_trusted_labeler(event.pubkey)
+                        || self.is_seen_on_trusted_relay(related)?
+                    {
+                        reports.extend(Report::of_event(&event, id, author));
+                    }
+                }
+            }
+        }
+
+        reports.sort_by(|a, b| {
+            (a.by, &a.report_type)
+                .cmp(&(b.by, &b.report_type))
+                .then(b.created_at.cmp(&a.created_at))
+        });
+        reports.dedup_by(|a, b| a.by == b.by && a.report_type == b.report_type);
+        Ok(reports)
+    }
+
+    /// Get the reports of a person by people we follow (or ourselves). Only the
+    /// latest report of each type by each person is kept.
+    pub fn get_reports_for_pubkey(&self, pubkey: PublicKey) -> Result<Vec<Report>, Error> {
+        let mut reports: Vec<Report> = self
+            .find_events_by_filter(
+                &self.trusted_labelers_filter(EventKind::Reporting, pubkey)?,
+                |_| true,
+            )?
+            .iter()
+            .filter_map(|event| Report::of_person(event, pubkey))
+            .collect();
+
+        reports.sort_by(|a, b| {
+            (a.by, &a.report_type)
+                .cmp(&(b.by, &b.report_type))
+                .then(b.created_at.cmp(&a.created_at))
+        });
+        reports.dedup_by(|a, b| a.by == b.by && a.report_type == b.report_type);
+        Ok(reports)
+    }
+
+    /// Get the labels put on an event by people we follow (or ourselves)
+    pub fn get_labels_for(&self, id: Id) -> Result<Vec<Label>, Error> {
+        let mut labels: Vec<Label> = Vec::new();
+        for (related, rel) in self.find_relationships_by_id(id)? {
+            if let RelationshipById::Labels { .. } = rel {
+                if let Some(event) = self.read_event(related)? {
+                    if crate::reports::is_trusted_labeler(event.pubkey) {
+                        labels.extend(Label::of_event(&event, id));
+                    }
+                }
+            }
+        }
+        Ok(Self::dedup_labels(labels))
+    }
+
+    /// Get the labels put on a person by people we follow (or ourselves)
+    pub fn get_labels_for_pubkey(&self, pubkey: PublicKey) -> Result<Vec<Label>, Error> {
+        let labels: Vec<Label> = self
+            .find_events_by_filter(
+                &self.trusted_labelers_filter(EventKind::Label, pubkey)?,
+                |_| true,
+            )?
+            .iter()
+            .flat_map(|event| Label::of_person(event, pubkey))
+            .collect();
+        Ok(Self::dedup_labels(labels))
     }
 
-    /// Get the matching replaceable event (possibly parameterized)
-    /// TBD: optimize this by storing better event indexes
-    pub fn get_replaceable_event(
-        &self,
-        kind: EventKind,
-        pubkey: PublicKey,
-        parameter: &str,
-    ) -> Result<Option<Event>, Error> {
-        if !kind.is_replaceable() {
-            return Err(ErrorKind::General("Event kind is not replaceable".to_owned()).into());
+    // Events of `kind` tagging `pubkey` by people we follow (or ourselves)
+    fn trusted_labelers_filter(&self, kind: EventKind, pubkey: PublicKey) -> Result<Filter, Error> {
+        let mut authors: Vec<PublicKey> = self
+            .get_people_in_list(PersonList::Followed)?
+            .drain(..)
+            .map(|(pk, _)| pk)
+            .collect();
+        if let Some(pk) = GLOBALS.identity.public_key() {
+            authors.push(pk);
         }
 
-        let mut filter = Filter::new();
-        filter.add_event_kind(kind);
-        filter.add_author(pubkey);
+        let mut filter = Filter {
+            authors,
+            kinds: vec![kind],
+            ..Default::default()
+        };
+        filter.set_tag_values('p', vec![pubkey.as_hex_string()]);
+        Ok(filter)
+    }
 
-        Ok(self
-            .find_events_by_filter(&filter, |e| {
-                if kind.is_parameterized_replaceable() {
-                    e.parameter().as_deref() == Some(parameter)
-                } else {
-                    true
-                }
-            })?
-            .first()
-            .cloned())
+    fn dedup_labels(mut labels: Vec<Label>) -> Vec<Label> {
+        labels.sort_by(|a, b| {
+            (a.by, &a.namespace, &a.label)
+                .cmp(&(b.by, &b.namespace, &b.label))
+                .then(b.created_at.cmp(&a.created_at))
+        });
+        labels.dedup_by(|a, b| a.by == b.by && a.namespace == b.namespace && a.label == b.label);
+        labels
     }
 
     /// Find events by filter.
@@ -1604,7 +2857,7 @@ impl Storage {
     where
         F: Fn(&Event) -> bool,
     {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
 
         // We insert into a BTreeSet to keep them time-ordered
         let mut output: BTreeSet<Event> = BTreeSet::new();
@@ -1842,7 +3095,7 @@ impl Storage {
             .case_insensitive(true)
             .build()?;
 
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let iter = self.db_events()?.iter(&txn)?;
         let mut events: Vec<Event> = Vec::new();
         for result in iter {
@@ -1887,6 +3140,42 @@ impl Storage {
         self.switch_to_rumor3(event, txn)
     }
 
+    // We don't call this externally. Whenever we write an event, we do this
+    fn write_event_addr_index<'a>(
+        &'a self,
+        event: &Event,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        // Only index addressable events
+        if !event.kind.is_parameterized_replaceable() {
+            return Ok(());
+        }
+        let d = match event.parameter() {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let key = AddrKey::from_parts(event.kind, event.pubkey, &d);
+
+        // Keep the entry if it already points to a newer event
+        let newer = match self.db_event_addr_index()?.get(txn, key.as_slice())? {
+            Some(bytes) => AddrVal::from_bytes(bytes)?.into_parts().0 > event.created_at,
+            None => false,
+        };
+        if !newer {
+            let val = AddrVal::from_parts(event.created_at, event.id);
+            self.db_event_addr_index()?
+                .put(txn, key.as_slice(), val.as_slice())?;
+        }
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
     // We don't call this externally. Whenever we write an event, we do this
     fn write_event_akci_index<'a>(
         &'a self,
@@ -1980,6 +3269,17 @@ impl Storage {
         self.write_relationship_by_id2(id, related, relationship_by_id, rw_txn)
     }
 
+    /// Delete a relationship between two events
+    #[inline]
+    pub(crate) fn delete_relationship_by_id<'a>(
+        &'a self,
+        id: Id,
+        related: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.delete_relationship_by_id2(id, related, rw_txn)
+    }
+
     /// Find relationships belonging to the given event
     ///
     /// The found Ids relates to the passed in Id,
@@ -2099,57 +3399,217 @@ impl Storage {
             .collect())
     }
 
-    /// Returns the reaction data without processing
-    pub fn get_reactions_raw(&self, id: Id) -> Result<Vec<(PublicKey, String)>, Error> {
-        let mut output: Vec<(PublicKey, String)> = Vec::new();
-        for (_subid, rel) in self.find_relationships_by_id(id)? {
+    /// Read the stored summary of reactions to an event, if it has one. This
+    /// leaves out reactions not yet summarized, see
+    /// [get_reaction_summary](Self::get_reaction_summary) for all of them.
+    #[inline]
+    pub fn read_reaction_summary<'a>(
+        &'a self,
+        id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<Option<ReactionSummary>, Error> {
+        self.read_reaction_summary1(id, rw_txn)
+    }
+
+    /// Write the summary of reactions to an event
+    #[inline]
+    pub(crate) fn write_reaction_summary<'a>(
+        &'a self,
+        id: Id,
+        summary: &ReactionSummary,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        self.write_reaction_summary1(id, summary, rw_txn)
+    }
+
+    /// Record a reaction to an event
+    ///
+    /// Reactions are normally kept as relationships. Once an event has more than
+    /// `reaction_summary_threshold` reactions they are folded into a
+    /// [ReactionSummary](crate::ReactionSummary) instead, and if
+    /// `prune_summarized_reactions` is set the reaction events themselves are deleted.
+    /// Recording the same reaction twice, or one that its author already deleted,
+    /// does nothing.
+    pub(crate) fn write_reaction<'a>(
+        &'a self,
+        target: Id,
+        reaction_id: Id,
+        by: PublicKey,
+        reaction: String,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let threshold = self.read_setting_reaction_summary_threshold();
+        let prune = self.read_setting_prune_summarized_reactions();
+
+        // Deletes may arrive before the reaction they delete
+        let deleted =
+            self.find_relationships_by_id(reaction_id)?
+                .iter()
+                .any(|(_, rel)| match rel {
+                    RelationshipById::Deletes { by: deleter, .. } => *deleter == by,
+                    _ => false,
+                });
+        if deleted {
+            return Ok(());
+        }
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        if let Some(mut summary) = self.read_reaction_summary(target, Some(txn))? {
+            summary.add(reaction_id, by, reaction);
+            self.write_reaction_summary(target, &summary, Some(txn))?;
+            if prune {
+                self.prune_summarized_reaction(target, reaction_id, txn)?;
+            }
+        } else {
+            let existing: Vec<(Id, PublicKey, String)> = self
+                .find_relationships_by_id(target)?
+                .drain(..)
+                .filter_map(|(id, rel)| match rel {
+                    RelationshipById::ReactsTo { by, reaction } if id != reaction_id => {
+                        Some((id, by, reaction))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            if threshold == 0 || (existing.len() as u64) < threshold {
+                self.write_relationship_by_id(
+                    target,
+                    reaction_id,
+                    RelationshipById::ReactsTo { by, reaction },
+                    Some(txn),
+                )?;
+            } else {
+                let mut summary = ReactionSummary::default();
+                for (id, by, reaction) in existing {
+                    summary.add(id, by, reaction);
+                    self.delete_relationship_by_id(target, id, Some(txn))?;
+                    if prune {
+                        self.prune_summarized_reaction(target, id, txn)?;
+                    }
+                }
+                summary.add(reaction_id, by, reaction);
+                self.write_reaction_summary(target, &summary, Some(txn))?;
+                if prune {
+                    self.prune_summarized_reaction(target, reaction_id, txn)?;
+                }
+            }
+        }
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    // Delete a summarized reaction event, remembering which summary counts it
+    fn prune_summarized_reaction<'a>(
+        &'a self,
+        target: Id,
+        reaction_id: Id,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        self.delete_event(reaction_id, Some(txn))?;
+        self.write_summarized_reaction1(reaction_id, target, Some(txn))?;
+        Ok(())
+    }
+
+    /// Take a deleted reaction out of the summary it was counted in. `target` is the
+    /// event it reacts to, if we still have the reaction to know that.
+    ///
+    /// Returns the event whose summary changed, if any.
+    pub(crate) fn delete_summarized_reaction<'a>(
+        &'a self,
+        reaction_id: Id,
+        by: PublicKey,
+        target: Option<Id>,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<Option<Id>, Error> {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let target = match target {
+            Some(target) => Some(target),
+            None => self.read_summarized_reaction1(reaction_id, Some(txn))?,
+        };
+
+        let mut changed = None;
+        if let Some(target) = target {
+            if let Some(mut summary) = self.read_reaction_summary(target, Some(txn))? {
+                if summary.remove(reaction_id, by) {
+                    self.write_reaction_summary(target, &summary, Some(txn))?;
+                    self.delete_summarized_reaction1(reaction_id, Some(txn))?;
+                    changed = Some(target);
+                }
+            }
+        }
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(changed)
+    }
+
+    /// Get a summary of the reactions to an event, whether or not they have been
+    /// summarized in storage yet
+    pub fn get_reaction_summary(&self, id: Id) -> Result<ReactionSummary, Error> {
+        let mut summary = self.read_reaction_summary(id, None)?.unwrap_or_default();
+        for (related, rel) in self.find_relationships_by_id(id)? {
             if let RelationshipById::ReactsTo { by, reaction } = rel {
-                output.push((by, reaction));
+                summary.add(related, by, reaction);
             }
         }
-        Ok(output)
+        Ok(summary)
+    }
+
+    /// Returns the reaction data without processing
+    ///
+    /// For summarized events this only includes the remembered reactors.
+    pub fn get_reactions_raw(&self, id: Id) -> Result<Vec<(PublicKey, String)>, Error> {
+        Ok(self
+            .get_reaction_summary(id)?
+            .reactors
+            .into_iter()
+            .map(|(_, by, reaction)| (by, reaction))
+            .collect())
     }
 
     /// Returns the list of reactions and whether or not this account has already reacted to this event
     #[allow(clippy::type_complexity)]
     pub fn get_reactions(&self, id: Id) -> Result<(Vec<(char, usize)>, Option<char>), Error> {
+        fn symbol(reaction: &str) -> char {
+            if reaction.is_empty() {
+                '+'
+            } else if reaction.starts_with(":") && reaction.ends_with(":") {
+                '□' // placeholder for custom reaction that we don't support
+            } else {
+                reaction.chars().next().unwrap()
+            }
+        }
+
         // Whether or not the Gossip user already reacted to this event
         let mut our_reaction: Option<char> = None;
 
         // Get the event (once self-reactions get deleted we can remove this)
         let maybe_target_event = self.read_event(id)?;
 
-        // Collect up to one reaction per pubkey
-        let mut phase1: HashMap<PublicKey, char> = HashMap::new();
-        for (_, rel) in self.find_relationships_by_id(id)? {
-            if let RelationshipById::ReactsTo { by, reaction } = rel {
-                if let Some(target_event) = &maybe_target_event {
-                    if target_event.pubkey == by {
-                        // Do not let people like their own post
-                        continue;
-                    }
-                }
-                let symbol: char = if reaction.is_empty() {
-                    '+'
-                } else if reaction.starts_with(":") && reaction.ends_with(":") {
-                    '□' // placeholder for custom reaction that we don't support
-                } else {
-                    reaction.chars().next().unwrap()
-                };
-                phase1.insert(by, symbol);
-                if Some(by) == GLOBALS.identity.public_key() {
-                    our_reaction = Some(symbol);
-                }
-            }
-        }
+        // The summary has at most one reaction per pubkey
+        let summary = self.get_reaction_summary(id)?;
 
         // Collate by reaction
         let mut output: HashMap<char, usize> = HashMap::new();
-        for (_, symbol) in phase1 {
-            output
-                .entry(symbol)
-                .and_modify(|count| *count += 1)
-                .or_insert_with(|| 1);
+        for (_, by, reaction) in summary.reactors.iter().chain(summary.overflow.iter()) {
+            if let Some(target_event) = &maybe_target_event {
+                if target_event.pubkey == *by {
+                    // Do not let people like their own post
+                    continue;
+                }
+            }
+            let symbol = symbol(reaction);
+            if Some(*by) == GLOBALS.identity.public_key() {
+                our_reaction = Some(symbol);
+            }
+            *output.entry(symbol).or_insert(0) += 1;
         }
 
         let mut v: Vec<(char, usize)> = output.drain().collect();
@@ -2240,6 +3700,82 @@ impl Storage {
         Ok(annotations)
     }
 
+    /// Get the replies, reactions, zaps, reposts and deletions of an event.
+    ///
+    /// This reads the relationship indexes, not the events, except to check
+    /// deletions. Replies are in no particular order and are not spam filtered,
+    /// see [get_replies](Storage::get_replies) for that. If we don't have the event
+    /// itself, replies to its address and deletions are left out.
+    pub fn get_relationships(&self, id: Id) -> Result<Relationships, Error> {
+        let mut relationships = Relationships {
+            reactions: self.read_reaction_summary(id, None)?.unwrap_or_default(),
+            ..Default::default()
+        };
+        let maybe_event = self.read_event(id)?;
+
+        let mut annotations: Vec<Id> = Vec::new();
+        for (related, rel) in self.find_relationships_by_id(id)? {
+            match rel {
+                RelationshipById::RepliesTo => relationships.replies.push(related),
+                RelationshipById::Annotates => annotations.push(related),
+                RelationshipById::ReactsTo { by, reaction } => {
+                    relationships.reactions.add(related, by, reaction)
+                }
+                RelationshipById::Zaps { by, amount } => {
+                    relationships.zaps.push((related, by, amount))
+                }
+                RelationshipById::Reposts => relationships.reposts.push(related),
+                RelationshipById::Deletes { by, reason } => {
+                    if let Some(event) = &maybe_event {
+                        if event.delete_author_allowed(by) {
+                            relationships.deletions.push((related, reason));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for annotation in annotations {
+            relationships
+                .replies
+                .extend(self.get_non_replaceable_replies(annotation)?);
+        }
+
+        if let Some(event) = &maybe_event {
+            // Replies to its address, and deletions of it by address
+            if event.kind.is_replaceable() {
+                let addr = NAddr {
+                    d: event.parameter().unwrap_or("".to_string()),
+                    relays: vec![],
+                    kind: event.kind,
+                    author: event.pubkey,
+                };
+                for (related, rel) in self.find_relationships_by_addr(&addr)? {
+                    match rel {
+                        RelationshipByAddr::RepliesTo => relationships.replies.push(related),
+                        RelationshipByAddr::Deletes { by, reason } => {
+                            if event.delete_author_allowed(by) {
+                                relationships.deletions.push((related, reason));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            // Deletions must come after the event
+            relationships.deletions.retain(|(deleting_id, _)| {
+                matches!(
+                    self.read_event(*deleting_id),
+                    Ok(Some(deleting)) if deleting.created_at > event.created_at
+                )
+            });
+        }
+
+        Ok(relationships)
+    }
+
     /// Read a PersonRelay record
     #[inline]
     pub fn read_person_relay(
@@ -2265,6 +3801,29 @@ impl Storage {
         self.write_person_relay2(person_relay, rw_txn)
     }
 
+    /// Write many person relay records in one transaction, such as when importing
+    /// a follow list with relay hints
+    pub fn write_person_relays<'a>(
+        &'a self,
+        person_relays: &[PersonRelay],
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        for person_relay in person_relays {
+            // Don't save banned relay URLs
+            if Self::url_is_banned(&person_relay.url) {
+                continue;
+            }
+            self.write_person_relay2(person_relay, Some(txn))?;
+        }
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
     /// Modify a specific person relay record
     pub fn modify_person_relay<'a, M>(
         &'a self,
@@ -2420,7 +3979,7 @@ impl Storage {
                     if time > dmcdata.latest_message_created_at {
                         dmcdata.latest_message_created_at = time;
                         dmcdata.latest_message_content =
-                            GLOBALS.identity.decrypt_event_contents(event).ok();
+                            GLOBALS.dm_cache.decrypt_event_contents(event).ok();
                     }
                     dmcdata.message_count += 1;
                     dmcdata.unread_message_count += unread;
@@ -2431,7 +3990,7 @@ impl Storage {
                             dm_channel: dmchannel,
                             latest_message_created_at: time,
                             latest_message_content: GLOBALS
-                                .identity
+                                .dm_cache
                                 .decrypt_event_contents(event)
                                 .ok(),
                             message_count: 1,
@@ -2440,7 +3999,7 @@ impl Storage {
                     );
                 }
             } else if event.kind == EventKind::GiftWrap {
-                if let Ok(rumor) = GLOBALS.identity.unwrap_giftwrap(event) {
+                if let Ok(rumor) = GLOBALS.dm_cache.unwrap_giftwrap(event) {
                     let rumor_event = rumor.into_event_with_bad_signature();
                     let time = rumor_event.created_at;
                     let dmchannel = match DmChannel::from_event(&rumor_event, Some(my_pubkey)) {
@@ -2504,7 +4063,7 @@ impl Storage {
             .drain(..)
             .map(|e| {
                 if e.kind == EventKind::GiftWrap {
-                    if let Ok(rumor) = GLOBALS.identity.unwrap_giftwrap(&e) {
+                    if let Ok(rumor) = GLOBALS.dm_cache.unwrap_giftwrap(&e) {
                         (rumor.created_at, e)
                     } else {
                         (e.created_at, e)
@@ -2529,12 +4088,13 @@ impl Storage {
         let txn = maybe_local_txn!(self, rw_txn, local_txn);
 
         // Erase all indices first
+        self.db_event_addr_index()?.clear(txn)?;
         self.db_event_akci_index()?.clear(txn)?;
         self.db_event_kci_index()?.clear(txn)?;
         self.db_event_tci_index()?.clear(txn)?;
         self.db_hashtags()?.clear(txn)?;
 
-        let loop_txn = self.env.read_txn()?;
+        let loop_txn = self.get_read_txn()?;
         for result in self.db_events()?.iter(&loop_txn)? {
             let (_key, val) = result?;
             let event = Event::read_from_buffer(val)?;
@@ -2561,6 +4121,7 @@ impl Storage {
                 &event, // this handles giftwrap internally
                 Some(txn),
             )?;
+            self.write_event_addr_index(&event, Some(txn))?;
             for hashtag in event.hashtags() {
                 if hashtag.is_empty() {
                     continue;
@@ -2585,7 +4146,7 @@ impl Storage {
         // Erase the index first
         self.db_event_tci_index()?.clear(txn)?;
 
-        let loop_txn = self.env.read_txn()?;
+        let loop_txn = self.get_read_txn()?;
         for result in self.db_events()?.iter(&loop_txn)? {
             let (_key, val) = result?;
             let event = Event::read_from_buffer(val)?;
@@ -2750,7 +4311,7 @@ impl Storage {
         let txn = maybe_local_txn!(self, rw_txn, local_txn);
 
         // Iterate through all events
-        let loop_txn = self.env.read_txn()?;
+        let loop_txn = self.get_read_txn()?;
         for result in self.db_events()?.iter(&loop_txn)? {
             let (_key, val) = result?;
             let event = Event::read_from_buffer(val)?;
@@ -2827,7 +4388,7 @@ impl Storage {
             Some(x) => x,
             None => {
                 local_txn = Some(GLOBALS.db().get_write_txn()?);
-                local_txn.as_mut().unwrap()
+                &mut **local_txn.as_mut().unwrap()
             }
         };
 
@@ -2886,3 +4447,317 @@ impl Storage {
         Ok(iter)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ops::Deref;
+    use std::path::PathBuf;
+
+    /// A Storage in a fresh directory under the temp dir. The directory is
+    /// removed when this is dropped.
+    pub(super) struct TestStorage {
+        storage: Option<Storage>,
+        dir: PathBuf,
+    }
+
+    impl TestStorage {
+        pub(super) fn new(name: &str) -> TestStorage {
+            Self::with_map_size(name, 1048576 * 16)
+        }
+
+        pub(super) fn with_map_size(name: &str, map_size: usize) -> TestStorage {
+            let dir = crate::test_util::temp_dir(name);
+            let env = Storage::new_env(&dir, false, map_size).unwrap();
+            TestStorage {
+                storage: Some(Storage::from_env(env)),
+                dir,
+            }
+        }
+    }
+
+    impl Deref for TestStorage {
+        type Target = Storage;
+
+        fn deref(&self) -> &Storage {
+            self.storage.as_ref().unwrap()
+        }
+    }
+
+    impl Drop for TestStorage {
+        fn drop(&mut self) {
+            // Close the environment before removing its files
+            self.storage = None;
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn test_grow_map_when_full() {
+        // A tiny map that fills up quickly
+        let storage = TestStorage::with_map_size("grow_map", 1048576);
+        let db: RawDatabase = {
+            let mut txn = storage.env.write_txn().unwrap();
+            let db = storage
+                .env
+                .database_options()
+                .types::<Bytes, Bytes>()
+                .name("test")
+                .create(&mut txn)
+                .unwrap();
+            txn.commit().unwrap();
+            db
+        };
+
+        let write = |n: u32| -> Result<(), heed::Error> {
+            let mut txn = storage.env.write_txn()?;
+            db.put(&mut txn, &n.to_be_bytes(), &[0; 4096])?;
+            txn.commit()
+        };
+
+        let mut n: u32 = 0;
+        loop {
+            match write(n) {
+                Ok(()) => n += 1,
+                Err(heed::Error::Mdb(heed::MdbError::MapFull)) => break,
+                Err(e) => panic!("{e}"),
+            }
+            assert!(n < 10_000, "map never filled up");
+        }
+
+        // No transactions are active
+        assert!(unsafe { storage.grow_map_if_needed() }.unwrap());
+        assert!(storage.env.info().map_size > 1048576);
+        write(n).unwrap();
+    }
+
+    #[test]
+    fn test_grow_map_at_runtime() {
+        // A tiny map that fills up quickly
+        let storage = TestStorage::with_map_size("grow_map_at_runtime", 1048576);
+        let db: RawDatabase = {
+            let mut txn = storage.get_write_txn().unwrap();
+            let db = storage
+                .env
+                .database_options()
+                .types::<Bytes, Bytes>()
+                .name("test")
+                .create(&mut txn)
+                .unwrap();
+            txn.commit().unwrap();
+            db
+        };
+
+        let write = |n: u32| -> Result<(), Error> {
+            let mut txn = storage.get_write_txn()?;
+            db.put(&mut txn, &n.to_be_bytes(), &[0; 4096])?;
+            txn.commit()?;
+            Ok(())
+        };
+        let is_map_full = |e: &Error| {
+            matches!(
+                e.kind,
+                ErrorKind::Lmdb(heed::Error::Mdb(heed::MdbError::MapFull))
+            )
+        };
+
+        // While we hold a transaction, the map can't grow
+        let mut n: u32 = 0;
+        {
+            let _txn = storage.get_read_txn().unwrap();
+            loop {
+                match storage.with_map_growth(|| write(n)) {
+                    Ok(()) => n += 1,
+                    Err(e) if is_map_full(&e) => break,
+                    Err(e) => panic!("{e}"),
+                }
+                assert!(n < 10_000, "map never filled up");
+            }
+        }
+        assert_eq!(storage.env.info().map_size, 1048576);
+
+        // Once it is closed, the failed write grows the map and goes through
+        storage.with_map_growth(|| write(n)).unwrap();
+        let map_size = storage.env.info().map_size;
+        assert!(map_size > 1048576);
+
+        // Fill it up again
+        loop {
+            n += 1;
+            match write(n) {
+                Ok(()) => (),
+                Err(e) if is_map_full(&e) => break,
+                Err(e) => panic!("{e}"),
+            }
+            assert!(n < 100_000, "map never filled up");
+        }
+
+        // Writes sharing a transaction are run again together in a new one
+        storage
+            .write_with_map_growth(|txn| {
+                db.put(txn, &n.to_be_bytes(), &[0; 4096])?;
+                db.put(txn, &(n + 1).to_be_bytes(), &[0; 4096])?;
+                Ok(())
+            })
+            .unwrap();
+        assert!(storage.env.info().map_size > map_size);
+        let txn = storage.get_read_txn().unwrap();
+        assert!(db.get(&txn, &(n + 1).to_be_bytes()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_write_person_relays_in_bulk() {
+        let storage = TestStorage::new("person_relays");
+        let _ = storage.db_person_relays2().unwrap();
+
+        let urls: Vec<RelayUrl> = (0..3)
+            .map(|n| RelayUrl::try_from_str(&format!("wss://relay{}.example/", n)).unwrap())
+            .collect();
+        let pubkeys: Vec<PublicKey> = (0..200)
+            .map(|_| nostr_types::PrivateKey::generate().public_key())
+            .collect();
+        let mut person_relays: Vec<PersonRelay> = Vec::new();
+        for pubkey in pubkeys.iter() {
+            for url in urls.iter() {
+                let mut pr = PersonRelay::new(*pubkey, url.clone());
+                pr.last_suggested = Some(1000);
+                person_relays.push(pr);
+            }
+        }
+
+        let mut txn = storage.get_write_txn().unwrap();
+        storage
+            .write_person_relays(&person_relays, Some(&mut txn))
+            .unwrap();
+        txn.commit().unwrap();
+
+        for pubkey in pubkeys.iter() {
+            let prs = storage.get_person_relays(*pubkey).unwrap();
+            assert_eq!(prs.len(), urls.len());
+            assert!(prs.iter().all(|pr| pr.last_suggested == Some(1000)));
+        }
+    }
+
+    #[test]
+    fn test_drafts() {
+        let storage = TestStorage::new("drafts");
+        let _ = storage.db_drafts1().unwrap();
+
+        let draft = |id: u64, content: &str, saved_at: i64| Draft1 {
+            id,
+            content: content.to_owned(),
+            subject: None,
+            content_warning: None,
+            replying_to: None,
+            dm_channel: None,
+            annotation: false,
+            saved_at: Unixtime(saved_at),
+        };
+
+        storage.save_draft(&draft(1, "first", 100), None).unwrap();
+        storage.save_draft(&draft(2, "second", 200), None).unwrap();
+        // Saving again replaces the earlier save
+        storage
+            .save_draft(&draft(1, "first, edited", 300), None)
+            .unwrap();
+
+        let drafts = storage.load_drafts().unwrap();
+        assert_eq!(drafts.len(), 2);
+        assert_eq!(drafts[0], draft(1, "first, edited", 300));
+        assert_eq!(drafts[1], draft(2, "second", 200));
+
+        storage.delete_draft(1, None).unwrap();
+        let drafts = storage.load_drafts().unwrap();
+        assert_eq!(drafts, vec![draft(2, "second", 200)]);
+    }
+
+    #[test]
+    fn test_migration_status_and_rebuilds() {
+        let storage = TestStorage::new("migrations");
+
+        storage.write_migration_level(50, None).unwrap();
+        let status = storage.migration_status().unwrap();
+        assert_eq!(status.first().map(|m| m.level), Some(23));
+        assert!(status.iter().all(|m| m.applied == (m.level <= 50)));
+
+        storage
+            .set_rebuild_pending(Rebuild::TagIndex, true, None)
+            .unwrap();
+        assert!(storage.is_rebuild_pending(Rebuild::TagIndex));
+        assert!(storage.get_flag_rebuild_tag_index_needed());
+        storage
+            .set_rebuild_pending(Rebuild::TagIndex, false, None)
+            .unwrap();
+        assert!(!storage.is_rebuild_pending(Rebuild::TagIndex));
+    }
+
+    #[test]
+    fn test_migrate_from_baseline() {
+        use speedy::Writable;
+
+        let storage = TestStorage::new("migrate_baseline");
+
+        // A database as the last release left it: relays in the relays3 table,
+        // once under a non-canonical URL, and the old relays-per-person setting
+        storage.write_migration_level(47, None).unwrap();
+        let url1 = RelayUrl::try_from_str("wss://relay.example:443/nostr/").unwrap();
+        let url2 = RelayUrl::try_from_str("wss://relay.example/nostr").unwrap();
+        let mut relay = types::Relay3::new(url1);
+        relay.success_count = 3;
+        relay.rank = 5;
+        storage.write_relay3(&relay, None).unwrap();
+        let mut relay = types::Relay3::new(url2.clone());
+        relay.success_count = 4;
+        relay.allow_connect = Some(true);
+        storage.write_relay3(&relay, None).unwrap();
+        {
+            let mut txn = storage.get_write_txn().unwrap();
+            storage
+                .db_general()
+                .unwrap()
+                .put(
+                    &mut txn,
+                    b"num_relays_per_person",
+                    &4u8.write_to_vec().unwrap(),
+                )
+                .unwrap();
+            txn.commit().unwrap();
+        }
+
+        storage.migrate(47).unwrap();
+
+        assert_eq!(
+            storage.read_migration_level().unwrap(),
+            Some(Storage::MAX_MIGRATION_LEVEL)
+        );
+        assert!(storage.filter_relays3(|_| true).unwrap().is_empty());
+        let relays = storage.filter_relays(|_| true).unwrap();
+        assert_eq!(relays.len(), 1);
+        assert_eq!(relays[0].url, url2);
+        assert_eq!(relays[0].success_count, 7);
+        assert_eq!(relays[0].rank, 5);
+        assert_eq!(relays[0].allow_connect, Some(true));
+        assert_eq!(relays[0].trust, Relay::DEFAULT_TRUST);
+        assert!(!relays[0].no_post);
+        assert_eq!(storage.read_setting_feed_relays_per_person(), 4);
+        assert_eq!(storage.read_setting_metadata_relays_per_person(), 4);
+        assert!(storage.get_flag_rebuild_indexes_needed());
+    }
+
+    #[test]
+    fn test_relay_urls_are_canonicalized() {
+        let storage = TestStorage::new("relay_urls");
+        let _ = storage.db_relays4().unwrap();
+
+        let url1 = RelayUrl::try_from_str("wss://relay.example:443/nostr/").unwrap();
+        let url2 = RelayUrl::try_from_str("wss://relay.example/nostr").unwrap();
+        assert_eq!(crate::relay::canonical_url(&url1), url2);
+
+        storage.write_relay_if_missing(&url1, None).unwrap();
+        storage.write_relay_if_missing(&url2, None).unwrap();
+        let relays = storage.filter_relays(|_| true).unwrap();
+        assert_eq!(relays.len(), 1);
+        assert_eq!(relays[0].url, url2);
+        assert!(storage.read_relay(&url1).unwrap().is_some());
+    }
+}
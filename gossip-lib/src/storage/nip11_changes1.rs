@@ -0,0 +1,82 @@
+use crate::error::Error;
+use crate::storage::types::Nip11Change1;
+use crate::storage::{RawDatabase, Storage, MAX_LMDB_KEY};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::RelayUrl;
+use std::sync::Mutex;
+
+// Url:At -> Nip11Change1
+//   key: key!(url.as_str().as_bytes(), at.to_be_bytes())
+//   val: serde_json::to_vec(change) | serde_json::from_slice(bytes)
+
+static NIP11_CHANGES1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut NIP11_CHANGES1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_nip11_changes1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = NIP11_CHANGES1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = NIP11_CHANGES1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = NIP11_CHANGES1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("nip11_changes")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                NIP11_CHANGES1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_nip11_change1<'a>(
+        &'a self,
+        change: &Nip11Change1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut key: Vec<u8> = change.url.as_str().as_bytes().to_owned();
+        key.truncate(MAX_LMDB_KEY - 8);
+        key.extend(change.at.to_be_bytes());
+        let bytes = serde_json::to_vec(change)?;
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_nip11_changes1()?.put(txn, &key, &bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn get_nip11_changes1(&self, url: &RelayUrl) -> Result<Vec<Nip11Change1>, Error> {
+        let mut start_key: Vec<u8> = url.as_str().as_bytes().to_owned();
+        start_key.truncate(MAX_LMDB_KEY - 8);
+        let txn = self.get_read_txn()?;
+        let mut output: Vec<Nip11Change1> = Vec::new();
+        for result in self.db_nip11_changes1()?.prefix_iter(&txn, &start_key)? {
+            let (_key, val) = result?;
+            let change: Nip11Change1 = serde_json::from_slice(val)?;
+            // Longer urls share the prefix
+            if change.url == *url {
+                output.push(change);
+            }
+        }
+        Ok(output)
+    }
+}
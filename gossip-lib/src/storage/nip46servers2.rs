@@ -30,7 +30,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -69,7 +69,7 @@ impl Storage {
         pubkey: PublicKey,
     ) -> Result<Option<Nip46Server>, Error> {
         let key = pubkey.as_bytes();
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         Ok(match self.db_nip46servers2()?.get(&txn, key)? {
             Some(bytes) => Some(Nip46Server::read_from_buffer(bytes)?),
             None => None,
@@ -77,7 +77,7 @@ impl Storage {
     }
 
     pub(crate) fn read_all_nip46servers2(&self) -> Result<Vec<Nip46Server>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut output: Vec<Nip46Server> = Vec::new();
         for result in self.db_nip46servers2()?.iter(&txn)? {
             let (_key, val) = result?;
@@ -0,0 +1,115 @@
+use crate::error::Error;
+use crate::storage::types::Notification1;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use speedy::{Readable, Writable};
+use std::sync::Mutex;
+
+// Notification key -> Notification
+//   key: notification.key()
+//   val: notification.write_to_vec() | Notification1::read_from_buffer(val)
+
+static NOTIFICATIONS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut NOTIFICATIONS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_notifications1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = NOTIFICATIONS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = NOTIFICATIONS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = NOTIFICATIONS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("notifications")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                NOTIFICATIONS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    /// Returns false (and writes nothing) if an equivalent notification already exists
+    pub(crate) fn write_notification1<'a>(
+        &'a self,
+        notification: &Notification1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<bool, Error> {
+        let key = notification.key();
+        let bytes = notification.write_to_vec()?;
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let new = self.db_notifications1()?.get(txn, &key)?.is_none();
+        if new {
+            self.db_notifications1()?.put(txn, &key, &bytes)?;
+        }
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(new)
+    }
+
+    pub(crate) fn filter_notifications1<F>(&self, f: F) -> Result<Vec<Notification1>, Error>
+    where
+        F: Fn(&Notification1) -> bool,
+    {
+        let txn = self.get_read_txn()?;
+        let mut output: Vec<Notification1> = Vec::new();
+        for result in self.db_notifications1()?.iter(&txn)? {
+            let (_key, val) = result?;
+            let notification = Notification1::read_from_buffer(val)?;
+            if f(&notification) {
+                output.push(notification);
+            }
+        }
+        output.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(output)
+    }
+
+    pub(crate) fn mark_notifications_read1<'a, F>(
+        &'a self,
+        f: F,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&Notification1) -> bool,
+    {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        // Collect first (modifying during iteration does not work in LMDB)
+        let mut updates: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        for result in self.db_notifications1()?.iter(txn)? {
+            let (key, val) = result?;
+            let mut notification = Notification1::read_from_buffer(val)?;
+            if !notification.read && f(&notification) {
+                notification.read = true;
+                updates.push((key.to_owned(), notification.write_to_vec()?));
+            }
+        }
+        for (key, bytes) in updates.drain(..) {
+            self.db_notifications1()?.put(txn, &key, &bytes)?;
+        }
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+}
@@ -32,7 +32,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -52,7 +52,7 @@ impl Storage {
         pubkey: &PublicKey,
     ) -> Result<HashMap<PersonList1, Private>, Error> {
         let key: Vec<u8> = pubkey.to_bytes();
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         Ok(match self.db_person_lists2()?.get(&txn, &key)? {
             None => HashMap::new(),
             Some(bytes) => HashMap::<PersonList1, Private>::read_from_buffer(bytes)?,
@@ -79,7 +79,7 @@ impl Storage {
     }
 
     pub(crate) fn get_people_in_all_followed_lists2(&self) -> Result<Vec<PublicKey>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut pubkeys: Vec<PublicKey> = Vec::new();
         for result in self.db_person_lists2()?.iter(&txn)? {
             let (key, val) = result?;
@@ -96,7 +96,7 @@ impl Storage {
         &self,
         list: PersonList1,
     ) -> Result<Vec<(PublicKey, Private)>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut output: Vec<(PublicKey, Private)> = Vec::new();
         for result in self.db_person_lists2()?.iter(&txn)? {
             let (key, val) = result?;
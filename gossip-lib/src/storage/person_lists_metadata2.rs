@@ -27,7 +27,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -78,7 +78,7 @@ impl Storage {
     pub(crate) fn get_all_person_list_metadata2(
         &self,
     ) -> Result<Vec<(PersonList1, PersonListMetadata2)>, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut output: Vec<(PersonList1, PersonListMetadata2)> = Vec::new();
         for result in self.db_person_lists_metadata2()?.iter(&txn)? {
             let (key, val) = result?;
@@ -30,7 +30,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -46,7 +46,7 @@ impl Storage {
     }
 
     pub(crate) fn get_person_relays2_size(&self) -> Result<usize, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let stat = self.db_person_relays2()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
@@ -81,7 +81,7 @@ impl Storage {
         let mut key = pubkey.to_bytes();
         key.extend(url.as_str().as_bytes());
         key.truncate(MAX_LMDB_KEY);
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         Ok(match self.db_person_relays2()?.get(&txn, &key)? {
             Some(bytes) => Some(PersonRelay2::read_from_buffer(bytes)?),
             None => None,
@@ -90,7 +90,7 @@ impl Storage {
 
     pub(crate) fn get_person_relays2(&self, pubkey: PublicKey) -> Result<Vec<PersonRelay2>, Error> {
         let start_key = pubkey.to_bytes();
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let iter = self.db_person_relays2()?.prefix_iter(&txn, &start_key)?;
         let mut output: Vec<PersonRelay2> = Vec::new();
         for result in iter {
@@ -103,7 +103,7 @@ impl Storage {
 
     pub(crate) fn have_persons_relays2(&self, pubkey: PublicKey) -> Result<bool, Error> {
         let start_key = pubkey.to_bytes();
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let iter = self.db_person_relays2()?.prefix_iter(&txn, &start_key)?;
         for result in iter {
             let (_key, val) = result?;
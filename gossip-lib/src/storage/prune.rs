@@ -37,7 +37,7 @@ impl Storage {
         let mut hashtag_deletions: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
         let mut relationship_deletions: Vec<Vec<u8>> = Vec::new();
         {
-            let txn = self.env.read_txn()?;
+            let txn = self.get_read_txn()?;
 
             // Extract the Ids of events to delete.
             for result in self.db_events()?.iter(&txn)? {
@@ -137,19 +137,19 @@ impl Storage {
                 "PRUNE: deleting {} records from event_seen_on_relay",
                 event_seen_on_relay_deletions.len()
             );
-            let mut txn = self.env.write_txn()?;
+            let mut txn = self.get_write_txn()?;
             for deletion in event_seen_on_relay_deletions.drain(..) {
                 self.db_event_seen_on_relay()?.delete(&mut txn, &deletion)?;
             }
             txn.commit()?;
 
             // Delete from event_viewed
-            let mut txn = self.env.write_txn()?;
+            let mut txn = self.get_write_txn()?;
             for (n, id) in ids.iter().enumerate() {
                 self.db_event_viewed()?.delete(&mut txn, id.as_slice())?;
                 if n % 100_000 == 0 {
                     txn.commit()?;
-                    txn = self.env.write_txn()?;
+                    txn = self.get_write_txn()?;
                 }
             }
             txn.commit()?;
@@ -160,7 +160,7 @@ impl Storage {
                 "PRUNE: deleting {} records from hashtags",
                 hashtag_deletions.len()
             );
-            let mut txn = self.env.write_txn()?;
+            let mut txn = self.get_write_txn()?;
             for deletion in hashtag_deletions.drain(..) {
                 self.db_hashtags()?
                     .delete_one_duplicate(&mut txn, &deletion.0, &deletion.1)?;
@@ -172,7 +172,7 @@ impl Storage {
                 "PRUNE: deleting {} relationships",
                 relationship_deletions.len()
             );
-            let mut txn = self.env.write_txn()?;
+            let mut txn = self.get_write_txn()?;
             for deletion in relationship_deletions.drain(..) {
                 self.db_relationships_by_id()?.delete(&mut txn, &deletion)?;
             }
@@ -180,12 +180,12 @@ impl Storage {
 
             // delete from events
             tracing::info!("PRUNE: deleting {} records from events", ids.len());
-            let mut txn = self.env.write_txn()?;
+            let mut txn = self.get_write_txn()?;
             for (n, id) in ids.iter().enumerate() {
                 self.db_events()?.delete(&mut txn, id.as_slice())?;
                 if n % 100_000 == 0 {
                     txn.commit()?;
-                    txn = self.env.write_txn()?;
+                    txn = self.get_write_txn()?;
                 }
             }
             txn.commit()?;
@@ -212,7 +212,7 @@ impl Storage {
         filter.limit = Some(6);
 
         let mut count = 0;
-        let loop_txn = self.env.read_txn()?;
+        let loop_txn = self.get_read_txn()?;
         for (_pk, person) in PersonTable::iter(&loop_txn)? {
             // Keep if they are in a person list
             if !self.read_person_lists(&person.pubkey)?.is_empty() {
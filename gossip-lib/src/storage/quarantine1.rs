@@ -0,0 +1,94 @@
+use crate::error::Error;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::{Event, Id};
+use speedy::Readable;
+use std::sync::Mutex;
+
+// Id -> Event
+//   key: id.as_slice()
+//   val: event bytes as they were found in the events table
+//        (these may not even parse, which may be why they were quarantined)
+
+static QUARANTINE1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut QUARANTINE1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_quarantine1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = QUARANTINE1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = QUARANTINE1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = QUARANTINE1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("quarantine")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                QUARANTINE1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn get_quarantine1_len(&self) -> Result<u64, Error> {
+        let txn = self.get_read_txn()?;
+        Ok(self.db_quarantine1()?.len(&txn)?)
+    }
+
+    pub(crate) fn quarantine_event_bytes1<'a>(
+        &'a self,
+        id: Id,
+        bytes: &[u8],
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_quarantine1()?.put(txn, id.as_slice(), bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn read_quarantined_events1(&self) -> Result<Vec<(Id, Option<Event>)>, Error> {
+        let txn = self.get_read_txn()?;
+        let mut output: Vec<(Id, Option<Event>)> = Vec::new();
+        for result in self.db_quarantine1()?.iter(&txn)? {
+            let (key, val) = result?;
+            let id = Id(key.try_into()?);
+            output.push((id, Event::read_from_buffer(val).ok()));
+        }
+        Ok(output)
+    }
+
+    pub(crate) fn purge_quarantine1<'a>(
+        &'a self,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<usize, Error> {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let count = self.db_quarantine1()?.len(txn)? as usize;
+        self.db_quarantine1()?.clear(txn)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(count)
+    }
+}
@@ -0,0 +1,85 @@
+use crate::error::Error;
+use crate::storage::types::ReactionSummary1;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::Id;
+use speedy::{Readable, Writable};
+use std::sync::Mutex;
+
+// Id -> ReactionSummary1
+//   key: id.as_slice()
+//   val: summary.write_to_vec() | ReactionSummary1::read_from_buffer(val)
+
+static REACTION_SUMMARIES1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut REACTION_SUMMARIES1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_reaction_summaries1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = REACTION_SUMMARIES1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = REACTION_SUMMARIES1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = REACTION_SUMMARIES1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("reaction_summaries")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                REACTION_SUMMARIES1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_reaction_summary1<'a>(
+        &'a self,
+        id: Id,
+        summary: &ReactionSummary1,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let bytes = summary.write_to_vec()?;
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_reaction_summaries1()?
+            .put(txn, id.as_slice(), &bytes)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn read_reaction_summary1<'a>(
+        &'a self,
+        id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<Option<ReactionSummary1>, Error> {
+        let db = self.db_reaction_summaries1()?;
+        let bytes = match rw_txn {
+            Some(txn) => db.get(txn, id.as_slice())?.map(|b| b.to_owned()),
+            None => {
+                let txn = self.get_read_txn()?;
+                db.get(&txn, id.as_slice())?.map(|b| b.to_owned())
+            }
+        };
+        match bytes {
+            Some(bytes) => Ok(Some(ReactionSummary1::read_from_buffer(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+}
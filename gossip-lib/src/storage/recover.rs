@@ -0,0 +1,231 @@
+use super::Storage;
+use crate::error::{Error, ErrorKind};
+use crate::profile::Profile;
+use heed::types::Bytes;
+use heed::{CompactionOption, Env, EnvFlags, EnvOpenOptions, MdbError};
+use nostr_types::Unixtime;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Keys in the general table that are carried over into a fresh database
+const SALVAGED_KEYS: [&[u8]; 4] = [
+    b"public_key",
+    b"encrypted_private_key",
+    b"client_public_key",
+    b"client_encrypted_private_key",
+];
+
+/// How a corrupted LMDB was recovered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageRecovery {
+    /// A compacted copy replaced the database. The corrupted one was kept at the path.
+    Compacted(PathBuf),
+
+    /// The corrupted database was moved to `kept_at` and a fresh one was started
+    StartedFresh {
+        kept_at: PathBuf,
+        kept_private_key: bool,
+    },
+}
+
+impl std::fmt::Display for StorageRecovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageRecovery::Compacted(kept_at) => write!(
+                f,
+                "The database was corrupted and has been repaired. Some data may be missing. The corrupted database was kept at {}",
+                kept_at.display()
+            ),
+            StorageRecovery::StartedFresh {
+                kept_at,
+                kept_private_key: true,
+            } => write!(
+                f,
+                "The database was corrupted and could not be repaired, so it was started fresh with your keys. The corrupted database was kept at {}",
+                kept_at.display()
+            ),
+            StorageRecovery::StartedFresh {
+                kept_at,
+                kept_private_key: false,
+            } => write!(
+                f,
+                "The database was corrupted and could not be repaired, so it was started fresh. Your private key could not be recovered and must be imported again. The corrupted database was kept at {}",
+                kept_at.display()
+            ),
+        }
+    }
+}
+
+/// Whether an error means the LMDB is corrupted
+fn is_corruption(e: &Error) -> bool {
+    matches!(
+        e.kind,
+        ErrorKind::Lmdb(heed::Error::Mdb(
+            MdbError::Corrupted | MdbError::PageNotFound | MdbError::Invalid | MdbError::Panic
+        ))
+    )
+}
+
+// Enough table handles for every table any version of gossip has made. These are
+// only used by the check, never by the live environment.
+const CHECK_MAX_DBS: u32 = 1024;
+
+impl Storage {
+    // Recovery -------------------------------------------------------
+
+    /// Check the LMDB in `dir` for corruption before it is opened.
+    ///
+    /// This uses its own read-only environment, closed again before returning, so
+    /// the tables it opens don't use up the live environment's table slots.
+    /// Corruption is reported as [ErrorKind::StorageCorrupted]; nothing is changed
+    /// on disk. The user can then choose to [recover](Self::recover).
+    pub fn check<P: AsRef<Path> + std::fmt::Debug>(dir: P, rapid: bool) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        if !dir.join("data.mdb").exists() {
+            return Ok(());
+        }
+
+        let mut builder = EnvOpenOptions::new();
+        unsafe {
+            builder.flags(EnvFlags::READ_ONLY | EnvFlags::NO_TLS);
+        }
+        builder.max_dbs(CHECK_MAX_DBS);
+        builder.map_size(Self::map_size_for(dir, rapid));
+        let env = unsafe { builder.open(dir)? };
+
+        let result = check_env(&env);
+        env.prepare_for_closing().wait();
+        match result {
+            Err(e) if is_corruption(&e) => {
+                tracing::error!("LMDB at {:?} is corrupted: {}", dir, e);
+                Err(ErrorKind::StorageCorrupted.into())
+            }
+            other => other,
+        }
+    }
+
+    /// Recover the corrupted LMDB of the current profile. It must not be open.
+    ///
+    /// This first tries a compacted copy, which only copies the pages that are
+    /// still in use and so can leave damaged ones behind. If that doesn't give a
+    /// working database, a fresh one is started, keeping the user's keys if they
+    /// can still be read. Either way the corrupted database is kept.
+    pub fn recover(rapid: bool) -> Result<StorageRecovery, Error> {
+        let dir = Profile::lmdb_dir()?;
+        let dir = dir.as_path();
+        let data = dir.join("data.mdb");
+        let lock = dir.join("lock.mdb");
+        let copy = dir.join("recovered.mdb");
+        let kept_at = dir.join(format!("data_corrupted_{}.mdb", Unixtime::now().0));
+
+        let mut salvaged: Vec<(&[u8], Vec<u8>)> = Vec::new();
+        let mut copied = false;
+        if let Ok(env) = Self::new_env(dir, rapid, Self::map_size_for(dir, rapid)) {
+            salvaged = salvage_keys(&env);
+
+            tracing::info!("Recovering LMDB by compacting it...");
+            let _ = fs::remove_file(&copy);
+            copied = env.copy_to_file(&copy, CompactionOption::Enabled).is_ok();
+            env.prepare_for_closing().wait();
+        }
+
+        // Keep the corrupted database, in case something more can be done with it
+        fs::rename(&data, &kept_at)?;
+        let _ = fs::remove_file(&lock);
+
+        if copied {
+            fs::rename(&copy, &data)?;
+            match Self::check(dir, rapid) {
+                Ok(()) => {
+                    let recovery = StorageRecovery::Compacted(kept_at);
+                    tracing::warn!("{}", recovery);
+                    return Ok(recovery);
+                }
+                Err(e) => {
+                    tracing::error!("Compacted LMDB is not usable either: {}", e);
+                    fs::remove_file(&data)?;
+                    let _ = fs::remove_file(&lock);
+                }
+            }
+        } else {
+            tracing::error!("Could not compact the corrupted LMDB");
+            let _ = fs::remove_file(&copy);
+        }
+
+        // Last resort, start fresh with what we could salvage. Nothing has been
+        // migrated yet, so these survive initialization.
+        let env = Self::new_env(dir, rapid, Self::map_size_for(dir, rapid))?;
+        {
+            let mut txn = env.write_txn()?;
+            let general = env.create_database::<Bytes, Bytes>(&mut txn, None)?;
+            for (key, bytes) in &salvaged {
+                general.put(&mut txn, key, bytes)?;
+            }
+            txn.commit()?;
+        }
+        env.prepare_for_closing().wait();
+
+        let recovery = StorageRecovery::StartedFresh {
+            kept_at,
+            kept_private_key: salvaged
+                .iter()
+                .any(|(key, _)| *key == b"encrypted_private_key"),
+        };
+        tracing::warn!("{}", recovery);
+        Ok(recovery)
+    }
+}
+
+// Read the list of tables and the first record of each. This doesn't read every
+// page, but it finds the corruption we get from unclean shutdowns.
+fn check_env(env: &Env) -> Result<(), Error> {
+    let txn = env.read_txn()?;
+    let main = match env.open_database::<Bytes, Bytes>(&txn, None)? {
+        Some(db) => db,
+        None => return Ok(()),
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    for result in main.iter(&txn)? {
+        let (key, _) = result?;
+        if let Ok(name) = std::str::from_utf8(key) {
+            names.push(name.to_owned());
+        }
+    }
+
+    for name in &names {
+        match env.open_database::<Bytes, Bytes>(&txn, Some(name)) {
+            Ok(Some(db)) => {
+                let _ = db.first(&txn)?;
+            }
+            Ok(None) => {}
+            // A general key, not a table
+            Err(heed::Error::Mdb(MdbError::Incompatible)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(())
+}
+
+// Read whatever keys we can out of a corrupted environment
+fn salvage_keys(env: &Env) -> Vec<(&'static [u8], Vec<u8>)> {
+    let mut salvaged: Vec<(&'static [u8], Vec<u8>)> = Vec::new();
+
+    let txn = match env.read_txn() {
+        Ok(txn) => txn,
+        Err(_) => return salvaged,
+    };
+    let general = match env.open_database::<Bytes, Bytes>(&txn, None) {
+        Ok(Some(db)) => db,
+        _ => return salvaged,
+    };
+
+    for key in SALVAGED_KEYS {
+        if let Ok(Some(bytes)) = general.get(&txn, key) {
+            salvaged.push((key, bytes.to_vec()));
+        }
+    }
+
+    salvaged
+}
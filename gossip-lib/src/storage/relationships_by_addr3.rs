@@ -29,7 +29,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -69,7 +69,7 @@ impl Storage {
         addr: &NAddr,
     ) -> Result<Vec<(Id, RelationshipByAddr3)>, Error> {
         let key = relationships_by_addr3_into_key(addr);
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let iter = match self
             .db_relationships_by_addr3()?
             .get_duplicates(&txn, &key)?
@@ -36,7 +36,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -72,12 +72,31 @@ impl Storage {
         Ok(())
     }
 
+    pub(crate) fn delete_relationship_by_id2<'a>(
+        &'a self,
+        id: Id,
+        related: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut key = id.as_ref().as_slice().to_owned();
+        key.extend(related.as_ref());
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let _ = self.db_relationships_by_id2()?.delete(txn, &key)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
     pub(crate) fn find_relationships_by_id2(
         &self,
         id: Id,
     ) -> Result<Vec<(Id, RelationshipById2)>, Error> {
         let start_key = id.as_slice();
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let iter = self
             .db_relationships_by_id2()?
             .prefix_iter(&txn, start_key)?;
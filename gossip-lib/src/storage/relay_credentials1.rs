@@ -0,0 +1,93 @@
+use crate::error::Error;
+use crate::storage::{RawDatabase, Storage, MAX_LMDB_KEY};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::RelayUrl;
+use std::sync::Mutex;
+
+// Url -> RelayCredential1, encrypted to ourselves
+//   key: url.as_str().as_bytes()
+//   val: the ciphertext of serde_json::to_string(credential)
+
+static RELAY_CREDENTIALS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut RELAY_CREDENTIALS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_relay_credentials1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = RELAY_CREDENTIALS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = RELAY_CREDENTIALS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = RELAY_CREDENTIALS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("relay_credentials")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                RELAY_CREDENTIALS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_relay_credential1<'a>(
+        &'a self,
+        url: &RelayUrl,
+        ciphertext: &str,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut key: Vec<u8> = url.as_str().as_bytes().to_owned();
+        key.truncate(MAX_LMDB_KEY);
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_relay_credentials1()?
+            .put(txn, &key, ciphertext.as_bytes())?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn read_relay_credential1(&self, url: &RelayUrl) -> Result<Option<String>, Error> {
+        let mut key: Vec<u8> = url.as_str().as_bytes().to_owned();
+        key.truncate(MAX_LMDB_KEY);
+        let txn = self.get_read_txn()?;
+        match self.db_relay_credentials1()?.get(&txn, &key)? {
+            None => Ok(None),
+            Some(bytes) => Ok(Some(String::from_utf8_lossy(bytes).into_owned())),
+        }
+    }
+
+    pub(crate) fn delete_relay_credential1<'a>(
+        &'a self,
+        url: &RelayUrl,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut key: Vec<u8> = url.as_str().as_bytes().to_owned();
+        key.truncate(MAX_LMDB_KEY);
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let _ = self.db_relay_credentials1()?.delete(txn, &key)?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+}
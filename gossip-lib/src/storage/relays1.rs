@@ -30,7 +30,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -47,7 +47,7 @@ impl Storage {
 
     #[allow(dead_code)]
     pub(crate) fn get_relays1_len(&self) -> Result<u64, Error> {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         Ok(self.db_relays1()?.len(&txn)?)
     }
 
@@ -177,7 +177,7 @@ impl Storage {
         if key.is_empty() {
             return Err(ErrorKind::Empty("relay url".to_owned()).into());
         }
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         match self.db_relays1()?.get(&txn, key)? {
             Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
             None => Ok(None),
@@ -188,7 +188,7 @@ impl Storage {
     where
         F: Fn(&Relay1) -> bool,
     {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut output: Vec<Relay1> = Vec::new();
         let iter = self.db_relays1()?.iter(&txn)?;
         for result in iter {
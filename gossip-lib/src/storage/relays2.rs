@@ -28,7 +28,7 @@ impl Storage {
 
                 // Create it. We know that nobody else is doing this and that
                 // it cannot happen twice.
-                let mut txn = self.env.write_txn()?;
+                let mut txn = self.get_write_txn()?;
                 let db = self
                     .env
                     .database_options()
@@ -72,7 +72,7 @@ impl Storage {
     where
         F: Fn(&Relay2) -> bool,
     {
-        let txn = self.env.read_txn()?;
+        let txn = self.get_read_txn()?;
         let mut output: Vec<Relay2> = Vec::new();
         let iter = self.db_relays2()?.iter(&txn)?;
         for result in iter {
@@ -0,0 +1,203 @@
+use crate::error::Error;
+use crate::storage::types::ReplaceableSighting1;
+use crate::storage::{RawDatabase, Storage, MAX_LMDB_KEY};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::{Event, EventKind, Id, PublicKey, RelayUrl, Unixtime};
+use speedy::{Readable, Writable};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// PublicKey:EventKind:Parameter -> Vec<ReplaceableSighting1>
+//   key: pubkey.to_bytes(), u32::from(kind).to_be_bytes(), parameter.as_bytes()
+//        (truncated to MAX_LMDB_KEY)
+//   val: sightings.write_to_vec() | Vec::<ReplaceableSighting1>::read_from_buffer(val)
+
+// Only the most recent versions are remembered
+const MAX_SIGHTINGS: usize = 64;
+
+static REPLACEABLE_SIGHTINGS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut REPLACEABLE_SIGHTINGS1_DB: Option<RawDatabase> = None;
+
+/// A disagreement between relays about a replaceable event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplaceableConflict {
+    /// Different versions with the same created_at. Only the author can have
+    /// signed these, but relays will keep whichever they saw first, so
+    /// different relays serve different "latest" versions.
+    Equivocation {
+        parameter: String,
+        created_at: Unixtime,
+        versions: Vec<(Id, Vec<RelayUrl>)>,
+    },
+
+    /// A relay that has only served us an older version than the latest we
+    /// know of, the last time we heard from it
+    Stale {
+        parameter: String,
+        relay: RelayUrl,
+        served: Id,
+        served_created_at: Unixtime,
+        latest: Id,
+        latest_created_at: Unixtime,
+    },
+}
+
+fn sightings_key(pubkey: PublicKey, kind: EventKind, parameter: &str) -> Vec<u8> {
+    let mut key = pubkey.to_bytes();
+    key.extend(u32::from(kind).to_be_bytes());
+    key.extend(parameter.as_bytes());
+    key.truncate(MAX_LMDB_KEY);
+    key
+}
+
+impl Storage {
+    pub(super) fn db_replaceable_sightings1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = REPLACEABLE_SIGHTINGS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = REPLACEABLE_SIGHTINGS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = REPLACEABLE_SIGHTINGS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("replaceable_sightings")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                REPLACEABLE_SIGHTINGS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn add_replaceable_sighting1<'a>(
+        &'a self,
+        event: &Event,
+        relay: &RelayUrl,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let parameter = event.parameter().unwrap_or_default();
+        let key = sightings_key(event.pubkey, event.kind, &parameter);
+
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        let mut sightings: Vec<ReplaceableSighting1> =
+            match self.db_replaceable_sightings1()?.get(txn, &key)? {
+                Some(bytes) => Vec::<ReplaceableSighting1>::read_from_buffer(bytes)?,
+                None => Vec::new(),
+            };
+
+        if !sightings
+            .iter()
+            .any(|s| s.id == event.id && s.relay == *relay)
+        {
+            if let Some(other) = sightings
+                .iter()
+                .find(|s| s.created_at == event.created_at && s.id != event.id)
+            {
+                tracing::warn!(
+                    "{} served a different version of {:?} by {} than {} did, with the same created_at",
+                    relay,
+                    event.kind,
+                    event.pubkey.as_hex_string(),
+                    other.relay
+                );
+            }
+
+            sightings.push(ReplaceableSighting1 {
+                id: event.id,
+                created_at: event.created_at,
+                relay: relay.to_owned(),
+            });
+            if sightings.len() > MAX_SIGHTINGS {
+                sightings.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+                sightings.truncate(MAX_SIGHTINGS);
+            }
+
+            let bytes = sightings.write_to_vec()?;
+            self.db_replaceable_sightings1()?.put(txn, &key, &bytes)?;
+        }
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn detect_replaceable_conflicts1(
+        &self,
+        author: PublicKey,
+        kind: EventKind,
+    ) -> Result<Vec<ReplaceableConflict>, Error> {
+        let prefix = sightings_key(author, kind, "");
+        let txn = self.get_read_txn()?;
+        let iter = self
+            .db_replaceable_sightings1()?
+            .prefix_iter(&txn, &prefix)?;
+
+        let mut output: Vec<ReplaceableConflict> = Vec::new();
+        for result in iter {
+            let (key, val) = result?;
+            let parameter = String::from_utf8_lossy(&key[prefix.len()..]).into_owned();
+            let sightings = Vec::<ReplaceableSighting1>::read_from_buffer(val)?;
+            let latest = match sightings.iter().max_by_key(|s| s.created_at) {
+                Some(s) => s.clone(),
+                None => continue,
+            };
+
+            // Different versions at the same time
+            let mut by_time: HashMap<Unixtime, Vec<(Id, Vec<RelayUrl>)>> = HashMap::new();
+            for s in sightings.iter() {
+                let versions = by_time.entry(s.created_at).or_default();
+                match versions.iter_mut().find(|(id, _)| *id == s.id) {
+                    Some((_, relays)) => relays.push(s.relay.clone()),
+                    None => versions.push((s.id, vec![s.relay.clone()])),
+                }
+            }
+            for (created_at, versions) in by_time.drain() {
+                if versions.len() > 1 {
+                    output.push(ReplaceableConflict::Equivocation {
+                        parameter: parameter.clone(),
+                        created_at,
+                        versions,
+                    });
+                }
+            }
+
+            // Relays that never gave us the latest version
+            let mut newest_by_relay: HashMap<RelayUrl, &ReplaceableSighting1> = HashMap::new();
+            for s in sightings.iter() {
+                let newest = newest_by_relay.entry(s.relay.clone()).or_insert(s);
+                if s.created_at > newest.created_at {
+                    *newest = s;
+                }
+            }
+            for (relay, newest) in newest_by_relay.drain() {
+                if newest.created_at < latest.created_at {
+                    output.push(ReplaceableConflict::Stale {
+                        parameter: parameter.clone(),
+                        relay,
+                        served: newest.id,
+                        served_created_at: newest.created_at,
+                        latest: latest.id,
+                        latest_created_at: latest.created_at,
+                    });
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,97 @@
+use crate::error::Error;
+use crate::storage::{RawDatabase, Storage};
+use heed::types::Bytes;
+use heed::RwTxn;
+use nostr_types::Id;
+use std::sync::Mutex;
+
+// Reaction Id -> Id of the event it reacts to
+//   key: reaction_id.as_slice()
+//   val: target_id.as_slice()
+//
+// Only kept for reaction events that were pruned after being summarized, so that
+// a later deletion can still find the summary they were counted in.
+
+static SUMMARIZED_REACTIONS1_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut SUMMARIZED_REACTIONS1_DB: Option<RawDatabase> = None;
+
+impl Storage {
+    pub(super) fn db_summarized_reactions1(&self) -> Result<RawDatabase, Error> {
+        unsafe {
+            if let Some(db) = SUMMARIZED_REACTIONS1_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = SUMMARIZED_REACTIONS1_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = SUMMARIZED_REACTIONS1_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = self.get_write_txn()?;
+                let db = self
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    // no .flags needed
+                    .name("summarized_reactions")
+                    .create(&mut txn)?;
+                txn.commit()?;
+                SUMMARIZED_REACTIONS1_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+
+    pub(crate) fn write_summarized_reaction1<'a>(
+        &'a self,
+        reaction_id: Id,
+        target: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_summarized_reactions1()?
+            .put(txn, reaction_id.as_slice(), target.as_slice())?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+
+    pub(crate) fn read_summarized_reaction1<'a>(
+        &'a self,
+        reaction_id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<Option<Id>, Error> {
+        let db = self.db_summarized_reactions1()?;
+        let bytes = match rw_txn {
+            Some(txn) => db.get(txn, reaction_id.as_slice())?.map(|b| b.to_owned()),
+            None => {
+                let txn = self.get_read_txn()?;
+                db.get(&txn, reaction_id.as_slice())?.map(|b| b.to_owned())
+            }
+        };
+        Ok(bytes.map(|b| Id(b[..32].try_into().unwrap())))
+    }
+
+    pub(crate) fn delete_summarized_reaction1<'a>(
+        &'a self,
+        reaction_id: Id,
+        rw_txn: Option<&mut RwTxn<'a>>,
+    ) -> Result<(), Error> {
+        let mut local_txn = None;
+        let txn = maybe_local_txn!(self, rw_txn, local_txn);
+
+        self.db_summarized_reactions1()?
+            .delete(txn, reaction_id.as_slice())?;
+
+        maybe_local_txn_commit!(local_txn);
+
+        Ok(())
+    }
+}
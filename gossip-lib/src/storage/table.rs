@@ -15,14 +15,14 @@ pub trait Table {
     /// Number of records
     #[allow(dead_code)]
     fn num_records() -> Result<u64, Error> {
-        let txn = GLOBALS.db().env.read_txn()?;
+        let txn = GLOBALS.db().get_read_txn()?;
         Ok(Self::db()?.len(&txn)?)
     }
 
     /// Bytes used
     #[allow(dead_code)]
     fn bytes_used() -> Result<usize, Error> {
-        let txn = GLOBALS.db().env.read_txn()?;
+        let txn = GLOBALS.db().get_read_txn()?;
         let stat = Self::db()?.stat(&txn)?;
         Ok(stat.page_size as usize
             * (stat.branch_pages + stat.leaf_pages + stat.overflow_pages + 2) as usize)
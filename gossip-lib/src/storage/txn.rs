@@ -0,0 +1,61 @@
+use heed::{RoTxn, RwTxn};
+use parking_lot::RwLockReadGuard;
+use std::ops::{Deref, DerefMut};
+
+// Every transaction holds the storage's transaction gate for reading. Growing the
+// LMDB map takes it for writing, because the map may only be resized while no
+// transactions are open in this process.
+
+/// A read transaction
+pub struct ReadTxn<'a> {
+    // Declared first so that it drops before the gate is released
+    txn: RoTxn<'a>,
+    _gate: RwLockReadGuard<'a, ()>,
+}
+
+impl<'a> ReadTxn<'a> {
+    pub(super) fn new(txn: RoTxn<'a>, gate: RwLockReadGuard<'a, ()>) -> ReadTxn<'a> {
+        ReadTxn { txn, _gate: gate }
+    }
+}
+
+impl<'a> Deref for ReadTxn<'a> {
+    type Target = RoTxn<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.txn
+    }
+}
+
+/// A write transaction. It is aborted if dropped without being committed.
+pub struct WriteTxn<'a> {
+    // Declared first so that it drops before the gate is released
+    txn: RwTxn<'a>,
+    _gate: RwLockReadGuard<'a, ()>,
+}
+
+impl<'a> WriteTxn<'a> {
+    pub(super) fn new(txn: RwTxn<'a>, gate: RwLockReadGuard<'a, ()>) -> WriteTxn<'a> {
+        WriteTxn { txn, _gate: gate }
+    }
+
+    /// Commit the transaction
+    pub fn commit(self) -> Result<(), heed::Error> {
+        let WriteTxn { txn, _gate } = self;
+        txn.commit()
+    }
+}
+
+impl<'a> Deref for WriteTxn<'a> {
+    type Target = RwTxn<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.txn
+    }
+}
+
+impl DerefMut for WriteTxn<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.txn
+    }
+}
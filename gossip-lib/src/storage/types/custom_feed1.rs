@@ -0,0 +1,26 @@
+use nostr_types::{EventKind, PublicKey};
+use speedy::{Readable, Writable};
+
+/// A user-defined feed of the events of a chosen set of people
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Writable)]
+pub struct CustomFeed1 {
+    /// The name of the feed, which identifies it
+    pub name: String,
+
+    /// Whose events are in the feed
+    pub pubkeys: Vec<PublicKey>,
+
+    /// Which event kinds are in the feed. If empty, the usual feed kinds are used.
+    pub kinds: Vec<EventKind>,
+}
+
+impl CustomFeed1 {
+    /// The event kinds to subscribe to and show
+    pub fn event_kinds(&self) -> Vec<EventKind> {
+        if self.kinds.is_empty() {
+            crate::feed::feed_displayable_event_kinds(false)
+        } else {
+            self.kinds.clone()
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use nostr_types::{Id, PublicKey, Unixtime};
+use speedy::{Readable, Writable};
+
+/// A post being composed, saved so that it survives a crash or navigating away
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Writable)]
+pub struct Draft1 {
+    /// Identifies the draft. The composer picks this when it starts a new draft.
+    pub id: u64,
+
+    /// The text of the post. For a direct message this is stored encrypted
+    /// to ourselves.
+    pub content: String,
+
+    /// The subject, if one is included
+    pub subject: Option<String>,
+
+    /// The content warning, if one is included
+    pub content_warning: Option<String>,
+
+    /// The event being replied to
+    pub replying_to: Option<Id>,
+
+    /// The other people in the DM channel, if this is a direct message
+    pub dm_channel: Option<Vec<PublicKey>>,
+
+    /// If this is an annotation
+    pub annotation: bool,
+
+    /// When the draft was last saved
+    pub saved_at: Unixtime,
+}
+
+impl Draft1 {
+    /// Whether the draft has nothing worth keeping in it
+    pub fn is_empty(&self) -> bool {
+        self.content.trim().is_empty()
+            && !self.subject.as_ref().is_some_and(|s| !s.trim().is_empty())
+    }
+
+    /// Whether the draft says the same as `other`, ignoring when each was saved
+    pub fn same_as(&self, other: &Draft1) -> bool {
+        Draft1 {
+            saved_at: other.saved_at,
+            ..self.clone()
+        } == *other
+    }
+}
@@ -1,6 +1,18 @@
+mod custom_feed1;
+pub use custom_feed1::CustomFeed1;
+
+mod draft1;
+pub use draft1::Draft1;
+
 mod handler;
 pub use handler::{Handler, HandlerKey};
 
+mod nip11_change1;
+pub use nip11_change1::Nip11Change1;
+
+mod notification1;
+pub use notification1::{Notification1, NotificationKind1};
+
 mod person2;
 pub use person2::Person2;
 
@@ -31,6 +43,9 @@ pub use person_relay2::PersonRelay2;
 mod following;
 pub use following::Following;
 
+mod reaction_summary1;
+pub use reaction_summary1::ReactionSummary1;
+
 mod relationship1;
 pub use relationship1::Relationship1;
 
@@ -56,7 +71,16 @@ mod relay2;
 pub use relay2::Relay2;
 
 mod relay3;
-pub use relay3::{Relay3, ScoreFactors};
+pub use relay3::Relay3;
+
+mod relay4;
+pub use relay4::{Relay4, RelayRejection, ScoreFactors};
+
+mod relay_credential1;
+pub use relay_credential1::RelayCredential1;
+
+mod replaceable_sighting1;
+pub use replaceable_sighting1::ReplaceableSighting1;
 
 use crate::error::Error;
 use nostr_types::{Id, PublicKey};
@@ -0,0 +1,73 @@
+use nostr_types::{RelayInformationDocument, RelayUrl};
+use serde::{Deserialize, Serialize};
+
+/// A change in what a relay says it supports, as seen between two fetches of its
+/// NIP-11 document
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Nip11Change1 {
+    /// The relay
+    pub url: RelayUrl,
+
+    /// When we noticed the change (unixtime seconds)
+    pub at: u64,
+
+    /// NIPs it newly claims to support
+    pub added_nips: Vec<u32>,
+
+    /// NIPs it no longer claims to support
+    pub removed_nips: Vec<u32>,
+
+    /// The `limitation` section before and after, if that changed
+    pub limitation: Option<(serde_json::Value, serde_json::Value)>,
+}
+
+impl Nip11Change1 {
+    /// What changed between `old` and `new`, or None if neither the supported NIPs
+    /// nor the limitations did
+    pub fn between(
+        url: RelayUrl,
+        at: u64,
+        old: &RelayInformationDocument,
+        new: &RelayInformationDocument,
+    ) -> Option<Nip11Change1> {
+        let added_nips: Vec<u32> = new
+            .supported_nips
+            .iter()
+            .filter(|nip| !old.supported_nips.contains(nip))
+            .copied()
+            .collect();
+        let removed_nips: Vec<u32> = old
+            .supported_nips
+            .iter()
+            .filter(|nip| !new.supported_nips.contains(nip))
+            .copied()
+            .collect();
+
+        let old_limitation = limitation_of(old);
+        let new_limitation = limitation_of(new);
+        let limitation = if old_limitation != new_limitation {
+            Some((old_limitation, new_limitation))
+        } else {
+            None
+        };
+
+        if added_nips.is_empty() && removed_nips.is_empty() && limitation.is_none() {
+            return None;
+        }
+
+        Some(Nip11Change1 {
+            url,
+            at,
+            added_nips,
+            removed_nips,
+            limitation,
+        })
+    }
+}
+
+fn limitation_of(doc: &RelayInformationDocument) -> serde_json::Value {
+    serde_json::to_value(doc)
+        .ok()
+        .and_then(|mut v| v.get_mut("limitation").map(|l| l.take()))
+        .unwrap_or(serde_json::Value::Null)
+}
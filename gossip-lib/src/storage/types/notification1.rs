@@ -0,0 +1,68 @@
+use nostr_types::{Id, MilliSatoshi, PublicKey, Unixtime};
+use speedy::{Readable, Writable};
+
+/// What a notification is about
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Writable)]
+pub enum NotificationKind1 {
+    /// Somebody mentioned the user
+    Mention,
+
+    /// Somebody replied to one of the user's events
+    Reply,
+
+    /// Somebody reacted to one of the user's events
+    Reaction(String),
+
+    /// Somebody zapped the user or one of the user's events
+    Zap(MilliSatoshi),
+
+    /// Somebody started following the user
+    NewFollower,
+}
+
+/// A notification of something another person did that targets the user
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Writable)]
+pub struct Notification1 {
+    /// The event that caused the notification
+    pub id: Id,
+
+    /// What kind of notification this is
+    pub kind: NotificationKind1,
+
+    /// Who caused it (for zaps, the payer)
+    pub from: PublicKey,
+
+    /// The user's event that it refers to, if any
+    pub target: Option<Id>,
+
+    /// When the causing event was created
+    pub created_at: Unixtime,
+
+    /// If the user has seen it
+    pub read: bool,
+}
+
+impl Notification1 {
+    /// The storage key. This deduplicates notifications: the same event only
+    /// notifies once, a person's reactions to a given event only notify once,
+    /// and a person only notifies as a new follower once.
+    pub fn key(&self) -> Vec<u8> {
+        let mut key: Vec<u8> = Vec::new();
+        match (&self.kind, self.target) {
+            (NotificationKind1::Reaction(_), Some(target)) => {
+                key.push(b'r');
+                key.extend(target.as_slice());
+                key.extend(self.from.as_bytes());
+            }
+            (NotificationKind1::NewFollower, _) => {
+                key.push(b'f');
+                key.extend(self.from.as_bytes());
+            }
+            _ => {
+                key.push(b'e');
+                key.extend(self.id.as_slice());
+            }
+        }
+        key
+    }
+}
@@ -0,0 +1,114 @@
+use nostr_types::{Id, PublicKey};
+use speedy::{Readable, Writable};
+
+/// A summary of the reactions to an event, kept instead of every reaction once a
+/// popular event has attracted many
+#[derive(Debug, Clone, Default, PartialEq, Eq, Readable, Writable)]
+pub struct ReactionSummary1 {
+    /// Who reacted and with what, by reaction event, at most one reaction per
+    /// person. Only the first `MAX_REACTORS` people are remembered this way.
+    pub reactors: Vec<(Id, PublicKey, String)>,
+
+    /// Reactions beyond the remembered reactors, by reaction event. These are not
+    /// checked for a second reaction by the same person.
+    pub overflow: Vec<(Id, PublicKey, String)>,
+}
+
+impl ReactionSummary1 {
+    /// How many reactors are remembered before only counting
+    pub const MAX_REACTORS: usize = 5000;
+
+    /// Add a reaction. A second reaction by a remembered reactor replaces their first.
+    /// Adding the same reaction event again does nothing.
+    pub fn add(&mut self, id: Id, by: PublicKey, reaction: String) {
+        if self.contains(id) {
+            return;
+        }
+        if let Some(existing) = self.reactors.iter_mut().find(|(_, pk, _)| *pk == by) {
+            *existing = (id, by, reaction);
+        } else if self.reactors.len() < Self::MAX_REACTORS {
+            self.reactors.push((id, by, reaction));
+        } else {
+            self.overflow.push((id, by, reaction));
+        }
+    }
+
+    /// Whether this reaction event is counted
+    pub fn contains(&self, id: Id) -> bool {
+        self.reactors.iter().any(|(i, _, _)| *i == id)
+            || self.overflow.iter().any(|(i, _, _)| *i == id)
+    }
+
+    /// Remove a reaction event, as when it is deleted. Only its author may remove
+    /// it. Returns whether it was removed.
+    pub fn remove(&mut self, id: Id, by: PublicKey) -> bool {
+        let before = self.reactors.len() + self.overflow.len();
+        self.reactors.retain(|(i, pk, _)| *i != id || *pk != by);
+        self.overflow.retain(|(i, pk, _)| *i != id || *pk != by);
+        before != self.reactors.len() + self.overflow.len()
+    }
+
+    /// Counts of each reaction, most common first
+    pub fn counts(&self) -> Vec<(String, u64)> {
+        let mut counts: Vec<(String, u64)> = Vec::new();
+        for (_, _, reaction) in self.reactors.iter().chain(self.overflow.iter()) {
+            match counts.iter_mut().find(|(r, _)| r == reaction) {
+                Some(count) => count.1 += 1,
+                None => counts.push((reaction.clone(), 1)),
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    /// The total number of reactions
+    pub fn len(&self) -> u64 {
+        (self.reactors.len() + self.overflow.len()) as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nostr_types::PrivateKey;
+
+    #[test]
+    fn test_reaction_summary_add() {
+        let alice = PrivateKey::generate().public_key();
+        let bob = PrivateKey::generate().public_key();
+        let id = |n: u8| Id([n; 32]);
+
+        let mut summary = ReactionSummary1::default();
+        summary.add(id(1), alice, "+".to_owned());
+        summary.add(id(2), bob, "+".to_owned());
+        // A second reaction replaces the first
+        summary.add(id(3), alice, "🤙".to_owned());
+        assert_eq!(summary.len(), 2);
+        assert_eq!(
+            summary.counts(),
+            vec![("+".to_owned(), 1), ("🤙".to_owned(), 1)]
+        );
+
+        // Once full, reactions are kept without checking who made them
+        summary
+            .reactors
+            .resize(ReactionSummary1::MAX_REACTORS, (id(4), bob, "+".to_owned()));
+        let carol = PrivateKey::generate().public_key();
+        summary.add(id(5), carol, "+".to_owned());
+        assert_eq!(summary.overflow, vec![(id(5), carol, "+".to_owned())]);
+        assert_eq!(summary.len(), ReactionSummary1::MAX_REACTORS as u64 + 1);
+
+        // Seeing the same reaction again does not count it twice
+        summary.add(id(5), carol, "+".to_owned());
+        assert_eq!(summary.len(), ReactionSummary1::MAX_REACTORS as u64 + 1);
+
+        // Only the author can delete it
+        assert!(!summary.remove(id(5), alice));
+        assert!(summary.remove(id(5), carol));
+        assert!(summary.overflow.is_empty());
+    }
+}
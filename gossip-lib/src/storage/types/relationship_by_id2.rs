@@ -21,13 +21,22 @@ pub enum RelationshipById2 {
     Timestamps,
 
     // NIP-09 Event Deletion
-    Deletes { by: PublicKey, reason: String },
+    Deletes {
+        by: PublicKey,
+        reason: String,
+    },
 
     // NIP-25 Reactions
-    ReactsTo { by: PublicKey, reaction: String },
+    ReactsTo {
+        by: PublicKey,
+        reaction: String,
+    },
 
     // NIP-32 Labeling
-    Labels { label: String, namespace: String },
+    Labels {
+        label: String,
+        namespace: String,
+    },
 
     // NIP-51 Lists
     Mutes,
@@ -47,13 +56,24 @@ pub enum RelationshipById2 {
     Reports(String),
 
     // NIP-57 Lightning Zaps
-    Zaps { by: PublicKey, amount: MilliSatoshi },
-
-    // NIP-72 Moderated Communities (Reddit-style)
-    // Approves { in_community: NAddr },
+    Zaps {
+        by: PublicKey,
+        amount: MilliSatoshi,
+    },
 
     // NIP-90 Data Vending Machines
     SuppliesJobResult,
+
+    // NIP-88 Polls
+    RespondsToPoll,
+
+    // NIP-72 Moderated Communities (Reddit-style), in the community at
+    // 34550:community_author:community_d
+    Approves {
+        by: PublicKey,
+        community_author: PublicKey,
+        community_d: String,
+    },
 }
 
 impl From<RelationshipById1> for RelationshipById2 {
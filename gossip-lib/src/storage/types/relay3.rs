@@ -1,5 +1,3 @@
-use crate::error::Error;
-use crate::globals::GLOBALS;
 use nostr_types::{RelayInformationDocument, RelayUrl, Unixtime};
 use serde::{Deserialize, Serialize};
 
@@ -165,133 +163,10 @@ impl Relay3 {
         self.success_count as f32 / attempts as f32
     }
 
-    pub fn should_avoid(&self) -> bool {
-        #[allow(clippy::if_same_then_else)]
-        if self.rank == 0 {
-            true
-        } else if GLOBALS
-            .db()
-            .read_setting_relay_connection_requires_approval()
-            && self.allow_connect == Some(false)
-        {
-            true
-        } else if crate::storage::Storage::url_is_banned(&self.url) {
-            true
-        } else if let Some(when) = self.avoid_until {
-            when >= Unixtime::now()
-        } else {
-            false
-        }
-    }
-
     pub fn is_good_for_advertise(&self) -> bool {
-        if self.should_avoid() {
-            return false;
-        }
-
         self.has_usage_bits(Self::INBOX)
             || self.has_usage_bits(Self::OUTBOX)
             || self.has_usage_bits(Self::DISCOVER)
             || (self.rank > 0 && self.success_rate() > 0.50 && self.success_count > 15)
     }
-
-    /// This gives a pure score for the relay outside of context
-    ///
-    /// Output ranges from 0.0 (worst) to 1.0 (best)
-    ///
-    /// Typical good relays still only score about 0.3, simply because rank goes so high.
-    ///
-    /// If `None` is returned, do not use this relay.
-    pub fn score(&self) -> f32 {
-        if self.should_avoid() {
-            return 0.0;
-        }
-
-        let mut score: f32 = 1.0;
-
-        // Adjust by rank:
-        //   1 = 0.11111
-        //   3 = 0.33333
-        //   5 = 0.55555
-        //   9 = 1.0
-        score *= self.rank as f32 / 9.0;
-
-        // Adjust by success rate (max penalty of cutting in half)
-        score *= 0.5 + 0.5 * self.success_rate();
-
-        // We don't penalize low-attempt relays even as they are less reliable
-        // because we want to let new relays establish.
-
-        score
-    }
-
-    /// This adjusts the score based on two other optional factors
-    pub fn adjusted_score(&self, factors: ScoreFactors) -> f32 {
-        let mut score = self.score();
-        if factors.connected {
-            if !GLOBALS.connected_relays.contains_key(&self.url) {
-                score /= 2.0;
-            }
-        }
-        if factors.success_count {
-            if self.success_count > 0 {
-                score *= (self.success_count as f32).log10();
-            } else {
-                score = 0.0;
-            }
-        }
-        score
-    }
-
-    pub fn choose_relays<F>(bits: u64, f: F) -> Result<Vec<Relay3>, Error>
-    where
-        F: Fn(&Relay3) -> bool,
-    {
-        GLOBALS
-            .db()
-            .filter_relays(|r| r.has_usage_bits(bits) && !r.should_avoid() && f(r))
-    }
-
-    pub fn choose_relay_urls<F>(bits: u64, f: F) -> Result<Vec<RelayUrl>, Error>
-    where
-        F: Fn(&Relay3) -> bool,
-    {
-        Ok(GLOBALS
-            .db()
-            .filter_relays(|r| r.has_usage_bits(bits) && !r.should_avoid() && f(r))?
-            .iter()
-            .map(|r| r.url.clone())
-            .collect())
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct ScoreFactors {
-    /// Double the score of relays currently connected
-    pub connected: bool,
-
-    /// Increase the score of relays with more total successful connections
-    pub success_count: bool,
-}
-
-impl ScoreFactors {
-    pub const BASE: ScoreFactors = ScoreFactors {
-        connected: false,
-        success_count: false,
-    };
-
-    pub const PREFER_CONNECTED_IGNORE_COUNT: ScoreFactors = ScoreFactors {
-        connected: true,
-        success_count: false,
-    };
-
-    pub const PREFER_COUNT_IGNORE_CONNECTED: ScoreFactors = ScoreFactors {
-        connected: false,
-        success_count: true,
-    };
-
-    pub const FULLY_ADJUSTED: ScoreFactors = ScoreFactors {
-        connected: true,
-        success_count: true,
-    };
 }
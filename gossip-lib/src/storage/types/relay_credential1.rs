@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A credential that a relay wants before it lets us connect, which the user got
+/// out of band (e.g. the token for a paid relay). It is applied in the websocket
+/// handshake.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RelayCredential1 {
+    /// Sent as an HTTP header
+    Header { name: String, value: String },
+
+    /// Added to the relay URL as a query parameter
+    UrlParameter { name: String, value: String },
+}
+
+impl RelayCredential1 {
+    pub fn name(&self) -> &str {
+        match self {
+            RelayCredential1::Header { name, .. } => name,
+            RelayCredential1::UrlParameter { name, .. } => name,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            RelayCredential1::Header { value, .. } => value,
+            RelayCredential1::UrlParameter { value, .. } => value,
+        }
+    }
+}
+
+// The value is a secret. Keep it out of debug output so it never gets logged.
+impl fmt::Debug for RelayCredential1 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayCredential1::Header { name, .. } => f
+                .debug_struct("Header")
+                .field("name", name)
+                .field("value", &"<redacted>")
+                .finish(),
+            RelayCredential1::UrlParameter { name, .. } => f
+                .debug_struct("UrlParameter")
+                .field("name", name)
+                .field("value", &"<redacted>")
+                .finish(),
+        }
+    }
+}
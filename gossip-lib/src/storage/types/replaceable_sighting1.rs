@@ -0,0 +1,15 @@
+use nostr_types::{Id, RelayUrl, Unixtime};
+use speedy::{Readable, Writable};
+
+/// A version of a replaceable event that a relay served us
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Writable)]
+pub struct ReplaceableSighting1 {
+    /// The id of the version
+    pub id: Id,
+
+    /// When that version was created
+    pub created_at: Unixtime,
+
+    /// The relay that served it
+    pub relay: RelayUrl,
+}
@@ -1,12 +1,25 @@
-use crate::error::ErrorKind;
+use crate::comms::ToOverlordMessage;
+use crate::error::{Error, ErrorKind};
+use crate::relay_metrics;
+use crate::storage::Rebuild;
 use crate::RunState;
 use crate::GLOBALS;
-use std::sync::atomic::Ordering;
+use nostr_types::{RelayInformationDocument, RelayUrl, Unixtime};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tokio::time::Instant;
 
 const TICK: u64 = 500;
 
+// If quiet hours were in effect when we last checked
+static IN_QUIET_HOURS: AtomicBool = AtomicBool::new(false);
+
+// If an automatic prune is running
+static AUTO_PRUNING: AtomicBool = AtomicBool::new(false);
+
+// If relays' NIP-11 documents are being refetched
+static REFETCHING_RELAY_INFO: AtomicBool = AtomicBool::new(false);
+
 pub(crate) fn start_background_tasks() {
     tracing::info!("Starting general background tasks");
 
@@ -75,6 +88,16 @@ async fn do_online_tasks(tick: usize) {
     if tick % 3 == 0 {
         GLOBALS.people.maybe_fetch_metadata().await;
     }
+
+    // Refetch stale NIP-11 documents of connected relays every 120 ticks (1 minute)
+    if tick % 120 == 0 {
+        maybe_refetch_relay_info();
+    }
+
+    // Send refused posts again once their retry time comes, every 10 ticks (5 seconds)
+    if tick % 10 == 0 {
+        crate::delivery::retry_due();
+    }
 }
 
 async fn do_general_tasks(tick: usize) {
@@ -91,6 +114,226 @@ async fn do_general_tasks(tick: usize) {
 
     // Update handlers for quick menu rendering
     let _ = GLOBALS.update_handlers();
+
+    // Save relay ingestion metrics every 120 ticks (1 minute)
+    if tick % 120 == 0 {
+        if let Err(e) = relay_metrics::save() {
+            tracing::error!("{}", e);
+        }
+    }
+
+    // Enter or leave quiet mode at the edges of quiet hours every 120 ticks (1 minute)
+    if tick % 120 == 0 {
+        check_quiet_hours();
+    }
+
+    // Prune if it is scheduled every 120 ticks (1 minute)
+    if tick % 120 == 0 {
+        maybe_auto_prune();
+    }
+
+    // Lock the key after inactivity every 10 ticks (5 seconds)
+    if tick % 10 == 0 {
+        maybe_auto_lock();
+    }
+}
+
+fn maybe_auto_lock() {
+    let minutes = GLOBALS.db().read_setting_auto_lock_minutes();
+    if minutes == 0 || !GLOBALS.identity.is_unlocked() {
+        return;
+    }
+
+    let idle = Unixtime::now().0 - GLOBALS.last_activity.load(Ordering::Relaxed);
+    if idle < minutes as i64 * 60 {
+        return;
+    }
+
+    match crate::Overlord::lock_key() {
+        Ok(()) => {
+            tracing::info!("Locked the key after {} minutes of inactivity", minutes);
+            GLOBALS.status_queue.write().write(format!(
+                "Your key was locked after {} minutes of inactivity.",
+                minutes
+            ));
+        }
+        Err(e) => tracing::error!("{}", e),
+    }
+}
+
+fn check_quiet_hours() {
+    let quiet = GLOBALS.db().read_setting_quiet_hours() && {
+        let local =
+            Unixtime::now().0 + GLOBALS.local_utc_offset_secs.load(Ordering::Relaxed) as i64;
+        let hour = (local.rem_euclid(86400) / 3600) as u8;
+        let start = GLOBALS.db().read_setting_quiet_hours_start();
+        let end = GLOBALS.db().read_setting_quiet_hours_end();
+        if start <= end {
+            hour >= start && hour < end
+        } else {
+            // spans midnight
+            hour >= start || hour < end
+        }
+    };
+
+    // Only act when quiet hours begin or end, so the user can still change
+    // quiet mode by hand in between
+    if IN_QUIET_HOURS.swap(quiet, Ordering::Relaxed) != quiet {
+        let _ = GLOBALS
+            .to_overlord
+            .send(ToOverlordMessage::SetQuietMode(quiet));
+    }
+}
+
+fn maybe_auto_prune() {
+    if !GLOBALS.db().read_setting_auto_prune() || AUTO_PRUNING.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // Don't compete with other heavy operations
+    if GLOBALS.wait_for_data_migration.load(Ordering::Relaxed)
+        || GLOBALS.audit_status.read().is_some()
+        || GLOBALS.prune_status.read().is_some()
+    {
+        return;
+    }
+
+    let now = Unixtime::now();
+    let interval = GLOBALS.db().read_setting_auto_prune_interval_days() as i64 * 60 * 60 * 24;
+    match GLOBALS.db().read_last_auto_prune() {
+        Ok(Some(last)) if now.0 - last.0 < interval => return,
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("{}", e);
+            return;
+        }
+    }
+
+    // Skip small databases until the next interval
+    let min_size = GLOBALS.db().read_setting_auto_prune_min_size_mb() * 1024 * 1024;
+    match GLOBALS.db().used_size() {
+        Ok(size) if size < min_size => {
+            tracing::info!(
+                "Skipping automatic pruning, the database is only {} bytes",
+                size
+            );
+            if let Err(e) = GLOBALS.db().write_last_auto_prune(now, None) {
+                tracing::error!("{}", e);
+            }
+            return;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("{}", e);
+            return;
+        }
+    }
+
+    AUTO_PRUNING.store(true, Ordering::Relaxed);
+    tokio::task::spawn(async move {
+        match auto_prune(now).await {
+            Ok((events, files)) => GLOBALS.status_queue.write().write(format!(
+                "Automatic pruning removed {} events and {} cached files.",
+                events, files
+            )),
+            Err(e) => tracing::error!("Automatic pruning failed: {}", e),
+        }
+        AUTO_PRUNING.store(false, Ordering::Relaxed);
+    });
+}
+
+async fn auto_prune(now: Unixtime) -> Result<(usize, usize), Error> {
+    tracing::info!("Pruning automatically...");
+
+    let then = now
+        - Duration::new(
+            GLOBALS.db().read_setting_prune_period_days() * 60 * 60 * 24,
+            0,
+        );
+    let events = tokio::task::spawn_blocking(move || -> Result<usize, Error> {
+        GLOBALS.db().prune_misc()?;
+        let events = GLOBALS.db().prune_old_events(then)?;
+
+        // Pruning leaves stale index entries behind, rebuild them at next startup
+        if events > 0 {
+            GLOBALS
+                .db()
+                .set_rebuild_pending(Rebuild::EventIndexes, true, None)?;
+        }
+
+        Ok(events)
+    })
+    .await??;
+
+    let age = Duration::new(
+        GLOBALS.db().read_setting_cache_prune_period_days() * 60 * 60 * 24,
+        0,
+    );
+    let files = GLOBALS.fetcher.prune(age).await?;
+
+    GLOBALS.db().write_last_auto_prune(now, None)?;
+
+    Ok((events, files))
+}
+
+fn maybe_refetch_relay_info() {
+    if REFETCHING_RELAY_INFO.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let stale = GLOBALS.db().read_setting_relay_info_becomes_stale_hours() * 60 * 60;
+    let now = Unixtime::now().0 as u64;
+
+    let urls: Vec<RelayUrl> = GLOBALS
+        .connected_relays
+        .iter()
+        .map(|r| r.key().to_owned())
+        .filter(|url| match GLOBALS.db().read_relay(url) {
+            Ok(Some(relay)) => relay.last_attempt_nip11.unwrap_or(0) + stale < now,
+            _ => false,
+        })
+        .collect();
+    if urls.is_empty() {
+        return;
+    }
+
+    REFETCHING_RELAY_INFO.store(true, Ordering::Relaxed);
+    tokio::task::spawn(async move {
+        for url in urls {
+            if let Err(e) = refetch_relay_info(&url).await {
+                tracing::debug!("{}: Unable to refetch NIP-11: {}", url, e);
+            }
+        }
+        REFETCHING_RELAY_INFO.store(false, Ordering::Relaxed);
+    });
+}
+
+async fn refetch_relay_info(url: &RelayUrl) -> Result<(), Error> {
+    let timeout = Duration::from_secs(GLOBALS.db().read_setting_fetcher_timeout_sec());
+    let result = tokio::time::timeout(timeout, crate::direct::fetch_nip11(url.as_str())).await;
+
+    // Don't try again until it is stale again, even if this failed
+    let now = Unixtime::now().0 as u64;
+    GLOBALS.db().modify_relay(
+        url,
+        |relay| {
+            relay.last_attempt_nip11 = Some(now);
+        },
+        None,
+    )?;
+
+    let doc: RelayInformationDocument = serde_json::from_value(result??)?;
+    let old = GLOBALS.db().read_relay(url)?.and_then(|relay| relay.nip11);
+    crate::relay::note_nip11_change(url, old.as_ref(), &doc)?;
+    GLOBALS.db().modify_relay(
+        url,
+        |relay| {
+            relay.nip11 = Some(doc.clone());
+        },
+        None,
+    )?;
+
+    Ok(())
 }
 
 async fn update_inbox_indicator() {
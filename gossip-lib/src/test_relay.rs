@@ -0,0 +1,417 @@
+use crate::error::Error;
+use futures_util::{SinkExt, StreamExt};
+use nostr_types::{ClientMessage, Event, Filter, RelayMessage, RelayUrl, SubscriptionId};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, watch, Notify};
+use tungstenite::protocol::Message as WsMessage;
+
+// Something to push to every connected client
+#[derive(Clone)]
+enum Push {
+    // A new event, sent to the subscriptions it matches
+    Event(Event),
+
+    // A message sent as is
+    Message(RelayMessage),
+}
+
+#[derive(Default)]
+struct State {
+    // Events served to subscriptions
+    events: Vec<Event>,
+
+    // Everything clients have sent us, in order
+    received: Vec<ClientMessage>,
+
+    // If set, EVENTs are refused with this message
+    reject: Option<String>,
+}
+
+/// A minimal nostr relay on loopback, for testing the code that talks to relays.
+///
+/// It handles REQ, CLOSE and EVENT, answering with EVENT, EOSE and OK. Everything
+/// else gets a NOTICE. Events are not verified, so tests can use made up ones.
+///
+/// Events can be injected before or while clients are connected. Every message
+/// clients send is recorded so tests can assert on it.
+///
+/// The relay stops when this is dropped.
+pub struct TestRelay {
+    url: RelayUrl,
+    state: Arc<Mutex<State>>,
+    received: Arc<Notify>,
+    push: broadcast::Sender<Push>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl TestRelay {
+    /// Start a relay on a free loopback port
+    pub async fn start() -> Result<TestRelay, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let url = RelayUrl::try_from_str(&format!("ws://{}/", addr))?;
+
+        let state: Arc<Mutex<State>> = Default::default();
+        let received = Arc::new(Notify::new());
+        let (push, _) = broadcast::channel(1024);
+        let (shutdown, _) = watch::channel(false);
+
+        let relay = TestRelay {
+            url,
+            state,
+            received,
+            push,
+            shutdown,
+        };
+
+        tokio::spawn(accept_loop(
+            listener,
+            relay.state.clone(),
+            relay.received.clone(),
+            relay.push.clone(),
+            relay.shutdown.subscribe(),
+        ));
+
+        Ok(relay)
+    }
+
+    /// The url to connect to
+    pub fn url(&self) -> RelayUrl {
+        self.url.clone()
+    }
+
+    /// Add an event. It is served to future subscriptions, and to open ones it matches.
+    pub fn inject(&self, event: Event) {
+        if store(&self.state, event.clone()) {
+            let _ = self.push.send(Push::Event(event));
+        }
+    }
+
+    /// Add events, as with `inject()`
+    pub fn inject_all(&self, events: impl IntoIterator<Item = Event>) {
+        for event in events {
+            self.inject(event);
+        }
+    }
+
+    /// Send a message to every connected client, e.g. an AUTH challenge or a NOTICE
+    pub fn send(&self, message: RelayMessage) {
+        let _ = self.push.send(Push::Message(message));
+    }
+
+    /// Refuse EVENTs with this message, or accept them again with None
+    pub fn reject_events(&self, message: Option<String>) {
+        self.state.lock().reject = message;
+    }
+
+    /// The events the relay has, injected or posted
+    pub fn events(&self) -> Vec<Event> {
+        self.state.lock().events.clone()
+    }
+
+    /// Every message clients have sent, in order
+    pub fn received(&self) -> Vec<ClientMessage> {
+        self.state.lock().received.clone()
+    }
+
+    /// The events clients have posted, in order
+    pub fn received_events(&self) -> Vec<Event> {
+        self.state
+            .lock()
+            .received
+            .iter()
+            .filter_map(|m| match m {
+                ClientMessage::Event(e) => Some((**e).clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Wait until a client has sent a message matching `f`, and return it.
+    ///
+    /// Messages received before this was called count too.
+    pub async fn wait_for<F>(&self, f: F, timeout: Duration) -> Result<ClientMessage, Error>
+    where
+        F: Fn(&ClientMessage) -> bool,
+    {
+        let wait = async {
+            loop {
+                // Register before looking, so we can't miss a message in between
+                let notified = self.received.notified();
+                if let Some(m) = self.state.lock().received.iter().find(|m| f(m)) {
+                    return m.clone();
+                }
+                notified.await;
+            }
+        };
+        Ok(tokio::time::timeout(timeout, wait).await?)
+    }
+}
+
+impl Drop for TestRelay {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+// Store an event, returning false if we already had it
+fn store(state: &Mutex<State>, event: Event) -> bool {
+    let mut state = state.lock();
+    if state.events.iter().any(|e| e.id == event.id) {
+        return false;
+    }
+    state.events.push(event);
+    true
+}
+
+// The stored events matching a filter, newest first, up to its limit
+fn matching(state: &Mutex<State>, filter: &Filter) -> Vec<Event> {
+    let mut events: Vec<Event> = state
+        .lock()
+        .events
+        .iter()
+        .filter(|e| filter.event_matches(e))
+        .cloned()
+        .collect();
+    events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    if let Some(limit) = filter.limit {
+        events.truncate(limit);
+    }
+    events
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    state: Arc<Mutex<State>>,
+    received: Arc<Notify>,
+    push: broadcast::Sender<Push>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            accepted = listener.accept() => {
+                let Ok((stream, _)) = accepted else { break };
+                tokio::spawn(serve(
+                    stream,
+                    state.clone(),
+                    received.clone(),
+                    push.clone(),
+                    shutdown.clone(),
+                ));
+            }
+        }
+    }
+}
+
+async fn serve(
+    stream: TcpStream,
+    state: Arc<Mutex<State>>,
+    received: Arc<Notify>,
+    push: broadcast::Sender<Push>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), Error> {
+    let mut websocket = tokio_tungstenite::accept_async(stream).await?;
+    let mut pushed = push.subscribe();
+    let mut subscriptions: HashMap<SubscriptionId, Filter> = HashMap::new();
+
+    loop {
+        // What to send back
+        let mut replies: Vec<RelayMessage> = Vec::new();
+
+        tokio::select! {
+            _ = shutdown.changed() => {
+                let _ = websocket.close(None).await;
+                return Ok(());
+            },
+            p = pushed.recv() => match p {
+                Ok(Push::Event(event)) => {
+                    for (sub, filter) in &subscriptions {
+                        if filter.event_matches(&event) {
+                            replies.push(RelayMessage::Event(sub.clone(), Box::new(event.clone())));
+                        }
+                    }
+                }
+                Ok(Push::Message(message)) => replies.push(message),
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            ws_message = websocket.next() => {
+                let text = match ws_message {
+                    Some(Ok(WsMessage::Text(t))) => t,
+                    Some(Ok(WsMessage::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+
+                let message: ClientMessage = match serde_json::from_str(&text) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        let notice = format!("error: could not parse message: {e}");
+                        websocket.send(reply(RelayMessage::Notice(notice))?).await?;
+                        continue;
+                    }
+                };
+
+                state.lock().received.push(message.clone());
+                received.notify_waiters();
+
+                match message {
+                    ClientMessage::Req(sub, filter) => {
+                        for event in matching(&state, &filter) {
+                            replies.push(RelayMessage::Event(sub.clone(), Box::new(event)));
+                        }
+                        replies.push(RelayMessage::Eose(sub.clone()));
+                        subscriptions.insert(sub, filter);
+                    }
+                    ClientMessage::Close(sub) => {
+                        subscriptions.remove(&sub);
+                    }
+                    ClientMessage::Event(event) => {
+                        let reject = state.lock().reject.clone();
+                        match reject {
+                            Some(why) => replies.push(RelayMessage::Ok(event.id, false, why)),
+                            None => {
+                                replies.push(RelayMessage::Ok(event.id, true, "".to_owned()));
+                                if store(&state, (*event).clone()) {
+                                    let _ = push.send(Push::Event(*event));
+                                }
+                            }
+                        }
+                    }
+                    _ => replies.push(RelayMessage::Notice(
+                        "error: not supported by the test relay".to_owned(),
+                    )),
+                }
+            },
+        }
+
+        for message in replies {
+            websocket.send(reply(message)?).await?;
+        }
+    }
+}
+
+fn reply(message: RelayMessage) -> Result<WsMessage, Error> {
+    Ok(WsMessage::Text(serde_json::to_string(&message)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::signed_event;
+    use nostr_types::{EventKind, KeySigner};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn text_note(signer: &KeySigner, created_at: i64) -> Event {
+        signed_event(signer, created_at, EventKind::TextNote, vec![], "hello")
+    }
+
+    type Client = tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >;
+
+    async fn connect(relay: &TestRelay) -> Client {
+        let (client, _) = tokio_tungstenite::connect_async(relay.url().as_str())
+            .await
+            .unwrap();
+        client
+    }
+
+    async fn send(client: &mut Client, message: ClientMessage) {
+        let wire = serde_json::to_string(&message).unwrap();
+        client.send(WsMessage::Text(wire)).await.unwrap();
+    }
+
+    async fn next(client: &mut Client) -> RelayMessage {
+        loop {
+            let message = tokio::time::timeout(TIMEOUT, client.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            if let WsMessage::Text(t) = message {
+                return serde_json::from_str(&t).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relay_protocol() {
+        let author = KeySigner::generate("", 1).unwrap();
+        let old = text_note(&author, 1700000000);
+        let new = text_note(&author, 1700000100);
+
+        let relay = TestRelay::start().await.unwrap();
+        relay.inject_all(vec![old.clone(), new.clone()]);
+
+        let mut client = connect(&relay).await;
+
+        // Stored events come newest first, then EOSE
+        let sub = SubscriptionId("sub".to_owned());
+        let mut filter = Filter::new();
+        filter.add_event_kind(EventKind::TextNote);
+        send(&mut client, ClientMessage::Req(sub.clone(), filter)).await;
+        match next(&mut client).await {
+            RelayMessage::Event(s, e) => assert!(s == sub && e.id == new.id),
+            _ => panic!("expected the newer event"),
+        }
+        match next(&mut client).await {
+            RelayMessage::Event(s, e) => assert!(s == sub && e.id == old.id),
+            _ => panic!("expected the older event"),
+        }
+        assert!(matches!(next(&mut client).await, RelayMessage::Eose(s) if s == sub));
+
+        // Events injected later reach the open subscription
+        let live = text_note(&author, 1700000200);
+        relay.inject(live.clone());
+        match next(&mut client).await {
+            RelayMessage::Event(s, e) => assert!(s == sub && e.id == live.id),
+            _ => panic!("expected the injected event"),
+        }
+
+        // Posted events are accepted, recorded, and served
+        let posted = text_note(&author, 1700000300);
+        send(&mut client, ClientMessage::Event(Box::new(posted.clone()))).await;
+        match next(&mut client).await {
+            RelayMessage::Ok(id, true, _) => assert_eq!(id, posted.id),
+            _ => panic!("expected OK"),
+        }
+        match next(&mut client).await {
+            RelayMessage::Event(s, e) => assert!(s == sub && e.id == posted.id),
+            _ => panic!("expected the posted event"),
+        }
+        assert_eq!(relay.received_events().len(), 1);
+        assert_eq!(relay.events().len(), 4);
+
+        // Closed subscriptions get nothing more
+        send(&mut client, ClientMessage::Close(sub.clone())).await;
+        relay
+            .wait_for(|m| matches!(m, ClientMessage::Close(_)), TIMEOUT)
+            .await
+            .unwrap();
+        relay.inject(text_note(&author, 1700000400));
+
+        // Rejections
+        relay.reject_events(Some("blocked: testing".to_owned()));
+        let refused = text_note(&author, 1700000500);
+        send(&mut client, ClientMessage::Event(Box::new(refused.clone()))).await;
+        match next(&mut client).await {
+            RelayMessage::Ok(id, false, why) => {
+                assert_eq!(id, refused.id);
+                assert_eq!(why, "blocked: testing");
+            }
+            _ => panic!("expected a refusal"),
+        }
+        assert!(!relay.events().iter().any(|e| e.id == refused.id));
+        assert!(relay
+            .received()
+            .iter()
+            .any(|m| matches!(m, ClientMessage::Event(e) if e.id == refused.id)));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::globals::GLOBALS;
+use crate::storage::Storage;
+use nostr_types::{Event, EventKind, KeySigner, PreEvent, Signer, Tag, Unixtime};
+use std::fs;
+use std::path::PathBuf;
+
+/// An event signed by `signer`, so that it has a real id and verifies like one
+/// from a relay would
+pub(crate) fn signed_event(
+    signer: &KeySigner,
+    created_at: i64,
+    kind: EventKind,
+    tags: Vec<Tag>,
+    content: &str,
+) -> Event {
+    let pre_event = PreEvent {
+        pubkey: signer.public_key(),
+        created_at: Unixtime(created_at),
+        kind,
+        tags,
+        content: content.to_owned(),
+    };
+    signer.sign_event(pre_event).unwrap()
+}
+
+/// A fresh directory for a test, under the temp dir
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gossip_test_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Give GLOBALS a Storage, for tests of code that goes through GLOBALS.db(). All
+/// such tests in the process share it.
+pub(crate) fn init_globals_storage() {
+    GLOBALS
+        .storage
+        .get_or_init(|| Storage::new(temp_dir("globals"), false).unwrap());
+}
@@ -51,6 +51,16 @@ impl UserIdentity {
     // Any function that changes UserIdentity and changes the key should run this instead
     fn on_keychange(&self) -> Result<(), Error> {
         self.on_change()?;
+
+        // Do not keep plaintext decrypted with a key we no longer have
+        GLOBALS.dm_cache.clear();
+        GLOBALS.ui_invalidate_all();
+
+        // What relays gave us on the config subscription was for the old key
+        GLOBALS
+            .db()
+            .modify_all_relays(|relay| relay.last_config_eose_at = None, None)?;
+
         if !matches!(*self.inner.read_arc(), Identity::None) {
             // Rebuild the event tag index if the identity changes
             // since the 'p' tags it needs to index just changed.
@@ -100,6 +110,10 @@ impl UserIdentity {
             .store(false, std::sync::atomic::Ordering::Relaxed);
         GLOBALS.wait_for_login_notify.notify_one();
 
+        // Resume anything that was waiting for the key
+        GLOBALS.note_activity();
+        crate::Overlord::release_held_for_signer();
+
         Ok(())
     }
 
@@ -157,6 +171,22 @@ impl UserIdentity {
         Ok(())
     }
 
+    /// Lock the private key, forgetting the decrypted key until it is unlocked again
+    pub fn lock(&self) -> Result<(), Error> {
+        if !self.is_unlocked() {
+            return Ok(());
+        }
+
+        // Reloading from storage leaves only the encrypted key
+        self.load()?;
+
+        // Do not keep plaintext decrypted with the key
+        GLOBALS.dm_cache.clear();
+        GLOBALS.ui_invalidate_all();
+
+        Ok(())
+    }
+
     pub(crate) fn generate_private_key(&self, pass: &str) -> Result<(), Error> {
         let log_n = GLOBALS.db().read_setting_log_n();
         *self.inner.write_arc() = Identity::generate(pass, log_n)?;